@@ -31,28 +31,46 @@ if [[ "$current_path" == "$main_path" ]]; then
     exit 1
 fi
 
+# Land on the branch this worktree was started from (`coven worker
+# --branch-from`), if any, instead of main
+target_branch=$(git config --default "" --get "branch.$current_branch.coven-base")
+target_branch=${target_branch:-$main_branch}
+
 # Check for uncommitted changes
 if ! git diff --quiet 2>/dev/null || ! git diff --cached --quiet 2>/dev/null; then
     echo "Error: uncommitted changes — commit or stash before landing" >&2
     exit 1
 fi
 
-# Rebase onto main
-commit_count=$(git rev-list --count "$main_branch..$current_branch" 2>/dev/null || echo "0")
+# Rebase onto the target branch
+commit_count=$(git rev-list --count "$target_branch..$current_branch" 2>/dev/null || echo "0")
 
 if [[ "$commit_count" -eq 0 ]]; then
-    echo "No commits to land (branch is up to date with $main_branch)"
+    echo "No commits to land (branch is up to date with $target_branch)"
     exit 0
 fi
 
-echo "Rebasing $commit_count commit(s) onto $main_branch..."
+echo "Rebasing $commit_count commit(s) onto $target_branch..."
 
-if ! rebase_output=$(git rebase "$main_branch" 2>&1); then
+if ! rebase_output=$(git rebase "$target_branch" 2>&1); then
     conflicting_files=$(git diff --name-only --diff-filter=U 2>/dev/null || true)
     if [[ -n "$conflicting_files" ]]; then
         echo "Rebase has conflicts in:" >&2
         echo "$conflicting_files" | sed 's/^/  /' >&2
         echo "" >&2
+        # Show the conflicts themselves so they can be fixed without first
+        # re-reading each file; long ones are cut short
+        max_lines=80
+        echo "Current conflict content:" >&2
+        while IFS= read -r file; do
+            hunks=$(git diff -- "$file" 2>/dev/null || true)
+            total=$(wc -l <<< "$hunks")
+            head -n "$max_lines" <<< "$hunks" >&2
+            if (( total > max_lines )); then
+                echo "... $((total - max_lines)) more line(s) — read $file for the rest" >&2
+            fi
+        done <<< "$conflicting_files"
+        echo "" >&2
         echo "To resolve:" >&2
         echo "  1. Fix the conflicts in the files above" >&2
         echo "  2. git add <resolved-files>" >&2
@@ -64,12 +82,18 @@ if ! rebase_output=$(git rebase "$main_branch" 2>&1); then
     exit 1
 fi
 
-# Fast-forward main in the main worktree
-cd "$main_path"
-if ! git merge --ff-only "$current_branch" 2>&1; then
-    echo "Error: could not fast-forward $main_branch to $current_branch" >&2
-    echo "Main may have new commits. Try rebasing again." >&2
+# Fast-forward main in the main worktree; a --branch-from branch that isn't
+# checked out there is fast-forwarded in place (`fetch .` refuses anything else)
+if [[ "$target_branch" == "$main_branch" ]]; then
+    cd "$main_path"
+    fast_forward=(git merge --ff-only "$current_branch")
+else
+    fast_forward=(git fetch . "$current_branch:refs/heads/$target_branch")
+fi
+if ! "${fast_forward[@]}" 2>&1; then
+    echo "Error: could not fast-forward $target_branch to $current_branch" >&2
+    echo "$target_branch may have new commits. Try rebasing again." >&2
     exit 1
 fi
 
-echo "Landed $commit_count commit(s) on $main_branch"
+echo "Landed $commit_count commit(s) on $target_branch"
@@ -113,6 +113,31 @@ pub fn load_agents(dir: &Path) -> Result<Vec<AgentDef>> {
     Ok(agents)
 }
 
+/// Load definitions from `dir` and from `override_dir`, the latter winning
+/// when both define an agent of the same name.
+///
+/// Unlike `dir`, `override_dir` was asked for explicitly, so it must exist.
+pub fn load_agents_with_override(dir: &Path, override_dir: &Path) -> Result<Vec<AgentDef>> {
+    if !override_dir.is_dir() {
+        bail!("agents directory not found: {}", override_dir.display());
+    }
+    let mut agents = load_agents(override_dir)?;
+    for agent in load_agents(dir)? {
+        if !agents.iter().any(|a| a.name == agent.name) {
+            agents.push(agent);
+        }
+    }
+    agents.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(agents)
+}
+
+/// Format agent args as sorted `key=value` pairs separated by spaces.
+pub(crate) fn format_args_display(args: &HashMap<String, String>) -> String {
+    let mut parts: Vec<_> = args.iter().map(|(k, v)| format!("{k}={v}")).collect();
+    parts.sort();
+    parts.join(" ")
+}
+
 impl AgentDef {
     /// Render the title template with the given arguments, if one is configured.
     ///
@@ -131,6 +156,23 @@ impl AgentDef {
         Ok(Some(rendered))
     }
 
+    /// Label for phase headers and terminal titles.
+    ///
+    /// Uses the rendered title template when it produces non-blank text,
+    /// falling back to the agent name plus its args otherwise (no template,
+    /// render error, or only unset placeholders).
+    pub fn display_title(&self, args: &HashMap<String, String>) -> String {
+        if let Ok(Some(title)) = self.render_title(args)
+            && !title.trim().is_empty()
+        {
+            return format!("{}: {}", self.name, title.trim());
+        }
+        match format_args_display(args) {
+            d if d.is_empty() => self.name.clone(),
+            d => format!("{} {d}", self.name),
+        }
+    }
+
     /// Render the prompt template with the given arguments.
     ///
     /// Validates that all required args are present, then uses Handlebars
@@ -237,6 +279,60 @@ You are a code reviewer working on {{issue}}.
         assert_eq!(agents[1].name, "beta");
     }
 
+    #[test]
+    fn override_dir_wins_on_name_collision() {
+        let repo = TempDir::new().unwrap();
+        let shared = TempDir::new().unwrap();
+        for (dir, name, description) in [
+            (&repo, "plan", "Repo plan"),
+            (&repo, "implement", "Repo implement"),
+            (&shared, "plan", "Shared plan"),
+            (&shared, "review", "Shared review"),
+        ] {
+            fs::write(
+                dir.path().join(format!("{name}.md")),
+                format!("---\ndescription: \"{description}\"\n---\n\nPrompt."),
+            )
+            .unwrap();
+        }
+
+        let agents = load_agents_with_override(repo.path(), shared.path()).unwrap();
+        let loaded: Vec<_> = agents
+            .iter()
+            .map(|a| (a.name.as_str(), a.frontmatter.description.as_str()))
+            .collect();
+        assert_eq!(
+            loaded,
+            [
+                ("implement", "Repo implement"),
+                ("plan", "Shared plan"),
+                ("review", "Shared review"),
+            ]
+        );
+    }
+
+    #[test]
+    fn override_dir_alone_is_enough() {
+        let shared = TempDir::new().unwrap();
+        fs::write(
+            shared.path().join("plan.md"),
+            "---\ndescription: \"Plan\"\n---\n\nPrompt.",
+        )
+        .unwrap();
+        let agents =
+            load_agents_with_override(Path::new("/nonexistent/path/agents"), shared.path())
+                .unwrap();
+        assert_eq!(agents.len(), 1);
+    }
+
+    #[test]
+    fn missing_override_dir_is_an_error() {
+        let repo = TempDir::new().unwrap();
+        let err =
+            load_agents_with_override(repo.path(), Path::new("/nonexistent/shared")).unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/shared"), "{err}");
+    }
+
     #[test]
     fn render_with_all_args() {
         let (fm, body) = parse_agent_file(VALID_AGENT).unwrap();
@@ -370,4 +466,50 @@ Do the thing."#;
         let title = agent.render_title(&HashMap::new()).unwrap();
         assert!(title.is_none());
     }
+
+    fn titled_agent(title: Option<&str>) -> AgentDef {
+        AgentDef {
+            name: "main".into(),
+            frontmatter: AgentFrontmatter {
+                description: "Worker".into(),
+                args: Vec::new(),
+                max_concurrency: None,
+                claude_args: Vec::new(),
+                title: title.map(String::from),
+            },
+            prompt_template: String::new(),
+        }
+    }
+
+    fn task_args(task: &str) -> HashMap<String, String> {
+        HashMap::from([("task".to_string(), task.to_string())])
+    }
+
+    #[test]
+    fn display_title_uses_template() {
+        let agent = titled_agent(Some("Implement: {{task}}"));
+        assert_eq!(
+            agent.display_title(&task_args("Fix the bug")),
+            "main: Implement: Fix the bug"
+        );
+    }
+
+    #[test]
+    fn display_title_falls_back_without_template() {
+        let agent = titled_agent(None);
+        assert_eq!(agent.display_title(&task_args("Fix")), "main task=Fix");
+        assert_eq!(agent.display_title(&HashMap::new()), "main");
+    }
+
+    #[test]
+    fn display_title_falls_back_on_render_error() {
+        let agent = titled_agent(Some("{{#if task}}unclosed"));
+        assert_eq!(agent.display_title(&task_args("Fix")), "main task=Fix");
+    }
+
+    #[test]
+    fn display_title_falls_back_when_blank() {
+        let agent = titled_agent(Some("{{missing}}"));
+        assert_eq!(agent.display_title(&task_args("Fix")), "main task=Fix");
+    }
 }
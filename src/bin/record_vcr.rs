@@ -1,12 +1,17 @@
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Context, Result, bail};
 use tokio::sync::{Semaphore, mpsc};
 use tokio::task::LocalSet;
 
 use coven::commands;
-use coven::vcr::{DEFAULT_TEST_MODEL, Io, MultiStep, TestCase, TriggerController, VcrContext};
+use coven::vcr::{
+    DEFAULT_TEST_MODEL, Io, MultiStep, OutputTap, StatusTestWorker, TestCase, TriggerController,
+    VcrContext,
+};
+use coven::worker_state;
 
 /// Writes to stderr with a `[prefix] ` prepended to each line.
 struct PrefixWriter {
@@ -59,6 +64,15 @@ struct CaseEntry {
     name: String,
 }
 
+/// The session `coven tutorial` replays, kept in `assets/` rather than
+/// `tests/cases/` since it ships in the binary instead of being a test.
+fn tutorial_case() -> CaseEntry {
+    CaseEntry {
+        case_dir: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets/tutorial"),
+        name: "tutorial".to_string(),
+    }
+}
+
 /// Discover all test cases by walking `tests/cases/{theme}/{name}/{name}.toml`.
 fn discover_cases(cases_dir: &Path) -> Result<Vec<CaseEntry>> {
     let mut entries = Vec::new();
@@ -100,11 +114,21 @@ async fn main() -> Result<()> {
     let args: Vec<String> = std::env::args().collect();
     let cases_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/cases");
 
-    let all_cases = discover_cases(&cases_dir)?;
+    let mut all_cases = discover_cases(&cases_dir)?;
+    all_cases.push(tutorial_case());
+
+    // `--checksums` also records rendered-output checksums for replay to verify.
+    let checksums = args[1..].iter().any(|a| a == "--checksums");
+    let filters: Vec<&str> = args[1..]
+        .iter()
+        .map(String::as_str)
+        .filter(|a| *a != "--checksums")
+        .collect();
 
-    let cases: Vec<CaseEntry> = if args.len() > 1 {
+    let cases: Vec<CaseEntry> = if filters.is_empty() {
+        all_cases
+    } else {
         // Filter by CLI args: accept "name" (searches all themes) or "theme/name".
-        let filters: Vec<&str> = args[1..].iter().map(String::as_str).collect();
         let mut matched = Vec::new();
         for filter in &filters {
             let found: Vec<_> = if filter.contains('/') {
@@ -135,8 +159,6 @@ async fn main() -> Result<()> {
             }
         }
         matched
-    } else {
-        all_cases
     };
 
     // Record cases concurrently using LocalSet (VcrContext is !Send due to RefCell).
@@ -153,7 +175,7 @@ async fn main() -> Result<()> {
                     let name = case.name.clone();
                     let result = async {
                         let _permit = sem.acquire().await.map_err(|e| anyhow::anyhow!("{e}"))?;
-                        record_case(&case.case_dir, &case.name).await
+                        record_case(&case.case_dir, &case.name, checksums).await
                     }
                     .await;
                     (name, result)
@@ -210,6 +232,17 @@ fn git_commit_all(dir: &Path, message: &str) -> Result<()> {
     Ok(())
 }
 
+/// Register `workers` in the repo at `dir` under the recorder's own PID.
+fn register_workers(dir: &Path, workers: &[StatusTestWorker]) -> Result<()> {
+    for w in workers {
+        worker_state::register(dir, &w.branch)?;
+        if w.agent.is_some() {
+            worker_state::update(dir, &w.branch, w.agent.as_deref(), &w.args, 0.0)?;
+        }
+    }
+    Ok(())
+}
+
 /// Create a temp directory with test files and an initial git commit.
 fn setup_test_dir(name: &str, case: &TestCase) -> Result<PathBuf> {
     let tmp_dir = std::env::temp_dir().join(format!("coven-vcr-{name}"));
@@ -255,7 +288,19 @@ fn ensure_model_arg(args: &mut Vec<String>) {
     }
 }
 
-async fn record_case(case_dir: &Path, name: &str) -> Result<()> {
+/// Attach an output tap to `vcr` when recording with `--checksums`. The
+/// command's output should go through the tap's writer either way.
+fn tap_output(vcr: VcrContext, checksums: bool) -> (VcrContext, OutputTap) {
+    let tap = OutputTap::default();
+    let vcr = if checksums {
+        vcr.with_output_tap(tap.clone())
+    } else {
+        vcr
+    };
+    (vcr, tap)
+}
+
+async fn record_case(case_dir: &Path, name: &str, checksums: bool) -> Result<()> {
     let toml_path = case_dir.join(format!("{name}.toml"));
     let vcr_path = case_dir.join(format!("{name}.vcr"));
 
@@ -264,37 +309,82 @@ async fn record_case(case_dir: &Path, name: &str) -> Result<()> {
     let case: TestCase = toml::from_str(&toml_content)?;
 
     if case.is_multi() {
-        return record_multi_case(case_dir, name, case).await;
+        return record_multi_case(case_dir, name, case, checksums).await;
     }
 
     let tmp_dir = setup_test_dir(name, &case)?;
     let (term_tx, term_rx) = mpsc::unbounded_channel();
     let (_event_tx, event_rx) = mpsc::unbounded_channel();
-    let controller = TriggerController::new(&case.messages, term_tx.clone())?.with_auto_exit();
-    let vcr = VcrContext::record_with_triggers(controller);
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    let controller = TriggerController::new(&case.messages, term_tx.clone())?
+        .with_auto_exit()
+        .with_shutdown(shutdown_tx.clone());
+    let (vcr, tap) = tap_output(VcrContext::record_with_triggers(controller), checksums);
     let mut io = Io::new(event_rx, term_rx);
+    io.watch_shutdown(shutdown_rx);
     if case.display.headless {
         io.set_term_tx_keepalive(term_tx);
     }
-    let mut output = PrefixWriter::new(name);
+    let mut output = tap.writer(PrefixWriter::new(name));
 
     if case.is_worker() {
         let worker_config = case.worker.as_ref().context("worker config missing")?;
+        if worker_config.shutdown_at_start {
+            shutdown_tx.send_replace(true);
+        }
         let mut extra_args = worker_config.claude_args.clone();
         ensure_model_arg(&mut extra_args);
         let worktree_base = tmp_dir.with_file_name(format!("coven-vcr-{name}-worktrees"));
         std::fs::create_dir_all(&worktree_base)?;
+        register_workers(&tmp_dir, &worker_config.peers)?;
         commands::worker::worker(
             commands::worker::WorkerConfig {
-                show_thinking: case.display.show_thinking,
+                display: commands::DisplayOptions {
+                    show_thinking: case.display.show_thinking,
+                    term_width: Some(80),
+                    ..Default::default()
+                },
+                protocol_log: None,
+                steering_timeout: None,
+                max_cost: None,
+                preflight: false,
+                no_default_permission_mode: worker_config.no_default_permission_mode,
                 branch: None,
-                worktree_base: worktree_base.clone(),
+                worktree_base: Some(worktree_base.clone()),
+                agents_dir: worker_config.agents_dir.as_ref().map(PathBuf::from),
                 extra_args,
                 working_dir: Some(tmp_dir.clone()),
-                fork: false,
-                reload: false,
+                tag_flags: commands::TagFlags {
+                    fork: false,
+                    fork_tools: None,
+                    fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+                    reload: false,
+                },
                 no_wait: false,
-                term_width: Some(80),
+                cleanup: if worker_config.dry_run_cleanup {
+                    commands::worker::ExitCleanup::DryRun
+                } else {
+                    commands::worker::ExitCleanup::Remove
+                },
+                cycles: commands::worker::Cycles::from_flags(
+                    worker_config.cycles.once,
+                    worker_config.cycles.dispatch_only,
+                ),
+                debug_dispatch: worker_config.debug_dispatch,
+                lock_wait: coven::semaphore::LockWait::default(),
+                branch_prefix: None,
+                branch_from: None,
+                max_loop_warnings: None,
+                wake_on: Vec::new(),
+                push_after_land: None,
+                post_land: worker_config.post_land.clone().map(|command| {
+                    coven::post_land::PostLandHook {
+                        command,
+                        required: false,
+                        timeout: Duration::from_secs(coven::post_land::DEFAULT_TIMEOUT_SECS),
+                    }
+                }),
+                control_socket: None,
             },
             &mut io,
             &vcr,
@@ -314,18 +404,31 @@ async fn record_case(case_dir: &Path, name: &str) -> Result<()> {
         commands::ralph::ralph(
             commands::ralph::RalphConfig {
                 prompt_source,
-                iterations: 10, // safety limit for recording
-                break_tag: ralph_config.break_tag.clone(),
-                no_break: false,
+                first_prompt: None,
+                iterations: 10,
+                // safety limit for recording
+                break_tag: Some(ralph_config.break_tag.clone()),
                 no_wait: ralph_config.no_wait,
-                show_thinking: case.display.show_thinking,
-                tag_flags: commands::ralph::TagFlags {
+                display: commands::DisplayOptions {
+                    show_thinking: case.display.show_thinking,
+                    term_width: Some(80),
+                    ..Default::default()
+                },
+                protocol_log: None,
+                steering_timeout: None,
+                max_cost: None,
+                iteration_timeout: ralph_config.iteration_timeout.map(Duration::from_secs),
+                preflight: false,
+                no_default_permission_mode: false,
+                commit_each: false,
+                tag_flags: commands::TagFlags {
                     fork: false,
+                    fork_tools: None,
+                    fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
                     reload: false,
                 },
                 extra_args,
                 working_dir: Some(tmp_dir.clone()),
-                term_width: Some(80),
             },
             &mut io,
             &vcr,
@@ -336,24 +439,84 @@ async fn record_case(case_dir: &Path, name: &str) -> Result<()> {
         let init_config = case.init.as_ref().context("init config missing")?;
         let stdin_input = format!("{}\n", init_config.stdin);
         let mut stdin = std::io::Cursor::new(stdin_input);
-        commands::init::init(&vcr, &mut output, &mut stdin, Some(tmp_dir.clone())).await?;
+        commands::init::init(
+            &vcr,
+            &mut output,
+            &mut stdin,
+            Some(tmp_dir.clone()),
+            init_config.update,
+        )
+        .await?;
     } else if case.is_gc() {
-        commands::gc::gc(&vcr, false, Some(tmp_dir.as_path()), &mut output).await?;
-    } else if case.is_status() {
-        commands::status::status(&vcr, Some(tmp_dir.as_path()), &mut output).await?;
+        let config = commands::gc::GcConfig::default();
+        let mut stdin = std::io::empty();
+        commands::gc::gc(
+            &vcr,
+            &config,
+            Some(tmp_dir.as_path()),
+            &mut output,
+            &mut stdin,
+        )
+        .await?;
+    } else if let Some(status_config) = &case.status {
+        register_workers(&tmp_dir, &status_config.workers)?;
+        commands::status::status(
+            &vcr,
+            Some(tmp_dir.as_path()),
+            status_config.json,
+            &mut output,
+        )
+        .await?;
+    } else if let Some(fork_config) = &case.fork {
+        let mut extra_args = fork_config.claude_args.clone();
+        ensure_model_arg(&mut extra_args);
+        commands::fork_cmd::fork(
+            commands::fork_cmd::ForkCmdConfig {
+                tasks: fork_config.tasks.clone(),
+                extra_args,
+                no_default_permission_mode: fork_config.no_default_permission_mode,
+                working_dir: Some(tmp_dir.clone()),
+                term_width: Some(80),
+            },
+            &mut io,
+            &vcr,
+            &mut output,
+        )
+        .await?;
     } else {
         let run_config = case.run.as_ref().context("run config missing")?;
         let mut claude_args = run_config.claude_args.clone();
         ensure_model_arg(&mut claude_args);
         commands::run::run(
             commands::run::RunConfig {
-                prompt: Some(run_config.prompt.clone()),
+                prompt: Some(run_config.resolve_prompt(&tmp_dir)?),
+                resume: None,
+                continue_last: false,
+                notes_dir: None,
                 extra_args: claude_args,
-                show_thinking: case.display.show_thinking,
-                fork: run_config.fork,
-                reload: run_config.reload,
+                display: commands::DisplayOptions {
+                    show_thinking: case.display.show_thinking,
+                    term_width: Some(80),
+                    ..Default::default()
+                },
+                protocol_log: None,
+                steering_timeout: None,
+                max_cost: None,
+                preflight: false,
+                no_default_permission_mode: false,
+                tag_flags: commands::TagFlags {
+                    fork: run_config.fork,
+                    fork_tools: run_config
+                        .fork_tools
+                        .as_deref()
+                        .map(str::parse)
+                        .transpose()?,
+                    fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+                    reload: run_config.reload,
+                },
+                fork_tasks: run_config.fork_tasks.clone(),
                 working_dir: Some(tmp_dir.clone()),
-                term_width: Some(80),
+                json_events: None,
             },
             &mut io,
             &vcr,
@@ -362,6 +525,7 @@ async fn record_case(case_dir: &Path, name: &str) -> Result<()> {
         .await?;
     }
 
+    vcr.checkpoint_output()?;
     vcr.write_recording(&vcr_path)?;
     std::fs::remove_dir_all(&tmp_dir).ok();
     Ok(())
@@ -370,7 +534,12 @@ async fn record_case(case_dir: &Path, name: &str) -> Result<()> {
 /// Record a multi-step test case. Steps are executed sequentially unless they
 /// share a `concurrent_group`, in which case they run concurrently.
 /// Each step writes its own VCR file: `<test>__<step>.vcr`.
-async fn record_multi_case(case_dir: &Path, name: &str, case: TestCase) -> Result<()> {
+async fn record_multi_case(
+    case_dir: &Path,
+    name: &str,
+    case: TestCase,
+    checksums: bool,
+) -> Result<()> {
     let tmp_dir = setup_test_dir(name, &case)?;
     let show_thinking = case.display.show_thinking;
     let multi = case
@@ -391,7 +560,7 @@ async fn record_multi_case(case_dir: &Path, name: &str, case: TestCase) -> Resul
                 let n = name.to_string();
                 let td = tmp_dir.clone();
                 handles.push(tokio::task::spawn_local(async move {
-                    record_multi_step(dir, n, step, td, show_thinking).await
+                    record_multi_step(dir, n, step, td, show_thinking, checksums).await
                 }));
             }
             for handle in handles {
@@ -404,6 +573,7 @@ async fn record_multi_case(case_dir: &Path, name: &str, case: TestCase) -> Resul
                 step,
                 tmp_dir.clone(),
                 show_thinking,
+                checksums,
             )
             .await?;
         }
@@ -419,18 +589,21 @@ async fn record_multi_step(
     test_name: String,
     step: MultiStep,
     tmp_dir: PathBuf,
-    show_thinking: bool,
+    show_thinking: Option<bool>,
+    checksums: bool,
 ) -> Result<()> {
     let vcr_path = case_dir.join(format!("{test_name}__{}.vcr", step.name));
     let default_model = DEFAULT_TEST_MODEL;
 
     match step.command.as_str() {
         "init" => {
-            let vcr = VcrContext::record();
-            let mut output = PrefixWriter::new(format!("{test_name}/{}", step.name));
+            let (vcr, tap) = tap_output(VcrContext::record(), checksums);
+            let mut output = tap.writer(PrefixWriter::new(format!("{test_name}/{}", step.name)));
             let stdin_input = format!("{}\n", step.stdin.as_deref().unwrap_or(""));
             let mut stdin = std::io::Cursor::new(stdin_input);
-            commands::init::init(&vcr, &mut output, &mut stdin, Some(tmp_dir.clone())).await?;
+            commands::init::init(&vcr, &mut output, &mut stdin, Some(tmp_dir.clone()), false)
+                .await?;
+            vcr.checkpoint_output()?;
             vcr.write_recording(&vcr_path)?;
 
             // Commit init-created files so they're available in worktree checkouts.
@@ -441,9 +614,9 @@ async fn record_multi_step(
             let (_event_tx, event_rx) = mpsc::unbounded_channel();
 
             let controller = TriggerController::new(&step.messages, term_tx)?.with_auto_exit();
-            let vcr = VcrContext::record_with_triggers(controller);
+            let (vcr, tap) = tap_output(VcrContext::record_with_triggers(controller), checksums);
             let mut io = Io::new(event_rx, term_rx);
-            let mut output = PrefixWriter::new(format!("{test_name}/{}", step.name));
+            let mut output = tap.writer(PrefixWriter::new(format!("{test_name}/{}", step.name)));
 
             let mut extra_args = step.claude_args;
             if !extra_args.iter().any(|a| a == "--model") {
@@ -456,15 +629,39 @@ async fn record_multi_step(
 
             commands::worker::worker(
                 commands::worker::WorkerConfig {
-                    show_thinking,
+                    display: commands::DisplayOptions {
+                        show_thinking,
+                        term_width: Some(80),
+                        ..Default::default()
+                    },
+                    protocol_log: None,
+                    steering_timeout: None,
+                    max_cost: None,
+                    preflight: false,
+                    no_default_permission_mode: false,
                     branch: None,
-                    worktree_base: worktree_base.clone(),
+                    worktree_base: Some(worktree_base.clone()),
+                    agents_dir: None,
                     extra_args,
                     working_dir: Some(tmp_dir),
-                    fork: false,
-                    reload: false,
+                    tag_flags: commands::TagFlags {
+                        fork: false,
+                        fork_tools: None,
+                        fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+                        reload: false,
+                    },
                     no_wait: false,
-                    term_width: Some(80),
+                    cleanup: commands::worker::ExitCleanup::Remove,
+                    cycles: commands::worker::Cycles::Loop,
+                    debug_dispatch: false,
+                    lock_wait: coven::semaphore::LockWait::default(),
+                    branch_prefix: None,
+                    branch_from: None,
+                    max_loop_warnings: None,
+                    wake_on: Vec::new(),
+                    push_after_land: None,
+                    post_land: None,
+                    control_socket: None,
                 },
                 &mut io,
                 &vcr,
@@ -472,6 +669,7 @@ async fn record_multi_step(
             )
             .await?;
 
+            vcr.checkpoint_output()?;
             vcr.write_recording(&vcr_path)?;
             std::fs::remove_dir_all(&worktree_base).ok();
         }
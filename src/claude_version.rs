@@ -0,0 +1,180 @@
+//! The claude CLI version, checked once at startup: protocol breakage
+//! usually arrives with a new claude release, so say so before the parse
+//! warnings do.
+
+use std::fmt;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::Result;
+
+use crate::display::renderer::Renderer;
+use crate::vcr::VcrContext;
+
+/// A `major.minor.patch` version; pre-release and build suffixes are dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The oldest claude coven works with: stream-json input and partial
+/// messages.
+pub const MIN_VERSION: Version = Version {
+    major: 2,
+    minor: 0,
+    patch: 0,
+};
+
+/// The newest `major.minor` line coven has been tested with. Patch releases
+/// of it don't warn; claude ships those too often to chase.
+pub const TESTED_UP_TO: (u64, u64) = (2, 1);
+
+/// The first `x.y.z` in `claude --version` output, e.g. `2.0.14 (Claude Code)`
+/// or `v2.1.0-beta.3+build.7`.
+pub fn parse(output: &str) -> Option<Version> {
+    output.split_whitespace().find_map(|word| {
+        let word = word.strip_prefix('v').unwrap_or(word);
+        let core = word.split(['-', '+']).next()?;
+        let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+        let version = Version {
+            major: parts.next()??,
+            minor: parts.next()??,
+            patch: parts.next()??,
+        };
+        parts.next().is_none().then_some(version)
+    })
+}
+
+/// What to warn about for `claude --version` output, if anything.
+pub fn warning(output: &str) -> Option<String> {
+    let Some(version) = parse(output) else {
+        return Some(format!(
+            "couldn't read a version from `claude --version` ({:?}); parse warnings may follow",
+            output.trim()
+        ));
+    };
+    if version < MIN_VERSION {
+        return Some(format!(
+            "claude {version} is older than {MIN_VERSION}, the oldest coven supports; \
+             parse warnings may follow (`claude update` upgrades it)"
+        ));
+    }
+    let (major, minor) = TESTED_UP_TO;
+    if (version.major, version.minor) > TESTED_UP_TO {
+        return Some(format!(
+            "claude {version} is newer than coven has been tested with ({major}.{minor}.x); \
+             parse warnings may follow"
+        ));
+    }
+    None
+}
+
+/// Run `claude --version`; `None` if it doesn't run (spawning a session
+/// will report that properly).
+fn query() -> Option<String> {
+    let output = Command::new("claude")
+        .arg("--version")
+        .env_remove("CLAUDECODE")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Check the claude version through the VCR and render a warning if it's
+/// outside what coven supports. Commands call this once at startup, so
+/// ralph and worker loops don't run `claude --version` per iteration.
+pub fn check<W: Write>(renderer: &mut Renderer<W>, vcr: &VcrContext) -> Result<()> {
+    if let Some(warning) = vcr.claude_version(query)?.as_deref().and_then(warning) {
+        renderer.render_warning(&warning);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn version(major: u64, minor: u64, patch: u64) -> Version {
+        Version {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    #[test]
+    fn parses_version_strings() {
+        assert_eq!(parse("2.0.14 (Claude Code)"), Some(version(2, 0, 14)));
+        assert_eq!(parse("v2.1.3"), Some(version(2, 1, 3)));
+        assert_eq!(parse("2.1.0-beta.3 (Claude Code)"), Some(version(2, 1, 0)));
+        assert_eq!(parse("1.0.128+build.7\n"), Some(version(1, 0, 128)));
+        assert_eq!(parse("Claude Code version 2.0.5"), Some(version(2, 0, 5)));
+        // As recorded in tests/cases.
+        let recorded =
+            "2.1.280-dev.20260921.t204017.sha80abbfe (Claude Code) (v2.1.280 release candidate)";
+        assert_eq!(parse(recorded), Some(version(2, 1, 280)));
+        assert_eq!(warning(recorded), None);
+    }
+
+    #[test]
+    fn rejects_non_versions() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("claude: command not found"), None);
+        assert_eq!(parse("2.0 (Claude Code)"), None);
+        assert_eq!(parse("2.0.1.4"), None);
+        assert_eq!(parse("2.x.1"), None);
+    }
+
+    #[test]
+    fn warns_outside_the_supported_range() {
+        assert_eq!(warning("2.0.14 (Claude Code)"), None);
+        assert_eq!(warning("2.1.99 (Claude Code)"), None);
+        let old = warning("1.0.128 (Claude Code)").unwrap();
+        assert!(old.contains("claude 1.0.128 is older than 2.0.0"), "{old}");
+        let new = warning("2.2.0 (Claude Code)").unwrap();
+        assert!(
+            new.contains("newer than coven has been tested with (2.1.x)"),
+            "{new}"
+        );
+        assert!(new.contains("parse warnings may follow"), "{new}");
+        let garbled = warning("unknown").unwrap();
+        assert!(garbled.contains("couldn't read a version"), "{garbled}");
+    }
+
+    #[test]
+    fn check_replays_the_recorded_version() {
+        let entry = serde_json::json!({
+            "label": "claude_version",
+            "args": null,
+            "result": {"Ok": "3.0.0 (Claude Code)"},
+        });
+        let vcr = VcrContext::replay(&entry.to_string()).unwrap();
+        let mut renderer = Renderer::with_writer(Vec::new());
+        check(&mut renderer, &vcr).unwrap();
+        assert!(vcr.unreplayed_labels().is_empty());
+        let output = String::from_utf8(renderer.writer().clone()).unwrap();
+        assert!(output.contains("[warn] claude 3.0.0 is newer"), "{output}");
+    }
+
+    #[test]
+    fn recordings_without_a_version_skip_the_check() {
+        let vcr = VcrContext::replay("").unwrap();
+        let mut renderer = Renderer::with_writer(Vec::new());
+        check(&mut renderer, &vcr).unwrap();
+        assert!(renderer.writer().is_empty());
+    }
+}
@@ -1,27 +1,169 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
+use coven::commands::worker::{Cycles, ExitCleanup};
+use coven::commands::{DisplayOptions, TagFlags};
+use coven::display::theme::Theme;
+use coven::fork::ForkTools;
+use coven::notify::Notifier;
 
 /// Claude session options shared across all command modes.
 #[derive(clap::Args, Debug)]
 pub struct ClaudeOpts {
-    /// Stream thinking text inline in dim italic instead of collapsing.
-    #[arg(long)]
-    pub show_thinking: bool,
+    #[command(flatten)]
+    pub display: DisplayOpts,
 
     /// Enable model-driven context forking via <fork> tags.
     #[arg(long)]
     pub fork: bool,
 
+    /// Restrict the tools of forked children: comma-separated tool names to
+    /// pre-approve, `!Name` to deny (e.g. "Read,Grep,Glob,!Bash").
+    #[arg(long, value_name = "SPEC", requires = "fork")]
+    pub fork_tools: Option<ForkTools>,
+
+    /// Most forked children to run at once; the rest wait for a running
+    /// child to finish.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = coven::fork::DEFAULT_FORK_CONCURRENCY,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        requires = "fork"
+    )]
+    pub fork_concurrency: usize,
+
     /// Enable model-driven reload via <reload> tags.
     #[arg(long)]
     pub reload: bool,
 
+    /// Warn if claude produces no output this many seconds after a steering
+    /// message sent while no tool call is running (0 disables).
+    #[arg(long, value_name = "SECS", default_value = "90")]
+    pub steering_timeout: u64,
+
+    /// Stop once spending passes this many USD: the current turn finishes,
+    /// then queued follow-ups and further iterations or agent phases are skipped.
+    #[arg(long, value_name = "USD")]
+    pub max_cost: Option<f64>,
+
+    /// Check the API host is reachable before starting claude, and back off
+    /// while it isn't. On by default for ralph and worker; pass
+    /// `--preflight-connectivity=false` to disable.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub preflight_connectivity: Option<bool>,
+
+    /// Don't pass `--permission-mode acceptEdits` when no permission mode is
+    /// given; claude then uses its own default (or its settings' `defaultMode`).
+    #[arg(long)]
+    pub no_default_permission_mode: bool,
+
+    /// Debug the stream-json protocol: log claude output that fails to parse
+    /// to PATH (default: a file in the temp directory), and accept
+    /// `:raw <json>` to write a line to claude's stdin.
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "")]
+    pub debug_protocol: Option<PathBuf>,
+
+    /// Append a plain-text copy of the display (ANSI escapes stripped) to this file.
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Run this shell command whenever coven starts waiting for you (e.g.
+    /// `notify-send coven "$COVEN_NOTIFY_MESSAGE"`). It gets a short message
+    /// in `$COVEN_NOTIFY_MESSAGE` and the directory in `$COVEN_NOTIFY_DIR`.
+    #[arg(long, value_name = "COMMAND")]
+    pub notify: Option<String>,
+
     /// Extra arguments to pass through to claude (after --).
     #[arg(last = true)]
     pub claude_args: Vec<String>,
 }
 
+/// How Claude's output is displayed.
+#[derive(clap::Args, Debug)]
+pub struct DisplayOpts {
+    /// Stream thinking text inline in dim italic instead of collapsing.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub show_thinking: Option<bool>,
+
+    /// Show token usage (fresh vs cached input, output) under each Done line.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub show_usage: Option<bool>,
+
+    /// Preview the first N lines (default 3) of each successful tool result
+    /// under its call.
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "3")]
+    pub show_results: Option<usize>,
+
+    /// Style `**bold**`, `code` spans, headings and code fences in
+    /// Claude's text as it streams.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub render_markdown: Option<bool>,
+
+    /// Hide claude's informational stderr output (e.g. MCP server logs);
+    /// stderr lines that look like errors are still shown as warnings.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub quiet_stderr: Option<bool>,
+
+    /// Stop echoing a text block after N lines; the rest is summarized in a
+    /// placeholder and viewable with `:N`.
+    #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_stream_lines: Option<usize>,
+}
+
+impl ClaudeOpts {
+    /// The display flags, plus `--log-file`.
+    pub fn display_options(&self) -> DisplayOptions {
+        let display = &self.display;
+        DisplayOptions {
+            show_thinking: display.show_thinking,
+            show_usage: display.show_usage,
+            show_results: display.show_results,
+            render_markdown: display.render_markdown,
+            quiet_stderr: display.quiet_stderr,
+            max_stream_lines: display.max_stream_lines,
+            log_file: self.log_file.clone(),
+            term_width: None,
+        }
+    }
+
+    /// `--fork` and `--reload`, with the fork settings.
+    pub fn tag_flags(&self) -> TagFlags {
+        TagFlags {
+            fork: self.fork,
+            fork_tools: self.fork_tools.clone(),
+            fork_concurrency: self.fork_concurrency,
+            reload: self.reload,
+        }
+    }
+
+    /// Where `--debug-protocol` logs unparseable output, if enabled.
+    pub fn protocol_log(&self) -> Option<PathBuf> {
+        let path = self.debug_protocol.clone()?;
+        if path.as_os_str().is_empty() {
+            let name = format!("coven-protocol-{}.log", std::process::id());
+            return Some(std::env::temp_dir().join(name));
+        }
+        Some(path)
+    }
+
+    /// The steering silence window, or `None` if disabled.
+    pub fn steering_timeout(&self) -> Option<Duration> {
+        (self.steering_timeout > 0).then(|| Duration::from_secs(self.steering_timeout))
+    }
+
+    /// The `--notify` command, reporting the current directory.
+    pub fn notifier(&self) -> Result<Option<Notifier>> {
+        let Some(command) = self.notify.clone() else {
+            return Ok(None);
+        };
+        let dir = std::env::current_dir().context("failed to get current directory")?;
+        Ok(Some(Notifier { command, dir }))
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(
     name = "coven",
@@ -36,6 +178,64 @@ pub struct Cli {
     #[arg(value_name = "PROMPT")]
     pub prompt: Option<String>,
 
+    /// Read the prompt from a file (`-` for stdin) instead of the command line.
+    #[arg(long, value_name = "PATH", conflicts_with = "prompt")]
+    pub prompt_file: Option<PathBuf>,
+
+    /// Resume a previous claude session by ID instead of starting fresh.
+    #[arg(long, value_name = "SESSION_ID", conflicts_with = "continue_session")]
+    pub resume: Option<String>,
+
+    /// Resume the most recent session in this directory.
+    #[arg(long = "continue", short = 'c')]
+    pub continue_session: bool,
+
+    /// Fork this task before the first turn instead of waiting for the model
+    /// to emit a <fork> tag; the parent starts by synthesizing the results.
+    /// Repeatable; requires --fork.
+    #[arg(
+        long = "fork-tasks",
+        value_name = "TASK",
+        requires = "fork",
+        conflicts_with_all = ["resume", "continue_session"]
+    )]
+    pub fork_tasks: Vec<String>,
+
+    /// Read --fork-tasks from a file, one task per line.
+    #[arg(
+        long,
+        value_name = "PATH",
+        requires = "fork",
+        conflicts_with_all = ["resume", "continue_session"]
+    )]
+    pub fork_tasks_file: Option<PathBuf>,
+
+    /// Write `:note`s to `<DIR>/<session-id>.notes.md` when the session ends.
+    #[arg(long, value_name = "DIR")]
+    pub notes_dir: Option<PathBuf>,
+
+    /// Write every claude event, and coven's own, as one JSON line each to
+    /// stdout, and move the display to stderr.
+    #[arg(long)]
+    pub json_events: bool,
+
+    /// Log every git command coven runs (command line, exit status, first
+    /// line of stderr on failure) to PATH, or to stderr if no path is given.
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        num_args = 0..=1,
+        default_missing_value = ""
+    )]
+    pub trace_git: Option<PathBuf>,
+
+    /// Color palette: `dark` (default), `light` for light backgrounds, or
+    /// `plain` for no colors at all. Defaults to `COVEN_THEME` if set, else
+    /// `plain` when `NO_COLOR` is set.
+    #[arg(long, global = true, value_name = "THEME")]
+    pub theme: Option<Theme>,
+
     #[command(flatten)]
     pub claude_opts: ClaudeOpts,
 }
@@ -43,70 +243,276 @@ pub struct Cli {
 #[derive(Subcommand, Debug)]
 pub enum Command {
     /// Run claude in a loop with filesystem state accumulation.
-    Ralph {
-        /// Prompt to send to claude on each iteration.
-        #[arg(
-            value_name = "PROMPT",
-            required_unless_present = "prompt_command",
-            conflicts_with = "prompt_command"
-        )]
-        prompt: Option<String>,
-
-        /// Shell command that produces the prompt for each iteration on stdout.
-        ///
-        /// Receives `COVEN_ITERATION` in the environment (1-based). Non-zero
-        /// exit or empty stdout ends the loop cleanly.
-        #[arg(long, value_name = "CMD")]
-        prompt_command: Option<String>,
-
-        /// Maximum number of iterations (0 = infinite).
-        #[arg(long, default_value = "0")]
-        iterations: u32,
-
-        /// Tag that signals loop completion.
-        #[arg(long, default_value = "break")]
-        break_tag: String,
-
-        /// Disable break tag detection (requires --iterations or --prompt-command).
-        #[arg(long)]
-        no_break: bool,
+    ///
+    /// Exit status: 0 when the loop ends on the break tag, --iterations, or
+    /// an exhausted --prompt-command; 2 when claude exits mid-turn; 3 when
+    /// --max-cost is reached; 130 when you end the loop yourself.
+    Ralph(RalphArgs),
 
-        /// Disable <wait-for-user> tag detection (model cannot pause for human input).
+    /// Initialize project with default agent prompts and directory structure.
+    Init {
+        /// Upgrade an initialized project: refresh untouched files, and write
+        /// `<file>.new` next to locally edited ones instead of overwriting them.
         #[arg(long)]
-        no_wait: bool,
-
-        #[command(flatten)]
-        claude_opts: ClaudeOpts,
+        update: bool,
     },
 
-    /// Initialize project with default agent prompts and directory structure.
-    Init,
-
     /// Show status of all active workers.
-    Status,
+    Status {
+        /// Print a JSON document (`{"workers": [...]}`) instead of text.
+        #[arg(long)]
+        json: bool,
+    },
 
-    /// Remove orphaned worktrees left behind by dead workers.
+    /// List each worktree's disk usage and age, and the orphaned ones dead
+    /// workers left behind that `--force` would remove.
     Gc {
-        /// Force removal even if worktree has uncommitted or untracked changes.
+        /// Remove the orphaned worktrees instead of only listing them. Ones
+        /// with uncommitted changes or unlanded commits go too, after listing
+        /// what would be lost and asking for confirmation.
         #[arg(long)]
         force: bool,
-    },
 
-    /// Start an orchestration worker (dispatch → agent → land loop).
-    Worker {
-        /// Branch name for the worktree (random if not specified).
-        #[arg(long)]
-        branch: Option<String>,
+        /// Only remove worktrees with no commits or file changes for this
+        /// long (e.g. 12h, 7d, 2w).
+        #[arg(long, value_name = "AGE", value_parser = coven::commands::gc::parse_age)]
+        older_than: Option<Duration>,
 
-        /// Base directory for worktrees. Default: ~/.coven/worktrees.
+        /// Base directory for worktrees; ones outside it are left alone.
+        /// Default: `worktree_base` in .coven/config.toml, else
+        /// ~/.coven/worktrees.
         #[arg(long)]
         worktree_base: Option<PathBuf>,
 
-        /// Disable <wait-for-user> tag detection (model cannot pause for human input).
+        /// Print what `--force` would remove without removing it, even with
+        /// `--force`.
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Walk through a recorded session, trying the keys you'd use in a real
+    /// one (viewing messages, steering, follow-ups).
+    Tutorial,
+
+    /// Run tasks as parallel claude sessions, print each one's result, and
+    /// exit. Each task is its session's whole prompt; Ctrl+C stops them all.
+    ///
+    /// Exit status: 1 when a task's session died or was stopped.
+    Fork {
+        /// One task per session.
+        #[arg(value_name = "TASK", required = true)]
+        tasks: Vec<String>,
+
+        /// Don't pass `--permission-mode acceptEdits` when no permission mode
+        /// is given; claude then uses its own default (or its settings'
+        /// `defaultMode`).
         #[arg(long)]
-        no_wait: bool,
+        no_default_permission_mode: bool,
 
-        #[command(flatten)]
-        claude_opts: ClaudeOpts,
+        /// Extra arguments to pass through to claude (after --).
+        #[arg(last = true)]
+        claude_args: Vec<String>,
     },
+
+    /// Start an orchestration worker (dispatch → agent → land loop).
+    Worker(WorkerArgs),
+}
+
+/// Arguments to `coven ralph`.
+#[derive(clap::Args, Debug)]
+pub struct RalphArgs {
+    /// Prompt to send to claude on each iteration.
+    #[arg(
+        value_name = "PROMPT",
+        required_unless_present_any = ["prompt_command", "prompt_file"],
+        conflicts_with = "prompt_command"
+    )]
+    pub prompt: Option<String>,
+
+    /// Read the prompt from a file (`-` for stdin) instead of the command line.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["prompt", "prompt_command"]
+    )]
+    pub prompt_file: Option<PathBuf>,
+
+    /// Shell command that produces the prompt for each iteration on stdout.
+    ///
+    /// Receives `COVEN_ITERATION` in the environment (1-based). Non-zero
+    /// exit or empty stdout ends the loop cleanly.
+    #[arg(long, value_name = "CMD")]
+    pub prompt_command: Option<String>,
+
+    /// Prompt for the first iteration only, e.g. to plan before later
+    /// iterations carry out the plan with the regular prompt.
+    #[arg(long, value_name = "TEXT")]
+    pub first_prompt: Option<String>,
+
+    /// Maximum number of iterations (0 = infinite).
+    #[arg(long, default_value = "0")]
+    pub iterations: u32,
+
+    /// Tag that signals loop completion.
+    #[arg(long, default_value = "break")]
+    pub break_tag: String,
+
+    /// Disable break tag detection (requires --iterations or --prompt-command).
+    #[arg(long)]
+    pub no_break: bool,
+
+    /// Disable <wait-for-user> tag detection (model cannot pause for human input).
+    #[arg(long)]
+    pub no_wait: bool,
+
+    /// Kill an iteration's session if it hasn't finished this many seconds
+    /// after starting, and move on to the next iteration.
+    #[arg(long, value_name = "SECS")]
+    pub iteration_timeout: Option<u64>,
+
+    /// After each iteration, commit anything it left uncommitted
+    /// (`git add -A`) as "ralph iteration N: <first line of the result>".
+    #[arg(long)]
+    pub commit_each: bool,
+
+    #[command(flatten)]
+    pub claude_opts: ClaudeOpts,
+}
+
+/// Arguments to `coven worker`.
+#[derive(clap::Args, Debug)]
+pub struct WorkerArgs {
+    /// Branch name for the worktree (random if not specified).
+    #[arg(long)]
+    pub branch: Option<String>,
+
+    /// Put generated branch names under this prefix, e.g. `laptop` gives
+    /// `laptop/swift-fox-42`. Can't be combined with --branch.
+    #[arg(long, value_name = "PREFIX", conflicts_with = "branch")]
+    pub branch_prefix: Option<String>,
+
+    /// Start the worktree from this local branch instead of main, and
+    /// sync with and land on it (e.g. a long-running feature branch).
+    #[arg(long, value_name = "BRANCH")]
+    pub branch_from: Option<String>,
+
+    /// Base directory for worktrees. Default: `worktree_base` in
+    /// .coven/config.toml, else ~/.coven/worktrees.
+    #[arg(long)]
+    pub worktree_base: Option<PathBuf>,
+
+    /// Extra agent definitions, overriding same-named ones in
+    /// .coven/agents. Relative paths are resolved against the repo.
+    #[arg(long, value_name = "PATH")]
+    pub agents_dir: Option<PathBuf>,
+
+    /// Disable <wait-for-user> tag detection (model cannot pause for human input).
+    #[arg(long)]
+    pub no_wait: bool,
+
+    #[command(flatten)]
+    pub cleanup: CleanupOpts,
+
+    #[command(flatten)]
+    pub cycles: CycleOpts,
+
+    /// Save each dispatch cycle's inputs (prompt, agent catalog, worker
+    /// status) and raw output under ~/.coven/debug/<branch>/.
+    #[arg(long)]
+    pub debug_dispatch: bool,
+
+    /// After waiting this long for an agent's `max_concurrency` slot
+    /// (e.g. dispatch's), say which workers hold it and for how long,
+    /// then keep waiting.
+    #[arg(long, value_name = "AGE", default_value = "10m", value_parser = coven::commands::gc::parse_age)]
+    pub lock_timeout: Duration,
+
+    /// When the lock timeout passes, take over a slot its holder has had
+    /// for at least this long (e.g. 1h). The holder may still be running.
+    #[arg(long, value_name = "AGE", value_parser = coven::commands::gc::parse_age)]
+    pub steal_stale_lock: Option<Duration>,
+
+    /// End an agent phase after this many warnings about repeating the
+    /// same tool call; the agent is then asked for its transition.
+    #[arg(long, value_name = "N")]
+    pub max_loop_warnings: Option<usize>,
+
+    /// While sleeping, also wake when files matching this pattern change
+    /// in the main worktree, committed or not (`.covenignore` syntax,
+    /// relative to the repo root). Repeatable; an empty pattern disables.
+    #[arg(long, value_name = "GLOB", default_value = coven::commands::worker::DEFAULT_WAKE_ON)]
+    pub wake_on: Vec<String>,
+
+    /// After an agent lands, push main to this remote (default `origin`).
+    /// A rejected push is landed again on top of the remote's main; other
+    /// failures pause the worker. Main is also fast-forwarded from the
+    /// remote before each dispatch.
+    #[arg(long, value_name = "REMOTE", num_args = 0..=1, default_missing_value = "origin")]
+    pub push_after_land: Option<String>,
+
+    /// Shell command to run in the main repo after an agent lands commits.
+    /// Details of the land are passed as environment variables (see README).
+    #[arg(long, value_name = "CMD")]
+    pub post_land: Option<String>,
+
+    /// Pause the worker when the post-land hook fails (default: warn and continue).
+    #[arg(long, requires = "post_land")]
+    pub post_land_required: bool,
+
+    /// Kill the post-land hook after this many seconds.
+    #[arg(long, value_name = "SECS", default_value_t = coven::post_land::DEFAULT_TIMEOUT_SECS, requires = "post_land")]
+    pub post_land_timeout: u64,
+
+    /// Stream worker state and events as line-delimited JSON on a Unix socket.
+    #[arg(long, value_name = "PATH")]
+    pub control_socket: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub claude_opts: ClaudeOpts,
+}
+
+/// What `coven worker` does with its worktree on exit.
+#[derive(clap::Args, Debug)]
+pub struct CleanupOpts {
+    /// Leave the worktree and its branch in place on exit, for inspection.
+    #[arg(long)]
+    pub keep_worktree: bool,
+
+    /// On exit, print the git commands that would remove the worktree
+    /// and its branch instead of running them.
+    #[arg(long, conflicts_with = "keep_worktree")]
+    pub dry_run_cleanup: bool,
+}
+
+impl CleanupOpts {
+    pub fn cleanup(&self) -> ExitCleanup {
+        if self.keep_worktree {
+            ExitCleanup::Keep
+        } else if self.dry_run_cleanup {
+            ExitCleanup::DryRun
+        } else {
+            ExitCleanup::Remove
+        }
+    }
+}
+
+/// How many cycles `coven worker` runs.
+#[derive(clap::Args, Debug)]
+pub struct CycleOpts {
+    /// Run a single dispatch → agent → land cycle, then exit instead of
+    /// looping back to dispatch or sleeping.
+    #[arg(long)]
+    pub once: bool,
+
+    /// Run only the dispatch phase: print the agent (and args) or sleep
+    /// it chose, its raw <next> block and the worker status it saw, then
+    /// exit without running the agent.
+    #[arg(long)]
+    pub dispatch_only: bool,
+}
+
+impl CycleOpts {
+    pub fn cycles(&self) -> Cycles {
+        Cycles::from_flags(self.once, self.dispatch_only)
+    }
 }
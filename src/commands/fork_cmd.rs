@@ -0,0 +1,68 @@
+//! `coven fork`: run tasks as parallel fork children with no parent session,
+//! print each child's result, and exit.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
+
+use crate::config;
+use crate::fork::{self, ChildResults, ForkConfig, ForkOrigin};
+use crate::session::runner::PermissionMode;
+use crate::vcr::{Io, VcrContext};
+
+use super::{RawModeGuard, render_startup, setup_display, terminal_size};
+
+pub struct ForkCmdConfig {
+    /// One child session per task; the task is the child's whole prompt.
+    pub tasks: Vec<String>,
+    pub extra_args: Vec<String>,
+    /// Leave the permission mode to claude instead of passing
+    /// `--permission-mode acceptEdits`.
+    pub no_default_permission_mode: bool,
+    pub working_dir: Option<PathBuf>,
+    /// Override terminal width for display truncation (used in tests).
+    pub term_width: Option<usize>,
+}
+
+/// Run every task as a fork child with the default fork settings, then
+/// print the results. The shared `claude_args` of `.coven/config.toml` come
+/// before the command's own. Returns each task with its child's result.
+pub async fn fork<W: Write>(
+    config: ForkCmdConfig,
+    io: &mut Io,
+    vcr: &VcrContext,
+    writer: W,
+) -> Result<ChildResults> {
+    if config.tasks.is_empty() {
+        bail!("coven fork needs at least one task");
+    }
+    let shared_args = config::load_shared_args(vcr, config.working_dir.as_deref())?;
+    let extra_args = config::layer_args(config.extra_args, &shared_args);
+    let size = terminal_size(vcr, config.term_width)?;
+    let (mut renderer, mut input) = setup_display(writer, size, false, false, None, false, None)?;
+    let permission_mode = PermissionMode::resolve(&extra_args, config.no_default_permission_mode);
+    render_startup(&mut renderer, vcr, &permission_mode)?;
+    let _raw = RawModeGuard::acquire(io)?;
+
+    let fork_config = ForkConfig {
+        extra_args,
+        working_dir: config.working_dir,
+        tools: None,
+        max_child_result_chars: fork::DEFAULT_MAX_CHILD_RESULT_CHARS,
+        concurrency: fork::DEFAULT_FORK_CONCURRENCY,
+        no_default_permission_mode: config.no_default_permission_mode,
+    };
+    let results = fork::run_children(
+        ForkOrigin::Standalone,
+        config.tasks,
+        &fork_config,
+        &mut renderer,
+        &mut input,
+        io,
+        vcr,
+    )
+    .await?;
+    renderer.render_fork_results(&results);
+    Ok(results)
+}
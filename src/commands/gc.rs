@@ -1,28 +1,143 @@
 use std::collections::HashSet;
-use std::io::Write;
-use std::path::Path;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::num::NonZero;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
 
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 
+use crate::dispatch_debug;
 use crate::vcr::VcrContext;
 use crate::worker_state;
-use crate::worktree;
+use crate::worktree::{self, Execution, WorktreeEntry};
+use crate::worktree_base;
+
+/// Filesystem entries a worktree's size walk visits before giving up, so a
+/// huge `node_modules` or `target` can't stall gc.
+const WALK_LIMIT: usize = 200_000;
+
+/// Worktrees inspected at once. Each runs a few git commands and a size
+/// walk, which is itself spread across threads.
+const INSPECT_CONCURRENCY: usize = 8;
+
+/// Options for [`gc`].
+#[derive(Debug, Default)]
+pub struct GcConfig {
+    /// Actually remove things; without it gc only lists what it would
+    /// remove. Worktrees with uncommitted changes or unlanded commits are
+    /// only removed after listing them and asking for confirmation.
+    pub force: bool,
+    /// Only remove worktrees with no commits or file changes for this long.
+    pub older_than: Option<Duration>,
+    /// Where coven puts worktrees (`--worktree-base`); defaults like the
+    /// worker's. Worktrees outside it are never removed.
+    pub worktree_base: Option<PathBuf>,
+    /// Don't remove anything even with `force`: just print what would go.
+    pub dry_run: bool,
+    /// Where `--debug-dispatch` records are pruned from; defaults to
+    /// `~/.coven/debug`.
+    pub debug_dir: Option<PathBuf>,
+}
+
+/// Size, age, and unsaved work of one worktree.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorktreeUsage {
+    pub bytes: u64,
+    /// The size walk stopped at [`WALK_LIMIT`] entries, so `bytes` is a lower bound.
+    pub truncated: bool,
+    /// Seconds since the most recently modified file was changed.
+    pub modified_age: Option<u64>,
+    /// Seconds since HEAD was committed.
+    pub commit_age: Option<u64>,
+    /// Modified and untracked files.
+    pub changed_files: usize,
+    /// Commits not on main.
+    pub unlanded_commits: u64,
+}
+
+impl WorktreeUsage {
+    /// Seconds since the last commit or file change, whichever was later.
+    fn age(&self) -> Option<u64> {
+        match (self.modified_age, self.commit_age) {
+            (Some(modified), Some(commit)) => Some(modified.min(commit)),
+            (modified, commit) => modified.or(commit),
+        }
+    }
+
+    /// Whether removing the worktree would lose work.
+    fn has_unsaved_work(&self) -> bool {
+        self.changed_files > 0 || self.unlanded_commits > 0
+    }
+}
+
+/// What gc does with a worktree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Selection {
+    /// A live worker owns it.
+    Live,
+    /// Not under the worktree base, so coven didn't create it.
+    OutsideBase,
+    /// Active more recently than `--older-than`.
+    Recent,
+    /// Has unsaved work and `--force` wasn't given.
+    Protected,
+    /// Nothing would be lost.
+    Remove,
+    /// `--force` removes it, losing work, once the user confirms.
+    RemoveLosingWork,
+}
+
+impl GcConfig {
+    /// Whether nothing is removed, only listed: the default, until `--force`.
+    fn is_dry_run(&self) -> bool {
+        self.dry_run || !self.force
+    }
+}
+
+/// Decide what to do with a non-main worktree.
+fn select(usage: &WorktreeUsage, live: bool, in_base: bool, config: &GcConfig) -> Selection {
+    if live {
+        return Selection::Live;
+    }
+    if !in_base {
+        return Selection::OutsideBase;
+    }
+    if let Some(min_age) = config.older_than
+        && usage.age().is_none_or(|age| age < min_age.as_secs())
+    {
+        return Selection::Recent;
+    }
+    match (usage.has_unsaved_work(), config.force) {
+        (false, _) => Selection::Remove,
+        (true, false) => Selection::Protected,
+        (true, true) => Selection::RemoveLosingWork,
+    }
+}
 
 /// Remove orphaned worktrees left behind by dead workers.
 ///
-/// Lists all git worktrees, compares against live workers, and removes
-/// any non-main worktree that no live worker owns.
+/// Lists all git worktrees with their disk usage and age, compares against
+/// live workers, and selects any non-main worktree under the worktree base
+/// that no live worker owns (and, with `older_than`, that has been idle that
+/// long). Worktrees of landed branches have nothing to lose, so they go.
 ///
-/// When `force` is true, passes `--force` to `git worktree remove` so
-/// dirty worktrees can be cleaned up.
+/// Without `force` this is a dry run that prints what would be removed.
+/// With it, worktrees with uncommitted changes or unlanded commits are
+/// removed too, once what would be lost is listed and `stdin` answers `y`.
 pub async fn gc(
     vcr: &VcrContext,
-    force: bool,
+    config: &GcConfig,
     working_dir: Option<&Path>,
     writer: &mut impl Write,
+    stdin: &mut impl BufRead,
 ) -> Result<()> {
     let project_root = super::resolve_working_dir(vcr, working_dir).await?;
 
+    prune_dispatch_debug(vcr, config, writer).await?;
+
     let worktrees = vcr
         .call(
             "worktree::list_worktrees",
@@ -37,43 +152,263 @@ pub async fn gc(
     let live_workers = vcr
         .call(
             "worker_state::read_all",
-            project_root,
+            project_root.clone(),
             async |p: &String| worker_state::read_all(Path::new(p)),
         )
         .await?;
 
     let live_branches: HashSet<&str> = live_workers.iter().map(|w| w.branch.as_str()).collect();
 
-    let orphaned: Vec<_> = worktrees
+    let others: Vec<_> = worktrees.iter().filter(|wt| !wt.is_main).collect();
+    if others.is_empty() {
+        writeln!(writer, "No orphaned worktrees.")?;
+        return Ok(());
+    }
+
+    let flag = config
+        .worktree_base
+        .as_deref()
+        .map(worktree::utf8_path)
+        .transpose()?
+        .map(str::to_string);
+    let base = vcr
+        .call(
+            "gc::worktree_base",
+            (project_root, flag),
+            async |(root, flag): &(String, Option<String>)| {
+                resolve_base(Path::new(root), flag.as_deref().map(Path::new))
+            },
+        )
+        .await?;
+
+    let paths: Vec<String> = others
         .iter()
-        .filter(|wt| !wt.is_main)
-        .filter(|wt| {
-            wt.branch
+        .map(|wt| wt.path.display().to_string())
+        .collect();
+    let usages = vcr
+        .call(
+            "gc::inspect_worktrees",
+            paths,
+            async |paths: &Vec<String>| Ok(inspect_all(paths)),
+        )
+        .await?;
+
+    let inspected: Vec<_> = others
+        .iter()
+        .zip(&usages)
+        .map(|(wt, usage)| {
+            let live = wt
+                .branch
                 .as_deref()
-                .is_none_or(|b| !live_branches.contains(b))
+                .is_some_and(|b| live_branches.contains(b));
+            let in_base = wt.path.starts_with(&base);
+            let selection = usage
+                .as_ref()
+                .ok()
+                .map(|u| select(u, live, in_base, config));
+            Inspected {
+                entry: wt,
+                usage: usage.as_ref(),
+                selection,
+            }
         })
         .collect();
 
-    if orphaned.is_empty() {
-        writeln!(writer, "No orphaned worktrees.")?;
+    write_usage_table(writer, &inspected)?;
+
+    // A dry run loses nothing, so there's nothing to confirm.
+    let confirmed = config.is_dry_run() || confirm_losing_work(writer, stdin, &inspected)?;
+    let to_remove: Vec<_> = inspected
+        .iter()
+        .filter(|i| match i.selection {
+            Some(Selection::Remove) => true,
+            Some(Selection::RemoveLosingWork) => confirmed,
+            _ => false,
+        })
+        .collect();
+
+    remove_worktrees(vcr, config, writer, &to_remove).await?;
+    write_kept_summary(writer, config, &base, &inspected)
+}
+
+/// Remove `--debug-dispatch` records older than [`dispatch_debug::MAX_AGE`].
+async fn prune_dispatch_debug(
+    vcr: &VcrContext,
+    config: &GcConfig,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let dir = config.debug_dir.as_ref().map(|p| p.display().to_string());
+    let pruned = vcr
+        .call(
+            "gc::prune_dispatch_debug",
+            (dir, config.is_dry_run()),
+            async |(dir, dry_run): &(Option<String>, bool)| {
+                let root = dir.as_ref().map(PathBuf::from);
+                match root.or_else(dispatch_debug::home_default) {
+                    Some(root) => dispatch_debug::prune(&root, dispatch_debug::MAX_AGE, *dry_run),
+                    None => Ok(0),
+                }
+            },
+        )
+        .await?;
+    if pruned > 0 {
+        let verb = if config.is_dry_run() {
+            "Would remove"
+        } else {
+            "Removed"
+        };
+        let age = format_age(dispatch_debug::MAX_AGE.as_secs());
+        writeln!(
+            writer,
+            "{verb} {pruned} dispatch debug record(s) older than {age}.\n"
+        )?;
+    }
+    Ok(())
+}
+
+/// The worktree base, resolved like the worker's and canonicalized (when
+/// it exists) so it compares equal to the paths git reports.
+fn resolve_base(repo_path: &Path, flag: Option<&Path>) -> Result<PathBuf> {
+    let repo_config = crate::config::load(repo_path)?;
+    let base = worktree_base::resolve(
+        flag,
+        repo_config.worktree_base.as_deref(),
+        repo_path,
+        worktree_base::home_default(),
+    )?;
+    Ok(base.canonicalize().unwrap_or(base))
+}
+
+/// A non-main worktree with what gc found out about it.
+struct Inspected<'a> {
+    entry: &'a WorktreeEntry,
+    usage: Result<&'a WorktreeUsage, &'a String>,
+    /// `None` if it couldn't be inspected; such worktrees are left alone.
+    selection: Option<Selection>,
+}
+
+impl Inspected<'_> {
+    fn label(&self) -> &str {
+        self.entry.branch.as_deref().unwrap_or("(detached)")
+    }
+}
+
+fn write_usage_table(writer: &mut impl Write, inspected: &[Inspected<'_>]) -> Result<()> {
+    let label_width = inspected.iter().map(|i| i.label().len()).max().unwrap_or(0);
+    writeln!(writer, "{} worktree(s):\n", inspected.len())?;
+    for i in inspected {
+        let label = i.label();
+        match i.usage {
+            Ok(usage) => {
+                let size = format_size(usage.bytes, usage.truncated);
+                let age = usage
+                    .age()
+                    .map_or_else(|| "?".to_string(), |a| format!("{} ago", format_age(a)));
+                let state = match i.selection {
+                    Some(Selection::Live) => "live",
+                    Some(Selection::OutsideBase) => "outside worktree base",
+                    _ => "orphaned",
+                };
+                let mut notes = vec![state.to_string()];
+                notes.extend(unsaved_work(usage));
+                writeln!(
+                    writer,
+                    "  {label:<label_width$}  {size:>8}  {age:>8}  {}",
+                    notes.join(", ")
+                )?;
+            }
+            Err(e) => writeln!(writer, "  {label:<label_width$}  can't inspect: {e}")?,
+        }
+    }
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Describe the work removing a worktree would lose, if any.
+fn unsaved_work(usage: &WorktreeUsage) -> Vec<String> {
+    let mut parts = Vec::new();
+    match usage.changed_files {
+        0 => {}
+        1 => parts.push("1 changed file".to_string()),
+        n => parts.push(format!("{n} changed files")),
+    }
+    match usage.unlanded_commits {
+        0 => {}
+        1 => parts.push("1 unlanded commit".to_string()),
+        n => parts.push(format!("{n} unlanded commits")),
+    }
+    parts
+}
+
+/// With `--force`, list the work that removal would lose and ask whether to
+/// go ahead. Returns true if there's nothing to ask about or the user said yes.
+fn confirm_losing_work(
+    writer: &mut impl Write,
+    stdin: &mut impl BufRead,
+    inspected: &[Inspected<'_>],
+) -> Result<bool> {
+    let at_risk: Vec<_> = inspected
+        .iter()
+        .filter(|i| i.selection == Some(Selection::RemoveLosingWork))
+        .collect();
+    if at_risk.is_empty() {
+        return Ok(true);
+    }
+    writeln!(writer, "Removing these will lose work:\n")?;
+    for i in &at_risk {
+        let work = i.usage.map(unsaved_work).unwrap_or_default();
+        writeln!(
+            writer,
+            "  {} ({}): {}",
+            i.label(),
+            i.entry.path.display(),
+            work.join(", ")
+        )?;
+    }
+    write!(writer, "\nRemove them anyway? [y/N] ")?;
+    writer.flush()?;
+    let mut answer = String::new();
+    stdin.read_line(&mut answer)?;
+    let confirmed = matches!(answer.trim().to_lowercase().as_str(), "y" | "yes");
+    if !confirmed {
+        writeln!(writer, "Keeping them.")?;
+    }
+    writeln!(writer)?;
+    Ok(confirmed)
+}
+
+async fn remove_worktrees(
+    vcr: &VcrContext,
+    config: &GcConfig,
+    writer: &mut impl Write,
+    to_remove: &[&Inspected<'_>],
+) -> Result<()> {
+    if to_remove.is_empty() {
+        writeln!(writer, "Nothing to remove.")?;
         return Ok(());
     }
 
+    if config.is_dry_run() {
+        return plan_removals(vcr, config, writer, to_remove).await;
+    }
+
     writeln!(
         writer,
         "Removing {} orphaned worktree(s):\n",
-        orphaned.len()
+        to_remove.len()
     )?;
 
     let mut removed = 0;
-    for wt in &orphaned {
-        let label = wt.branch.as_deref().unwrap_or("(detached)");
-        write!(writer, "  {} ({})", label, wt.path.display())?;
+    for i in to_remove {
+        write!(writer, "  {} ({})", i.label(), i.entry.path.display())?;
 
-        let wt_path = wt.path.display().to_string();
+        let wt_path = i.entry.path.display().to_string();
+        let force = i.selection == Some(Selection::RemoveLosingWork);
         let result = vcr
             .call("worktree::remove", wt_path.clone(), async |p: &String| {
-                worktree::remove(Path::new(p), force).map_err(|e| anyhow::anyhow!("{e}"))
+                worktree::remove(Path::new(p), force, Execution::Run)
+                    .map(drop)
+                    .map_err(|e| anyhow::anyhow!("{e}"))
             })
             .await;
 
@@ -85,10 +420,7 @@ pub async fn gc(
             Err(e) => {
                 writeln!(writer, " — failed: {e}")?;
                 if !force {
-                    writeln!(
-                        writer,
-                        "    hint: re-run with --force, or: git worktree remove --force {wt_path}"
-                    )?;
+                    writeln!(writer, "    hint: git worktree remove --force {wt_path}")?;
                 }
             }
         }
@@ -97,6 +429,728 @@ pub async fn gc(
     if removed > 0 {
         writeln!(writer, "\nRemoved {removed} worktree(s).")?;
     }
+    Ok(())
+}
 
+/// A dry run (no `--force`, or `--dry-run`): list the git commands removing
+/// each worktree would run. Read-only lookups still happen; nothing is
+/// removed.
+async fn plan_removals(
+    vcr: &VcrContext,
+    config: &GcConfig,
+    writer: &mut impl Write,
+    to_remove: &[&Inspected<'_>],
+) -> Result<()> {
+    writeln!(
+        writer,
+        "Would remove {} orphaned worktree(s) (dry run):\n",
+        to_remove.len()
+    )?;
+    for i in to_remove {
+        writeln!(writer, "  {} ({})", i.label(), i.entry.path.display())?;
+        let force = i.selection == Some(Selection::RemoveLosingWork);
+        let plan = vcr
+            .call(
+                "worktree::remove_dry_run",
+                i.entry.path.display().to_string(),
+                async |p: &String| {
+                    worktree::remove(Path::new(p), force, Execution::DryRun)
+                        .map_err(|e| anyhow::anyhow!("{e}"))
+                },
+            )
+            .await;
+        match plan {
+            Ok(plan) => {
+                for command in plan {
+                    writeln!(writer, "    {command}")?;
+                }
+            }
+            Err(e) => writeln!(writer, "    can't plan removal: {e}")?,
+        }
+    }
+    if !config.force {
+        writeln!(writer, "\nRun with --force to remove them.")?;
+    }
     Ok(())
 }
+
+/// Explain which orphaned worktrees were kept, and why.
+fn write_kept_summary(
+    writer: &mut impl Write,
+    config: &GcConfig,
+    base: &Path,
+    inspected: &[Inspected<'_>],
+) -> Result<()> {
+    let count = |selection| {
+        inspected
+            .iter()
+            .filter(|i| i.selection == Some(selection))
+            .count()
+    };
+    let protected = count(Selection::Protected);
+    let recent = count(Selection::Recent);
+    let outside = count(Selection::OutsideBase);
+    if protected > 0 || recent > 0 || outside > 0 {
+        writeln!(writer)?;
+    }
+    if outside > 0 {
+        writeln!(
+            writer,
+            "Kept {outside} outside the worktree base ({}).",
+            base.display()
+        )?;
+    }
+    if protected > 0 {
+        writeln!(
+            writer,
+            "Kept {protected} with uncommitted changes or unlanded commits (--force to remove)."
+        )?;
+    }
+    if let Some(older_than) = config.older_than
+        && recent > 0
+    {
+        writeln!(
+            writer,
+            "Kept {recent} active within the last {}.",
+            format_age(older_than.as_secs())
+        )?;
+    }
+    Ok(())
+}
+
+// ── Inspection ──────────────────────────────────────────────────────────
+
+/// Inspect each worktree: its disk usage, age, and unsaved work.
+///
+/// Worktrees are inspected [`INSPECT_CONCURRENCY`] at a time; results come
+/// back in the order of `paths` and are recorded as one VCR entry.
+fn inspect_all(paths: &[String]) -> Vec<Result<WorktreeUsage, String>> {
+    let now = SystemTime::now();
+    map_concurrently(paths, INSPECT_CONCURRENCY, |p| {
+        inspect(Path::new(p), now).map_err(|e| format!("{e:#}"))
+    })
+}
+
+/// Map `f` over `items` on up to `limit` threads, keeping the input order.
+fn map_concurrently<T: Sync, R: Send>(
+    items: &[T],
+    limit: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..limit.clamp(1, items.len().max(1)))
+            .map(|_| {
+                let (next, f) = (&next, &f);
+                scope.spawn(move || {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            return done;
+                        };
+                        done.push((i, f(item)));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    results.sort_by_key(|&(i, _)| i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+fn inspect(path: &Path, now: SystemTime) -> Result<WorktreeUsage> {
+    let changed_files = worktree::changed_file_count(path)?;
+    let unlanded_commits = worktree::unique_commit_count(path)?;
+    let commit_time = worktree::head_commit_time(path)?;
+    let now_secs = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs();
+
+    let usage = disk_usage(path, WALK_LIMIT);
+    Ok(WorktreeUsage {
+        bytes: usage.bytes,
+        truncated: usage.truncated,
+        modified_age: usage
+            .newest
+            .map(|t| now.duration_since(t).unwrap_or_default().as_secs()),
+        commit_age: Some(now_secs.saturating_sub(commit_time)),
+        changed_files,
+        unlanded_commits,
+    })
+}
+
+/// Result of a [`disk_usage`] walk.
+#[derive(Debug, Clone, Copy, Default)]
+struct DirUsage {
+    bytes: u64,
+    /// Latest modification time of anything walked.
+    newest: Option<SystemTime>,
+    /// The walk ran out of entries before finishing.
+    truncated: bool,
+}
+
+impl DirUsage {
+    fn merge(&mut self, other: Self) {
+        self.bytes += other.bytes;
+        self.newest = self.newest.max(other.newest);
+        self.truncated |= other.truncated;
+    }
+}
+
+/// Total size and latest modification time of everything under `root`,
+/// like `du`. Symlinks aren't followed. The top-level entries are split
+/// across threads, which share a budget of `limit` entries.
+fn disk_usage(root: &Path, limit: usize) -> DirUsage {
+    let mut total = DirUsage {
+        newest: fs::symlink_metadata(root).and_then(|m| m.modified()).ok(),
+        ..DirUsage::default()
+    };
+    let Ok(entries) = fs::read_dir(root) else {
+        return total;
+    };
+    let entries: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    if entries.is_empty() {
+        return total;
+    }
+
+    let threads = std::thread::available_parallelism().map_or(4, NonZero::get);
+    let budget = AtomicUsize::new(limit);
+    std::thread::scope(|scope| {
+        let walkers: Vec<_> = entries
+            .chunks(entries.len().div_ceil(threads))
+            .map(|chunk| {
+                let budget = &budget;
+                scope.spawn(move || {
+                    let mut usage = DirUsage::default();
+                    for path in chunk {
+                        walk(path, budget, &mut usage);
+                    }
+                    usage
+                })
+            })
+            .collect();
+        for walker in walkers {
+            if let Ok(usage) = walker.join() {
+                total.merge(usage);
+            }
+        }
+    });
+    total
+}
+
+fn walk(path: &Path, budget: &AtomicUsize, usage: &mut DirUsage) {
+    if budget
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+        .is_err()
+    {
+        usage.truncated = true;
+        return;
+    }
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return;
+    };
+    usage.bytes += meta.len();
+    usage.newest = usage.newest.max(meta.modified().ok());
+    if meta.is_dir()
+        && let Ok(entries) = fs::read_dir(path)
+    {
+        for entry in entries.flatten() {
+            walk(&entry.path(), budget, usage);
+        }
+    }
+}
+
+// ── Formatting and parsing ──────────────────────────────────────────────
+
+/// Human-readable size (`340 MB`), prefixed with `≥` when it's a lower bound.
+fn format_size(bytes: u64, truncated: bool) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut unit = 0;
+    let mut whole = bytes;
+    let mut tenths = 0;
+    while whole >= 1000 && unit + 1 < UNITS.len() {
+        tenths = whole % 1000 / 100;
+        whole /= 1000;
+        unit += 1;
+    }
+    let bound = if truncated { "≥" } else { "" };
+    if unit == 0 || whole >= 10 {
+        format!("{bound}{whole} {}", UNITS[unit])
+    } else {
+        format!("{bound}{whole}.{tenths} {}", UNITS[unit])
+    }
+}
+
+/// Compact age in the largest whole unit: `45s`, `12m`, `5h`, `3d`.
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3_600 => format!("{}m", secs / 60),
+        3_600..86_400 => format!("{}h", secs / 3_600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+/// Parse an `--older-than` age: a number with a unit of `s`, `m`, `h`, `d`,
+/// or `w` (e.g. `12h`, `7d`).
+pub fn parse_age(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid age '{s}': expected a number and unit, like 7d"))?;
+    let unit_secs = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 604_800,
+        "" => return Err(format!("age '{s}' needs a unit: s, m, h, d, or w")),
+        other => {
+            return Err(format!(
+                "unknown unit '{other}' in age '{s}' (use s, m, h, d, or w)"
+            ));
+        }
+    };
+    number
+        .checked_mul(unit_secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("age '{s}' is too large"))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86_400;
+
+    fn usage(age_days: u64, changed_files: usize, unlanded_commits: u64) -> WorktreeUsage {
+        WorktreeUsage {
+            modified_age: Some(age_days * DAY),
+            commit_age: Some(age_days * DAY + 60),
+            changed_files,
+            unlanded_commits,
+            ..WorktreeUsage::default()
+        }
+    }
+
+    #[test]
+    fn selection_matrix() {
+        use Selection::{Live, OutsideBase, Protected, Recent, Remove, RemoveLosingWork};
+        let week = Some(Duration::from_secs(7 * DAY));
+        // (age in days, changed files, unlanded commits, older_than, force, expected)
+        let cases = [
+            (30, 0, 0, None, false, Remove),
+            (30, 0, 0, week, false, Remove),
+            (1, 0, 0, None, false, Remove),
+            (1, 0, 0, week, false, Recent),
+            (1, 0, 0, week, true, Recent),
+            (30, 3, 0, None, false, Protected),
+            (30, 0, 2, None, false, Protected),
+            (30, 3, 2, week, false, Protected),
+            (30, 3, 0, None, true, RemoveLosingWork),
+            (30, 0, 2, week, true, RemoveLosingWork),
+            (1, 3, 2, week, true, Recent),
+        ];
+        for (age, changed, unlanded, older_than, force, expected) in cases {
+            let config = GcConfig {
+                force,
+                older_than,
+                ..GcConfig::default()
+            };
+            assert_eq!(
+                select(&usage(age, changed, unlanded), false, true, &config),
+                expected,
+                "age {age}d, {changed} changed, {unlanded} unlanded, {older_than:?}, force {force}"
+            );
+        }
+        let config = GcConfig {
+            force: true,
+            ..GcConfig::default()
+        };
+        assert_eq!(select(&usage(30, 0, 0), true, true, &config), Live);
+        assert_eq!(select(&usage(30, 0, 0), false, false, &config), OutsideBase);
+        assert_eq!(select(&usage(30, 3, 2), false, false, &config), OutsideBase);
+    }
+
+    #[test]
+    fn unknown_age_is_never_old_enough() {
+        let config = GcConfig {
+            older_than: Some(Duration::from_secs(DAY)),
+            ..GcConfig::default()
+        };
+        assert_eq!(
+            select(&WorktreeUsage::default(), false, true, &config),
+            Selection::Recent
+        );
+    }
+
+    #[test]
+    fn age_is_the_most_recent_activity() {
+        let mut u = WorktreeUsage {
+            modified_age: Some(100),
+            commit_age: Some(5_000),
+            ..WorktreeUsage::default()
+        };
+        assert_eq!(u.age(), Some(100));
+        u.modified_age = None;
+        assert_eq!(u.age(), Some(5_000));
+    }
+
+    #[test]
+    fn disk_usage_sums_nested_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("top.txt"), vec![0u8; 1_000]).unwrap();
+        fs::write(dir.path().join("a/one.txt"), vec![0u8; 2_000]).unwrap();
+        fs::write(dir.path().join("a/b/two.txt"), vec![0u8; 3_000]).unwrap();
+
+        let usage = disk_usage(dir.path(), WALK_LIMIT);
+        assert!(!usage.truncated);
+        // Files plus the directory entries themselves.
+        assert!(usage.bytes >= 6_000, "{}", usage.bytes);
+        let file_meta = fs::metadata(dir.path().join("a/b/two.txt")).unwrap();
+        assert!(usage.newest.unwrap() >= file_meta.modified().unwrap());
+    }
+
+    #[test]
+    fn disk_usage_stops_at_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..20 {
+            fs::write(dir.path().join(format!("{i}.txt")), "x").unwrap();
+        }
+        let usage = disk_usage(dir.path(), 5);
+        assert!(usage.truncated);
+        assert!(usage.bytes <= 5 * 4_096);
+    }
+
+    #[test]
+    fn map_concurrently_overlaps_within_limit_and_keeps_order() {
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let items: Vec<usize> = (0..12).collect();
+        let results = map_concurrently(&items, 3, |&i| {
+            let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            // Hold the slot until another inspection overlaps this one (or
+            // give up, so a sequential implementation fails instead of hanging).
+            for _ in 0..1_000 {
+                if peak.load(Ordering::SeqCst) > 1 {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            i * 10
+        });
+        assert_eq!(results, (0..12).map(|i| i * 10).collect::<Vec<_>>());
+        let peak = peak.into_inner();
+        assert!((2..=3).contains(&peak), "peak concurrency {peak}");
+    }
+
+    #[test]
+    fn map_concurrently_handles_empty_input() {
+        let results: Vec<u8> = map_concurrently(&[] as &[u8], 4, |&x| x);
+        assert!(results.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn disk_usage_does_not_follow_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("big.bin"), vec![0u8; 100_000]).unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+        assert!(disk_usage(dir.path(), WALK_LIMIT).bytes < 100_000);
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {args:?}");
+    }
+
+    /// A repo on `main` with one commit.
+    fn init_repo() -> tempfile::TempDir {
+        let repo = tempfile::tempdir().unwrap();
+        git(repo.path(), &["init", "-b", "main"]);
+        git(repo.path(), &["config", "user.email", "test@test.com"]);
+        git(repo.path(), &["config", "user.name", "Test"]);
+        fs::write(repo.path().join("README.md"), "hi\n").unwrap();
+        git(repo.path(), &["add", "."]);
+        git(repo.path(), &["commit", "-m", "initial"]);
+        repo
+    }
+
+    #[test]
+    fn inspect_reports_ages_from_now() {
+        let repo = init_repo();
+        fs::write(repo.path().join("scratch.txt"), "wip\n").unwrap();
+
+        let later = SystemTime::now() + Duration::from_secs(3 * DAY);
+        let usage = inspect(repo.path(), later).unwrap();
+        assert_eq!(usage.changed_files, 1);
+        assert_eq!(usage.unlanded_commits, 0);
+        // Within a minute of three days.
+        let about_three_days = |secs: Option<u64>| secs.unwrap().abs_diff(3 * DAY) < 60;
+        assert!(about_three_days(usage.commit_age), "{usage:?}");
+        assert!(about_three_days(usage.modified_age), "{usage:?}");
+        assert!(usage.bytes > 0);
+    }
+
+    #[tokio::test]
+    async fn removes_landed_worktrees_under_the_base_only() {
+        let repo = init_repo();
+        let base = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        let spawn = |base_path: &Path, branch: &str| {
+            worktree::spawn(&worktree::SpawnOptions {
+                repo_path: repo.path(),
+                branch: Some(branch),
+                branch_prefix: None,
+                base_path,
+                branch_from: None,
+            })
+            .unwrap()
+            .worktree_path
+        };
+        let landed = spawn(base.path(), "landed");
+        let unlanded = spawn(base.path(), "unlanded");
+        fs::write(unlanded.join("new.txt"), "work\n").unwrap();
+        git(&unlanded, &["add", "."]);
+        git(&unlanded, &["commit", "-m", "work"]);
+        let outside = spawn(elsewhere.path(), "outside");
+
+        let config = GcConfig {
+            force: true,
+            worktree_base: Some(base.path().to_path_buf()),
+            debug_dir: Some(base.path().join("debug")),
+            ..GcConfig::default()
+        };
+        let mut output = Vec::new();
+        gc(
+            &VcrContext::live(),
+            &config,
+            Some(repo.path()),
+            &mut output,
+            &mut std::io::Cursor::new("n\n"),
+        )
+        .await
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(!landed.exists(), "{output}");
+        assert!(unlanded.exists(), "{output}");
+        assert!(outside.exists(), "{output}");
+        assert!(
+            output.contains("Removing 1 orphaned worktree(s)"),
+            "{output}"
+        );
+        assert!(output.contains("outside worktree base"), "{output}");
+        assert!(
+            output.contains("Kept 1 outside the worktree base"),
+            "{output}"
+        );
+        // Declining the confirmation keeps the worktree with unlanded work.
+        assert!(output.contains("Keeping them."), "{output}");
+    }
+
+    #[tokio::test]
+    async fn dry_run_prints_commands_and_removes_nothing() {
+        let repo = init_repo();
+        let base = tempfile::tempdir().unwrap();
+        let spawn = |branch: &str| {
+            worktree::spawn(&worktree::SpawnOptions {
+                repo_path: repo.path(),
+                branch: Some(branch),
+                branch_prefix: None,
+                base_path: base.path(),
+                branch_from: None,
+            })
+            .unwrap()
+            .worktree_path
+        };
+        let landed = spawn("landed");
+        let unlanded = spawn("unlanded");
+        fs::write(unlanded.join("new.txt"), "work\n").unwrap();
+
+        let config = GcConfig {
+            force: true,
+            dry_run: true,
+            worktree_base: Some(base.path().to_path_buf()),
+            debug_dir: Some(base.path().join("debug")),
+            ..GcConfig::default()
+        };
+        let mut output = Vec::new();
+        gc(
+            &VcrContext::live(),
+            &config,
+            Some(repo.path()),
+            &mut output,
+            &mut std::io::empty(),
+        )
+        .await
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(landed.exists(), "{output}");
+        assert!(unlanded.exists(), "{output}");
+        assert!(
+            output.contains("Would remove 2 orphaned worktree(s) (dry run)"),
+            "{output}"
+        );
+        // --force would lose work, but a dry run doesn't ask.
+        assert!(!output.contains("Remove them anyway?"), "{output}");
+        let main = repo.path().canonicalize().unwrap();
+        let landed = landed.canonicalize().unwrap();
+        let unlanded = unlanded.canonicalize().unwrap();
+        for expected in [
+            format!(
+                "git -C {} worktree remove {}\n",
+                main.display(),
+                landed.display()
+            ),
+            format!("git -C {} branch -D landed", main.display()),
+            format!(
+                "git -C {} worktree remove --force {}",
+                main.display(),
+                unlanded.display()
+            ),
+        ] {
+            assert!(output.contains(&expected), "{expected}\n{output}");
+        }
+    }
+
+    #[test]
+    fn formats_sizes_and_ages() {
+        assert_eq!(format_size(512, false), "512 B");
+        assert_eq!(format_size(1_234, false), "1.2 KB");
+        assert_eq!(format_size(340_000_000, false), "340 MB");
+        assert_eq!(format_size(2_500_000_000, true), "≥2.5 GB");
+        assert_eq!(format_age(45), "45s");
+        assert_eq!(format_age(3 * 3_600 + 5), "3h");
+        assert_eq!(format_age(12 * DAY), "12d");
+    }
+
+    #[test]
+    fn parses_ages() {
+        assert_eq!(parse_age("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_age("12h"), Ok(Duration::from_hours(12)));
+        assert_eq!(parse_age("7d"), Ok(Duration::from_secs(7 * DAY)));
+        assert_eq!(parse_age("2w"), Ok(Duration::from_secs(14 * DAY)));
+        assert!(parse_age("7").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("7y").is_err());
+    }
+
+    /// Replay a gc run over a main worktree, a live worker's, and three
+    /// orphans: one old and clean, one old with unsaved work, one recent.
+    fn gc_replay(dry_run: bool) -> VcrContext {
+        let root = "/repo";
+        let worktree = |branch: &str, is_main: bool| {
+            let path = if is_main {
+                root.to_string()
+            } else {
+                format!("/wt/repo/{branch}")
+            };
+            serde_json::json!({"path": path, "branch": branch, "is_main": is_main})
+        };
+        let usage = |bytes: u64, age_days: u64, changed: usize, unlanded: u64| {
+            serde_json::json!({"Ok": {
+                "bytes": bytes,
+                "truncated": bytes > 1_000_000_000,
+                "modified_age": age_days * DAY + 600,
+                "commit_age": age_days * DAY + 7_200,
+                "changed_files": changed,
+                "unlanded_commits": unlanded,
+            }})
+        };
+        let branches = ["swift-fox-1", "calm-oak-2", "bold-pine-3", "keen-wave-4"];
+        let paths: Vec<String> = branches.iter().map(|b| format!("/wt/repo/{b}")).collect();
+        let mut worktrees = vec![worktree("main", true)];
+        worktrees.extend(branches.iter().map(|b| worktree(b, false)));
+        let entries = [
+            serde_json::json!({"label": "current_dir", "args": null, "result": {"Ok": root}}),
+            serde_json::json!({"label": "gc::prune_dispatch_debug", "args": [null, dry_run], "result": {"Ok": 2}}),
+            serde_json::json!({"label": "worktree::list_worktrees", "args": root, "result": {"Ok": worktrees}}),
+            serde_json::json!({"label": "worker_state::read_all", "args": root, "result": {"Ok": [
+                {"pid": 1, "branch": "swift-fox-1", "agent": "dispatch", "args": {}},
+            ]}}),
+            serde_json::json!({"label": "gc::worktree_base", "args": [root, null], "result": {"Ok": "/wt"}}),
+            serde_json::json!({"label": "gc::inspect_worktrees", "args": paths, "result": {"Ok": [
+                usage(1_800_000_000, 0, 4, 1),
+                usage(340_000_000, 30, 0, 0),
+                usage(2_500_000_000, 12, 3, 2),
+                usage(12_000, 0, 0, 0),
+            ]}}),
+        ]
+        .map(|entry| entry.to_string());
+        // Without --force only the clean orphan is selected, and a dry run
+        // plans its removal instead; bold-pine-3 is removed after confirming.
+        let removals = if dry_run {
+            vec![
+                serde_json::json!({"label": "worktree::remove_dry_run", "args": "/wt/repo/calm-oak-2", "result": {"Ok": [
+                    {"dir": root, "args": ["worktree", "remove", "/wt/repo/calm-oak-2"]},
+                    {"dir": root, "args": ["branch", "-D", "calm-oak-2"]},
+                ]}}),
+            ]
+        } else {
+            vec![
+                serde_json::json!({"label": "worktree::remove", "args": "/wt/repo/calm-oak-2", "result": {"Ok": null}}),
+                serde_json::json!({"label": "worktree::remove", "args": "/wt/repo/bold-pine-3", "result": {"Ok": null}}),
+            ]
+        };
+        let entries: Vec<String> = entries
+            .into_iter()
+            .chain(removals.iter().map(ToString::to_string))
+            .collect();
+        VcrContext::replay(&entries.join("\n")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn gc_force_older_than_output() {
+        let vcr = gc_replay(false);
+        let config = GcConfig {
+            force: true,
+            older_than: Some(Duration::from_secs(7 * DAY)),
+            ..GcConfig::default()
+        };
+        let mut output = Vec::new();
+        let mut stdin = std::io::Cursor::new("y\n");
+        gc(&vcr, &config, None, &mut output, &mut stdin)
+            .await
+            .unwrap();
+        assert!(vcr.unreplayed_labels().is_empty());
+        insta::assert_snapshot!(String::from_utf8(output).unwrap());
+    }
+
+    #[tokio::test]
+    async fn gc_without_force_is_a_dry_run() {
+        let vcr = gc_replay(true);
+        let config = GcConfig {
+            force: false,
+            older_than: Some(Duration::from_secs(7 * DAY)),
+            ..GcConfig::default()
+        };
+        let mut output = Vec::new();
+        gc(&vcr, &config, None, &mut output, &mut std::io::empty())
+            .await
+            .unwrap();
+        assert!(vcr.unreplayed_labels().is_empty());
+        insta::assert_snapshot!(String::from_utf8(output).unwrap());
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
 use std::fs;
 use std::io::{BufRead, Write};
 use std::path::{Path, PathBuf};
@@ -15,27 +17,50 @@ const LAND_SCRIPT: &str = include_str!("../../.coven/land.sh");
 const SYSTEM_DOC: &str = include_str!("../../.coven/system.md");
 const CONFIG_DOC: &str = include_str!("../../.coven/config.toml");
 
-struct TemplateFile {
-    path: &'static str,
+const COVEN_DIR: &str = ".coven";
+
+/// Records the content hash of each scaffold file as coven last wrote it, so
+/// `init --update` can tell untouched files from locally edited ones.
+const MANIFEST_PATH: &str = ".coven/init-manifest.toml";
+
+/// A file coven generates, with its path relative to the project root.
+struct ScaffoldFile {
+    path: String,
     content: &'static str,
 }
 
-const AGENT_TEMPLATES: &[TemplateFile] = &[
-    TemplateFile {
-        path: "dispatch.md",
-        content: DISPATCH_PROMPT,
-    },
-    TemplateFile {
-        path: "main.md",
-        content: MAIN_PROMPT,
-    },
-    TemplateFile {
-        path: "review.md",
-        content: REVIEW_PROMPT,
-    },
-];
-
-const COVEN_DIR: &str = ".coven";
+/// The scaffolding files coven manages. Deterministic for a given coven
+/// version, so `init --update` can diff it against what's on disk.
+///
+/// `brief.md` and `.gitignore` are user-owned and handled separately.
+fn scaffold_files() -> Vec<ScaffoldFile> {
+    vec![
+        ScaffoldFile {
+            path: format!("{AGENTS_DIR}/dispatch.md"),
+            content: DISPATCH_PROMPT,
+        },
+        ScaffoldFile {
+            path: format!("{AGENTS_DIR}/main.md"),
+            content: MAIN_PROMPT,
+        },
+        ScaffoldFile {
+            path: format!("{AGENTS_DIR}/review.md"),
+            content: REVIEW_PROMPT,
+        },
+        ScaffoldFile {
+            path: format!("{COVEN_DIR}/system.md"),
+            content: SYSTEM_DOC,
+        },
+        ScaffoldFile {
+            path: format!("{COVEN_DIR}/config.toml"),
+            content: CONFIG_DOC,
+        },
+        ScaffoldFile {
+            path: format!("{COVEN_DIR}/land.sh"),
+            content: LAND_SCRIPT,
+        },
+    ]
+}
 
 /// Result of creating init files, used for VCR recording.
 #[derive(Serialize, Deserialize)]
@@ -44,6 +69,85 @@ struct CreateFilesResult {
     skipped: Vec<String>,
 }
 
+/// What `init --update` did with a single file.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum UpdateStatus {
+    /// File was missing and has been created.
+    Created,
+    /// File was unmodified since coven wrote it and has been replaced.
+    Updated,
+    /// File already matches the current version.
+    UpToDate,
+    /// File was edited locally; the new version was written to `<path>.new`.
+    NeedsMerge,
+    /// File differs and the manifest has no record of what coven wrote (it
+    /// predates the manifest), so it may or may not have local edits. Left
+    /// alone; the new version was written to `<path>.new`.
+    Unknown,
+}
+
+/// Per-file outcome of `init --update`, used for VCR recording.
+#[derive(Serialize, Deserialize)]
+struct UpdateEntry {
+    path: String,
+    status: UpdateStatus,
+}
+
+/// Result of updating init files, used for VCR recording.
+#[derive(Serialize, Deserialize)]
+struct UpdateFilesResult {
+    entries: Vec<UpdateEntry>,
+}
+
+/// On-disk format of [`MANIFEST_PATH`].
+#[derive(Serialize, Deserialize, Default)]
+struct Manifest {
+    /// Scaffold path → hex content hash of the version coven last wrote.
+    #[serde(default)]
+    files: BTreeMap<String, String>,
+}
+
+/// Stable [FNV-1a](crate::fnv) hash of file content, hex-encoded, so the
+/// manifest outlives coven and Rust upgrades.
+fn content_hash(content: &str) -> String {
+    format!("{:016x}", crate::fnv::hash(content.as_bytes()))
+}
+
+/// Decide what to do with a scaffold file given its current content on disk
+/// (if any), the hash recorded when coven last wrote it (if any), and the
+/// newly generated content.
+fn plan_update(on_disk: Option<&str>, recorded_hash: Option<&str>, new: &str) -> UpdateStatus {
+    match on_disk {
+        None => UpdateStatus::Created,
+        Some(current) if current == new => UpdateStatus::UpToDate,
+        Some(_) if recorded_hash.is_none() => UpdateStatus::Unknown,
+        Some(current) if recorded_hash == Some(content_hash(current).as_str()) => {
+            UpdateStatus::Updated
+        }
+        Some(_) => UpdateStatus::NeedsMerge,
+    }
+}
+
+fn read_manifest(project_root: &Path) -> Result<Manifest> {
+    let path = project_root.join(MANIFEST_PATH);
+    if !path.exists() {
+        return Ok(Manifest::default());
+    }
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    toml::from_str(&content).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+fn write_manifest(project_root: &Path, manifest: &Manifest) -> Result<()> {
+    let path = project_root.join(MANIFEST_PATH);
+    let body = toml::to_string(manifest).context("failed to serialize init manifest")?;
+    let content = format!(
+        "# Written by `coven init` so `coven init --update` can detect local edits.\n\n{body}"
+    );
+    fs::write(&path, content).with_context(|| format!("failed to write {}", path.display()))
+}
+
 const BRIEF_TEMPLATE: &str = "\
 # Brief
 
@@ -61,46 +165,97 @@ fn create_files(project_root: &Path) -> Result<CreateFilesResult> {
 
     let mut created = Vec::new();
     let mut skipped = Vec::new();
+    let mut manifest = read_manifest(project_root)?;
 
-    for template in AGENT_TEMPLATES {
-        let path = agents_dir.join(template.path);
+    for file in scaffold_files() {
+        let path = project_root.join(&file.path);
         if path.exists() {
-            skipped.push(format!("{AGENTS_DIR}/{}", template.path));
+            skipped.push(file.path);
         } else {
-            fs::write(&path, template.content)
+            fs::write(&path, file.content)
                 .with_context(|| format!("failed to write {}", path.display()))?;
-            created.push(format!("{AGENTS_DIR}/{}", template.path));
+            manifest
+                .files
+                .insert(file.path.clone(), content_hash(file.content));
+            created.push(file.path);
         }
     }
+    write_manifest(project_root, &manifest)?;
 
-    let system_path = project_root.join(COVEN_DIR).join("system.md");
-    if system_path.exists() {
-        skipped.push(format!("{COVEN_DIR}/system.md"));
-    } else {
-        fs::write(&system_path, SYSTEM_DOC)
-            .with_context(|| format!("failed to write {}", system_path.display()))?;
-        created.push(format!("{COVEN_DIR}/system.md"));
-    }
+    create_user_files(project_root, &mut created, &mut skipped)?;
 
-    let config_path = project_root.join(COVEN_DIR).join("config.toml");
-    if config_path.exists() {
-        skipped.push(format!("{COVEN_DIR}/config.toml"));
-    } else {
-        fs::write(&config_path, CONFIG_DOC)
-            .with_context(|| format!("failed to write {}", config_path.display()))?;
-        created.push(format!("{COVEN_DIR}/config.toml"));
-    }
+    Ok(CreateFilesResult { created, skipped })
+}
 
-    let land_script_path = project_root.join(COVEN_DIR).join("land.sh");
-    if land_script_path.exists() {
-        skipped.push(format!("{COVEN_DIR}/land.sh"));
-    } else {
-        fs::write(&land_script_path, LAND_SCRIPT)
-            .with_context(|| format!("failed to write {}", land_script_path.display()))?;
-        created.push(format!("{COVEN_DIR}/land.sh"));
+/// Bring scaffold files up to date without clobbering local edits.
+///
+/// Files coven wrote and nobody touched since are replaced; locally edited
+/// files are left alone and the new version is written next to them as
+/// `<path>.new` for manual merging.
+fn update_files(project_root: &Path) -> Result<UpdateFilesResult> {
+    let agents_dir = project_root.join(AGENTS_DIR);
+    fs::create_dir_all(&agents_dir)
+        .with_context(|| format!("failed to create {}", agents_dir.display()))?;
+
+    let mut manifest = read_manifest(project_root)?;
+    let mut entries = Vec::new();
+
+    for file in scaffold_files() {
+        let path = project_root.join(&file.path);
+        let on_disk = if path.exists() {
+            Some(
+                fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read {}", path.display()))?,
+            )
+        } else {
+            None
+        };
+        let status = plan_update(
+            on_disk.as_deref(),
+            manifest.files.get(&file.path).map(String::as_str),
+            file.content,
+        );
+        match status {
+            UpdateStatus::Created | UpdateStatus::Updated => {
+                fs::write(&path, file.content)
+                    .with_context(|| format!("failed to write {}", path.display()))?;
+            }
+            UpdateStatus::NeedsMerge | UpdateStatus::Unknown => {
+                let new_path = project_root.join(format!("{}.new", file.path));
+                fs::write(&new_path, file.content)
+                    .with_context(|| format!("failed to write {}", new_path.display()))?;
+            }
+            UpdateStatus::UpToDate => {}
+        }
+        if !matches!(status, UpdateStatus::NeedsMerge | UpdateStatus::Unknown) {
+            manifest
+                .files
+                .insert(file.path.clone(), content_hash(file.content));
+        }
+        entries.push(UpdateEntry {
+            path: file.path,
+            status,
+        });
     }
+    write_manifest(project_root, &manifest)?;
 
-    // Create brief.md at project root
+    let mut created = Vec::new();
+    create_user_files(project_root, &mut created, &mut Vec::new())?;
+    entries.extend(created.into_iter().map(|path| UpdateEntry {
+        path,
+        status: UpdateStatus::Created,
+    }));
+
+    Ok(UpdateFilesResult { entries })
+}
+
+/// Create `brief.md` if missing and make sure `.gitignore` covers `scratch.md`.
+/// These are user-owned: never overwritten once they exist.
+fn create_user_files(
+    project_root: &Path,
+    created: &mut Vec<String>,
+    skipped: &mut Vec<String>,
+) -> Result<()> {
     let brief_path = project_root.join("brief.md");
     if brief_path.exists() {
         skipped.push("brief.md".to_string());
@@ -124,19 +279,50 @@ fn create_files(project_root: &Path) -> Result<CreateFilesResult> {
         fs::write(&gitignore_path, new_content).context("failed to update .gitignore")?;
         created.push(".gitignore (added scratch.md)".to_string());
     }
+    Ok(())
+}
 
-    Ok(CreateFilesResult { created, skipped })
+/// Render the `init --update` summary table.
+fn format_update_summary(entries: &[UpdateEntry]) -> String {
+    let width = entries.iter().map(|e| e.path.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for entry in entries {
+        let status = match entry.status {
+            UpdateStatus::Created => "created".to_string(),
+            UpdateStatus::Updated => "updated".to_string(),
+            UpdateStatus::UpToDate => "up to date".to_string(),
+            UpdateStatus::NeedsMerge => {
+                format!("modified locally — new version at {}.new", entry.path)
+            }
+            UpdateStatus::Unknown => {
+                format!(
+                    "no record of coven's version — new version at {}.new",
+                    entry.path
+                )
+            }
+        };
+        let _ = writeln!(out, "  {:<width$}  {status}", entry.path);
+    }
+    out
 }
 
 /// Initialize the project with agent prompts and orchestration files.
+///
+/// With `update`, existing scaffold files are brought up to date instead of
+/// skipped (see [`update_files`]).
 pub async fn init(
     vcr: &VcrContext,
     writer: &mut impl Write,
     stdin: &mut impl BufRead,
     working_dir: Option<PathBuf>,
+    update: bool,
 ) -> Result<()> {
     let project_root = super::resolve_working_dir(vcr, working_dir.as_deref()).await?;
 
+    if update {
+        return init_update(vcr, writer, project_root).await;
+    }
+
     let result: CreateFilesResult = vcr
         .call(
             "init_create_files",
@@ -158,6 +344,13 @@ pub async fn init(
         for path in &result.skipped {
             writeln!(writer, "  {path}")?;
         }
+        // brief.md belongs to the user; only coven's own files can be upgraded.
+        if result.skipped.iter().any(|p| p != "brief.md") {
+            writeln!(
+                writer,
+                "Run `coven init --update` to upgrade them to this version of coven."
+            )?;
+        }
     }
 
     writeln!(writer)?;
@@ -170,3 +363,160 @@ pub async fn init(
 
     Ok(())
 }
+
+async fn init_update(
+    vcr: &VcrContext,
+    writer: &mut impl Write,
+    project_root: String,
+) -> Result<()> {
+    let result: UpdateFilesResult = vcr
+        .call("init_update_files", project_root, async |root: &String| {
+            update_files(Path::new(root))
+        })
+        .await?;
+
+    write!(writer, "{}", format_update_summary(&result.entries))?;
+
+    let count = |status| result.entries.iter().filter(|e| e.status == status).count();
+    let needs_merge = count(UpdateStatus::NeedsMerge);
+    let unknown = count(UpdateStatus::Unknown);
+    writeln!(writer)?;
+    if needs_merge > 0 {
+        writeln!(
+            writer,
+            "{needs_merge} file(s) have local edits. Merge the .new versions by hand, then delete them."
+        )?;
+    }
+    if unknown > 0 {
+        writeln!(
+            writer,
+            "{unknown} file(s) predate coven's init manifest and differ from this version. Compare them with the .new versions, keep what you want, then delete the .new files."
+        )?;
+    }
+    if needs_merge == 0 && unknown == 0 {
+        writeln!(writer, "All files up to date.")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_hash_is_stable() {
+        // Pinned: the manifest must stay readable across coven versions.
+        assert_eq!(content_hash(""), "cbf29ce484222325");
+        assert_eq!(content_hash("a"), "af63dc4c8601ec8c");
+    }
+
+    #[test]
+    fn plan_update_cases() {
+        let old = "old template";
+        let old_hash = content_hash(old);
+        assert_eq!(plan_update(None, None, "new"), UpdateStatus::Created);
+        assert_eq!(
+            plan_update(Some("new"), None, "new"),
+            UpdateStatus::UpToDate
+        );
+        assert_eq!(
+            plan_update(Some(old), Some(&old_hash), "new"),
+            UpdateStatus::Updated
+        );
+        assert_eq!(
+            plan_update(Some("edited"), Some(&old_hash), "new"),
+            UpdateStatus::NeedsMerge
+        );
+        // No record of what coven wrote — can't tell whether it was edited.
+        assert_eq!(plan_update(Some(old), None, "new"), UpdateStatus::Unknown);
+    }
+
+    #[test]
+    fn update_preserves_locally_modified_agent() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        create_files(root).unwrap();
+
+        let main_path = root.join(AGENTS_DIR).join("main.md");
+        fs::write(&main_path, "my custom main agent").unwrap();
+        // Simulate an older coven version having written review.md.
+        let review_path = root.join(AGENTS_DIR).join("review.md");
+        fs::write(&review_path, "old review").unwrap();
+        let mut manifest = read_manifest(root).unwrap();
+        manifest.files.insert(
+            format!("{AGENTS_DIR}/review.md"),
+            content_hash("old review"),
+        );
+        write_manifest(root, &manifest).unwrap();
+        fs::remove_file(root.join(COVEN_DIR).join("land.sh")).unwrap();
+
+        let result = update_files(root).unwrap();
+        let status_of = |p: &str| {
+            result
+                .entries
+                .iter()
+                .find(|e| e.path == p)
+                .map(|e| e.status)
+                .unwrap()
+        };
+
+        assert_eq!(
+            status_of(&format!("{AGENTS_DIR}/main.md")),
+            UpdateStatus::NeedsMerge
+        );
+        assert_eq!(
+            fs::read_to_string(&main_path).unwrap(),
+            "my custom main agent"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join(AGENTS_DIR).join("main.md.new")).unwrap(),
+            MAIN_PROMPT
+        );
+
+        assert_eq!(
+            status_of(&format!("{AGENTS_DIR}/review.md")),
+            UpdateStatus::Updated
+        );
+        assert_eq!(fs::read_to_string(&review_path).unwrap(), REVIEW_PROMPT);
+
+        assert_eq!(
+            status_of(&format!("{COVEN_DIR}/land.sh")),
+            UpdateStatus::Created
+        );
+        assert_eq!(
+            status_of(&format!("{AGENTS_DIR}/dispatch.md")),
+            UpdateStatus::UpToDate
+        );
+
+        // Running again is idempotent: the edit is still flagged, nothing else changes.
+        let again = update_files(root).unwrap();
+        assert!(
+            again
+                .entries
+                .iter()
+                .all(|e| e.status == UpdateStatus::UpToDate
+                    || (e.status == UpdateStatus::NeedsMerge && e.path.ends_with("main.md")))
+        );
+    }
+
+    #[test]
+    fn update_summary_lists_every_file() {
+        let summary = format_update_summary(&[
+            UpdateEntry {
+                path: ".coven/agents/main.md".into(),
+                status: UpdateStatus::NeedsMerge,
+            },
+            UpdateEntry {
+                path: ".coven/land.sh".into(),
+                status: UpdateStatus::Updated,
+            },
+        ]);
+        assert_eq!(
+            summary,
+            "  .coven/agents/main.md  modified locally — new version at .coven/agents/main.md.new\n  \
+             .coven/land.sh         updated\n"
+        );
+    }
+}
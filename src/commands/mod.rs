@@ -1,20 +1,98 @@
+pub mod fork_cmd;
 pub mod gc;
 pub mod init;
 pub mod ralph;
 pub mod run;
 pub mod status;
+pub mod tutorial;
 pub mod worker;
 
-use std::io::Write;
-use std::path::Path;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use crossterm::terminal;
 
+use crate::claude_version;
+use crate::config::{self, FlagDefaults, Section};
 use crate::display::input::InputHandler;
+use crate::display::log_file::LogTee;
 use crate::display::renderer::{HintContext, Renderer};
+use crate::display::{MIN_WIDTH, TerminalSize, query_terminal_size};
+use crate::fork::ForkTools;
+use crate::session::runner::PermissionMode;
 use crate::vcr::{Io, VcrContext};
 
+/// Tag-based features gated by CLI flags.
+pub struct TagFlags {
+    pub fork: bool,
+    /// Tool restrictions for forked children (`--fork-tools`).
+    pub fork_tools: Option<ForkTools>,
+    /// Most forked children running at once (`--fork-concurrency`).
+    pub fork_concurrency: usize,
+    pub reload: bool,
+}
+
+/// How a command renders its sessions.
+#[derive(Default)]
+///
+/// A flag left `None` is filled in from `.coven/config.toml`, and is off if
+/// that doesn't set it either.
+pub struct DisplayOptions {
+    pub show_thinking: Option<bool>,
+    /// Render token usage (cache vs fresh) under Done lines and totals.
+    pub show_usage: Option<bool>,
+    /// Preview this many lines of each tool result (`--show-results`).
+    pub show_results: Option<usize>,
+    /// Style markdown in assistant text (`--render-markdown`).
+    pub render_markdown: Option<bool>,
+    /// Drop informational stderr lines from claude (`--quiet-stderr`).
+    pub quiet_stderr: Option<bool>,
+    /// Stop echoing a text block after this many lines (`--max-stream-lines`).
+    pub max_stream_lines: Option<usize>,
+    /// Append a plain-text copy of the display to this file (`--log-file`).
+    pub log_file: Option<PathBuf>,
+    /// Override terminal width for display truncation (used in tests).
+    pub term_width: Option<usize>,
+}
+
+impl DisplayOptions {
+    /// Set up the renderer and input handler for these options (see
+    /// [`setup_display`]). `raw_commands` enables the `:raw` input command.
+    pub(crate) fn setup<W: Write>(
+        &self,
+        writer: W,
+        vcr: &VcrContext,
+        raw_commands: bool,
+    ) -> Result<(Renderer<LogTee<W>>, InputHandler)> {
+        let (mut renderer, input) = setup_display(
+            writer,
+            terminal_size(vcr, self.term_width)?,
+            self.show_thinking.unwrap_or(false),
+            self.show_usage.unwrap_or(false),
+            self.show_results,
+            raw_commands,
+            self.log_file.as_deref(),
+        )?;
+        renderer.set_render_markdown(self.render_markdown.unwrap_or(false));
+        renderer.set_quiet_stderr(self.quiet_stderr.unwrap_or(false));
+        renderer.set_max_stream_lines(self.max_stream_lines);
+        Ok((renderer, input))
+    }
+}
+
+/// Warn about an unsupported claude version, then show which permission mode
+/// sessions will use.
+pub(crate) fn render_startup<W: Write>(
+    renderer: &mut Renderer<W>,
+    vcr: &VcrContext,
+    permission_mode: &PermissionMode,
+) -> Result<()> {
+    claude_version::check(renderer, vcr)?;
+    renderer.render_permission_mode(permission_mode);
+    Ok(())
+}
+
 /// Render the initial keybinding hints unless we're headless (no tty stdin).
 pub(crate) fn render_initial_hints<W: Write>(renderer: &mut Renderer<W>, io: &Io, has_wait: bool) {
     if !io.is_headless() {
@@ -22,10 +100,10 @@ pub(crate) fn render_initial_hints<W: Write>(renderer: &mut Renderer<W>, io: &Io
     }
 }
 
-/// Guard that disables terminal raw mode on drop.
+/// Guard that disables terminal raw mode (and bracketed paste) on drop.
 ///
-/// When `active` is true, raw mode was enabled on creation and will be
-/// disabled on drop. When false (no real tty, e.g. headless production or
+/// When `active` is true, raw mode and bracketed paste were enabled on
+/// creation and will be disabled on drop. When false (no real tty, e.g. headless production or
 /// any test), the guard is inert.
 pub(crate) struct RawModeGuard {
     active: bool,
@@ -36,6 +114,7 @@ impl RawModeGuard {
     pub fn acquire(io: &Io) -> Result<Self> {
         if io.has_tty_stdin() {
             terminal::enable_raw_mode()?;
+            crate::display::set_bracketed_paste(true);
             Ok(Self { active: true })
         } else {
             Ok(Self { active: false })
@@ -46,27 +125,102 @@ impl RawModeGuard {
 impl Drop for RawModeGuard {
     fn drop(&mut self) {
         if self.active {
+            crate::display::set_bracketed_paste(false);
             terminal::disable_raw_mode().ok();
         }
     }
 }
 
 /// Set up the display renderer and input handler with common configuration.
+/// Output is also appended to `log_file` as plain text (`--log-file`).
 ///
 /// Caller is responsible for acquiring raw mode (via [`RawModeGuard`]) before
 /// calling `renderer.render_hints()`.
 pub(crate) fn setup_display<W: Write>(
     writer: W,
-    term_width: Option<usize>,
+    size: TerminalSize,
     show_thinking: bool,
-) -> (Renderer<W>, InputHandler) {
-    let mut renderer = Renderer::with_writer(writer);
-    if let Some(w) = term_width {
-        renderer.set_width(w);
-    }
+    show_usage: bool,
+    show_results: Option<usize>,
+    raw_commands: bool,
+    log_file: Option<&Path>,
+) -> Result<(Renderer<LogTee<W>>, InputHandler)> {
+    let mut renderer = Renderer::with_writer(LogTee::open(writer, log_file)?);
+    renderer.set_size(size);
     renderer.set_show_thinking(show_thinking);
-    let input = InputHandler::new(2);
-    (renderer, input)
+    renderer.set_show_usage(show_usage);
+    renderer.set_result_preview_lines(show_results.unwrap_or(0));
+    let mut input = InputHandler::new(2);
+    input.set_raw_commands(raw_commands);
+    input.set_width(renderer.width());
+    renderer.render_narrow_notice();
+    Ok((renderer, input))
+}
+
+/// The terminal size to render at, recorded through the VCR. Tests pin the
+/// width with `term_width`.
+///
+/// Refuses to record below [`MIN_WIDTH`]: rendering would fall back to the
+/// default width, so the recording wouldn't show what was on screen.
+pub(crate) fn terminal_size(vcr: &VcrContext, term_width: Option<usize>) -> Result<TerminalSize> {
+    let size = vcr.terminal_size(query_terminal_size)?;
+    let size = TerminalSize {
+        width: term_width.unwrap_or(size.width),
+        ..size
+    };
+    if vcr.is_recording() && size.is_degraded() {
+        bail!(
+            "refusing to record at {} columns; widen the terminal to at least {MIN_WIDTH}",
+            size.width
+        );
+    }
+    Ok(size)
+}
+
+/// Read a prompt from `--prompt-file`: the file at `path`, or stdin for `-`.
+/// Trailing newlines are trimmed; an empty prompt is an error.
+pub fn read_prompt_file(path: &Path) -> Result<String> {
+    read_prompt(path, &mut std::io::stdin().lock())
+}
+
+fn read_prompt(path: &Path, stdin: &mut impl Read) -> Result<String> {
+    let mut prompt = String::new();
+    if path == Path::new("-") {
+        stdin
+            .read_to_string(&mut prompt)
+            .context("failed to read prompt from stdin")?;
+    } else {
+        prompt = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read prompt file {}", path.display()))?;
+    }
+    let prompt = prompt.trim_end_matches(['\n', '\r']);
+    if prompt.trim().is_empty() {
+        bail!(
+            "prompt file {} is empty",
+            if path == Path::new("-") {
+                "(stdin)".to_string()
+            } else {
+                path.display().to_string()
+            }
+        );
+    }
+    Ok(prompt.to_string())
+}
+
+/// Read tasks from `--fork-tasks-file`: one per line, blank lines skipped.
+pub fn read_task_file(path: &Path) -> Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read task file {}", path.display()))?;
+    let tasks: Vec<String> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect();
+    if tasks.is_empty() {
+        bail!("task file {} has no tasks", path.display());
+    }
+    Ok(tasks)
 }
 
 /// Resolve the working directory through VCR. Uses the configured directory
@@ -84,3 +238,112 @@ pub(crate) async fn resolve_working_dir(
     })
     .await
 }
+
+/// Fill in the flags a command wasn't given from its `section` of the
+/// `.coven/config.toml` under `working_dir`, read through `vcr`. Explicit
+/// flags always win. Returns the permission mode its sessions will use.
+pub(crate) fn apply_project_defaults(
+    vcr: &VcrContext,
+    working_dir: Option<&Path>,
+    section: Section,
+    display: &mut DisplayOptions,
+    max_cost: &mut Option<f64>,
+    extra_args: &mut Vec<String>,
+    no_default_permission_mode: bool,
+) -> Result<PermissionMode> {
+    let given = FlagDefaults {
+        show_thinking: display.show_thinking,
+        show_usage: display.show_usage,
+        show_results: display.show_results,
+        render_markdown: display.render_markdown,
+        quiet_stderr: display.quiet_stderr,
+        max_stream_lines: display.max_stream_lines,
+        max_cost: *max_cost,
+        claude_args: std::mem::take(extra_args),
+    };
+    let flags = given.or(&config::load_defaults(vcr, working_dir, section)?);
+    display.show_thinking = flags.show_thinking;
+    display.show_usage = flags.show_usage;
+    display.show_results = flags.show_results;
+    display.render_markdown = flags.render_markdown;
+    display.quiet_stderr = flags.quiet_stderr;
+    display.max_stream_lines = flags.max_stream_lines;
+    *max_cost = flags.max_cost;
+    *extra_args = flags.claude_args;
+    Ok(PermissionMode::resolve(
+        extra_args,
+        no_default_permission_mode,
+    ))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recording_refuses_a_degraded_width() {
+        let vcr = VcrContext::record();
+        let err = terminal_size(&vcr, Some(5)).unwrap_err();
+        assert!(err.to_string().contains("at 5 columns"), "{err}");
+        assert_eq!(
+            terminal_size(&vcr, Some(MIN_WIDTH)).unwrap().width,
+            MIN_WIDTH
+        );
+        assert_eq!(
+            terminal_size(&VcrContext::live(), Some(5)).unwrap().width,
+            5
+        );
+    }
+
+    #[test]
+    fn prompt_file_trims_trailing_newlines_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prompt.md");
+        std::fs::write(&path, "  Fix the bug.\n\nThen run the tests.\r\n\n").unwrap();
+        assert_eq!(
+            read_prompt(&path, &mut std::io::empty()).unwrap(),
+            "  Fix the bug.\n\nThen run the tests."
+        );
+    }
+
+    #[test]
+    fn prompt_file_dash_reads_stdin() {
+        let mut stdin = std::io::Cursor::new("from a pipe\n");
+        assert_eq!(
+            read_prompt(Path::new("-"), &mut stdin).unwrap(),
+            "from a pipe"
+        );
+    }
+
+    #[test]
+    fn empty_prompt_file_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty.md");
+        std::fs::write(&path, " \n\n").unwrap();
+        let err = read_prompt(&path, &mut std::io::empty()).unwrap_err();
+        assert!(err.to_string().contains("is empty"), "{err}");
+        let err = read_prompt(Path::new("-"), &mut std::io::empty()).unwrap_err();
+        assert_eq!(err.to_string(), "prompt file (stdin) is empty");
+    }
+
+    #[test]
+    fn task_file_has_one_task_per_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.txt");
+        std::fs::write(&path, "Review auth.rs\n\n  Review db.rs  \r\n").unwrap();
+        assert_eq!(
+            read_task_file(&path).unwrap(),
+            ["Review auth.rs", "Review db.rs"]
+        );
+        std::fs::write(&path, "\n \n").unwrap();
+        assert!(read_task_file(&path).is_err());
+    }
+
+    #[test]
+    fn missing_prompt_file_names_the_path() {
+        let err =
+            read_prompt(Path::new("/nonexistent/prompt.md"), &mut std::io::empty()).unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/prompt.md"), "{err}");
+    }
+}
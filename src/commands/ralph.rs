@@ -1,28 +1,31 @@
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
 
+use crate::config::Section;
 use crate::display::input::InputHandler;
 use crate::display::renderer::{Renderer, StoredMessage};
 use crate::fork::{self, ForkConfig};
+use crate::preflight::{self, Backoff};
 use crate::protocol::parse::extract_tag_inner;
 use crate::reload;
+use crate::session::outbox;
 use crate::session::runner::{SessionConfig, SessionRunner};
-use crate::session::state::SessionState;
+use crate::session::state::{SessionState, TokenUsage};
+use crate::timing::{Span, format_elapsed};
 use crate::vcr::{Io, VcrContext};
+use crate::worktree;
 
-use crate::session::event_loop::{self, SessionFeatures, SessionOutcome};
+use crate::session::event_loop::{self, CostBudget, SessionFeatures, SessionOutcome};
 use crate::transition::WAIT_FOR_USER_PROMPT;
 
-use super::{RawModeGuard, render_initial_hints, setup_display};
-
-/// Tag-based features gated by CLI flags.
-pub struct TagFlags {
-    pub fork: bool,
-    pub reload: bool,
-}
+use super::{
+    DisplayOptions, RawModeGuard, TagFlags, apply_project_defaults, render_initial_hints,
+    render_startup,
+};
 
 /// Where each iteration's prompt comes from.
 pub enum PromptSource {
@@ -49,29 +52,49 @@ impl PromptSource {
 
 pub struct RalphConfig {
     pub prompt_source: PromptSource,
+    /// Prompt for the first iteration only; `prompt_source` takes over from
+    /// the second (`--first-prompt`).
+    pub first_prompt: Option<String>,
     pub iterations: u32,
-    pub break_tag: String,
-    pub no_break: bool,
+    /// Tag that ends the loop (`--break-tag`); `None` with `--no-break`.
+    pub break_tag: Option<String>,
     pub no_wait: bool,
-    pub show_thinking: bool,
+    pub display: DisplayOptions,
+    /// `--debug-protocol`: where to log claude output that fails to parse.
+    /// Also enables the `:raw` input command.
+    pub protocol_log: Option<PathBuf>,
+    /// Warn when claude is silent this long after a steering message (`None` disables).
+    pub steering_timeout: Option<Duration>,
+    /// Stop the loop once it has cost more than this many USD (`--max-cost`).
+    pub max_cost: Option<f64>,
+    /// Kill a session that hasn't produced a result this long after it was
+    /// spawned and move on to the next iteration (`--iteration-timeout`).
+    pub iteration_timeout: Option<Duration>,
+    /// Check connectivity before each iteration (`--preflight-connectivity`).
+    pub preflight: bool,
+    /// Leave the permission mode to claude instead of defaulting to
+    /// `acceptEdits` (`--no-default-permission-mode`).
+    pub no_default_permission_mode: bool,
+    /// Commit whatever each iteration leaves uncommitted (`--commit-each`).
+    pub commit_each: bool,
     pub tag_flags: TagFlags,
     pub extra_args: Vec<String>,
     pub working_dir: Option<PathBuf>,
-    /// Override terminal width for display truncation (used in tests).
-    pub term_width: Option<usize>,
 }
 
 impl RalphConfig {
     fn system_prompt(&self) -> String {
-        let base = if self.no_break {
-            "After you respond, a new session will start with the same prompt and the \
-             filesystem as you left it. This repeats automatically."
-                .to_string()
-        } else {
-            ralph_system_prompt(&self.break_tag, self.no_wait)
+        let base = match &self.break_tag {
+            Some(break_tag) => ralph_system_prompt(break_tag, self.no_wait),
+            None => "After you respond, a new session will start with the same prompt and the \
+                     filesystem as you left it. This repeats automatically."
+                .to_string(),
         };
         let mut prompt = if self.tag_flags.fork {
-            format!("{base}\n\n{}", fork::fork_system_prompt())
+            format!(
+                "{base}\n\n{}",
+                fork::fork_system_prompt(self.tag_flags.fork_tools.as_ref())
+            )
         } else {
             base
         };
@@ -86,6 +109,7 @@ impl RalphConfig {
             prompt: Some(prompt),
             extra_args: self.extra_args.clone(),
             append_system_prompt: Some(system_prompt.to_string()),
+            no_default_permission_mode: self.no_default_permission_mode,
             working_dir: self.working_dir.clone(),
             ..Default::default()
         }
@@ -96,8 +120,8 @@ impl RalphConfig {
         if !self.no_wait {
             tags.push("wait-for-user".to_string());
         }
-        if !self.no_break {
-            tags.push(self.break_tag.clone());
+        if let Some(break_tag) = &self.break_tag {
+            tags.push(break_tag.clone());
         }
         if self.tag_flags.fork {
             tags.push("fork".to_string());
@@ -108,19 +132,43 @@ impl RalphConfig {
         tags
     }
 
-    /// Check for `<break>` tag (respects `--no-break`).
-    fn scan_break(&self, text: &str) -> Option<String> {
-        if self.no_break {
-            None
-        } else {
-            scan_break_tag(text, &self.break_tag)
-        }
+    /// Check for the break tag (respects `--no-break`). Returns the tag and
+    /// the reason given in it.
+    fn scan_break(&self, text: &str) -> Option<(&str, String)> {
+        let tag = self.break_tag.as_deref()?;
+        scan_break_tag(text, tag).map(|reason| (tag, reason))
+    }
+}
+
+/// Tags coven already gives a meaning to, which a break tag must not shadow.
+const RESERVED_TAGS: &[&str] = &["wait-for-user", "fork", "reload", "next"];
+
+/// Check that a break tag is a simple identifier that can't collide with
+/// coven's own tags.
+fn validate_break_tag(tag: &str) -> Result<()> {
+    let is_identifier = !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !is_identifier {
+        bail!(
+            "invalid --break-tag {tag:?}: use letters, digits, '-' or '_' only \
+             (e.g. `--break-tag done`, not `--break-tag \"<done>\"`)"
+        );
+    }
+    if RESERVED_TAGS.contains(&tag) {
+        bail!("invalid --break-tag {tag:?}: <{tag}> is already used by coven");
     }
+    Ok(())
 }
 
 /// Scan response text for `<tag>reason</tag>` and return the reason if found.
+/// The reason is optional: `<tag></tag>` and `<tag/>` give an empty one.
 fn scan_break_tag(text: &str, tag: &str) -> Option<String> {
-    extract_tag_inner(text, tag).map(|s| s.trim().to_string())
+    if let Some(reason) = extract_tag_inner(text, tag) {
+        return Some(reason.trim().to_string());
+    }
+    text.contains(&format!("<{tag}/>")).then(String::new)
 }
 
 /// Recorded output of running the prompt command once.
@@ -148,14 +196,18 @@ enum PromptResolution {
     Exhausted(String),
 }
 
-/// Resolve the prompt for the current iteration.
+/// Resolve the prompt for the current iteration: `--first-prompt` for the
+/// first, if set, and the prompt source otherwise.
 async fn resolve_prompt(
-    source: &PromptSource,
+    config: &RalphConfig,
     iteration: u32,
-    working_dir: Option<&Path>,
     vcr: &VcrContext,
 ) -> Result<PromptResolution> {
-    match source {
+    if let Some(first) = config.first_prompt.as_ref().filter(|_| iteration == 1) {
+        return Ok(PromptResolution::Prompt(first.clone()));
+    }
+    let working_dir = config.working_dir.as_deref();
+    match &config.prompt_source {
         PromptSource::Static(s) => Ok(PromptResolution::Prompt(s.clone())),
         PromptSource::Command(cmd) => {
             let args = PromptCommandArgs {
@@ -177,8 +229,9 @@ async fn run_prompt_command(
     iteration: u32,
     working_dir: Option<&Path>,
 ) -> Result<PromptCommandOutput> {
-    let mut cmd = tokio::process::Command::new("sh");
-    cmd.arg("-c")
+    let (shell, flag) = crate::platform::shell();
+    let mut cmd = tokio::process::Command::new(shell);
+    cmd.arg(flag)
         .arg(command)
         .env("COVEN_ITERATION", iteration.to_string());
     if let Some(dir) = working_dir {
@@ -216,6 +269,55 @@ fn interpret_prompt_command(output: &PromptCommandOutput) -> PromptResolution {
     }
 }
 
+/// Arguments recorded for a `--commit-each` commit. Like
+/// [`PromptCommandArgs`], `working_dir` is left out so recordings replay
+/// from any directory.
+#[derive(Debug, Serialize, Deserialize)]
+struct CommitArgs {
+    message: String,
+}
+
+/// `ralph iteration N: <first line of the result>`.
+fn commit_message(iteration: u32, result_text: &str) -> String {
+    match result_text.trim().lines().next() {
+        Some(line) => format!("ralph iteration {iteration}: {}", line.trim()),
+        None => format!("ralph iteration {iteration}"),
+    }
+}
+
+/// `--commit-each`: commit whatever the iteration left uncommitted, timing
+/// it since git hooks can take a while. A clean tree is left alone, and a
+/// failed commit is a warning, not an error.
+async fn commit_iteration<W: Write>(
+    config: &RalphConfig,
+    iteration: u32,
+    result_text: &str,
+    ctx: &mut Ctx<'_, W>,
+) -> Result<()> {
+    let dir = config.working_dir.as_deref().unwrap_or(Path::new("."));
+    let message = commit_message(iteration, result_text);
+    let args = CommitArgs {
+        message: message.clone(),
+    };
+    let commit_start = ctx.vcr.clock_ms()?;
+    let committed = ctx
+        .vcr
+        .call_typed_err("worktree::commit_all", args, async |a: &CommitArgs| {
+            worktree::commit_all(dir, &a.message)
+        })
+        .await?;
+    let span = Span::between("commit", commit_start, ctx.vcr.clock_ms()?);
+    match committed {
+        Ok(true) => {
+            ctx.renderer.render_commit(&message);
+            ctx.renderer.render_timing(&span.describe());
+        }
+        Ok(false) => {}
+        Err(e) => ctx.renderer.render_warning(&format!("--commit-each: {e}")),
+    }
+    Ok(())
+}
+
 /// Build the ralph system prompt for the given break tag.
 fn ralph_system_prompt(break_tag: &str, no_wait: bool) -> String {
     let mut prompt = format!(
@@ -242,11 +344,173 @@ struct Ctx<'a, W: Write> {
     vcr: &'a VcrContext,
 }
 
+/// Structured artifacts of a finished ralph loop, for library consumers and tests.
+#[derive(Debug, Default)]
+pub struct RalphReport {
+    /// Every message the renderer stored, across all iterations.
+    pub messages: Vec<StoredMessage>,
+    /// One entry per iteration that completed at least one turn.
+    pub iterations: Vec<IterationReport>,
+    pub total_cost: f64,
+    /// Iterations started, including any cut short.
+    pub iterations_run: u32,
+    pub stop_reason: StopReason,
+    /// What the model gave as its reason for breaking, if it gave one.
+    pub break_reason: Option<String>,
+    /// Wall-clock time the whole loop took.
+    pub elapsed: Duration,
+}
+
+impl RalphReport {
+    /// One-line summary for the end of the loop, e.g.
+    /// `ralph: 3 iterations in 14m, $0.12 total, stopped by break tag (all fixed)`.
+    pub fn summary(&self) -> String {
+        let s = if self.iterations_run == 1 { "" } else { "s" };
+        let mut summary = format!(
+            "ralph: {} iteration{s} in {}, ${:.2} total, stopped by {}",
+            self.iterations_run,
+            format_elapsed(self.elapsed),
+            self.total_cost,
+            self.stop_reason.describe()
+        );
+        if let Some(reason) = &self.break_reason {
+            summary = format!("{summary} ({reason})");
+        }
+        summary
+    }
+}
+
+/// Why a ralph loop ended, for scripts driving `coven ralph`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The model emitted the break tag.
+    BreakTag,
+    /// `--iterations` ran out.
+    IterationsExhausted,
+    /// `--prompt-command` had no more prompts.
+    PromptExhausted,
+    /// `--max-cost` was reached.
+    BudgetExceeded,
+    /// claude kept failing with temporary API errors.
+    ApiErrors,
+    /// The claude process exited without finishing its turn.
+    ProcessExited,
+    /// The user ended the loop (Ctrl+C, Ctrl+D).
+    #[default]
+    UserExit,
+}
+
+impl StopReason {
+    /// Process exit code for `coven ralph`: 0 when the loop ran to a natural
+    /// end, otherwise distinct per reason.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::BreakTag | Self::IterationsExhausted | Self::PromptExhausted => 0,
+            Self::ProcessExited => 2,
+            Self::BudgetExceeded => 3,
+            Self::ApiErrors => 4,
+            Self::UserExit => 130,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::BreakTag => "break tag",
+            Self::IterationsExhausted => "iteration limit",
+            Self::PromptExhausted => "prompt command",
+            Self::BudgetExceeded => "cost budget",
+            Self::ApiErrors => "API errors",
+            Self::ProcessExited => "claude exiting",
+            Self::UserExit => "user",
+        }
+    }
+}
+
+/// What a single ralph iteration produced.
+#[derive(Debug)]
+pub struct IterationReport {
+    /// 1-based iteration number.
+    pub iteration: u32,
+    /// Result text of the iteration's last completed turn.
+    pub result_text: String,
+    pub cost: f64,
+    pub session_id: Option<String>,
+    /// Wall-clock time from the iteration's start to its last completed turn.
+    pub elapsed: Duration,
+}
+
 /// Per-loop cost and iteration tracking.
+#[derive(Default)]
 struct IterState {
     iteration: u32,
+    /// Clock reading when the current iteration started.
+    iteration_start_ms: u64,
     iteration_cost: f64,
     total_cost: f64,
+    /// Token usage summed over every claude process this loop has run.
+    total_usage: TokenUsage,
+    reports: Vec<IterationReport>,
+    /// Backoff after failed preflights and error results.
+    backoff: Backoff,
+    /// Messages the last session ended without delivering, for the next
+    /// iteration's prompt.
+    undelivered: Vec<String>,
+    /// The reason given in the break tag that ended the loop.
+    break_reason: Option<String>,
+    /// This iteration's session already got its one wrap-up resume after
+    /// hitting `--max-turns`.
+    wrapped_up: bool,
+    /// Temporary API errors in a row, reset by a session that ends without one.
+    api_retries: u32,
+}
+
+impl IterState {
+    /// Add the cost and usage of a session that ended without a result.
+    fn record_unfinished(&mut self, state: &SessionState) {
+        self.iteration_cost += state.total_cost_usd;
+        self.total_cost += self.iteration_cost;
+        self.total_usage += state.usage;
+    }
+
+    /// Add the cost and usage of a session that finished its turn at
+    /// `now_ms`. Returns the time the iteration has taken so far.
+    fn record_completed(&mut self, state: &SessionState, result_text: &str, now_ms: u64) -> Span {
+        self.iteration_cost += state.total_cost_usd;
+        self.total_cost += self.iteration_cost;
+        self.total_usage += state.usage;
+        self.record_turn(result_text, state.session_id.as_ref());
+        self.backoff.record_session(state);
+        if !state.failed_retryably() {
+            self.api_retries = 0;
+        }
+        let span = Span::between("iteration", self.iteration_start_ms, now_ms);
+        if let Some(report) = self.reports.last_mut() {
+            report.elapsed = span.elapsed;
+        }
+        span
+    }
+
+    /// Record a completed turn, merging into the current iteration's report
+    /// when the turn was a resume (wait-for-user, interrupt, reload).
+    fn record_turn(&mut self, result_text: &str, session_id: Option<&String>) {
+        if let Some(report) = self.reports.last_mut()
+            && report.iteration == self.iteration
+        {
+            report.result_text = result_text.to_string();
+            report.cost += self.iteration_cost;
+            if session_id.is_some() {
+                report.session_id = session_id.cloned();
+            }
+            return;
+        }
+        self.reports.push(IterationReport {
+            iteration: self.iteration,
+            result_text: result_text.to_string(),
+            cost: self.iteration_cost,
+            session_id: session_id.cloned(),
+            elapsed: Duration::ZERO,
+        });
+    }
 }
 
 /// Run ralph loop mode.
@@ -255,25 +519,46 @@ pub async fn ralph<W: Write>(
     io: &mut Io,
     vcr: &VcrContext,
     writer: W,
-) -> Result<Vec<StoredMessage>> {
+) -> Result<RalphReport> {
     // Headless: force-disable wait-for-user. There's no human to respond,
     // so the model must not be told the feature exists, and any tag it
     // emits anyway must be ignored rather than "dismissed" silently.
     if io.is_headless() {
         config.no_wait = true;
     }
+    if let Some(break_tag) = &config.break_tag {
+        validate_break_tag(break_tag)?;
+    }
+    let permission_mode = apply_project_defaults(
+        vcr,
+        config.working_dir.as_deref(),
+        Section::Ralph,
+        &mut config.display,
+        &mut config.max_cost,
+        &mut config.extra_args,
+        config.no_default_permission_mode,
+    )?;
     let _raw = RawModeGuard::acquire(io)?;
 
-    let (mut renderer, mut input) = setup_display(writer, config.term_width, config.show_thinking);
+    let (mut renderer, mut input) =
+        config
+            .display
+            .setup(writer, vcr, config.protocol_log.is_some())?;
+    render_startup(&mut renderer, vcr, &permission_mode)?;
     render_initial_hints(&mut renderer, io, !config.no_wait);
     let system_prompt = config.system_prompt();
     if config.tag_flags.fork {
-        config.extra_args.extend(ForkConfig::disallowed_tool_args());
+        config
+            .extra_args
+            .extend(ForkConfig::disallowed_tool_args(&[], None));
     }
     let fork_config = ForkConfig::if_enabled(
         config.tag_flags.fork,
         &config.extra_args,
         &config.working_dir,
+        config.tag_flags.fork_tools.as_ref(),
+        config.tag_flags.fork_concurrency,
+        config.no_default_permission_mode,
     );
     let watched_tags = config.watched_tags();
 
@@ -283,40 +568,35 @@ pub async fn ralph<W: Write>(
         io,
         vcr,
     };
-    let mut iter = IterState {
-        iteration: 0,
-        iteration_cost: 0.0,
-        total_cost: 0.0,
-    };
+    let mut iter = IterState::default();
+    let loop_start = vcr.clock_ms()?;
 
-    loop {
+    let stop_reason = loop {
         iter.iteration += 1;
         if config.iterations > 0 && iter.iteration > config.iterations {
             ctx.renderer.write_raw(&format!(
                 "\r\nReached iteration limit ({})\r\n",
                 config.iterations
             ));
-            break;
+            iter.iteration -= 1;
+            break StopReason::IterationsExhausted;
         }
 
-        let prompt = match resolve_prompt(
-            &config.prompt_source,
-            iter.iteration,
-            config.working_dir.as_deref(),
-            ctx.vcr,
-        )
-        .await?
-        {
-            PromptResolution::Prompt(p) => p,
+        let prompt = match resolve_prompt(&config, iter.iteration, ctx.vcr).await? {
+            PromptResolution::Prompt(p) => {
+                outbox::carry_into_prompt(&p, &std::mem::take(&mut iter.undelivered))
+            }
             PromptResolution::Exhausted(reason) => {
                 ctx.renderer
                     .write_raw(&format!("\r\nPrompt source exhausted: {reason}\r\n"));
-                break;
+                iter.iteration -= 1;
+                break StopReason::PromptExhausted;
             }
         };
 
         ctx.renderer
             .write_raw(&format!("\r\n--- Iteration {} ---\r\n\r\n", iter.iteration));
+        iter.iteration_start_ms = ctx.vcr.clock_ms()?;
 
         let session_config = config.session_config(&system_prompt, prompt);
         let features = SessionFeatures {
@@ -324,16 +604,31 @@ pub async fn ralph<W: Write>(
             reload_enabled: config.tag_flags.reload,
             base_config: &session_config,
             watched_tags: watched_tags.clone(),
+            steering_timeout: config.steering_timeout,
+            resume_wedged: false,
+            budget: None,
+            max_loop_warnings: None,
+            protocol_log: config.protocol_log.as_deref(),
         };
 
         iter.iteration_cost = 0.0;
-        match run_iteration(&session_config, &features, &config, &mut iter, &mut ctx).await? {
+        iter.wrapped_up = false;
+        match run_iteration(&session_config, features, &config, &mut iter, &mut ctx).await? {
             IterationResult::Next => continue,
-            IterationResult::Exit => break,
+            IterationResult::Exit(reason) => break reason,
         }
-    }
+    };
 
-    Ok(renderer.into_messages())
+    let elapsed = Span::between("ralph", loop_start, vcr.clock_ms()?).elapsed;
+    Ok(RalphReport {
+        messages: renderer.into_messages(),
+        iterations: iter.reports,
+        total_cost: iter.total_cost,
+        iterations_run: iter.iteration,
+        stop_reason,
+        break_reason: iter.break_reason,
+        elapsed,
+    })
 }
 
 /// What to do after handling a session outcome.
@@ -341,27 +636,43 @@ enum LoopAction {
     /// Start the next iteration of the ralph loop.
     NextIteration,
     /// Continue the inner session loop (session was resumed).
-    Resume(Box<SessionRunner>, SessionState),
+    Resume(Box<SessionRunner>, Box<SessionState>),
     /// Exit the ralph loop entirely.
-    Exit,
+    Exit(StopReason),
 }
 
 enum IterationResult {
     Next,
-    Exit,
+    Exit(StopReason),
 }
 
 async fn run_iteration<W: Write>(
     session_config: &SessionConfig,
-    features: &SessionFeatures<'_>,
+    mut features: SessionFeatures<'_>,
     config: &RalphConfig,
     iter: &mut IterState,
     ctx: &mut Ctx<'_, W>,
 ) -> Result<IterationResult> {
+    if !preflight::wait_until_online(
+        config.preflight,
+        &mut iter.backoff,
+        ctx.renderer,
+        ctx.input,
+        ctx.io,
+        ctx.vcr,
+    )
+    .await?
+    {
+        return Ok(IterationResult::Exit(StopReason::UserExit));
+    }
     let mut runner = event_loop::spawn_session(session_config.clone(), ctx.io, ctx.vcr).await?;
     let mut state = SessionState::default();
 
     loop {
+        features.budget = CostBudget::new(config.max_cost, iter.total_cost + iter.iteration_cost);
+        if let Some(timeout) = config.iteration_timeout {
+            ctx.io.arm_session_timeout(timeout);
+        }
         let outcome = event_loop::run_session(
             &mut runner,
             &mut state,
@@ -369,9 +680,15 @@ async fn run_iteration<W: Write>(
             ctx.input,
             ctx.io,
             ctx.vcr,
-            features,
+            &features,
         )
-        .await?;
+        .await;
+        ctx.io.disarm_session_timeout();
+        let outcome = outcome?;
+        // After an interrupt they're back in the input line instead.
+        if !matches!(outcome, SessionOutcome::Interrupted) {
+            iter.undelivered.append(&mut state.undelivered);
+        }
         // Wait for session file persistence before killing, so the session
         // can be safely resumed. Skip for interrupts/exits.
         if matches!(
@@ -388,13 +705,75 @@ async fn run_iteration<W: Write>(
             LoopAction::NextIteration => return Ok(IterationResult::Next),
             LoopAction::Resume(new_runner, new_state) => {
                 runner = *new_runner;
-                state = new_state;
+                state = *new_state;
             }
-            LoopAction::Exit => return Ok(IterationResult::Exit),
+            LoopAction::Exit(reason) => return Ok(IterationResult::Exit(reason)),
         }
     }
 }
 
+/// Announce the end of the loop, with the model's reason on its own line
+/// when it gave one.
+fn render_break<W: Write>(ctx: &mut Ctx<'_, W>, iter: &mut IterState, tag: &str, reason: String) {
+    let s = if iter.iteration == 1 { "" } else { "s" };
+    ctx.renderer.write_raw(&format!(
+        "\r\nLoop complete ({} iteration{s}, <{tag}>)\r\n",
+        iter.iteration
+    ));
+    if !reason.is_empty() {
+        ctx.renderer.write_raw(&format!("Break: {reason}\r\n"));
+        iter.break_reason = Some(reason);
+    }
+}
+
+/// After a temporary API error, back off and start the next iteration, or
+/// stop once [`preflight::MAX_API_RETRIES`] in a row have failed.
+fn retry_after_api_error<W: Write>(iter: &mut IterState, renderer: &mut Renderer<W>) -> LoopAction {
+    iter.api_retries += 1;
+    if iter.api_retries > preflight::MAX_API_RETRIES {
+        renderer.render_warning(&format!(
+            "giving up after {} temporary API errors in a row",
+            preflight::MAX_API_RETRIES
+        ));
+        return LoopAction::Exit(StopReason::ApiErrors);
+    }
+    renderer.render_warning(&format!(
+        "retrying after a temporary API error ({}/{})",
+        iter.api_retries,
+        preflight::MAX_API_RETRIES
+    ));
+    LoopAction::NextIteration
+}
+
+/// Resume a session that hit `--max-turns` with a prompt to wrap up, once
+/// per iteration. None when there's nothing to resume.
+async fn wrap_up_after_max_turns<W: Write>(
+    state: &mut SessionState,
+    iter: &mut IterState,
+    session_config: &SessionConfig,
+    ctx: &mut Ctx<'_, W>,
+) -> Result<Option<LoopAction>> {
+    if !state.hit_max_turns() || std::mem::replace(&mut iter.wrapped_up, true) {
+        return Ok(None);
+    }
+    let Some(session_id) = state.session_id.take() else {
+        return Ok(None);
+    };
+    let (runner, new_state) = event_loop::spawn_wrap_up_session(
+        session_id,
+        session_config,
+        ctx.renderer,
+        ctx.io,
+        ctx.vcr,
+    )
+    .await?;
+    iter.iteration_cost = 0.0;
+    Ok(Some(LoopAction::Resume(
+        Box::new(runner),
+        Box::new(new_state),
+    )))
+}
+
 /// Process a session outcome: handle completion (wait-for-user, break tag),
 /// interrupts, and process exits.
 async fn handle_session_outcome<W: Write>(
@@ -407,17 +786,26 @@ async fn handle_session_outcome<W: Write>(
 ) -> Result<LoopAction> {
     match outcome {
         SessionOutcome::Completed { result_text, .. } => {
-            iter.iteration_cost += state.total_cost_usd;
-            iter.total_cost += iter.iteration_cost;
+            let span = iter.record_completed(state, &result_text, ctx.vcr.clock_ms()?);
             ctx.renderer
                 .write_raw(&format!("  Total cost: ${:.2}\r\n", iter.total_cost));
+            ctx.renderer.render_usage("Total tokens", &iter.total_usage);
+            ctx.renderer.render_timing(&span.describe());
+            if state.budget_exceeded {
+                return Ok(LoopAction::Exit(StopReason::BudgetExceeded));
+            }
+            if state.failed_retryably() {
+                return Ok(retry_after_api_error(iter, ctx.renderer));
+            }
+            if let Some(action) = wrap_up_after_max_turns(state, iter, session_config, ctx).await? {
+                return Ok(action);
+            }
 
-            // User pressed Ctrl+W — wait for input before continuing.
-            // Escape dismisses the wait and falls through to tag processing.
-            if state.wait_requested {
-                state.wait_requested = false;
-                ctx.renderer.write_raw("\x07");
-                ctx.renderer.write_raw("\r\n[waiting for user input]\r\n");
+            // User pressed Ctrl+W, or every tool was denied (untrusted directory)
+            // — wait for input before continuing. Escape (or Ctrl+W again)
+            // dismisses the wait and falls through to tag processing.
+            if std::mem::take(&mut state.wait_requested) | state.untrusted_dir.take().is_some() {
+                ctx.renderer.render_wait_requested();
                 match wait_input_and_resume(state, session_config, ctx).await? {
                     WaitResumeAction::Resume(runner, new_state) => {
                         iter.iteration_cost = 0.0;
@@ -425,8 +813,9 @@ async fn handle_session_outcome<W: Write>(
                     }
                     WaitResumeAction::Dismissed => {
                         // Fall through to tag processing below.
+                        ctx.renderer.render_wait_cancelled();
                     }
-                    WaitResumeAction::Exit => return Ok(LoopAction::Exit),
+                    WaitResumeAction::Exit => return Ok(LoopAction::Exit(StopReason::UserExit)),
                 }
             }
 
@@ -435,10 +824,7 @@ async fn handle_session_outcome<W: Write>(
                 && let Some(reason) =
                     crate::protocol::parse::extract_tag_inner(&result_text, "wait-for-user")
             {
-                let reason = reason.trim();
-                ctx.renderer.write_raw("\x07");
-                ctx.renderer
-                    .write_raw(&format!("\r\nWaiting for user: {reason}\r\n"));
+                ctx.renderer.render_wait_for_user(reason.trim());
                 match wait_input_and_resume(state, session_config, ctx).await? {
                     WaitResumeAction::Resume(runner, new_state) => {
                         iter.iteration_cost = 0.0;
@@ -447,17 +833,16 @@ async fn handle_session_outcome<W: Write>(
                     WaitResumeAction::Dismissed => {
                         // Fall through to break tag check below.
                     }
-                    WaitResumeAction::Exit => return Ok(LoopAction::Exit),
+                    WaitResumeAction::Exit => return Ok(LoopAction::Exit(StopReason::UserExit)),
                 }
             }
 
-            if let Some(reason) = config.scan_break(&result_text) {
-                let s = if iter.iteration == 1 { "" } else { "s" };
-                ctx.renderer.write_raw(&format!(
-                    "\r\nLoop complete ({} iteration{s}): {reason}\r\n",
-                    iter.iteration
-                ));
-                return Ok(LoopAction::Exit);
+            if config.commit_each {
+                commit_iteration(config, iter.iteration, &result_text, ctx).await?;
+            }
+            if let Some((tag, reason)) = config.scan_break(&result_text) {
+                render_break(ctx, iter, tag, reason);
+                return Ok(LoopAction::Exit(StopReason::BreakTag));
             }
 
             Ok(LoopAction::NextIteration)
@@ -465,17 +850,21 @@ async fn handle_session_outcome<W: Write>(
         SessionOutcome::Interrupted => {
             state.wait_requested = false;
             iter.iteration_cost += state.total_cost_usd;
+            iter.total_usage += state.usage;
             ctx.renderer.render_interrupted();
             match wait_input_and_resume(state, session_config, ctx).await? {
                 WaitResumeAction::Resume(runner, new_state) => {
                     Ok(LoopAction::Resume(runner, new_state))
                 }
-                WaitResumeAction::Dismissed | WaitResumeAction::Exit => Ok(LoopAction::Exit),
+                WaitResumeAction::Dismissed | WaitResumeAction::Exit => {
+                    Ok(LoopAction::Exit(StopReason::UserExit))
+                }
             }
         }
         SessionOutcome::Reload { .. } => {
+            iter.total_usage += state.usage;
             let Some(session_id) = state.session_id.take() else {
-                return Ok(LoopAction::Exit);
+                return Ok(LoopAction::Exit(StopReason::ProcessExited));
             };
             let (runner, new_state) = reload::spawn_reload_session(
                 session_id,
@@ -486,16 +875,32 @@ async fn handle_session_outcome<W: Write>(
             )
             .await?;
             iter.iteration_cost = 0.0;
-            Ok(LoopAction::Resume(Box::new(runner), new_state))
+            Ok(LoopAction::Resume(Box::new(runner), Box::new(new_state)))
+        }
+        // claude gave up on a temporary API error: back off and start the
+        // next iteration rather than ending the loop.
+        SessionOutcome::ProcessExited if state.failed_retryably() => {
+            iter.record_unfinished(state);
+            iter.backoff.record_failure();
+            Ok(retry_after_api_error(iter, ctx.renderer))
+        }
+        SessionOutcome::TimedOut => {
+            iter.record_unfinished(state);
+            let secs = config.iteration_timeout.map_or(0, |t| t.as_secs());
+            ctx.renderer.render_iteration_timeout(secs);
+            Ok(LoopAction::NextIteration)
+        }
+        // Only `coven worker` watches for shutdown signals.
+        SessionOutcome::ProcessExited | SessionOutcome::Shutdown => {
+            Ok(LoopAction::Exit(StopReason::ProcessExited))
         }
-        SessionOutcome::ProcessExited => Ok(LoopAction::Exit),
     }
 }
 
 /// What to do after waiting for user input at a pause point.
 enum WaitResumeAction {
     /// User provided text — resume with a new session.
-    Resume(Box<SessionRunner>, SessionState),
+    Resume(Box<SessionRunner>, Box<SessionState>),
     /// User dismissed the wait (Escape) — proceed without resuming.
     Dismissed,
     /// User exited (Ctrl+C / Ctrl+D).
@@ -531,20 +936,249 @@ async fn wait_input_and_resume<W: Write>(
                 session_id: Some(session_id),
                 ..Default::default()
             };
-            Ok(WaitResumeAction::Resume(Box::new(runner), new_state))
+            Ok(WaitResumeAction::Resume(
+                Box::new(runner),
+                Box::new(new_state),
+            ))
         }
         Some(event_loop::WaitInterruptResult::Dismissed) => {
             state.session_id = Some(session_id);
             Ok(WaitResumeAction::Dismissed)
         }
-        None => Ok(WaitResumeAction::Exit),
+        Some(event_loop::WaitInterruptResult::EndSession) | None => Ok(WaitResumeAction::Exit),
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
 
+    fn config(iteration_timeout: Option<Duration>) -> RalphConfig {
+        RalphConfig {
+            prompt_source: PromptSource::Static("fix the flaky test".to_string()),
+            first_prompt: None,
+            iterations: 2,
+            break_tag: Some("break".to_string()),
+            no_wait: true,
+            display: DisplayOptions {
+                term_width: Some(80),
+                ..Default::default()
+            },
+            protocol_log: None,
+            steering_timeout: None,
+            max_cost: None,
+            iteration_timeout,
+            preflight: false,
+            no_default_permission_mode: false,
+            commit_each: false,
+            tag_flags: TagFlags {
+                fork: false,
+                fork_tools: None,
+                fork_concurrency: crate::fork::DEFAULT_FORK_CONCURRENCY,
+                reload: false,
+            },
+            extra_args: Vec::new(),
+            working_dir: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn first_prompt_is_used_for_the_first_iteration_only() {
+        let mut config = config(Some(Duration::from_mins(10)));
+        config.iterations = 3;
+        config.first_prompt = Some("read PLAN.md and plan the work".to_string());
+        let system_prompt = config.system_prompt();
+        let iteration = |prompt: &str| {
+            let spawn = serde_json::json!({
+                "label": "spawn",
+                "args": config.session_config(&system_prompt, prompt.into()),
+                "result": {"Ok": null},
+            });
+            let timeout = serde_json::json!({
+                "label": "next_event",
+                "args": null,
+                "result": {"Ok": "SessionTimeout"},
+            });
+            [spawn.to_string(), timeout.to_string()]
+        };
+        let ndjson = [
+            iteration("read PLAN.md and plan the work"),
+            iteration("fix the flaky test"),
+            iteration("fix the flaky test"),
+        ]
+        .concat();
+        let vcr = VcrContext::replay(&ndjson.join("\n")).unwrap();
+        let mut io = Io::dummy();
+        let report = ralph(config, &mut io, &vcr, Vec::new()).await.unwrap();
+        assert!(vcr.unreplayed_labels().is_empty());
+        assert_eq!(report.iterations_run, 3);
+    }
+
+    #[tokio::test]
+    async fn max_turns_resumes_once_to_wrap_up() {
+        let mut config = config(None);
+        config.iterations = 1;
+        let session_config =
+            config.session_config(&config.system_prompt(), "fix the flaky test".into());
+        let claude = |event: serde_json::Value| {
+            serde_json::json!({
+                "label": "next_event",
+                "args": null,
+                "result": {"Ok": {"Claude": {"Claude": event}}},
+            })
+        };
+        let spawn = |config: &SessionConfig| serde_json::json!({"label": "spawn", "args": config, "result": {"Ok": null}});
+        let max_turns = claude(serde_json::json!({
+            "type": "result",
+            "subtype": "error_max_turns",
+            "is_error": true,
+            "num_turns": 3,
+            "total_cost_usd": 0.25,
+            "session_id": "s1",
+        }));
+        let ndjson = [
+            spawn(&session_config),
+            claude(serde_json::json!({"type": "system", "subtype": "init", "session_id": "s1"})),
+            max_turns.clone(),
+            spawn(&session_config.resume_with(event_loop::WRAP_UP_PROMPT.into(), "s1".into())),
+            max_turns,
+        ]
+        .map(|entry| entry.to_string());
+        let vcr = VcrContext::replay(&ndjson.join("\n")).unwrap();
+        let mut io = Io::dummy();
+        let mut output = Vec::new();
+        let report = ralph(config, &mut io, &vcr, &mut output).await.unwrap();
+        assert!(vcr.unreplayed_labels().is_empty());
+        assert_eq!(report.iterations_run, 1);
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output
+                .matches("max turns reached \u{2014} resuming once to wrap up")
+                .count(),
+            1,
+            "{output}"
+        );
+        assert_eq!(output.matches("Max turns reached").count(), 2, "{output}");
+    }
+
+    #[tokio::test]
+    async fn commit_each_commits_dirty_iterations_and_warns_on_failure() {
+        let mut config = config(None);
+        config.iterations = 3;
+        config.commit_each = true;
+        let session_config =
+            config.session_config(&config.system_prompt(), "fix the flaky test".into());
+        let iteration = |result: &str, message: &str, committed: serde_json::Value| {
+            [
+                serde_json::json!({"label": "spawn", "args": session_config, "result": {"Ok": null}}),
+                serde_json::json!({
+                    "label": "next_event",
+                    "args": null,
+                    "result": {"Ok": {"Claude": {"Claude": {
+                        "type": "result",
+                        "subtype": "success",
+                        "result": result,
+                        "total_cost_usd": 0.1,
+                        "session_id": "s1",
+                    }}}},
+                }),
+                serde_json::json!({
+                    "label": "worktree::commit_all",
+                    "args": {"message": message},
+                    "result": committed,
+                }),
+            ]
+            .map(|entry| entry.to_string())
+        };
+        let ndjson = [
+            iteration(
+                "Fixed the race in the watcher.\n\nDetails follow.",
+                "ralph iteration 1: Fixed the race in the watcher.",
+                serde_json::json!({"Ok": true}),
+            ),
+            iteration(
+                "Nothing left to change.",
+                "ralph iteration 2: Nothing left to change.",
+                serde_json::json!({"Ok": false}),
+            ),
+            iteration(
+                "Updated the docs.",
+                "ralph iteration 3: Updated the docs.",
+                serde_json::json!({"Err": {"GitCommand": "git commit failed: hook rejected"}}),
+            ),
+        ]
+        .concat();
+        let vcr = VcrContext::replay(&ndjson.join("\n")).unwrap();
+        let mut io = Io::dummy();
+        let mut output = Vec::new();
+        let report = ralph(config, &mut io, &vcr, &mut output).await.unwrap();
+        assert!(vcr.unreplayed_labels().is_empty());
+        assert_eq!(report.iterations_run, 3);
+        let output = String::from_utf8(output).unwrap();
+        assert!(
+            output.contains("Committed: ralph iteration 1: Fixed the race in the watcher."),
+            "{output}"
+        );
+        assert_eq!(output.matches("Committed:").count(), 1, "{output}");
+        assert!(
+            output.contains(
+                "[warn] --commit-each: git command failed: git commit failed: hook rejected"
+            ),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn summary_and_exit_code_follow_stop_reason() {
+        let mut report = RalphReport {
+            total_cost: 0.123,
+            iterations_run: 3,
+            stop_reason: StopReason::BreakTag,
+            ..Default::default()
+        };
+        assert_eq!(
+            report.summary(),
+            "ralph: 3 iterations in 0s, $0.12 total, stopped by break tag"
+        );
+        assert_eq!(report.stop_reason.exit_code(), 0);
+        report.break_reason = Some("all bugs fixed".into());
+        assert_eq!(
+            report.summary(),
+            "ralph: 3 iterations in 0s, $0.12 total, stopped by break tag (all bugs fixed)"
+        );
+        report.break_reason = None;
+        report.iterations_run = 1;
+        report.stop_reason = StopReason::ProcessExited;
+        report.elapsed = Duration::from_secs(852);
+        assert_eq!(
+            report.summary(),
+            "ralph: 1 iteration in 14m12s, $0.12 total, stopped by claude exiting"
+        );
+        assert_eq!(report.stop_reason.exit_code(), 2);
+        assert_eq!(StopReason::UserExit.exit_code(), 130);
+    }
+
+    #[test]
+    fn api_error_retries_stop_after_the_cap() {
+        let mut iter = IterState::default();
+        let mut renderer = Renderer::with_writer(Vec::<u8>::new());
+        for _ in 0..preflight::MAX_API_RETRIES {
+            let action = retry_after_api_error(&mut iter, &mut renderer);
+            assert!(matches!(action, LoopAction::NextIteration));
+        }
+        let action = retry_after_api_error(&mut iter, &mut renderer);
+        assert!(matches!(action, LoopAction::Exit(StopReason::ApiErrors)));
+        let out = String::from_utf8(renderer.writer().clone()).unwrap();
+        assert!(out.contains("(5/5)"), "{out}");
+        assert!(out.contains("giving up after 5"), "{out}");
+
+        // A session that ends without one starts the count over.
+        iter.record_completed(&SessionState::default(), "done", 0);
+        let action = retry_after_api_error(&mut iter, &mut renderer);
+        assert!(matches!(action, LoopAction::NextIteration));
+    }
+
     #[test]
     fn scan_break_tag_found() {
         let text = "I've completed the task. <break>All bugs are fixed.</break> Done.";
@@ -563,6 +1197,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn scan_break_tag_custom_ignores_default_tag() {
+        let text = "The other loop says: <break>stop</break>. Still going.";
+        assert_eq!(scan_break_tag(text, "done"), None);
+    }
+
+    #[test]
+    fn system_prompt_names_custom_tag() {
+        let prompt = ralph_system_prompt("done", true);
+        assert!(prompt.contains("<done>reason</done>"));
+        assert!(!prompt.contains("<break>"));
+    }
+
+    #[test]
+    fn validate_break_tag_accepts_identifiers() {
+        for tag in ["break", "done", "loop-a_done", "stop2"] {
+            assert!(validate_break_tag(tag).is_ok(), "{tag}");
+        }
+    }
+
+    #[test]
+    fn validate_break_tag_rejects_non_identifiers() {
+        for tag in ["", "<done>", "all done", "done/"] {
+            let err = validate_break_tag(tag).unwrap_err().to_string();
+            assert!(err.contains("letters, digits"), "{tag}: {err}");
+        }
+    }
+
+    #[test]
+    fn validate_break_tag_rejects_reserved() {
+        let err = validate_break_tag("fork").unwrap_err().to_string();
+        assert!(err.contains("already used"), "{err}");
+    }
+
+    #[test]
+    fn scan_break_tag_empty() {
+        assert_eq!(
+            scan_break_tag("All done. <break></break>", "break"),
+            Some(String::new())
+        );
+        assert_eq!(
+            scan_break_tag("All done. <break/>", "break"),
+            Some(String::new())
+        );
+        assert_eq!(
+            scan_break_tag("<break>  \n </break>", "break"),
+            Some(String::new())
+        );
+        assert_eq!(scan_break_tag("All done. <done/>", "break"), None);
+    }
+
     #[test]
     fn scan_break_tag_not_found() {
         let text = "Still working on the bugs.";
@@ -1,29 +1,73 @@
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
 
+use crate::config::Section;
 use crate::display::input::InputHandler;
+use crate::display::log_file::LogTee;
 use crate::display::renderer::{Renderer, StoredMessage};
 use crate::fork::{self, ForkConfig};
+use crate::preflight::{self, Backoff};
 use crate::reload;
+use crate::session::persist;
 use crate::session::runner::{SessionConfig, SessionRunner};
 use crate::session::state::{SessionState, SessionStatus};
 use crate::vcr::{Io, VcrContext};
 
-use crate::session::event_loop::{self, FollowUpAction, SessionFeatures, SessionOutcome};
+use crate::session::event_loop::{
+    self, CostBudget, FollowUpAction, SessionFeatures, SessionOutcome,
+};
 
-use super::{RawModeGuard, render_initial_hints, setup_display};
+use super::{
+    DisplayOptions, RawModeGuard, TagFlags, apply_project_defaults, render_initial_hints,
+    render_startup,
+};
 
 pub struct RunConfig {
     pub prompt: Option<String>,
+    /// Resume this claude session instead of starting fresh (`--resume`).
+    pub resume: Option<String>,
+    /// Resume the most recent session in the working directory (`--continue`).
+    pub continue_last: bool,
+    /// Directory to write `:note`s to when the session ends (`--notes-dir`).
+    pub notes_dir: Option<PathBuf>,
     pub extra_args: Vec<String>,
-    pub show_thinking: bool,
-    pub fork: bool,
-    pub reload: bool,
+    pub display: DisplayOptions,
+    /// `--debug-protocol`: where to log claude output that fails to parse.
+    /// Also enables the `:raw` input command.
+    pub protocol_log: Option<PathBuf>,
+    /// Warn when claude is silent this long after a steering message (`None` disables).
+    pub steering_timeout: Option<Duration>,
+    /// Stop once the session has cost more than this many USD (`--max-cost`).
+    pub max_cost: Option<f64>,
+    /// Check connectivity before starting claude (`--preflight-connectivity`).
+    pub preflight: bool,
+    /// Leave the permission mode to claude instead of defaulting to
+    /// `acceptEdits` (`--no-default-permission-mode`).
+    pub no_default_permission_mode: bool,
+    pub tag_flags: TagFlags,
+    /// Fork these tasks before the first turn (`--fork-tasks`); requires `--fork`.
+    pub fork_tasks: Vec<String>,
     pub working_dir: Option<PathBuf>,
-    /// Override terminal width for display truncation (used in tests).
-    pub term_width: Option<usize>,
+    /// Write the session as NDJSON here (`--json-events`).
+    pub json_events: Option<Box<dyn Write + Send>>,
+}
+
+/// Structured artifacts of a completed `run`, for library consumers and tests.
+#[derive(Debug, Default)]
+pub struct RunReport {
+    /// Every message the renderer stored (tool calls, results, thinking).
+    pub messages: Vec<StoredMessage>,
+    /// Result text of the last completed turn.
+    pub result_text: String,
+    /// Cost summed over every completed turn.
+    pub cost: f64,
+    /// The most recent claude session ID, if one was started.
+    pub session_id: Option<String>,
+    /// Scratch notes the user jotted with `:note`.
+    pub notes: Vec<String>,
 }
 
 struct Ctx<'a, W: Write> {
@@ -31,66 +75,67 @@ struct Ctx<'a, W: Write> {
     renderer: &'a mut Renderer<W>,
     io: &'a mut Io,
     vcr: &'a VcrContext,
+    report: &'a mut RunReport,
+    /// Cost of earlier claude processes (before an interrupt or reload), for `--max-cost`.
+    prior_cost: f64,
 }
 
-/// Run a single interactive session. Returns the stored messages for inspection.
+/// Run a single interactive session. Returns the report for inspection.
 pub async fn run<W: Write>(
     mut config: RunConfig,
     io: &mut Io,
     vcr: &VcrContext,
     writer: W,
-) -> Result<Vec<StoredMessage>> {
-    if config.prompt.is_none() && io.is_headless() {
+) -> Result<RunReport> {
+    if config.prompt.is_none() && io.is_headless() && config.fork_tasks.is_empty() {
         bail!("a prompt is required when stdin is not a terminal");
     }
-    let (mut renderer, mut input) = setup_display(writer, config.term_width, config.show_thinking);
+    let (mut renderer, mut input) = run_display(&mut config, vcr, writer)?;
     let mut state = SessionState::default();
     let _raw = RawModeGuard::acquire(io)?;
     render_initial_hints(&mut renderer, io, false);
 
-    let mut append_system_prompt: Option<String> = None;
-    if config.fork {
-        config.extra_args.extend(ForkConfig::disallowed_tool_args());
-        append_system_prompt = Some(fork::fork_system_prompt().to_string());
-    }
-    if config.reload {
-        reload::append_reload_prompt(&mut append_system_prompt);
-    }
-    let fork_config = ForkConfig::if_enabled(config.fork, &config.extra_args, &config.working_dir);
+    let base_session_cfg = base_session_config(&mut config);
+    let fork_config = ForkConfig::if_enabled(
+        config.tag_flags.fork,
+        &config.extra_args,
+        &config.working_dir,
+        config.tag_flags.fork_tools.as_ref(),
+        config.tag_flags.fork_concurrency,
+        config.no_default_permission_mode,
+    );
 
-    let base_session_cfg = SessionConfig {
-        extra_args: config.extra_args.clone(),
-        append_system_prompt,
-        working_dir: config.working_dir.clone(),
-        ..Default::default()
-    };
+    let resume = resolve_resume(&config, vcr).await?;
+    state.resuming = resume.is_some();
 
+    let mut report = RunReport::default();
     let mut ctx = Ctx {
         input: &mut input,
         renderer: &mut renderer,
         io,
         vcr,
+        report: &mut report,
+        prior_cost: 0.0,
     };
 
-    let Some(mut runner) =
-        get_initial_runner(&config, &base_session_cfg, &mut state, &mut ctx).await?
+    let Some(mut runner) = get_initial_runner(
+        &config,
+        resume.as_deref(),
+        &base_session_cfg,
+        fork_config.as_ref(),
+        &mut state,
+        &mut ctx,
+    )
+    .await?
     else {
-        return Ok(vec![]);
-    };
-    let mut watched_tags = Vec::new();
-    if config.fork {
-        watched_tags.push("fork".to_string());
-    }
-    if config.reload {
-        watched_tags.push("reload".to_string());
-    }
-    let features = SessionFeatures {
-        fork_config: fork_config.as_ref(),
-        reload_enabled: config.reload,
-        base_config: &base_session_cfg,
-        watched_tags,
+        ctx.report.notes = ctx.renderer.notes().to_vec();
+        save_notes(&config, None, false, ctx.renderer, vcr).await?;
+        renderer.emit_json_result(None, 0.0, "");
+        return Ok(report);
     };
-    loop {
+    let mut features = session_features(&config, &base_session_cfg, fork_config.as_ref());
+    let ended_abnormally = loop {
+        features.budget = CostBudget::new(config.max_cost, ctx.prior_cost);
         let outcome = event_loop::run_session(
             &mut runner,
             &mut state,
@@ -102,6 +147,13 @@ pub async fn run<W: Write>(
         )
         .await?;
 
+        let abnormal = matches!(
+            outcome,
+            SessionOutcome::Interrupted
+                | SessionOutcome::ProcessExited
+                | SessionOutcome::TimedOut
+                | SessionOutcome::Shutdown
+        );
         let resumed = handle_outcome(
             outcome,
             &base_session_cfg,
@@ -111,13 +163,174 @@ pub async fn run<W: Write>(
         )
         .await?;
         if !resumed {
-            break;
+            break abnormal;
         }
-    }
+    };
 
     runner.close_input();
     let _ = runner.wait().await;
-    Ok(renderer.into_messages())
+    report.notes = renderer.notes().to_vec();
+    save_notes(
+        &config,
+        report.session_id.as_deref(),
+        ended_abnormally,
+        &mut renderer,
+        vcr,
+    )
+    .await?;
+    renderer.emit_json_result(
+        report.session_id.as_deref(),
+        report.cost,
+        &report.result_text,
+    );
+    report.messages = renderer.into_messages();
+    Ok(report)
+}
+
+/// Set up the display for a run, with `--render-markdown` and
+/// `--json-events` applied, after filling in the flags it wasn't given from
+/// `.coven/config.toml`.
+fn run_display<W: Write>(
+    config: &mut RunConfig,
+    vcr: &VcrContext,
+    writer: W,
+) -> Result<(Renderer<LogTee<W>>, InputHandler)> {
+    let permission_mode = apply_project_defaults(
+        vcr,
+        config.working_dir.as_deref(),
+        Section::Run,
+        &mut config.display,
+        &mut config.max_cost,
+        &mut config.extra_args,
+        config.no_default_permission_mode,
+    )?;
+    let (mut renderer, input) = config
+        .display
+        .setup(writer, vcr, config.protocol_log.is_some())?;
+    if let Some(out) = config.json_events.take() {
+        renderer.set_json_events(out);
+    }
+    render_startup(&mut renderer, vcr, &permission_mode)?;
+    Ok((renderer, input))
+}
+
+/// Build the session config shared by every spawn, adding the fork and
+/// reload system prompts (and fork's tool restrictions) when enabled.
+fn base_session_config(config: &mut RunConfig) -> SessionConfig {
+    let mut append_system_prompt: Option<String> = None;
+    if config.tag_flags.fork {
+        config
+            .extra_args
+            .extend(ForkConfig::disallowed_tool_args(&[], None));
+        append_system_prompt = Some(fork::fork_system_prompt(
+            config.tag_flags.fork_tools.as_ref(),
+        ));
+    }
+    if config.tag_flags.reload {
+        reload::append_reload_prompt(&mut append_system_prompt);
+    }
+    SessionConfig {
+        extra_args: config.extra_args.clone(),
+        append_system_prompt,
+        no_default_permission_mode: config.no_default_permission_mode,
+        working_dir: config.working_dir.clone(),
+        ..Default::default()
+    }
+}
+
+/// Session features for this run; the budget is set per session.
+fn session_features<'a>(
+    config: &'a RunConfig,
+    base_config: &'a SessionConfig,
+    fork_config: Option<&'a ForkConfig>,
+) -> SessionFeatures<'a> {
+    SessionFeatures {
+        fork_config,
+        reload_enabled: config.tag_flags.reload,
+        base_config,
+        watched_tags: watched_tags(config),
+        steering_timeout: config.steering_timeout,
+        resume_wedged: false,
+        budget: None,
+        max_loop_warnings: None,
+        protocol_log: config.protocol_log.as_deref(),
+    }
+}
+
+/// Tags the model may emit that this run acts on.
+fn watched_tags(config: &RunConfig) -> Vec<String> {
+    let mut tags = Vec::new();
+    if config.tag_flags.fork {
+        tags.push("fork".to_string());
+    }
+    if config.tag_flags.reload {
+        tags.push("reload".to_string());
+    }
+    tags
+}
+
+/// The session ID to resume from `--resume` or `--continue`, if either was given.
+async fn resolve_resume(config: &RunConfig, vcr: &VcrContext) -> Result<Option<String>> {
+    if config.resume.is_some() || !config.continue_last {
+        return Ok(config.resume.clone());
+    }
+    persist::latest_session_id(vcr, config.working_dir.as_deref())
+        .await?
+        .context("no previous session to continue in this directory")
+        .map(Some)
+}
+
+/// Write the session's `:note`s to `<notes_dir>/<session-id>.notes.md`.
+///
+/// Without `--notes-dir`, notes are only written when the session ended
+/// abnormally (interrupt or claude exiting), to a temp directory, so they
+/// aren't lost. Prints the file path either way.
+async fn save_notes<W: Write>(
+    config: &RunConfig,
+    session_id: Option<&str>,
+    abnormal: bool,
+    renderer: &mut Renderer<W>,
+    vcr: &VcrContext,
+) -> Result<()> {
+    if renderer.notes().is_empty() {
+        return Ok(());
+    }
+    let dir = match &config.notes_dir {
+        Some(dir) => dir.clone(),
+        None if abnormal => std::env::temp_dir().join("coven-notes"),
+        None => return Ok(()),
+    };
+    let path = dir.join(format!("{}.notes.md", session_id.unwrap_or("session")));
+    let contents = format_notes_file(session_id, renderer.notes());
+    vcr.call(
+        "save_notes",
+        (path.display().to_string(), contents),
+        async |(p, c): &(String, String)| write_notes_file(Path::new(p), c),
+    )
+    .await?;
+    renderer.write_raw(&format!("\r\nNotes saved to {}\r\n", path.display()));
+    Ok(())
+}
+
+fn format_notes_file(session_id: Option<&str>, notes: &[String]) -> String {
+    let mut out = match session_id {
+        Some(id) => format!("# Notes for session {id}\n\n"),
+        None => "# Notes\n\n".to_string(),
+    };
+    for note in notes {
+        out.push_str("- ");
+        out.push_str(note);
+        out.push('\n');
+    }
+    out
+}
+
+fn write_notes_file(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    std::fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
 }
 
 /// Handle a session outcome. Returns `true` if the session was resumed.
@@ -128,8 +341,21 @@ async fn handle_outcome<W: Write>(
     state: &mut SessionState,
     ctx: &mut Ctx<'_, W>,
 ) -> Result<bool> {
+    if let Some(id) = &state.session_id {
+        ctx.report.session_id = Some(id.clone());
+    }
     match outcome {
-        SessionOutcome::Completed { .. } => {
+        SessionOutcome::Completed { result_text } => {
+            ctx.report.result_text = result_text;
+            ctx.report.cost += state.total_cost_usd;
+            if state.budget_exceeded {
+                return Ok(false);
+            }
+            if state.untrusted_dir.take().is_some() {
+                ctx.renderer.write_raw(
+                    "  Press Ctrl+O to open claude here and trust it, then send a message to retry.\r\n",
+                );
+            }
             match event_loop::wait_for_followup(
                 ctx.input,
                 ctx.renderer,
@@ -152,8 +378,7 @@ async fn handle_outcome<W: Write>(
                         ..base_session_cfg.clone()
                     };
                     event_loop::open_interactive_session(&interactive_cfg, ctx.io)?;
-                    ctx.renderer.render_returned_from_interactive();
-                    resume_after_pause(session_id, base_session_cfg, runner, state, ctx).await
+                    return_from_interactive(session_id, base_session_cfg, runner, state, ctx).await
                 }
                 FollowUpAction::Exit => Ok(false),
             }
@@ -175,6 +400,7 @@ async fn handle_outcome<W: Write>(
             )
             .await;
             runner.kill().await?;
+            ctx.prior_cost += state.total_cost_usd;
             let Some(session_id) = state.session_id.take() else {
                 return Ok(false);
             };
@@ -190,18 +416,82 @@ async fn handle_outcome<W: Write>(
             *state = new_state;
             Ok(true)
         }
-        SessionOutcome::ProcessExited => Ok(false),
+        // Claude exited before starting the session: most likely an unknown
+        // `--resume` ID. Its stderr has already been rendered.
+        SessionOutcome::ProcessExited if state.resuming => {
+            bail!("could not resume the session")
+        }
+        // `run` never arms a session timeout or watches for shutdown signals.
+        SessionOutcome::ProcessExited | SessionOutcome::TimedOut | SessionOutcome::Shutdown => {
+            Ok(false)
+        }
     }
 }
 
 /// Get the initial runner: either from prompt or by waiting for interactive input.
+/// Resumes `resume` when given instead of starting a fresh session.
 /// Returns None if the user exits without submitting.
+/// Framing for `--fork-tasks` when no prompt is given.
+const DEFAULT_FORK_FRAMING: &str = "Work through these tasks in parallel.";
+
+/// Run `--fork-tasks` before the parent's first turn: the children start
+/// from the user's prompt, and their results become the parent's first
+/// message, so it starts by synthesizing them.
+async fn pre_fork<W: Write>(
+    config: &RunConfig,
+    fork_config: &ForkConfig,
+    ctx: &mut Ctx<'_, W>,
+) -> Result<String> {
+    let framing = config.prompt.as_deref().unwrap_or(DEFAULT_FORK_FRAMING);
+    let results = fork::run_fork(
+        fork::ForkOrigin::Prompt(framing),
+        config.fork_tasks.clone(),
+        fork_config,
+        ctx.renderer,
+        ctx.input,
+        ctx.io,
+        ctx.vcr,
+    )
+    .await?;
+    Ok(format!("{framing}\n\n{results}"))
+}
+
 async fn get_initial_runner<W: Write>(
     config: &RunConfig,
+    resume: Option<&str>,
     base_session_cfg: &SessionConfig,
+    fork_config: Option<&ForkConfig>,
     state: &mut SessionState,
     ctx: &mut Ctx<'_, W>,
 ) -> Result<Option<SessionRunner>> {
+    let base_session_cfg = &SessionConfig {
+        resume: resume.map(String::from),
+        ..base_session_cfg.clone()
+    };
+    if config.preflight
+        && !preflight::wait_until_online(
+            true,
+            &mut Backoff::default(),
+            ctx.renderer,
+            ctx.input,
+            ctx.io,
+            ctx.vcr,
+        )
+        .await?
+    {
+        return Ok(None);
+    }
+    if !config.fork_tasks.is_empty() {
+        let fork_config = fork_config.context("--fork-tasks requires --fork")?;
+        let prompt = pre_fork(config, fork_config, ctx).await?;
+        let session_cfg = SessionConfig {
+            prompt: Some(prompt),
+            ..base_session_cfg.clone()
+        };
+        let runner = event_loop::spawn_session(session_cfg, ctx.io, ctx.vcr).await?;
+        state.status = SessionStatus::Running;
+        return Ok(Some(runner));
+    }
     if let Some(prompt) = &config.prompt {
         let session_cfg = SessionConfig {
             prompt: Some(prompt.clone()),
@@ -244,14 +534,30 @@ async fn get_initial_runner<W: Write>(
             let runner = event_loop::spawn_session(session_cfg, ctx.io, ctx.vcr).await?;
             state.status = SessionStatus::Running;
             state.session_id = Some(session_id);
+            state.resuming = false;
             Ok(Some(runner))
         }
-        Some(event_loop::WaitResult::Dismissed) | None => Ok(None),
+        Some(event_loop::WaitResult::Dismissed | event_loop::WaitResult::EndSession) | None => {
+            Ok(None)
+        }
     }
 }
 
 /// Wait for user input after a pause (interrupt or interactive), then resume.
 /// Returns `true` if resumed, `false` if the user exited.
+/// Back from the native TUI after a completed turn: say so, then carry on
+/// like after an interrupt, resuming the session with the next message.
+async fn return_from_interactive<W: Write>(
+    session_id: String,
+    base_session_cfg: &SessionConfig,
+    runner: &mut SessionRunner,
+    state: &mut SessionState,
+    ctx: &mut Ctx<'_, W>,
+) -> Result<bool> {
+    ctx.renderer.render_returned_from_interactive();
+    resume_after_pause(session_id, base_session_cfg, runner, state, ctx).await
+}
+
 async fn resume_after_pause<W: Write>(
     session_id: String,
     base_session_cfg: &SessionConfig,
@@ -273,7 +579,100 @@ async fn resume_after_pause<W: Write>(
     };
     let session_cfg = base_session_cfg.resume_with(text, session_id.clone());
     *runner = event_loop::spawn_session(session_cfg, ctx.io, ctx.vcr).await?;
+    ctx.prior_cost += state.total_cost_usd;
     *state = SessionState::default();
     state.session_id = Some(session_id);
     Ok(true)
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn notes_file_lists_notes_under_session_heading() {
+        let notes = vec![
+            "[14:03:22Z] flaky test".to_string(),
+            "[14:05:10Z] use v2".to_string(),
+        ];
+        assert_eq!(
+            format_notes_file(Some("abc-123"), &notes),
+            "# Notes for session abc-123\n\n- [14:03:22Z] flaky test\n- [14:05:10Z] use v2\n"
+        );
+        assert!(format_notes_file(None, &notes).starts_with("# Notes\n\n"));
+    }
+
+    /// Type `text` and press Enter, as recorded key events.
+    fn typed(text: &str) -> Vec<serde_json::Value> {
+        use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+        use crate::vcr::IoEvent;
+        text.chars()
+            .map(KeyCode::Char)
+            .chain([KeyCode::Enter])
+            .map(|code| {
+                let key = IoEvent::Terminal(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+                serde_json::json!({"label": "next_event", "args": null, "result": {"Ok": key}})
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn returning_from_interactive_resumes_the_session() {
+        let mut calls =
+            vec![serde_json::json!({"label": "idle", "args": null, "result": {"Ok": null}})];
+        calls.extend(typed("carry on"));
+        calls.push(serde_json::json!({
+            "label": "spawn",
+            "args": {"append_system_prompt": null, "extra_args": [], "prompt": "carry on", "resume": "abc-123"},
+            "result": {"Ok": null},
+        }));
+        let ndjson: Vec<String> = calls.iter().map(ToString::to_string).collect();
+        let vcr = VcrContext::replay(&ndjson.join("\n")).unwrap();
+
+        let mut input = InputHandler::new(2);
+        let mut renderer = Renderer::with_writer(Vec::<u8>::new());
+        let mut io = Io::dummy();
+        let mut report = RunReport::default();
+        let mut ctx = Ctx {
+            input: &mut input,
+            renderer: &mut renderer,
+            io: &mut io,
+            vcr: &vcr,
+            report: &mut report,
+            prior_cost: 0.5,
+        };
+        let mut runner = SessionRunner::stub();
+        let mut state = SessionState {
+            total_cost_usd: 0.25,
+            ..SessionState::default()
+        };
+        let resumed = return_from_interactive(
+            "abc-123".to_string(),
+            &SessionConfig::default(),
+            &mut runner,
+            &mut state,
+            &mut ctx,
+        )
+        .await
+        .unwrap();
+
+        assert!(resumed);
+        assert!((ctx.prior_cost - 0.75).abs() < f64::EPSILON);
+        assert_eq!(state.session_id.as_deref(), Some("abc-123"));
+        assert!(state.total_cost_usd.abs() < f64::EPSILON);
+        assert!(vcr.unreplayed_labels().is_empty());
+        let output = String::from_utf8(renderer.writer().clone()).unwrap();
+        assert!(output.contains("returned to coven"), "{output}");
+    }
+
+    #[test]
+    fn write_notes_file_creates_missing_dirs() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("logs/nested/abc.notes.md");
+        write_notes_file(&path, "# Notes\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "# Notes\n");
+    }
+}
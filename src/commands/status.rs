@@ -1,15 +1,19 @@
+use std::collections::BTreeMap;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
+use serde::Serialize;
 
 use crate::vcr::VcrContext;
-use crate::worker_state::{self, StatusStyle};
+use crate::worker_state::{self, StatusStyle, WorkerState};
 
-/// Display the status of all active workers.
+/// Display the status of all active workers, as text or (with `json`) as a
+/// JSON document for scripts.
 pub async fn status(
     vcr: &VcrContext,
     working_dir: Option<&Path>,
+    json: bool,
     writer: &mut impl Write,
 ) -> Result<()> {
     let project_root = super::resolve_working_dir(vcr, working_dir).await?;
@@ -22,17 +26,70 @@ pub async fn status(
         )
         .await?;
 
+    if json {
+        serde_json::to_writer_pretty(&mut *writer, &StatusJson::new(&states))?;
+        writeln!(writer)?;
+        return Ok(());
+    }
+
     if states.is_empty() {
         writeln!(writer, "No active workers.")?;
         return Ok(());
     }
 
+    let now = vcr.clock_ms()? / 1000;
     writeln!(writer, "{} active worker(s):\n", states.len())?;
     write!(
         writer,
         "{}",
-        worker_state::format_workers(&states, StatusStyle::Cli)
+        worker_state::format_workers(&states, StatusStyle::Cli { now })
     )?;
 
     Ok(())
 }
+
+/// `coven status --json` output.
+#[derive(Serialize)]
+struct StatusJson<'a> {
+    workers: Vec<WorkerJson<'a>>,
+}
+
+#[derive(Serialize)]
+struct WorkerJson<'a> {
+    branch: &'a str,
+    pid: u32,
+    worktree: Option<&'a PathBuf>,
+    /// Agent currently running, `null` when idle.
+    agent: Option<&'a str>,
+    /// Sorted so output is stable between runs.
+    args: BTreeMap<&'a str, &'a str>,
+    /// Seconds since the Unix epoch.
+    updated_at: Option<u64>,
+    /// When the current agent started, in seconds since the Unix epoch.
+    phase_started_at: Option<u64>,
+    total_cost_usd: f64,
+}
+
+impl<'a> StatusJson<'a> {
+    fn new(states: &'a [WorkerState]) -> Self {
+        let mut workers: Vec<_> = states
+            .iter()
+            .map(|s| WorkerJson {
+                branch: &s.branch,
+                pid: s.pid,
+                worktree: s.worktree.as_ref(),
+                agent: s.agent.as_deref(),
+                args: s
+                    .args
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str()))
+                    .collect(),
+                updated_at: s.updated_at,
+                phase_started_at: s.phase_started_at,
+                total_cost_usd: s.total_cost_usd,
+            })
+            .collect();
+        workers.sort_by_key(|w| w.branch);
+        Self { workers }
+    }
+}
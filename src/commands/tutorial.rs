@@ -0,0 +1,409 @@
+//! `coven tutorial`: replay a bundled recording through the normal renderer,
+//! pausing at checkpoints where the user tries the keys they'd use in a real
+//! session.
+//!
+//! Claude's side comes from the recording, fed into `Io`'s event channel as
+//! if a claude process were streaming it; the keyboard is live. Checkpoints
+//! run keys through the real `InputHandler` and wait for the action asked for.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::Duration;
+
+use anyhow::Result;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use tokio::sync::mpsc;
+
+use super::{RawModeGuard, setup_display, terminal_size};
+use crate::display::input::{InputAction, InputHandler};
+use crate::display::renderer::Renderer;
+use crate::event::{AppEvent, InputMode};
+use crate::protocol::types::InboundEvent;
+use crate::session::event_loop;
+use crate::session::state::SessionState;
+use crate::vcr::{self, Io, IoEvent, VcrContext};
+
+/// The session replayed: claude touring a small to-do list project. Made
+/// with `record-vcr` from `assets/tutorial/tutorial.toml`.
+const RECORDING: &str = include_str!("../../assets/tutorial/tutorial.vcr");
+
+/// Delay between replayed streaming deltas, and between other events.
+const DELTA_PACE: Duration = Duration::from_millis(15);
+const EVENT_PACE: Duration = Duration::from_millis(400);
+
+/// One step of the tutorial.
+#[derive(Debug, Clone, Copy)]
+pub enum Step {
+    /// Explain something.
+    Say(&'static str),
+    /// Replay the recording up to a point.
+    Play(Until),
+    /// Explain a key, then wait until the user uses it.
+    Try {
+        explain: &'static str,
+        expect: Expect,
+    },
+}
+
+/// Where a [`Step::Play`] stops.
+#[derive(Debug, Clone, Copy)]
+pub enum Until {
+    /// After this many more tool results.
+    ToolResults(usize),
+    /// At the end of the recording.
+    End,
+}
+
+/// The action a checkpoint asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expect {
+    /// Open a message with `:N`.
+    View,
+    /// Send a steering message (Enter).
+    Steer,
+    /// Queue a follow-up (Alt+Enter).
+    FollowUp,
+    /// Ctrl+W.
+    Wait,
+    /// Ctrl+O.
+    Interactive,
+}
+
+impl Expect {
+    /// Whether `action` is what this checkpoint asks for.
+    pub fn is_met_by(self, action: &InputAction) -> bool {
+        match (self, action) {
+            (Self::Steer, InputAction::Submit(text, InputMode::Steering))
+            | (Self::FollowUp, InputAction::Submit(text, InputMode::FollowUp)) => {
+                !text.trim().is_empty()
+            }
+            (Self::View, InputAction::ViewMessage(_))
+            | (Self::Wait, InputAction::WaitRequested)
+            | (Self::Interactive, InputAction::Interactive) => true,
+            _ => false,
+        }
+    }
+
+    /// A nudge for a finished action that wasn't the one asked for.
+    fn retry_hint(self) -> &'static str {
+        match self {
+            Self::View => "Not quite \u{2014} type :1 and press Enter.",
+            Self::Steer => "Not quite \u{2014} type a message and press Enter.",
+            Self::FollowUp => "Not quite \u{2014} type a message and press Alt+Enter.",
+            Self::Wait => "Not quite \u{2014} press Ctrl+W.",
+            Self::Interactive => "Not quite \u{2014} press Ctrl+O.",
+        }
+    }
+}
+
+/// The tutorial, in order.
+pub const SCRIPT: &[Step] = &[
+    Step::Say(
+        "Welcome to coven! You'll watch a recorded claude session and try the keys you'd \
+         use in a real one.\nPress Ctrl+D at any time to quit.",
+    ),
+    Step::Play(Until::ToolResults(1)),
+    Step::Try {
+        explain: "Tool calls are numbered, like [1] above. Type :1 and press Enter to open \
+                  the first one in your pager (quit the pager to come back).",
+        expect: Expect::View,
+    },
+    Step::Play(Until::ToolResults(1)),
+    Step::Try {
+        explain: "While claude works, type a message and press Enter to steer: it reaches \
+                  claude right away, mid-turn. Try it.",
+        expect: Expect::Steer,
+    },
+    Step::Play(Until::ToolResults(1)),
+    Step::Try {
+        explain: "Alt+Enter queues a follow-up instead, sent once claude finishes its turn. \
+                  Type one and press Alt+Enter.",
+        expect: Expect::FollowUp,
+    },
+    Step::Play(Until::End),
+    Step::Try {
+        explain: "In `coven ralph` and `coven worker`, Ctrl+W asks coven to wait for you \
+                  when the session ends instead of moving on. Press Ctrl+W.",
+        expect: Expect::Wait,
+    },
+    Step::Try {
+        explain: "Ctrl+O hands the session over to claude's own interface, for when you'd \
+                  rather drive it directly. Press Ctrl+O.",
+        expect: Expect::Interactive,
+    },
+    Step::Say(
+        "That's the tour! Type while claude works to steer, Alt+Enter to follow up, :N to \
+         look closer.\nRun `coven \"your prompt\"` to start a real session.",
+    ),
+];
+
+/// Run the tutorial. Returns early, without error, if the user quits.
+pub async fn tutorial<W: Write>(io: &mut Io, vcr: &VcrContext, writer: W) -> Result<()> {
+    let mut events: VecDeque<AppEvent> = vcr::recorded_claude_events(RECORDING)?.into();
+    let _raw = RawModeGuard::acquire(io)?;
+    let (mut renderer, mut input) = setup_display(
+        writer,
+        terminal_size(vcr, None)?,
+        false,
+        false,
+        None,
+        false,
+        None,
+    )?;
+    let mut state = SessionState::default();
+
+    for step in SCRIPT {
+        let carry_on = match *step {
+            Step::Say(text) => {
+                renderer.render_tutorial(text);
+                true
+            }
+            Step::Play(until) => {
+                let segment = take_segment(&mut events, until);
+                play(segment, &mut state, &mut renderer, io, vcr).await?
+            }
+            Step::Try { explain, expect } => {
+                checkpoint(explain, expect, &mut renderer, &mut input, io, vcr).await?
+            }
+        };
+        if !carry_on {
+            renderer.render_tutorial("Tutorial ended. Run `coven tutorial` to start over.");
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Take the events up to where `until` stops from the front of `events`.
+fn take_segment(events: &mut VecDeque<AppEvent>, until: Until) -> Vec<AppEvent> {
+    let Until::ToolResults(mut remaining) = until else {
+        return events.drain(..).collect();
+    };
+    let mut segment = Vec::new();
+    while remaining > 0
+        && let Some(event) = events.pop_front()
+    {
+        if is_tool_result(&event) {
+            remaining -= 1;
+        }
+        segment.push(event);
+    }
+    segment
+}
+
+/// The result of one of claude's own tool calls (not a subagent's).
+fn is_tool_result(event: &AppEvent) -> bool {
+    matches!(
+        event,
+        AppEvent::Claude(inbound)
+            if matches!(&**inbound, InboundEvent::User(user)
+                if user.parent_tool_use_id.is_none() && user.tool_use_result.is_some())
+    )
+}
+
+/// Stream `segment` through `io` at a readable pace and render it as a
+/// session would. Returns false if the user quit.
+async fn play<W: Write>(
+    segment: Vec<AppEvent>,
+    state: &mut SessionState,
+    renderer: &mut Renderer<W>,
+    io: &mut Io,
+    vcr: &VcrContext,
+) -> Result<bool> {
+    let mut remaining = segment.len();
+    vcr.call("tutorial::play", (), async |(): &()| {
+        let tx = io.replace_event_channel();
+        tokio::spawn(feed(segment, tx));
+        Ok(())
+    })
+    .await?;
+    while remaining > 0 {
+        let event = vcr
+            .call("next_event", (), async |(): &()| io.next_event().await)
+            .await?;
+        match event {
+            IoEvent::Claude(AppEvent::Claude(inbound)) => {
+                remaining -= 1;
+                event_loop::handle_inbound(&inbound, state, renderer, false);
+            }
+            IoEvent::Claude(AppEvent::ProcessExit(_)) | IoEvent::Shutdown => return Ok(false),
+            IoEvent::Claude(_) => remaining -= 1,
+            IoEvent::Terminal(Event::Key(key)) if is_quit(&key) => return Ok(false),
+            IoEvent::Terminal(_) | IoEvent::Silence | IoEvent::SessionTimeout => {}
+        }
+    }
+    Ok(true)
+}
+
+/// Send `events` the way claude would stream them.
+async fn feed(events: Vec<AppEvent>, tx: mpsc::UnboundedSender<AppEvent>) {
+    for event in events {
+        let pace = match &event {
+            AppEvent::Claude(inbound) if matches!(**inbound, InboundEvent::StreamEvent(_)) => {
+                DELTA_PACE
+            }
+            _ => EVENT_PACE,
+        };
+        tokio::time::sleep(pace).await;
+        if tx.send(event).is_err() {
+            return;
+        }
+    }
+    // Claude is "still running" until the tutorial moves on.
+    tx.closed().await;
+}
+
+fn is_quit(key: &KeyEvent) -> bool {
+    key.modifiers.contains(KeyModifiers::CONTROL) && matches!(key.code, KeyCode::Char('c' | 'd'))
+}
+
+/// Explain a key and handle input until the user does what `expect` asks,
+/// acting on it as a session would. Returns false if the user quit.
+async fn checkpoint<W: Write>(
+    explain: &str,
+    expect: Expect,
+    renderer: &mut Renderer<W>,
+    input: &mut InputHandler,
+    io: &mut Io,
+    vcr: &VcrContext,
+) -> Result<bool> {
+    renderer.render_tutorial(explain);
+    io.clear_event_channel();
+    loop {
+        let event = vcr
+            .call("next_event", (), async |(): &()| io.next_event().await)
+            .await?;
+        let key = match event {
+            IoEvent::Terminal(Event::Key(key)) => key,
+            IoEvent::Shutdown => return Ok(false),
+            _ => continue,
+        };
+        let action = input.handle_key(&key, renderer.writer());
+        match &action {
+            InputAction::Interrupt | InputAction::EndSession => return Ok(false),
+            InputAction::Activated(_) => {
+                renderer.begin_input_line();
+                input.redraw(renderer.writer());
+                continue;
+            }
+            InputAction::None => continue,
+            _ => {}
+        }
+        if !expect.is_met_by(&action) {
+            renderer.render_tutorial(expect.retry_hint());
+            continue;
+        }
+        match action {
+            InputAction::ViewMessage(query) => event_loop::view_message(renderer, &query, io)?,
+            InputAction::Submit(text, InputMode::Steering) => renderer.render_steering_sent(&text),
+            InputAction::Submit(text, InputMode::FollowUp) => {
+                renderer.render_followup_queued(&text);
+            }
+            _ => {}
+        }
+        return Ok(true);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expectations_match_only_their_action() {
+        let steer = InputAction::Submit("use v2".into(), InputMode::Steering);
+        let follow_up = InputAction::Submit("then docs".into(), InputMode::FollowUp);
+        assert!(Expect::Steer.is_met_by(&steer));
+        assert!(!Expect::Steer.is_met_by(&follow_up));
+        assert!(Expect::FollowUp.is_met_by(&follow_up));
+        assert!(!Expect::Steer.is_met_by(&InputAction::Submit("  ".into(), InputMode::Steering)));
+        assert!(Expect::View.is_met_by(&InputAction::ViewMessage("1".into())));
+        assert!(Expect::Wait.is_met_by(&InputAction::WaitRequested));
+        assert!(!Expect::Wait.is_met_by(&InputAction::Interactive));
+        assert!(Expect::Interactive.is_met_by(&InputAction::Interactive));
+    }
+
+    /// Every `Play` step stops where the script says: the bundled recording
+    /// has a tool result for each, and something left for the last.
+    #[test]
+    fn script_fits_the_recording() {
+        let mut events: VecDeque<AppEvent> = vcr::recorded_claude_events(RECORDING).unwrap().into();
+        for step in SCRIPT {
+            let Step::Play(until) = *step else {
+                continue;
+            };
+            let segment = take_segment(&mut events, until);
+            assert!(!segment.is_empty());
+            if let Until::ToolResults(n) = until {
+                assert_eq!(segment.iter().filter(|e| is_tool_result(e)).count(), n);
+                assert!(is_tool_result(segment.last().unwrap()));
+            }
+        }
+        assert!(events.is_empty());
+    }
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> serde_json::Value {
+        let event = IoEvent::Terminal(Event::Key(KeyEvent::new(code, modifiers)));
+        serde_json::json!({"label": "next_event", "args": null, "result": {"Ok": event}})
+    }
+
+    fn typed(text: &str) -> Vec<serde_json::Value> {
+        text.chars()
+            .map(|c| key(KeyCode::Char(c), KeyModifiers::NONE))
+            .collect()
+    }
+
+    fn replay(entries: &[serde_json::Value]) -> VcrContext {
+        let lines: Vec<String> = entries.iter().map(ToString::to_string).collect();
+        VcrContext::replay(&lines.join("\n")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn checkpoint_waits_for_the_expected_action() {
+        // A follow-up first (Alt+Enter), then the steering message asked for.
+        let mut entries = typed("later");
+        entries.push(key(KeyCode::Enter, KeyModifiers::ALT));
+        entries.extend(typed("use v2"));
+        entries.push(key(KeyCode::Enter, KeyModifiers::NONE));
+        let vcr = replay(&entries);
+        let mut renderer = Renderer::with_writer(Vec::new());
+        let mut input = InputHandler::new(2);
+        let mut io = Io::dummy();
+
+        let done = checkpoint(
+            "Steer!",
+            Expect::Steer,
+            &mut renderer,
+            &mut input,
+            &mut io,
+            &vcr,
+        )
+        .await
+        .unwrap();
+        assert!(done);
+        assert!(vcr.unreplayed_labels().is_empty());
+        let out = String::from_utf8(renderer.writer().clone()).unwrap();
+        assert!(out.contains(Expect::Steer.retry_hint()), "{out}");
+        assert!(out.contains("use v2"), "{out}");
+    }
+
+    #[tokio::test]
+    async fn quitting_ends_a_checkpoint() {
+        let vcr = replay(&[key(KeyCode::Char('d'), KeyModifiers::CONTROL)]);
+        let mut renderer = Renderer::with_writer(Vec::new());
+        let mut input = InputHandler::new(2);
+        let mut io = Io::dummy();
+        let done = checkpoint(
+            "Press Ctrl+W.",
+            Expect::Wait,
+            &mut renderer,
+            &mut input,
+            &mut io,
+            &vcr,
+        )
+        .await
+        .unwrap();
+        assert!(!done);
+    }
+}
@@ -2,28 +2,40 @@ use std::collections::HashMap;
 use std::fmt::Write as FmtWrite;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Context, Result, bail};
-use crossterm::event::Event;
+use crossterm::event::{Event, KeyCode, KeyModifiers};
 use notify::{RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
 
 use crate::agents::{self, AgentDef};
-use crate::config;
+use crate::config::{self, Section};
+use crate::control_socket::{ControlSocket, WorkerEvent};
+use crate::covenignore::IgnoreRules;
+use crate::dispatch_debug::{self, DispatchRecord};
 use crate::display::input::{InputAction, InputHandler};
-use crate::display::renderer::Renderer;
+use crate::display::renderer::{Renderer, StoredMessage};
 use crate::fork::{self, ForkConfig};
+use crate::post_land::{self, HookOutput, LandInfo, Landed, PostLandHook};
+use crate::preflight::{self, Backoff};
 use crate::semaphore;
-use crate::session::runner::SessionConfig;
-use crate::session::state::SessionState;
+use crate::session::outbox;
+use crate::session::runner::{SessionConfig, SessionRunner, has_flag};
+use crate::session::state::{SessionState, TokenUsage};
+use crate::timing::{LandAttempts, Span, Spans, format_elapsed};
 use crate::transition::{self, Transition};
 use crate::vcr::{Io, IoEvent, VcrContext};
 use crate::worker_state;
-use crate::worktree::{self, SpawnOptions};
+use crate::worktree::{self, Execution, PushOutcome, SpawnOptions, SpawnResult};
+use crate::worktree_base::{self, BaseFilesystemWarning};
 
-use crate::session::event_loop::{self, SessionFeatures, SessionOutcome};
+use crate::session::event_loop::{self, CostBudget, SessionFeatures, SessionOutcome};
 
-use super::{RawModeGuard, render_initial_hints, setup_display};
+use super::{
+    DisplayOptions, RawModeGuard, TagFlags, apply_project_defaults, render_initial_hints,
+    render_startup,
+};
 
 /// Shared mutable context threaded through worker phases.
 struct PhaseContext<'a, W: Write> {
@@ -33,21 +45,249 @@ struct PhaseContext<'a, W: Write> {
     vcr: &'a VcrContext,
     fork_config: Option<&'a ForkConfig>,
     reload_enabled: bool,
+    steering_timeout: Option<Duration>,
+    max_cost: Option<f64>,
+    max_loop_warnings: Option<usize>,
+    protocol_log: Option<&'a Path>,
+    preflight: bool,
+    no_wait: bool,
+    debug_dispatch: bool,
+    lock_wait: semaphore::LockWait,
+    /// Backoff after failed preflights and error results.
+    backoff: Backoff,
     total_cost: f64,
+    /// Token usage of every claude process the worker has run, including
+    /// phases that ended in an exit.
+    total_usage: TokenUsage,
+    /// One entry per agent run; the last entry is the phase in progress.
+    phases: Vec<PhaseReport>,
+    /// Timed spans not yet shown in a timing line.
+    spans: Spans,
+    /// Messages a session ended without delivering, for the next phase's prompt.
+    undelivered: Vec<String>,
+    control: Option<&'a ControlSocket>,
+}
+
+impl<W: Write> PhaseContext<'_, W> {
+    /// Publish an event to control socket clients, if `--control-socket` is set.
+    fn emit(&self, event: WorkerEvent) {
+        if let Some(control) = self.control {
+            control.emit(event);
+        }
+    }
+
+    /// `prompt` plus any messages an earlier session ended without delivering.
+    fn carry_undelivered(&mut self, prompt: &str) -> String {
+        outbox::carry_into_prompt(prompt, &std::mem::take(&mut self.undelivered))
+    }
+
+    /// Keep the messages a session ended without delivering for the next
+    /// phase. After an interrupt they're back in the input line instead.
+    fn keep_undelivered(&mut self, outcome: &SessionOutcome, state: &mut SessionState) {
+        if !matches!(outcome, SessionOutcome::Interrupted) {
+            self.undelivered.append(&mut state.undelivered);
+        }
+    }
+
+    /// Wait out connectivity backoff before starting claude. False if the
+    /// user exited while waiting.
+    async fn wait_until_online(&mut self) -> Result<bool> {
+        preflight::wait_until_online(
+            self.preflight,
+            &mut self.backoff,
+            self.renderer,
+            self.input,
+            self.io,
+            self.vcr,
+        )
+        .await
+    }
+
+    /// Record a completed turn of the phase in progress.
+    fn record_turn(&mut self, state: &mut SessionState, result_text: &str) {
+        self.backoff.record_session(state);
+        if let Some(phase) = self.phases.last_mut() {
+            phase.result_text = result_text.to_string();
+            phase.cost += state.total_cost_usd;
+            phase.session_id.clone_from(&state.session_id);
+            phase.land.merge(&std::mem::take(&mut state.land));
+        }
+    }
+
+    /// Time the phase that just ended, from when it began waiting for its
+    /// agent's slot (`start_ms`) until now. The agent's runs of `land.sh`
+    /// get a `land` span of their own, taken out of the agent's.
+    fn time_phase(&mut self, agent: &str, start_ms: u64, acquired_ms: u64) -> Result<()> {
+        let mut span = Span::between(agent, start_ms, self.vcr.clock_ms()?);
+        span.lock_wait = Duration::from_millis(acquired_ms.saturating_sub(start_ms));
+        let land = self.phases.last_mut().and_then(|phase| {
+            phase.elapsed = span.elapsed;
+            phase.lock_wait = span.lock_wait;
+            phase.land.span()
+        });
+        if let Some(land) = &land {
+            span.elapsed = span.elapsed.saturating_sub(land.elapsed);
+        }
+        self.spans.push(span);
+        if let Some(land) = land {
+            self.spans.push(land);
+        }
+        Ok(())
+    }
+
+    /// Show the spans timed since the last timing line.
+    fn show_timing(&mut self) {
+        if let Some(line) = self.spans.take_line() {
+            self.renderer.render_timing(&line);
+        }
+    }
+
+    /// Add a finished phase session's cost to the worker's totals and show
+    /// them. Its token usage was already added as each process ended.
+    fn add_to_totals(&mut self, cost: f64) {
+        self.total_cost += cost;
+        self.renderer
+            .write_raw(&format!("  Total cost: ${:.2}\r\n", self.total_cost));
+        self.renderer
+            .render_usage("Total tokens", &self.total_usage);
+    }
+
+    /// Whether the worker has spent more than `--max-cost`.
+    fn over_budget(&self) -> bool {
+        self.max_cost.is_some_and(|max| self.total_cost > max)
+    }
+}
+
+/// Structured artifacts of a finished worker, for library consumers and tests.
+#[derive(Debug, Default)]
+pub struct WorkerReport {
+    /// Every message the renderer stored, across all phases.
+    pub messages: Vec<StoredMessage>,
+    /// One entry per agent run, in order.
+    pub phases: Vec<PhaseReport>,
+    pub total_cost: f64,
+}
+
+/// What a single agent phase produced.
+#[derive(Debug)]
+pub struct PhaseReport {
+    pub agent: String,
+    /// Result text of the phase's last completed turn (including transition retries).
+    pub result_text: String,
+    pub cost: f64,
+    pub session_id: Option<String>,
+    /// Wall-clock time from waiting for the agent's slot to the transition.
+    pub elapsed: Duration,
+    /// The part of `elapsed` spent waiting for the agent's slot.
+    pub lock_wait: Duration,
+    /// The agent's runs of `land.sh`, part of `elapsed`.
+    pub land: LandAttempts,
+}
+
+impl PhaseReport {
+    /// A phase that has just started, with nothing to report yet.
+    fn started(agent: &str) -> Self {
+        Self {
+            agent: agent.to_string(),
+            result_text: String::new(),
+            cost: 0.0,
+            session_id: None,
+            elapsed: Duration::ZERO,
+            lock_wait: Duration::ZERO,
+            land: LandAttempts::default(),
+        }
+    }
+}
+
+/// What to do with the worktree and its branch when the worker exits.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExitCleanup {
+    /// Remove them.
+    #[default]
+    Remove,
+    /// Leave them in place (`--keep-worktree`).
+    Keep,
+    /// Print the git commands that would remove them instead of running
+    /// them (`--dry-run-cleanup`).
+    DryRun,
+}
+
+/// How much of the dispatch → agent → land cycle the worker runs before
+/// exiting.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Cycles {
+    /// Keep cycling, sleeping whenever dispatch finds nothing to do.
+    #[default]
+    Loop,
+    /// A single cycle (`--once`).
+    Once,
+    /// Run dispatch, show what it decided, then exit without running the
+    /// agent (`--dispatch-only`).
+    DispatchOnly,
+}
+
+impl Cycles {
+    /// From the `--once` and `--dispatch-only` flags; the latter wins.
+    pub fn from_flags(once: bool, dispatch_only: bool) -> Self {
+        if dispatch_only {
+            Self::DispatchOnly
+        } else if once {
+            Self::Once
+        } else {
+            Self::Loop
+        }
+    }
 }
 
 pub struct WorkerConfig {
-    pub show_thinking: bool,
+    pub display: DisplayOptions,
+    /// `--debug-protocol`: where to log claude output that fails to parse.
+    /// Also enables the `:raw` input command.
+    pub protocol_log: Option<PathBuf>,
+    /// Restart claude when claude is silent this long after a steering message (`None` disables).
+    pub steering_timeout: Option<Duration>,
+    /// Stop once the worker has cost more than this many USD (`--max-cost`).
+    pub max_cost: Option<f64>,
+    /// Check connectivity before each phase (`--preflight-connectivity`).
+    pub preflight: bool,
+    /// Leave the permission mode to claude instead of defaulting to
+    /// `acceptEdits` (`--no-default-permission-mode`).
+    pub no_default_permission_mode: bool,
     pub branch: Option<String>,
-    pub worktree_base: PathBuf,
+    /// `--branch-prefix`: generated branch names become `<prefix>/<name>`.
+    pub branch_prefix: Option<String>,
+    /// `--branch-from`: start from, sync with and land on this branch
+    /// instead of main's.
+    pub branch_from: Option<String>,
+    /// `--worktree-base`; falls back to `.coven/config.toml`, then `~/.coven/worktrees`.
+    pub worktree_base: Option<PathBuf>,
+    /// `--agents-dir`: definitions here override the worktree's `.coven/agents`.
+    /// Relative paths are resolved against the repo when the worker starts.
+    pub agents_dir: Option<PathBuf>,
     pub extra_args: Vec<String>,
     /// Override for the project root directory (used by test recording).
     pub working_dir: Option<PathBuf>,
-    pub fork: bool,
-    pub reload: bool,
+    pub tag_flags: TagFlags,
     pub no_wait: bool,
-    /// Override terminal width for display truncation (used in tests).
-    pub term_width: Option<usize>,
+    pub cleanup: ExitCleanup,
+    pub cycles: Cycles,
+    /// Save what each dispatch session saw and answered (`--debug-dispatch`).
+    pub debug_dispatch: bool,
+    /// How long to wait for an agent's `max_concurrency` slot before saying
+    /// who holds it, and whether to take over a stale one (`--lock-timeout`,
+    /// `--steal-stale-lock`).
+    pub lock_wait: semaphore::LockWait,
+    /// End a phase after this many tool-call loop warnings (`--max-loop-warnings`).
+    pub max_loop_warnings: Option<usize>,
+    /// While sleeping, also wake on changes to main-worktree files matching
+    /// these patterns (`--wake-on`).
+    pub wake_on: Vec<String>,
+    /// Remote to push main to after an agent lands (`--push-after-land`).
+    pub push_after_land: Option<String>,
+    /// Command to run after an agent lands on main.
+    pub post_land: Option<PostLandHook>,
+    /// Socket streaming worker events to dashboards (`--control-socket`).
+    pub control_socket: Option<ControlSocket>,
 }
 
 /// Serializable args for VCR-recording `worktree::spawn`.
@@ -55,7 +295,21 @@ pub struct WorkerConfig {
 struct SpawnArgs {
     repo_path: String,
     branch: Option<String>,
+    /// Omitted when unset so older recordings still replay.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    branch_prefix: Option<String>,
     base_path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    branch_from: Option<String>,
+}
+
+/// Resolved worker paths, plus a warning if the worktree base looks slow.
+#[derive(Serialize, Deserialize)]
+struct WorkerPaths {
+    #[serde(flatten)]
+    spawn: SpawnArgs,
+    #[serde(default)]
+    base_warning: Option<BaseFilesystemWarning>,
 }
 
 /// Serializable args for VCR-recording `worker_state::update`.
@@ -65,6 +319,10 @@ struct WorkerUpdateArgs {
     branch: String,
     agent: Option<String>,
     args: HashMap<String, String>,
+    /// The worker's cost so far. Only `coven status` reads it, so it's left
+    /// out of the recording and older recordings still replay.
+    #[serde(skip)]
+    total_cost_usd: f64,
 }
 
 /// Serializable args for VCR-recording `semaphore::acquire`.
@@ -73,60 +331,131 @@ struct SemaphoreAcquireArgs {
     path: String,
     agent: String,
     max_concurrency: u32,
+    /// Recorded in the slot file for other workers to see. This and `wait`
+    /// are left out of the recording so older recordings still replay.
+    #[serde(skip)]
+    branch: String,
+    #[serde(skip)]
+    wait: semaphore::LockWait,
 }
 
-/// Run a worker: spawn a worktree, loop through the generic agent loop.
-pub async fn worker<W: Write>(
-    mut config: WorkerConfig,
-    io: &mut Io,
+/// Resolve the repo and worktree base paths and spawn the worker's worktree.
+///
+/// Also returns a warning to show if the worktree base is on a slower
+/// filesystem than the repo.
+async fn spawn_worktree(
+    config: &mut WorkerConfig,
     vcr: &VcrContext,
-    writer: W,
-) -> Result<()> {
-    // Headless: force-disable wait-for-user. There's no human to respond,
-    // so the model must not be told the feature exists, and any tag it
-    // emits anyway must be ignored rather than "dismissed" silently.
-    if io.is_headless() {
-        config.no_wait = true;
-    }
-    // Default to acceptEdits (same as other commands) unless the user
-    // specified a permission mode. The user is expected to set up persistent
-    // permissions for their project so agents can run unattended.
-    if !crate::session::runner::has_flag(&config.extra_args, "--permission-mode") {
-        config
-            .extra_args
-            .extend(["--permission-mode".to_string(), "acceptEdits".to_string()]);
-    }
-    if config.fork {
-        config.extra_args.extend(ForkConfig::disallowed_tool_args());
-    }
-
-    let configured_dir = config.working_dir.as_ref().map(|d| d.display().to_string());
-    let configured_base = config.worktree_base.display().to_string();
-    let spawn_args: SpawnArgs = vcr
+) -> Result<(SpawnResult, Option<String>)> {
+    let configured_dir = config
+        .working_dir
+        .as_deref()
+        .map(worktree::utf8_path)
+        .transpose()?
+        .map(str::to_string);
+    let paths: WorkerPaths = vcr
         .call("worker_paths", (), async |(): &()| {
             let repo_path = match configured_dir {
-                Some(s) => s,
-                None => std::env::current_dir()?.display().to_string(),
+                Some(s) => PathBuf::from(s),
+                None => std::env::current_dir()?,
             };
-            Ok(SpawnArgs {
-                repo_path,
-                branch: config.branch.clone(),
-                base_path: configured_base,
+            let repo_config = config::load(&repo_path)?;
+            let base = worktree_base::resolve(
+                config.worktree_base.as_deref(),
+                repo_config.worktree_base.as_deref(),
+                &repo_path,
+                worktree_base::home_default(),
+            )?;
+            let base_warning = worktree_base::check_filesystem(&repo_path, &base);
+            Ok(WorkerPaths {
+                spawn: SpawnArgs {
+                    repo_path: worktree::utf8_path(&repo_path)?.to_string(),
+                    branch: config.branch.clone(),
+                    branch_prefix: config.branch_prefix.clone(),
+                    base_path: worktree::utf8_path(&base)?.to_string(),
+                    branch_from: config.branch_from.clone(),
+                },
+                base_warning,
             })
         })
         .await?;
+    let warning = paths.base_warning.map(|w| {
+        format!(
+            "worktree base {} {w}, so worktrees (and copied ignored files like \
+             node_modules) may be slow. Pass --worktree-base DIR on the repo's disk, \
+             or set worktree_base in .coven/config.toml.",
+            paths.spawn.base_path
+        )
+    });
+    // Relative to the repo the worker was started from, not the worktree.
+    config.agents_dir = config
+        .agents_dir
+        .take()
+        .map(|dir| Path::new(&paths.spawn.repo_path).join(dir));
+    let spawn_args = paths.spawn;
     let spawn_result = vcr
         .call_typed_err("worktree::spawn", spawn_args, async |a: &SpawnArgs| {
             worktree::spawn(&SpawnOptions {
                 repo_path: Path::new(&a.repo_path),
                 branch: a.branch.as_deref(),
+                branch_prefix: a.branch_prefix.as_deref(),
                 base_path: Path::new(&a.base_path),
+                branch_from: a.branch_from.as_deref(),
             })
         })
         .await??;
+    Ok((spawn_result, warning))
+}
+
+/// Fill in what the worker implies beyond the flags it was given.
+fn apply_defaults(config: &mut WorkerConfig, io: &Io) {
+    // Headless: force-disable wait-for-user. There's no human to respond,
+    // so the model must not be told the feature exists, and any tag it
+    // emits anyway must be ignored rather than "dismissed" silently.
+    if io.is_headless() {
+        config.no_wait = true;
+    }
+    // Default to acceptEdits (same as other commands) unless the user
+    // specified a permission mode or left it to claude. The user is expected
+    // to set up persistent permissions for their project so agents can run
+    // unattended.
+    if !config.no_default_permission_mode && !has_flag(&config.extra_args, "--permission-mode") {
+        config
+            .extra_args
+            .extend(["--permission-mode".to_string(), "acceptEdits".to_string()]);
+    }
+    if config.tag_flags.fork {
+        config
+            .extra_args
+            .extend(ForkConfig::disallowed_tool_args(&[], None));
+    }
+}
+
+/// Run a worker: spawn a worktree, loop through the generic agent loop.
+pub async fn worker<W: Write>(
+    mut config: WorkerConfig,
+    io: &mut Io,
+    vcr: &VcrContext,
+    writer: W,
+) -> Result<WorkerReport> {
+    let permission_mode = apply_project_defaults(
+        vcr,
+        config.working_dir.as_deref(),
+        Section::Worker,
+        &mut config.display,
+        &mut config.max_cost,
+        &mut config.extra_args,
+        config.no_default_permission_mode,
+    )?;
+    apply_defaults(&mut config, io);
+    let (spawn_result, base_warning) = spawn_worktree(&mut config, vcr).await?;
 
     let raw = RawModeGuard::acquire(io)?;
-    let (mut renderer, mut input) = setup_display(writer, config.term_width, config.show_thinking);
+    let (mut renderer, mut input) =
+        config
+            .display
+            .setup(writer, vcr, config.protocol_log.is_some())?;
+    render_startup(&mut renderer, vcr, &permission_mode)?;
     render_initial_hints(&mut renderer, io, !config.no_wait);
 
     let wt_str = spawn_result.worktree_path.display().to_string();
@@ -144,11 +473,23 @@ pub async fn worker<W: Write>(
         spawn_result.branch,
         spawn_result.worktree_path.display()
     ));
+    if let Some(warning) = base_warning {
+        renderer.render_alert(&warning);
+    }
+    if let Some(control) = &config.control_socket {
+        control.emit(WorkerEvent::Started {
+            branch: spawn_result.branch.clone(),
+            worktree: spawn_result.worktree_path.clone(),
+        });
+    }
 
     let fork_config = ForkConfig::if_enabled(
-        config.fork,
+        config.tag_flags.fork,
         &config.extra_args,
         &Some(spawn_result.worktree_path.clone()),
+        config.tag_flags.fork_tools.as_ref(),
+        config.tag_flags.fork_concurrency,
+        config.no_default_permission_mode,
     );
 
     let mut ctx = PhaseContext {
@@ -157,8 +498,22 @@ pub async fn worker<W: Write>(
         io,
         vcr,
         fork_config: fork_config.as_ref(),
-        reload_enabled: config.reload,
+        reload_enabled: config.tag_flags.reload,
+        steering_timeout: config.steering_timeout,
+        max_cost: config.max_cost,
+        max_loop_warnings: config.max_loop_warnings,
+        protocol_log: config.protocol_log.as_deref(),
+        preflight: config.preflight,
+        no_wait: config.no_wait,
+        debug_dispatch: config.debug_dispatch,
+        lock_wait: config.lock_wait,
+        backoff: Backoff::default(),
         total_cost: 0.0,
+        total_usage: TokenUsage::default(),
+        phases: Vec::new(),
+        spans: Spans::default(),
+        undelivered: Vec::new(),
+        control: config.control_socket.as_ref(),
     };
 
     let result = worker_loop(
@@ -168,10 +523,36 @@ pub async fn worker<W: Write>(
         &mut ctx,
     )
     .await;
+    let phases = std::mem::take(&mut ctx.phases);
+    let total_cost = ctx.total_cost;
+    let shutting_down = io.shutdown_requested();
 
     drop(raw);
     renderer.set_title("");
+    if shutting_down {
+        renderer.write_raw("\r\nShutdown requested \u{2014} cleaning up.\r\n");
+    }
+    teardown(&mut renderer, vcr, &spawn_result, &config, shutting_down).await?;
 
+    result?;
+    Ok(WorkerReport {
+        messages: renderer.into_messages(),
+        phases,
+        total_cost,
+    })
+}
+
+/// Deregister the worker and remove its worktree (unless `--keep-worktree`
+/// or `--dry-run-cleanup` is set), warning if removal fails. On shutdown, a
+/// rebase left behind by a land cut short is aborted first.
+async fn teardown<W: Write>(
+    renderer: &mut Renderer<W>,
+    vcr: &VcrContext,
+    spawn_result: &SpawnResult,
+    config: &WorkerConfig,
+    shutting_down: bool,
+) -> Result<()> {
+    let wt_str = spawn_result.worktree_path.display().to_string();
     vcr.call(
         "worker_state::deregister",
         (wt_str.clone(), spawn_result.branch.clone()),
@@ -182,20 +563,136 @@ pub async fn worker<W: Write>(
     )
     .await?;
 
+    if config.cleanup == ExitCleanup::Keep {
+        renderer.write_raw(&format!(
+            "\r\nKeeping worktree (branch {}):\r\n  cd {wt_str}\r\n",
+            spawn_result.branch
+        ));
+        return Ok(());
+    }
+    if config.cleanup == ExitCleanup::DryRun {
+        let plan = vcr
+            .call_typed_err(
+                "worktree::remove_dry_run",
+                wt_str.clone(),
+                async |p: &String| worktree::remove(Path::new(p), false, Execution::DryRun),
+            )
+            .await?;
+        match plan {
+            Ok(plan) => {
+                renderer.write_raw("\r\nDry run \u{2014} removing the worktree would run:\r\n");
+                for command in plan {
+                    renderer.write_raw(&format!("  {command}\r\n"));
+                }
+            }
+            Err(e) => renderer.write_raw(&format!(
+                "\r\nWarning: failed to plan worktree removal: {e}\r\n"
+            )),
+        }
+        return Ok(());
+    }
+    if shutting_down {
+        abort_interrupted_rebase(renderer, vcr, &wt_str).await?;
+    }
     renderer.write_raw("\r\nRemoving worktree...\r\n");
-    if let Err(e) = vcr
-        .call_typed_err("worktree::remove", wt_str.clone(), async |p: &String| {
-            worktree::remove(Path::new(p), false)
-        })
-        .await?
-    {
+    let mut removed = remove_worktree(vcr, &wt_str).await?;
+    if removed.is_err() && trash_untracked(renderer, vcr, spawn_result).await? {
+        removed = remove_worktree(vcr, &wt_str).await?;
+    }
+    if let Err(e) = removed {
         renderer.write_raw(&format!(
             "Warning: failed to remove worktree: {e}\r\n\
              hint: git worktree remove --force {wt_str}\r\n"
         ));
     }
+    Ok(())
+}
 
-    result
+/// Remove the worker's worktree and delete its branch.
+async fn remove_worktree(
+    vcr: &VcrContext,
+    wt_str: &str,
+) -> Result<Result<(), worktree::WorktreeError>> {
+    vcr.call_typed_err(
+        "worktree::remove",
+        wt_str.to_string(),
+        async |p: &String| worktree::remove(Path::new(p), false, Execution::Run).map(drop),
+    )
+    .await
+}
+
+/// Move the files an agent left untracked, which keep `git worktree remove`
+/// from taking the worktree, into the trash instead of losing them. Returns
+/// whether anything moved.
+async fn trash_untracked<W: Write>(
+    renderer: &mut Renderer<W>,
+    vcr: &VcrContext,
+    spawn_result: &SpawnResult,
+) -> Result<bool> {
+    let Some(base) = spawn_result.worktree_base() else {
+        return Ok(false);
+    };
+    let timestamp = vcr.clock_ms()? / 1000;
+    let trash = worktree::trash_dir(base, &spawn_result.branch, timestamp);
+    let args = (
+        spawn_result.worktree_path.display().to_string(),
+        trash.display().to_string(),
+    );
+    let moved = vcr
+        .call_typed_err("worktree::clean", args, async |a: &(String, String)| {
+            worktree::clean(Path::new(&a.0), Path::new(&a.1))
+        })
+        .await?;
+    match moved {
+        Ok(moved) if !moved.is_empty() => {
+            renderer.write_raw(&format!(
+                "Moved untracked files to {}:\r\n",
+                trash.display()
+            ));
+            for path in moved {
+                renderer.write_raw(&format!("  {path}\r\n"));
+            }
+            Ok(true)
+        }
+        Ok(_) => Ok(false),
+        Err(e) => {
+            renderer.write_raw(&format!(
+                "Warning: failed to move untracked files to trash: {e}\r\n"
+            ));
+            Ok(false)
+        }
+    }
+}
+
+/// Abort the rebase a killed land left in progress, if any, so the worktree
+/// can be removed. Failures are warnings: removal reports its own.
+async fn abort_interrupted_rebase<W: Write>(
+    renderer: &mut Renderer<W>,
+    vcr: &VcrContext,
+    wt_str: &str,
+) -> Result<()> {
+    let in_progress = vcr
+        .call_typed_err(
+            "worktree::is_rebase_in_progress",
+            wt_str.to_string(),
+            async |p: &String| worktree::is_rebase_in_progress(Path::new(p)),
+        )
+        .await?;
+    if !matches!(in_progress, Ok(true)) {
+        return Ok(());
+    }
+    renderer.write_raw("Aborting the interrupted rebase...\r\n");
+    if let Err(e) = vcr
+        .call_typed_err(
+            "worktree::abort_rebase",
+            wt_str.to_string(),
+            async |p: &String| worktree::abort_rebase(Path::new(p)),
+        )
+        .await?
+    {
+        renderer.write_raw(&format!("Warning: failed to abort rebase: {e}\r\n"));
+    }
+    Ok(())
 }
 
 /// Generic agent loop: entry agent → parse transition → next agent → ...
@@ -218,25 +715,29 @@ async fn worker_loop<W: Write>(
         .await?;
 
     loop {
-        // Sync worktree to latest main so the entry agent sees current state
-        ctx.vcr
-            .call_typed_err(
-                "worktree::sync_to_main",
-                wt_str.clone(),
-                async |p: &String| worktree::sync_to_main(Path::new(p)),
-            )
-            .await?
-            .context("failed to sync worktree to main")?;
+        let sync_start = ctx.vcr.clock_ms()?;
+        sync_worktree(config, &wt_str, ctx).await?;
+        ctx.spans
+            .push(Span::between("sync", sync_start, ctx.vcr.clock_ms()?));
 
         // Get current HEAD SHA for sleep coordination
         let head_sha = vcr_main_head_sha(ctx.vcr, wt_str.clone()).await?;
 
-        // If a peer dispatch already decided to sleep at this HEAD, skip dispatch
-        let sleep_signal = vcr_read_sleep_signal(ctx.vcr, &wt_str).await?;
+        // If a peer dispatch already decided to sleep at this HEAD, skip
+        // dispatch — unless dispatch is all this worker is here to run.
+        let sleep_signal = if config.cycles == Cycles::DispatchOnly {
+            None
+        } else {
+            vcr_read_sleep_signal(ctx.vcr, &wt_str).await?
+        };
         if sleep_signal.as_deref() == Some(head_sha.as_str()) {
             ctx.renderer.write_raw(
                 "\r\nSkipping dispatch \u{2014} peer worker already decided to sleep at this commit.\r\n",
             );
+            if config.cycles != Cycles::Loop {
+                ctx.renderer.write_raw("\r\nNothing to do.\r\n");
+                return Ok(());
+            }
         } else {
             match run_agent_chain(
                 config,
@@ -248,10 +749,31 @@ async fn worker_loop<W: Write>(
             )
             .await?
             {
-                ChainResult::Sleep => {
-                    vcr_write_sleep_signal(ctx.vcr, &wt_str, &head_sha).await?;
+                result @ (ChainResult::Sleep | ChainResult::Saturated) => {
+                    if matches!(result, ChainResult::Sleep) {
+                        vcr_write_sleep_signal(ctx.vcr, &wt_str, &head_sha).await?;
+                    }
+                    if config.cycles != Cycles::Loop {
+                        let message = if matches!(result, ChainResult::Saturated) {
+                            "Nothing started \u{2014} dispatch's choices were all busy."
+                        } else if ctx.phases.len() <= 1 {
+                            // Only the entry agent ran: it found nothing to do.
+                            "Nothing to do \u{2014} dispatch chose to sleep."
+                        } else {
+                            "Cycle complete (--once)."
+                        };
+                        ctx.renderer.write_raw(&format!("\r\n{message}\r\n"));
+                        return Ok(());
+                    }
+                }
+                // Skipping leaves no sleep signal: peers still dispatch here.
+                ChainResult::Skipped => {
+                    ctx.renderer.write_raw("\r\nDispatch skipped.\r\n");
+                    if config.cycles != Cycles::Loop {
+                        return Ok(());
+                    }
                 }
-                ChainResult::Exited => return Ok(()),
+                ChainResult::CycleDone | ChainResult::Exited => return Ok(()),
             }
         }
 
@@ -260,18 +782,91 @@ async fn worker_loop<W: Write>(
             .set_title(&format!("cv sleeping \u{2014} {branch}"));
         ctx.renderer
             .write_raw("\r\nTransition: sleep \u{2014} waiting for new commits...\r\n");
+        ctx.emit(WorkerEvent::Sleeping { head: head_sha });
         ctx.io.clear_event_channel();
-        let wait = wait_for_new_commits(worktree_path, ctx.renderer, ctx.input, ctx.io, ctx.vcr);
+        let wait = wait_for_new_commits(
+            worktree_path,
+            &config.wake_on,
+            ctx.renderer,
+            ctx.input,
+            ctx.io,
+            ctx.vcr,
+        );
         if matches!(wait.await?, WaitOutcome::Exited) {
             return Ok(());
         }
+        ctx.emit(WorkerEvent::Woke);
     }
 }
 
+/// Bring the worktree up to date with main, or with the remote's main after
+/// fetching it (`--push-after-land`), so the entry agent sees current state.
+async fn sync_worktree<W: Write>(
+    config: &WorkerConfig,
+    wt_str: &str,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Result<()> {
+    let synced = match &config.push_after_land {
+        Some(remote) if fetch_main(remote, wt_str, ctx).await? => {
+            ctx.vcr
+                .call_typed_err(
+                    "worktree::sync_to_remote_main",
+                    (wt_str.to_string(), remote.clone()),
+                    async |a: &(String, String)| {
+                        worktree::sync_to_remote_main(Path::new(&a.0), &a.1)
+                    },
+                )
+                .await?
+        }
+        _ => {
+            ctx.vcr
+                .call_typed_err(
+                    "worktree::sync_to_main",
+                    wt_str.to_string(),
+                    async |p: &String| worktree::sync_to_main(Path::new(p)),
+                )
+                .await?
+        }
+    };
+    synced.context("failed to sync worktree to main")
+}
+
+/// Fetch main from `remote` (`--push-after-land`) so the worktree can sync
+/// to work pushed from other machines. Failing to fetch — offline, say — is
+/// only a warning. Returns whether the fetch worked.
+async fn fetch_main<W: Write>(
+    remote: &str,
+    wt_str: &str,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Result<bool> {
+    let fetched = ctx
+        .vcr
+        .call_typed_err(
+            "worktree::fetch_remote_main",
+            (wt_str.to_string(), remote.to_string()),
+            async |a: &(String, String)| worktree::fetch_remote_main(Path::new(&a.0), &a.1),
+        )
+        .await?;
+    if let Err(e) = &fetched {
+        ctx.renderer
+            .render_warning(&format!("couldn't fetch main from {remote}: {e}"));
+    }
+    Ok(fetched.is_ok())
+}
+
 /// Result of running an agent chain.
 enum ChainResult {
     /// Chain ended with a sleep transition — wait for new commits.
     Sleep,
+    /// Dispatch's choices were all at their `max_concurrency` limit: wait
+    /// for new commits without telling peers to sleep, since a peer may
+    /// find a free slot.
+    Saturated,
+    /// `--once`: the chain handed back to the entry agent, so the cycle is over.
+    CycleDone,
+    /// The user skipped this dispatch: wait for new commits without telling
+    /// peers to sleep.
+    Skipped,
     /// User exited.
     Exited,
 }
@@ -293,102 +888,108 @@ async fn run_agent_chain<W: Write>(
     let main_worktree_branch = vcr_main_branch_name(ctx.vcr, &wt_str).await?;
 
     let mut is_entry = true;
+    // Set when the budget ran out with unlanded commits: one more phase runs
+    // so they can land, then the worker stops.
+    let mut landing_over_budget = false;
     loop {
-        let agent_defs = vcr_load_agents(ctx.vcr, worktree_path).await?;
+        let agent_defs =
+            vcr_load_agents(ctx.vcr, worktree_path, config.agents_dir.as_deref()).await?;
 
         let agent_def = agent_defs
             .iter()
             .find(|a| a.name == agent_name)
             .with_context(|| format!("unknown agent: {agent_name}"))?;
 
-        let _semaphore_permit =
-            vcr_acquire_semaphore(ctx.vcr, &wt_str, &agent_name, agent_def).await?;
+        let phase_start = ctx.vcr.clock_ms()?;
+        let _semaphore_permit = acquire_slot(ctx, &wt_str, branch, &agent_name, agent_def).await?;
+        let acquired = ctx.vcr.clock_ms()?;
 
         // After acquiring the entry agent's semaphore, check if a peer dispatch
         // decided to sleep while we were waiting. This avoids redundant dispatch
         // runs when multiple workers wake simultaneously.
-        if is_entry {
-            is_entry = false;
+        if std::mem::take(&mut is_entry) && config.cycles != Cycles::DispatchOnly {
             let signal = vcr_read_sleep_signal(ctx.vcr, &wt_str).await?;
             if signal.as_deref() == Some(head_sha) {
                 return Ok(ChainResult::Sleep);
             }
         }
 
-        vcr_update_worker_state(ctx.vcr, &wt_str, branch, Some(&agent_name), &agent_args).await?;
+        vcr_update_worker_state(ctx, &wt_str, branch, Some(&agent_name), &agent_args).await?;
 
-        let agent_prompt = agent_def.render(&agent_args)?;
+        let is_dispatch = agent_name == entry_agent;
+        let agent_prompt =
+            render_agent_prompt(ctx.vcr, &wt_str, agent_def, &agent_args, is_dispatch).await?;
 
-        // Merge per-agent claude_args with worker-level extra_args.
-        // Agent args come first, worker args last (CLI-level `-- [ARGS]` can override).
-        let mut merged_args = agent_def.frontmatter.claude_args.clone();
-        merged_args.extend(config.extra_args.iter().cloned());
+        let merged_args = merge_agent_args(&agent_def.frontmatter.claude_args, &config.extra_args);
 
-        let transition_prompt =
-            transition::format_transition_system_prompt(&agent_defs, config.no_wait);
-        let all_workers = ctx
-            .vcr
-            .call(
-                "worker_state::read_all",
-                wt_str.clone(),
-                async |p: &String| worker_state::read_all(Path::new(p)),
-            )
-            .await?;
-        let others: Vec<_> = all_workers.iter().filter(|s| s.branch != branch).collect();
-        let worker_status_section = if others.is_empty() {
-            "\n\nNo other workers active.".to_string()
-        } else {
-            format!(
-                "\n\n## Worker Status\n\n{}",
-                worker_state::format_workers(&others, worker_state::StatusStyle::Dispatch)
-            )
-        };
+        let worker_status_section = vcr_worker_status_section(ctx.vcr, &wt_str, branch).await?;
         let system_prompt = build_system_prompt(
             &system_doc,
-            &transition_prompt,
+            &transition::format_transition_system_prompt(&agent_defs, config.no_wait),
             &worker_status_section,
             &main_worktree_branch,
             ctx.fork_config,
         );
 
-        ctx.renderer
-            .write_raw(&format!("\r\n=== Agent: {agent_name} ===\r\n\r\n"));
-        let title_suffix = match agent_def.render_title(&agent_args)? {
-            Some(t) => format!("{agent_name}: {t}"),
-            None => match format_args_display(&agent_args) {
-                d if d.is_empty() => agent_name.clone(),
-                d => format!("{agent_name} {d}"),
-            },
-        };
-        ctx.renderer
-            .set_title(&format!("cv {title_suffix} \u{2014} {branch}"));
+        announce_phase(ctx, agent_def, &agent_args, branch);
 
-        let parsed_transition = run_phase_with_wait(
+        // Main before the phase, to tell whether the phase landed on it.
+        let before = vcr_main_head_sha(ctx.vcr, wt_str.clone()).await?;
+        ctx.phases.push(PhaseReport::started(&agent_name));
+        let dispatch = Dispatch {
+            branch,
+            worker_status: &worker_status_section,
+        };
+        let (parsed_transition, sleep) = match run_phase_with_wait(
             &agent_prompt,
             worktree_path,
             &merged_args,
             &system_prompt,
             &agent_defs,
-            config.no_wait,
+            is_dispatch.then_some(&dispatch),
             ctx,
         )
-        .await?;
-        let Some(parsed_transition) = parsed_transition else {
-            return Ok(ChainResult::Exited);
+        .await?
+        {
+            PhaseEnd::Transition(transition) => (transition, ChainResult::Sleep),
+            PhaseEnd::Saturated => (Transition::Sleep, ChainResult::Saturated),
+            PhaseEnd::Skipped => {
+                return end_chain(ctx, &wt_str, branch, ChainResult::Skipped).await;
+            }
+            PhaseEnd::Exited => return Ok(ChainResult::Exited),
         };
+        ctx.time_phase(&agent_name, phase_start, acquired)?;
+        if !after_land(config, &before, worktree_path, branch, &agent_name, ctx).await? {
+            return Ok(ChainResult::Exited);
+        }
+        emit_phase_end(ctx, &agent_name, &parsed_transition);
+        if config.cycles == Cycles::DispatchOnly {
+            return end_dispatch_only(ctx, &dispatch, &wt_str, &parsed_transition).await;
+        }
+
+        if ctx.over_budget() {
+            if landing_over_budget || !over_budget_landing(&parsed_transition, &wt_str, ctx).await?
+            {
+                return Ok(ChainResult::Exited);
+            }
+            landing_over_budget = true;
+        }
 
         match parsed_transition {
+            Transition::Next { agent, .. }
+                if config.cycles == Cycles::Once && agent == entry_agent =>
+            {
+                ctx.renderer.write_raw("\r\nCycle complete (--once).\r\n");
+                return end_chain(ctx, &wt_str, branch, ChainResult::CycleDone).await;
+            }
             Transition::Next { agent, args } => {
-                let args_display = format_args_display(&args);
+                let args_display = agents::format_args_display(&args);
                 ctx.renderer
                     .write_raw(&format!("\r\nTransition: {agent} {args_display}\r\n"));
                 agent_name = agent;
                 agent_args = args;
             }
-            Transition::Sleep => {
-                vcr_update_worker_state(ctx.vcr, &wt_str, branch, None, &HashMap::new()).await?;
-                return Ok(ChainResult::Sleep);
-            }
+            Transition::Sleep => return end_chain(ctx, &wt_str, branch, sleep).await,
             Transition::WaitForUser { .. } => {
                 bail!("unexpected WaitForUser transition in agent chain")
             }
@@ -396,20 +997,386 @@ async fn run_agent_chain<W: Write>(
     }
 }
 
+/// The chain stopped running agents: clear the worker's current agent.
+async fn end_chain<W: Write>(
+    ctx: &PhaseContext<'_, W>,
+    wt_str: &str,
+    branch: &str,
+    result: ChainResult,
+) -> Result<ChainResult> {
+    vcr_update_worker_state(ctx, wt_str, branch, None, &HashMap::new()).await?;
+    Ok(result)
+}
+
+/// Decide whether an over-budget worker runs one more phase: only when the
+/// agent handed off to another agent and the branch has commits that would
+/// otherwise never land.
+async fn over_budget_landing<W: Write>(
+    transition: &Transition,
+    wt_str: &str,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Result<bool> {
+    let Transition::Next { agent, .. } = transition else {
+        return Ok(false);
+    };
+    let has_commits = ctx
+        .vcr
+        .call_typed_err(
+            "worktree::has_unique_commits",
+            wt_str.to_string(),
+            async |p: &String| worktree::has_unique_commits(Path::new(p)),
+        )
+        .await??;
+    if has_commits {
+        ctx.renderer.write_raw(&format!(
+            "\r\nOver budget with unlanded commits \u{2014} running {agent} to land them, then stopping.\r\n"
+        ));
+    } else {
+        ctx.renderer.write_raw(&format!(
+            "\r\nOver budget with nothing to land \u{2014} skipping {agent}.\r\n"
+        ));
+    }
+    Ok(has_commits)
+}
+
+/// Show the timing line for a phase that ended, and tell control socket
+/// clients which transition it chose.
+fn emit_phase_end<W: Write>(ctx: &mut PhaseContext<'_, W>, agent: &str, transition: &Transition) {
+    ctx.show_timing();
+    let phase = ctx.phases.last();
+    ctx.emit(WorkerEvent::PhaseEnded {
+        agent: agent.to_string(),
+        cost: phase.map_or(0.0, |p| p.cost),
+        elapsed_ms: phase.map_or(0, |p| {
+            u64::try_from(p.elapsed.as_millis()).unwrap_or(u64::MAX)
+        }),
+    });
+    let (next, args) = match transition {
+        Transition::Next { agent, args } => (Some(agent.clone()), args.clone()),
+        Transition::Sleep => (None, HashMap::new()),
+        Transition::WaitForUser { .. } => return,
+    };
+    ctx.emit(WorkerEvent::Transition {
+        from: agent.to_string(),
+        next,
+        args,
+    });
+}
+
+/// `--dispatch-only`: show what dispatch chose, the `<next>` block it wrote
+/// and the worker status it was shown, then end the chain without running
+/// the agent.
+async fn end_dispatch_only<W: Write>(
+    ctx: &mut PhaseContext<'_, W>,
+    dispatch: &Dispatch<'_>,
+    wt_str: &str,
+    transition: &Transition,
+) -> Result<ChainResult> {
+    let mut out = String::from("\r\nDispatch decision (--dispatch-only):\r\n");
+    match transition {
+        Transition::Next { agent, args } => {
+            let _ = write!(out, "  agent: {agent}\r\n");
+            if !args.is_empty() {
+                let args = agents::format_args_display(args);
+                let _ = write!(out, "  args:  {args}\r\n");
+            }
+        }
+        Transition::Sleep => out.push_str("  sleep: true\r\n"),
+        Transition::WaitForUser { .. } => {}
+    }
+    let result_text = ctx.phases.last().map_or("", |p| p.result_text.as_str());
+    // Dispatch's last answer: after a `max_concurrency` fallback to sleep it
+    // still names the busy agent.
+    if let Some(block) = crate::protocol::parse::extract_tag_inner(result_text, "next") {
+        out.push_str("\r\nRaw <next> block:\r\n");
+        let block = format!("<next>{block}</next>");
+        for line in block.lines() {
+            let _ = write!(out, "  {line}\r\n");
+        }
+    }
+    out.push_str("\r\nWorker status it saw:\r\n");
+    for line in dispatch.worker_status.trim().lines() {
+        let _ = write!(out, "  {line}\r\n");
+    }
+    ctx.renderer.write_raw(&out);
+    end_chain(ctx, wt_str, dispatch.branch, ChainResult::CycleDone).await
+}
+
+/// Show the phase header and terminal title, and tell control socket clients.
+fn announce_phase<W: Write>(
+    ctx: &mut PhaseContext<'_, W>,
+    agent: &AgentDef,
+    args: &HashMap<String, String>,
+    branch: &str,
+) {
+    let title = agent.display_title(args);
+    ctx.renderer
+        .write_raw(&format!("\r\n=== Agent: {title} ===\r\n\r\n"));
+    ctx.renderer
+        .set_title(&format!("cv {title} \u{2014} {branch}"));
+    ctx.emit(WorkerEvent::PhaseStarted {
+        agent: agent.name.clone(),
+        title,
+        args: args.clone(),
+    });
+}
+
+/// If the phase that just finished landed on main, list what landed, then
+/// push main and run the post-land hook.
+///
+/// Returns `false` if the user exited while paused on a failed push or
+/// required hook.
+async fn after_land<W: Write>(
+    config: &WorkerConfig,
+    main_before: &str,
+    worktree_path: &Path,
+    branch: &str,
+    agent: &str,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Result<bool> {
+    let wt_str = worktree_path.display().to_string();
+    let landed: Option<Landed> = ctx
+        .vcr
+        .call(
+            "post_land::detect_land",
+            (wt_str.clone(), main_before.to_string()),
+            async |a: &(String, String)| post_land::detect_land(Path::new(&a.0), &a.1),
+        )
+        .await?;
+    let Some(landed) = landed else {
+        return Ok(true);
+    };
+
+    let mut info = LandInfo {
+        branch: branch.to_string(),
+        main_branch: landed.main_branch,
+        landed_sha: landed.sha,
+        agent: agent.to_string(),
+        cost: ctx.phases.last().map_or(0.0, |p| p.cost),
+    };
+    ctx.emit(WorkerEvent::Landed {
+        agent: info.agent.clone(),
+        sha: info.landed_sha.clone(),
+        main_branch: info.main_branch.clone(),
+    });
+    let short_sha: String = info.landed_sha.chars().take(7).collect();
+    ctx.renderer.write_raw(&format!(
+        "\r\nLanded {short_sha} on {}:\r\n",
+        info.main_branch
+    ));
+    ctx.renderer
+        .render_land_summary(&landed.summary, post_land::SUMMARY_COMMITS);
+
+    if let Some(remote) = &config.push_after_land {
+        if !push_after_land(remote, worktree_path, ctx).await? {
+            return Ok(false);
+        }
+        if config.post_land.is_some() {
+            // Landing again on the remote's main rewrites the landed commits.
+            info.landed_sha = vcr_main_head_sha(ctx.vcr, wt_str).await?;
+        }
+    }
+    let Some(hook) = &config.post_land else {
+        return Ok(true);
+    };
+    run_post_land(hook, info, landed.main_path, ctx).await
+}
+
+/// How many times a rejected push is landed again on the remote's main
+/// before the worker pauses.
+const MAX_RELANDS: usize = 3;
+
+/// Push main to `remote` after a land (`--push-after-land`). A rejected
+/// push means another machine pushed first: land again on top of the
+/// remote's main and retry. Other failures — usually authentication —
+/// pause the worker until the user presses Enter to retry.
+///
+/// Returns `false` if the user exited while paused.
+async fn push_after_land<W: Write>(
+    remote: &str,
+    worktree_path: &Path,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Result<bool> {
+    let args = (worktree_path.display().to_string(), remote.to_string());
+    let mut relands = 0;
+    loop {
+        let outcome = ctx
+            .vcr
+            .call_typed_err(
+                "worktree::push_main",
+                args.clone(),
+                async |a: &(String, String)| worktree::push_main(Path::new(&a.0), &a.1),
+            )
+            .await??;
+        let failure = match outcome {
+            PushOutcome::Pushed => {
+                ctx.renderer.write_raw(&format!("Pushed to {remote}.\r\n"));
+                return Ok(true);
+            }
+            PushOutcome::Rejected if relands < MAX_RELANDS => {
+                relands += 1;
+                ctx.renderer.write_raw(&format!(
+                    "{remote} has new commits \u{2014} landing again on top of them...\r\n"
+                ));
+                let relanded = ctx
+                    .vcr
+                    .call_typed_err(
+                        "worktree::reland_onto_remote",
+                        args.clone(),
+                        async |a: &(String, String)| {
+                            worktree::reland_onto_remote(Path::new(&a.0), &a.1)
+                        },
+                    )
+                    .await??;
+                if relanded {
+                    continue;
+                }
+                format!("couldn't land on top of {remote}'s main; reconcile them by hand")
+            }
+            PushOutcome::Rejected => format!("{remote} kept rejecting the push"),
+            PushOutcome::Failed(error) => format!("push to {remote} failed: {error}"),
+        };
+        ctx.renderer.render_warning(&failure);
+        if ctx.io.is_headless() {
+            bail!("{failure} (--push-after-land)");
+        }
+        if !wait_for_enter(failure, ctx).await? {
+            return Ok(false);
+        }
+        ctx.emit(WorkerEvent::Resumed);
+        relands = 0;
+    }
+}
+
+/// Run the post-land hook for a land [`after_land`] announced.
+///
+/// Returns `false` if the user exited while paused on a failed required hook.
+async fn run_post_land<W: Write>(
+    hook: &PostLandHook,
+    info: LandInfo,
+    main_path: PathBuf,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Result<bool> {
+    ctx.renderer.write_raw("Running post-land hook...\r\n");
+    let args = post_land::RunArgs {
+        command: hook.command.clone(),
+        dir: main_path,
+        info,
+        timeout_secs: hook.timeout.as_secs(),
+    };
+    let hook_start = ctx.vcr.clock_ms()?;
+    let output: HookOutput = ctx
+        .vcr
+        .call("post_land::run", args, async |a: &post_land::RunArgs| {
+            post_land::run(
+                &a.command,
+                &a.dir,
+                &a.info,
+                Duration::from_secs(a.timeout_secs),
+            )
+            .await
+        })
+        .await?;
+    ctx.spans
+        .push(Span::between("post-land", hook_start, ctx.vcr.clock_ms()?));
+    ctx.renderer
+        .render_hook_output(&output.output, post_land::PREVIEW_LINES);
+
+    let Some(failure) = output.failure(hook.timeout) else {
+        return Ok(true);
+    };
+    ctx.renderer.render_warning(&failure);
+    if !hook.required {
+        return Ok(true);
+    }
+    if ctx.io.is_headless() {
+        bail!("{failure} (--post-land-required)");
+    }
+    let resumed = wait_for_enter(failure, ctx).await?;
+    if resumed {
+        ctx.emit(WorkerEvent::Resumed);
+    }
+    Ok(resumed)
+}
+
+/// Pause for `reason` until the user presses Enter (`true`) or Ctrl+C /
+/// Ctrl+D (`false`).
+async fn wait_for_enter<W: Write>(reason: String, ctx: &mut PhaseContext<'_, W>) -> Result<bool> {
+    let message = format!("worker paused: {reason}");
+    ctx.emit(WorkerEvent::Paused { reason });
+    ctx.renderer.write_raw("\x07");
+    crate::notify::waiting(&message, ctx.io, ctx.renderer, ctx.vcr).await?;
+    ctx.renderer
+        .write_raw("Press Enter to continue, Ctrl+C to exit.\r\n");
+    ctx.io.clear_event_channel();
+    ctx.vcr.call("idle", (), async |(): &()| Ok(())).await?;
+    loop {
+        let event = ctx
+            .vcr
+            .call("next_event", (), async |(): &()| ctx.io.next_event().await)
+            .await?;
+        if matches!(event, IoEvent::Shutdown) {
+            ctx.io.note_shutdown();
+            return Ok(false);
+        }
+        if let IoEvent::Terminal(Event::Key(key)) = event {
+            match key.code {
+                KeyCode::Enter => return Ok(true),
+                KeyCode::Char('c' | 'd') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(false);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// How many times dispatch is asked to choose again when it picks an agent
+/// that's at its `max_concurrency` limit, before the worker sleeps instead.
+const MAX_SATURATED_RETRIES: usize = 2;
+
+/// The dispatch phase's place in the worker, for checking its choice.
+struct Dispatch<'a> {
+    branch: &'a str,
+    /// The worker status section of its system prompt.
+    worker_status: &'a str,
+}
+
+/// How a phase ended, once its waits and retries are done.
+enum PhaseEnd {
+    Transition(Transition),
+    /// Dispatch kept choosing agents at their `max_concurrency` limit: sleep,
+    /// though not because there's nothing to do at this commit.
+    Saturated,
+    /// The user skipped the dispatch phase (see [`PhaseOutcome::Skipped`]).
+    Skipped,
+    /// The user exited.
+    Exited,
+}
+
+impl From<Option<Transition>> for PhaseEnd {
+    fn from(transition: Option<Transition>) -> Self {
+        transition.map_or(Self::Exited, Self::Transition)
+    }
+}
+
 /// Run a phase session, looping on `WaitForUser` transitions.
 ///
 /// If the agent outputs `<wait-for-user>`, we wait for user input, resume the
 /// session, and repeat until we get a `Next` or `Sleep` transition.
-/// Returns `None` if the user exited.
+/// `dispatch` is set in the dispatch phase, where picking an agent other
+/// workers already run at its `max_concurrency` limit resumes the session to
+/// choose again, `--debug-dispatch` saves what the session saw, and Ctrl+D
+/// at an interrupt skips the dispatch.
 async fn run_phase_with_wait<W: Write>(
     initial_prompt: &str,
     worktree_path: &Path,
     extra_args: &[String],
     system_prompt: &str,
     agents: &[AgentDef],
-    no_wait: bool,
+    dispatch: Option<&Dispatch<'_>>,
     ctx: &mut PhaseContext<'_, W>,
-) -> Result<Option<Transition>> {
+) -> Result<PhaseEnd> {
     let base_config = build_phase_config(
         worktree_path,
         extra_args,
@@ -419,49 +1386,57 @@ async fn run_phase_with_wait<W: Write>(
     );
     let mut phase_prompt = initial_prompt.to_string();
     let mut phase_resume: Option<String> = None;
+    let mut saturated_retries = 0;
+    let mut debug = DispatchDebug::start(ctx, dispatch, initial_prompt, system_prompt);
 
     loop {
+        let outcome = run_phase_session(
+            &phase_prompt,
+            &base_config,
+            phase_resume.as_deref(),
+            dispatch.is_some(),
+            ctx,
+        )
+        .await?;
         let PhaseOutcome::Completed {
             result_text,
             cost,
             session_id,
             wait_requested,
-        } = run_phase_session(&phase_prompt, &base_config, phase_resume.as_deref(), ctx).await?
+        } = outcome
         else {
-            return Ok(None);
+            return Ok(match outcome {
+                PhaseOutcome::Skipped => PhaseEnd::Skipped,
+                _ => PhaseEnd::Exited,
+            });
         };
 
-        ctx.total_cost += cost;
-        ctx.renderer
-            .write_raw(&format!("  Total cost: ${:.2}\r\n", ctx.total_cost));
+        ctx.add_to_totals(cost);
+        if let Some(debug) = &mut debug {
+            debug.record_output(&result_text, ctx).await?;
+        }
+
+        // Over budget: no more sessions, so take the transition as written,
+        // without waiting for the user or retrying a malformed one.
+        if ctx.over_budget() {
+            return Ok(transition::parse_transition(&result_text)
+                .ok()
+                .filter(|t| !matches!(t, Transition::WaitForUser { .. }))
+                .into());
+        }
 
         // User pressed Ctrl+W — wait for input before parsing/following transition.
-        // Escape dismisses the wait and falls through to transition parsing.
+        // Escape (or Ctrl+W again) dismisses the wait and falls through to
+        // transition parsing.
         if wait_requested {
-            ctx.renderer.write_raw("\x07");
-            ctx.renderer.write_raw("\r\n[waiting for user input]\r\n");
-            let sid = session_id
-                .as_deref()
-                .context("no session ID for wait resume")?;
-            match event_loop::wait_for_dismissable_input(
-                ctx.input,
-                ctx.renderer,
-                ctx.io,
-                ctx.vcr,
-                sid,
-                &base_config,
-            )
-            .await?
-            {
+            match wait_after_ctrl_w(session_id.as_deref(), &base_config, ctx).await? {
                 Some(event_loop::WaitInterruptResult::Text(user_text)) => {
                     phase_prompt = user_text;
                     phase_resume = session_id;
                     continue;
                 }
-                Some(event_loop::WaitInterruptResult::Dismissed) => {
-                    // Fall through to parse transitions from the last result.
-                }
-                None => return Ok(None),
+                Some(event_loop::WaitInterruptResult::Dismissed) => {}
+                _ => return Ok(PhaseEnd::Exited),
             }
         }
 
@@ -472,48 +1447,271 @@ async fn run_phase_with_wait<W: Write>(
             agents,
             ctx,
         )
-        .await?
-        else {
-            return Ok(None);
-        };
+        .await?
+        else {
+            return Ok(PhaseEnd::Exited);
+        };
+
+        match transition {
+            Transition::WaitForUser { reason } => {
+                let sid = session_id
+                    .as_deref()
+                    .context("no session ID for wait-for-user resume")?;
+                let Some(prompt) = wait_for_user(reason, sid, &base_config, ctx).await? else {
+                    return Ok(PhaseEnd::Exited);
+                };
+                phase_prompt = prompt;
+                phase_resume = Some(sid.to_string());
+            }
+            other => {
+                let Some(Dispatch { branch, .. }) = dispatch else {
+                    return Ok(PhaseEnd::Transition(other));
+                };
+                if let Some(debug) = &mut debug {
+                    debug.save_decision(&other, worktree_path, ctx).await?;
+                }
+                let saturated =
+                    vcr_saturated_choice(ctx.vcr, worktree_path, branch, agents, &other).await?;
+                if saturated.is_empty() {
+                    return Ok(PhaseEnd::Transition(other));
+                }
+                let Some(prompt) = choose_again(&saturated, &mut saturated_retries, ctx) else {
+                    return Ok(PhaseEnd::Saturated);
+                };
+                phase_prompt = prompt;
+                phase_resume = Some(session_id.context("no session ID for max_concurrency retry")?);
+            }
+        }
+    }
+}
+
+/// `--debug-dispatch`: the record of a dispatch cycle, saved as it goes.
+struct DispatchDebug {
+    record: DispatchRecord,
+    /// The cycle's directory, once it's been saved.
+    dir: Option<String>,
+}
+
+impl DispatchDebug {
+    /// Start a record for a dispatch phase, if `--debug-dispatch` is on.
+    fn start<W: Write>(
+        ctx: &PhaseContext<'_, W>,
+        dispatch: Option<&Dispatch<'_>>,
+        prompt: &str,
+        system_prompt: &str,
+    ) -> Option<Self> {
+        let dispatch = dispatch.filter(|_| ctx.debug_dispatch)?;
+        Some(Self {
+            record: DispatchRecord {
+                branch: dispatch.branch.to_string(),
+                prompt: prompt.to_string(),
+                system_prompt: system_prompt.to_string(),
+                worker_status: dispatch.worker_status.to_string(),
+                outputs: Vec::new(),
+            },
+            dir: None,
+        })
+    }
+
+    /// Add a session's output, saving straight away if it has no
+    /// transition the worker can follow.
+    async fn record_output<W: Write>(
+        &mut self,
+        result_text: &str,
+        ctx: &mut PhaseContext<'_, W>,
+    ) -> Result<()> {
+        self.record.outputs.push(result_text.to_string());
+        if transition::parse_transition(result_text).is_err() {
+            self.save_and_show(ctx).await?;
+        }
+        Ok(())
+    }
+
+    /// Write the record so far, keeping to one directory per cycle.
+    async fn save<W: Write>(&mut self, ctx: &PhaseContext<'_, W>) -> Result<String> {
+        let args = (self.dir.clone(), self.record.clone());
+        let dir = ctx
+            .vcr
+            .call(
+                "dispatch_debug::write",
+                args,
+                async |(dir, record): &(Option<String>, DispatchRecord)| {
+                    let root = dispatch_debug::home_default()
+                        .context("can't find the home directory for --debug-dispatch")?;
+                    let dir = dispatch_debug::write(&root, dir.as_deref().map(Path::new), record)?;
+                    Ok(dir.display().to_string())
+                },
+            )
+            .await?;
+        self.dir = Some(dir.clone());
+        Ok(dir)
+    }
+
+    async fn save_and_show<W: Write>(&mut self, ctx: &mut PhaseContext<'_, W>) -> Result<()> {
+        let dir = self.save(ctx).await?;
+        ctx.renderer.write_raw(&format!(
+            "\r\nDispatch inputs and output saved to {dir}\r\n"
+        ));
+        Ok(())
+    }
+
+    /// Save the cycle once dispatch has decided, pointing at the record if
+    /// it picked an agent another worker is already running.
+    async fn save_decision<W: Write>(
+        &mut self,
+        transition: &Transition,
+        worktree_path: &Path,
+        ctx: &mut PhaseContext<'_, W>,
+    ) -> Result<()> {
+        let Transition::Next { agent, .. } = transition else {
+            self.save(ctx).await?;
+            return Ok(());
+        };
+        let states = ctx
+            .vcr
+            .call(
+                "worker_state::read_all",
+                worktree_path.display().to_string(),
+                async |p: &String| worker_state::read_all(Path::new(p)),
+            )
+            .await?;
+        let running_elsewhere = states
+            .iter()
+            .any(|s| s.branch != self.record.branch && s.agent.as_ref() == Some(agent));
+        if running_elsewhere {
+            ctx.renderer.write_raw(&format!(
+                "\r\nDispatch chose {agent}, which another worker is already running."
+            ));
+            self.save_and_show(ctx).await
+        } else {
+            self.save(ctx).await.map(drop)
+        }
+    }
+}
+
+/// Handle a `<wait-for-user>` transition: wait for the user's reply, or with
+/// `--no-wait` re-prompt for a `<next>` tag instead. Returns the prompt to
+/// resume the session with, or `None` if the user exited.
+async fn wait_for_user<W: Write>(
+    reason: String,
+    session_id: &str,
+    base_config: &SessionConfig,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Result<Option<String>> {
+    if ctx.no_wait {
+        // --no-wait: skip waiting, re-prompt for a valid transition.
+        ctx.renderer.write_raw(&format!(
+            "\r\nIgnoring wait-for-user ({reason}) — re-prompting\r\n"
+        ));
+        return Ok(Some(
+            "The <wait-for-user> tag is disabled. You must output a <next> \
+             transition to hand off to another agent, or <next> with sleep: true \
+             if no work is available."
+                .to_string(),
+        ));
+    }
+    ctx.renderer.render_wait_for_user(&reason);
+    ctx.emit(WorkerEvent::Paused { reason });
+    let user_text = event_loop::wait_for_interrupt_input(
+        ctx.input,
+        ctx.renderer,
+        ctx.io,
+        ctx.vcr,
+        session_id,
+        base_config,
+    )
+    .await?;
+    if user_text.is_some() {
+        ctx.emit(WorkerEvent::Resumed);
+    }
+    Ok(user_text)
+}
+
+/// Dispatch chose agents already at `max_concurrency`: the prompt asking it
+/// to choose again, or `None` to sleep once the retries are used up.
+fn choose_again<W: Write>(
+    saturated: &[(&str, u32)],
+    retries: &mut usize,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Option<String> {
+    let names: Vec<_> = saturated.iter().map(|(name, _)| *name).collect();
+    let names = names.join(", ");
+    if *retries == MAX_SATURATED_RETRIES {
+        ctx.renderer.write_raw(&format!(
+            "\r\nAt max_concurrency: {names} \u{2014} sleeping\r\n"
+        ));
+        return None;
+    }
+    *retries += 1;
+    ctx.renderer.write_raw(&format!(
+        "\r\nAt max_concurrency: {names} \u{2014} choosing again ({retries}/{MAX_SATURATED_RETRIES})...\r\n\r\n"
+    ));
+    Some(transition::saturated_prompt(saturated))
+}
+
+/// If `transition` picks an agent with a `max_concurrency` limit, check
+/// whether other workers already run it that many times. Returns the
+/// saturated agents when the chosen one is among them, otherwise nothing.
+async fn vcr_saturated_choice<'a>(
+    vcr: &VcrContext,
+    worktree_path: &Path,
+    branch: &str,
+    agents: &'a [AgentDef],
+    transition: &Transition,
+) -> Result<Vec<(&'a str, u32)>> {
+    let Transition::Next { agent, .. } = transition else {
+        return Ok(Vec::new());
+    };
+    let limited = agents
+        .iter()
+        .any(|a| a.name == *agent && a.frontmatter.max_concurrency.is_some());
+    if !limited {
+        return Ok(Vec::new());
+    }
+    let states = vcr
+        .call(
+            "worker_state::read_all",
+            worktree_path.display().to_string(),
+            async |p: &String| worker_state::read_all(Path::new(p)),
+        )
+        .await?;
+    let saturated = worker_state::saturated_agents(agents, &states, branch);
+    Ok(if saturated.iter().any(|(name, _)| name == agent) {
+        saturated
+    } else {
+        Vec::new()
+    })
+}
 
-        match transition {
-            Transition::WaitForUser { reason } => {
-                let sid = session_id
-                    .as_deref()
-                    .context("no session ID for wait-for-user resume")?;
-                if no_wait {
-                    // --no-wait: skip waiting, re-prompt for a valid transition.
-                    ctx.renderer.write_raw(&format!(
-                        "\r\nIgnoring wait-for-user ({reason}) — re-prompting\r\n"
-                    ));
-                    phase_prompt = "The <wait-for-user> tag is disabled. You must output a <next> \
-                         transition to hand off to another agent, or <next> with sleep: true \
-                         if no work is available."
-                        .to_string();
-                } else {
-                    ctx.renderer.write_raw("\x07");
-                    ctx.renderer
-                        .write_raw(&format!("\r\nWaiting for user: {reason}\r\n"));
-                    let Some(user_text) = event_loop::wait_for_interrupt_input(
-                        ctx.input,
-                        ctx.renderer,
-                        ctx.io,
-                        ctx.vcr,
-                        sid,
-                        &base_config,
-                    )
-                    .await?
-                    else {
-                        return Ok(None);
-                    };
-                    phase_prompt = user_text;
-                }
-                phase_resume = Some(sid.to_string());
-            }
-            other => return Ok(Some(other)),
-        }
+/// Wait for input after a phase the user asked to pause with Ctrl+W.
+///
+/// Returns `None` if the user exited.
+async fn wait_after_ctrl_w<W: Write>(
+    session_id: Option<&str>,
+    base_config: &SessionConfig,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Result<Option<event_loop::WaitInterruptResult>> {
+    ctx.renderer.render_wait_requested();
+    ctx.emit(WorkerEvent::Paused {
+        reason: "wait requested (Ctrl+W)".to_string(),
+    });
+    let sid = session_id.context("no session ID for wait resume")?;
+    let result = event_loop::wait_for_dismissable_input(
+        ctx.input,
+        ctx.renderer,
+        ctx.io,
+        ctx.vcr,
+        sid,
+        base_config,
+    )
+    .await?;
+    if result.is_some() {
+        ctx.emit(WorkerEvent::Resumed);
     }
+    if matches!(result, Some(event_loop::WaitInterruptResult::Dismissed)) {
+        ctx.renderer.render_wait_cancelled();
+    }
+    Ok(result)
 }
 
 /// Assemble the system prompt from its components.
@@ -531,9 +1729,9 @@ fn build_system_prompt(
     }
     prompt.push_str(transition_prompt);
     let _ = write!(prompt, "\n\nMain worktree branch: {main_worktree_branch}");
-    if fork_config.is_some() {
+    if let Some(fork_config) = fork_config {
         prompt.push_str("\n\n");
-        prompt.push_str(fork::fork_system_prompt());
+        prompt.push_str(&fork::fork_system_prompt(fork_config.tools.as_ref()));
     }
     // Worker status is dynamic (changes as other workers start/stop), so it
     // goes last to maximise the cacheable prefix for the Claude API.
@@ -541,13 +1739,6 @@ fn build_system_prompt(
     prompt
 }
 
-/// Format args as a sorted display string.
-fn format_args_display(args: &HashMap<String, String>) -> String {
-    let mut parts: Vec<_> = args.iter().map(|(k, v)| format!("{k}={v}")).collect();
-    parts.sort();
-    parts.join(" ")
-}
-
 /// Maximum number of automatic corrective retries before falling back to user input.
 const MAX_TRANSITION_RETRIES: usize = 3;
 
@@ -583,7 +1774,7 @@ async fn parse_transition_with_retry<W: Write>(
             cost: retry_cost,
             session_id: retry_sid,
             ..
-        } = run_phase_session(&retry_prompt, base_config, Some(&current_sid), ctx).await?
+        } = run_phase_session(&retry_prompt, base_config, Some(&current_sid), false, ctx).await?
         else {
             return Ok(None);
         };
@@ -619,6 +1810,9 @@ async fn wait_for_transition_input<W: Write>(
         ctx.renderer.write_raw(&format!(
             "\r\nTransition output could not be parsed: {last_err}\r\n"
         ));
+        ctx.emit(WorkerEvent::Paused {
+            reason: format!("transition could not be parsed: {last_err}"),
+        });
 
         let Some(text) = event_loop::wait_for_interrupt_input(
             ctx.input,
@@ -632,13 +1826,14 @@ async fn wait_for_transition_input<W: Write>(
         else {
             return Ok(None);
         };
+        ctx.emit(WorkerEvent::Resumed);
 
         let PhaseOutcome::Completed {
             result_text,
             cost,
             session_id: new_sid,
             ..
-        } = run_phase_session(&text, base_config, Some(&session_id), ctx).await?
+        } = run_phase_session(&text, base_config, Some(&session_id), false, ctx).await?
         else {
             return Ok(None);
         };
@@ -657,6 +1852,56 @@ async fn wait_for_transition_input<W: Write>(
     }
 }
 
+/// Template arg through which dispatch can see what recently landed.
+const RECENT_COMMITS_ARG: &str = "recent_commits";
+
+/// How many of main's commits `recent_commits` lists.
+const RECENT_COMMITS: usize = 20;
+
+/// Render an agent's prompt. Dispatch also gets `recent_commits`, main's
+/// latest commits, when its template uses them.
+async fn render_agent_prompt(
+    vcr: &VcrContext,
+    wt_str: &str,
+    agent_def: &AgentDef,
+    args: &HashMap<String, String>,
+    is_dispatch: bool,
+) -> Result<String> {
+    if !is_dispatch || !agent_def.prompt_template.contains(RECENT_COMMITS_ARG) {
+        return agent_def.render(args);
+    }
+    let log = vcr
+        .call(
+            "worktree::recent_log",
+            (wt_str.to_string(), RECENT_COMMITS),
+            async |(p, n): &(String, usize)| Ok(worktree::recent_log(Path::new(p), *n)?),
+        )
+        .await?;
+    let mut args = args.clone();
+    args.insert(RECENT_COMMITS_ARG.to_string(), log);
+    agent_def.render(&args)
+}
+
+/// Describe the other active workers for the agent's system prompt.
+async fn vcr_worker_status_section(vcr: &VcrContext, wt_str: &str, branch: &str) -> Result<String> {
+    let all_workers = vcr
+        .call(
+            "worker_state::read_all",
+            wt_str.to_string(),
+            async |p: &String| worker_state::read_all(Path::new(p)),
+        )
+        .await?;
+    let others: Vec<_> = all_workers.iter().filter(|s| s.branch != branch).collect();
+    Ok(if others.is_empty() {
+        "\n\nNo other workers active.".to_string()
+    } else {
+        format!(
+            "\n\n## Worker Status\n\n{}",
+            worker_state::format_workers(&others, worker_state::StatusStyle::Dispatch)
+        )
+    })
+}
+
 /// Load `.coven/system.md` if it exists, empty string otherwise.
 async fn vcr_load_system_doc(vcr: &VcrContext, wt_str: &str) -> Result<String> {
     vcr.call(
@@ -691,46 +1936,123 @@ async fn vcr_main_head_sha(vcr: &VcrContext, wt_str: String) -> Result<String> {
     .await
 }
 
-/// VCR-wrapped agent loading.
-async fn vcr_load_agents(vcr: &VcrContext, worktree_path: &Path) -> Result<Vec<AgentDef>> {
+/// VCR-wrapped agent loading, with `--agents-dir` taking precedence.
+async fn vcr_load_agents(
+    vcr: &VcrContext,
+    worktree_path: &Path,
+    override_dir: Option<&Path>,
+) -> Result<Vec<AgentDef>> {
     let agents_dir = worktree_path.join(agents::AGENTS_DIR);
     let agents_dir_str = agents_dir.display().to_string();
-    let agent_defs = vcr
-        .call("agents::load_agents", agents_dir_str, async |d: &String| {
-            agents::load_agents(Path::new(d))
-        })
-        .await?;
+    let agent_defs = match override_dir {
+        None => {
+            vcr.call("agents::load_agents", agents_dir_str, async |d: &String| {
+                agents::load_agents(Path::new(d))
+            })
+            .await?
+        }
+        Some(override_dir) => {
+            let args = (agents_dir_str, override_dir.display().to_string());
+            vcr.call(
+                "agents::load_agents",
+                args,
+                async |(d, o): &(String, String)| {
+                    agents::load_agents_with_override(Path::new(d), Path::new(o))
+                },
+            )
+            .await?
+        }
+    };
     if agent_defs.is_empty() {
-        bail!("no agent definitions found in {}", agents_dir.display());
+        match override_dir {
+            Some(dir) => bail!(
+                "no agent definitions found in {} or {}",
+                dir.display(),
+                agents_dir.display()
+            ),
+            None => bail!("no agent definitions found in {}", agents_dir.display()),
+        }
     }
     Ok(agent_defs)
 }
 
-/// VCR-wrapped `semaphore::acquire`. Returns `None` if the agent has no
+/// Wait for the agent's semaphore slot, saying who holds it each time
+/// `--lock-timeout` passes. Returns `None` if the agent has no
 /// `max_concurrency` set (unlimited concurrency).
-async fn vcr_acquire_semaphore(
-    vcr: &VcrContext,
+async fn acquire_slot<W: Write>(
+    ctx: &mut PhaseContext<'_, W>,
     wt_str: &str,
+    branch: &str,
     agent_name: &str,
     agent_def: &AgentDef,
 ) -> Result<Option<semaphore::SemaphorePermit>> {
     let Some(max) = agent_def.frontmatter.max_concurrency else {
         return Ok(None);
     };
-    let permit = vcr
-        .call(
-            &format!("semaphore::acquire::{agent_name}"),
-            SemaphoreAcquireArgs {
-                path: wt_str.to_string(),
-                agent: agent_name.to_string(),
-                max_concurrency: max,
-            },
-            async |a: &SemaphoreAcquireArgs| {
-                semaphore::acquire(Path::new(&a.path), &a.agent, a.max_concurrency).await
-            },
-        )
-        .await?;
-    Ok(Some(permit))
+    let mut waited = Duration::ZERO;
+    loop {
+        let acquired = ctx
+            .vcr
+            .call(
+                &format!("semaphore::acquire::{agent_name}"),
+                SemaphoreAcquireArgs {
+                    path: wt_str.to_string(),
+                    agent: agent_name.to_string(),
+                    max_concurrency: max,
+                    branch: branch.to_string(),
+                    wait: ctx.lock_wait,
+                },
+                async |a: &SemaphoreAcquireArgs| {
+                    semaphore::acquire(
+                        Path::new(&a.path),
+                        &a.agent,
+                        a.max_concurrency,
+                        &a.branch,
+                        a.wait,
+                    )
+                    .await
+                },
+            )
+            .await?;
+        match acquired {
+            semaphore::Acquired::Permit(permit) => return Ok(Some(permit)),
+            semaphore::Acquired::Stolen(permit, holder) => {
+                ctx.renderer.render_warning(&format!(
+                    "Took {agent_name}'s slot from {} after {} (--steal-stale-lock); \
+                     it may still be running.",
+                    holder.branch,
+                    format_elapsed(Duration::from_secs(holder.held_secs)),
+                ));
+                return Ok(Some(permit));
+            }
+            semaphore::Acquired::TimedOut(holders) => {
+                waited += ctx.lock_wait.timeout;
+                ctx.renderer.render_warning(&format!(
+                    "Still waiting for {agent_name}'s slot after {}: {}",
+                    format_elapsed(waited),
+                    describe_holders(&holders),
+                ));
+            }
+        }
+    }
+}
+
+/// `held by swift-fox for 25m, calm-owl for 3m`.
+fn describe_holders(holders: &[semaphore::Holder]) -> String {
+    if holders.is_empty() {
+        return "held by a worker that didn't record itself".to_string();
+    }
+    let holders: Vec<String> = holders
+        .iter()
+        .map(|h| {
+            format!(
+                "{} for {}",
+                h.branch,
+                format_elapsed(Duration::from_secs(h.held_secs))
+            )
+        })
+        .collect();
+    format!("held by {}", holders.join(", "))
 }
 
 /// VCR-wrapped `worker_state::write_sleep_signal`.
@@ -753,27 +2075,35 @@ async fn vcr_read_sleep_signal(vcr: &VcrContext, wt_str: &str) -> Result<Option<
     .await
 }
 
-/// VCR-wrapped `worker_state::update`.
-async fn vcr_update_worker_state(
-    vcr: &VcrContext,
+/// VCR-wrapped `worker_state::update`, with the cost of the phases run so far.
+async fn vcr_update_worker_state<W: Write>(
+    ctx: &PhaseContext<'_, W>,
     path: &str,
     branch: &str,
     agent: Option<&str>,
     args: &HashMap<String, String>,
 ) -> Result<()> {
-    vcr.call(
-        "worker_state::update",
-        WorkerUpdateArgs {
-            path: path.to_string(),
-            branch: branch.to_string(),
-            agent: agent.map(String::from),
-            args: args.clone(),
-        },
-        async |a: &WorkerUpdateArgs| {
-            worker_state::update(Path::new(&a.path), &a.branch, a.agent.as_deref(), &a.args)
-        },
-    )
-    .await
+    ctx.vcr
+        .call(
+            "worker_state::update",
+            WorkerUpdateArgs {
+                path: path.to_string(),
+                branch: branch.to_string(),
+                agent: agent.map(String::from),
+                args: args.clone(),
+                total_cost_usd: ctx.total_cost,
+            },
+            async |a: &WorkerUpdateArgs| {
+                worker_state::update(
+                    Path::new(&a.path),
+                    &a.branch,
+                    a.agent.as_deref(),
+                    &a.args,
+                    a.total_cost_usd,
+                )
+            },
+        )
+        .await
 }
 
 enum PhaseOutcome {
@@ -783,6 +2113,8 @@ enum PhaseOutcome {
         session_id: Option<String>,
         wait_requested: bool,
     },
+    /// Ctrl+D at the interrupt prompt of a dispatch phase: skip this dispatch.
+    Skipped,
     Exited,
 }
 
@@ -796,49 +2128,141 @@ fn build_phase_config(
 ) -> SessionConfig {
     let mut append_system_prompt = system_prompt
         .map(String::from)
-        .or_else(|| fork_config.map(|_| fork::fork_system_prompt().to_string()));
+        .or_else(|| fork_config.map(|f| fork::fork_system_prompt(f.tools.as_ref())));
     if reload_enabled {
         crate::reload::append_reload_prompt(&mut append_system_prompt);
     }
     SessionConfig {
         extra_args: extra_args.to_vec(),
         append_system_prompt,
+        // `apply_defaults` put coven's default in `extra_args`; no mode
+        // there means it was left to claude.
+        no_default_permission_mode: !has_flag(extra_args, "--permission-mode"),
         working_dir: Some(worktree_path.to_path_buf()),
         ..Default::default()
     }
 }
 
+/// Session features for a worker phase: agents always watch `<next>` and
+/// `<wait-for-user>`, and wedged processes are restarted automatically.
+fn phase_features<'a, W: Write>(
+    session_config: &'a SessionConfig,
+    ctx: &PhaseContext<'a, W>,
+) -> SessionFeatures<'a> {
+    let mut watched_tags = vec!["next".to_string(), "wait-for-user".to_string()];
+    if ctx.fork_config.is_some() {
+        watched_tags.push("fork".to_string());
+    }
+    if ctx.reload_enabled {
+        watched_tags.push("reload".to_string());
+    }
+    SessionFeatures {
+        fork_config: ctx.fork_config,
+        reload_enabled: ctx.reload_enabled,
+        base_config: session_config,
+        watched_tags,
+        steering_timeout: ctx.steering_timeout,
+        resume_wedged: true,
+        budget: CostBudget::new(ctx.max_cost, ctx.total_cost),
+        max_loop_warnings: ctx.max_loop_warnings,
+        protocol_log: ctx.protocol_log,
+    }
+}
+
+/// Flags whose worker-level occurrences stay even when an agent sets them:
+/// an agent file doesn't get to change the permission mode, and claude adds
+/// up tool lists rather than overriding them.
+const WORKER_ARGS_KEPT: &[&str] = &[
+    "--permission-mode",
+    "--dangerously-skip-permissions",
+    "--allowedTools",
+    "--disallowedTools",
+];
+
+/// Claude flags that take a value as the next argument. Flags whose value
+/// is optional aren't listed: passed without `=`, they take none.
+const CLAUDE_FLAGS_WITH_VALUE: &[&str] = &[
+    "--add-dir",
+    "--agent",
+    "--agents",
+    "--allowedTools",
+    "--allowed-tools",
+    "--append-system-prompt",
+    "--append-system-prompt-file",
+    "--autocompact",
+    "--betas",
+    "--debug-file",
+    "--disallowedTools",
+    "--disallowed-tools",
+    "--effort",
+    "--fallback-model",
+    "--file",
+    "--input-format",
+    "--json-schema",
+    "--max-budget-usd",
+    "--max-turns",
+    "--mcp-config",
+    "--model",
+    "--name",
+    "--output-format",
+    "--permission-mode",
+    "--permission-prompt-tool",
+    "--plugin-dir",
+    "--session-id",
+    "--setting-sources",
+    "--settings",
+    "--system-prompt",
+    "--system-prompt-file",
+    "--tools",
+];
+
+/// Claude args for an agent's session: its frontmatter `claude_args`, then
+/// the worker-level args minus any flag the agent already sets (with the
+/// flag's value), so the agent's `--model` and the like win.
+fn merge_agent_args(agent_args: &[String], worker_args: &[String]) -> Vec<String> {
+    let mut merged = agent_args.to_vec();
+    let mut rest = worker_args.iter();
+    while let Some(arg) = rest.next() {
+        let flag = arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag);
+        let overridden = flag.starts_with("--")
+            && !WORKER_ARGS_KEPT.contains(&flag)
+            && has_flag(agent_args, flag);
+        if !overridden {
+            merged.push(arg.clone());
+        } else if !arg.contains('=') && CLAUDE_FLAGS_WITH_VALUE.contains(&flag) {
+            rest.next();
+        }
+    }
+    merged
+}
+
 /// Run an interactive claude session for a worker phase.
 ///
 /// If `resume` is provided, the session is resumed from the given session ID
-/// rather than starting fresh.
+/// rather than starting fresh. In the dispatch phase (`dispatch`), Ctrl+D at
+/// an interrupt skips the phase instead of exiting.
 async fn run_phase_session<W: Write>(
     prompt: &str,
     base_config: &SessionConfig,
     resume: Option<&str>,
+    dispatch: bool,
     ctx: &mut PhaseContext<'_, W>,
 ) -> Result<PhaseOutcome> {
     let session_config = SessionConfig {
-        prompt: Some(prompt.to_string()),
+        prompt: Some(ctx.carry_undelivered(prompt)),
         resume: resume.map(String::from),
         ..base_config.clone()
     };
 
+    // Between phases is a safe point to stop: nothing is running yet.
+    if ctx.io.shutdown_requested() || !ctx.wait_until_online().await? {
+        return Ok(PhaseOutcome::Exited);
+    }
     let mut runner = event_loop::spawn_session(session_config.clone(), ctx.io, ctx.vcr).await?;
     let mut state = SessionState::default();
-    let mut watched_tags = vec!["next".to_string(), "wait-for-user".to_string()];
-    if ctx.fork_config.is_some() {
-        watched_tags.push("fork".to_string());
-    }
-    if ctx.reload_enabled {
-        watched_tags.push("reload".to_string());
-    }
-    let features = SessionFeatures {
-        fork_config: ctx.fork_config,
-        reload_enabled: ctx.reload_enabled,
-        base_config: &session_config,
-        watched_tags,
-    };
+    let mut wrapped_up = false;
+    let mut api_retries = 0;
+    let features = phase_features(&session_config, ctx);
 
     loop {
         let outcome = event_loop::run_session(
@@ -851,24 +2275,47 @@ async fn run_phase_session<W: Write>(
             &features,
         )
         .await?;
-
-        // Wait for session file persistence before killing, so the
-        // session can be safely resumed. Skip for interrupts/exits.
-        if matches!(
-            outcome,
-            SessionOutcome::Completed { .. } | SessionOutcome::Reload { .. }
-        ) {
-            crate::session::persist::wait_if_needed(
-                &state,
-                ctx.vcr,
-                base_config.working_dir.as_deref(),
-            )
-            .await;
-        }
-        runner.kill().await?;
+        ctx.keep_undelivered(&outcome, &mut state);
+        end_session(&mut runner, &outcome, &state, base_config, ctx.vcr).await?;
 
         match outcome {
+            SessionOutcome::Completed { .. } | SessionOutcome::ProcessExited
+                if state.failed_retryably() =>
+            {
+                api_retries += 1;
+                ctx.total_usage += state.usage;
+                if let SessionOutcome::Completed { result_text, .. } = outcome {
+                    ctx.record_turn(&mut state, &result_text);
+                } else {
+                    ctx.backoff.record_failure();
+                }
+                let session_id = state.session_id.take();
+                let Some(respawned) =
+                    retry_after_api_error(&session_config, session_id, api_retries, ctx).await?
+                else {
+                    return Ok(PhaseOutcome::Exited);
+                };
+                (runner, state) = respawned;
+            }
+            SessionOutcome::Completed { result_text, .. }
+                if state.hit_max_turns() && !wrapped_up && state.session_id.is_some() =>
+            {
+                wrapped_up = true;
+                ctx.total_usage += state.usage;
+                ctx.record_turn(&mut state, &result_text);
+                (runner, state) = wrap_up_session(&session_config, &mut state, ctx).await?;
+            }
+            SessionOutcome::Completed { result_text, .. } if state.untrusted_dir.is_some() => {
+                ctx.total_usage += state.usage;
+                ctx.record_turn(&mut state, &result_text);
+                let Some(restarted) = restart_when_trusted(&session_config, ctx).await? else {
+                    return Ok(PhaseOutcome::Exited);
+                };
+                (runner, state) = restarted;
+            }
             SessionOutcome::Completed { result_text, .. } => {
+                ctx.total_usage += state.usage;
+                ctx.record_turn(&mut state, &result_text);
                 return Ok(PhaseOutcome::Completed {
                     result_text,
                     cost: state.total_cost_usd,
@@ -877,52 +2324,196 @@ async fn run_phase_session<W: Write>(
                 });
             }
             SessionOutcome::Reload { .. } => {
-                let Some(session_id) = state.session_id.take() else {
+                ctx.total_usage += state.usage;
+                let Some(reloaded) =
+                    reload_session(&session_config, state.session_id.take(), ctx).await?
+                else {
                     return Ok(PhaseOutcome::Exited);
                 };
-                let (new_runner, new_state) = crate::reload::spawn_reload_session(
-                    session_id,
-                    &session_config,
-                    ctx.renderer,
-                    ctx.io,
-                    ctx.vcr,
-                )
-                .await?;
-                runner = new_runner;
-                state = new_state;
+                (runner, state) = reloaded;
             }
             SessionOutcome::Interrupted => {
+                ctx.total_usage += state.usage;
                 let Some(session_id) = state.session_id.take() else {
                     return Ok(PhaseOutcome::Exited);
                 };
-                ctx.renderer.render_interrupted();
-
-                let Some(text) = event_loop::wait_for_interrupt_input(
-                    ctx.input,
-                    ctx.renderer,
-                    ctx.io,
-                    ctx.vcr,
-                    &session_id,
-                    &session_config,
-                )
-                .await?
-                else {
-                    return Ok(PhaseOutcome::Exited);
-                };
-                let resume_config = session_config.resume_with(text, session_id.clone());
-                runner = event_loop::spawn_session(resume_config, ctx.io, ctx.vcr).await?;
-                state = SessionState::default();
-                state.session_id = Some(session_id);
+                match resume_after_interrupt(&session_config, session_id, dispatch, ctx).await? {
+                    AfterInterrupt::Resumed(resumed) => (runner, state) = *resumed,
+                    AfterInterrupt::Skipped => return Ok(PhaseOutcome::Skipped),
+                    AfterInterrupt::Exited => return Ok(PhaseOutcome::Exited),
+                }
             }
-            SessionOutcome::ProcessExited => {
+            // Workers never arm a session timeout.
+            SessionOutcome::ProcessExited | SessionOutcome::TimedOut | SessionOutcome::Shutdown => {
+                ctx.total_usage += state.usage;
                 return Ok(PhaseOutcome::Exited);
             }
         }
     }
 }
 
+/// Wait for session file persistence before killing, so the session can
+/// be safely resumed. Skip for interrupts/exits.
+async fn end_session(
+    runner: &mut SessionRunner,
+    outcome: &SessionOutcome,
+    state: &SessionState,
+    base_config: &SessionConfig,
+    vcr: &VcrContext,
+) -> Result<()> {
+    if matches!(
+        outcome,
+        SessionOutcome::Completed { .. } | SessionOutcome::Reload { .. }
+    ) {
+        crate::session::persist::wait_if_needed(state, vcr, base_config.working_dir.as_deref())
+            .await;
+    }
+    runner.kill().await
+}
+
+/// Resume a session that hit `--max-turns` once, asking it to wrap up.
+async fn wrap_up_session<W: Write>(
+    session_config: &SessionConfig,
+    state: &mut SessionState,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Result<(SessionRunner, SessionState)> {
+    let session_id = state.session_id.take().unwrap_or_default();
+    event_loop::spawn_wrap_up_session(session_id, session_config, ctx.renderer, ctx.io, ctx.vcr)
+        .await
+}
+
+/// Respawn a session that asked to reload. None without a session ID to
+/// resume.
+async fn reload_session<W: Write>(
+    session_config: &SessionConfig,
+    session_id: Option<String>,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Result<Option<(SessionRunner, SessionState)>> {
+    let Some(session_id) = session_id else {
+        return Ok(None);
+    };
+    crate::reload::spawn_reload_session(session_id, session_config, ctx.renderer, ctx.io, ctx.vcr)
+        .await
+        .map(Some)
+}
+
+/// Pause after a session found its directory untrusted until the user has
+/// trusted it and pressed Enter, then start the phase over. None if the user
+/// exited; an error when headless, with nobody to fix it.
+async fn restart_when_trusted<W: Write>(
+    session_config: &SessionConfig,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Result<Option<(SessionRunner, SessionState)>> {
+    if ctx.io.is_headless() {
+        bail!("claude denied every tool call: the worktree's directory isn't trusted");
+    }
+    ctx.renderer
+        .write_raw("  Once it's trusted, the phase starts over.\r\n");
+    if !wait_for_enter("directory not trusted".to_string(), ctx).await? {
+        return Ok(None);
+    }
+    ctx.emit(WorkerEvent::Resumed);
+    let runner = event_loop::spawn_session(session_config.clone(), ctx.io, ctx.vcr).await?;
+    Ok(Some((runner, SessionState::default())))
+}
+
+/// What the user chose at an interrupt prompt.
+enum AfterInterrupt {
+    Resumed(Box<(SessionRunner, SessionState)>),
+    /// Ctrl+D in the dispatch phase.
+    Skipped,
+    Exited,
+}
+
+/// Wait for the user's message after an interrupt, then resume the session
+/// with it. In the dispatch phase, Ctrl+D skips the dispatch; otherwise it
+/// exits, like Ctrl+C.
+async fn resume_after_interrupt<W: Write>(
+    session_config: &SessionConfig,
+    session_id: String,
+    dispatch: bool,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Result<AfterInterrupt> {
+    if dispatch {
+        ctx.renderer.render_dispatch_interrupted();
+    } else {
+        ctx.renderer.render_interrupted();
+    }
+    ctx.emit(WorkerEvent::Paused {
+        reason: "interrupted".to_string(),
+    });
+
+    let choice = event_loop::wait_for_interrupt_choice(
+        ctx.input,
+        ctx.renderer,
+        ctx.io,
+        ctx.vcr,
+        &session_id,
+        session_config,
+    )
+    .await?;
+    let text = match choice {
+        Some(event_loop::WaitInterruptResult::Text(text)) => text,
+        Some(event_loop::WaitInterruptResult::EndSession) if dispatch => {
+            return Ok(AfterInterrupt::Skipped);
+        }
+        _ => return Ok(AfterInterrupt::Exited),
+    };
+    ctx.emit(WorkerEvent::Resumed);
+    let resume_config = session_config.resume_with(text, session_id.clone());
+    let runner = event_loop::spawn_session(resume_config, ctx.io, ctx.vcr).await?;
+    let state = SessionState {
+        session_id: Some(session_id),
+        ..SessionState::default()
+    };
+    Ok(AfterInterrupt::Resumed(Box::new((runner, state))))
+}
+
+/// Sent when resuming a session whose last request failed with a temporary
+/// API error.
+const RETRY_PROMPT: &str =
+    "The previous request failed with a temporary API error. Continue where you left off.";
+
+/// Back off after the `attempt`th temporary API error in a row, then
+/// respawn the session: resumed if claude got far enough to create it,
+/// otherwise started over. None if the user exited while waiting, or after
+/// [`preflight::MAX_API_RETRIES`] attempts.
+async fn retry_after_api_error<W: Write>(
+    session_config: &SessionConfig,
+    session_id: Option<String>,
+    attempt: u32,
+    ctx: &mut PhaseContext<'_, W>,
+) -> Result<Option<(SessionRunner, SessionState)>> {
+    if attempt > preflight::MAX_API_RETRIES {
+        ctx.renderer.render_warning(&format!(
+            "giving up after {} temporary API errors in a row",
+            preflight::MAX_API_RETRIES
+        ));
+        return Ok(None);
+    }
+    ctx.renderer.render_warning(&format!(
+        "retrying after a temporary API error ({attempt}/{})",
+        preflight::MAX_API_RETRIES
+    ));
+    if !ctx.wait_until_online().await? {
+        return Ok(None);
+    }
+    let retry_config = match session_id.clone() {
+        Some(id) => session_config.resume_with(RETRY_PROMPT.to_string(), id),
+        None => session_config.clone(),
+    };
+    let runner = event_loop::spawn_session(retry_config, ctx.io, ctx.vcr).await?;
+    let state = SessionState {
+        session_id,
+        ..SessionState::default()
+    };
+    Ok(Some((runner, state)))
+}
+
 enum WaitOutcome {
     NewCommits,
+    /// A `--wake-on` file changed in the main worktree.
+    WakeFilesChanged,
     Exited,
 }
 
@@ -941,7 +2532,12 @@ fn setup_ref_watcher(
 ) -> Result<(notify::RecommendedWatcher, tokio::sync::mpsc::Receiver<()>)> {
     let (tx, rx) = tokio::sync::mpsc::channel(1);
 
-    let mut watcher = notify::recommended_watcher(move |_: notify::Result<notify::Event>| {
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        // Reading the ref (as main_head_sha does on every notification) is
+        // reported as an access on Linux; only changes count.
+        if event.is_ok_and(|e| e.kind.is_access()) {
+            return;
+        }
         // Best-effort send; if the channel is full, a notification is already pending.
         let _ = tx.try_send(());
     })
@@ -968,6 +2564,93 @@ struct RefPaths {
     packed_refs: PathBuf,
 }
 
+impl RefPaths {
+    /// The main worktree: the directory holding the common `.git` dir.
+    /// `None` for a bare repo.
+    fn main_worktree(&self) -> Option<&Path> {
+        let common_dir = self.refs_heads_dir.parent()?.parent()?;
+        if common_dir.file_name()? != ".git" {
+            return None;
+        }
+        common_dir.parent()
+    }
+}
+
+/// Default `--wake-on` pattern.
+pub const DEFAULT_WAKE_ON: &str = "issues/**";
+
+/// How long `--wake-on` file events must stay quiet before the worker
+/// wakes, so an editor's burst of writes for one save counts once.
+const WAKE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Set up a filesystem watcher for files under `main_dir` matching
+/// `patterns` (`.covenignore` syntax, relative to `main_dir`).
+///
+/// Only the directory before each pattern's first wildcard is watched, so
+/// `issues/**` doesn't watch build output. Best-effort like
+/// [`setup_ref_watcher`]: paths that can't be watched are skipped.
+fn setup_wake_watcher(
+    main_dir: &Path,
+    patterns: &[String],
+) -> Result<(notify::RecommendedWatcher, tokio::sync::mpsc::Receiver<()>)> {
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    let main_dir = main_dir
+        .canonicalize()
+        .unwrap_or_else(|_| main_dir.to_path_buf());
+    let rules = IgnoreRules::parse(&patterns.join("\n"));
+
+    let root = main_dir.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        if event.kind.is_access() {
+            return;
+        }
+        let matched = event.paths.iter().any(|path| {
+            path.strip_prefix(&root)
+                .is_ok_and(|rel| rules.matches(&slash_path(rel)))
+        });
+        if matched {
+            // Best-effort send; if the channel is full, a wake is already pending.
+            let _ = tx.try_send(());
+        }
+    })
+    .context("failed to create filesystem watcher")?;
+
+    for pattern in patterns {
+        // A directory that doesn't exist yet is caught by watching the
+        // nearest one that does.
+        let dir = main_dir.join(literal_prefix(pattern));
+        if let Some(dir) = dir.ancestors().find(|d| d.is_dir()) {
+            let _ = watcher.watch(dir, RecursiveMode::Recursive);
+        }
+    }
+
+    Ok((watcher, rx))
+}
+
+/// The leading path components of a pattern that contain no wildcard.
+/// Patterns without a `/` match at any depth, so they have none.
+fn literal_prefix(pattern: &str) -> PathBuf {
+    let pattern = pattern.trim_start_matches('/');
+    if !pattern.trim_end_matches('/').contains('/') {
+        return PathBuf::new();
+    }
+    pattern
+        .split('/')
+        .take_while(|part| !part.contains(['*', '?']))
+        .collect()
+}
+
+/// `path` with `/` separators, as `.covenignore` patterns expect.
+fn slash_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
 /// Resolve the git ref paths to watch. Returns `None` if the git commands fail.
 fn resolve_ref_paths(worktree_path: &Path) -> Option<RefPaths> {
     let main_branch = worktree::main_branch_name(worktree_path).ok()?;
@@ -980,10 +2663,11 @@ fn resolve_ref_paths(worktree_path: &Path) -> Option<RefPaths> {
     })
 }
 
-/// Wait for new commits on main using filesystem notifications, while allowing
-/// the user to exit.
+/// Wait for new commits on main, or changes to `wake_on` files in the main
+/// worktree, using filesystem notifications, while allowing the user to exit.
 async fn wait_for_new_commits<W: Write>(
     worktree_path: &Path,
+    wake_on: &[String],
     renderer: &mut Renderer<W>,
     input: &mut InputHandler,
     io: &mut Io,
@@ -998,8 +2682,19 @@ async fn wait_for_new_commits<W: Write>(
             Ok(resolve_ref_paths(Path::new(p)))
         })
         .await?;
-    // _watcher must stay alive for the duration of the loop.
+    // The watchers must stay alive for the duration of the loop.
+    let main_dir = ref_paths
+        .as_ref()
+        .and_then(RefPaths::main_worktree)
+        .map(Path::to_path_buf);
     let (_watcher, mut rx) = setup_ref_watcher(ref_paths)?;
+    let (_wake_watcher, mut wake_rx) = match &main_dir {
+        Some(dir) if !wake_on.is_empty() => {
+            let (watcher, rx) = setup_wake_watcher(dir, wake_on)?;
+            (Some(watcher), rx)
+        }
+        _ => (None, tokio::sync::mpsc::channel(1).1),
+    };
 
     // Read baseline after watcher setup: any commit after the watcher is
     // active will fire a notification, and any commit before this read is
@@ -1018,11 +2713,20 @@ async fn wait_for_new_commits<W: Write>(
                 }
                 // Spurious notification — loop and wait again
             }
+            Some(()) = wake_rx.recv() => {
+                while let Ok(Some(())) = tokio::time::timeout(WAKE_DEBOUNCE, wake_rx.recv()).await {}
+                renderer.write_raw("Watched files changed in the main worktree.\r\n");
+                return Ok(WaitOutcome::WakeFilesChanged);
+            }
             event = vcr.call("next_event", (), async |(): &()| io.next_event().await) => {
                 let event = event?;
+                if matches!(event, IoEvent::Shutdown) {
+                    io.note_shutdown();
+                    return Ok(WaitOutcome::Exited);
+                }
                 if let IoEvent::Terminal(Event::Key(key_event)) = event {
                     let action = input.handle_key(&key_event, renderer.writer());
-                    match action {
+                    match event_loop::record_note(action, renderer, vcr).await? {
                         InputAction::Interrupt | InputAction::EndSession => {
                             return Ok(WaitOutcome::Exited);
                         }
@@ -1055,3 +2759,124 @@ fn main_head_sha(worktree_path: &Path) -> Result<String> {
 
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::fs;
+    use std::process::Command;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    fn init_repo(dir: &Path) {
+        git(dir, &["init", "-b", "main"]);
+        git(dir, &["config", "user.email", "test@test.com"]);
+        git(dir, &["config", "user.name", "Test"]);
+        git(dir, &["commit", "--allow-empty", "-m", "initial commit"]);
+    }
+
+    /// Whether the watcher fires within `wait`.
+    async fn fires(rx: &mut tokio::sync::mpsc::Receiver<()>, wait: Duration) -> bool {
+        tokio::time::timeout(wait, rx.recv()).await.is_ok()
+    }
+
+    #[test]
+    fn main_worktree_is_found_from_a_linked_worktree() {
+        let repo = TempDir::new().unwrap();
+        let linked = TempDir::new().unwrap();
+        init_repo(repo.path());
+        let linked_path = linked.path().join("wt");
+        git(
+            repo.path(),
+            &[
+                "worktree",
+                "add",
+                "-b",
+                "feature",
+                linked_path.to_str().unwrap(),
+            ],
+        );
+
+        let paths = resolve_ref_paths(&linked_path).unwrap();
+        assert_eq!(
+            paths.main_worktree().unwrap().canonicalize().unwrap(),
+            repo.path().canonicalize().unwrap()
+        );
+    }
+
+    #[test]
+    fn agent_flags_drop_the_worker_flag_and_its_value() {
+        let args = |list: &[&str]| list.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let agent = args(&["--model", "opus", "--max-budget-usd", "2", "--verbose"]);
+        let worker = args(&[
+            "--model",
+            "haiku",
+            "--max-budget-usd",
+            "-1",
+            "--verbose",
+            "notes.md",
+            "--effort=high",
+        ]);
+        assert_eq!(
+            merge_agent_args(&agent, &worker),
+            args(&[
+                "--model",
+                "opus",
+                "--max-budget-usd",
+                "2",
+                "--verbose",
+                "notes.md",
+                "--effort=high",
+            ])
+        );
+    }
+
+    #[test]
+    fn literal_prefix_stops_at_the_first_wildcard() {
+        assert_eq!(literal_prefix("issues/**"), PathBuf::from("issues"));
+        assert_eq!(
+            literal_prefix("/docs/issues/*.md"),
+            PathBuf::from("docs/issues")
+        );
+        assert_eq!(literal_prefix("*.md"), PathBuf::new());
+        assert_eq!(literal_prefix("issues/"), PathBuf::new());
+    }
+
+    #[tokio::test]
+    async fn wake_watcher_fires_for_matching_files_only() {
+        let main = TempDir::new().unwrap();
+        init_repo(main.path());
+        fs::create_dir(main.path().join("issues")).unwrap();
+        let (_watcher, mut rx) =
+            setup_wake_watcher(main.path(), &[DEFAULT_WAKE_ON.to_string()]).unwrap();
+
+        fs::write(main.path().join("notes.md"), "not an issue\n").unwrap();
+        assert!(!fires(&mut rx, Duration::from_millis(300)).await);
+
+        fs::write(main.path().join("issues/flaky-test.md"), "# Flaky test\n").unwrap();
+        assert!(fires(&mut rx, Duration::from_secs(5)).await);
+    }
+
+    #[tokio::test]
+    async fn wake_watcher_sees_a_directory_created_later() {
+        let main = TempDir::new().unwrap();
+        let (_watcher, mut rx) =
+            setup_wake_watcher(main.path(), &[DEFAULT_WAKE_ON.to_string()]).unwrap();
+        fs::create_dir(main.path().join("issues")).unwrap();
+        fs::write(main.path().join("issues/new.md"), "# New\n").unwrap();
+        assert!(fires(&mut rx, Duration::from_secs(5)).await);
+    }
+}
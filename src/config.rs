@@ -1,8 +1,11 @@
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 
+use crate::vcr::VcrContext;
+
 const CONFIG_PATH: &str = ".coven/config.toml";
 
 /// Project-level coven configuration from `.coven/config.toml`.
@@ -11,6 +14,67 @@ pub struct Config {
     /// Which agent runs first when a worker starts or wakes from sleep.
     #[serde(default = "default_entry_agent")]
     pub entry_agent: String,
+    /// Default worker worktree base for this repo (relative paths are
+    /// resolved against the repo). `--worktree-base` takes precedence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worktree_base: Option<PathBuf>,
+    /// Extra claude arguments for every command, under each section's own.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub claude_args: Vec<String>,
+    /// Default flags for `coven` runs.
+    #[serde(default, skip_serializing_if = "FlagDefaults::is_empty")]
+    pub run: FlagDefaults,
+    /// Default flags for `coven ralph`.
+    #[serde(default, skip_serializing_if = "FlagDefaults::is_empty")]
+    pub ralph: FlagDefaults,
+    /// Default flags for `coven worker`.
+    #[serde(default, skip_serializing_if = "FlagDefaults::is_empty")]
+    pub worker: FlagDefaults,
+}
+
+/// A command with its own section of default flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Run,
+    Ralph,
+    Worker,
+}
+
+/// Default flags for one command. Each only fills in a flag the command
+/// line doesn't give.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlagDefaults {
+    pub show_thinking: Option<bool>,
+    pub show_usage: Option<bool>,
+    pub show_results: Option<usize>,
+    pub render_markdown: Option<bool>,
+    pub quiet_stderr: Option<bool>,
+    pub max_stream_lines: Option<usize>,
+    pub max_cost: Option<f64>,
+    pub claude_args: Vec<String>,
+}
+
+impl FlagDefaults {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// These flags (as given on a command line), with the ones left unset
+    /// filled in from `defaults`.
+    #[must_use]
+    pub fn or(self, defaults: &Self) -> Self {
+        Self {
+            show_thinking: self.show_thinking.or(defaults.show_thinking),
+            show_usage: self.show_usage.or(defaults.show_usage),
+            show_results: self.show_results.or(defaults.show_results),
+            render_markdown: self.render_markdown.or(defaults.render_markdown),
+            quiet_stderr: self.quiet_stderr.or(defaults.quiet_stderr),
+            max_stream_lines: self.max_stream_lines.or(defaults.max_stream_lines),
+            max_cost: self.max_cost.or(defaults.max_cost),
+            claude_args: layer_args(self.claude_args, &defaults.claude_args),
+        }
+    }
 }
 
 fn default_entry_agent() -> String {
@@ -21,19 +85,173 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             entry_agent: default_entry_agent(),
+            worktree_base: None,
+            claude_args: Vec::new(),
+            run: FlagDefaults::default(),
+            ralph: FlagDefaults::default(),
+            worker: FlagDefaults::default(),
+        }
+    }
+}
+
+impl Config {
+    /// The defaults for `section`, with the shared `claude_args` under the
+    /// section's own.
+    pub fn defaults(&self, section: Section) -> FlagDefaults {
+        let flags = match section {
+            Section::Run => &self.run,
+            Section::Ralph => &self.ralph,
+            Section::Worker => &self.worker,
+        };
+        FlagDefaults {
+            claude_args: layer_args(flags.claude_args.clone(), &self.claude_args),
+            ..flags.clone()
         }
     }
 }
 
+/// `args` preceded by the flags of `defaults` that `args` doesn't give
+/// itself. A default flag is dropped along with the values following it.
+pub fn layer_args(args: Vec<String>, defaults: &[String]) -> Vec<String> {
+    let given: HashSet<&str> = args.iter().filter_map(|a| flag_name(a)).collect();
+    let mut keep = true;
+    let mut layered: Vec<String> = defaults
+        .iter()
+        .filter(|arg| {
+            if let Some(name) = flag_name(arg) {
+                keep = !given.contains(name);
+            }
+            keep
+        })
+        .cloned()
+        .collect();
+    layered.extend(args);
+    layered
+}
+
+/// The name of a `--flag` or `--flag=value` argument, or `None` for a value.
+fn flag_name(arg: &str) -> Option<&str> {
+    arg.starts_with('-')
+        .then(|| arg.split_once('=').map_or(arg, |(name, _)| name))
+}
+
 /// Load configuration from `.coven/config.toml` under `worktree_path`.
 ///
-/// Falls back to defaults if the file is missing.
+/// Falls back to defaults if the file is missing. Invalid TOML is an error
+/// naming the file and the line.
 pub fn load(worktree_path: &Path) -> Result<Config> {
     let path = worktree_path.join(CONFIG_PATH);
     if !path.exists() {
         return Ok(Config::default());
     }
     let contents = std::fs::read_to_string(&path)?;
-    let config: Config = toml::from_str(&contents)?;
-    Ok(config)
+    toml::from_str(&contents).map_err(|e| anyhow!("{}: {e}", path.display()))
+}
+
+/// The default flags for `section` from the config under `working_dir`
+/// (default: the current directory).
+pub fn load_defaults(
+    vcr: &VcrContext,
+    working_dir: Option<&Path>,
+    section: Section,
+) -> Result<FlagDefaults> {
+    Ok(load_through(vcr, working_dir)?.defaults(section))
+}
+
+/// The top-level `claude_args` from the config under `working_dir`, for
+/// commands with no section of their own (`coven fork`).
+pub fn load_shared_args(vcr: &VcrContext, working_dir: Option<&Path>) -> Result<Vec<String>> {
+    Ok(load_through(vcr, working_dir)?.claude_args)
+}
+
+fn load_through(vcr: &VcrContext, working_dir: Option<&Path>) -> Result<Config> {
+    vcr.project_config(|| match working_dir {
+        Some(dir) => load(dir),
+        None => load(&std::env::current_dir()?),
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn command_line_args_win_over_defaults() {
+        let defaults = args(&["--model", "sonnet", "--permission-mode", "acceptEdits"]);
+        assert_eq!(
+            layer_args(args(&["--model=opus", "--verbose"]), &defaults),
+            args(&[
+                "--permission-mode",
+                "acceptEdits",
+                "--model=opus",
+                "--verbose"
+            ])
+        );
+        assert_eq!(
+            layer_args(Vec::new(), &defaults),
+            args(&["--model", "sonnet", "--permission-mode", "acceptEdits"])
+        );
+    }
+
+    #[test]
+    fn section_defaults_layer_over_shared_args() {
+        let config: Config = toml::from_str(
+            r#"
+            claude_args = ["--model", "sonnet", "--permission-mode", "acceptEdits"]
+
+            [ralph]
+            show_thinking = true
+            max_cost = 2.5
+            claude_args = ["--model", "opus"]
+            "#,
+        )
+        .unwrap();
+        let ralph = config.defaults(Section::Ralph);
+        assert_eq!(ralph.show_thinking, Some(true));
+        assert_eq!(ralph.max_cost, Some(2.5));
+        assert_eq!(
+            ralph.claude_args,
+            args(&["--permission-mode", "acceptEdits", "--model", "opus"])
+        );
+        let run = config.defaults(Section::Run);
+        assert_eq!(run.show_thinking, None);
+        assert_eq!(run.claude_args, config.claude_args);
+    }
+
+    #[test]
+    fn given_flags_win_over_defaults_even_when_off() {
+        let defaults = FlagDefaults {
+            show_thinking: Some(true),
+            render_markdown: Some(true),
+            show_results: Some(5),
+            claude_args: args(&["--model", "sonnet"]),
+            ..FlagDefaults::default()
+        };
+        let given = FlagDefaults {
+            show_thinking: Some(false),
+            show_results: Some(2),
+            ..FlagDefaults::default()
+        };
+        let flags = given.or(&defaults);
+        assert_eq!(flags.show_thinking, Some(false));
+        assert_eq!(flags.render_markdown, Some(true));
+        assert_eq!(flags.show_results, Some(2));
+        assert_eq!(flags.show_usage, None);
+        assert_eq!(flags.claude_args, args(&["--model", "sonnet"]));
+    }
+
+    #[test]
+    fn invalid_toml_names_the_file_and_line() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".coven")).unwrap();
+        std::fs::write(dir.path().join(CONFIG_PATH), "[run]\nshow_thinking = yes\n").unwrap();
+        let error = load(dir.path()).unwrap_err().to_string();
+        assert!(error.contains(".coven/config.toml"), "{error}");
+        assert!(error.contains("line 2"), "{error}");
+    }
 }
@@ -0,0 +1,333 @@
+//! Worker control socket (`coven worker --control-socket PATH`).
+//!
+//! A Unix domain socket serving line-delimited JSON for dashboards. Each
+//! client gets a [`WorkerEvent::Snapshot`] of the current state on connect,
+//! then every event as it happens. A client that falls behind misses events
+//! and gets a [`WorkerEvent::Dropped`] marker; it never stalls the worker.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+/// Events queued per client before it starts missing them.
+const CLIENT_QUEUE: usize = 256;
+
+/// One line of the control socket protocol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkerEvent {
+    /// Current state, sent once to each client as it connects.
+    Snapshot(WorkerSnapshot),
+    /// The worker's worktree is ready.
+    Started { branch: String, worktree: PathBuf },
+    /// An agent phase began.
+    PhaseStarted {
+        agent: String,
+        title: String,
+        args: HashMap<String, String>,
+    },
+    /// An agent phase finished with a transition, `elapsed_ms` after it
+    /// started waiting for its agent's slot.
+    PhaseEnded {
+        agent: String,
+        cost: f64,
+        elapsed_ms: u64,
+    },
+    /// The transition an agent chose: hand off to `next`, or sleep if `next` is `None`.
+    Transition {
+        from: String,
+        next: Option<String>,
+        args: HashMap<String, String>,
+    },
+    /// The current phase landed commits on main (sent just before `PhaseEnded`).
+    Landed {
+        agent: String,
+        sha: String,
+        main_branch: String,
+    },
+    /// The worker is waiting for the user.
+    Paused { reason: String },
+    /// The user responded and the worker carried on.
+    Resumed,
+    /// Sleeping until main moves past `head`.
+    Sleeping { head: String },
+    /// New commits on main woke the worker.
+    Woke,
+    /// This client fell behind and missed `count` events.
+    Dropped { count: u64 },
+}
+
+/// What the worker is doing right now.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkerStatus {
+    #[default]
+    Starting,
+    Running,
+    Paused,
+    Sleeping,
+}
+
+/// The worker's state, as accumulated from the events so far.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorkerSnapshot {
+    pub status: WorkerStatus,
+    pub branch: Option<String>,
+    pub worktree: Option<PathBuf>,
+    /// Agent of the current (or paused) phase.
+    pub agent: Option<String>,
+    pub args: HashMap<String, String>,
+    pub pause_reason: Option<String>,
+    /// Number of phases started.
+    pub phases: usize,
+    /// Cost of all finished phases.
+    pub total_cost: f64,
+}
+
+impl WorkerSnapshot {
+    fn apply(&mut self, event: &WorkerEvent) {
+        match event {
+            WorkerEvent::Started { branch, worktree } => {
+                self.branch = Some(branch.clone());
+                self.worktree = Some(worktree.clone());
+            }
+            WorkerEvent::PhaseStarted { agent, args, .. } => {
+                self.status = WorkerStatus::Running;
+                self.agent = Some(agent.clone());
+                self.args.clone_from(args);
+                self.phases += 1;
+            }
+            WorkerEvent::PhaseEnded { cost, .. } => self.total_cost += cost,
+            WorkerEvent::Paused { reason } => {
+                self.status = WorkerStatus::Paused;
+                self.pause_reason = Some(reason.clone());
+            }
+            WorkerEvent::Resumed | WorkerEvent::Woke => {
+                self.status = WorkerStatus::Running;
+                self.pause_reason = None;
+            }
+            WorkerEvent::Sleeping { .. } => {
+                self.status = WorkerStatus::Sleeping;
+                self.agent = None;
+                self.args.clear();
+            }
+            WorkerEvent::Snapshot(_)
+            | WorkerEvent::Transition { .. }
+            | WorkerEvent::Landed { .. }
+            | WorkerEvent::Dropped { .. } => {}
+        }
+    }
+}
+
+/// Snapshot and event channel, locked together so a connecting client sees
+/// every event after its snapshot exactly once.
+struct Hub {
+    snapshot: WorkerSnapshot,
+    tx: broadcast::Sender<WorkerEvent>,
+}
+
+/// A listening control socket. The socket file is removed on drop.
+pub struct ControlSocket {
+    hub: Arc<Mutex<Hub>>,
+    path: PathBuf,
+    accept_task: JoinHandle<()>,
+}
+
+impl ControlSocket {
+    /// Listen on `path`, replacing a stale socket file left by a dead worker.
+    ///
+    /// Must be called within a tokio runtime.
+    pub fn bind(path: &Path) -> Result<Self> {
+        #[cfg(unix)]
+        {
+            let listener = bind_listener(path)?;
+            let (tx, _) = broadcast::channel(CLIENT_QUEUE);
+            let hub = Arc::new(Mutex::new(Hub {
+                snapshot: WorkerSnapshot::default(),
+                tx,
+            }));
+            let accept_task = tokio::spawn(accept_clients(listener, Arc::downgrade(&hub)));
+            Ok(Self {
+                hub,
+                path: path.to_path_buf(),
+                accept_task,
+            })
+        }
+        #[cfg(not(unix))]
+        {
+            anyhow::bail!(
+                "--control-socket needs Unix domain sockets, which aren't supported on this platform ({})",
+                path.display()
+            )
+        }
+    }
+
+    /// Record `event` in the snapshot and send it to every connected client.
+    pub fn emit(&self, event: WorkerEvent) {
+        let mut hub = self.hub.lock().unwrap_or_else(PoisonError::into_inner);
+        hub.snapshot.apply(&event);
+        // No receivers just means no clients are connected.
+        let _ = hub.tx.send(event);
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+#[cfg(unix)]
+fn bind_listener(path: &Path) -> Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+
+    use anyhow::{Context, bail};
+
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if !meta.file_type().is_socket() {
+            bail!("{} exists and is not a socket", path.display());
+        }
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            bail!("{} is in use by another process", path.display());
+        }
+        std::fs::remove_file(path)
+            .with_context(|| format!("failed to remove stale socket {}", path.display()))?;
+    }
+    tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("failed to listen on {}", path.display()))
+}
+
+#[cfg(unix)]
+async fn accept_clients(listener: tokio::net::UnixListener, hub: std::sync::Weak<Mutex<Hub>>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        let Some(hub) = hub.upgrade() else {
+            return;
+        };
+        let (snapshot, rx) = {
+            let hub = hub.lock().unwrap_or_else(PoisonError::into_inner);
+            (hub.snapshot.clone(), hub.tx.subscribe())
+        };
+        tokio::spawn(serve_client(stream, snapshot, rx));
+    }
+}
+
+/// Stream the snapshot and then events to one client until it disconnects
+/// or the worker exits.
+#[cfg(unix)]
+async fn serve_client(
+    mut stream: tokio::net::UnixStream,
+    snapshot: WorkerSnapshot,
+    mut rx: broadcast::Receiver<WorkerEvent>,
+) {
+    use tokio::io::AsyncWriteExt;
+
+    let mut event = WorkerEvent::Snapshot(snapshot);
+    loop {
+        let Ok(mut line) = serde_json::to_string(&event) else {
+            return;
+        };
+        line.push('\n');
+        if stream.write_all(line.as_bytes()).await.is_err() {
+            return;
+        }
+        event = match rx.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(count)) => WorkerEvent::Dropped { count },
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_tracks_phases_and_pauses() {
+        let mut snapshot = WorkerSnapshot::default();
+        snapshot.apply(&WorkerEvent::Started {
+            branch: "swift-fox-1".into(),
+            worktree: PathBuf::from("/wt"),
+        });
+        snapshot.apply(&WorkerEvent::PhaseStarted {
+            agent: "dispatch".into(),
+            title: "dispatch".into(),
+            args: HashMap::new(),
+        });
+        snapshot.apply(&WorkerEvent::Paused {
+            reason: "which task?".into(),
+        });
+        assert_eq!(snapshot.status, WorkerStatus::Paused);
+        assert_eq!(snapshot.pause_reason.as_deref(), Some("which task?"));
+
+        snapshot.apply(&WorkerEvent::Resumed);
+        snapshot.apply(&WorkerEvent::PhaseEnded {
+            agent: "dispatch".into(),
+            cost: 0.5,
+            elapsed_ms: 62_000,
+        });
+        snapshot.apply(&WorkerEvent::Sleeping { head: "abc".into() });
+        assert_eq!(snapshot.status, WorkerStatus::Sleeping);
+        assert_eq!(snapshot.branch.as_deref(), Some("swift-fox-1"));
+        assert_eq!(snapshot.agent, None);
+        assert_eq!(snapshot.pause_reason, None);
+        assert_eq!(snapshot.phases, 1);
+        assert!((snapshot.total_cost - 0.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn events_serialize_as_tagged_lines() {
+        let line = serde_json::to_string(&WorkerEvent::Dropped { count: 3 }).unwrap();
+        assert_eq!(line, r#"{"type":"dropped","count":3}"#);
+        let line =
+            serde_json::to_string(&WorkerEvent::Snapshot(WorkerSnapshot::default())).unwrap();
+        assert!(line.starts_with(r#"{"type":"snapshot","status":"starting""#));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn slow_client_gets_dropped_marker() {
+        use tokio::io::{AsyncBufReadExt, BufReader};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("worker.sock");
+        let socket = ControlSocket::bind(&path).unwrap();
+        let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let mut lines = BufReader::new(stream).lines();
+        let first = lines.next_line().await.unwrap().unwrap();
+        assert!(first.contains(r#""type":"snapshot""#));
+
+        // Overflow the client's queue before it reads anything more.
+        for _ in 0..CLIENT_QUEUE + 10 {
+            socket.emit(WorkerEvent::Woke);
+        }
+        drop(socket);
+        assert!(!path.exists());
+
+        let mut received = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            received.push(serde_json::from_str::<WorkerEvent>(&line).unwrap());
+        }
+        assert!(matches!(received.first(), Some(WorkerEvent::Dropped { count }) if *count > 0));
+        assert!(received.len() <= CLIENT_QUEUE + 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_to_replace_regular_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("not-a-socket");
+        std::fs::write(&path, "").unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        assert!(ControlSocket::bind(&path).is_err());
+        assert!(path.exists());
+    }
+}
@@ -0,0 +1,261 @@
+//! `.covenignore`: gitignore-style patterns, read from the repo root, for
+//! gitignored files that `worktree::spawn` should not copy into new worktrees.
+//!
+//! Supports the common gitignore syntax: `#` comments, `!` negation (the last
+//! matching pattern wins), a trailing `/` for directories only, `*` and `?`
+//! within a path component, and `**` for any number of components. Patterns
+//! with no `/` except a trailing one match a name at any depth; others are
+//! relative to the repo root. As in git, a file inside an excluded directory
+//! can't be re-included.
+
+use std::path::Path;
+
+pub const FILE_NAME: &str = ".covenignore";
+
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    negated: bool,
+    dir_only: bool,
+    /// Matched against the whole path rather than just its last component.
+    anchored: bool,
+    /// Pattern components, split on `/`.
+    parts: Vec<String>,
+}
+
+impl IgnoreRules {
+    /// Read `<root>/.covenignore`; no file means no rules.
+    pub fn load(root: &Path) -> std::io::Result<Self> {
+        match std::fs::read_to_string(root.join(FILE_NAME)) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let rules = text.lines().filter_map(Rule::parse).collect();
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether `path` (relative to the repo root, `/`-separated) is excluded,
+    /// directly or through one of its parent directories.
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+        (1..components.len()).any(|depth| self.verdict(&components[..depth], true))
+            || self.verdict(&components, is_dir)
+    }
+
+    /// Whether some rule could exclude a path inside `dir`. When none can,
+    /// there's no need to walk it: everything in it is kept.
+    pub fn may_exclude_within(&self, dir: &str) -> bool {
+        let components: Vec<&str> = dir.split('/').filter(|c| !c.is_empty()).collect();
+        self.rules
+            .iter()
+            .any(|rule| !rule.negated && (!rule.anchored || could_extend(&rule.parts, &components)))
+    }
+
+    /// Whether the rules select `path`, for pattern lists that pick files
+    /// rather than exclude them (`--wake-on`).
+    pub fn matches(&self, path: &str) -> bool {
+        self.is_ignored(path, false)
+    }
+
+    /// The last matching rule decides; no match means not ignored.
+    fn verdict(&self, components: &[&str], is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(components, is_dir))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+        Some(Self {
+            negated,
+            dir_only,
+            anchored,
+            parts: line.split('/').map(String::from).collect(),
+        })
+    }
+
+    fn matches(&self, components: &[&str], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            match_components(&self.parts, components)
+        } else {
+            components
+                .last()
+                .is_some_and(|name| glob(&self.parts[0], name))
+        }
+    }
+}
+
+/// Match pattern components against path components, `**` standing for
+/// zero or more of them.
+fn match_components(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((part, rest)) if part == "**" => {
+            (0..=path.len()).any(|skip| match_components(rest, &path[skip..]))
+        }
+        Some((part, rest)) => path
+            .split_first()
+            .is_some_and(|(name, tail)| glob(part, name) && match_components(rest, tail)),
+    }
+}
+
+/// Whether `pattern` could match some path strictly below `dir`.
+fn could_extend(pattern: &[String], dir: &[&str]) -> bool {
+    match (pattern.split_first(), dir.split_first()) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some((part, _)), Some(_)) if part == "**" => true,
+        (Some((part, rest)), Some((name, tail))) => glob(part, name) && could_extend(rest, tail),
+    }
+}
+
+/// Match one component: `*` is any run of characters, `?` any one.
+fn glob(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and how much of the name it has absorbed.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_wildcards() {
+        assert!(glob("*.log", "debug.log"));
+        assert!(glob("*.log", ".log"));
+        assert!(!glob("*.log", "debug.log.gz"));
+        assert!(glob("cache-?", "cache-1"));
+        assert!(!glob("cache-?", "cache-12"));
+        assert!(glob("a*b*c", "aXXbYYc"));
+        assert!(!glob("a*b*c", "aXXbYY"));
+    }
+
+    #[test]
+    fn unanchored_patterns_match_at_any_depth() {
+        let rules = IgnoreRules::parse("*.log\nnode_modules/\n");
+        assert!(rules.is_ignored("debug.log", false));
+        assert!(rules.is_ignored("logs/app/debug.log", false));
+        assert!(rules.is_ignored("web/node_modules/", true));
+        assert!(rules.is_ignored("web/node_modules/react/index.js", false));
+        // Directory-only patterns skip files of the same name.
+        assert!(!rules.is_ignored("web/node_modules", false));
+        assert!(!rules.is_ignored(".env", false));
+    }
+
+    #[test]
+    fn slashes_anchor_to_the_root() {
+        let rules = IgnoreRules::parse("/target\nbuild/cache\n");
+        assert!(rules.is_ignored("target/", true));
+        assert!(!rules.is_ignored("crates/a/target/", true));
+        assert!(rules.is_ignored("build/cache/x.bin", false));
+        assert!(!rules.is_ignored("web/build/cache", false));
+    }
+
+    #[test]
+    fn double_star_spans_directories() {
+        let rules = IgnoreRules::parse("**/fixtures/*.bin\ndata/**/raw\n");
+        assert!(rules.is_ignored("fixtures/a.bin", false));
+        assert!(rules.is_ignored("tests/deep/fixtures/a.bin", false));
+        assert!(!rules.is_ignored("tests/fixtures/a.txt", false));
+        assert!(rules.is_ignored("data/raw", true));
+        assert!(rules.is_ignored("data/2024/06/raw/x.csv", false));
+    }
+
+    #[test]
+    fn negation_reincludes_unless_a_parent_is_excluded() {
+        let rules =
+            IgnoreRules::parse("# secrets stay home\n*.env\n!.env.example\ncache/\n!cache/keep\n");
+        assert!(rules.is_ignored(".env", false));
+        assert!(rules.is_ignored("prod.env", false));
+        assert!(!rules.is_ignored(".env.example", false));
+        assert!(rules.is_ignored("cache/keep", false));
+    }
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let rules = IgnoreRules::parse("!*.log\n*.log\n");
+        assert!(rules.is_ignored("a.log", false));
+        let rules = IgnoreRules::parse("*.log\n!*.log\n");
+        assert!(!rules.is_ignored("a.log", false));
+    }
+
+    #[test]
+    fn only_rules_that_can_reach_inside_a_dir_need_a_walk() {
+        let rules = IgnoreRules::parse("/build/cache\ndata/**/raw\n!target/keep\n");
+        assert!(rules.may_exclude_within("build"));
+        assert!(!rules.may_exclude_within("build/cache"));
+        assert!(rules.may_exclude_within("data/2024"));
+        assert!(!rules.may_exclude_within("target"));
+        assert!(!rules.may_exclude_within("node_modules"));
+        // Unanchored patterns match a name at any depth.
+        assert!(IgnoreRules::parse("*.log\n").may_exclude_within("target/debug"));
+    }
+
+    #[test]
+    fn load_missing_file_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(IgnoreRules::load(dir.path()).unwrap().is_empty());
+        std::fs::write(dir.path().join(FILE_NAME), "# nothing yet\n\n").unwrap();
+        assert!(IgnoreRules::load(dir.path()).unwrap().is_empty());
+    }
+}
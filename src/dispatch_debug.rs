@@ -0,0 +1,166 @@
+//! `--debug-dispatch`: what each dispatch session was shown and what it
+//! answered, kept under `~/.coven/debug/<branch>/<timestamp>/` so a puzzling
+//! decision can be looked into after the fact. `coven gc --force` prunes old ones.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::platform;
+use crate::utc::UtcDateTime;
+
+/// The file written in each cycle's directory.
+pub const FILE_NAME: &str = "dispatch.json";
+
+/// Cycle directories older than this are removed by `coven gc --force`.
+pub const MAX_AGE: Duration = Duration::from_hours(7 * 24);
+
+/// `~/.coven/debug`, or `None` if the home directory is unknown.
+pub fn home_default() -> Option<PathBuf> {
+    platform::home_dir().map(|home| home.join(".coven").join("debug"))
+}
+
+/// The exact inputs and raw output of one dispatch cycle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DispatchRecord {
+    pub branch: String,
+    pub prompt: String,
+    /// The full system prompt, agent catalog included.
+    pub system_prompt: String,
+    /// The worker status section of the system prompt.
+    pub worker_status: String,
+    /// Result text of each session dispatch ran this cycle, oldest first.
+    pub outputs: Vec<String>,
+}
+
+/// Write `record` into its cycle directory: `dir` if it was already created
+/// this cycle, otherwise a new one under `root`. Returns the directory.
+pub fn write(root: &Path, dir: Option<&Path>, record: &DispatchRecord) -> Result<PathBuf> {
+    let dir = if let Some(dir) = dir {
+        dir.to_path_buf()
+    } else {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        cycle_dir(root, &record.branch, now)
+    };
+    fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let json = serde_json::to_string_pretty(record)?;
+    let path = dir.join(FILE_NAME);
+    fs::write(&path, json).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(dir)
+}
+
+/// `<root>/<branch>/<UTC timestamp>`, with any `/` in the branch replaced
+/// so every cycle sits exactly two levels down.
+fn cycle_dir(root: &Path, branch: &str, now_secs: u64) -> PathBuf {
+    root.join(branch.replace('/', "-"))
+        .join(utc_stamp(now_secs))
+}
+
+/// Seconds since the Unix epoch as a sortable UTC timestamp
+/// (`20261016-140322`).
+fn utc_stamp(secs: u64) -> String {
+    let t = UtcDateTime::from_unix(secs);
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        t.year, t.month, t.day, t.hour, t.minute, t.second
+    )
+}
+
+/// Remove cycle directories under `root` last written more than `max_age`
+/// ago, and branch directories left empty. With `dry_run`, only count them.
+/// Returns how many cycles were (or would be) removed.
+pub fn prune(root: &Path, max_age: Duration, dry_run: bool) -> Result<usize> {
+    let branches = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", root.display())),
+    };
+    let mut removed = 0;
+    for branch in branches {
+        let branch = branch?.path();
+        if !branch.is_dir() {
+            continue;
+        }
+        let mut kept = 0;
+        for cycle in fs::read_dir(&branch)? {
+            let cycle = cycle?;
+            let age = cycle.metadata()?.modified()?.elapsed().unwrap_or_default();
+            if age < max_age {
+                kept += 1;
+                continue;
+            }
+            if !dry_run {
+                fs::remove_dir_all(cycle.path())
+                    .with_context(|| format!("failed to remove {}", cycle.path().display()))?;
+            }
+            removed += 1;
+        }
+        if kept == 0 && !dry_run {
+            fs::remove_dir(&branch).ok();
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn record() -> DispatchRecord {
+        DispatchRecord {
+            branch: "feature/login".into(),
+            prompt: "Decide what to do next.".into(),
+            system_prompt: "# Transition Protocol\n\n## Worker Status\n\n- swift-fox-1".into(),
+            worker_status: "\n\n## Worker Status\n\n- swift-fox-1".into(),
+            outputs: vec!["<next>\nagent: greet\n</next>".into()],
+        }
+    }
+
+    #[test]
+    fn timestamps_are_utc_and_sortable() {
+        assert_eq!(utc_stamp(0), "19700101-000000");
+        assert_eq!(utc_stamp(951_782_400), "20000229-000000");
+        assert_eq!(utc_stamp(1_792_159_402), "20261016-140322");
+    }
+
+    #[test]
+    fn branch_slashes_stay_one_level() {
+        let dir = cycle_dir(Path::new("/d"), "feature/login", 0);
+        assert_eq!(dir, Path::new("/d/feature-login/19700101-000000"));
+    }
+
+    #[test]
+    fn write_reuses_the_cycle_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let mut record = record();
+        let dir = write(root.path(), None, &record).unwrap();
+        assert!(dir.starts_with(root.path().join("feature-login")));
+
+        record.outputs.push("<next>\nsleep: true\n</next>".into());
+        assert_eq!(write(root.path(), Some(&dir), &record).unwrap(), dir);
+        let written: DispatchRecord =
+            serde_json::from_str(&fs::read_to_string(dir.join(FILE_NAME)).unwrap()).unwrap();
+        assert_eq!(written, record);
+    }
+
+    #[test]
+    fn prune_removes_only_old_cycles() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = write(root.path(), None, &record()).unwrap();
+        assert_eq!(prune(root.path(), MAX_AGE, false).unwrap(), 0);
+        assert!(dir.exists());
+
+        assert_eq!(prune(root.path(), Duration::ZERO, true).unwrap(), 1);
+        assert!(dir.exists());
+        assert_eq!(prune(root.path(), Duration::ZERO, false).unwrap(), 1);
+        assert!(!root.path().join("feature-login").exists());
+        assert_eq!(
+            prune(&root.path().join("missing"), MAX_AGE, false).unwrap(),
+            0
+        );
+    }
+}
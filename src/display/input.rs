@@ -2,12 +2,18 @@ use std::io::Write;
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use crossterm::{cursor, queue, terminal};
-use unicode_width::UnicodeWidthStr;
+use unicode_width::UnicodeWidthChar;
 
-use super::term_width;
+use super::TerminalSize;
 use super::theme;
 use crate::event::InputMode;
 
+/// Killed text kept for yanking back with Ctrl+Y / Alt+Y.
+const KILL_RING_SIZE: usize = 16;
+
+/// How a newline in the buffer (from a paste) is shown on the input line.
+const NEWLINE_MARK: char = '⏎';
+
 /// Result of processing a key event.
 pub enum InputAction {
     /// No action yet — still editing.
@@ -18,6 +24,14 @@ pub enum InputAction {
     Submit(String, InputMode),
     /// User wants to view a message (e.g. ":3", ":2/1", ":Bash", ":Edit[-1]").
     ViewMessage(String),
+    /// User jotted a scratch note (`:note <text>`); `steer` (`:note!`) also
+    /// sends it to the model as a steering message.
+    Note { text: String, steer: bool },
+    /// User typed `:queue`, `:queue drop N` or `:q-N` to manage queued follow-ups.
+    Queue(QueueCommand),
+    /// User typed `:raw <json>` to write a line to claude's stdin verbatim.
+    /// Only produced with `--debug-protocol`.
+    Raw(String),
     /// User cancelled input (Escape with text in buffer).
     Cancel,
     /// User dismissed the prompt (Escape on empty buffer).
@@ -30,6 +44,17 @@ pub enum InputAction {
     Interactive,
     /// User pressed Ctrl+W to toggle wait-for-input after session completes.
     WaitRequested,
+    /// User pressed Ctrl+L to clear and redraw the screen.
+    Redraw,
+}
+
+/// What to do with the queued follow-ups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueCommand {
+    /// Show them, numbered from 1.
+    List,
+    /// Remove the one with this number before it's sent.
+    Drop(usize),
 }
 
 /// Simple line editor for user input in raw mode.
@@ -39,13 +64,33 @@ pub struct InputHandler {
     cursor: usize,
     active: bool,
     prefix_width: usize,
-    /// Tracks where the terminal cursor currently is (display columns from
-    /// the start of the input line, including the prefix). Used by `redraw()`
-    /// to navigate back to the beginning before reprinting.
-    term_cursor_display: usize,
+    /// Which wrapped line of the input the terminal cursor is on (0 for the
+    /// line with the prefix). Used by `redraw()` to navigate back to the
+    /// beginning before reprinting.
+    term_cursor_row: usize,
+    /// Text removed by the kill commands (Ctrl+U/K/W, Alt+D, Alt+Backspace),
+    /// most recent last. Kept across inputs.
+    kill_ring: Vec<String>,
+    /// The text just yanked, as `(start char, end char, ring index)`, so
+    /// Alt+Y can swap it for an older kill. Cleared by any other key.
+    last_yank: Option<(usize, usize, usize)>,
+    /// Submitted steering and follow-up messages, oldest first. Kept for the
+    /// whole process, so it spans ralph iterations and worker phases.
+    history: Vec<String>,
+    /// Index into `history` of the entry recalled with Up/Down, if any.
+    history_pos: Option<usize>,
+    /// What was typed before Up started recalling history; Down past the
+    /// newest entry brings it back.
+    history_draft: String,
     /// Whether a hint line was rendered above the current input line.
     /// When set, `clear_input_lines` moves up one extra line to erase it.
     has_hint_line: bool,
+    /// Text to start the next prompt with, set via `set_draft`.
+    draft: Option<String>,
+    /// Whether `:raw` is a command (`--debug-protocol`) rather than text.
+    raw_commands: bool,
+    /// Terminal columns, for wrapping. Kept in step with the renderer's.
+    width: usize,
 }
 
 impl InputHandler {
@@ -55,24 +100,66 @@ impl InputHandler {
             cursor: 0,
             active: false,
             prefix_width,
-            term_cursor_display: 0,
+            term_cursor_row: 0,
+            kill_ring: Vec::new(),
+            last_yank: None,
+            history: Vec::new(),
+            history_pos: None,
+            history_draft: String::new(),
             has_hint_line: false,
+            draft: None,
+            raw_commands: false,
+            width: TerminalSize::default().width,
         }
     }
 
+    /// Wrap at `width` columns, e.g. after the terminal was resized.
+    pub fn set_width(&mut self, width: usize) {
+        self.width = width.max(MIN_WRAP_WIDTH);
+    }
+
+    /// Accept `:raw <json>` as a command (`--debug-protocol`).
+    pub fn set_raw_commands(&mut self, enabled: bool) {
+        self.raw_commands = enabled;
+    }
+
     pub fn is_active(&self) -> bool {
         self.active
     }
 
+    /// Whether the input buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
     /// Activate the input handler (show prompt, start accepting keys).
     pub fn activate(&mut self) {
         self.buffer.clear();
         self.cursor = 0;
         self.active = true;
-        self.term_cursor_display = self.prefix_width;
+        self.term_cursor_row = 0;
+        self.last_yank = None;
+        self.history_pos = None;
         self.has_hint_line = false;
     }
 
+    /// Pre-fill the next prompt (see `restore_draft`) with `text`, e.g.
+    /// messages that never reached claude before an interrupt.
+    pub fn set_draft(&mut self, text: String) {
+        self.draft = Some(text);
+    }
+
+    /// Move the pending draft, if any, into the just-activated buffer with
+    /// the cursor at its end. Returns whether there was one to redraw.
+    pub fn restore_draft(&mut self) -> bool {
+        let Some(draft) = self.draft.take() else {
+            return false;
+        };
+        self.cursor = draft.chars().count();
+        self.buffer = draft;
+        true
+    }
+
     /// Mark that a hint line was rendered above the current input line.
     pub fn set_has_hint_line(&mut self) {
         self.has_hint_line = true;
@@ -85,6 +172,14 @@ impl InputHandler {
         self.cursor = 0;
     }
 
+    /// Deactivate and erase the input line, dropping any typed text.
+    pub fn discard(&mut self, out: &mut impl Write) {
+        if self.active {
+            self.deactivate();
+            self.clear_input_lines(out);
+        }
+    }
+
     /// Byte offset in the buffer corresponding to the current char-index cursor.
     fn cursor_byte_pos(&self) -> usize {
         self.byte_pos_at(self.cursor)
@@ -120,48 +215,52 @@ impl InputHandler {
     /// Redraw the entire input line and position the cursor.
     ///
     /// Call this after any buffer or cursor modification. Uses
-    /// `term_cursor_display` to navigate to the start of the input,
+    /// `term_cursor_row` to navigate to the start of the input,
     /// then reprints the prefix and buffer, clears leftover content,
-    /// and moves the cursor to the correct column.
+    /// and moves the cursor to the correct row and column.
     pub fn redraw(&mut self, out: &mut impl Write) {
-        let tw = term_width();
+        let tw = self.width;
 
         // Move to start of input region
-        let cur_line = self.term_cursor_display / tw;
-        if cur_line > 0 {
+        if self.term_cursor_row > 0 {
             queue!(
                 out,
-                cursor::MoveUp(u16::try_from(cur_line).unwrap_or(u16::MAX))
+                cursor::MoveUp(u16::try_from(self.term_cursor_row).unwrap_or(u16::MAX))
             )
             .ok();
         }
         queue!(out, crossterm::style::Print("\r")).ok();
 
-        // Redraw prefix + buffer
-        let total_display = self.prefix_width + self.buffer.width();
+        // Redraw prefix + buffer, one char on screen per char in the buffer
+        let shown = self.buffer.replace('\n', &NEWLINE_MARK.to_string());
+        let len = shown.chars().count();
+        let (end_line, end_col) = wrapped_position(self.prefix_width, &shown, len, tw);
 
         queue!(
             out,
             crossterm::style::Print(theme::prompt_style().apply("> ")),
-            crossterm::style::Print(&self.buffer),
+            crossterm::style::Print(&shown),
         )
         .ok();
 
-        // When total display width is an exact multiple of terminal width, the
-        // terminal cursor is in "pending wrap" state rather than on the next line.
-        // Print a space to force the wrap to resolve, then move back.
-        if total_display > 0 && total_display.is_multiple_of(tw) {
+        // When the text ends exactly at the right margin, the terminal cursor
+        // is in "pending wrap" state rather than on the next line. Print a
+        // space to force the wrap to resolve, then move back.
+        let end_line = if end_col >= tw {
             queue!(out, crossterm::style::Print(" "), cursor::MoveLeft(1)).ok();
-        }
+            end_line + 1
+        } else {
+            end_line
+        };
 
         queue!(out, terminal::Clear(terminal::ClearType::FromCursorDown)).ok();
 
         // Move terminal cursor from end-of-buffer to the actual cursor position
-        let byte_pos = self.cursor_byte_pos();
-        let new_cursor_display = self.prefix_width + self.buffer[..byte_pos].width();
-        let end_line = total_display / tw;
-        let target_line = new_cursor_display / tw;
-        let target_col = new_cursor_display % tw;
+        let (target_line, target_col) =
+            match wrapped_position(self.prefix_width, &shown, self.cursor, tw) {
+                (line, col) if col >= tw => (line + 1, 0),
+                position => position,
+            };
 
         let lines_up = end_line.saturating_sub(target_line);
         if lines_up > 0 {
@@ -178,17 +277,15 @@ impl InputHandler {
         .ok();
         out.flush().ok();
 
-        self.term_cursor_display = new_cursor_display;
+        self.term_cursor_row = target_line;
     }
 
     /// Clear all terminal lines occupied by the input (prefix + buffer),
     /// accounting for line wrapping at the terminal width.
     /// Also clears the hint line above the input if one was rendered.
     fn clear_input_lines(&self, out: &mut impl Write) {
-        let tw = term_width();
-        // Use term_cursor_display to find which line the cursor is on,
-        // then move to the start of the input region.
-        let mut lines_up = self.term_cursor_display / tw;
+        // Move from the cursor's line to the start of the input region.
+        let mut lines_up = self.term_cursor_row;
         // If a hint line was rendered above the input, include it in the clear.
         if self.has_hint_line {
             lines_up += 1;
@@ -224,12 +321,92 @@ impl InputHandler {
     }
 
     /// Delete chars in `[from_char..to_char)`, set cursor to `from_char`, and redraw.
-    fn delete_range(&mut self, from_char: usize, to_char: usize, out: &mut impl Write) {
+    /// Returns the deleted text.
+    fn delete_range(&mut self, from_char: usize, to_char: usize, out: &mut impl Write) -> String {
         let from_byte = self.byte_pos_at(from_char);
         let to_byte = self.byte_pos_at(to_char);
-        self.buffer.drain(from_byte..to_byte);
+        let deleted = self.buffer.drain(from_byte..to_byte).collect();
         self.cursor = from_char;
         self.redraw(out);
+        deleted
+    }
+
+    /// Delete chars in `[from_char..to_char)` onto the kill ring.
+    fn kill_range(&mut self, from_char: usize, to_char: usize, out: &mut impl Write) {
+        let killed = self.delete_range(from_char, to_char, out);
+        if killed.is_empty() {
+            return;
+        }
+        if self.kill_ring.len() == KILL_RING_SIZE {
+            self.kill_ring.remove(0);
+        }
+        self.kill_ring.push(killed);
+    }
+
+    /// Insert the kill ring entry `index` at the cursor (Ctrl+Y).
+    fn yank(&mut self, index: usize, out: &mut impl Write) {
+        let Some(text) = self.kill_ring.get(index) else {
+            return;
+        };
+        let start = self.cursor;
+        let byte_pos = self.cursor_byte_pos();
+        self.buffer.insert_str(byte_pos, text);
+        self.cursor += text.chars().count();
+        self.last_yank = Some((start, self.cursor, index));
+        self.redraw(out);
+    }
+
+    /// Replace the text just yanked with the next older kill (Alt+Y).
+    fn yank_pop(&mut self, out: &mut impl Write) {
+        let Some((start, end, index)) = self.last_yank else {
+            return;
+        };
+        let older = index.checked_sub(1).unwrap_or(self.kill_ring.len() - 1);
+        let from_byte = self.byte_pos_at(start);
+        let to_byte = self.byte_pos_at(end);
+        self.buffer.drain(from_byte..to_byte);
+        self.cursor = start;
+        self.yank(older, out);
+    }
+
+    /// Replace the buffer with `text`, cursor at the end, and redraw.
+    fn set_buffer(&mut self, text: String, out: &mut impl Write) {
+        self.cursor = text.chars().count();
+        self.buffer = text;
+        self.redraw(out);
+    }
+
+    /// Recall the previous history entry (Up). The first Up saves what's
+    /// been typed so far; the buffer is a copy, so editing a recalled entry
+    /// leaves the history alone.
+    fn history_prev(&mut self, out: &mut impl Write) {
+        let pos = if let Some(pos) = self.history_pos {
+            pos.saturating_sub(1)
+        } else {
+            let Some(last) = self.history.len().checked_sub(1) else {
+                return;
+            };
+            self.history_draft = self.buffer.clone();
+            last
+        };
+        self.history_pos = Some(pos);
+        self.set_buffer(self.history[pos].clone(), out);
+    }
+
+    /// Recall the next history entry (Down), or the saved draft after the
+    /// newest one.
+    fn history_next(&mut self, out: &mut impl Write) {
+        let Some(pos) = self.history_pos else {
+            return;
+        };
+        if pos + 1 < self.history.len() {
+            self.history_pos = Some(pos + 1);
+            self.set_buffer(self.history[pos + 1].clone(), out);
+        } else {
+            self.history_pos = None;
+            let draft = std::mem::take(&mut self.history_draft);
+            self.set_buffer(draft, out);
+        }
     }
 
     /// Byte offset for a given char index.
@@ -245,7 +422,56 @@ impl InputHandler {
         if !self.active {
             return self.handle_inactive_key(event, out);
         }
+        let last_yank = self.last_yank.take();
+        let alt = event.modifiers.contains(KeyModifiers::ALT);
+        match event.code {
+            KeyCode::Char('y') if alt => {
+                self.last_yank = last_yank;
+                self.yank_pop(out);
+                InputAction::None
+            }
+            KeyCode::Char('y') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                if let Some(index) = self.kill_ring.len().checked_sub(1) {
+                    self.yank(index, out);
+                }
+                InputAction::None
+            }
+            KeyCode::Up => {
+                self.history_prev(out);
+                InputAction::None
+            }
+            KeyCode::Down => {
+                self.history_next(out);
+                InputAction::None
+            }
+            _ => self.handle_edit_key(event, out),
+        }
+    }
 
+    /// Insert pasted text at the cursor (bracketed paste). Its newlines are
+    /// kept, so a multi-line paste stays one message until Enter. Returns
+    /// `Activated` when the paste starts the input line, like a typed key.
+    pub fn handle_paste(&mut self, text: &str, out: &mut impl Write) -> InputAction {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let Some(first) = text.chars().next() else {
+            return InputAction::None;
+        };
+        let was_active = self.active;
+        if !was_active {
+            self.activate();
+        }
+        self.last_yank = None;
+        let byte_pos = self.cursor_byte_pos();
+        self.buffer.insert_str(byte_pos, &text);
+        self.cursor += text.chars().count();
+        if !was_active {
+            return InputAction::Activated(first);
+        }
+        self.redraw(out);
+        InputAction::None
+    }
+
+    fn handle_edit_key(&mut self, event: &KeyEvent, out: &mut impl Write) -> InputAction {
         let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
         let alt = event.modifiers.contains(KeyModifiers::ALT);
         let len = self.buffer.chars().count();
@@ -254,6 +480,12 @@ impl InputHandler {
             KeyCode::Char('c') if ctrl => InputAction::Interrupt,
             KeyCode::Char('d') if ctrl => InputAction::EndSession,
             KeyCode::Char('o') if ctrl => InputAction::Interactive,
+            KeyCode::Char('l') if ctrl => {
+                // The screen is about to be cleared; the input line is
+                // redrawn from its first row.
+                self.term_cursor_row = 0;
+                InputAction::Redraw
+            }
 
             KeyCode::Left if ctrl || alt => {
                 self.move_cursor(self.word_boundary_left(), out);
@@ -298,25 +530,25 @@ impl InputHandler {
 
             KeyCode::Backspace if alt => {
                 let t = self.word_boundary_left();
-                self.delete_range(t, self.cursor, out);
+                self.kill_range(t, self.cursor, out);
                 InputAction::None
             }
             KeyCode::Char('w') if ctrl => {
                 let t = self.word_boundary_left();
-                self.delete_range(t, self.cursor, out);
+                self.kill_range(t, self.cursor, out);
                 InputAction::None
             }
             KeyCode::Char('u') if ctrl => {
-                self.delete_range(0, self.cursor, out);
+                self.kill_range(0, self.cursor, out);
                 InputAction::None
             }
             KeyCode::Char('k') if ctrl => {
-                self.delete_range(self.cursor, len, out);
+                self.kill_range(self.cursor, len, out);
                 InputAction::None
             }
             KeyCode::Char('d') if alt => {
                 let t = self.word_boundary_right();
-                self.delete_range(self.cursor, t, out);
+                self.kill_range(self.cursor, t, out);
                 InputAction::None
             }
             KeyCode::Delete if self.cursor < len => {
@@ -357,6 +589,7 @@ impl InputHandler {
             KeyCode::Char('d') if ctrl => InputAction::EndSession,
             KeyCode::Char('o') if ctrl => InputAction::Interactive,
             KeyCode::Char('w') if ctrl => InputAction::WaitRequested,
+            KeyCode::Char('l') if ctrl => InputAction::Redraw,
             KeyCode::Char(c) => {
                 // Activate and buffer the character, but don't redraw yet.
                 // The caller will call begin_input_line() to set up a fresh
@@ -380,6 +613,20 @@ impl InputHandler {
             return InputAction::None;
         }
 
+        if let Some((text, steer)) = parse_note_command(&text) {
+            return InputAction::Note { text, steer };
+        }
+
+        if let Some(command) = parse_queue_command(&text) {
+            return InputAction::Queue(command);
+        }
+
+        if self.raw_commands
+            && let Some(line) = parse_raw_command(&text)
+        {
+            return InputAction::Raw(line);
+        }
+
         if let Some(query) = parse_view_command(&text) {
             return InputAction::ViewMessage(query);
         }
@@ -390,73 +637,382 @@ impl InputHandler {
             InputMode::Steering
         };
 
+        if self.history.last() != Some(&text) {
+            self.history.push(text.clone());
+        }
         InputAction::Submit(text, mode)
     }
 }
 
+/// The narrowest width input wraps at: room for one wide character. The
+/// renderer never goes below `MIN_WIDTH`, but the wrapping math mustn't
+/// divide by zero whatever width it's given.
+const MIN_WRAP_WIDTH: usize = 2;
+
+/// Wrapped line and column at which the char at index `cursor` (or the end
+/// of `text`, if `cursor` is past it) starts, when `text` is printed after a
+/// `prefix_width`-column prompt on a terminal `width` columns wide.
+///
+/// A wide character that doesn't fit at the end of a line wraps whole to the
+/// next one, as terminals do. The end of `text` can land at column `width`
+/// (the terminal's pending-wrap state); callers treat that as the start of
+/// the next line.
+fn wrapped_position(
+    prefix_width: usize,
+    text: &str,
+    cursor: usize,
+    width: usize,
+) -> (usize, usize) {
+    let width = width.max(MIN_WRAP_WIDTH);
+    let (mut line, mut col) = (prefix_width / width, prefix_width % width);
+    for (i, c) in text.chars().enumerate() {
+        let w = c.width().unwrap_or(0);
+        if col + w > width {
+            line += 1;
+            col = 0;
+        }
+        if i == cursor {
+            return (line, col);
+        }
+        col += w;
+    }
+    (line, col)
+}
+
+/// Parse `:note <text>` and `:note! <text>`. Returns the note text (possibly
+/// empty) and whether it should also be sent as steering.
+fn parse_note_command(text: &str) -> Option<(String, bool)> {
+    let rest = text.trim().strip_prefix(':')?.strip_prefix("note")?;
+    let (rest, steer) = match rest.strip_prefix('!') {
+        Some(r) => (r, true),
+        None => (rest, false),
+    };
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None; // e.g. `:notes`
+    }
+    Some((rest.trim().to_string(), steer))
+}
+
+/// Parse `:queue`, `:queue drop N` and its shorthand `:q-N`. Any other
+/// arguments to `:queue` list the queue, whose header shows the usage,
+/// rather than sending the typo to claude.
+fn parse_queue_command(text: &str) -> Option<QueueCommand> {
+    let rest = text.trim().strip_prefix(':')?;
+    if let Some(n) = rest.strip_prefix("q-") {
+        return n.parse().ok().map(QueueCommand::Drop);
+    }
+    let rest = rest.strip_prefix("queue")?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    match rest.split_whitespace().collect::<Vec<_>>()[..] {
+        ["drop", n] => Some(n.parse().map_or(QueueCommand::List, QueueCommand::Drop)),
+        _ => Some(QueueCommand::List),
+    }
+}
+
+/// Parse `:raw <json>`, returning the JSON text (possibly empty).
+fn parse_raw_command(text: &str) -> Option<String> {
+    let rest = text.trim().strip_prefix(':')?.strip_prefix("raw")?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(rest.trim().to_string())
+}
+
 /// Parse view commands. Returns the label query string.
 ///
 /// Accepted forms:
 /// - `:N` or `:P/C` — numeric (e.g. `:3` → `"3"`, `:2/1` → `"2/1"`)
 /// - `:Label` or `:Label[index]` — label-based (e.g. `:Bash` → `"Bash"`, `:Edit[-1]` → `"Edit[-1]"`)
+/// - `:/text` or `:/text/N` — content search, spaces allowed (e.g. `:/cargo test` → `"/cargo test"`)
+///
+/// Any other single word after the colon is passed through as well, so the
+/// lookup can explain what's wrong with it. Text containing spaces or
+/// starting with punctuation (e.g. `:)`) is not a view command.
 fn parse_view_command(text: &str) -> Option<String> {
-    let rest = text.trim().strip_prefix(':')?;
+    let rest = text.trim().strip_prefix(':')?.trim_start();
+    // A bare `:` asks for the index of all messages.
     if rest.is_empty() {
+        return Some(String::new());
+    }
+    if rest.starts_with('/') {
+        return Some(rest.trim_end().to_string());
+    }
+    if rest.contains(char::is_whitespace)
+        || !rest.starts_with(|c: char| c.is_alphanumeric() || c == '[')
+    {
         return None;
     }
 
-    // Numeric: "N" or "P/C"
+    // Canonicalize numbers (`:03` → `"3"`) so they match label prefixes.
     if let Some((left, right)) = rest.split_once('/')
         && let (Ok(p), Ok(c)) = (left.parse::<usize>(), right.parse::<usize>())
     {
-        if p > 0 && c > 0 {
-            return Some(format!("{p}/{c}"));
-        }
-        return None;
+        return Some(format!("{p}/{c}"));
     }
     if let Ok(n) = rest.parse::<usize>() {
-        return if n > 0 { Some(n.to_string()) } else { None };
+        return Some(n.to_string());
     }
 
-    // Label-based: validate Name or Name[index]
-    let (name, tail) = if let Some(bracket) = rest.find('[') {
-        let after = rest[bracket + 1..].strip_suffix(']')?;
-        let _: isize = after.parse().ok()?;
-        (&rest[..bracket], &rest[bracket..])
-    } else {
-        (rest, "")
-    };
-
-    if name.is_empty()
-        || !name
-            .chars()
-            .all(|c| c.is_alphanumeric() || matches!(c, '-' | ':' | '_'))
-    {
-        return None;
-    }
-
-    Some(format!("{name}{tail}"))
+    Some(rest.to_string())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn press(input: &mut InputHandler, code: KeyCode, modifiers: KeyModifiers) {
+        input.handle_key(&KeyEvent::new(code, modifiers), &mut Vec::new());
+    }
+
+    fn type_text(input: &mut InputHandler, text: &str) {
+        for c in text.chars() {
+            press(input, KeyCode::Char(c), KeyModifiers::NONE);
+        }
+    }
+
+    #[test]
+    fn wrapped_position_ascii() {
+        // "> " prompt, 10-column terminal.
+        assert_eq!(wrapped_position(2, "abcdefgh", 0, 10), (0, 2));
+        assert_eq!(wrapped_position(2, "abcdefgh", 7, 10), (0, 9));
+        // Ends exactly at the margin: pending wrap.
+        assert_eq!(wrapped_position(2, "abcdefgh", 8, 10), (0, 10));
+        assert_eq!(wrapped_position(2, "abcdefghijk", 9, 10), (1, 1));
+    }
+
+    #[test]
+    fn redraw_survives_degenerate_widths() {
+        for width in [0, 1, 5, 19] {
+            let mut input = InputHandler::new(2);
+            input.set_width(width);
+            input.activate();
+            type_text(&mut input, "wrap 漢字 around");
+            let mut out = Vec::new();
+            input.redraw(&mut out);
+            assert!(!out.is_empty());
+            // The cursor sits after the text, past a pending wrap.
+            let (line, col) = wrapped_position(2, "wrap 漢字 around", 16, width);
+            let wrap = width.max(MIN_WRAP_WIDTH);
+            let expected = if col >= wrap { line + 1 } else { line };
+            assert_eq!(input.term_cursor_row, expected, "width {width}");
+        }
+    }
+
+    #[test]
+    fn wrapped_position_wide_chars() {
+        // Four 2-column chars fill the first line exactly.
+        assert_eq!(wrapped_position(2, "漢字漢字ab", 3, 10), (0, 8));
+        assert_eq!(wrapped_position(2, "漢字漢字ab", 4, 10), (1, 0));
+        assert_eq!(wrapped_position(2, "漢字漢字ab", 6, 10), (1, 2));
+        // On a 9-column terminal the fourth char doesn't fit in the last
+        // column and wraps whole, leaving a gap.
+        assert_eq!(wrapped_position(2, "漢字漢字ab", 3, 9), (1, 0));
+        assert_eq!(wrapped_position(2, "漢字漢字ab", 6, 9), (1, 4));
+        // Mixed widths across several lines.
+        assert_eq!(wrapped_position(2, "a漢b字c漢d字e漢", 10, 5), (3, 3));
+    }
+
+    #[test]
+    fn cursor_motion_and_kills() {
+        let mut input = InputHandler::new(2);
+        type_text(&mut input, "fix the tpyo here");
+        press(&mut input, KeyCode::Char('b'), KeyModifiers::ALT);
+        press(&mut input, KeyCode::Char('b'), KeyModifiers::ALT);
+        press(&mut input, KeyCode::Char('d'), KeyModifiers::ALT);
+        type_text(&mut input, "typo ");
+        assert_eq!(input.buffer, "fix the typo here");
+        press(&mut input, KeyCode::Char('e'), KeyModifiers::CONTROL);
+        press(&mut input, KeyCode::Left, KeyModifiers::NONE);
+        press(&mut input, KeyCode::Char('k'), KeyModifiers::CONTROL);
+        assert_eq!(input.buffer, "fix the typo her");
+        press(&mut input, KeyCode::Char('a'), KeyModifiers::CONTROL);
+        press(&mut input, KeyCode::Char('f'), KeyModifiers::ALT);
+        press(&mut input, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(input.buffer, "the typo her");
+        assert_eq!(input.cursor, 0);
+    }
+
+    fn submit(input: &mut InputHandler, text: &str, modifiers: KeyModifiers) {
+        type_text(input, text);
+        press(input, KeyCode::Enter, modifiers);
+    }
+
+    #[test]
+    fn history_recalls_submitted_messages() {
+        let mut input = InputHandler::new(2);
+        submit(&mut input, "first steering", KeyModifiers::NONE);
+        submit(&mut input, "a follow-up", KeyModifiers::ALT);
+        submit(&mut input, ":3", KeyModifiers::NONE);
+
+        // Up on an (emptied) buffer recalls the last message; view commands
+        // aren't recorded.
+        type_text(&mut input, "x");
+        press(&mut input, KeyCode::Backspace, KeyModifiers::NONE);
+        press(&mut input, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(input.buffer, "a follow-up");
+        press(&mut input, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(input.buffer, "first steering");
+        press(&mut input, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(input.buffer, "first steering");
+        press(&mut input, KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(input.buffer, "a follow-up");
+        press(&mut input, KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(input.buffer, "");
+    }
+
+    #[test]
+    fn editing_recalled_entry_keeps_history() {
+        let mut input = InputHandler::new(2);
+        submit(&mut input, "run the tests", KeyModifiers::NONE);
+
+        type_text(&mut input, "draft");
+        press(&mut input, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(input.buffer, "run the tests");
+        assert_eq!(input.cursor, 13);
+        type_text(&mut input, " again");
+        press(&mut input, KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(input.buffer, "draft");
+        press(&mut input, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(input.buffer, "run the tests");
+        assert_eq!(input.history, ["run the tests"]);
+
+        // Resending the edited entry adds it; repeating it doesn't.
+        type_text(&mut input, " again");
+        press(&mut input, KeyCode::Enter, KeyModifiers::NONE);
+        submit(&mut input, "run the tests again", KeyModifiers::NONE);
+        assert_eq!(input.history, ["run the tests", "run the tests again"]);
+    }
+
+    #[test]
+    fn ctrl_l_asks_for_a_redraw_and_keeps_the_text() {
+        let mut input = InputHandler::new(2);
+        let ctrl_l = KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL);
+        assert!(matches!(
+            input.handle_key(&ctrl_l, &mut Vec::new()),
+            InputAction::Redraw
+        ));
+        assert!(!input.is_active());
+
+        type_text(&mut input, "half typed");
+        assert!(matches!(
+            input.handle_key(&ctrl_l, &mut Vec::new()),
+            InputAction::Redraw
+        ));
+        let mut out = Vec::new();
+        input.redraw(&mut out);
+        let shown = String::from_utf8_lossy(&out);
+        assert!(shown.contains("half typed"), "{shown:?}");
+        assert!(!shown.contains("typedl"), "{shown:?}");
+    }
+
+    #[test]
+    fn multi_line_paste_is_one_message() {
+        let mut input = InputHandler::new(2);
+        let trace = "error[E0308]: mismatched types\r\n --> src/main.rs:4:5\n";
+        let action = input.handle_paste(trace, &mut Vec::new());
+        assert!(matches!(action, InputAction::Activated('e')));
+        assert!(input.is_active());
+        type_text(&mut input, "why?");
+
+        let mut out = Vec::new();
+        input.redraw(&mut out);
+        let shown = String::from_utf8_lossy(&out);
+        assert!(
+            shown.contains("mismatched types⏎ --> src/main.rs:4:5⏎why?"),
+            "{shown:?}"
+        );
+
+        // Pasting into an active line inserts at the cursor.
+        press(&mut input, KeyCode::Char('a'), KeyModifiers::CONTROL);
+        let action = input.handle_paste("context:\n", &mut Vec::new());
+        assert!(matches!(action, InputAction::None));
+
+        let action = input.handle_key(
+            &KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+            &mut Vec::new(),
+        );
+        assert!(matches!(
+            action,
+            InputAction::Submit(text, InputMode::Steering)
+                if text == "context:\nerror[E0308]: mismatched types\n --> src/main.rs:4:5\nwhy?"
+        ));
+    }
+
+    #[test]
+    fn kill_ring_yank_and_rotate() {
+        let mut input = InputHandler::new(2);
+        type_text(&mut input, "hello world");
+        press(&mut input, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        press(&mut input, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(input.buffer, "");
+
+        press(&mut input, KeyCode::Char('y'), KeyModifiers::CONTROL);
+        assert_eq!(input.buffer, "hello ");
+        press(&mut input, KeyCode::Char('y'), KeyModifiers::ALT);
+        assert_eq!(input.buffer, "world");
+        press(&mut input, KeyCode::Char('y'), KeyModifiers::ALT);
+        assert_eq!(input.buffer, "hello ");
+
+        // Any other key ends the yank; Alt+Y then does nothing.
+        type_text(&mut input, "!");
+        press(&mut input, KeyCode::Char('y'), KeyModifiers::ALT);
+        assert_eq!(input.buffer, "hello !");
+    }
+
+    #[test]
+    fn raw_command_only_with_debug_protocol() {
+        let mut out = Vec::new();
+        let mut submit = |input: &mut InputHandler| {
+            input.activate();
+            type_text(input, r#":raw {"type":"x"}"#);
+            input.handle_key(&KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), &mut out)
+        };
+        let mut input = InputHandler::new(2);
+        assert!(matches!(
+            submit(&mut input),
+            InputAction::Submit(text, InputMode::Steering) if text == r#":raw {"type":"x"}"#
+        ));
+        input.set_raw_commands(true);
+        assert!(matches!(
+            submit(&mut input),
+            InputAction::Raw(line) if line == r#"{"type":"x"}"#
+        ));
+        assert_eq!(parse_raw_command(":raw"), Some(String::new()));
+        assert_eq!(parse_raw_command(":rawx {}"), None);
+    }
+
+    #[test]
+    fn parse_note_command_forms() {
+        assert_eq!(
+            parse_note_command(":note flaky test again"),
+            Some(("flaky test again".to_string(), false))
+        );
+        assert_eq!(
+            parse_note_command("  :note!  use the v2 API "),
+            Some(("use the v2 API".to_string(), true))
+        );
+        assert_eq!(parse_note_command(":note"), Some((String::new(), false)));
+        assert_eq!(parse_note_command(":notes"), None);
+        assert_eq!(parse_note_command(":notebook x"), None);
+        assert_eq!(parse_note_command("note this"), None);
+        assert_eq!(parse_view_command(":notes"), Some("notes".to_string()));
+    }
+
     #[test]
     fn parse_view_command_numeric() {
         assert_eq!(parse_view_command(":1"), Some("1".to_string()));
         assert_eq!(parse_view_command(":42"), Some("42".to_string()));
-        assert_eq!(parse_view_command(":0"), None);
-        assert_eq!(parse_view_command(": 3"), None); // space not allowed
+        assert_eq!(parse_view_command(":03"), Some("3".to_string()));
+        assert_eq!(parse_view_command(": 3"), Some("3".to_string()));
+        assert_eq!(parse_view_command("  :3  "), Some("3".to_string()));
     }
 
     #[test]
     fn parse_view_command_slash_notation() {
         assert_eq!(parse_view_command(":2/1"), Some("2/1".to_string()));
         assert_eq!(parse_view_command(":10/3"), Some("10/3".to_string()));
-        assert_eq!(parse_view_command(":0/1"), None);
-        assert_eq!(parse_view_command(":1/0"), None);
-        assert_eq!(parse_view_command(":a/1"), None);
     }
 
     #[test]
@@ -478,12 +1034,65 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_view_command_search() {
+        assert_eq!(
+            parse_view_command(":/cargo test"),
+            Some("/cargo test".to_string())
+        );
+        assert_eq!(
+            parse_view_command(" : /cargo test/2 "),
+            Some("/cargo test/2".to_string())
+        );
+        assert_eq!(parse_view_command(":/"), Some("/".to_string()));
+    }
+
+    #[test]
+    fn parse_view_command_passes_malformed_through() {
+        // Lookup explains these instead of sending them to claude.
+        assert_eq!(parse_view_command(":0"), Some("0".to_string()));
+        assert_eq!(parse_view_command(":a/1"), Some("a/1".to_string()));
+        assert_eq!(parse_view_command(":[0]"), Some("[0]".to_string()));
+        assert_eq!(
+            parse_view_command(":Bash[abc]"),
+            Some("Bash[abc]".to_string())
+        );
+        assert_eq!(
+            parse_view_command(":Bash[2/1]"),
+            Some("Bash[2/1]".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_queue_command_forms() {
+        assert_eq!(parse_queue_command(":queue"), Some(QueueCommand::List));
+        assert_eq!(parse_queue_command(" :queue "), Some(QueueCommand::List));
+        assert_eq!(
+            parse_queue_command(":queue drop 2"),
+            Some(QueueCommand::Drop(2))
+        );
+        assert_eq!(parse_queue_command(":q-1"), Some(QueueCommand::Drop(1)));
+        // Typos list the queue instead of being sent as steering.
+        assert_eq!(
+            parse_queue_command(":queue drop two"),
+            Some(QueueCommand::List)
+        );
+        assert_eq!(parse_queue_command(":queued"), None);
+        assert_eq!(parse_queue_command(":q-x"), None);
+        assert_eq!(parse_queue_command("queue"), None);
+    }
+
+    #[test]
+    fn parse_view_command_bare_colon_is_index() {
+        assert_eq!(parse_view_command(":"), Some(String::new()));
+        assert_eq!(parse_view_command("  :  "), Some(String::new()));
+    }
+
     #[test]
     fn parse_view_command_invalid() {
         assert_eq!(parse_view_command("hello"), None);
         assert_eq!(parse_view_command(""), None);
-        assert_eq!(parse_view_command(":"), None);
-        assert_eq!(parse_view_command(":[0]"), None); // empty name
-        assert_eq!(parse_view_command(":Bash[abc]"), None); // non-integer index
+        assert_eq!(parse_view_command(":)"), None);
+        assert_eq!(parse_view_command(":Bash is broken"), None);
     }
 }
@@ -0,0 +1,130 @@
+//! `--json-events`: the session as NDJSON on stdout, for another program to
+//! consume while coven still handles resuming, steering and forks.
+//!
+//! Every inbound claude event is written as one line, as parsed. Coven adds
+//! lines of its own, all with a `coven_` type: `coven_user_message`,
+//! `coven_steering_sent` and `coven_followup_sent` for messages the user
+//! typed (the last for follow-ups sent from the queue), and a final
+//! `coven_result` with the session ID, total cost and last result text.
+
+use std::io::Write;
+
+use serde_json::{Value, json};
+
+use crate::protocol::types::InboundEvent;
+
+/// Writes events as JSON lines. A failed write (say, the reading end of a
+/// pipe went away) stops the stream rather than the session.
+pub struct JsonEvents {
+    out: Option<Box<dyn Write + Send>>,
+}
+
+impl JsonEvents {
+    pub fn new(out: Box<dyn Write + Send>) -> Self {
+        Self { out: Some(out) }
+    }
+
+    pub fn inbound(&mut self, event: &InboundEvent) {
+        if let Ok(value) = serde_json::to_value(event) {
+            self.write(&value);
+        }
+    }
+
+    /// A message the user sent: `kind` is `user_message`, `steering_sent`
+    /// or `followup_sent`.
+    pub fn user_message(&mut self, kind: &str, text: &str) {
+        self.write(&json!({ "type": format!("coven_{kind}"), "text": text }));
+    }
+
+    /// The closing `coven_result` line.
+    pub fn result(&mut self, session_id: Option<&str>, total_cost_usd: f64, result: &str) {
+        self.write(&json!({
+            "type": "coven_result",
+            "session_id": session_id,
+            "total_cost_usd": total_cost_usd,
+            "result": result,
+        }));
+    }
+
+    fn write(&mut self, value: &Value) {
+        let Some(out) = &mut self.out else {
+            return;
+        };
+        let written = serde_json::to_writer(&mut *out, value)
+            .map_err(std::io::Error::from)
+            .and_then(|()| out.write_all(b"\n"))
+            .and_then(|()| out.flush());
+        if written.is_err() {
+            self.out = None;
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Accepts one line, then fails like a closed pipe.
+    struct ClosesAfterOneLine(SharedBuf);
+
+    impl Write for ClosesAfterOneLine {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0.0.lock().unwrap().ends_with(b"\n") {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn coven_lines_are_typed() {
+        let buf = SharedBuf::default();
+        let mut events = JsonEvents::new(Box::new(buf.clone()));
+        events.user_message("steering_sent", "use the \"fast\" path");
+        events.result(Some("s1"), 0.25, "done");
+        let out = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            out,
+            concat!(
+                r#"{"text":"use the \"fast\" path","type":"coven_steering_sent"}"#,
+                "\n",
+                r#"{"result":"done","session_id":"s1","total_cost_usd":0.25,"type":"coven_result"}"#,
+                "\n",
+            )
+        );
+    }
+
+    #[test]
+    fn a_failed_write_ends_the_stream() {
+        let buf = SharedBuf::default();
+        let mut events = JsonEvents::new(Box::new(ClosesAfterOneLine(buf.clone())));
+        events.user_message("user_message", "one");
+        events.user_message("user_message", "two");
+        assert!(events.out.is_none());
+        events.result(None, 0.0, "");
+        let out = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(out, "{\"text\":\"one\",\"type\":\"coven_user_message\"}\n");
+    }
+}
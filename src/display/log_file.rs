@@ -0,0 +1,251 @@
+//! `--log-file`: a plain-text copy of everything the renderer writes.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// Writer that passes output through to `inner` and appends a plain-text
+/// copy to the log file, if there is one.
+///
+/// A failed log write disables the log rather than interrupting the display.
+pub struct LogTee<W> {
+    inner: W,
+    log: Option<PlainText<File>>,
+}
+
+impl<W: Write> LogTee<W> {
+    /// Wrap `inner`, appending to `path` (created along with its parent
+    /// directories if missing) when given.
+    pub fn open(inner: W, path: Option<&Path>) -> Result<Self> {
+        let log = match path {
+            Some(path) => {
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(parent).with_context(|| {
+                        format!("failed to create log directory {}", parent.display())
+                    })?;
+                }
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("failed to open log file {}", path.display()))?;
+                Some(PlainText::new(file))
+            }
+            None => None,
+        };
+        Ok(Self { inner, log })
+    }
+}
+
+impl<W: Write> Write for LogTee<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if let Some(log) = &mut self.log
+            && log.write_all(&buf[..n]).is_err()
+        {
+            self.log = None;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(log) = &mut self.log
+            && log.flush().is_err()
+        {
+            self.log = None;
+        }
+        self.inner.flush()
+    }
+}
+
+/// Where [`PlainText`] is within an escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    None,
+    /// After ESC.
+    Start,
+    /// Inside `ESC [ … final`, collecting the numeric parameter.
+    Csi(usize),
+    /// Inside `ESC ] … BEL` or `ESC ] … ESC \`.
+    Osc,
+    /// ESC seen inside an OSC sequence.
+    OscEsc,
+}
+
+/// Writer that strips terminal escape sequences and carriage returns,
+/// writing whole lines to `out`.
+///
+/// `\r\n` becomes `\n`. A bare `\r` or cursor-left (`ESC [ n D`) rewinds the
+/// line being built, as on a terminal, so redrawn lines are logged once.
+/// The unfinished line is written when the writer is dropped.
+struct PlainText<F: Write> {
+    out: F,
+    line: Vec<u8>,
+    escape: Escape,
+    pending_cr: bool,
+}
+
+impl<F: Write> PlainText<F> {
+    fn new(out: F) -> Self {
+        Self {
+            out,
+            line: Vec::new(),
+            escape: Escape::None,
+            pending_cr: false,
+        }
+    }
+
+    fn push(&mut self, byte: u8) -> io::Result<()> {
+        if std::mem::take(&mut self.pending_cr) && byte != b'\n' {
+            self.line.clear();
+        }
+        self.escape = match (self.escape, byte) {
+            (Escape::None, 0x1b) => Escape::Start,
+            (Escape::None, b'\r') => {
+                self.pending_cr = true;
+                Escape::None
+            }
+            (Escape::None, b'\n') => {
+                self.line.push(b'\n');
+                self.out.write_all(&self.line)?;
+                self.line.clear();
+                Escape::None
+            }
+            (Escape::None, _) => {
+                self.line.push(byte);
+                Escape::None
+            }
+            (Escape::Start, b'[') => Escape::Csi(0),
+            (Escape::Start, b']') => Escape::Osc,
+            (Escape::Csi(n), b'0'..=b'9') => Escape::Csi(
+                n.saturating_mul(10)
+                    .saturating_add(usize::from(byte - b'0')),
+            ),
+            (Escape::Csi(n), b'D') => {
+                self.move_left(n.max(1));
+                Escape::None
+            }
+            (Escape::Start, _)
+            | (Escape::Csi(_), 0x40..=0x7e)
+            | (Escape::Osc, 0x07)
+            | (Escape::OscEsc, b'\\') => Escape::None,
+            (Escape::Csi(n), _) => Escape::Csi(n),
+            (Escape::Osc | Escape::OscEsc, 0x1b) => Escape::OscEsc,
+            (Escape::Osc | Escape::OscEsc, _) => Escape::Osc,
+        };
+        Ok(())
+    }
+
+    /// Drop the last `columns` characters from the current line.
+    fn move_left(&mut self, columns: usize) {
+        for _ in 0..columns {
+            // Pop one UTF-8 character: continuation bytes, then its lead byte.
+            while self.line.last().is_some_and(|b| b & 0xc0 == 0x80) {
+                self.line.pop();
+            }
+            if self.line.pop().is_none() {
+                break;
+            }
+        }
+    }
+}
+
+impl<F: Write> Write for PlainText<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.push(byte)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl<F: Write> Drop for PlainText<F> {
+    fn drop(&mut self) {
+        if !self.line.is_empty() {
+            self.line.push(b'\n');
+            self.out.write_all(&self.line).ok();
+        }
+        self.out.flush().ok();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn plain(chunks: &[&str]) -> String {
+        let mut out = Vec::new();
+        {
+            let mut writer = PlainText::new(&mut out);
+            for chunk in chunks {
+                writer.write_all(chunk.as_bytes()).unwrap();
+            }
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn strips_colors_and_normalizes_newlines() {
+        assert_eq!(
+            plain(&["\x1b[1;32mDone\x1b[0m $0.01\r\n", "next\r\n"]),
+            "Done $0.01\nnext\n"
+        );
+    }
+
+    #[test]
+    fn strips_osc_titles() {
+        assert_eq!(plain(&["\x1b]0;cv title\x07a\n"]), "a\n");
+        assert_eq!(plain(&["\x1b]0;cv title\x1b\\b\n"]), "b\n");
+    }
+
+    #[test]
+    fn sequences_split_across_writes() {
+        assert_eq!(
+            plain(&["x\x1b", "[3", "1mred\x1b[0", "m\r", "\n"]),
+            "xred\n"
+        );
+    }
+
+    #[test]
+    fn bare_carriage_return_rewinds_line() {
+        assert_eq!(
+            plain(&["  \u{2026}working (2s)\r\x1b[2K", "[1] Bash ls\r\n"]),
+            "[1] Bash ls\n"
+        );
+    }
+
+    #[test]
+    fn cursor_left_erases_characters() {
+        assert_eq!(
+            plain(&["[1] Bash sleep 5  \u{2026}working (3s)\x1b[15D\x1b[K ok\r\n"]),
+            "[1] Bash sleep 5 ok\n"
+        );
+    }
+
+    #[test]
+    fn unfinished_line_written_on_drop() {
+        assert_eq!(plain(&["partial"]), "partial\n");
+    }
+
+    #[test]
+    fn tee_appends_and_creates_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs").join("session.log");
+        for text in ["first\r\n", "\x1b[2msecond\x1b[0m\r\n"] {
+            let mut screen = Vec::new();
+            let mut tee = LogTee::open(&mut screen, Some(&path)).unwrap();
+            tee.write_all(text.as_bytes()).unwrap();
+            tee.flush().unwrap();
+            drop(tee);
+            assert_eq!(screen, text.as_bytes());
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+    }
+}
@@ -0,0 +1,307 @@
+//! Inline markdown styling for streamed assistant text (`--render-markdown`).
+//!
+//! Text arrives in arbitrary chunks, so a marker can be split across two
+//! deltas (`*` then `*bold**`). [`MarkdownStream`] holds back a trailing
+//! fragment that might still turn into a marker, and bold text until its
+//! closing `**` shows up on the same line, and styles the rest as it comes.
+//! Only `**bold**`, `` `code` `` spans, `#` headings and code fences are
+//! recognized; everything else passes through untouched.
+
+use crossterm::style::ContentStyle;
+
+use super::theme;
+
+/// How a run of text is styled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub bold: bool,
+    /// An inline code span or a fenced code block.
+    pub code: bool,
+    pub heading: bool,
+}
+
+impl Style {
+    pub fn content_style(self) -> ContentStyle {
+        let mut style = if self.code {
+            theme::inline_code()
+        } else {
+            ContentStyle::default()
+        };
+        if self.heading {
+            style.attributes.extend(theme::heading().attributes);
+        }
+        if self.bold {
+            style.attributes.extend(theme::bold().attributes);
+        }
+        style
+    }
+}
+
+/// A run of text in one style, markers removed.
+pub type Span = (Style, String);
+
+const FENCE: &str = "```";
+
+/// Stateful inline parser for one text block.
+#[derive(Debug, Default)]
+pub struct MarkdownStream {
+    /// The end of the last chunk, held back until it's clear whether it's a
+    /// marker.
+    pending: String,
+    /// Bold, code and heading state of the current line.
+    style: Style,
+    /// Inside a code fence: lines are passed through literally.
+    in_fence: bool,
+    /// Past the start of the current line.
+    mid_line: bool,
+    /// The last character on this line was alphanumeric, so a `**` here is
+    /// inside a word (`2**10`) and can't open bold.
+    after_word: bool,
+}
+
+impl MarkdownStream {
+    /// Style the next chunk of streamed text.
+    pub fn feed(&mut self, chunk: &str) -> Vec<Span> {
+        let mut text = std::mem::take(&mut self.pending);
+        text.push_str(chunk);
+        let mut spans = Vec::new();
+        let mut rest = text.as_str();
+        while !rest.is_empty() {
+            if !self.mid_line {
+                let Some(after) = self.line_start(rest, &mut spans) else {
+                    self.pending = rest.to_string();
+                    break;
+                };
+                rest = after;
+                continue;
+            }
+            if self.in_fence {
+                rest = self.literal_line(rest, &mut spans);
+                continue;
+            }
+            rest = self.inline(rest, &mut spans, false);
+        }
+        spans
+    }
+
+    /// Emit whatever was held back, at the end of the text block, and reset
+    /// for the next one.
+    pub fn finish(&mut self) -> Vec<Span> {
+        let pending = std::mem::take(&mut self.pending);
+        if self.mid_line && !self.in_fence {
+            // Held back mid-line: a marker that never closed is just text.
+            let mut spans = Vec::new();
+            self.inline(&pending, &mut spans, true);
+            *self = Self::default();
+            return spans;
+        }
+        let style = Style {
+            code: self.in_fence || pending.starts_with(FENCE),
+            ..self.style
+        };
+        *self = Self::default();
+        if pending.is_empty() {
+            Vec::new()
+        } else {
+            vec![(style, pending)]
+        }
+    }
+
+    /// Handle the start of a line: fence markers, fenced lines and heading
+    /// prefixes. Returns what's left to parse, or `None` to wait for more
+    /// text.
+    fn line_start<'a>(&mut self, rest: &'a str, spans: &mut Vec<Span>) -> Option<&'a str> {
+        if rest.len() < FENCE.len() && FENCE.starts_with(rest) {
+            return None;
+        }
+        if rest.starts_with(FENCE) {
+            // Hold the whole marker line so the language tag stays with it.
+            let end = rest.find('\n')? + 1;
+            push(spans, code(), &rest[..end]);
+            self.in_fence = !self.in_fence;
+            return Some(&rest[end..]);
+        }
+        if !self.in_fence {
+            let hashes = rest.len() - rest.trim_start_matches('#').len();
+            if hashes == rest.len() && hashes <= 6 {
+                return None;
+            }
+            self.style.heading = (1..=6).contains(&hashes) && rest[hashes..].starts_with(' ');
+        }
+        self.mid_line = true;
+        Some(rest)
+    }
+
+    /// Pass a fenced line through as-is, up to and including its newline.
+    fn literal_line<'a>(&mut self, rest: &'a str, spans: &mut Vec<Span>) -> &'a str {
+        let end = rest.find('\n').map_or(rest.len(), |i| {
+            self.mid_line = false;
+            i + 1
+        });
+        push(spans, code(), &rest[..end]);
+        &rest[end..]
+    }
+
+    /// Style text up to the end of the line. Returns what's left. A chunk
+    /// ending in what may be half a `**`, or a `**` whose line hasn't shown
+    /// a closing one yet, is held back unless `at_end`: an unclosed `**` is
+    /// text. So is a `**` inside a word or before a space, as in `2**10` or
+    /// `a ** b`.
+    fn inline<'a>(&mut self, rest: &'a str, spans: &mut Vec<Span>, at_end: bool) -> &'a str {
+        let mut chars = rest.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\n' => {
+                    // Spans don't carry over a line, so a stray marker can't
+                    // style the rest of the answer.
+                    self.style = Style::default();
+                    self.mid_line = false;
+                    self.after_word = false;
+                    push(spans, self.style, "\n");
+                    return &rest[i + 1..];
+                }
+                '`' => {
+                    self.style.code = !self.style.code;
+                    self.after_word = false;
+                }
+                '*' if !self.style.code => match chars.peek() {
+                    Some((_, '*')) => {
+                        chars.next();
+                        if self.style.bold {
+                            self.style.bold = false;
+                            continue;
+                        }
+                        let after = &rest[i + 2..];
+                        let line = &after[..after.find('\n').unwrap_or(after.len())];
+                        if self.after_word || line.starts_with(char::is_whitespace) {
+                            push(spans, self.style, "**");
+                        } else if line.contains("**") {
+                            self.style.bold = true;
+                        } else if line.len() < after.len() || at_end {
+                            push(spans, self.style, "**");
+                        } else {
+                            self.pending = rest[i..].to_string();
+                            return "";
+                        }
+                    }
+                    Some(_) => push(spans, self.style, "*"),
+                    None if at_end => push(spans, self.style, "*"),
+                    None => self.pending = rest[i..].to_string(),
+                },
+                _ => {
+                    self.after_word = c.is_alphanumeric();
+                    push(spans, self.style, &rest[i..i + c.len_utf8()]);
+                }
+            }
+        }
+        ""
+    }
+}
+
+fn code() -> Style {
+    Style {
+        code: true,
+        ..Style::default()
+    }
+}
+
+/// Append `text` in `style`, merging with the previous span if it matches.
+fn push(spans: &mut Vec<Span>, style: Style, text: &str) {
+    match spans.last_mut() {
+        Some((last, run)) if *last == style => run.push_str(text),
+        _ => spans.push((style, text.to_string())),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::fmt::Write;
+
+    use super::*;
+
+    const ANSWER: &str = "## Summary\n\nThe **parser** now handles `split **markers**` too.\n\n```rust\nlet x = a ** b; // `literal`\n```\n\n* one *star* stays\n#include isn't a heading\n";
+
+    /// Feed `chunks` and show each styled run as `[b c h|text]`.
+    fn render(chunks: &[&str]) -> String {
+        let mut stream = MarkdownStream::default();
+        let mut spans: Vec<Span> = Vec::new();
+        for chunk in chunks {
+            for (style, text) in stream.feed(chunk) {
+                push(&mut spans, style, &text);
+            }
+        }
+        for (style, text) in stream.finish() {
+            push(&mut spans, style, &text);
+        }
+        let mut out = String::new();
+        for (style, text) in spans {
+            if style == Style::default() {
+                out.push_str(&text);
+                continue;
+            }
+            let flags = [(style.bold, "b"), (style.code, "c"), (style.heading, "h")]
+                .iter()
+                .filter(|(on, _)| *on)
+                .map(|(_, flag)| *flag)
+                .collect::<Vec<_>>()
+                .join(" ");
+            write!(out, "[{flags}|{text}]").unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn styles_a_whole_answer() {
+        insta::assert_snapshot!(render(&[ANSWER]));
+    }
+
+    #[test]
+    fn chunk_boundaries_dont_matter() {
+        let whole = render(&[ANSWER]);
+        let chars: Vec<String> = ANSWER.chars().map(String::from).collect();
+        let one_by_one: Vec<&str> = chars.iter().map(String::as_str).collect();
+        assert_eq!(render(&one_by_one), whole);
+        for split in 1..ANSWER.len() {
+            assert_eq!(render(&[&ANSWER[..split], &ANSWER[split..]]), whole);
+        }
+    }
+
+    #[test]
+    fn split_bold_marker_is_held_back() {
+        let mut stream = MarkdownStream::default();
+        assert_eq!(stream.feed("a *"), [(Style::default(), "a ".to_string())]);
+        let bold = Style {
+            bold: true,
+            ..Style::default()
+        };
+        assert_eq!(stream.feed("*b"), []);
+        assert_eq!(
+            stream.feed("** c"),
+            [
+                (bold, "b".to_string()),
+                (Style::default(), " c".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_markers_stop_at_the_line_end() {
+        insta::assert_snapshot!(render(&["2**10 is `big\n", "next"]));
+    }
+
+    #[test]
+    fn trailing_fragment_is_flushed_at_the_end() {
+        assert_eq!(render(&["ends with *"]), "ends with *");
+        assert_eq!(render(&["```py"]), "[c|```py]");
+        assert_eq!(render(&["##"]), "##");
+    }
+
+    #[test]
+    fn unclosed_bold_marker_is_literal() {
+        assert_eq!(render(&["2**10 is big"]), "2**10 is big");
+        assert_eq!(render(&["2**", "10 is **big**"]), "2**10 is [b|big]");
+        assert_eq!(render(&["a ** b **c**"]), "a ** b [b|c]");
+        assert_eq!(render(&["a **b", "** c"]), "a [b|b] c");
+    }
+}
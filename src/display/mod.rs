@@ -1,12 +1,138 @@
 pub mod input;
+pub mod json_events;
+pub mod log_file;
+pub mod markdown;
 pub mod renderer;
 pub mod theme;
 pub mod tool_format;
 
-/// Query the current terminal width, defaulting to 80.
-pub(crate) fn term_width() -> usize {
-    crossterm::terminal::size()
-        .map(|(w, _)| w as usize)
-        .unwrap_or(80)
-        .max(1)
+use serde::{Deserialize, Serialize};
+
+/// The terminal's size in columns and rows.
+///
+/// Rendering code reads it from the renderer ([`renderer::Renderer::width`],
+/// [`renderer::Renderer::height`]), never from the terminal: the live size
+/// is queried once per command and recorded, so replay renders at the size
+/// the recording was made at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerminalSize {
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Narrower than this, truncated lines shrink to `...` and the input wraps
+/// every few characters, so coven renders at the default width instead.
+pub const MIN_WIDTH: usize = 20;
+
+impl TerminalSize {
+    /// Too narrow to render at (a collapsed tmux pane, a CI wrapper
+    /// reporting 0 columns).
+    pub fn is_degraded(self) -> bool {
+        self.width < MIN_WIDTH
+    }
+
+    /// The size to render at: the default width in place of a degraded one.
+    #[must_use]
+    pub fn usable(self) -> Self {
+        if self.is_degraded() {
+            Self {
+                width: Self::default().width,
+                ..self
+            }
+        } else {
+            self
+        }
+    }
+}
+
+impl Default for TerminalSize {
+    /// What's assumed when there's no terminal to ask.
+    fn default() -> Self {
+        Self {
+            width: 80,
+            height: 24,
+        }
+    }
+}
+
+/// Turn the terminal's bracketed paste mode on or off. While it's on, a
+/// paste arrives as one `Event::Paste` instead of a stream of keys, so its
+/// newlines don't submit the input line. Goes to stderr when stdout isn't
+/// the terminal (`--json-events`).
+pub fn set_bracketed_paste(enabled: bool) {
+    use std::io::IsTerminal;
+
+    use crossterm::event::{DisableBracketedPaste, EnableBracketedPaste};
+
+    fn send(out: &mut impl std::io::Write, enabled: bool) {
+        if enabled {
+            crossterm::execute!(out, EnableBracketedPaste).ok();
+        } else {
+            crossterm::execute!(out, DisableBracketedPaste).ok();
+        }
+    }
+    if std::io::stdout().is_terminal() {
+        send(&mut std::io::stdout(), enabled);
+    } else {
+        send(&mut std::io::stderr(), enabled);
+    }
+}
+
+/// Query the real terminal's size, falling back to the default for any
+/// dimension it can't report. Only `setup_display` calls this, through the
+/// VCR; everything else asks the renderer.
+pub(crate) fn query_terminal_size() -> TerminalSize {
+    let fallback = TerminalSize::default();
+    match crossterm::terminal::size() {
+        Ok((width, height)) => TerminalSize {
+            width: usize::from(width),
+            height: if height == 0 {
+                fallback.height
+            } else {
+                usize::from(height)
+            },
+        },
+        Err(_) => fallback,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::path::Path;
+
+    /// Direct terminal size queries make rendering depend on whatever
+    /// terminal runs the tests. Only this module queries; only
+    /// `setup_display` calls the query.
+    #[test]
+    fn terminal_size_is_only_queried_in_setup() {
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut offenders = Vec::new();
+        let mut dirs = vec![src.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let relative = path
+                    .strip_prefix(&src)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let text = std::fs::read_to_string(&path).unwrap();
+                let queries = text.contains("terminal::size(") && relative != "display/mod.rs";
+                let calls = text.contains("query_terminal_size(")
+                    && !matches!(relative.as_str(), "display/mod.rs" | "commands/mod.rs");
+                if queries || calls {
+                    offenders.push(relative);
+                }
+            }
+        }
+        assert!(
+            offenders.is_empty(),
+            "terminal size queried in {offenders:?}"
+        );
+    }
 }
@@ -1,15 +1,29 @@
 use std::collections::HashMap;
 use std::io::{self, Write};
+use std::time::{Duration, Instant};
 
+use crossterm::cursor::{MoveLeft, MoveTo};
 use crossterm::queue;
 use crossterm::style::Print;
+use crossterm::terminal::{Clear, ClearType};
 use serde_json::Value;
-use unicode_width::UnicodeWidthChar;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use super::term_width;
+use super::TerminalSize;
+use super::json_events::JsonEvents;
+use super::markdown::{MarkdownStream, Span, Style};
 use super::theme;
-use super::tool_format::{first_line, format_tool_detail, format_tool_view};
-use crate::protocol::types::{RateLimitInfo, StreamEvent};
+use super::tool_format::{first_line, format_tool_detail, format_tool_view, is_retry, retry_note};
+use crate::fork::ChildOutcome;
+use crate::protocol::types::{
+    ApiError, ApiErrorKind, AssistantContentBlock, InboundEvent, PermissionDenial, RateLimitInfo,
+    ResultSubtype, SessionResult, StreamEvent,
+};
+use crate::session::runner::PermissionMode;
+use crate::session::state::TokenUsage;
+use crate::timing::format_elapsed;
+use crate::utc::UtcDateTime;
+use crate::worktree::LandSummary;
 
 /// Context for rendering keybinding hints.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -29,18 +43,80 @@ pub struct StoredMessage {
     pub content: String,
     /// Tool result text, attached when the result arrives.
     pub result: Option<String>,
+    /// The tool result was an error.
+    pub failed: bool,
+}
+
+/// Why a view query didn't resolve to a message.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum QueryError {
+    #[error("can't read :{0} — expected :N, :P/C, :Name, :Name[index] or :/text")]
+    Malformed(String),
+    #[error("no message [{0}]")]
+    NoSuchNumber(String),
+    #[error("no message contains {0:?}")]
+    NoMatch(String),
+    #[error("/{pattern}/{rank} is out of range — {}", match_count(*.count))]
+    MatchOutOfRange {
+        pattern: String,
+        rank: usize,
+        count: usize,
+    },
+    #[error("no {name} messages{}", suggest(.suggestions))]
+    UnknownTool {
+        name: String,
+        /// Stored tool names close to `name`, best match first.
+        suggestions: Vec<String>,
+    },
+    #[error("{name}[{index}] is out of range — {}", valid_range(*.count))]
+    IndexOutOfRange {
+        name: String,
+        index: isize,
+        count: usize,
+    },
+}
+
+fn suggest(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!(" — did you mean {}?", suggestions.join(", "))
+    }
+}
+
+fn match_count(count: usize) -> String {
+    match count {
+        1 => "only 1 message matches".to_string(),
+        n => format!("only {n} messages match"),
+    }
+}
+
+fn valid_range(count: usize) -> String {
+    match count {
+        1 => "only [0] or [-1] exists".to_string(),
+        n => format!("valid indexes are 0..={} or -{n}..=-1", n - 1),
+    }
 }
 
 /// Format the content of a message for display, looking it up by label query.
 ///
-/// Supports two query forms:
+/// Supports three query forms:
 /// - Numeric: `"3"` or `"2/1"` — matches `[3]` or `[2/1]` label prefix
 /// - Label-based: `"Bash[0]"`, `"Edit[-1]"`, or plain `"Bash"` — matches
-///   the tool name portion of labels, then indexes into matches
-///
-/// Returns `None` if no message matches.
-pub fn format_message(messages: &[StoredMessage], query: &str) -> Option<String> {
-    let msg = resolve_query(messages, query)?;
+///   the tool name portion of labels (case-insensitively), then indexes
+///   into matches
+/// - Search: `"/cargo test"` or `"/cargo test/2"` — the most recent (or
+///   second most recent) message whose content or result contains the text,
+///   case-insensitively. The header says how many matched.
+pub fn format_message(messages: &[StoredMessage], query: &str) -> Result<String, QueryError> {
+    let (msg, matched) = match query.trim().strip_prefix('/') {
+        Some(search) => {
+            let hit = resolve_search(messages, search)?;
+            let note = format!("  (match {} of {})", hit.rank, hit.count);
+            (hit.message, note)
+        }
+        None => (resolve_query(messages, query)?, String::new()),
+    };
     let tool_name = tool_name_from_label(&msg.label);
 
     // Try tool-specific formatting; fall back to stored content (pretty JSON or plain text)
@@ -49,44 +125,242 @@ pub fn format_message(messages: &[StoredMessage], query: &str) -> Option<String>
         .and_then(|input| format_tool_view(tool_name, &input))
         .unwrap_or_else(|| msg.content.clone());
 
-    Some(match &msg.result {
+    Ok(match &msg.result {
         Some(result) => format!(
-            "{}\n\n{}\n\n--- Result ---\n\n{}",
+            "{}{matched}\n\n{}\n\n--- Result ---\n\n{}",
             msg.label, content, result
         ),
-        None => format!("{}\n\n{}", msg.label, content),
+        None => format!("{}{matched}\n\n{}", msg.label, content),
     })
 }
 
+/// Format the `:notes` listing.
+pub fn format_notes(notes: &[String]) -> String {
+    if notes.is_empty() {
+        return "Notes\n\nNo notes yet \u{2014} add one with :note <text>".to_string();
+    }
+    let items: Vec<_> = notes.iter().map(|n| format!("- {n}")).collect();
+    format!("Notes\n\n{}", items.join("\n"))
+}
+
+/// Format the bare `:` listing: one line per stored message with the
+/// first line of its detail, and `✗` on failed tool calls.
+pub fn format_index(messages: &[StoredMessage]) -> String {
+    if messages.is_empty() {
+        return "Messages\n\nNo messages yet".to_string();
+    }
+    format!("Messages\n\n{}", index_lines(messages).join("\n"))
+}
+
+/// One line per message for the `:` listing, labels padded to line up.
+fn index_lines(messages: &[StoredMessage]) -> Vec<String> {
+    let label_width = messages.iter().map(|m| m.label.width()).max().unwrap_or(0);
+    messages
+        .iter()
+        .map(|msg| {
+            let detail = match serde_json::from_str::<Value>(&msg.content) {
+                Ok(input @ Value::Object(_)) => {
+                    format_tool_detail(tool_name_from_label(&msg.label), &input)
+                }
+                _ => msg.content.clone(),
+            };
+            let detail = truncate_to_width(first_line(detail.trim()), INDEX_DETAIL_WIDTH);
+            let padding = " ".repeat(label_width - msg.label.width());
+            let marker = if msg.failed { "  \u{2717}" } else { "" };
+            format!("{}{padding}  {detail}{marker}", msg.label)
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+/// Label of the message listing a result's permission denials.
+const DENIALS_LABEL: &str = "Denials";
+
+/// Messages recapped when the screen is redrawn.
+const RECAP_MESSAGES: usize = 5;
+
+/// Widest detail shown per line of the `:` listing.
+const INDEX_DETAIL_WIDTH: usize = 60;
+
+/// The most recently used tool that has been called more than once, to
+/// show off `:Name[-1]` in the Done line's hint.
+fn repeated_tool(messages: &[StoredMessage]) -> Option<&str> {
+    let tools: Vec<&str> = messages
+        .iter()
+        .filter(|m| serde_json::from_str::<Value>(&m.content).is_ok_and(|v| v.is_object()))
+        .map(|m| tool_name_from_label(&m.label))
+        .collect();
+    tools
+        .iter()
+        .rev()
+        .find(|name| tools.iter().filter(|t| t == name).count() > 1)
+        .copied()
+}
+
 /// Resolve a view query to a stored message.
-fn resolve_query<'a>(messages: &'a [StoredMessage], query: &str) -> Option<&'a StoredMessage> {
+fn resolve_query<'a>(
+    messages: &'a [StoredMessage],
+    query: &str,
+) -> Result<&'a StoredMessage, QueryError> {
+    let query = query.trim();
+    let malformed = || QueryError::Malformed(query.to_string());
+
+    if let Some(search) = query.strip_prefix('/') {
+        return resolve_search(messages, search).map(|hit| hit.message);
+    }
+
     // Numeric/P-C: starts with a digit → bracket-prefix match
     if query.starts_with(|c: char| c.is_ascii_digit()) {
+        let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        let well_formed = match query.split_once('/') {
+            Some((p, c)) => is_number(p) && is_number(c),
+            None => is_number(query),
+        };
+        if !well_formed {
+            return Err(malformed());
+        }
         let prefix = format!("[{query}]");
-        return messages.iter().find(|m| m.label.starts_with(&prefix));
+        return messages
+            .iter()
+            .find(|m| m.label.starts_with(&prefix))
+            .ok_or_else(|| QueryError::NoSuchNumber(query.to_string()));
     }
 
     // Label-based: parse Name[index] or plain Name (defaults to index 0)
     let (name, index) = if let Some(bracket) = query.find('[') {
-        let index_str = query[bracket + 1..].strip_suffix(']')?;
-        let index: isize = index_str.parse().ok()?;
+        let index_str = query[bracket + 1..]
+            .strip_suffix(']')
+            .ok_or_else(malformed)?;
+        let index: isize = index_str.trim().parse().map_err(|_| malformed())?;
         (&query[..bracket], index)
     } else {
         (query, 0)
     };
 
-    if name.is_empty() {
-        return None;
+    let valid_name = |n: &str| {
+        !n.is_empty()
+            && n.chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '-' | ':' | '_'))
+    };
+    if !valid_name(name) {
+        return Err(malformed());
+    }
+
+    let matches: Vec<_> = messages
+        .iter()
+        .filter(|m| tool_name_from_label(&m.label).eq_ignore_ascii_case(name))
+        .collect();
+    if matches.is_empty() {
+        return Err(QueryError::UnknownTool {
+            name: name.to_string(),
+            suggestions: close_tool_names(messages, name),
+        });
+    }
+
+    let count = matches.len();
+    let resolved = if index < 0 {
+        count.checked_sub(index.unsigned_abs())
+    } else {
+        Some(index.unsigned_abs())
+    };
+    resolved
+        .and_then(|i| matches.get(i).copied())
+        .ok_or(QueryError::IndexOutOfRange {
+            name: name.to_string(),
+            index,
+            count,
+        })
+}
+
+/// A message found by a `/text` search.
+struct SearchHit<'a> {
+    message: &'a StoredMessage,
+    /// 1 for the most recent match, 2 for the one before, ...
+    rank: usize,
+    count: usize,
+}
+
+/// Resolve the part of a search query after the leading `/`: `text`, or
+/// `text/N` for the Nth most recent match.
+fn resolve_search<'a>(
+    messages: &'a [StoredMessage],
+    search: &str,
+) -> Result<SearchHit<'a>, QueryError> {
+    let (pattern, rank) = match search.rsplit_once('/') {
+        Some((pattern, "")) => (pattern, 1),
+        Some((pattern, n)) if n.chars().all(|c| c.is_ascii_digit()) => {
+            (pattern, n.parse().unwrap_or(0))
+        }
+        _ => (search, 1),
+    };
+    if pattern.is_empty() || rank == 0 {
+        return Err(QueryError::Malformed(format!("/{search}")));
     }
 
+    let wanted = pattern.to_lowercase();
+    let contains = |text: &str| text.to_lowercase().contains(&wanted);
     let matches: Vec<_> = messages
         .iter()
-        .filter(|m| tool_name_from_label(&m.label) == name)
+        .rev()
+        .filter(|m| contains(&m.content) || m.result.as_deref().is_some_and(contains))
         .collect();
+    let count = matches.len();
+    if count == 0 {
+        return Err(QueryError::NoMatch(pattern.to_string()));
+    }
+    let message = matches
+        .get(rank - 1)
+        .copied()
+        .ok_or_else(|| QueryError::MatchOutOfRange {
+            pattern: pattern.to_string(),
+            rank,
+            count,
+        })?;
+    Ok(SearchHit {
+        message,
+        rank,
+        count,
+    })
+}
+
+/// Stored tool names that look like a typo of `name`, closest first (at most 3).
+fn close_tool_names(messages: &[StoredMessage], name: &str) -> Vec<String> {
+    let wanted = name.to_lowercase();
+    let mut scored: Vec<(usize, &str)> = Vec::new();
+    for msg in messages {
+        let tool = tool_name_from_label(&msg.label);
+        if scored.iter().any(|(_, t)| *t == tool) {
+            continue;
+        }
+        let lower = tool.to_lowercase();
+        let distance = edit_distance(&wanted, &lower);
+        let related = lower.contains(&wanted) || wanted.contains(&lower);
+        if related || distance <= 2 {
+            scored.push((distance, tool));
+        }
+    }
+    scored.sort_by_key(|(d, _)| *d);
+    scored
+        .into_iter()
+        .take(3)
+        .map(|(_, t)| t.to_string())
+        .collect()
+}
 
-    let len = matches.len().cast_signed();
-    let resolved = if index < 0 { len + index } else { index };
-    matches.get(usize::try_from(resolved).ok()?).copied()
+/// Levenshtein distance between two strings, by chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = prev[j] + usize::from(ca != *cb);
+            row.push(substitute.min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+    prev[b.len()]
 }
 
 /// Extract the tool name from a stored label like `"[2] Bash"` → `"Bash"`.
@@ -94,10 +368,76 @@ fn tool_name_from_label(label: &str) -> &str {
     label.find("] ").map_or(label, |pos| &label[pos + 2..])
 }
 
+/// A top-level tool call and whether its result was an error.
+struct PrevToolCall {
+    name: String,
+    input: Value,
+    failed: bool,
+}
+
+/// A tool call rendered before its name was known, e.g. because its
+/// `input_json_delta` arrived ahead of `content_block_start`. Named once the
+/// assistant message carrying the same `tool_use` block arrives.
+struct UnnamedTool {
+    /// `None` when the block's start never arrived.
+    tool_use_id: Option<String>,
+    /// Index into `messages` of its stored message.
+    message: usize,
+    tool_number: usize,
+}
+
+/// A compaction waiting for its summary.
+struct PendingCompaction {
+    /// Index into `messages` of its stored message.
+    message: usize,
+    /// Its `[N]` number.
+    number: usize,
+    /// Messages stored since the previous compaction (or the session start).
+    folded: usize,
+}
+
 /// Tracks an active subagent (Task/Agent tool call) for concurrent rendering.
 struct ActiveSubagent {
     tool_number: usize,
+    /// `Task` or `Agent`.
+    name: String,
     child_counter: usize,
+    started: Instant,
+}
+
+impl ActiveSubagent {
+    /// `[4] Task: 7 calls`, for the `…working` indicator.
+    fn progress(&self) -> String {
+        format!(
+            "[{}] {}: {}",
+            self.tool_number,
+            self.name,
+            call_count(self.child_counter)
+        )
+    }
+
+    /// `[4] Task done: 7 calls in 1m02s`, once its result is in. Runs under
+    /// a second leave the time out.
+    fn summary(&self, elapsed: Duration) -> String {
+        let line = format!(
+            "[{}] {} done: {}",
+            self.tool_number,
+            self.name,
+            call_count(self.child_counter)
+        );
+        if elapsed.as_secs() == 0 {
+            return line;
+        }
+        format!("{line} in {}", format_elapsed(elapsed))
+    }
+}
+
+fn call_count(n: usize) -> String {
+    if n == 1 {
+        "1 call".to_string()
+    } else {
+        format!("{n} calls")
+    }
 }
 
 /// Tracks an active fork for rendering child tool calls with `[P/C]` numbering.
@@ -111,6 +451,19 @@ struct ActiveFork {
 pub struct RendererConfig {
     /// Whether to stream thinking text inline instead of collapsing.
     pub show_thinking: bool,
+    /// Whether to render token usage lines (`--show-usage`).
+    pub show_usage: bool,
+    /// Lines of each successful tool result to preview under its call
+    /// (`--show-results`); 0 disables previews.
+    pub result_preview_lines: usize,
+    /// Whether to style `**bold**`, `code` and headings in assistant text
+    /// (`--render-markdown`).
+    pub render_markdown: bool,
+    /// Whether to drop informational stderr lines (`--quiet-stderr`).
+    pub quiet_stderr: bool,
+    /// Lines of a text block to echo before holding the rest back for `:N`
+    /// (`--max-stream-lines`); `None` echoes everything.
+    pub max_stream_lines: Option<usize>,
 }
 
 /// Tracks rendering state and produces colored terminal output.
@@ -119,16 +472,31 @@ pub struct Renderer<W: Write = io::Stdout> {
     current_block: Option<BlockKind>,
     /// Whether we're mid-line in text streaming.
     text_streaming: bool,
+    /// Inline markdown state of the text block being streamed.
+    markdown: MarkdownStream,
+    /// The text block being streamed, under `--max-stream-lines`.
+    text_block: TextBlock,
     /// Numbered messages for `:N` viewing.
     messages: Vec<StoredMessage>,
+    /// Scratch notes jotted with `:note`, each prefixed with its timestamp.
+    notes: Vec<String>,
     /// Tool use counter for numbering.
     tool_counter: usize,
-    /// The tool currently in progress (name + input).
+    /// The tool currently in progress (name + input). The name is empty
+    /// until its `content_block_start` arrives.
     current_tool: Option<(String, Value)>,
+    /// Tool calls still shown as `unknown`, awaiting their assistant message.
+    unnamed_tools: Vec<UnnamedTool>,
+    /// The last top-level tool call this turn, for spotting retries.
+    prev_tool: Option<PrevToolCall>,
     /// Accumulated thinking text for the current thinking block.
     current_thinking: Option<String>,
     /// Whether a tool call line is still open (no \r\n yet), awaiting its result.
     tool_line_open: bool,
+    /// Display width of the open tool call line.
+    tool_line_width: usize,
+    /// The "…working" indicator, if currently drawn.
+    activity: Option<ActivityIndicator>,
     /// Active subagents, keyed by `tool_use_id`.
     active_subagents: HashMap<String, ActiveSubagent>,
     /// Active fork (at most one at a time for v1).
@@ -140,16 +508,83 @@ pub struct Renderer<W: Write = io::Stdout> {
     /// Whether the last visible output was a tool call/result line.
     /// Consumed by `stream_text` to insert a blank line before text.
     had_tool_output: bool,
-    /// Whether the session is currently compacting.
-    compacting: bool,
+    /// The compaction in progress, until its summary arrives.
+    compacting: Option<PendingCompaction>,
+    /// How many stored messages precede the last compaction, so the next one
+    /// can say how many it folded away.
+    compacted_upto: usize,
     /// Display configuration.
     config: RendererConfig,
-    /// Terminal width for line truncation.
-    width: usize,
+    /// Terminal size, for line truncation and pager padding. Set from the
+    /// recorded terminal size by `setup_display` and on resize, never below
+    /// [`MIN_WIDTH`](super::MIN_WIDTH) columns.
+    size: TerminalSize,
+    /// The one-time notice about rendering a too-narrow terminal at the
+    /// default width.
+    narrow_notice: NarrowNotice,
+    /// `--json-events`: a copy of the session as NDJSON.
+    json_events: Option<JsonEvents>,
+    /// The last session header, shown again when the screen is redrawn.
+    header: Option<String>,
     /// Writer for output.
     out: W,
 }
 
+/// Where the "…working" indicator is drawn, so it can be erased in place.
+#[derive(Debug, Clone, Copy)]
+struct ActivityIndicator {
+    /// Display width of what was printed.
+    width: usize,
+    /// Appended to an open tool line, rather than on a line of its own.
+    inline: bool,
+}
+
+/// Whether the narrow-terminal notice is due.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NarrowNotice {
+    NotNeeded,
+    /// The terminal is this many columns wide; say so at the next chance.
+    Pending(usize),
+    Shown,
+}
+
+/// A text block streamed under `--max-stream-lines`: echoed until it
+/// reaches the limit, then only collected.
+#[derive(Debug, Default)]
+struct TextBlock {
+    /// The whole block so far, echoed or not.
+    full: String,
+    /// Lines echoed so far.
+    lines: usize,
+    /// How much of `full` was echoed, once the limit is reached.
+    echoed: Option<usize>,
+}
+
+impl TextBlock {
+    /// Add `text` to the block and return the part of it to echo.
+    fn push<'a>(&mut self, text: &'a str, max_lines: usize) -> &'a str {
+        let start = self.full.len();
+        self.full.push_str(text);
+        if self.echoed.is_some() {
+            return "";
+        }
+        for (i, _) in text.match_indices('\n') {
+            self.lines += 1;
+            if self.lines >= max_lines {
+                self.echoed = Some(start + i + 1);
+                return &text[..=i];
+            }
+        }
+        text
+    }
+
+    /// How many lines were held back.
+    fn hidden_lines(&self) -> usize {
+        self.echoed
+            .map_or(0, |echoed| self.full[echoed..].lines().count())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 enum BlockKind {
     Text,
@@ -174,31 +609,102 @@ impl<W: Write> Renderer<W> {
         Self {
             current_block: None,
             text_streaming: false,
+            markdown: MarkdownStream::default(),
+            text_block: TextBlock::default(),
             messages: Vec::new(),
+            notes: Vec::new(),
             tool_counter: 0,
             current_tool: None,
+            unnamed_tools: Vec::new(),
+            prev_tool: None,
             current_thinking: None,
             tool_line_open: false,
+            tool_line_width: 0,
+            activity: None,
             active_subagents: HashMap::new(),
             active_fork: None,
             current_tool_use_id: None,
             last_tool_indent: 0,
             had_tool_output: false,
-            compacting: false,
+            compacting: None,
+            compacted_upto: 0,
             config: RendererConfig::default(),
-            width: term_width(),
+            size: TerminalSize::default(),
+            narrow_notice: NarrowNotice::NotNeeded,
+            json_events: None,
+            header: None,
             out: writer,
         }
     }
 
+    /// Render at `size`, or at the default width if it's too narrow. The
+    /// first time that happens, [`render_narrow_notice`](Self::render_narrow_notice)
+    /// has a notice to show.
+    pub fn set_size(&mut self, size: TerminalSize) {
+        if self.narrow_notice != NarrowNotice::Shown {
+            self.narrow_notice = if size.is_degraded() {
+                NarrowNotice::Pending(size.width)
+            } else {
+                NarrowNotice::NotNeeded
+            };
+        }
+        self.size = size.usable();
+    }
+
     pub fn set_width(&mut self, width: usize) {
-        self.width = width;
+        self.set_size(TerminalSize { width, ..self.size });
+    }
+
+    /// Show the one-time notice that the terminal is too narrow, if
+    /// [`set_size`](Self::set_size) left one pending.
+    pub fn render_narrow_notice(&mut self) {
+        let NarrowNotice::Pending(columns) = self.narrow_notice else {
+            return;
+        };
+        self.narrow_notice = NarrowNotice::Shown;
+        self.ensure_new_line();
+        let notice = format!(
+            "[terminal is {columns} columns wide \u{2014} rendering at {} until it's resized]",
+            self.size.width
+        );
+        queue!(self.out, Print(theme::dim().apply(notice)), Print("\r\n")).ok();
+        self.out.flush().ok();
+    }
+
+    /// Terminal columns, for truncating and wrapping lines.
+    pub fn width(&self) -> usize {
+        self.size.width
+    }
+
+    /// Terminal rows.
+    pub fn height(&self) -> usize {
+        self.size.height
     }
 
     pub fn set_show_thinking(&mut self, show: bool) {
         self.config.show_thinking = show;
     }
 
+    pub fn set_show_usage(&mut self, show: bool) {
+        self.config.show_usage = show;
+    }
+
+    pub fn set_result_preview_lines(&mut self, lines: usize) {
+        self.config.result_preview_lines = lines;
+    }
+
+    pub fn set_render_markdown(&mut self, render: bool) {
+        self.config.render_markdown = render;
+    }
+
+    pub fn set_quiet_stderr(&mut self, quiet: bool) {
+        self.config.quiet_stderr = quiet;
+    }
+
+    pub fn set_max_stream_lines(&mut self, lines: Option<usize>) {
+        self.config.max_stream_lines = lines;
+    }
+
     pub fn writer(&mut self) -> &mut W {
         &mut self.out
     }
@@ -207,6 +713,17 @@ impl<W: Write> Renderer<W> {
         &self.messages
     }
 
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+
+    /// Store a `:note` and confirm it on screen.
+    pub fn add_note(&mut self, timestamp: &str, text: &str) {
+        self.notes.push(format!("[{timestamp}] {text}"));
+        let prefix = format!("\u{270e} note {}", self.notes.len());
+        self.render_dim_status_line(&prefix, text);
+    }
+
     /// Return the in-progress thinking block (if any) as a temporary `StoredMessage`.
     ///
     /// This allows `:N` viewing of thinking blocks that are still being streamed.
@@ -222,12 +739,37 @@ impl<W: Write> Renderer<W> {
                 label: format!("[{n}] Thinking"),
                 content,
                 result: None,
+                failed: false,
             })
         } else {
             None
         }
     }
 
+    /// Also write the session as NDJSON to `out` (`--json-events`).
+    pub fn set_json_events(&mut self, out: Box<dyn Write + Send>) {
+        self.json_events = Some(JsonEvents::new(out));
+    }
+
+    /// Pass an inbound event to the `--json-events` stream, if there is one.
+    pub fn emit_json_event(&mut self, event: &InboundEvent) {
+        if let Some(json) = &mut self.json_events {
+            json.inbound(event);
+        }
+    }
+
+    /// End the `--json-events` stream with its `coven_result` line.
+    pub fn emit_json_result(
+        &mut self,
+        session_id: Option<&str>,
+        total_cost_usd: f64,
+        result: &str,
+    ) {
+        if let Some(json) = &mut self.json_events {
+            json.result(session_id, total_cost_usd, result);
+        }
+    }
+
     pub fn into_messages(self) -> Vec<StoredMessage> {
         self.messages
     }
@@ -282,13 +824,54 @@ impl<W: Write> Renderer<W> {
     }
 
     pub fn render_session_header(&mut self, session_id: &str, model: &str) {
-        let header = format!("Session {session_id} ({model})");
-        queue!(self.out, Print(theme::dim().apply(header)), Print("\r\n")).ok();
+        self.render_header(format!("Session {session_id} ({model})"));
+    }
+
+    pub fn render_resumed_session_header(&mut self, session_id: &str, model: &str) {
+        self.render_header(format!("Resumed session {session_id} ({model})"));
+    }
+
+    fn render_header(&mut self, header: String) {
+        queue!(self.out, Print(theme::dim().apply(&header)), Print("\r\n")).ok();
         queue!(self.out, Print("\r\n")).ok();
         self.out.flush().ok();
+        self.header = Some(header);
+    }
+
+    /// Clear the screen and start over with the session header and a recap
+    /// of the latest messages (Ctrl+L), e.g. after a tool garbled the
+    /// terminal. The caller redraws the input line.
+    pub fn redraw_screen(&mut self) {
+        queue!(self.out, Clear(ClearType::All), MoveTo(0, 0)).ok();
+        self.activity = None;
+        self.text_streaming = false;
+        self.tool_line_open = false;
+        if let Some(header) = self.header.take() {
+            self.render_header(header);
+        }
+        self.render_recap(RECAP_MESSAGES);
+    }
+
+    /// The last `n` stored messages, one line each as in the `:` listing,
+    /// with a count of the earlier ones.
+    pub fn render_recap(&mut self, n: usize) {
+        let shown = n.min(self.messages.len());
+        let earlier = self.messages.len() - shown;
+        if earlier > 0 {
+            let line = format!("\u{2026} {earlier} earlier message(s), : lists them all");
+            queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
+        }
+        for line in index_lines(&self.messages[earlier..]) {
+            queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
+        }
+        if shown > 0 {
+            queue!(self.out, Print("\r\n")).ok();
+        }
+        self.out.flush().ok();
     }
 
     pub fn render_turn_separator(&mut self) {
+        self.prev_tool = None;
         queue!(self.out, Print(theme::dim().apply("---")), Print("\r\n")).ok();
         queue!(self.out, Print("\r\n")).ok();
         self.out.flush().ok();
@@ -296,28 +879,31 @@ impl<W: Write> Renderer<W> {
 
     pub fn render_result(&mut self, subtype: &str, cost: f64, duration_ms: u64, num_turns: u32) {
         self.finish_current_block();
-        // Round to tenths of a second (add 50ms to round instead of truncate)
-        let rounded = duration_ms + 50;
-        let whole_secs = rounded / 1000;
-        let tenths = (rounded % 1000) / 100;
-
-        let label = if subtype == "success" {
-            "Done"
-        } else {
-            "Error"
+        self.prev_tool = None;
+        let secs = format_tenths(duration_ms);
+
+        let kind = ResultSubtype::parse(subtype);
+        let label = kind.label().unwrap_or(subtype);
+        let label = match kind {
+            ResultSubtype::Success => theme::result_line().apply(label),
+            ResultSubtype::MaxTurns => theme::warning().apply(label),
+            ResultSubtype::ExecutionError | ResultSubtype::MaxBudget => theme::error().apply(label),
+            ResultSubtype::Unknown => theme::dim().apply(label),
         };
         let turn_word = if num_turns == 1 { "turn" } else { "turns" };
-        let stats = format!("  ${cost:.2} · {whole_secs}.{tenths}s · {num_turns} {turn_word}");
+        let stats = format!("  ${cost:.2} · {secs} · {num_turns} {turn_word}");
         let hint = if self.messages.is_empty() {
-            ""
+            String::new()
+        } else if let Some(tool) = repeated_tool(&self.messages) {
+            format!("  (:N or :{tool}[-1] to view)")
         } else {
-            "  (:N to view)"
+            "  (:N to view)".to_string()
         };
 
         queue!(self.out, Print("\r\n")).ok();
         queue!(
             self.out,
-            Print(theme::result_line().apply(label)),
+            Print(label),
             Print(theme::dim().apply(stats)),
             Print(theme::dim().apply(hint)),
             Print("\r\n"),
@@ -326,44 +912,132 @@ impl<W: Write> Renderer<W> {
         self.out.flush().ok();
     }
 
+    /// Render a dim `  {label}: 312k in (87% cached) · 9.4k out` line.
+    /// No-op unless `--show-usage` is set.
+    pub fn render_usage(&mut self, label: &str, usage: &TokenUsage) {
+        if !self.config.show_usage {
+            return;
+        }
+        let line = format!("  {label}: {}", usage.summary());
+        queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
+        self.out.flush().ok();
+    }
+
+    /// Render a dim `  Time: sync 2s · dispatch 1m02s` line.
+    pub fn render_timing(&mut self, line: &str) {
+        let line = format!("  Time: {line}");
+        queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
+        self.out.flush().ok();
+    }
+
+    /// Render the `  Committed: <message>` line for `coven ralph --commit-each`.
+    pub fn render_commit(&mut self, message: &str) {
+        let line = format!("  Committed: {message}");
+        queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
+        self.out.flush().ok();
+    }
+
+    /// Render a `  N permission denials during this turn` line for the
+    /// result's denials, kept as a `Denials` message for `:denials`.
+    pub fn render_permission_denials(&mut self, denials: &[PermissionDenial]) {
+        if denials.is_empty() {
+            return;
+        }
+        let content: Vec<String> = denials
+            .iter()
+            .map(|d| {
+                let detail = format_tool_detail(&d.tool_name, &d.tool_input);
+                format!("{}  {detail}", d.tool_name).trim_end().to_string()
+            })
+            .collect();
+        self.messages.push(StoredMessage {
+            label: DENIALS_LABEL.to_string(),
+            content: content.join("\n"),
+            result: None,
+            failed: false,
+        });
+        let query = if self
+            .messages
+            .iter()
+            .filter(|m| m.label == DENIALS_LABEL)
+            .count()
+            > 1
+        {
+            ":denials[-1]"
+        } else {
+            ":denials"
+        };
+        let (n, s) = (denials.len(), if denials.len() == 1 { "" } else { "s" });
+        let line = format!("  {n} permission denial{s} during this turn ({query} to view)");
+        queue!(self.out, Print(theme::warning().apply(line)), Print("\r\n")).ok();
+        self.out.flush().ok();
+    }
+
     // --- Compaction ---
 
-    /// Render a compaction indicator line: `[N] ⟳ Compacted`.
+    /// Render a compaction indicator line, `[N] ⟳ Compacting…`, and store
+    /// a `[N] Compaction` message for the summary to land in.
     pub fn render_compaction(&mut self) {
         self.finish_current_block();
-        self.compacting = true;
         self.tool_counter += 1;
         let n = self.tool_counter;
-        let label = format!("[{n}] \u{27f3} Compacted");
-        queue!(self.out, Print(theme::dim().apply(&label)), Print("\r\n"),).ok();
+        let line = format!("[{n}] \u{27f3} Compacting\u{2026}");
+        queue!(self.out, Print(theme::dim().apply(&line)), Print("\r\n"),).ok();
+        self.compacting = Some(PendingCompaction {
+            message: self.messages.len(),
+            number: n,
+            folded: self.messages.len() - self.compacted_upto,
+        });
         self.messages.push(StoredMessage {
-            label,
+            label: format!("[{n}] Compaction"),
             content: String::new(),
             result: None,
+            failed: false,
         });
+        self.compacted_upto = self.messages.len();
         self.out.flush().ok();
     }
 
     pub fn is_compacting(&self) -> bool {
-        self.compacting
+        self.compacting.is_some()
     }
 
-    /// Attach the compacted context (from the synthetic user message) to the
-    /// most recent compaction message for `:N` viewing.
+    /// Attach the compacted context (from the synthetic user message) to its
+    /// compaction message for `:N` viewing, and show a digest of it:
+    /// `  Compacted 42 messages · <first line of the summary>  (:N to view)`.
     pub fn set_compaction_content(&mut self, message: Option<&Value>) {
-        self.compacting = false;
-        let text = message
-            .and_then(|m| m.get("content"))
-            .and_then(Value::as_array)
-            .and_then(|arr| {
-                arr.iter()
-                    .find(|item| item.get("type").and_then(Value::as_str) == Some("text"))
-                    .and_then(|item| item.get("text").and_then(Value::as_str))
-            })
-            .unwrap_or_default();
-        if let Some(msg) = self.messages.last_mut() {
-            msg.content = text.to_string();
-        }
+        let Some(pending) = self.compacting.take() else {
+            return;
+        };
+        // Claude sends the summary as a plain string; accept content blocks
+        // too, as other user messages use.
+        let text = match message.and_then(|m| m.get("content")) {
+            Some(Value::String(text)) => text.as_str(),
+            Some(Value::Array(blocks)) => blocks
+                .iter()
+                .find(|item| item.get("type").and_then(Value::as_str) == Some("text"))
+                .and_then(|item| item.get("text").and_then(Value::as_str))
+                .unwrap_or_default(),
+            _ => "",
+        };
+        let Some(msg) = self.messages.get_mut(pending.message) else {
+            return;
+        };
+        msg.content = text.to_string();
+
+        let folded = match pending.folded {
+            1 => "1 message".to_string(),
+            n => format!("{n} messages"),
+        };
+        let digest = match summary_headline(text) {
+            Some(headline) => format!("  Compacted {folded} \u{b7} {headline}"),
+            None => format!("  Compacted {folded}"),
+        };
+        let hint = format!("  (:{} to view)", pending.number);
+        let room = self.size.width.saturating_sub(hint.width());
+        let line = format!("{}{hint}", truncate_to_width(&digest, room));
+        queue!(self.out, Print(theme::dim().apply(&line)), Print("\r\n"),).ok();
+        self.out.flush().ok();
     }
 
     // --- Stream events ---
@@ -379,10 +1053,18 @@ impl<W: Write> Renderer<W> {
                             self.text_streaming = false;
                         }
                         "tool_use" => {
-                            self.finish_current_block();
-                            let name = cb.name.as_deref().unwrap_or("unknown").to_string();
-                            self.current_block = Some(BlockKind::ToolUse);
-                            self.current_tool = Some((name, Value::Null));
+                            let name = cb.name.clone().unwrap_or_default();
+                            // Input that streamed in before this start belongs
+                            // to this block: keep it and just fill in the name.
+                            if let Some((pending, _)) = &mut self.current_tool
+                                && pending.is_empty()
+                            {
+                                *pending = name;
+                            } else {
+                                self.finish_current_block();
+                                self.current_block = Some(BlockKind::ToolUse);
+                                self.current_tool = Some((name, Value::Null));
+                            }
                             self.current_tool_use_id.clone_from(&cb.id);
                         }
                         "thinking" => {
@@ -413,7 +1095,13 @@ impl<W: Write> Renderer<W> {
                             }
                         }
                         "input_json_delta" => {
-                            // Accumulate tool input JSON — we'll render it on block_stop
+                            // Accumulate tool input JSON — we'll render it on block_stop.
+                            // Input ahead of its block's start opens an unnamed tool.
+                            if self.current_tool.is_none() && delta.partial_json.is_some() {
+                                self.finish_current_block();
+                                self.current_block = Some(BlockKind::ToolUse);
+                                self.current_tool = Some((String::new(), Value::Null));
+                            }
                             if let Some(ref partial) = delta.partial_json
                                 && let Some((_, input)) = &mut self.current_tool
                             {
@@ -436,6 +1124,8 @@ impl<W: Write> Renderer<W> {
                                 }
                             }
                         }
+                        // `signature_delta` (thinking-block signatures) and
+                        // delta types newer than coven have nothing to show.
                         _ => {}
                     }
                 }
@@ -447,6 +1137,45 @@ impl<W: Write> Renderer<W> {
         }
     }
 
+    /// Name tool calls rendered as `unknown` from the `tool_use` blocks of
+    /// a top-level assistant message, so `:Name` queries find them. A call
+    /// whose start never arrived has no ID to match, so it takes the next
+    /// block that isn't another unnamed call's.
+    pub fn name_unnamed_tools(&mut self, content: &[AssistantContentBlock]) {
+        for block in content {
+            let AssistantContentBlock::ToolUse { id, name, .. } = block else {
+                continue;
+            };
+            let pos = self
+                .unnamed_tools
+                .iter()
+                .position(|t| t.tool_use_id.as_deref() == Some(id))
+                .or_else(|| {
+                    self.unnamed_tools
+                        .iter()
+                        .position(|t| t.tool_use_id.is_none())
+                });
+            let Some(pos) = pos else {
+                continue;
+            };
+            let tool = self.unnamed_tools.remove(pos);
+            if let Some(msg) = self.messages.get_mut(tool.message) {
+                msg.label = format!("[{}] {}", tool.tool_number, display_tool_name(name));
+            }
+            if matches!(name.as_str(), "Task" | "Agent") {
+                self.active_subagents.insert(
+                    id.clone(),
+                    ActiveSubagent {
+                        tool_number: tool.tool_number,
+                        name: name.clone(),
+                        child_counter: 0,
+                        started: Instant::now(),
+                    },
+                );
+            }
+        }
+    }
+
     // --- Tool results ---
 
     pub fn render_tool_result(&mut self, result: &Value, message: Option<&Value>) {
@@ -501,7 +1230,17 @@ impl<W: Write> Renderer<W> {
             } else {
                 self.close_tool_line();
             }
+            let summary = sa.summary(sa.started.elapsed());
+            queue!(
+                self.out,
+                Print(theme::dim().apply(format!("  {summary}"))),
+                Print("\r\n"),
+            )
+            .ok();
         } else {
+            if let Some(prev) = &mut self.prev_tool {
+                prev.failed = is_error;
+            }
             self.apply_tool_result(&text, is_error);
         }
         self.out.flush().ok();
@@ -550,7 +1289,7 @@ impl<W: Write> Renderer<W> {
             self.tool_counter += 1;
             let n = self.tool_counter;
             task_numbers.push(n);
-            let label = truncate_to_width(&format!("[{n}] \u{2442} Fork  {task}"), self.width);
+            let label = truncate_to_width(&format!("[{n}] \u{2442} Fork  {task}"), self.size.width);
             queue!(
                 self.out,
                 Print(theme::fork_tool().apply(&label)),
@@ -561,6 +1300,7 @@ impl<W: Write> Renderer<W> {
                 label: format!("[{n}] \u{2442} Fork"),
                 content: task.clone(),
                 result: None,
+                failed: false,
             });
         }
         self.active_fork = Some(ActiveFork {
@@ -593,15 +1333,17 @@ impl<W: Write> Renderer<W> {
 
         let label = truncate_to_width(
             &format!("  [{number_label}] {display_name}  {detail}"),
-            self.width,
+            self.size.width,
         );
         queue!(self.out, Print(theme::fork_tool().apply(&label))).ok();
+        self.tool_line_width = label.width();
 
         let content = serde_json::to_string_pretty(input).unwrap_or_default();
         self.messages.push(StoredMessage {
             label: format!("[{number_label}] {display_name}"),
             content,
             result: None,
+            failed: false,
         });
         self.tool_line_open = true;
     }
@@ -609,7 +1351,61 @@ impl<W: Write> Renderer<W> {
     /// Record completion of a fork child by storing its result text on the
     /// task's `[N]` `StoredMessage`. No visible output — the task's completion
     /// is implicit from its `[N]` entry.
-    pub fn render_fork_child_done(&mut self, task_idx: usize, result_text: &str) {
+    /// A fork child finished: attach its result to the task's `[N]` entry
+    /// and print `  [N] ⑂ done  $0.04 · 12.3s`.
+    pub fn render_fork_child_done(&mut self, task_idx: usize, result: &SessionResult) {
+        let outcome = if result.is_error { "failed" } else { "done" };
+        let stats = format!(
+            "  ${:.2} \u{b7} {}",
+            result.total_cost_usd,
+            format_tenths(result.duration_ms)
+        );
+        self.finish_fork_child(task_idx, &result.result, result.is_error, outcome, &stats);
+    }
+
+    /// A fork child ended without a result, e.g. because its process died.
+    pub fn render_fork_child_failed(&mut self, task_idx: usize, error: &str) {
+        self.finish_fork_child(task_idx, error, true, "failed", &format!(": {error}"));
+    }
+
+    /// Note that only `limit` of the `total` children start right away; the
+    /// rest wait for a running child to finish.
+    pub fn render_fork_queued(&mut self, limit: usize, total: usize) {
+        self.ensure_new_line();
+        let line = format!("running {limit} of {total} children at a time");
+        queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
+        self.out.flush().ok();
+    }
+
+    /// A queued fork child started, leaving `running` of `total` children
+    /// running.
+    pub fn render_fork_child_started(&mut self, task_idx: usize, running: usize, total: usize) {
+        self.close_tool_line();
+        let Some(&task_number) = self
+            .active_fork
+            .as_ref()
+            .and_then(|fork| fork.task_numbers.get(task_idx))
+        else {
+            return;
+        };
+        let line = truncate_to_width(
+            &format!(
+                "  [{task_number}] \u{2442} started \u{b7} running: {running}/{total} children"
+            ),
+            self.size.width,
+        );
+        queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
+        self.out.flush().ok();
+    }
+
+    fn finish_fork_child(
+        &mut self,
+        task_idx: usize,
+        result_text: &str,
+        failed: bool,
+        outcome: &str,
+        detail: &str,
+    ) {
         self.close_tool_line();
         let Some(ref fork) = self.active_fork else {
             return;
@@ -621,41 +1417,135 @@ impl<W: Write> Renderer<W> {
         let label_prefix = format!("[{task_number}] \u{2442} Fork");
         if let Some(msg) = self.messages.iter_mut().find(|m| m.label == label_prefix) {
             msg.result = Some(result_text.to_string());
+            msg.failed = failed;
         }
+        let line = truncate_to_width(
+            &format!("  [{task_number}] \u{2442} {outcome}{detail}"),
+            self.size.width,
+        );
+        queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
+        self.out.flush().ok();
     }
 
-    /// Render `[N] ⑂ Fork Result` and clean up after all fork children have completed.
-    pub fn render_fork_complete(&mut self, combined_result: &str) {
-        self.tool_counter += 1;
-        let n = self.tool_counter;
-        let label = format!("[{n}] \u{2442} Fork Result");
-        queue!(
-            self.out,
-            Print(theme::fork_tool().apply(&label)),
-            Print("\r\n"),
-        )
-        .ok();
-        self.messages.push(StoredMessage {
-            label,
-            content: combined_result.to_string(),
-            result: None,
+    /// Render the keys for steering fork children, under the task list.
+    pub fn render_fork_hints(&mut self, children: usize) {
+        let help = format!(
+            "{} select a child to steer · Esc deselect",
+            crate::fork::child_keys(children)
+        );
+        queue!(self.out, Print(theme::dim().apply(help)), Print("\r\n")).ok();
+        self.out.flush().ok();
+    }
+
+    /// Show which fork child typed text goes to (`None` after deselecting).
+    pub fn render_fork_steering_target(&mut self, target: Option<(usize, &str)>) {
+        if let Some((task_idx, task)) = target {
+            self.render_dim_status_line(&format!("steering → child {}", task_idx + 1), task);
+        } else {
+            self.ensure_new_line();
+            queue!(
+                self.out,
+                Print(theme::dim().apply("steering off")),
+                Print("\r\n")
+            )
+            .ok();
+            self.out.flush().ok();
+        }
+    }
+
+    pub fn render_fork_steering_sent(&mut self, task_idx: usize, text: &str) {
+        self.render_dim_status_line(&format!("⤷ steering child {}", task_idx + 1), text);
+    }
+
+    /// Show the `> ` prompt for text addressed to a fork child.
+    pub fn begin_fork_input_line(&mut self, task_idx: usize) {
+        self.ensure_new_line();
+        let help = format!("Enter steer child {} · Esc cancel", task_idx + 1);
+        queue!(
+            self.out,
+            Print(theme::dim().apply(help)),
+            Print("\r\n"),
+            Print(theme::prompt_style().apply("> ")),
+        )
+        .ok();
+        self.out.flush().ok();
+    }
+
+    /// Render `[N] ⑂ Fork Result` and clean up after all fork children have completed.
+    pub fn render_fork_complete(&mut self, combined_result: &str) {
+        self.tool_counter += 1;
+        let n = self.tool_counter;
+        let label = format!("[{n}] \u{2442} Fork Result");
+        queue!(
+            self.out,
+            Print(theme::fork_tool().apply(&label)),
+            Print("\r\n"),
+        )
+        .ok();
+        self.messages.push(StoredMessage {
+            label,
+            content: combined_result.to_string(),
+            result: None,
+            failed: false,
         });
         self.active_fork = None;
         self.had_tool_output = true;
         self.out.flush().ok();
     }
 
+    /// Ctrl+C stopped a `coven fork` with `unfinished` children left.
+    pub fn render_fork_interrupted(&mut self, unfinished: usize) {
+        self.finish_current_block();
+        let line = format!("[interrupted — stopped {unfinished} unfinished child(ren)]");
+        queue!(
+            self.out,
+            Print("\r\n"),
+            Print(theme::dim().apply(line)),
+            Print("\r\n"),
+        )
+        .ok();
+        self.out.flush().ok();
+    }
+
+    /// Print each `coven fork` child's result under its task, errors in red.
+    pub fn render_fork_results(&mut self, results: &[(String, ChildOutcome)]) {
+        self.active_fork = None;
+        for (i, (task, outcome)) in results.iter().enumerate() {
+            let heading = format!("\u{2442} {}: {task}", i + 1);
+            queue!(
+                self.out,
+                Print("\r\n"),
+                Print(theme::fork_tool().apply(heading)),
+                Print("\r\n"),
+            )
+            .ok();
+            match &outcome.result {
+                Ok(text) => {
+                    queue!(self.out, Print(text.trim_end().replace('\n', "\r\n"))).ok();
+                }
+                Err(error) => {
+                    queue!(self.out, Print(theme::error().apply(error))).ok();
+                }
+            }
+            queue!(self.out, Print("\r\n")).ok();
+        }
+        self.out.flush().ok();
+    }
+
     // --- User message indicators ---
 
     /// Render a dim status line: `<prefix>: <text>`.
     fn render_dim_status_line(&mut self, prefix: &str, text: &str) {
         self.ensure_new_line();
-        let line = format!("{prefix}: {text}");
+        let line = format!("{prefix}: {text}").replace('\n', "\r\n");
         queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
         self.out.flush().ok();
     }
 
     pub fn render_steering_sent(&mut self, text: &str) {
+        if let Some(json) = &mut self.json_events {
+            json.user_message("steering_sent", text);
+        }
         self.render_dim_status_line("⤷ steering", text);
     }
 
@@ -663,12 +1553,80 @@ impl<W: Write> Renderer<W> {
         self.render_dim_status_line("⏳ queued", text);
     }
 
+    /// List the queued follow-ups for `:queue`.
+    pub fn render_queue(&mut self, queued: &[&str]) {
+        if queued.is_empty() {
+            self.render_dim_status_line("⏳ queue", "empty");
+            return;
+        }
+        self.render_dim_status_line("⏳ queue", "drop one with :queue drop N or :q-N");
+        for (i, text) in queued.iter().enumerate() {
+            self.render_dim_status_line(&format!("  {}", i + 1), text);
+        }
+    }
+
+    pub fn render_followup_dropped(&mut self, text: &str) {
+        self.render_dim_status_line("✗ dropped", text);
+    }
+
     pub fn render_followup_sent(&mut self, text: &str) {
+        if let Some(json) = &mut self.json_events {
+            json.user_message("followup_sent", text);
+        }
         self.render_dim_status_line("⤷ follow-up", text);
     }
 
+    /// A `:raw` line written to claude's stdin (`--debug-protocol`).
+    pub fn render_raw_sent(&mut self, line: &str) {
+        self.ensure_new_line();
+        queue!(
+            self.out,
+            Print(theme::warning().apply(format!("[raw \u{2192}] {line}"))),
+            Print("\r\n"),
+        )
+        .ok();
+        self.out.flush().ok();
+    }
+
+    /// Which permission mode sessions will use and where it came from.
+    pub fn render_permission_mode(&mut self, mode: &PermissionMode) {
+        self.render_dim_status_line("permission mode", &mode.describe());
+    }
+
+    /// Where `--debug-protocol` is writing claude output that failed to parse.
+    pub fn render_protocol_log(&mut self, path: &str) {
+        self.render_dim_status_line("unparsed output logged to", path);
+    }
+
+    /// List the user's messages that the session ended without delivering.
+    pub fn render_undelivered(&mut self, messages: &[String]) {
+        self.finish_current_block();
+        self.ensure_new_line();
+        queue!(
+            self.out,
+            Print(theme::dim().apply("not delivered:")),
+            Print("\r\n")
+        )
+        .ok();
+        for text in messages {
+            let line = format!("  {}", text.replace('\n', " "));
+            queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
+        }
+        self.out.flush().ok();
+    }
+
     // --- Prompt ---
 
+    /// Explanatory text in `coven tutorial`, set off by a blank line.
+    pub fn render_tutorial(&mut self, text: &str) {
+        self.ensure_new_line();
+        queue!(self.out, Print("\r\n")).ok();
+        for line in text.lines() {
+            queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
+        }
+        self.out.flush().ok();
+    }
+
     pub fn show_prompt(&mut self) {
         queue!(self.out, Print(theme::prompt_style().apply("> ")),).ok();
         self.out.flush().ok();
@@ -684,8 +1642,11 @@ impl<W: Write> Renderer<W> {
 
     /// Print a styled record of the user's message (e.g. `> hello`).
     pub fn render_user_message(&mut self, text: &str) {
+        if let Some(json) = &mut self.json_events {
+            json.user_message("user_message", text);
+        }
         self.ensure_new_line();
-        let line = format!("> {text}");
+        let line = format!("> {text}").replace('\n', "\r\n");
         queue!(
             self.out,
             Print(theme::prompt_style().apply(line)),
@@ -701,6 +1662,7 @@ impl<W: Write> Renderer<W> {
     fn ensure_new_line(&mut self) {
         self.close_tool_line();
         if self.text_streaming {
+            self.finish_markdown();
             queue!(self.out, Print("\r\n")).ok();
             self.text_streaming = false;
         }
@@ -708,7 +1670,9 @@ impl<W: Write> Renderer<W> {
 
     /// Render a tool call line: `[N] ▶ ToolName  detail`. Child tool calls
     /// within a subagent use `[P/C]` prefixed numbering with indented, dimmer
-    /// style (`C` is a per-subagent counter). Leaves the line open for the result.
+    /// style (`C` is a per-subagent counter). A top-level call retrying a
+    /// failed one gets `↻` and a dim note of what changed. Leaves the line
+    /// open for the result.
     fn render_tool_call_line(
         &mut self,
         name: &str,
@@ -734,9 +1698,15 @@ impl<W: Write> Renderer<W> {
         // Indent width: prefix + "[" + number_label + "] "
         self.last_tool_indent = prefix.len() + 1 + number_label.len() + 2;
 
+        let (is_retry, retry_note) = if is_child {
+            (false, None)
+        } else {
+            self.record_top_level_call(name, input)
+        };
+        let marker = if is_retry { "↻" } else { "▶" };
         let label = truncate_to_width(
-            &format!("{prefix}[{number_label}] ▶ {display_name}  {detail}"),
-            self.width,
+            &format!("{prefix}[{number_label}] {marker} {display_name}  {detail}"),
+            self.size.width,
         );
         let style = if is_child {
             theme::tool_name_dim()
@@ -744,17 +1714,44 @@ impl<W: Write> Renderer<W> {
             theme::tool_name()
         };
         queue!(self.out, Print(style.apply(&label))).ok();
+        self.tool_line_width = label.width();
+        if let Some(note) = retry_note {
+            let note = format!("  ({note})");
+            let room = self.size.width.saturating_sub(self.tool_line_width);
+            if room > "  (...)".len() {
+                let note = truncate_to_width(&note, room);
+                queue!(self.out, Print(theme::dim().apply(&note))).ok();
+                self.tool_line_width += note.width();
+            }
+        }
 
         let content = serde_json::to_string_pretty(input).unwrap_or_default();
         self.messages.push(StoredMessage {
             label: format!("[{number_label}] {display_name}"),
             content,
             result: None,
+            failed: false,
         });
 
         self.tool_line_open = true;
     }
 
+    /// Record a top-level tool call. Returns whether it retries the previous
+    /// call, and if so a note of what changed, when there's one to give.
+    fn record_top_level_call(&mut self, name: &str, input: &Value) -> (bool, Option<String>) {
+        let prev = self.prev_tool.replace(PrevToolCall {
+            name: name.to_string(),
+            input: input.clone(),
+            failed: false,
+        });
+        match prev {
+            Some(prev) if is_retry(&prev.name, prev.failed, name) => {
+                (true, retry_note(name, &prev.input, input))
+            }
+            _ => (false, None),
+        }
+    }
+
     /// Store result text on the most recent message and render error or close the tool line.
     fn apply_tool_result(&mut self, text: &str, is_error: bool) {
         if !text.is_empty()
@@ -762,11 +1759,41 @@ impl<W: Write> Renderer<W> {
         {
             msg.result = Some(text.to_string());
         }
+        if is_error && let Some(msg) = self.messages.last_mut() {
+            msg.failed = true;
+        }
         if is_error {
             self.render_error_line(text);
         } else {
             self.close_tool_line();
+            self.render_result_preview(text);
+        }
+    }
+
+    /// Render the first lines of a tool result in dim beneath its call
+    /// (`--show-results`), each cut to the terminal width. `:N` shows the rest.
+    fn render_result_preview(&mut self, text: &str) {
+        let max_lines = self.config.result_preview_lines;
+        let lines: Vec<&str> = text.trim_matches('\n').lines().collect();
+        if max_lines == 0 || lines.iter().all(|l| l.trim().is_empty()) {
+            return;
+        }
+        let indent = self.tool_indent();
+        for line in lines.iter().take(max_lines) {
+            let line: String = line
+                .replace('\t', "    ")
+                .chars()
+                .filter(|c| !c.is_control())
+                .collect();
+            let line = truncate_to_width(&format!("{indent}{line}"), self.size.width);
+            queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
+        }
+        if let Some(more) = lines.len().checked_sub(max_lines).filter(|&n| n > 0) {
+            let s = if more == 1 { "" } else { "s" };
+            let line = format!("{indent}\u{2026} +{more} line{s}");
+            queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
         }
+        self.had_tool_output = true;
     }
 
     /// Render an error line beneath a tool call: `✗ <first line of error text>`.
@@ -779,7 +1806,7 @@ impl<W: Write> Renderer<W> {
             let brief = first_line(text);
             format!("{indent}✗ {brief}")
         };
-        let error_line = truncate_to_width(&error_line, self.width);
+        let error_line = truncate_to_width(&error_line, self.size.width);
         queue!(
             self.out,
             Print(theme::error().apply(&error_line)),
@@ -823,20 +1850,70 @@ impl<W: Write> Renderer<W> {
             self.text_streaming = true;
             trimmed
         };
+        let text = match self.config.max_stream_lines {
+            Some(max) => self.text_block.push(text, max),
+            None => text,
+        };
         if text.is_empty() {
             return;
         }
+        if self.config.render_markdown {
+            let spans = self.markdown.feed(text);
+            self.print_spans(spans);
+            return;
+        }
         // Replace \n with \r\n for raw mode
         let text = text.replace('\n', "\r\n");
         queue!(self.out, Print(&text)).ok();
         self.out.flush().ok();
     }
 
+    /// Print any markdown held back at the end of a text block.
+    fn finish_markdown(&mut self) {
+        let spans = self.markdown.finish();
+        self.print_spans(spans);
+    }
+
+    fn print_spans(&mut self, spans: Vec<Span>) {
+        for (style, text) in spans {
+            let text = text.replace('\n', "\r\n");
+            if style == Style::default() {
+                queue!(self.out, Print(text)).ok();
+            } else {
+                queue!(self.out, Print(style.content_style().apply(text))).ok();
+            }
+        }
+        self.out.flush().ok();
+    }
+
+    /// Under `--max-stream-lines`, store a text block that went over the
+    /// limit as `[N] Text` and say how much of it wasn't shown.
+    fn render_held_back_text(&mut self, block: TextBlock) {
+        let hidden = block.hidden_lines();
+        if hidden == 0 {
+            return;
+        }
+        self.tool_counter += 1;
+        let n = self.tool_counter;
+        self.messages.push(StoredMessage {
+            label: format!("[{n}] Text"),
+            content: block.full,
+            result: None,
+            failed: false,
+        });
+        let lines = if hidden == 1 { "line" } else { "lines" };
+        let placeholder = format!("\u{2026} (+{hidden} more {lines}, :{n} to view)");
+        queue!(self.out, Print(theme::dim().apply(placeholder))).ok();
+    }
+
     fn finish_current_block(&mut self) {
         self.close_tool_line();
         match self.current_block.take() {
             Some(BlockKind::Text) => {
+                let block = std::mem::take(&mut self.text_block);
                 if self.text_streaming {
+                    self.finish_markdown();
+                    self.render_held_back_text(block);
                     queue!(self.out, Print("\r\n\r\n")).ok();
                     self.text_streaming = false;
                 }
@@ -851,6 +1928,21 @@ impl<W: Write> Renderer<W> {
                         }
                         other => other,
                     };
+                    let name = if name.is_empty() {
+                        tool_name_from_input(&input).unwrap_or_default()
+                    } else {
+                        name
+                    };
+                    if name.is_empty() {
+                        self.render_tool_call_line("unknown", &input, None);
+                        self.unnamed_tools.push(UnnamedTool {
+                            tool_use_id,
+                            message: self.messages.len() - 1,
+                            tool_number: self.tool_counter,
+                        });
+                        self.out.flush().ok();
+                        return;
+                    }
                     self.render_tool_call_line(&name, &input, None);
                     // Register Task/Agent tool calls as active subagents
                     if matches!(name.as_str(), "Task" | "Agent")
@@ -860,7 +1952,9 @@ impl<W: Write> Renderer<W> {
                             id,
                             ActiveSubagent {
                                 tool_number: self.tool_counter,
+                                name: name.clone(),
                                 child_counter: 0,
+                                started: Instant::now(),
                             },
                         );
                     }
@@ -877,6 +1971,7 @@ impl<W: Write> Renderer<W> {
                     label: format!("[{n}] Thinking"),
                     content,
                     result: None,
+                    failed: false,
                 });
             }
             None => {}
@@ -885,16 +1980,62 @@ impl<W: Write> Renderer<W> {
     }
 
     pub fn render_rate_limit(&mut self, info: &RateLimitInfo) {
-        let msg = format!(
-            "[rate limit] {} usage at {:.0}% ({})",
-            info.rate_limit_type,
-            info.utilization * 100.0,
-            info.status,
-        );
+        let resets = info
+            .resets_at
+            .map(|t| format!(" \u{b7} resets {}", format_utc_datetime(t)))
+            .unwrap_or_default();
+        let msg = if info.is_rejected() {
+            format!(
+                "[rate limit] {} limit reached{resets}",
+                info.rate_limit_type
+            )
+        } else {
+            format!(
+                "[rate limit] {} usage at {:.0}%{resets}",
+                info.rate_limit_type,
+                info.utilization * 100.0,
+            )
+        };
         queue!(self.out, Print(theme::dim().apply(msg)), Print("\r\n"),).ok();
         self.out.flush().ok();
     }
 
+    /// An API error claude reported, in full, with what to do about it.
+    pub fn render_api_error(&mut self, error: &ApiError) {
+        let label = error
+            .status
+            .map_or_else(|| "[api error]".to_string(), |s| format!("[api error {s}]"));
+        let message = if error.message.is_empty() {
+            "(no message)"
+        } else {
+            &error.message
+        };
+        queue!(
+            self.out,
+            Print(theme::error().apply(format!("{label} {message}"))),
+            Print("\r\n"),
+        )
+        .ok();
+        let hint = match error.kind() {
+            ApiErrorKind::Overloaded | ApiErrorKind::RateLimited | ApiErrorKind::Server => {
+                Some("temporary; loops back off and retry")
+            }
+            ApiErrorKind::ContextLength => Some(
+                "the conversation no longer fits: send /compact, or start a fresh session with a shorter prompt",
+            ),
+            ApiErrorKind::Fatal => None,
+        };
+        if let Some(hint) = hint {
+            queue!(
+                self.out,
+                Print(theme::dim().apply(format!("  {hint}"))),
+                Print("\r\n")
+            )
+            .ok();
+        }
+        self.out.flush().ok();
+    }
+
     pub fn render_warning(&mut self, warning: &str) {
         queue!(
             self.out,
@@ -905,82 +2046,473 @@ impl<W: Write> Renderer<W> {
         self.out.flush().ok();
     }
 
-    pub fn render_returned_from_interactive(&mut self) {
+    /// Lines from claude's stderr: ones that look like errors get the
+    /// `[warn]` treatment, the rest (e.g. MCP server startup logs) a dim
+    /// `[claude]` prefix, or nothing with `--quiet-stderr`.
+    pub fn render_stderr(&mut self, text: &str) {
+        for line in text.lines().map(str::trim_end).filter(|l| !l.is_empty()) {
+            if is_stderr_error(line) {
+                self.ensure_new_line();
+                self.render_warning(line);
+            } else if !self.config.quiet_stderr {
+                self.ensure_new_line();
+                queue!(
+                    self.out,
+                    Print(theme::dim().apply(format!("[claude] {line}"))),
+                    Print("\r\n"),
+                )
+                .ok();
+                self.out.flush().ok();
+            }
+        }
+    }
+
+    /// Progress writing a large message to claude, e.g. `sending 4.2 MB context… 50%`.
+    pub fn render_send_progress(&mut self, sent: usize, total: usize) {
         self.finish_current_block();
+        let tenths_mb = total * 10 / (1 << 20);
+        let percent = sent * 100 / total.max(1);
         queue!(
             self.out,
-            Print("\r\n"),
-            Print(theme::dim().apply("[returned to coven — Ctrl+O to re-open interactive]")),
+            Print(theme::dim().apply(format!(
+                "[sending {}.{} MB context\u{2026} {percent}%]",
+                tenths_mb / 10,
+                tenths_mb % 10
+            ))),
             Print("\r\n"),
         )
         .ok();
         self.out.flush().ok();
     }
 
-    pub fn render_interrupted(&mut self) {
+    /// Claude repeated the same tool call `count` times in a row.
+    pub fn render_tool_loop(&mut self, tool: &str, count: usize) {
+        self.finish_current_block();
+        self.render_warning(&format!(
+            "{tool} called {count} times in a row with identical input \u{2014} nudging claude"
+        ));
+    }
+
+    /// The connectivity preflight failed, so claude wasn't started.
+    pub fn render_offline(&mut self, error: &str) {
         self.finish_current_block();
+        self.render_alert(&format!("API unreachable, not starting claude: {error}"));
+    }
+
+    /// Waiting before the next attempt after connectivity or session failures.
+    pub fn render_backoff(&mut self, secs: u64) {
         queue!(
             self.out,
-            Print("\r\n"),
-            Print(theme::dim().apply("[interrupted — Ctrl+O to open interactive]")),
+            Print(theme::dim().apply(format!("[retrying in {secs}s \u{2014} Ctrl+C to exit]"))),
             Print("\r\n"),
         )
         .ok();
         self.out.flush().ok();
     }
 
-    pub fn render_exit(&mut self, code: Option<i32>) {
-        let msg = match code {
-            Some(c) => format!("Claude process exited with code {c}"),
-            None => "Claude process exited".to_string(),
-        };
-        queue!(self.out, Print(theme::dim().apply(msg)), Print("\r\n"),).ok();
+    /// A message could not be written to claude's stdin.
+    pub fn render_send_failed(&mut self, error: &str) {
+        self.finish_current_block();
+        self.render_alert(&format!("message not delivered to claude: {error}"));
+    }
+
+    /// Spending passed the `--max-cost` budget, so the loop stops after this turn.
+    pub fn render_budget_exceeded(&mut self, spent: f64, max_cost: f64, skipped_followups: usize) {
+        queue!(
+            self.out,
+            Print(theme::warning().apply(format!(
+                "Budget exceeded: ${spent:.2} spent of ${max_cost:.2} (--max-cost) \u{2014} stopping"
+            ))),
+            Print("\r\n"),
+        )
+        .ok();
+        if skipped_followups > 0 {
+            queue!(
+                self.out,
+                Print(
+                    theme::dim()
+                        .apply(format!("  dropped {skipped_followups} queued follow-up(s)"))
+                ),
+                Print("\r\n"),
+            )
+            .ok();
+        }
         self.out.flush().ok();
     }
 
-    /// Write raw text (for input echo, etc.) with \r\n.
-    pub fn write_raw(&mut self, text: &str) {
-        queue!(self.out, Print(text)).ok();
+    /// Explain that claude is denying every tool because `dir` isn't trusted.
+    pub fn render_untrusted(&mut self, dir: &str) {
+        self.finish_current_block();
+        queue!(
+            self.out,
+            Print("\r\n"),
+            Print(theme::warning().apply(format!(
+                "Every tool call was denied: Claude Code doesn't trust {dir} yet, so it \
+                 ignores the permission mode and asks about each tool, which can't be \
+                 answered here."
+            ))),
+            Print("\r\n"),
+            Print(theme::dim().apply(format!(
+                "  To fix it, run `claude` in {dir} once and accept the trust prompt."
+            ))),
+            Print("\r\n"),
+        )
+        .ok();
         self.out.flush().ok();
     }
 
-    /// Set the terminal window title via OSC escape sequence.
-    pub fn set_title(&mut self, title: &str) {
-        let sanitized: String = title.chars().filter(|c| !c.is_ascii_control()).collect();
-        queue!(self.out, Print(format!("\x1b]2;{sanitized}\x07"))).ok();
+    /// A warning the user should act on, shown in bold yellow rather than dimmed.
+    pub fn render_alert(&mut self, warning: &str) {
+        queue!(
+            self.out,
+            Print(theme::warning().apply(format!("Warning: {warning}"))),
+            Print("\r\n"),
+        )
+        .ok();
         self.out.flush().ok();
     }
-}
 
-/// Truncate a string to fit within `max_width` display columns, appending `...` if truncated.
-fn truncate_to_width(s: &str, max_width: usize) -> String {
-    let ellipsis_width = 3; // "..."
-    let mut width = 0;
-    // Track the byte position where we'd cut for ellipsis
-    let mut cut_pos = 0;
-    let mut result = String::new();
-    for ch in s.chars() {
-        let ch_width = ch.width().unwrap_or(0);
-        if width + ch_width > max_width {
-            // Need to truncate — use the cut point we've been tracking
-            if max_width >= ellipsis_width {
-                result.truncate(cut_pos);
-                result.push_str("...");
-            } else {
-                result.clear();
-            }
-            return result;
+    /// Show the first `max_lines` lines of a hook's output, dimmed and indented.
+    pub fn render_hook_output(&mut self, output: &str, max_lines: usize) {
+        let lines: Vec<&str> = output.lines().filter(|l| !l.trim().is_empty()).collect();
+        for line in lines.iter().take(max_lines) {
+            queue!(
+                self.out,
+                Print(theme::dim().apply(format!("  {line}"))),
+                Print("\r\n"),
+            )
+            .ok();
         }
-        result.push(ch);
-        width += ch_width;
-        // Track the latest position that leaves room for "..."
-        if width <= max_width.saturating_sub(ellipsis_width) {
-            cut_pos = result.len();
+        if lines.len() > max_lines {
+            let more = lines.len() - max_lines;
+            queue!(
+                self.out,
+                Print(theme::dim().apply(format!("  … {more} more line(s)"))),
+                Print("\r\n"),
+            )
+            .ok();
+        }
+        self.out.flush().ok();
+    }
+
+    /// List what a land brought onto main, like a compact `git show --stat`:
+    /// up to `max_commits` commits, then the combined diff stat.
+    pub fn render_land_summary(&mut self, summary: &LandSummary, max_commits: usize) {
+        let mut lines: Vec<String> = summary
+            .commits
+            .iter()
+            .take(max_commits)
+            .map(|c| format!("  {} {}", c.sha, c.subject))
+            .collect();
+        if summary.commits.len() > max_commits {
+            let more = summary.commits.len() - max_commits;
+            lines.push(format!("  \u{2026} {more} more commit(s)"));
+        }
+        let stat = summary.stat;
+        if stat.files_changed > 0 {
+            let files = if stat.files_changed == 1 {
+                "file"
+            } else {
+                "files"
+            };
+            lines.push(format!(
+                "  {} {files} changed, +{} \u{2212}{}",
+                stat.files_changed, stat.insertions, stat.deletions
+            ));
+        }
+        for line in lines {
+            queue!(self.out, Print(theme::dim().apply(line)), Print("\r\n")).ok();
+        }
+        self.out.flush().ok();
+    }
+
+    /// Warn that a steering message got no response within `secs` seconds.
+    /// `resuming` indicates the session is being restarted automatically.
+    pub fn render_steering_silence(&mut self, secs: u64, resuming: bool) {
+        self.finish_current_block();
+        let hint = if resuming {
+            "restarting claude and resending the message"
+        } else {
+            "press Ctrl+C and resume to re-send it"
+        };
+        queue!(
+            self.out,
+            Print(theme::dim().apply(format!(
+                "[warn] steering may not have been received — claude has produced no output for {secs}s"
+            ))),
+            Print("\r\n"),
+            Print(theme::dim().apply(format!("  {hint}"))),
+            Print("\r\n"),
+        )
+        .ok();
+        self.out.flush().ok();
+    }
+
+    pub fn render_returned_from_interactive(&mut self) {
+        self.finish_current_block();
+        queue!(
+            self.out,
+            Print("\r\n"),
+            Print(theme::dim().apply("[returned to coven — Ctrl+O to re-open interactive]")),
+            Print("\r\n"),
+        )
+        .ok();
+        self.out.flush().ok();
+    }
+
+    /// The session finished after a Ctrl+W; ring the bell and prompt for input.
+    pub fn render_wait_requested(&mut self) {
+        queue!(
+            self.out,
+            Print("\x07"),
+            Print("\r\n[waiting for user input]\r\n")
+        )
+        .ok();
+        self.out.flush().ok();
+    }
+
+    /// The agent asked for the user with `<wait-for-user>`: ring the bell and
+    /// show its reason, set off so it isn't lost in the session output.
+    pub fn render_wait_for_user(&mut self, reason: &str) {
+        self.finish_current_block();
+        queue!(
+            self.out,
+            Print("\x07"),
+            Print("\r\n"),
+            Print(theme::warning().apply("Waiting for user: ")),
+            Print(reason.replace('\n', "\r\n")),
+            Print("\r\n"),
+        )
+        .ok();
+        self.out.flush().ok();
+    }
+
+    /// The user dismissed a Ctrl+W wait; the loop proceeds as if it never happened.
+    pub fn render_wait_cancelled(&mut self) {
+        queue!(
+            self.out,
+            Print(theme::dim().apply("[wait cancelled \u{2014} continuing]")),
+            Print("\r\n")
+        )
+        .ok();
+        self.out.flush().ok();
+    }
+
+    pub fn render_interrupted(&mut self) {
+        self.finish_current_block();
+        queue!(
+            self.out,
+            Print("\r\n"),
+            Print(theme::dim().apply("[interrupted — Ctrl+O to open interactive]")),
+            Print("\r\n"),
+        )
+        .ok();
+        self.out.flush().ok();
+    }
+
+    /// A worker's dispatch phase was interrupted; unlike other phases,
+    /// Ctrl+D skips just this dispatch.
+    pub fn render_dispatch_interrupted(&mut self) {
+        self.finish_current_block();
+        queue!(
+            self.out,
+            Print("\r\n"),
+            Print(theme::dim().apply(
+                "[dispatch interrupted — type to resume it, Ctrl+D to skip this dispatch \
+                 and sleep, Ctrl+C to stop the worker, Ctrl+O to open interactive]"
+            )),
+            Print("\r\n"),
+        )
+        .ok();
+        self.out.flush().ok();
+    }
+
+    /// A ralph iteration hit `--iteration-timeout` and was killed.
+    pub fn render_iteration_timeout(&mut self, secs: u64) {
+        self.finish_current_block();
+        queue!(
+            self.out,
+            Print("\r\n"),
+            Print(theme::dim().apply(format!("[iteration timed out after {secs}s]"))),
+            Print("\r\n"),
+        )
+        .ok();
+        self.out.flush().ok();
+    }
+
+    pub fn render_exit(&mut self, code: Option<i32>) {
+        let msg = match code {
+            Some(c) => format!("Claude process exited with code {c}"),
+            None => "Claude process exited".to_string(),
+        };
+        queue!(self.out, Print(theme::dim().apply(msg)), Print("\r\n"),).ok();
+        self.out.flush().ok();
+    }
+
+    /// Show (or update) a dim `…working (Ns)` indicator while claude is
+    /// silent, with how many calls each running subagent has made
+    /// (`· [4] Task: 7 calls`). It trails an open tool line if it fits,
+    /// otherwise sits on its own line; it isn't drawn mid-text. Erase it with
+    /// [`clear_activity`](Self::clear_activity) before rendering anything else.
+    pub fn render_activity(&mut self, elapsed_secs: u64) {
+        self.clear_activity();
+        let mut subagents: Vec<_> = self.active_subagents.values().collect();
+        subagents.sort_by_key(|sa| sa.tool_number);
+        let text = std::iter::once(format!("\u{2026}working ({elapsed_secs}s)"))
+            .chain(subagents.iter().map(|sa| sa.progress()))
+            .collect::<Vec<_>>()
+            .join(" \u{b7} ");
+        let inline = self.tool_line_open;
+        let (text, width) = if inline {
+            let width = text.width() + 2;
+            if self.tool_line_width + width >= self.size.width {
+                return;
+            }
+            (format!("  {text}"), width)
+        } else if self.text_streaming
+            || (self.config.show_thinking && self.current_block == Some(BlockKind::Thinking))
+        {
+            return;
+        } else {
+            let width = text.width();
+            (text, width)
+        };
+        queue!(self.out, Print(theme::dim().apply(text))).ok();
+        self.out.flush().ok();
+        self.activity = Some(ActivityIndicator { width, inline });
+    }
+
+    /// Erase the `…working` indicator, if shown, leaving the cursor where it
+    /// was before the indicator was drawn.
+    pub fn clear_activity(&mut self) {
+        let Some(activity) = self.activity.take() else {
+            return;
+        };
+        if activity.inline {
+            let width = u16::try_from(activity.width).unwrap_or(u16::MAX);
+            queue!(self.out, MoveLeft(width), Clear(ClearType::UntilNewLine)).ok();
+        } else {
+            queue!(self.out, Print("\r"), Clear(ClearType::CurrentLine)).ok();
+        }
+        self.out.flush().ok();
+    }
+
+    /// Write raw text (for input echo, etc.) with \r\n.
+    pub fn write_raw(&mut self, text: &str) {
+        queue!(self.out, Print(text)).ok();
+        self.out.flush().ok();
+    }
+
+    /// Set the terminal window title via OSC escape sequence.
+    ///
+    /// Long titles are truncated to [`MAX_TITLE_WIDTH`] so they don't blow
+    /// out tmux tabs.
+    pub fn set_title(&mut self, title: &str) {
+        let sanitized: String = title.chars().filter(|c| !c.is_ascii_control()).collect();
+        let sanitized = truncate_to_width(&sanitized, MAX_TITLE_WIDTH);
+        queue!(self.out, Print(format!("\x1b]2;{sanitized}\x07"))).ok();
+        self.out.flush().ok();
+    }
+}
+
+/// Format seconds since the Unix epoch as e.g. `Feb 20 14:00 UTC`.
+fn format_utc_datetime(secs: u64) -> String {
+    let t = UtcDateTime::from_unix(secs);
+    format!(
+        "{} {} {:02}:{:02} UTC",
+        t.month_abbrev(),
+        t.day,
+        t.hour,
+        t.minute
+    )
+}
+
+/// Maximum display width of a terminal title set by [`Renderer::set_title`].
+const MAX_TITLE_WIDTH: usize = 60;
+
+/// Truncate a string to fit within `max_width` display columns, appending `...` if truncated.
+fn truncate_to_width(s: &str, max_width: usize) -> String {
+    let ellipsis_width = 3; // "..."
+    let mut width = 0;
+    // Track the byte position where we'd cut for ellipsis
+    let mut cut_pos = 0;
+    let mut result = String::new();
+    for ch in s.chars() {
+        let ch_width = ch.width().unwrap_or(0);
+        if width + ch_width > max_width {
+            // Need to truncate — use the cut point we've been tracking
+            if max_width >= ellipsis_width {
+                result.truncate(cut_pos);
+                result.push_str("...");
+            } else {
+                result.clear();
+            }
+            return result;
+        }
+        result.push(ch);
+        width += ch_width;
+        // Track the latest position that leaves room for "..."
+        if width <= max_width.saturating_sub(ellipsis_width) {
+            cut_pos = result.len();
         }
     }
     result
 }
 
+/// Words that mark a stderr line as a problem rather than a log message.
+const STDERR_ERROR_WORDS: &[&str] = &[
+    "error",
+    "fail",
+    "fatal",
+    "panic",
+    "exception",
+    "traceback",
+    "denied",
+    "refused",
+    "not found",
+    "invalid",
+    "unable to",
+    "cannot",
+    "warn",
+];
+
+/// Whether a stderr line looks like it reports a problem.
+fn is_stderr_error(line: &str) -> bool {
+    let line = line.to_lowercase();
+    STDERR_ERROR_WORDS.iter().any(|word| line.contains(word))
+}
+
+/// Seconds rounded to tenths, as in Done lines: `12.3s`.
+pub(crate) fn format_tenths(duration_ms: u64) -> String {
+    // Add 50ms to round instead of truncate
+    let rounded = duration_ms + 50;
+    format!("{}.{}s", rounded / 1000, (rounded % 1000) / 100)
+}
+
+/// Claude Code opens every compaction summary with the same sentence.
+const SUMMARY_PREAMBLE: &str = "This session is being continued from a previous conversation";
+
+/// The first line of a compaction summary worth showing, skipping the
+/// standard preamble, markdown headings and section labels such as
+/// `Summary:` or `1. Primary Request and Intent:`.
+fn summary_headline(summary: &str) -> Option<&str> {
+    summary.lines().map(str::trim).find(|line| {
+        !line.is_empty()
+            && !line.starts_with('#')
+            && !line.ends_with(':')
+            && !line.starts_with(SUMMARY_PREAMBLE)
+    })
+}
+
+/// A tool name embedded in the call's own input, as some MCP tools do.
+fn tool_name_from_input(input: &Value) -> Option<String> {
+    ["tool_name", "tool"]
+        .iter()
+        .find_map(|key| input.get(key).and_then(Value::as_str))
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+}
+
 /// Shorten MCP tool names from `mcp__<server-key>__<tool>` to a colon-separated form.
 ///
 /// Plugin keys encode `plugin:<id>:<name>` as `plugin_<id>_<name>`, so we decode
@@ -1037,7 +2569,10 @@ fn strip_tool_use_error_wrap(s: &str) -> String {
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used)]
 mod tests {
+    use std::fmt::Write as _;
+
     use super::*;
 
     #[test]
@@ -1091,11 +2626,393 @@ mod tests {
         assert_eq!(truncate_to_width("漢字ab", 5), "漢...");
     }
 
+    #[test]
+    fn format_utc_datetime_examples() {
+        assert_eq!(format_utc_datetime(0), "Jan 1 00:00 UTC");
+        assert_eq!(format_utc_datetime(1_771_545_600), "Feb 20 00:00 UTC");
+        assert_eq!(format_utc_datetime(1_709_210_096), "Feb 29 12:34 UTC");
+        assert_eq!(format_utc_datetime(1_798_761_599), "Dec 31 23:59 UTC");
+    }
+
+    #[test]
+    fn notes_are_stored_and_listed() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        assert!(format_notes(r.notes()).contains("No notes yet"));
+        r.add_note("14:03:22Z", "tests flaky on CI");
+        r.add_note("14:05:10Z", "check the v2 API");
+        assert!(String::from_utf8_lossy(&r.out).contains("note 2: check the v2 API"));
+        assert_eq!(
+            format_notes(r.notes()),
+            "Notes\n\n- [14:03:22Z] tests flaky on CI\n- [14:05:10Z] check the v2 API"
+        );
+    }
+
+    #[test]
+    fn set_title_truncates_long_titles() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.set_title(&format!(
+            "cv main: {} \u{2014} swift-fox-3",
+            "x".repeat(100)
+        ));
+        let output = String::from_utf8(r.out).unwrap();
+        let title = output
+            .strip_prefix("\x1b]2;")
+            .and_then(|s| s.strip_suffix('\x07'))
+            .unwrap();
+        assert_eq!(title.chars().count(), MAX_TITLE_WIDTH);
+        assert!(title.ends_with("..."));
+    }
+
     /// Helper to create a StreamEvent from JSON.
     fn stream_event(json: Value) -> StreamEvent {
         serde_json::from_value(json).unwrap()
     }
 
+    fn open_tool_line(r: &mut Renderer<Vec<u8>>) {
+        r.render_tool_call_line("Bash", &serde_json::json!({"command": "sleep 60"}), None);
+        r.out.clear();
+    }
+
+    #[test]
+    fn retry_of_failed_call_gets_marker_and_note() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.set_width(80);
+        let error = serde_json::json!({"is_error": true, "content": "Error: exit 1"});
+        r.render_tool_call_line("Bash", &serde_json::json!({"command": "cargo tset"}), None);
+        r.render_tool_result(&error, None);
+        r.out.clear();
+
+        r.render_tool_call_line("Bash", &serde_json::json!({"command": "cargo test"}), None);
+        let output = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(output.contains("[2] ↻ Bash  cargo test"), "{output:?}");
+        assert!(output.contains("(-tset +test)"), "{output:?}");
+        assert_eq!(r.messages[1].label, "[2] Bash");
+
+        // A succeeding call isn't followed by a retry line.
+        r.render_tool_result(&serde_json::json!("ok"), None);
+        r.render_tool_call_line("Bash", &serde_json::json!({"command": "cargo test"}), None);
+        let output = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(output.contains("[3] ▶ Bash"), "{output:?}");
+    }
+
+    #[test]
+    fn result_preview_shows_first_lines_of_successes_only() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.set_width(30);
+        r.set_result_preview_lines(2);
+        open_tool_line(&mut r);
+        let long = "x".repeat(50);
+        r.render_tool_result(&serde_json::json!(format!("one\t1\n{long}\nthree\n")), None);
+        let output = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(output.contains("    one    1"), "{output:?}");
+        assert!(output.contains("\u{2026} +1 line\x1b"), "{output:?}");
+        assert!(!output.contains("three"), "{output:?}");
+        assert!(!output.contains(&long), "{output:?}");
+
+        open_tool_line(&mut r);
+        let error = serde_json::json!({"is_error": true, "content": "Error: exit 1\nstderr"});
+        r.render_tool_result(&error, None);
+        let output = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(output.contains("\u{2717} Error: exit 1"), "{output:?}");
+        assert!(!output.contains("stderr"), "{output:?}");
+    }
+
+    #[test]
+    fn narrow_terminal_renders_at_default_width_with_one_notice() {
+        let mut r = Renderer::with_writer(Vec::new());
+        for width in [0, 1, 5, 19] {
+            r.set_width(width);
+            assert_eq!(r.width(), 80);
+        }
+        r.render_narrow_notice();
+        r.render_narrow_notice();
+        let output = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert_eq!(output.matches("columns wide").count(), 1, "{output:?}");
+        assert!(output.contains("terminal is 19 columns wide"), "{output:?}");
+
+        r.set_width(20);
+        assert_eq!(r.width(), 20);
+        r.set_width(3);
+        assert_eq!(r.width(), 80);
+        r.render_narrow_notice();
+        assert!(r.out.is_empty());
+    }
+
+    #[test]
+    fn activity_trails_open_tool_line_and_erases_in_place() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.set_width(80);
+        open_tool_line(&mut r);
+
+        r.render_activity(3);
+        r.render_activity(4);
+        r.clear_activity();
+        let output = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(output.contains("  \u{2026}working (3s)"));
+        assert!(output.contains("  \u{2026}working (4s)"));
+        assert!(
+            !output.contains('\r'),
+            "must stay on the tool line: {output:?}"
+        );
+        // Each redraw and the final clear step back over the 15-column indicator.
+        assert_eq!(output.matches("\x1b[15D").count(), 2);
+        assert!(output.ends_with("\x1b[15D\x1b[K"));
+
+        // A second clear is a no-op.
+        r.clear_activity();
+        assert!(r.out.is_empty());
+    }
+
+    #[test]
+    fn activity_skipped_when_tool_line_is_full() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.set_width(20);
+        open_tool_line(&mut r);
+        r.render_activity(3);
+        assert!(r.out.is_empty());
+    }
+
+    #[test]
+    fn activity_gets_own_line_between_blocks_but_not_mid_text() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.render_activity(2);
+        r.clear_activity();
+        let output = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(output.contains("\u{2026}working (2s)"));
+        assert!(output.ends_with("\r\x1b[2K"));
+
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_start", "content_block": { "type": "text" } }
+        })));
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_delta",
+                       "delta": { "type": "text_delta", "text": "Let me" } }
+        })));
+        r.out.clear();
+        r.render_activity(2);
+        assert!(r.out.is_empty());
+    }
+
+    fn stream_text_block(r: &mut Renderer<Vec<u8>>, deltas: &[&str]) -> String {
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_start", "content_block": { "type": "text" } }
+        })));
+        for text in deltas {
+            r.handle_stream_event(&stream_event(serde_json::json!({
+                "event": { "type": "content_block_delta",
+                           "delta": { "type": "text_delta", "text": text } }
+            })));
+        }
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_stop" }
+        })));
+        String::from_utf8(std::mem::take(&mut r.out)).unwrap()
+    }
+
+    #[test]
+    fn signature_and_unknown_deltas_render_nothing() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        let expected = stream_text_block(&mut r, &["Hello ", "world"]);
+        for delta in [
+            serde_json::json!({ "type": "signature_delta", "signature": "EqQBCkgIBxAB" }),
+            serde_json::json!({ "type": "citations_delta", "citation": { "cited_text": "x" } }),
+        ] {
+            r.handle_stream_event(&stream_event(serde_json::json!({
+                "event": { "type": "content_block_start", "content_block": { "type": "text" } }
+            })));
+            r.handle_stream_event(&stream_event(serde_json::json!({
+                "event": { "type": "content_block_delta",
+                           "delta": { "type": "text_delta", "text": "Hello " } }
+            })));
+            r.handle_stream_event(&stream_event(serde_json::json!({
+                "event": { "type": "content_block_delta", "delta": delta }
+            })));
+            r.handle_stream_event(&stream_event(serde_json::json!({
+                "event": { "type": "content_block_delta",
+                           "delta": { "type": "text_delta", "text": "world" } }
+            })));
+            r.handle_stream_event(&stream_event(serde_json::json!({
+                "event": { "type": "content_block_stop" }
+            })));
+            assert_eq!(
+                String::from_utf8(std::mem::take(&mut r.out)).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn render_markdown_styles_text_split_across_deltas() {
+        let deltas = ["The **pars", "er** is `ok", "` now *"];
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        assert_eq!(
+            stream_text_block(&mut r, &deltas),
+            "The **parser** is `ok` now *\r\n\r\n"
+        );
+
+        r.set_render_markdown(true);
+        let output = stream_text_block(&mut r, &deltas);
+        assert!(
+            !output.contains("**") && !output.contains('`'),
+            "{output:?}"
+        );
+        assert!(output.contains("\x1b[1mparser"), "{output:?}");
+        assert!(output.ends_with(" now *\r\n\r\n"), "{output:?}");
+    }
+
+    /// `count` numbered lines, split into deltas that break mid-line.
+    fn long_text(count: usize) -> Vec<String> {
+        let mut text = String::new();
+        for i in 1..=count {
+            writeln!(text, "line {i}").unwrap();
+        }
+        text.as_bytes()
+            .chunks(7)
+            .map(|chunk| String::from_utf8(chunk.to_vec()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn show_usage_puts_tokens_under_the_done_line() {
+        let usage = TokenUsage {
+            fresh_input: 114,
+            cache_read: 10_200,
+            cache_creation: 2_000,
+            output: 1_830,
+        };
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.render_result("success", 0.06, 57_979, 14);
+        r.render_usage("tokens", &usage);
+        let without = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(!without.contains("tokens"), "{without:?}");
+
+        r.set_show_usage(true);
+        r.render_result("success", 0.06, 57_979, 14);
+        r.render_usage("tokens", &usage);
+        let output = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        insta::assert_snapshot!(
+            output
+                .replace("\x1b[38;5;10m\x1b[1m", "")
+                .replace("\x1b[2m", "")
+                .replace("\x1b[0m", "")
+                .replace("\r\n", "\n")
+        );
+    }
+
+    #[test]
+    fn max_stream_lines_holds_back_the_rest_for_view() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.set_max_stream_lines(Some(5));
+        let deltas = long_text(30);
+        let deltas: Vec<&str> = deltas.iter().map(String::as_str).collect();
+        let output = stream_text_block(&mut r, &deltas);
+        insta::assert_snapshot!(
+            output
+                .replace("\x1b[2m", "")
+                .replace("\x1b[0m", "")
+                .replace("\r\n", "\n")
+        );
+        let stored = r.messages().last().unwrap();
+        assert_eq!(stored.label, "[1] Text");
+        assert_eq!(stored.content, deltas.concat());
+        assert!(
+            format_message(r.messages(), "1")
+                .unwrap()
+                .contains("line 30")
+        );
+    }
+
+    #[test]
+    fn max_stream_lines_counts_each_block_afresh() {
+        let short = long_text(4);
+        let short: Vec<&str> = short.iter().map(String::as_str).collect();
+        let mut unlimited = Renderer::with_writer(Vec::<u8>::new());
+        let expected = stream_text_block(&mut unlimited, &short);
+
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.set_max_stream_lines(Some(5));
+        let long = long_text(8);
+        let long: Vec<&str> = long.iter().map(String::as_str).collect();
+        assert!(stream_text_block(&mut r, &long).contains("(+3 more lines, :1 to view)"));
+        assert_eq!(stream_text_block(&mut r, &short), expected);
+        assert_eq!(stream_text_block(&mut r, &short), expected);
+        // Exactly at the limit: nothing was held back.
+        let five = long_text(5);
+        let five: Vec<&str> = five.iter().map(String::as_str).collect();
+        assert!(!stream_text_block(&mut r, &five).contains("more line"));
+        assert_eq!(r.messages().len(), 1);
+    }
+
+    fn denial(command: &str) -> PermissionDenial {
+        serde_json::from_value(serde_json::json!({
+            "tool_name": "Bash",
+            "tool_use_id": "toolu_1",
+            "tool_input": {"command": command},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn permission_denials_summary_and_view() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.render_permission_denials(&[]);
+        assert!(r.out.is_empty() && r.messages.is_empty());
+
+        r.render_permission_denials(&[denial("git push"), denial("rm -rf target")]);
+        let output = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(
+            output.contains("  2 permission denials during this turn (:denials to view)"),
+            "{output:?}"
+        );
+        assert_eq!(
+            format_message(&r.messages, "denials").unwrap(),
+            "Denials\n\nBash  git push\nBash  rm -rf target"
+        );
+
+        r.render_permission_denials(&[denial("git push --force")]);
+        let output = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(
+            output.contains("  1 permission denial during this turn (:denials[-1] to view)"),
+            "{output:?}"
+        );
+        assert!(
+            format_message(&r.messages, "denials[-1]")
+                .unwrap()
+                .ends_with("Bash  git push --force")
+        );
+    }
+
+    #[test]
+    fn land_summary_lists_commits_then_stat() {
+        use crate::worktree::{DiffStat, LandedCommit};
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        let commit = |sha: &str, subject: &str| LandedCommit {
+            sha: sha.into(),
+            subject: subject.into(),
+        };
+        r.render_land_summary(
+            &LandSummary {
+                commits: vec![
+                    commit("abc1234", "Add parser"),
+                    commit("def5678", "Test parser"),
+                    commit("0123abc", "Docs"),
+                ],
+                stat: DiffStat {
+                    files_changed: 4,
+                    insertions: 120,
+                    deletions: 7,
+                },
+            },
+            2,
+        );
+        let output = String::from_utf8(r.out).unwrap();
+        assert!(output.contains("abc1234 Add parser"));
+        assert!(output.contains("def5678 Test parser"));
+        assert!(!output.contains("Docs"));
+        assert!(output.contains("\u{2026} 1 more commit(s)"));
+        assert!(output.contains("4 files changed, +120 \u{2212}7"));
+    }
+
     #[test]
     fn subagent_tool_call_does_not_merge_with_pending_tool_line() {
         let mut r = Renderer::with_writer(Vec::<u8>::new());
@@ -1155,4 +3072,422 @@ mod tests {
             lines[1]
         );
     }
+
+    #[test]
+    fn input_before_block_start_keeps_the_tool_name() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_delta",
+                       "delta": { "type": "input_json_delta", "partial_json": "{\"command\":" } }
+        })));
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_start",
+                       "content_block": { "type": "tool_use", "name": "Bash", "id": "t1" } }
+        })));
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_delta",
+                       "delta": { "type": "input_json_delta", "partial_json": "\"ls\"}" } }
+        })));
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_stop" }
+        })));
+
+        let output = String::from_utf8(r.out.clone()).unwrap();
+        assert!(output.contains("[1] \u{25b6} Bash  ls"), "{output:?}");
+        assert!(!output.contains("unknown"));
+        assert_eq!(r.messages().len(), 1);
+        assert_eq!(
+            resolve_query(r.messages(), "Bash").unwrap().label,
+            "[1] Bash"
+        );
+    }
+
+    #[test]
+    fn unnamed_tool_is_named_by_its_assistant_message() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_start",
+                       "content_block": { "type": "tool_use", "id": "t1" } }
+        })));
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_delta",
+                       "delta": { "type": "input_json_delta", "partial_json": "{\"pattern\":\"*.rs\"}" } }
+        })));
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_stop" }
+        })));
+        assert_eq!(r.messages()[0].label, "[1] unknown");
+
+        let content: Vec<AssistantContentBlock> = serde_json::from_value(serde_json::json!([
+            { "type": "tool_use", "id": "t1", "name": "Glob", "input": { "pattern": "*.rs" } }
+        ]))
+        .unwrap();
+        r.name_unnamed_tools(&content);
+        assert_eq!(
+            resolve_query(r.messages(), "Glob").unwrap().label,
+            "[1] Glob"
+        );
+        assert!(r.unnamed_tools.is_empty());
+    }
+
+    #[test]
+    fn unnamed_tool_takes_a_name_from_its_input() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_delta",
+                       "delta": { "type": "input_json_delta",
+                                  "partial_json": "{\"tool_name\":\"search_docs\",\"query\":\"vcr\"}" } }
+        })));
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_stop" }
+        })));
+        assert_eq!(r.messages()[0].label, "[1] search_docs");
+        assert!(r.unnamed_tools.is_empty());
+    }
+
+    #[test]
+    fn compaction_counts_messages_since_the_last_one() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.set_width(120);
+        let summary =
+            |text: &str| serde_json::json!({ "content": [{ "type": "text", "text": text }] });
+        open_tool_line(&mut r);
+        r.render_compaction();
+        r.set_compaction_content(Some(&summary("First summary")));
+        for _ in 0..2 {
+            open_tool_line(&mut r);
+        }
+        r.out.clear();
+        r.render_compaction();
+        r.set_compaction_content(Some(&summary("## Heading\n\nSecond summary")));
+
+        let out = String::from_utf8(r.out.clone()).unwrap();
+        assert!(
+            out.contains("Compacted 2 messages \u{b7} Second summary  (:5 to view)"),
+            "{out:?}"
+        );
+        assert_eq!(
+            resolve_query(r.messages(), "Compaction[-1]")
+                .unwrap()
+                .content,
+            "## Heading\n\nSecond summary"
+        );
+        assert!(!r.is_compacting());
+    }
+
+    #[test]
+    fn quiet_stderr_keeps_only_errors() {
+        let mut r = Renderer::with_writer(Vec::new());
+        r.set_quiet_stderr(true);
+        r.render_stderr("[MCP] server \"github\" ready\nfatal: not a git repository\n");
+        let out = String::from_utf8(r.writer().clone()).unwrap();
+        assert!(!out.contains("github"), "{out}");
+        assert!(out.contains("[warn] fatal: not a git repository"), "{out}");
+    }
+
+    /// Stream a complete Task call with id `id`.
+    fn start_task(r: &mut Renderer<Vec<u8>>, id: &str) {
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_start",
+                       "content_block": { "type": "tool_use", "name": "Task", "id": id } }
+        })));
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_delta",
+                       "delta": { "type": "input_json_delta", "partial_json": "{\"description\":\"Summarize README\"}" } }
+        })));
+        r.handle_stream_event(&stream_event(serde_json::json!({
+            "event": { "type": "content_block_stop" }
+        })));
+    }
+
+    #[test]
+    fn subagent_progress_shows_in_activity_and_summary() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.set_width(120);
+        start_task(&mut r, "sub1");
+        for file in ["README.md", "CHANGELOG.md"] {
+            r.render_subagent_tool_call("Read", &serde_json::json!({"file_path": file}), "sub1");
+            r.render_subagent_tool_result(&serde_json::json!({
+                "content": [{"type": "tool_result", "content": "ok"}]
+            }));
+        }
+        r.out.clear();
+
+        r.render_activity(5);
+        r.clear_activity();
+        let activity = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(
+            activity.contains("\u{2026}working (5s) \u{b7} [1] Task: 2 calls"),
+            "{activity:?}"
+        );
+
+        r.render_tool_result(
+            &serde_json::json!("The README describes coven."),
+            Some(&serde_json::json!({
+                "content": [{"type": "tool_result", "tool_use_id": "sub1", "content": "The README describes coven."}]
+            })),
+        );
+        let done = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(done.contains("  [1] Task done: 2 calls"), "{done:?}");
+        assert!(r.active_subagents.is_empty());
+
+        r.render_activity(6);
+        let activity = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(!activity.contains("Task"), "{activity:?}");
+    }
+
+    #[test]
+    fn subagent_summary_counts_and_times() {
+        let subagent = ActiveSubagent {
+            tool_number: 4,
+            name: "Task".into(),
+            child_counter: 1,
+            started: Instant::now(),
+        };
+        assert_eq!(subagent.progress(), "[4] Task: 1 call");
+        assert_eq!(
+            subagent.summary(Duration::from_millis(400)),
+            "[4] Task done: 1 call"
+        );
+        assert_eq!(
+            subagent.summary(Duration::from_secs(62)),
+            "[4] Task done: 1 call in 1m02s"
+        );
+    }
+
+    fn stored(labels: &[&str]) -> Vec<StoredMessage> {
+        labels
+            .iter()
+            .map(|label| StoredMessage {
+                label: (*label).to_string(),
+                content: String::new(),
+                result: None,
+                failed: false,
+            })
+            .collect()
+    }
+
+    fn sample() -> Vec<StoredMessage> {
+        stored(&[
+            "[1] Thinking",
+            "[2] Bash",
+            "[3] Read",
+            "[4] Bash",
+            "[4/1] Grep",
+        ])
+    }
+
+    /// A session's messages with real tool inputs: thinking, two Bash calls
+    /// (the second failing), a Read, and a subagent's Grep.
+    fn session_messages() -> Vec<StoredMessage> {
+        let msg = |label: &str, content: &str, failed: bool| StoredMessage {
+            label: label.to_string(),
+            content: content.to_string(),
+            result: None,
+            failed,
+        };
+        vec![
+            msg(
+                "[1] Thinking",
+                "Let me look at the tests.\nThen the code.",
+                false,
+            ),
+            msg("[2] Bash", r#"{"command": "cargo test"}"#, false),
+            msg("[3] Read", r#"{"file_path": "/repo/src/main.rs"}"#, false),
+            msg("[4] Bash", r#"{"command": "cargo test --no-run"}"#, true),
+            msg("[4/1] Grep", r#"{"pattern": "fn main"}"#, false),
+        ]
+    }
+
+    #[test]
+    fn index_lists_every_message() {
+        insta::assert_snapshot!(format_index(&session_messages()));
+    }
+
+    #[test]
+    fn redraw_recaps_the_latest_messages() {
+        let mut r = Renderer::with_writer(Vec::<u8>::new());
+        r.render_session_header("abc-123", "claude-sonnet");
+        r.messages = session_messages();
+        r.out.clear();
+        r.redraw_screen();
+        let output = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(output.starts_with("\x1b[2J\x1b[1;1H"), "{output:?}");
+        insta::assert_snapshot!(
+            output
+                .replace("\x1b[2J\x1b[1;1H", "")
+                .replace("\x1b[2m", "")
+                .replace("\x1b[0m", "")
+                .replace("\r\n", "\n")
+        );
+
+        r.render_recap(2);
+        let output = String::from_utf8(std::mem::take(&mut r.out)).unwrap();
+        assert!(output.contains("3 earlier message(s)"), "{output:?}");
+        assert!(!output.contains("[3] Read"), "{output:?}");
+    }
+
+    #[test]
+    fn index_without_messages() {
+        insta::assert_snapshot!(format_index(&[]));
+    }
+
+    #[test]
+    fn done_hint_names_a_repeated_tool() {
+        let mut r = Renderer::with_writer(Vec::new());
+        r.messages = session_messages();
+        r.render_result("success", 0.01, 1000, 1);
+        let out = String::from_utf8(r.writer().clone()).unwrap();
+        assert!(out.contains("(:N or :Bash[-1] to view)"), "{out}");
+
+        let mut r = Renderer::with_writer(Vec::new());
+        r.messages = session_messages().into_iter().skip(2).take(1).collect();
+        r.render_result("success", 0.01, 1000, 1);
+        let out = String::from_utf8(r.writer().clone()).unwrap();
+        assert!(out.contains("(:N to view)"), "{out}");
+    }
+
+    #[test]
+    fn resolve_query_numeric() {
+        let msgs = sample();
+        assert_eq!(resolve_query(&msgs, "3").unwrap().label, "[3] Read");
+        assert_eq!(resolve_query(&msgs, "4/1").unwrap().label, "[4/1] Grep");
+        assert_eq!(
+            resolve_query(&msgs, "9").unwrap_err().to_string(),
+            "no message [9]"
+        );
+    }
+
+    #[test]
+    fn resolve_query_label_is_case_insensitive_and_trimmed() {
+        let msgs = sample();
+        assert_eq!(resolve_query(&msgs, " bash ").unwrap().label, "[2] Bash");
+        assert_eq!(resolve_query(&msgs, "BASH[1]").unwrap().label, "[4] Bash");
+        assert_eq!(resolve_query(&msgs, "Bash[-1]").unwrap().label, "[4] Bash");
+        assert_eq!(resolve_query(&msgs, "Bash[-2]").unwrap().label, "[2] Bash");
+    }
+
+    #[test]
+    fn resolve_query_malformed() {
+        let msgs = sample();
+        for query in ["0/", "1/a", "[0]", "Bash[abc]", "Bash[0", "Ba sh"] {
+            assert_eq!(
+                resolve_query(&msgs, query).unwrap_err(),
+                QueryError::Malformed(query.to_string()),
+                "{query}"
+            );
+        }
+        assert_eq!(
+            resolve_query(&msgs, "Bash[x]").unwrap_err().to_string(),
+            "can't read :Bash[x] — expected :N, :P/C, :Name, :Name[index] or :/text"
+        );
+    }
+
+    #[test]
+    fn resolve_query_unknown_tool_suggests_close_names() {
+        let msgs = sample();
+        assert_eq!(
+            resolve_query(&msgs, "Bsah").unwrap_err().to_string(),
+            "no Bsah messages — did you mean Bash?"
+        );
+        assert_eq!(
+            resolve_query(&msgs, "Re").unwrap_err().to_string(),
+            "no Re messages — did you mean Read, Grep?"
+        );
+        assert_eq!(
+            resolve_query(&msgs, "WebFetch").unwrap_err().to_string(),
+            "no WebFetch messages"
+        );
+    }
+
+    #[test]
+    fn resolve_query_index_out_of_range() {
+        let msgs = sample();
+        assert_eq!(
+            resolve_query(&msgs, "Bash[2]").unwrap_err().to_string(),
+            "Bash[2] is out of range — valid indexes are 0..=1 or -2..=-1"
+        );
+        assert_eq!(
+            resolve_query(&msgs, "Bash[-3]").unwrap_err().to_string(),
+            "Bash[-3] is out of range — valid indexes are 0..=1 or -2..=-1"
+        );
+        assert_eq!(
+            resolve_query(&msgs, "Read[1]").unwrap_err().to_string(),
+            "Read[1] is out of range — only [0] or [-1] exists"
+        );
+    }
+
+    /// `session_messages` with results: both Bash calls ran tests.
+    fn searchable() -> Vec<StoredMessage> {
+        let mut msgs = session_messages();
+        msgs[1].result = Some("test result: ok. 3 passed".into());
+        msgs[3].result = Some("error[E0425]: cannot find value `x`".into());
+        msgs
+    }
+
+    #[test]
+    fn resolve_query_search_picks_the_most_recent_match() {
+        let msgs = searchable();
+        assert_eq!(
+            resolve_query(&msgs, "/CARGO TEST").unwrap().label,
+            "[4] Bash"
+        );
+        assert_eq!(
+            resolve_query(&msgs, "/cargo test/").unwrap().label,
+            "[4] Bash"
+        );
+        assert_eq!(
+            resolve_query(&msgs, "/cargo test/2").unwrap().label,
+            "[2] Bash"
+        );
+        // Results are searched too, and slashes belong to the text.
+        assert_eq!(resolve_query(&msgs, "/passed").unwrap().label, "[2] Bash");
+        assert_eq!(
+            resolve_query(&msgs, "/src/main.rs").unwrap().label,
+            "[3] Read"
+        );
+        assert_eq!(
+            resolve_query(&msgs, "/then the").unwrap().label,
+            "[1] Thinking"
+        );
+    }
+
+    #[test]
+    fn resolve_query_search_errors() {
+        let msgs = searchable();
+        for query in ["/", "//", "/cargo/0"] {
+            assert_eq!(
+                resolve_query(&msgs, query).unwrap_err(),
+                QueryError::Malformed(query.to_string()),
+                "{query}"
+            );
+        }
+        assert_eq!(
+            resolve_query(&msgs, "/clippy").unwrap_err().to_string(),
+            "no message contains \"clippy\""
+        );
+        assert_eq!(
+            resolve_query(&msgs, "/cargo test/3")
+                .unwrap_err()
+                .to_string(),
+            "/cargo test/3 is out of range — only 2 messages match"
+        );
+    }
+
+    #[test]
+    fn format_message_search_counts_matches() {
+        let view = format_message(&searchable(), "/cargo test/2").unwrap();
+        assert!(
+            view.starts_with("[2] Bash  (match 2 of 2)\n\n$ cargo test"),
+            "{view}"
+        );
+    }
+
+    #[test]
+    fn edit_distance_counts_edits() {
+        assert_eq!(edit_distance("bash", "bash"), 0);
+        assert_eq!(edit_distance("bsah", "bash"), 2);
+        assert_eq!(edit_distance("", "read"), 4);
+        assert_eq!(edit_distance("grep", "glob"), 3);
+    }
 }
@@ -7,65 +7,283 @@
 //!
 //! Use `Attribute::Dim` / `Attribute::Bold` for emphasis rather than bright
 //! color variants.
+//!
+//! The palette is picked once per process (`--theme`, `COVEN_THEME`,
+//! `NO_COLOR`) with [`set`]; the style functions below read it.
+
+use std::sync::OnceLock;
 
 use crossterm::style::{Attribute, Attributes, Color, ContentStyle};
 
-pub fn dim() -> ContentStyle {
-    ContentStyle {
-        attributes: Attribute::Dim.into(),
-        ..Default::default()
+/// A palette for coven's output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Theme {
+    /// Colors for dark terminal backgrounds.
+    #[default]
+    Dark,
+    /// Darker colors that stay readable on light backgrounds.
+    Light,
+    /// No colors or text attributes at all, for pipes and `NO_COLOR`.
+    Plain,
+}
+
+/// Something coven styles. Each [`Theme`] maps it to a [`ContentStyle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Dim,
+    DimItalic,
+    ToolName,
+    ToolNameDim,
+    Error,
+    Warning,
+    ResultLine,
+    Prompt,
+    ForkTool,
+    InlineCode,
+    Heading,
+    Bold,
+}
+
+impl Style {
+    pub const ALL: [Self; 12] = [
+        Self::Dim,
+        Self::DimItalic,
+        Self::ToolName,
+        Self::ToolNameDim,
+        Self::Error,
+        Self::Warning,
+        Self::ResultLine,
+        Self::Prompt,
+        Self::ForkTool,
+        Self::InlineCode,
+        Self::Heading,
+        Self::Bold,
+    ];
+}
+
+impl Theme {
+    /// The theme to use: `flag` (`--theme`), else `COVEN_THEME`, else
+    /// `plain` when `NO_COLOR` is set to anything but the empty string,
+    /// else `dark`.
+    pub fn resolve(
+        flag: Option<Self>,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, String> {
+        if let Some(theme) = flag {
+            return Ok(theme);
+        }
+        if let Some(name) = var("COVEN_THEME").filter(|v| !v.is_empty()) {
+            return <Self as clap::ValueEnum>::from_str(&name, true).map_err(|_| {
+                format!("COVEN_THEME={name:?} is not a theme (expected dark, light or plain)")
+            });
+        }
+        if var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return Ok(Self::Plain);
+        }
+        Ok(Self::Dark)
+    }
+
+    pub fn style(self, style: Style) -> ContentStyle {
+        let (dark, light, attributes): (Option<Color>, Option<Color>, Attributes) = match style {
+            Style::Dim => (None, None, Attribute::Dim.into()),
+            Style::DimItalic => (
+                None,
+                None,
+                Attributes::from(Attribute::Dim) | Attribute::Italic,
+            ),
+            Style::ToolName => (
+                Some(Color::Yellow),
+                Some(Color::DarkBlue),
+                Attributes::default(),
+            ),
+            Style::ToolNameDim => (
+                Some(Color::Yellow),
+                Some(Color::DarkBlue),
+                Attribute::Dim.into(),
+            ),
+            Style::Error => (
+                Some(Color::Red),
+                Some(Color::DarkRed),
+                Attributes::default(),
+            ),
+            Style::Warning => (
+                Some(Color::Yellow),
+                Some(Color::DarkYellow),
+                Attribute::Bold.into(),
+            ),
+            Style::ResultLine => (
+                Some(Color::Green),
+                Some(Color::DarkGreen),
+                Attribute::Bold.into(),
+            ),
+            Style::Prompt | Style::ForkTool => (
+                Some(Color::Cyan),
+                Some(Color::DarkCyan),
+                Attribute::Bold.into(),
+            ),
+            Style::InlineCode => (
+                Some(Color::Magenta),
+                Some(Color::DarkMagenta),
+                Attributes::default(),
+            ),
+            Style::Bold => (None, None, Attribute::Bold.into()),
+            Style::Heading => (
+                None,
+                None,
+                Attributes::from(Attribute::Dim) | Attribute::Underlined,
+            ),
+        };
+        match self {
+            Self::Dark => ContentStyle {
+                foreground_color: dark,
+                attributes,
+                ..Default::default()
+            },
+            Self::Light => ContentStyle {
+                foreground_color: light,
+                attributes,
+                ..Default::default()
+            },
+            Self::Plain => ContentStyle::default(),
+        }
     }
 }
 
-pub fn dim_italic() -> ContentStyle {
-    ContentStyle {
-        attributes: Attributes::from(Attribute::Dim) | Attribute::Italic,
-        ..Default::default()
+static ACTIVE: OnceLock<Theme> = OnceLock::new();
+
+/// Use `theme` for the rest of the process. Only the first call counts.
+pub fn set(theme: Theme) {
+    let _ = ACTIVE.set(theme);
+}
+
+/// The theme in use; `dark` until [`set`] is called.
+pub fn active() -> Theme {
+    ACTIVE.get().copied().unwrap_or_default()
+}
+
+/// A line of an Edit diff: `-` lines red, `+` lines green.
+pub fn diff_line(sign: char, line: &str) -> String {
+    let code = if sign == '-' { 31 } else { 32 };
+    match active() {
+        Theme::Plain => format!("{sign} {line}"),
+        Theme::Dark | Theme::Light => format!("\x1b[{code}m{sign} {line}\x1b[0m"),
     }
 }
 
+pub fn dim() -> ContentStyle {
+    active().style(Style::Dim)
+}
+
+pub fn dim_italic() -> ContentStyle {
+    active().style(Style::DimItalic)
+}
+
 pub fn tool_name() -> ContentStyle {
-    ContentStyle {
-        foreground_color: Some(Color::Yellow),
-        ..Default::default()
-    }
+    active().style(Style::ToolName)
 }
 
 pub fn tool_name_dim() -> ContentStyle {
-    ContentStyle {
-        foreground_color: Some(Color::Yellow),
-        attributes: Attribute::Dim.into(),
-        ..Default::default()
-    }
+    active().style(Style::ToolNameDim)
 }
 
 pub fn error() -> ContentStyle {
-    ContentStyle {
-        foreground_color: Some(Color::Red),
-        ..Default::default()
-    }
+    active().style(Style::Error)
+}
+
+pub fn warning() -> ContentStyle {
+    active().style(Style::Warning)
 }
 
 pub fn result_line() -> ContentStyle {
-    ContentStyle {
-        foreground_color: Some(Color::Green),
-        attributes: Attribute::Bold.into(),
-        ..Default::default()
-    }
+    active().style(Style::ResultLine)
 }
 
 pub fn prompt_style() -> ContentStyle {
-    ContentStyle {
-        foreground_color: Some(Color::Cyan),
-        attributes: Attribute::Bold.into(),
-        ..Default::default()
-    }
+    active().style(Style::Prompt)
 }
 
 pub fn fork_tool() -> ContentStyle {
-    ContentStyle {
-        foreground_color: Some(Color::Cyan),
-        attributes: Attribute::Bold.into(),
-        ..Default::default()
+    active().style(Style::ForkTool)
+}
+
+pub fn inline_code() -> ContentStyle {
+    active().style(Style::InlineCode)
+}
+
+pub fn heading() -> ContentStyle {
+    active().style(Style::Heading)
+}
+
+pub fn bold() -> ContentStyle {
+    active().style(Style::Bold)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::fmt::Write as _;
+
+    use super::*;
+
+    /// Every style applied to a sample, escapes shown.
+    fn swatch(theme: Theme) -> String {
+        let mut out = String::new();
+        for style in Style::ALL {
+            let styled = theme.style(style).apply("coven").to_string();
+            writeln!(out, "{style:?}: {styled:?}").unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn plain_theme_emits_no_escapes() {
+        insta::assert_snapshot!(swatch(Theme::Plain));
+    }
+
+    #[test]
+    fn light_theme_palette() {
+        insta::assert_snapshot!(swatch(Theme::Light));
+    }
+
+    #[test]
+    fn dark_theme_is_the_original_palette() {
+        let style = Theme::Dark.style(Style::Warning);
+        assert_eq!(style.foreground_color, Some(Color::Yellow));
+        assert_eq!(style.attributes, Attribute::Bold.into());
+        assert_eq!(Theme::Dark.style(Style::Dim).foreground_color, None);
+    }
+
+    #[test]
+    fn resolve_precedence() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                pairs
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| (*v).to_string())
+            }
+        };
+        assert_eq!(Theme::resolve(None, env(&[])), Ok(Theme::Dark));
+        assert_eq!(
+            Theme::resolve(None, env(&[("NO_COLOR", "1")])),
+            Ok(Theme::Plain)
+        );
+        assert_eq!(
+            Theme::resolve(None, env(&[("NO_COLOR", "")])),
+            Ok(Theme::Dark)
+        );
+        assert_eq!(
+            Theme::resolve(None, env(&[("NO_COLOR", "1"), ("COVEN_THEME", "Light")])),
+            Ok(Theme::Light)
+        );
+        assert_eq!(
+            Theme::resolve(Some(Theme::Dark), env(&[("COVEN_THEME", "plain")])),
+            Ok(Theme::Dark)
+        );
+        assert!(
+            Theme::resolve(None, env(&[("COVEN_THEME", "solarized")]))
+                .unwrap_err()
+                .contains("solarized")
+        );
     }
 }
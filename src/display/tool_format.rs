@@ -22,10 +22,10 @@ pub fn format_tool_view(tool_name: &str, input: &Value) -> Option<String> {
             let new = get_str(input, "new_string").unwrap_or("");
             let mut lines = vec![path.to_string()];
             for line in old.lines() {
-                lines.push(format!("\x1b[31m- {line}\x1b[0m"));
+                lines.push(super::theme::diff_line('-', line));
             }
             for line in new.lines() {
-                lines.push(format!("\x1b[32m+ {line}\x1b[0m"));
+                lines.push(super::theme::diff_line('+', line));
             }
             Some(lines.join("\n"))
         }
@@ -145,6 +145,165 @@ pub fn format_tool_detail(name: &str, input: &Value) -> String {
     }
 }
 
+/// Whether a tool call retries the previous one: same tool, and the previous
+/// call failed.
+pub fn is_retry(prev_name: &str, prev_failed: bool, name: &str) -> bool {
+    prev_failed && prev_name == name
+}
+
+/// Summarize how a retried call's input differs from the failed one, for the
+/// `↻` line. `None` when there's nothing compact to say.
+pub fn retry_note(name: &str, prev_input: &Value, input: &Value) -> Option<String> {
+    match name {
+        "Bash" => {
+            let prev = get_str(prev_input, "command")?;
+            let cmd = get_str(input, "command")?;
+            if prev == cmd {
+                Some("same command".to_string())
+            } else {
+                word_diff(prev, cmd)
+            }
+        }
+        "Edit" => {
+            let path_changed = get_str(prev_input, "file_path") != get_str(input, "file_path");
+            let old_changed = get_str(prev_input, "old_string") != get_str(input, "old_string");
+            Some(
+                match (path_changed, old_changed) {
+                    (true, _) => "different file",
+                    (false, true) => "same file, different old_string",
+                    (false, false) => "same file, same old_string",
+                }
+                .to_string(),
+            )
+        }
+        _ => {
+            let (Value::Object(prev), Value::Object(cur)) = (prev_input, input) else {
+                return None;
+            };
+            let mut changed: Vec<&str> = cur
+                .iter()
+                .filter(|(key, value)| prev.get(*key) != Some(value))
+                .map(|(key, _)| key.as_str())
+                .chain(
+                    prev.keys()
+                        .filter(|key| !cur.contains_key(*key))
+                        .map(String::as_str),
+                )
+                .collect();
+            changed.sort_unstable();
+            Some(if changed.is_empty() {
+                "same input".to_string()
+            } else {
+                format!("changed {}", changed.join(", "))
+            })
+        }
+    }
+}
+
+/// Largest LCS table (old tokens × new tokens) a retry diff may build; past
+/// it, word diffs fall back to lines and line diffs are skipped.
+const MAX_DIFF_CELLS: usize = 250_000;
+
+/// Word-level diff of two commands: each changed run as `-removed +added`,
+/// runs separated by commas. `None` if they have no words in common, where
+/// a diff would just restate both commands.
+///
+/// Commands too long to diff word by word (big heredocs) get a line count
+/// instead, e.g. `-2 +3 lines`.
+fn word_diff(old: &str, new: &str) -> Option<String> {
+    let old_words: Vec<&str> = old.split_whitespace().collect();
+    let new_words: Vec<&str> = new.split_whitespace().collect();
+    if old_words.len() * new_words.len() > MAX_DIFF_CELLS {
+        return line_diff(old, new);
+    }
+    let hunks = diff_hunks(&old_words, &new_words)?;
+    Some(if hunks.is_empty() {
+        "whitespace only".to_string()
+    } else {
+        hunks
+            .iter()
+            .map(|(removed, added)| format_hunk(removed, added))
+            .collect::<Vec<_>>()
+            .join(", ")
+    })
+}
+
+/// How many lines a retried command removed and added.
+fn line_diff(old: &str, new: &str) -> Option<String> {
+    let old: Vec<&str> = old.lines().map(str::trim_end).collect();
+    let new: Vec<&str> = new.lines().map(str::trim_end).collect();
+    if old.len() * new.len() > MAX_DIFF_CELLS {
+        return None;
+    }
+    let hunks = diff_hunks(&old, &new)?;
+    let removed: usize = hunks.iter().map(|(r, _)| r.len()).sum();
+    let added: usize = hunks.iter().map(|(_, a)| a.len()).sum();
+    Some(if hunks.is_empty() {
+        "whitespace only".to_string()
+    } else {
+        format!("-{removed} +{added} lines")
+    })
+}
+
+/// The changed runs between two token lists, as (removed, added) pairs,
+/// from their longest common subsequence. `None` if they share no token.
+fn diff_hunks<'a>(old: &[&'a str], new: &[&'a str]) -> Option<Vec<(Vec<&'a str>, Vec<&'a str>)>> {
+    // Longest common subsequence table, suffix-indexed.
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    if lcs[0][0] == 0 {
+        return None;
+    }
+    let mut hunks = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            push_hunk(&mut hunks, &mut removed, &mut added);
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            removed.push(old[i]);
+            i += 1;
+        } else {
+            added.push(new[j]);
+            j += 1;
+        }
+    }
+    push_hunk(&mut hunks, &mut removed, &mut added);
+    Some(hunks)
+}
+
+fn push_hunk<'a>(
+    hunks: &mut Vec<(Vec<&'a str>, Vec<&'a str>)>,
+    removed: &mut Vec<&'a str>,
+    added: &mut Vec<&'a str>,
+) {
+    if !removed.is_empty() || !added.is_empty() {
+        hunks.push((std::mem::take(removed), std::mem::take(added)));
+    }
+}
+
+fn format_hunk(removed: &[&str], added: &[&str]) -> String {
+    let mut parts = Vec::new();
+    if !removed.is_empty() {
+        parts.push(format!("-{}", removed.join(" ")));
+    }
+    if !added.is_empty() {
+        parts.push(format!("+{}", added.join(" ")));
+    }
+    parts.join(" ")
+}
+
 fn get_str<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
     value.get(key).and_then(Value::as_str)
 }
@@ -220,4 +379,70 @@ mod tests {
         let input = serde_json::json!({});
         assert_eq!(format_tool_detail("CustomTool", &input), "");
     }
+
+    #[test]
+    fn bash_retry_shows_word_diff() {
+        assert!(is_retry("Bash", true, "Bash"));
+        let prev = serde_json::json!({"command": "cargo test --lib foo"});
+        let input = serde_json::json!({"command": "cargo test --lib bar -- --nocapture"});
+        assert_eq!(
+            retry_note("Bash", &prev, &input).unwrap(),
+            "-foo +bar -- --nocapture"
+        );
+        let input = serde_json::json!({"command": "cargo nextest run --lib foo"});
+        assert_eq!(
+            retry_note("Bash", &prev, &input).unwrap(),
+            "-test +nextest run"
+        );
+        assert_eq!(retry_note("Bash", &prev, &prev).unwrap(), "same command");
+        let unrelated = serde_json::json!({"command": "git status"});
+        assert_eq!(retry_note("Bash", &prev, &unrelated), None);
+    }
+
+    #[test]
+    fn long_bash_retry_falls_back_to_line_diff() {
+        use std::fmt::Write;
+
+        let body: String = (0..400).fold(String::new(), |mut body, i| {
+            let _ = writeln!(body, "echo line {i}");
+            body
+        });
+        let prev = serde_json::json!({"command": format!("cat <<EOF\n{body}EOF")});
+        let input = serde_json::json!({
+            "command": format!("cat <<EOF\n{}EOF", body.replace("echo line 7\n", "echo seven\necho 7\n"))
+        });
+        assert_eq!(retry_note("Bash", &prev, &input).unwrap(), "-1 +2 lines");
+    }
+
+    #[test]
+    fn edit_retry_notes_old_string() {
+        assert!(is_retry("Edit", true, "Edit"));
+        let prev = serde_json::json!({
+            "file_path": "/src/lib.rs", "old_string": "fn a()", "new_string": "fn b()"
+        });
+        let input = serde_json::json!({
+            "file_path": "/src/lib.rs", "old_string": "fn a() {", "new_string": "fn b() {"
+        });
+        assert_eq!(
+            retry_note("Edit", &prev, &input).unwrap(),
+            "same file, different old_string"
+        );
+    }
+
+    #[test]
+    fn read_retry_lists_changed_fields() {
+        assert!(is_retry("Read", true, "Read"));
+        let prev = serde_json::json!({"file_path": "/src/mian.rs"});
+        let input = serde_json::json!({"file_path": "/src/main.rs", "limit": 50});
+        assert_eq!(
+            retry_note("Read", &prev, &input).unwrap(),
+            "changed file_path, limit"
+        );
+    }
+
+    #[test]
+    fn success_or_different_tool_is_not_a_retry() {
+        assert!(!is_retry("Bash", false, "Bash"));
+        assert!(!is_retry("Bash", true, "Read"));
+    }
 }
@@ -9,12 +9,25 @@ pub enum AppEvent {
     Claude(Box<InboundEvent>),
     /// A warning about an unparseable line from claude's stdout.
     ParseWarning(String),
-    /// Content from claude's stderr (displayed as a warning on exit).
+    /// Output from claude's stderr, forwarded a line at a time as it
+    /// arrives (recordings may hold several lines in one event).
     Stderr(String),
+    /// Progress writing a large message to claude's stdin.
+    SendProgress { sent: usize, total: usize },
+    /// Writing a message to claude's stdin failed; it was not delivered.
+    SendFailed(String),
     /// The claude process has exited.
     ProcessExit(Option<i32>),
 }
 
+impl AppEvent {
+    /// Whether claude wrote this to stdout, showing it has read its input
+    /// so far — unlike reports on coven's own stdin writes, stderr, or exit.
+    pub fn is_claude_output(&self) -> bool {
+        matches!(self, Self::Claude(_) | Self::ParseWarning(_))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InputMode {
     /// Write to stdin immediately (steering).
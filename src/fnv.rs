@@ -0,0 +1,24 @@
+//! 64-bit FNV-1a hashing.
+//!
+//! Used wherever a hash is persisted (VCR output checksums, the init
+//! manifest): `std`'s `DefaultHasher` isn't guaranteed stable across Rust
+//! releases, so stored hashes would go stale after a toolchain upgrade.
+
+/// Hash of the empty input; the starting state for [`extend`].
+pub const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+const PRIME: u64 = 0x0100_0000_01b3;
+
+/// Continue a running hash with more bytes, so input can be hashed in pieces.
+pub fn extend(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(PRIME);
+    }
+    hash
+}
+
+/// Hash `bytes` in one go.
+pub fn hash(bytes: &[u8]) -> u64 {
+    extend(OFFSET_BASIS, bytes)
+}
@@ -1,71 +1,492 @@
+use std::borrow::Cow;
 use std::io::Write;
 use std::path::PathBuf;
 
-use anyhow::Result;
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
-use crate::display::renderer::Renderer;
+use crate::display::input::{InputAction, InputHandler};
+use crate::display::renderer::{Renderer, format_tenths};
 use crate::event::AppEvent;
 use crate::protocol::types::{AssistantContentBlock, InboundEvent};
+use crate::session::event_loop;
 use crate::session::runner::{SessionConfig, SessionRunner};
-use crate::vcr::VcrContext;
+use crate::vcr::{Io, VcrContext};
 
 /// Configuration for fork behavior, threaded through the session loop.
 #[derive(Clone)]
 pub struct ForkConfig {
     pub extra_args: Vec<String>,
     pub working_dir: Option<PathBuf>,
+    /// Tool restrictions for children (`--fork-tools`); `None` means children
+    /// inherit the parent's tools unchanged.
+    pub tools: Option<ForkTools>,
+    /// Longest child result passed back to the parent, in characters; longer
+    /// ones are cut with a note saying so.
+    pub max_child_result_chars: usize,
+    /// Most children running at once (`--fork-concurrency`); further
+    /// children wait for a running one to finish.
+    pub concurrency: usize,
+    /// Leave children's permission mode to claude, like the parent
+    /// (`--no-default-permission-mode`).
+    pub no_default_permission_mode: bool,
 }
 
+/// Default for [`ForkConfig::concurrency`].
+pub const DEFAULT_FORK_CONCURRENCY: usize = 3;
+
+/// Default for [`ForkConfig::max_child_result_chars`]: far more than a
+/// summary needs, but short of flooding the parent's context.
+pub const DEFAULT_MAX_CHILD_RESULT_CHARS: usize = 20_000;
+
 impl ForkConfig {
     /// Build a `ForkConfig` if forking is enabled, otherwise `None`.
     pub fn if_enabled(
         enabled: bool,
         extra_args: &[String],
         working_dir: &Option<PathBuf>,
+        tools: Option<&ForkTools>,
+        concurrency: usize,
+        no_default_permission_mode: bool,
     ) -> Option<Self> {
         enabled.then(|| Self {
             extra_args: extra_args.to_vec(),
             working_dir: working_dir.clone(),
+            tools: tools.cloned(),
+            max_child_result_chars: DEFAULT_MAX_CHILD_RESULT_CHARS,
+            concurrency,
+            no_default_permission_mode,
         })
     }
 
-    /// CLI args to disallow the native subagent tools when fork is enabled.
+    /// CLI args disallowing tools for a session when fork is enabled.
     /// Fork is an alternative subagent model — Task/Agent is not needed.
-    pub fn disallowed_tool_args() -> [String; 2] {
-        ["--disallowed-tools".to_string(), "Task,Agent".to_string()]
+    /// `inherited` denials and the `--fork-tools` denials are merged in after
+    /// them, without duplicates.
+    pub fn disallowed_tool_args(inherited: &[String], tools: Option<&ForkTools>) -> [String; 2] {
+        let mut names: Vec<&str> = vec!["Task", "Agent"];
+        let extra = tools.map_or(&[][..], |t| &t.disallowed[..]);
+        for name in inherited.iter().chain(extra) {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+        ["--disallowed-tools".to_string(), names.join(",")]
+    }
+
+    /// Extra args for a child session.
+    ///
+    /// Without `--fork-tools`, children get the parent's args verbatim. With
+    /// it, the parent's tool flags are replaced: the spec's allowed tools
+    /// replace the parent's (when the spec allows any), and the spec's
+    /// denials are added to the parent's.
+    pub fn child_args(&self) -> Vec<String> {
+        let Some(tools) = &self.tools else {
+            return self.extra_args.clone();
+        };
+        let (mut args, parent) = split_tool_args(&self.extra_args);
+        let allowed = if tools.allowed.is_empty() {
+            parent.allowed
+        } else {
+            tools.allowed.clone()
+        };
+        if !allowed.is_empty() {
+            args.extend(["--allowedTools".to_string(), allowed.join(",")]);
+        }
+        args.extend(Self::disallowed_tool_args(&parent.disallowed, Some(tools)));
+        args
     }
 }
 
-/// Run the fork flow: spawn children in parallel, render output, collect results.
+/// Tool restrictions for fork children, parsed from `--fork-tools`.
 ///
-/// Each child resumes from the parent session with `--fork-session` and receives
-/// a simple follow-up prompt identifying its assigned task. Child events are
-/// multiplexed onto a shared channel and rendered with fork-specific styling.
+/// The spec is a comma-separated list of tool names in claude's
+/// `--allowedTools` syntax; a `!` prefix disallows the tool instead:
+/// `Read,Grep,Glob,Bash(git log:*),!WebFetch`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForkTools {
+    pub allowed: Vec<String>,
+    pub disallowed: Vec<String>,
+}
+
+impl FromStr for ForkTools {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let mut tools = Self::default();
+        for entry in split_tool_list(spec) {
+            let (list, name) = match entry.strip_prefix('!') {
+                Some(name) => (&mut tools.disallowed, name.trim()),
+                None => (&mut tools.allowed, entry),
+            };
+            if name.is_empty() {
+                bail!("empty tool name in --fork-tools spec: {spec:?}");
+            }
+            list.push(name.to_string());
+        }
+        if tools.allowed.is_empty() && tools.disallowed.is_empty() {
+            bail!("--fork-tools spec names no tools");
+        }
+        Ok(tools)
+    }
+}
+
+impl ForkTools {
+    /// Short description for the parent's system prompt.
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.allowed.is_empty() {
+            parts.push(format!("pre-approved: {}", self.allowed.join(", ")));
+        }
+        if !self.disallowed.is_empty() {
+            parts.push(format!("denied: {}", self.disallowed.join(", ")));
+        }
+        parts.join("; ")
+    }
+}
+
+/// Tool lists gathered from claude's tool flags.
+#[derive(Default)]
+struct ToolArgs {
+    allowed: Vec<String>,
+    disallowed: Vec<String>,
+}
+
+/// Split `args` into everything except the allowed/disallowed tool flags,
+/// and the tools those flags named.
 ///
-/// Returns the XML reintegration message to send back to the parent session.
+/// Handles both spellings (`--allowedTools`, `--allowed-tools`), the
+/// `--flag=value` form, and the variadic form where several values follow
+/// the flag.
+fn split_tool_args(args: &[String]) -> (Vec<String>, ToolArgs) {
+    let mut rest = Vec::new();
+    let mut tools = ToolArgs::default();
+    let mut iter = args.iter().peekable();
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        let list = match flag {
+            "--allowedTools" | "--allowed-tools" => &mut tools.allowed,
+            "--disallowedTools" | "--disallowed-tools" => &mut tools.disallowed,
+            _ => {
+                rest.push(arg.clone());
+                continue;
+            }
+        };
+        let mut values: Vec<&str> = inline.into_iter().collect();
+        if inline.is_none() {
+            while let Some(value) = iter.next_if(|a| !a.starts_with('-')) {
+                values.push(value);
+            }
+        }
+        for name in values.into_iter().flat_map(split_tool_list) {
+            if !list.iter().any(|t| t == name) {
+                list.push(name.to_string());
+            }
+        }
+    }
+    (rest, tools)
+}
+
+/// Split a tool list on commas outside parentheses, so rules like
+/// `Bash(git diff:*)` stay whole.
+fn split_tool_list(list: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                names.push(list[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    names.push(list[start..].trim());
+    names.retain(|n| !n.is_empty());
+    names
+}
+
+/// What fork children start from.
+#[derive(Debug, Clone, Copy)]
+pub enum ForkOrigin<'a> {
+    /// Resume the parent session with `--fork-session`, inheriting its context.
+    Session(&'a str),
+    /// The parent hasn't had a turn yet (`--fork-tasks`): children start fresh
+    /// sessions from the user's prompt.
+    Prompt(&'a str),
+    /// There is no parent (`coven fork`): each child starts a fresh session
+    /// with its task as the whole prompt, and Ctrl+C stops them all.
+    Standalone,
+}
+
+/// How a fork child ended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChildOutcome {
+    /// The child's result text, or why it has none.
+    pub result: std::result::Result<String, String>,
+    pub cost: f64,
+    /// Claude's `duration_ms` for the child's turn; 0 without a result.
+    pub duration_ms: u64,
+}
+
+impl ChildOutcome {
+    /// A child that ended without a result.
+    pub fn failed(error: &str) -> Self {
+        Self {
+            result: Err(error.to_string()),
+            cost: 0.0,
+            duration_ms: 0,
+        }
+    }
+}
+
+/// Each task with how its child ended.
+pub type ChildResults = Vec<(String, ChildOutcome)>;
+
+/// Run the fork flow: run the children (see [`run_children`]) and combine
+/// their results into the XML reintegration message to send back to the
+/// parent session.
 pub async fn run_fork<W: Write>(
-    parent_session_id: &str,
+    origin: ForkOrigin<'_>,
     tasks: Vec<String>,
     config: &ForkConfig,
     renderer: &mut Renderer<W>,
+    input: &mut InputHandler,
+    io: &mut Io,
     vcr: &VcrContext,
 ) -> Result<String> {
+    let results = run_children(origin, tasks, config, renderer, input, io, vcr).await?;
+    let reintegration = compose_reintegration_message(&results, config.max_child_result_chars);
+    renderer.render_fork_complete(&reintegration);
+    Ok(reintegration)
+}
+
+/// Spawn a child session per task in parallel, render their output, and
+/// collect their results in task order.
+///
+/// At most `config.concurrency` children run at once; the rest are queued
+/// and start, in task order, as running children finish. Each child starts from `origin` and receives a simple follow-up prompt
+/// identifying its assigned task. Child events are
+/// multiplexed onto a shared channel and rendered with fork-specific styling.
+///
+/// While children run, terminal input is routed to one of them: a number key
+/// selects a child, typed text plus Enter steers it, and Escape deselects.
+/// With [`ForkOrigin::Standalone`], Ctrl+C kills the running children and
+/// skips the queued ones; their results are errors.
+pub async fn run_children<W: Write>(
+    origin: ForkOrigin<'_>,
+    tasks: Vec<String>,
+    config: &ForkConfig,
+    renderer: &mut Renderer<W>,
+    input: &mut InputHandler,
+    io: &mut Io,
+    vcr: &VcrContext,
+) -> Result<ChildResults> {
     renderer.render_fork_start(&tasks);
+    if !io.is_headless() {
+        renderer.render_fork_hints(tasks.len());
+    }
 
     let num_tasks = tasks.len();
+    let limit = config.concurrency.max(1).min(num_tasks);
+    if limit < num_tasks {
+        renderer.render_fork_queued(limit, num_tasks);
+    }
     let (merged_tx, mut merged_rx) = mpsc::unbounded_channel::<(usize, AppEvent)>();
-    let mut runners: Vec<SessionRunner> = Vec::new();
+    let mut spawner = ChildSpawner {
+        origin,
+        tasks: &tasks,
+        config,
+        merged_tx: Some(merged_tx),
+        next: 0,
+    };
+    // Children start in task order, so `runners[idx]` is always child `idx`.
+    let mut runners = Vec::with_capacity(num_tasks);
+    while runners.len() < limit {
+        runners.push(spawner.spawn_next(vcr).await?);
+    }
 
-    for (i, task) in tasks.iter().enumerate() {
+    // Process events from all children. Each event is individually recorded
+    // so fork child tool calls and completions appear in VCR test snapshots.
+    let mut results: Vec<Option<ChildOutcome>> = vec![None; num_tasks];
+    let mut completed = 0;
+    let mut steering = ChildSteering {
+        tasks: &tasks,
+        selected: None,
+        interruptible: matches!(origin, ForkOrigin::Standalone),
+    };
+    let mut interrupted = false;
+
+    loop {
+        let event: ForkEvent = vcr
+            .call("fork_event", (), async |(): &()| {
+                Ok(tokio::select! {
+                    event = merged_rx.recv() => ForkEvent::Child(event),
+                    Some(event) = io.next_term_event() => ForkEvent::Terminal(event),
+                })
+            })
+            .await?;
+        let (idx, event) = match event {
+            ForkEvent::Child(Some(child_event)) => child_event,
+            ForkEvent::Child(None) => break,
+            ForkEvent::Terminal(Event::Key(key)) if key.kind != KeyEventKind::Release => {
+                interrupted = steering
+                    .handle_key(&key, &results, &mut runners, renderer, input, vcr)
+                    .await?;
+                if interrupted {
+                    break;
+                }
+                continue;
+            }
+            ForkEvent::Terminal(_) => continue,
+        };
+
+        let finished = handle_child_event(idx, event, &mut results, renderer);
+        if !finished {
+            continue;
+        }
+
+        completed += 1;
+        if completed == num_tasks {
+            break;
+        }
+        // Start the next queued child in the finished one's place.
+        if spawner.has_queued() {
+            let next = spawner.next;
+            runners.push(spawner.spawn_next(vcr).await?);
+            renderer.render_fork_child_started(next, runners.len() - completed, num_tasks);
+        }
+    }
+
+    input.discard(renderer.writer());
+    for runner in &mut runners {
+        if interrupted {
+            runner.kill().await?;
+        } else {
+            runner.close_input();
+        }
+        let _ = runner.wait().await;
+    }
+    if interrupted {
+        renderer.render_fork_interrupted(num_tasks - completed);
+    }
+
+    let missing = if interrupted {
+        "Interrupted"
+    } else {
+        "No result received"
+    };
+    Ok(tasks
+        .into_iter()
+        .zip(results)
+        .map(|(label, result)| {
+            let outcome = result.unwrap_or_else(|| ChildOutcome::failed(missing));
+            (label, outcome)
+        })
+        .collect())
+}
+
+/// Render one event from child `idx`, recording its result if it finished.
+/// Returns whether the child finished.
+fn handle_child_event<W: Write>(
+    idx: usize,
+    event: AppEvent,
+    results: &mut [Option<ChildOutcome>],
+    renderer: &mut Renderer<W>,
+) -> bool {
+    match event {
+        AppEvent::Claude(inbound) => match &*inbound {
+            InboundEvent::Assistant(msg) if msg.parent_tool_use_id.is_none() => {
+                for block in &msg.message.content {
+                    if let AssistantContentBlock::ToolUse { name, input, .. } = block {
+                        renderer.render_fork_child_tool_call(idx, name, input);
+                    }
+                }
+                false
+            }
+            InboundEvent::Result(result) => {
+                renderer.render_fork_child_done(idx, result);
+                results[idx] = Some(ChildOutcome {
+                    result: Ok(result.result.clone()),
+                    cost: result.total_cost_usd,
+                    duration_ms: result.duration_ms,
+                });
+                true
+            }
+            _ => false,
+        },
+        AppEvent::ParseWarning(w) => {
+            renderer.render_warning(&w);
+            false
+        }
+        AppEvent::Stderr(text) => {
+            renderer.render_stderr(&text);
+            false
+        }
+        AppEvent::SendFailed(error) => {
+            renderer.render_send_failed(&error);
+            false
+        }
+        AppEvent::ProcessExit(_) if results[idx].is_none() => {
+            let error = "Child process exited unexpectedly";
+            renderer.render_fork_child_failed(idx, error);
+            results[idx] = Some(ChildOutcome::failed(error));
+            true
+        }
+        AppEvent::SendProgress { .. } | AppEvent::ProcessExit(_) => false,
+    }
+}
+
+/// Starts fork children in task order, at most `config.concurrency` at a
+/// time, forwarding each child's events onto `merged_tx` tagged with its task
+/// index.
+struct ChildSpawner<'a> {
+    origin: ForkOrigin<'a>,
+    tasks: &'a [String],
+    config: &'a ForkConfig,
+    /// Dropped once the last child has started, so the merged channel closes
+    /// when every child's events are drained.
+    merged_tx: Option<mpsc::UnboundedSender<(usize, AppEvent)>>,
+    /// Index of the next child to start.
+    next: usize,
+}
+
+impl ChildSpawner<'_> {
+    fn has_queued(&self) -> bool {
+        self.next < self.tasks.len()
+    }
+
+    /// Start the next queued child.
+    async fn spawn_next(&mut self, vcr: &VcrContext) -> Result<SessionRunner> {
+        let i = self.next;
+        self.next += 1;
         let (child_tx, mut child_rx) = mpsc::unbounded_channel();
-        let mut extra_args = config.extra_args.clone();
-        extra_args.push("--fork-session".to_string());
+        let mut extra_args = self.config.child_args();
+        let assignment = format!("You were assigned '{}'", self.tasks[i]);
+        let (prompt, resume) = match self.origin {
+            ForkOrigin::Session(parent_session_id) => {
+                extra_args.push("--fork-session".to_string());
+                (assignment, Some(parent_session_id.to_string()))
+            }
+            ForkOrigin::Prompt(prompt) => (format!("{prompt}\n\n{assignment}"), None),
+            ForkOrigin::Standalone => (self.tasks[i].clone(), None),
+        };
         let child_config = SessionConfig {
-            prompt: Some(format!("You were assigned '{task}'")),
-            resume: Some(parent_session_id.to_string()),
+            prompt: Some(prompt),
+            resume,
             extra_args,
-            working_dir: config.working_dir.clone(),
+            no_default_permission_mode: self.config.no_default_permission_mode,
+            working_dir: self.config.working_dir.clone(),
             ..Default::default()
         };
 
@@ -73,85 +494,147 @@ pub async fn run_fork<W: Write>(
         // child_rx.recv() returns None, and the multiplexer task exits cleanly.
         let runner = vcr
             .call("fork_spawn", child_config, async |c: &SessionConfig| {
-                SessionRunner::spawn(c.clone(), child_tx).await
+                SessionRunner::spawn(c.clone(), child_tx)
             })
             .await?;
-        runners.push(runner);
 
-        let merged_tx = merged_tx.clone();
-        tokio::spawn(async move {
-            while let Some(event) = child_rx.recv().await {
-                if merged_tx.send((i, event)).is_err() {
-                    break;
+        let merged_tx = if self.has_queued() {
+            self.merged_tx.clone()
+        } else {
+            self.merged_tx.take()
+        };
+        if let Some(merged_tx) = merged_tx {
+            tokio::spawn(async move {
+                while let Some(event) = child_rx.recv().await {
+                    if merged_tx.send((i, event)).is_err() {
+                        break;
+                    }
                 }
-            }
-        });
+            });
+        }
+        Ok(runner)
     }
-    drop(merged_tx);
+}
 
-    // Process events from all children. Each event is individually recorded
-    // so fork child tool calls and completions appear in VCR test snapshots.
-    let mut results: Vec<Option<std::result::Result<String, String>>> = vec![None; num_tasks];
-    let mut completed = 0;
+/// What woke the fork loop. Untagged so child events record exactly as they
+/// did before terminal input was routed to children.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ForkEvent {
+    /// An event from child `idx`, or `None` once every child's channel closed.
+    Child(Option<(usize, AppEvent)>),
+    Terminal(Event),
+}
 
-    loop {
-        let event: Option<(usize, AppEvent)> = vcr
-            .call("fork_event", (), async |(): &()| Ok(merged_rx.recv().await))
-            .await?;
-        let Some((idx, event)) = event else { break };
-
-        match event {
-            AppEvent::Claude(inbound) => match &*inbound {
-                InboundEvent::Assistant(msg) if msg.parent_tool_use_id.is_none() => {
-                    for block in &msg.message.content {
-                        if let AssistantContentBlock::ToolUse { name, input, .. } = block {
-                            renderer.render_fork_child_tool_call(idx, name, input);
-                        }
-                    }
-                }
-                InboundEvent::Result(result) => {
-                    renderer.render_fork_child_done(idx, &result.result);
-                    results[idx] = Some(Ok(result.result.clone()));
-                    completed += 1;
-                    if completed == num_tasks {
-                        break;
-                    }
+/// Routes terminal input to the fork child the user selected.
+struct ChildSteering<'a> {
+    tasks: &'a [String],
+    /// Index of the child that typed text goes to.
+    selected: Option<usize>,
+    /// Whether Ctrl+C stops the children (`coven fork`) rather than
+    /// leaving them to finish for their parent.
+    interruptible: bool,
+}
+
+impl ChildSteering<'_> {
+    /// Act on a key press. Returns whether the user asked to stop the children.
+    async fn handle_key<W: Write>(
+        &mut self,
+        key: &KeyEvent,
+        results: &[Option<ChildOutcome>],
+        runners: &mut [SessionRunner],
+        renderer: &mut Renderer<W>,
+        input: &mut InputHandler,
+        vcr: &VcrContext,
+    ) -> Result<bool> {
+        if !input.is_active() {
+            let plain = !key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+            if let KeyCode::Char(c) = key.code
+                && plain
+                && let Some(idx) = self.child_for_key(c)
+            {
+                self.selected = Some(idx);
+                renderer.render_fork_steering_target(Some((idx, &self.tasks[idx])));
+                return Ok(false);
+            }
+            if key.code == KeyCode::Esc {
+                self.deselect(renderer);
+                return Ok(false);
+            }
+        }
+
+        let action = input.handle_key(key, renderer.writer());
+        match event_loop::record_note(action, renderer, vcr).await? {
+            InputAction::Activated(_) => {
+                if let Some(idx) = self.selected {
+                    renderer.begin_fork_input_line(idx);
+                    input.set_has_hint_line();
+                    input.redraw(renderer.writer());
+                } else {
+                    input.deactivate();
+                    renderer.render_warning(&format!(
+                        "select a child to steer first ({})",
+                        child_keys(self.tasks.len())
+                    ));
                 }
-                _ => {}
-            },
-            AppEvent::ParseWarning(w) | AppEvent::Stderr(w) => {
-                renderer.render_warning(&w);
             }
-            AppEvent::ProcessExit(_) => {
-                if results[idx].is_none() {
-                    results[idx] = Some(Err("Child process exited unexpectedly".to_string()));
-                    completed += 1;
-                    if completed == num_tasks {
-                        break;
-                    }
+            InputAction::Submit(text, _) => {
+                let Some(idx) = self.selected else {
+                    return Ok(false);
+                };
+                if results[idx].is_some() {
+                    renderer.render_warning(&format!("child {} already finished", idx + 1));
+                    return Ok(false);
                 }
+                let Some(runner) = runners.get_mut(idx) else {
+                    renderer.render_warning(&format!("child {} hasn't started yet", idx + 1));
+                    return Ok(false);
+                };
+                renderer.render_fork_steering_sent(idx, &text);
+                vcr.call(
+                    "fork_send_message",
+                    (idx, text),
+                    async |(_, t): &(usize, String)| runner.send_message(t),
+                )
+                .await?;
+            }
+            InputAction::ViewMessage(_) => {
+                renderer.render_warning("messages can be viewed once the fork finishes");
             }
+            InputAction::Dismiss => self.deselect(renderer),
+            InputAction::Interrupt if self.interruptible => return Ok(true),
+            InputAction::Interrupt => {
+                renderer.render_warning(
+                    "Ctrl+C can't stop fork children — they'll report back when done",
+                );
+            }
+            _ => {}
         }
+        Ok(false)
     }
 
-    for runner in &mut runners {
-        runner.close_input();
-        let _ = runner.wait().await;
+    /// The child a number key selects: `1` is the first child.
+    fn child_for_key(&self, c: char) -> Option<usize> {
+        let n = usize::try_from(c.to_digit(10)?).ok()?;
+        (1..=self.tasks.len()).contains(&n).then(|| n - 1)
     }
 
-    let result_tuples: Vec<(String, std::result::Result<String, String>)> = tasks
-        .into_iter()
-        .zip(results)
-        .map(|(label, result)| {
-            let outcome = result.unwrap_or_else(|| Err("No result received".to_string()));
-            (label, outcome)
-        })
-        .collect();
-
-    let reintegration = compose_reintegration_message(&result_tuples);
-    renderer.render_fork_complete(&reintegration);
+    fn deselect<W: Write>(&mut self, renderer: &mut Renderer<W>) {
+        if self.selected.take().is_some() {
+            renderer.render_fork_steering_target(None);
+        }
+    }
+}
 
-    Ok(reintegration)
+/// The number keys that select children, e.g. `1–3`.
+pub fn child_keys(children: usize) -> String {
+    if children == 1 {
+        "1".to_string()
+    } else {
+        format!("1–{children}")
+    }
 }
 
 /// Parse a `<fork>` tag from response text and return the task labels.
@@ -177,42 +660,65 @@ pub fn parse_fork_tag(text: &str) -> Option<Vec<String>> {
     if tasks.is_empty() { None } else { Some(tasks) }
 }
 
-/// Compose the XML reintegration message sent back to the parent session.
+/// Compose the reintegration message sent back to the parent session.
 ///
-/// Each task's result (or error) is wrapped in a `<task>` element inside
-/// `<fork-results>`, so the parent model can see what each child produced.
-pub fn compose_reintegration_message(results: &[(String, Result<String, String>)]) -> String {
+/// Inside `<fork-results>`, each child's result (or error) comes under a
+/// `### Task N: <task>` heading with its cost and duration, so the parent
+/// model can tell which task produced what. Results longer than `max_chars`
+/// are cut short.
+pub fn compose_reintegration_message(
+    results: &[(String, ChildOutcome)],
+    max_chars: usize,
+) -> String {
     use std::fmt::Write;
 
-    let mut xml = String::new();
-    let _ = writeln!(xml, "<fork-results>");
-    for (label, outcome) in results {
-        // Escape label for XML attribute. Body text is intentionally not escaped —
-        // these results are consumed by language models, not XML parsers.
-        let safe_label = label
-            .replace('&', "&amp;")
-            .replace('<', "&lt;")
-            .replace('>', "&gt;")
-            .replace('"', "&quot;");
-        match outcome {
-            Ok(text) => {
-                let _ = write!(xml, "<task label=\"{safe_label}\">\n{text}\n</task>\n");
-            }
-            Err(err) => {
-                let _ = write!(
-                    xml,
-                    "<task label=\"{safe_label}\" error=\"true\">\n{err}\n</task>\n"
+    // Body text is intentionally not escaped — these results are consumed
+    // by language models, not parsers.
+    let mut text = String::from("<fork-results>\n");
+    for (i, (task, outcome)) in results.iter().enumerate() {
+        if i > 0 {
+            text.push('\n');
+        }
+        let _ = writeln!(text, "### Task {}: {task}", i + 1);
+        match &outcome.result {
+            Ok(result) => {
+                let _ = writeln!(
+                    text,
+                    "Cost ${:.2} \u{b7} {}\n\n{}",
+                    outcome.cost,
+                    format_tenths(outcome.duration_ms),
+                    truncate_result(result, max_chars)
                 );
             }
+            Err(error) => {
+                let _ = writeln!(text, "Failed: {error}");
+            }
+        }
+    }
+    text.push_str("</fork-results>");
+    text
+}
+
+/// `text`, or its first `max_chars` characters and a note of how much was cut.
+fn truncate_result(text: &str, max_chars: usize) -> Cow<'_, str> {
+    match text.char_indices().nth(max_chars) {
+        None => Cow::Borrowed(text),
+        Some((end, _)) => {
+            let total = text.chars().count();
+            Cow::Owned(format!(
+                "{}\n[result truncated: showing the first {max_chars} of {total} characters]",
+                &text[..end]
+            ))
         }
     }
-    let _ = write!(xml, "</fork-results>");
-    xml
 }
 
 /// Build the system prompt fragment that teaches the model about forking.
-pub fn fork_system_prompt() -> &'static str {
-    "To delegate self-contained subtasks, emit a <fork> tag with a YAML list of short task labels:\n\
+///
+/// When children run with `--fork-tools`, the fragment says so, so the
+/// parent only delegates work the children can do.
+pub fn fork_system_prompt(tools: Option<&ForkTools>) -> String {
+    let mut prompt = "To delegate self-contained subtasks, emit a <fork> tag with a YAML list of short task labels:\n\
      <fork>\n\
      - Write tests for auth module\n\
      - Update API documentation\n\
@@ -222,9 +728,22 @@ pub fn fork_system_prompt() -> &'static str {
      you're parallelizing independent work or delegating a task that benefits from full context \
      inheritance. You'll receive the combined results in a <fork-results> message when all \
      tasks complete."
+        .to_string();
+    if let Some(tools) = tools {
+        use std::fmt::Write;
+        let _ = write!(
+            prompt,
+            "\n\nForked tasks run with restricted tools ({}). They can't ask for \
+             permission, so other tools that need it may be refused. Only delegate work \
+             those tools can do, and keep the rest for yourself.",
+            tools.describe()
+        );
+    }
+    prompt
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
 
@@ -272,66 +791,499 @@ mod tests {
         );
     }
 
+    fn done(text: &str) -> ChildOutcome {
+        ChildOutcome {
+            result: Ok(text.to_string()),
+            cost: 0.0123,
+            duration_ms: 3_140,
+        }
+    }
+
     #[test]
-    fn compose_reintegration_message_success() {
+    fn compose_reintegration_message_labels_each_task() {
         let results = vec![
-            ("Task A".to_string(), Ok("Result A".to_string())),
-            ("Task B".to_string(), Ok("Result B".to_string())),
+            ("Task A".to_string(), done("Result A")),
+            (
+                "Task B".to_string(),
+                ChildOutcome::failed("process crashed"),
+            ),
+            (
+                "Fix <thing>".to_string(),
+                done("Changed Vec<String> to Vec<&str>"),
+            ),
         ];
-        let msg = compose_reintegration_message(&results);
-        assert!(msg.starts_with("<fork-results>"));
-        assert!(msg.ends_with("</fork-results>"));
-        assert!(msg.contains("<task label=\"Task A\">"));
-        assert!(msg.contains("\nResult A\n"));
-        assert!(msg.contains("<task label=\"Task B\">"));
-        assert!(msg.contains("\nResult B\n"));
+        let msg = compose_reintegration_message(&results, DEFAULT_MAX_CHILD_RESULT_CHARS);
+        assert_eq!(
+            msg,
+            "<fork-results>\n\
+             ### Task 1: Task A\n\
+             Cost $0.01 \u{b7} 3.1s\n\
+             \n\
+             Result A\n\
+             \n\
+             ### Task 2: Task B\n\
+             Failed: process crashed\n\
+             \n\
+             ### Task 3: Fix <thing>\n\
+             Cost $0.01 \u{b7} 3.1s\n\
+             \n\
+             Changed Vec<String> to Vec<&str>\n\
+             </fork-results>"
+        );
     }
 
     #[test]
-    fn compose_reintegration_message_with_error() {
+    fn compose_reintegration_message_truncates_long_results() {
         let results = vec![
-            ("Good".to_string(), Ok("worked".to_string())),
-            ("Bad".to_string(), Err("process crashed".to_string())),
+            ("Long".to_string(), done(&"é".repeat(12))),
+            ("Short".to_string(), done(&"é".repeat(10))),
         ];
-        let msg = compose_reintegration_message(&results);
-        assert!(msg.contains("<task label=\"Good\">"));
-        assert!(msg.contains("<task label=\"Bad\" error=\"true\">"));
-        assert!(msg.contains("\nprocess crashed\n"));
+        let msg = compose_reintegration_message(&results, 10);
+        assert!(msg.contains(&format!(
+            "### Task 1: Long\nCost $0.01 \u{b7} 3.1s\n\n{}\n[result truncated: showing the first 10 of 12 characters]\n\n",
+            "é".repeat(10)
+        )));
+        assert!(msg.contains(&format!(
+            "### Task 2: Short\nCost $0.01 \u{b7} 3.1s\n\n{}\n</fork-results>",
+            "é".repeat(10)
+        )));
+    }
+
+    #[test]
+    fn fork_system_prompt_contains_tag() {
+        let prompt = fork_system_prompt(None);
+        assert!(prompt.contains("<fork>"));
+        assert!(prompt.contains("</fork>"));
+        assert!(prompt.contains("<fork-results>"));
+        assert!(!prompt.contains("restricted tools"));
+    }
+
+    #[test]
+    fn fork_event_reads_child_events_recorded_before_steering() {
+        assert!(matches!(
+            serde_json::from_str::<ForkEvent>("null").unwrap(),
+            ForkEvent::Child(None)
+        ));
+        assert!(matches!(
+            serde_json::from_str::<ForkEvent>(r#"[1, {"ProcessExit": null}]"#).unwrap(),
+            ForkEvent::Child(Some((1, AppEvent::ProcessExit(None))))
+        ));
+        let key = Event::Key(KeyEvent::new(KeyCode::Char('2'), KeyModifiers::NONE));
+        let json = serde_json::to_string(&ForkEvent::Terminal(key.clone())).unwrap();
+        assert!(matches!(
+            serde_json::from_str::<ForkEvent>(&json).unwrap(),
+            ForkEvent::Terminal(e) if e == key
+        ));
+    }
+
+    struct SteeringHarness {
+        tasks: Vec<String>,
+        results: Vec<Option<ChildOutcome>>,
+        runners: Vec<SessionRunner>,
+        renderer: Renderer<Vec<u8>>,
+        input: InputHandler,
+    }
+
+    impl SteeringHarness {
+        fn new(results: Vec<Option<ChildOutcome>>) -> Self {
+            Self {
+                tasks: (1..=results.len()).map(|n| format!("Task {n}")).collect(),
+                runners: results.iter().map(|_| SessionRunner::stub()).collect(),
+                results,
+                renderer: Renderer::with_writer(Vec::new()),
+                input: InputHandler::new(2),
+            }
+        }
+
+        /// Press each key in turn with a fresh routing state, returning the
+        /// rendered output.
+        async fn press(&mut self, keys: &[KeyCode], vcr: &VcrContext) -> String {
+            let mut steering = ChildSteering {
+                tasks: &self.tasks,
+                selected: None,
+                interruptible: false,
+            };
+            for &code in keys {
+                let key = KeyEvent::new(code, KeyModifiers::NONE);
+                steering
+                    .handle_key(
+                        &key,
+                        &self.results,
+                        &mut self.runners,
+                        &mut self.renderer,
+                        &mut self.input,
+                        vcr,
+                    )
+                    .await
+                    .unwrap();
+            }
+            String::from_utf8(self.renderer.writer().clone()).unwrap()
+        }
+    }
+
+    fn chars(text: &str) -> Vec<KeyCode> {
+        text.chars().map(KeyCode::Char).collect()
+    }
+
+    /// Replay context expecting exactly these child messages.
+    fn expect_sends(sends: &[(usize, &str)]) -> VcrContext {
+        let ndjson: Vec<String> = sends
+            .iter()
+            .map(|(idx, text)| {
+                serde_json::json!({
+                    "label": "fork_send_message",
+                    "args": [idx, text],
+                    "result": {"Ok": null},
+                })
+                .to_string()
+            })
+            .collect();
+        VcrContext::replay(&ndjson.join("\n")).unwrap()
+    }
+
+    async fn assert_all_sent(vcr: &VcrContext) {
+        let extra = vcr.call("probe", (), async |(): &()| Ok(())).await;
+        assert!(extra.unwrap_err().to_string().contains("exhausted"));
+    }
+
+    #[tokio::test]
+    async fn steering_routes_text_to_selected_child() {
+        let mut h = SteeringHarness::new(vec![None, None]);
+        let vcr = expect_sends(&[(1, "hi")]);
+        let mut keys = chars("2hi");
+        keys.push(KeyCode::Enter);
+        let out = h.press(&keys, &vcr).await;
+        assert!(out.contains("steering → child 2: Task 2"), "{out}");
+        assert!(out.contains("⤷ steering child 2: hi"), "{out}");
+        assert_all_sent(&vcr).await;
+    }
+
+    #[tokio::test]
+    async fn steering_needs_a_selected_running_child() {
+        let mut h = SteeringHarness::new(vec![Some(ChildOutcome::failed("done")), None, None]);
+        let vcr = expect_sends(&[]);
+
+        // Text without a selection isn't sent anywhere.
+        let mut keys = chars("x");
+        keys.push(KeyCode::Enter);
+        let out = h.press(&keys, &vcr).await;
+        assert!(out.contains("select a child to steer first (1–3)"), "{out}");
+
+        // A finished child can't be steered.
+        let mut keys = chars("1y");
+        keys.push(KeyCode::Enter);
+        let out = h.press(&keys, &vcr).await;
+        assert!(out.contains("child 1 already finished"), "{out}");
+
+        // Out-of-range digits start typing instead of selecting.
+        let out = h.press(&chars("9"), &vcr).await;
+        assert!(!out.contains("child 9"), "{out}");
+        assert_all_sent(&vcr).await;
+    }
+
+    #[tokio::test]
+    async fn escape_deselects_child() {
+        let mut h = SteeringHarness::new(vec![None, None]);
+        let vcr = expect_sends(&[]);
+        let mut keys = chars("1");
+        keys.push(KeyCode::Esc);
+        let out = h.press(&keys, &vcr).await;
+        assert!(out.contains("steering off"), "{out}");
     }
 
     #[test]
-    fn compose_reintegration_message_handles_angle_brackets() {
-        let results = vec![(
-            "Fix code".to_string(),
-            Ok("Changed Vec<String> to Vec<&str>".to_string()),
-        )];
-        let msg = compose_reintegration_message(&results);
-        assert!(msg.contains("\nChanged Vec<String> to Vec<&str>\n"));
+    fn fork_system_prompt_mentions_restricted_tools() {
+        let tools: ForkTools = "Read,Grep,!Bash".parse().unwrap();
+        let prompt = fork_system_prompt(Some(&tools));
+        assert!(prompt.contains("restricted tools (pre-approved: Read, Grep; denied: Bash)."));
+    }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| (*s).to_string()).collect()
+    }
+
+    fn config(extra_args: &[&str], spec: Option<&str>) -> ForkConfig {
+        ForkConfig {
+            extra_args: args(extra_args),
+            working_dir: None,
+            tools: spec.map(|s| s.parse().unwrap()),
+            max_child_result_chars: DEFAULT_MAX_CHILD_RESULT_CHARS,
+            concurrency: DEFAULT_FORK_CONCURRENCY,
+            no_default_permission_mode: false,
+        }
     }
 
     #[test]
-    fn compose_reintegration_message_escapes_label() {
-        let results = vec![("Fix \"quotes\"".to_string(), Ok("done".to_string()))];
-        let msg = compose_reintegration_message(&results);
-        assert!(msg.contains("label=\"Fix &quot;quotes&quot;\""));
+    fn fork_tools_parse() {
+        let tools: ForkTools = "Read, Bash(git log:*,git diff:*) ,!WebFetch"
+            .parse()
+            .unwrap();
+        assert_eq!(tools.allowed, args(&["Read", "Bash(git log:*,git diff:*)"]));
+        assert_eq!(tools.disallowed, args(&["WebFetch"]));
     }
 
     #[test]
-    fn compose_reintegration_message_escapes_ampersand_and_angle() {
-        let results = vec![("Fix A & B".to_string(), Ok("done".to_string()))];
-        let msg = compose_reintegration_message(&results);
-        assert!(msg.contains("label=\"Fix A &amp; B\""));
+    fn fork_tools_parse_rejects_empty() {
+        assert!("".parse::<ForkTools>().is_err());
+        assert!(" , ".parse::<ForkTools>().is_err());
+        assert!("Read,!".parse::<ForkTools>().is_err());
+    }
 
-        let results = vec![("Fix <thing>".to_string(), Ok("done".to_string()))];
-        let msg = compose_reintegration_message(&results);
-        assert!(msg.contains("label=\"Fix &lt;thing&gt;\""));
+    #[test]
+    fn disallowed_tool_args_merges_without_duplicates() {
+        assert_eq!(
+            ForkConfig::disallowed_tool_args(&[], None),
+            ["--disallowed-tools".to_string(), "Task,Agent".to_string()]
+        );
+        let tools: ForkTools = "!Bash,!Write".parse().unwrap();
+        assert_eq!(
+            ForkConfig::disallowed_tool_args(&args(&["Agent", "Bash"]), Some(&tools))[1],
+            "Task,Agent,Bash,Write"
+        );
     }
 
     #[test]
-    fn fork_system_prompt_contains_tag() {
-        let prompt = fork_system_prompt();
-        assert!(prompt.contains("<fork>"));
-        assert!(prompt.contains("</fork>"));
-        assert!(prompt.contains("<fork-results>"));
+    fn child_args_without_spec_match_parent() {
+        let parent = ["--allowedTools", "Bash", "--disallowed-tools", "Task,Agent"];
+        assert_eq!(config(&parent, None).child_args(), args(&parent));
+    }
+
+    #[test]
+    fn child_args_spec_replaces_allowed_and_adds_denials() {
+        let parent = [
+            "--model",
+            "sonnet",
+            "--allowedTools",
+            "Bash,Edit",
+            "--disallowedTools=WebSearch",
+            "--disallowed-tools",
+            "Task,Agent",
+        ];
+        let child = config(&parent, Some("Read,Grep,!Bash")).child_args();
+        assert_eq!(
+            child,
+            args(&[
+                "--model",
+                "sonnet",
+                "--allowedTools",
+                "Read,Grep",
+                "--disallowed-tools",
+                "Task,Agent,WebSearch,Bash",
+            ])
+        );
+    }
+
+    #[test]
+    fn child_args_denial_only_spec_keeps_parent_allowed() {
+        let parent = ["--allowed-tools", "Bash", "Edit", "--verbose"];
+        let child = config(&parent, Some("!Edit")).child_args();
+        assert_eq!(
+            child,
+            args(&[
+                "--verbose",
+                "--allowedTools",
+                "Bash,Edit",
+                "--disallowed-tools",
+                "Task,Agent,Edit",
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn prompt_origin_children_start_fresh_from_the_prompt() {
+        let config = ForkConfig {
+            extra_args: vec!["--model".into(), "haiku".into()],
+            working_dir: None,
+            tools: None,
+            max_child_result_chars: DEFAULT_MAX_CHILD_RESULT_CHARS,
+            concurrency: DEFAULT_FORK_CONCURRENCY,
+            no_default_permission_mode: false,
+        };
+        let child = |task: &str| {
+            serde_json::json!({
+                "label": "fork_spawn",
+                "args": {
+                    "prompt": format!("Review these modules.\n\nYou were assigned '{task}'"),
+                    "resume": null,
+                    "extra_args": ["--model", "haiku"],
+                    "append_system_prompt": null,
+                },
+                "result": {"Ok": null},
+            })
+        };
+        let done = |idx: usize, text: &str| {
+            serde_json::json!({
+                "label": "fork_event",
+                "args": null,
+                "result": {"Ok": [idx, {"Claude": {
+                    "type": "result", "subtype": "success", "is_error": false,
+                    "total_cost_usd": 0.01, "num_turns": 1, "duration_ms": 100,
+                    "result": text, "session_id": format!("child-{idx}"),
+                }}]},
+            })
+        };
+        let ndjson = [
+            child("auth.rs"),
+            child("db.rs"),
+            done(1, "db is fine"),
+            done(0, "auth needs work"),
+        ]
+        .map(|entry| entry.to_string());
+        let vcr = VcrContext::replay(&ndjson.join("\n")).unwrap();
+
+        let mut renderer = Renderer::with_writer(Vec::new());
+        let mut input = InputHandler::new(2);
+        let mut io = Io::dummy_headless();
+        let reintegration = run_fork(
+            ForkOrigin::Prompt("Review these modules."),
+            vec!["auth.rs".into(), "db.rs".into()],
+            &config,
+            &mut renderer,
+            &mut input,
+            &mut io,
+            &vcr,
+        )
+        .await
+        .unwrap();
+
+        assert!(vcr.unreplayed_labels().is_empty());
+        assert_eq!(
+            reintegration,
+            "<fork-results>\n### Task 1: auth.rs\nCost $0.01 \u{b7} 0.1s\n\nauth needs work\n\n\
+             ### Task 2: db.rs\nCost $0.01 \u{b7} 0.1s\n\ndb is fine\n</fork-results>"
+        );
+    }
+
+    #[tokio::test]
+    async fn standalone_ctrl_c_stops_the_children() {
+        let config = ForkConfig {
+            extra_args: vec![],
+            working_dir: None,
+            tools: None,
+            max_child_result_chars: DEFAULT_MAX_CHILD_RESULT_CHARS,
+            concurrency: 1,
+            no_default_permission_mode: false,
+        };
+        let ctrl_c = Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        let ndjson = [
+            serde_json::json!({
+                "label": "fork_spawn",
+                "args": {
+                    "prompt": "lint the crate",
+                    "resume": null,
+                    "extra_args": [],
+                    "append_system_prompt": null,
+                },
+                "result": {"Ok": null},
+            }),
+            serde_json::json!({
+                "label": "fork_event",
+                "args": null,
+                "result": {"Ok": serde_json::to_value(ForkEvent::Terminal(ctrl_c)).unwrap()},
+            }),
+        ]
+        .map(|entry| entry.to_string());
+        let vcr = VcrContext::replay(&ndjson.join("\n")).unwrap();
+
+        let mut renderer = Renderer::with_writer(Vec::new());
+        let mut input = InputHandler::new(2);
+        let mut io = Io::dummy_headless();
+        let results = run_children(
+            ForkOrigin::Standalone,
+            vec!["lint the crate".into(), "run the tests".into()],
+            &config,
+            &mut renderer,
+            &mut input,
+            &mut io,
+            &vcr,
+        )
+        .await
+        .unwrap();
+
+        assert!(vcr.unreplayed_labels().is_empty());
+        assert_eq!(
+            results,
+            vec![
+                (
+                    "lint the crate".to_string(),
+                    ChildOutcome::failed("Interrupted")
+                ),
+                (
+                    "run the tests".to_string(),
+                    ChildOutcome::failed("Interrupted")
+                ),
+            ]
+        );
+        let output = String::from_utf8(renderer.writer().clone()).unwrap();
+        assert!(
+            output.contains("stopped 2 unfinished child(ren)"),
+            "{output}"
+        );
+    }
+
+    #[tokio::test]
+    async fn queued_children_start_as_running_ones_finish() {
+        let config = ForkConfig {
+            extra_args: vec![],
+            working_dir: None,
+            tools: None,
+            max_child_result_chars: DEFAULT_MAX_CHILD_RESULT_CHARS,
+            concurrency: 2,
+            no_default_permission_mode: false,
+        };
+        let child = |task: &str| {
+            serde_json::json!({
+                "label": "fork_spawn",
+                "args": {
+                    "prompt": format!("You were assigned '{task}'"),
+                    "resume": "parent",
+                    "extra_args": ["--fork-session"],
+                    "append_system_prompt": null,
+                },
+                "result": {"Ok": null},
+            })
+        };
+        let done = |idx: usize| {
+            serde_json::json!({
+                "label": "fork_event",
+                "args": null,
+                "result": {"Ok": [idx, {"Claude": {
+                    "type": "result", "subtype": "success", "is_error": false,
+                    "total_cost_usd": 0.01, "num_turns": 1, "duration_ms": 100,
+                    "result": "ok", "session_id": format!("child-{idx}"),
+                }}]},
+            })
+        };
+        // Only two children start; the third starts once the second is done.
+        let ndjson = [
+            child("a"),
+            child("b"),
+            done(1),
+            child("c"),
+            done(0),
+            done(2),
+        ]
+        .map(|entry| entry.to_string());
+        let vcr = VcrContext::replay(&ndjson.join("\n")).unwrap();
+
+        let mut renderer = Renderer::with_writer(Vec::new());
+        let mut input = InputHandler::new(2);
+        let mut io = Io::dummy_headless();
+        run_fork(
+            ForkOrigin::Session("parent"),
+            vec!["a".into(), "b".into(), "c".into()],
+            &config,
+            &mut renderer,
+            &mut input,
+            &mut io,
+            &vcr,
+        )
+        .await
+        .unwrap();
+
+        assert!(vcr.unreplayed_labels().is_empty());
+        let output = String::from_utf8(renderer.writer().clone()).unwrap();
+        assert!(output.contains("running 2 of 3 children at a time"));
+        assert!(output.contains("[3] \u{2442} started \u{b7} running: 2/3 children"));
     }
 }
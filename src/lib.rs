@@ -1,14 +1,26 @@
 pub mod agents;
+pub mod claude_version;
 pub mod commands;
 pub mod config;
+pub mod control_socket;
+pub mod covenignore;
+pub mod dispatch_debug;
 pub mod display;
 pub mod event;
+pub mod fnv;
 pub mod fork;
+pub mod notify;
+pub mod platform;
+pub mod post_land;
+pub mod preflight;
 pub mod protocol;
 pub mod reload;
 pub mod semaphore;
 pub mod session;
+pub mod timing;
 pub mod transition;
+pub mod utc;
 pub mod vcr;
 pub mod worker_state;
 pub mod worktree;
+pub mod worktree_base;
@@ -1,13 +1,17 @@
 mod cli;
 
-use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
 use coven::commands;
+use coven::display::theme::{self, Theme};
+use coven::notify::Notifier;
 use coven::vcr::{Io, VcrContext};
 
-use cli::{Cli, Command};
+use cli::{Cli, Command, RalphArgs, WorkerArgs};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -16,91 +20,61 @@ async fn main() -> Result<()> {
 }
 
 async fn run(cli: Cli) -> Result<()> {
+    if let Some(path) = &cli.trace_git {
+        coven::worktree::trace_git_to(git_trace_sink(path)?);
+    }
+    let theme =
+        Theme::resolve(cli.theme, |key| std::env::var(key).ok()).map_err(anyhow::Error::msg)?;
+    theme::set(theme);
     match cli.command {
-        Some(Command::Init) => {
+        Some(Command::Init { update }) => {
             let vcr = VcrContext::live();
             let stdout = &mut std::io::stdout();
-            commands::init::init(&vcr, stdout, &mut std::io::stdin().lock(), None).await?;
-        }
-        Some(Command::Status) => {
-            commands::status::status(&VcrContext::live(), None, &mut std::io::stdout()).await?;
-        }
-        Some(Command::Gc { force }) => {
-            commands::gc::gc(&VcrContext::live(), force, None, &mut std::io::stdout()).await?;
+            let stdin = &mut std::io::stdin().lock();
+            commands::init::init(&vcr, stdout, stdin, None, update).await?;
         }
-        Some(Command::Ralph {
-            prompt,
-            prompt_command,
-            iterations,
-            break_tag,
-            no_break,
-            no_wait,
-            claude_opts,
-        }) => {
-            if no_break && iterations == 0 && prompt_command.is_none() {
-                anyhow::bail!(
-                    "--no-break requires --iterations or --prompt-command to prevent infinite looping"
-                );
-            }
-            let prompt_source = commands::ralph::PromptSource::from_cli(prompt, prompt_command)?;
-            let (mut io, vcr) = create_live_io();
-            commands::ralph::ralph(
-                commands::ralph::RalphConfig {
-                    prompt_source,
-                    iterations,
-                    break_tag,
-                    no_break,
-                    no_wait,
-                    show_thinking: claude_opts.show_thinking,
-                    tag_flags: commands::ralph::TagFlags {
-                        fork: claude_opts.fork,
-                        reload: claude_opts.reload,
-                    },
-                    extra_args: claude_opts.claude_args,
-                    working_dir: None,
-                    term_width: None,
-                },
-                &mut io,
-                &vcr,
-                std::io::stdout(),
-            )
-            .await?;
+        Some(Command::Status { json }) => {
+            commands::status::status(&VcrContext::live(), None, json, &mut std::io::stdout())
+                .await?;
         }
-        Some(Command::Worker {
-            branch,
+        Some(Command::Gc {
+            force,
+            older_than,
             worktree_base,
-            no_wait,
-            claude_opts,
+            dry_run,
         }) => {
-            let base = worktree_base.map_or_else(default_worktree_base, Ok)?;
-            let (mut io, vcr) = create_live_io();
-            commands::worker::worker(
-                commands::worker::WorkerConfig {
-                    show_thinking: claude_opts.show_thinking,
-                    branch,
-                    worktree_base: base,
-                    extra_args: claude_opts.claude_args,
-                    working_dir: None,
-                    fork: claude_opts.fork,
-                    reload: claude_opts.reload,
-                    no_wait,
-                    term_width: None,
-                },
-                &mut io,
-                &vcr,
-                std::io::stdout(),
+            let config = commands::gc::GcConfig {
+                force,
+                older_than,
+                worktree_base,
+                dry_run,
+                debug_dir: None,
+            };
+            let stdin = &mut std::io::stdin().lock();
+            commands::gc::gc(
+                &VcrContext::live(),
+                &config,
+                None,
+                &mut std::io::stdout(),
+                stdin,
             )
             .await?;
         }
-        None => {
-            let (mut io, vcr) = create_live_io();
-            commands::run::run(
-                commands::run::RunConfig {
-                    prompt: cli.prompt,
-                    extra_args: cli.claude_opts.claude_args,
-                    show_thinking: cli.claude_opts.show_thinking,
-                    fork: cli.claude_opts.fork,
-                    reload: cli.claude_opts.reload,
+        Some(Command::Tutorial) => {
+            let (mut io, vcr) = create_live_io(None);
+            commands::tutorial::tutorial(&mut io, &vcr, std::io::stdout()).await?;
+        }
+        Some(Command::Fork {
+            tasks,
+            no_default_permission_mode,
+            claude_args,
+        }) => {
+            let (mut io, vcr) = create_live_io(None);
+            let results = commands::fork_cmd::fork(
+                commands::fork_cmd::ForkCmdConfig {
+                    tasks,
+                    extra_args: claude_args,
+                    no_default_permission_mode,
                     working_dir: None,
                     term_width: None,
                 },
@@ -109,16 +83,295 @@ async fn run(cli: Cli) -> Result<()> {
                 std::io::stdout(),
             )
             .await?;
+            drop(io);
+            if results.iter().any(|(_, outcome)| outcome.result.is_err()) {
+                std::process::exit(1);
+            }
         }
+        Some(Command::Ralph(args)) => run_ralph(args).await?,
+        Some(Command::Worker(args)) => run_worker(args).await?,
+        None => run_session(cli).await?,
+    }
+
+    Ok(())
+}
+
+/// `coven ralph`.
+async fn run_ralph(args: RalphArgs) -> Result<()> {
+    let RalphArgs {
+        prompt,
+        prompt_file,
+        prompt_command,
+        first_prompt,
+        iterations,
+        break_tag,
+        no_break,
+        no_wait,
+        iteration_timeout,
+        commit_each,
+        claude_opts,
+    } = args;
+    if no_break && iterations == 0 && prompt_command.is_none() {
+        anyhow::bail!(
+            "--no-break requires --iterations or --prompt-command to prevent infinite looping"
+        );
+    }
+    let prompt = match prompt_file {
+        Some(path) => Some(commands::read_prompt_file(&path)?),
+        None => prompt,
+    };
+    let prompt_source = commands::ralph::PromptSource::from_cli(prompt, prompt_command)?;
+    let (mut io, vcr) = create_live_io(claude_opts.notifier()?);
+    let report = commands::ralph::ralph(
+        commands::ralph::RalphConfig {
+            prompt_source,
+            first_prompt,
+            iterations,
+            break_tag: (!no_break).then_some(break_tag),
+            no_wait,
+            display: claude_opts.display_options(),
+            protocol_log: claude_opts.protocol_log(),
+            steering_timeout: claude_opts.steering_timeout(),
+            max_cost: claude_opts.max_cost,
+            iteration_timeout: iteration_timeout.map(Duration::from_secs),
+            preflight: claude_opts.preflight_connectivity.unwrap_or(true),
+            no_default_permission_mode: claude_opts.no_default_permission_mode,
+            commit_each,
+            tag_flags: claude_opts.tag_flags(),
+            extra_args: claude_opts.claude_args,
+            working_dir: None,
+        },
+        &mut io,
+        &vcr,
+        std::io::stdout(),
+    )
+    .await?;
+    drop(io);
+    eprintln!("{}", report.summary());
+    let code = report.stop_reason.exit_code();
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// `coven worker`.
+async fn run_worker(args: WorkerArgs) -> Result<()> {
+    let WorkerArgs {
+        branch,
+        branch_prefix,
+        branch_from,
+        worktree_base,
+        agents_dir,
+        no_wait,
+        cleanup,
+        cycles,
+        debug_dispatch,
+        lock_timeout,
+        steal_stale_lock,
+        max_loop_warnings,
+        wake_on,
+        push_after_land,
+        post_land,
+        post_land_required,
+        post_land_timeout,
+        control_socket,
+        claude_opts,
+    } = args;
+    let (mut io, vcr) = create_live_io(claude_opts.notifier()?);
+    let control_socket = control_socket
+        .as_deref()
+        .map(coven::control_socket::ControlSocket::bind)
+        .transpose()?;
+    let signal_exit_code = watch_shutdown_signals(&mut io)?;
+    commands::worker::worker(
+        commands::worker::WorkerConfig {
+            display: claude_opts.display_options(),
+            protocol_log: claude_opts.protocol_log(),
+            steering_timeout: claude_opts.steering_timeout(),
+            max_cost: claude_opts.max_cost,
+            preflight: claude_opts.preflight_connectivity.unwrap_or(true),
+            no_default_permission_mode: claude_opts.no_default_permission_mode,
+            branch,
+            branch_prefix,
+            branch_from,
+            worktree_base,
+            agents_dir,
+            tag_flags: claude_opts.tag_flags(),
+            extra_args: claude_opts.claude_args,
+            working_dir: None,
+            no_wait,
+            cleanup: cleanup.cleanup(),
+            cycles: cycles.cycles(),
+            debug_dispatch,
+            lock_wait: coven::semaphore::LockWait {
+                timeout: lock_timeout,
+                steal_after: steal_stale_lock,
+            },
+            max_loop_warnings,
+            wake_on: wake_on.into_iter().filter(|p| !p.is_empty()).collect(),
+            push_after_land,
+            post_land: post_land.map(|command| coven::post_land::PostLandHook {
+                command,
+                required: post_land_required,
+                timeout: Duration::from_secs(post_land_timeout),
+            }),
+            control_socket,
+        },
+        &mut io,
+        &vcr,
+        std::io::stdout(),
+    )
+    .await?;
+    drop(io);
+    let code = signal_exit_code.load(Ordering::SeqCst);
+    if code != 0 {
+        std::process::exit(code);
     }
+    Ok(())
+}
 
+/// `coven [PROMPT]`: a single interactive session.
+async fn run_session(cli: Cli) -> Result<()> {
+    let prompt = match cli.prompt_file {
+        Some(path) => Some(commands::read_prompt_file(&path)?),
+        None => cli.prompt,
+    };
+    let mut fork_tasks = cli.fork_tasks;
+    if let Some(path) = &cli.fork_tasks_file {
+        fork_tasks.extend(commands::read_task_file(path)?);
+    }
+    // With --json-events, stdout carries the events and the display
+    // moves to stderr.
+    let (display, json_events): (
+        Box<dyn std::io::Write>,
+        Option<Box<dyn std::io::Write + Send>>,
+    ) = if cli.json_events {
+        (
+            Box::new(std::io::stderr()),
+            Some(Box::new(std::io::stdout())),
+        )
+    } else {
+        (Box::new(std::io::stdout()), None)
+    };
+    let claude_opts = cli.claude_opts;
+    let (mut io, vcr) = create_live_io(claude_opts.notifier()?);
+    commands::run::run(
+        commands::run::RunConfig {
+            prompt,
+            resume: cli.resume,
+            continue_last: cli.continue_session,
+            notes_dir: cli.notes_dir,
+            steering_timeout: claude_opts.steering_timeout(),
+            protocol_log: claude_opts.protocol_log(),
+            max_cost: claude_opts.max_cost,
+            preflight: claude_opts.preflight_connectivity.unwrap_or(false),
+            no_default_permission_mode: claude_opts.no_default_permission_mode,
+            display: claude_opts.display_options(),
+            tag_flags: claude_opts.tag_flags(),
+            extra_args: claude_opts.claude_args,
+            fork_tasks,
+            working_dir: None,
+            json_events,
+        },
+        &mut io,
+        &vcr,
+        display,
+    )
+    .await?;
     Ok(())
 }
 
+/// Where `--trace-git` writes: stderr for an empty path, else appended to the file.
+fn git_trace_sink(path: &std::path::Path) -> Result<Box<dyn std::io::Write + Send>> {
+    if path.as_os_str().is_empty() {
+        return Ok(Box::new(std::io::stderr()));
+    }
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open git trace file {}", path.display()))?;
+    Ok(Box::new(file))
+}
+
+/// Turn the first SIGTERM or SIGINT into a shutdown request on `io`, which
+/// the worker acts on at its next safe point before cleaning up; a second
+/// one exits right away. The returned code (128 + signal number) stays 0
+/// until a signal arrives.
+fn watch_shutdown_signals(io: &mut Io) -> Result<Arc<AtomicI32>> {
+    let (tx, rx) = tokio::sync::watch::channel(false);
+    io.watch_shutdown(rx);
+    let exit_code = Arc::new(AtomicI32::new(0));
+    let code = Arc::clone(&exit_code);
+
+    let mut signals = ShutdownSignals::install()?;
+    tokio::spawn(async move {
+        let signal = signals.recv().await;
+        code.store(128 + signal, Ordering::SeqCst);
+        tx.send(true).ok();
+        signals.recv().await;
+        coven::display::set_bracketed_paste(false);
+        crossterm::terminal::disable_raw_mode().ok();
+        std::process::exit(128 + signal);
+    });
+    Ok(exit_code)
+}
+
+/// The signals that request a worker shutdown.
+#[cfg(unix)]
+struct ShutdownSignals {
+    terminate: tokio::signal::unix::Signal,
+    interrupt: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl ShutdownSignals {
+    fn install() -> Result<Self> {
+        use tokio::signal::unix::{SignalKind, signal};
+        Ok(Self {
+            terminate: signal(SignalKind::terminate())
+                .context("failed to install SIGTERM handler")?,
+            interrupt: signal(SignalKind::interrupt())
+                .context("failed to install SIGINT handler")?,
+        })
+    }
+
+    /// Wait for the next signal and return its number.
+    async fn recv(&mut self) -> i32 {
+        tokio::select! {
+            _ = self.terminate.recv() => libc::SIGTERM,
+            _ = self.interrupt.recv() => libc::SIGINT,
+        }
+    }
+}
+
+/// The signals that request a worker shutdown: just Ctrl+C on Windows.
+#[cfg(windows)]
+struct ShutdownSignals {
+    ctrl_c: tokio::signal::windows::CtrlC,
+}
+
+#[cfg(windows)]
+impl ShutdownSignals {
+    fn install() -> Result<Self> {
+        Ok(Self {
+            ctrl_c: tokio::signal::windows::ctrl_c().context("failed to install Ctrl+C handler")?,
+        })
+    }
+
+    /// Wait for the next Ctrl+C, reported as SIGINT.
+    async fn recv(&mut self) -> i32 {
+        self.ctrl_c.recv().await;
+        2
+    }
+}
+
 /// Install a panic hook that restores terminal state before printing the panic.
 fn install_panic_hook() {
     let default_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |info| {
+        coven::display::set_bracketed_paste(false);
         crossterm::terminal::disable_raw_mode().ok();
         default_hook(info);
     }));
@@ -136,7 +389,7 @@ fn install_panic_hook() {
 ///
 /// The event channel starts empty — the first `SessionRunner::spawn`
 /// should provide claude events via `io.replace_event_channel()`.
-fn create_live_io() -> (Io, VcrContext) {
+fn create_live_io(notifier: Option<Notifier>) -> (Io, VcrContext) {
     use std::io::IsTerminal;
 
     use crossterm::event::EventStream;
@@ -208,13 +461,9 @@ fn create_live_io() -> (Io, VcrContext) {
     // so `recv()` parks instead of returning `None` (which `next_event`
     // would surface as `ProcessExit`) before the first session spawns.
     io.clear_event_channel();
+    if let Some(notifier) = notifier {
+        io.set_notifier(notifier);
+    }
     let vcr = VcrContext::live();
     (io, vcr)
 }
-
-fn default_worktree_base() -> Result<PathBuf> {
-    let home = std::env::var("HOME").map_err(|_| {
-        anyhow::anyhow!("HOME not set; use --worktree-base to specify worktree location")
-    })?;
-    Ok(PathBuf::from(home).join(".coven").join("worktrees"))
-}
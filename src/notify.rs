@@ -0,0 +1,155 @@
+//! `--notify`: a user command coven runs whenever it starts waiting for the
+//! user (e.g. `notify-send` or `osascript`), for sessions running out of
+//! sight in a background pane.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::display::renderer::Renderer;
+use crate::vcr::{Io, VcrContext};
+
+/// The `--notify` command and the directory it reports.
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    pub command: String,
+    pub dir: PathBuf,
+}
+
+/// One notification: recorded as the args of the `notify` VCR call, so
+/// tests see every notification without running a command.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub command: String,
+    pub dir: PathBuf,
+    pub message: String,
+}
+
+impl Notification {
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("COVEN_NOTIFY_MESSAGE", self.message.clone()),
+            ("COVEN_NOTIFY_DIR", self.dir.display().to_string()),
+        ]
+    }
+}
+
+/// Start the notifier without waiting for it to finish.
+pub fn spawn(notification: &Notification) -> std::io::Result<()> {
+    let (shell, flag) = crate::platform::shell();
+    tokio::process::Command::new(shell)
+        .arg(flag)
+        .arg(&notification.command)
+        .current_dir(&notification.dir)
+        .envs(notification.env())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Tell the user coven is waiting for them, if `--notify` is set. A
+/// notifier that fails to start is a warning, not an error.
+pub async fn waiting<W: Write>(
+    message: &str,
+    io: &Io,
+    renderer: &mut Renderer<W>,
+    vcr: &VcrContext,
+) -> Result<()> {
+    let Some(notifier) = io.notifier() else {
+        return Ok(());
+    };
+    let notification = Notification {
+        command: notifier.command.clone(),
+        dir: notifier.dir.clone(),
+        message: message.to_string(),
+    };
+    let error: Option<String> = vcr
+        .call("notify", notification, async |n: &Notification| {
+            Ok(spawn(n).err().map(|e| e.to_string()))
+        })
+        .await?;
+    if let Some(error) = error {
+        renderer.render_warning(&format!("--notify command failed to start: {error}"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn io_notifying() -> Io {
+        let mut io = Io::dummy();
+        io.set_notifier(Notifier {
+            command: "notify-send coven \"$COVEN_NOTIFY_MESSAGE\"".to_string(),
+            dir: PathBuf::from("/work/repo"),
+        });
+        io
+    }
+
+    fn entry(message: &str, error: Option<&str>) -> String {
+        serde_json::json!({
+            "label": "notify",
+            "args": {
+                "command": "notify-send coven \"$COVEN_NOTIFY_MESSAGE\"",
+                "dir": "/work/repo",
+                "message": message,
+            },
+            "result": {"Ok": error},
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn notifies_with_the_message() {
+        let vcr = VcrContext::replay(&entry("claude finished", None)).unwrap();
+        let mut renderer = Renderer::with_writer(Vec::new());
+        waiting("claude finished", &io_notifying(), &mut renderer, &vcr)
+            .await
+            .unwrap();
+        assert!(vcr.unreplayed_labels().is_empty());
+        assert!(renderer.writer().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failure_to_start_is_a_warning() {
+        let vcr = VcrContext::replay(&entry("paused", Some("No such file"))).unwrap();
+        let mut renderer = Renderer::with_writer(Vec::new());
+        waiting("paused", &io_notifying(), &mut renderer, &vcr)
+            .await
+            .unwrap();
+        let output = String::from_utf8(renderer.writer().clone()).unwrap();
+        assert!(output.contains("--notify command failed to start: No such file"));
+    }
+
+    #[tokio::test]
+    async fn nothing_without_a_notifier() {
+        let vcr = VcrContext::replay("").unwrap();
+        let mut renderer = Renderer::with_writer(Vec::new());
+        waiting("paused", &Io::dummy(), &mut renderer, &vcr)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn env_carries_message_and_dir() {
+        let notification = Notification {
+            command: String::new(),
+            dir: PathBuf::from("/work/repo"),
+            message: "waiting for input".to_string(),
+        };
+        assert_eq!(
+            notification.env(),
+            vec![
+                ("COVEN_NOTIFY_MESSAGE", "waiting for input".to_string()),
+                ("COVEN_NOTIFY_DIR", "/work/repo".to_string()),
+            ]
+        );
+    }
+}
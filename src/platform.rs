@@ -0,0 +1,418 @@
+//! Platform-specific helpers.
+//!
+//! Everything that differs between Unix and Windows lives here behind a
+//! plain function, so call sites stay free of `cfg` attributes.
+
+use std::path::{Path, PathBuf};
+
+/// The user's home directory.
+///
+/// Uses `HOME` everywhere (Git Bash and MSYS set it on Windows too), then
+/// falls back to `USERPROFILE` and `HOMEDRIVE` + `HOMEPATH` on Windows.
+pub fn home_dir() -> Option<PathBuf> {
+    home_dir_from(|key| std::env::var(key).ok())
+}
+
+fn home_dir_from(var: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let non_empty = |key: &str| var(key).filter(|v| !v.is_empty());
+    if let Some(home) = non_empty("HOME") {
+        return Some(PathBuf::from(home));
+    }
+    if !cfg!(windows) {
+        return None;
+    }
+    if let Some(profile) = non_empty("USERPROFILE") {
+        return Some(PathBuf::from(profile));
+    }
+    let drive = non_empty("HOMEDRIVE")?;
+    let path = non_empty("HOMEPATH")?;
+    Some(PathBuf::from(format!("{drive}{path}")))
+}
+
+/// Name of the environment variable(s) consulted by [`home_dir`], for error messages.
+pub const HOME_VAR: &str = if cfg!(windows) {
+    "HOME or USERPROFILE"
+} else {
+    "HOME"
+};
+
+/// Discard keyboard input the terminal has buffered but nobody has read yet.
+///
+/// Used after handing the terminal to a child process (pager, native TUI)
+/// so stray keypresses don't leak into coven's input line.
+pub fn flush_stdin_input() {
+    #[cfg(unix)]
+    // SAFETY: tcflush on STDIN_FILENO with TCIFLUSH is a POSIX syscall that
+    // discards buffered input bytes — no memory or resource safety concerns.
+    unsafe {
+        libc::tcflush(libc::STDIN_FILENO, libc::TCIFLUSH);
+    }
+    #[cfg(windows)]
+    // SAFETY: GetStdHandle returns a borrowed handle (or null/invalid, which
+    // FlushConsoleInputBuffer rejects with an error we ignore).
+    unsafe {
+        let handle = win32::GetStdHandle(win32::STD_INPUT_HANDLE);
+        win32::FlushConsoleInputBuffer(handle);
+    }
+}
+
+/// Check if a process with the given PID is alive.
+pub fn is_pid_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        // SAFETY: kill with signal 0 performs error checking without sending a signal.
+        unsafe { libc::kill(pid.cast_signed(), 0) == 0 }
+    }
+    #[cfg(windows)]
+    {
+        // SAFETY: the handle is checked for null before use and closed before returning.
+        unsafe {
+            let handle = win32::OpenProcess(win32::PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if handle.is_null() {
+                return false;
+            }
+            let mut code = 0u32;
+            let ok = win32::GetExitCodeProcess(handle, &raw mut code) != 0;
+            win32::CloseHandle(handle);
+            ok && code == win32::STILL_ACTIVE
+        }
+    }
+}
+
+/// Start the command's process in a process group of its own, so
+/// [`kill_process_group`] can reach everything it spawns. No-op on Windows.
+pub fn own_process_group(command: &mut tokio::process::Command) {
+    #[cfg(unix)]
+    command.process_group(0);
+    #[cfg(not(unix))]
+    let _ = command;
+}
+
+/// Kill the process group led by `pid` (see [`own_process_group`]). No-op
+/// on Windows, where only the process itself is killed when dropped.
+pub fn kill_process_group(pid: u32) {
+    #[cfg(unix)]
+    // SAFETY: killpg only sends a signal; a group that has already exited
+    // makes it fail with ESRCH, which is ignored.
+    unsafe {
+        libc::killpg(pid.cast_signed(), libc::SIGKILL);
+    }
+    #[cfg(not(unix))]
+    let _ = pid;
+}
+
+/// Identifier of the filesystem containing `path` (its device number), for
+/// telling whether two paths live on the same filesystem.
+///
+/// `None` if `path` can't be inspected or the platform has no such notion.
+pub fn filesystem_id(path: &Path) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        std::fs::metadata(path).ok().map(|m| m.dev())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Type name of the filesystem containing `path` if it's a network
+/// filesystem (`nfs`, `cifs`, `smbfs`, ...).
+///
+/// `None` for local filesystems, and wherever the type can't be determined.
+pub fn network_fs_type(path: &Path) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut buf = std::mem::MaybeUninit::<libc::statfs>::zeroed();
+        // SAFETY: c_path is a valid NUL-terminated string and buf points to
+        // writable memory of the right size; buf is only read after statfs
+        // reports success.
+        let stat = unsafe {
+            if libc::statfs(c_path.as_ptr(), buf.as_mut_ptr()) != 0 {
+                return None;
+            }
+            buf.assume_init()
+        };
+        // Magic numbers from statfs(2).
+        let name = match u64::try_from(stat.f_type).ok()? {
+            0x6969 => "nfs",
+            0x517B => "smb",
+            0xFF53_4D42 => "cifs",
+            0xFE53_4D42 => "smb2",
+            0x5346_414F => "afs",
+            0x00C3_6400 => "ceph",
+            0x0102_1997 => "9p",
+            _ => return None,
+        };
+        Some(name.to_string())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut buf = std::mem::MaybeUninit::<libc::statfs>::zeroed();
+        // SAFETY: as above; f_fstypename is a NUL-terminated C string
+        // filled in by a successful statfs.
+        let name = unsafe {
+            if libc::statfs(c_path.as_ptr(), buf.as_mut_ptr()) != 0 {
+                return None;
+            }
+            let stat = buf.assume_init();
+            std::ffi::CStr::from_ptr(stat.f_fstypename.as_ptr())
+                .to_string_lossy()
+                .into_owned()
+        };
+        matches!(name.as_str(), "nfs" | "smbfs" | "afpfs" | "webdav" | "cifs").then_some(name)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Program and flag that run a command line through the platform shell.
+pub fn shell() -> (&'static str, &'static str) {
+    if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    }
+}
+
+/// Pagers to try, in order, given `$PAGER`: its command line, then `less`,
+/// then `more`. Each is a program and its arguments.
+///
+/// A `$PAGER` whose quotes don't match is skipped.
+pub fn pager_commands(pager: Option<&str>) -> Vec<(String, Vec<String>)> {
+    let configured = pager
+        .and_then(split_words)
+        .filter(|words| !words.is_empty());
+    let fallbacks = [vec!["less".to_string()], vec!["more".to_string()]];
+    let mut commands = Vec::new();
+    for mut words in configured.into_iter().chain(fallbacks) {
+        let program = words.remove(0);
+        let mut args = words;
+        args.extend(
+            pager_color_args(&program, &args)
+                .iter()
+                .map(|a| (*a).to_string()),
+        );
+        if !commands.iter().any(|(p, a)| *p == program && *a == args) {
+            commands.push((program, args));
+        }
+    }
+    commands
+}
+
+/// Split a command line into words the way a POSIX shell would, without
+/// expansions: whitespace separates words, single quotes are literal, and
+/// double quotes allow `\"` and `\\`. Outside quotes a backslash escapes
+/// only whitespace, a quote or a backslash, so Windows paths survive. None
+/// for an unterminated quote.
+pub fn split_words(line: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' if matches!(chars.peek(), Some('"' | '\\')) => {
+                            word.extend(chars.next());
+                        }
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' if chars
+                .peek()
+                .is_some_and(|&n| n.is_whitespace() || matches!(n, '\'' | '"' | '\\')) =>
+            {
+                word.get_or_insert_default().extend(chars.next());
+            }
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+/// Extra arguments for `pager` so it renders ANSI colors.
+///
+/// Only `less` understands `-R`; other pagers (notably Windows `more`)
+/// would treat it as a file name. Nothing is added when `args` already
+/// ask `less` for raw control characters (`-R`, `-FRX`, `-r`, ...).
+pub fn pager_color_args(pager: &str, args: &[String]) -> &'static [&'static str] {
+    let name = pager.rsplit(['/', '\\']).next().unwrap_or(pager);
+    let lower = name.to_ascii_lowercase();
+    let raw_already = args.iter().any(|arg| match arg.strip_prefix("--") {
+        Some(long) => long.eq_ignore_ascii_case("raw-control-chars"),
+        None => arg.starts_with('-') && arg.contains(['R', 'r']),
+    });
+    if lower.strip_suffix(".exe").unwrap_or(&lower) == "less" && !raw_already {
+        &["-R"]
+    } else {
+        &[]
+    }
+}
+
+#[cfg(windows)]
+#[allow(non_snake_case, clippy::upper_case_acronyms)]
+mod win32 {
+    use std::ffi::c_void;
+
+    pub type HANDLE = *mut c_void;
+
+    pub const STD_INPUT_HANDLE: u32 = (-10i32).cast_unsigned();
+    pub const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+    pub const STILL_ACTIVE: u32 = 259;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        pub fn GetStdHandle(std_handle: u32) -> HANDLE;
+        pub fn FlushConsoleInputBuffer(console_input: HANDLE) -> i32;
+        pub fn OpenProcess(desired_access: u32, inherit_handle: i32, process_id: u32) -> HANDLE;
+        pub fn GetExitCodeProcess(process: HANDLE, exit_code: *mut u32) -> i32;
+        pub fn CloseHandle(object: HANDLE) -> i32;
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| (*v).to_string())
+        }
+    }
+
+    #[test]
+    fn home_dir_prefers_home() {
+        let vars = [("HOME", "/home/me"), ("USERPROFILE", r"C:\Users\me")];
+        assert_eq!(home_dir_from(env(&vars)), Some(PathBuf::from("/home/me")));
+    }
+
+    #[test]
+    fn home_dir_ignores_empty_home() {
+        let vars = [("HOME", "")];
+        assert_eq!(home_dir_from(env(&vars)), None);
+    }
+
+    #[test]
+    fn home_dir_windows_fallbacks() {
+        let profile = [("USERPROFILE", r"C:\Users\me")];
+        let split = [("HOMEDRIVE", "D:"), ("HOMEPATH", r"\Users\me")];
+        if cfg!(windows) {
+            assert_eq!(
+                home_dir_from(env(&profile)),
+                Some(PathBuf::from(r"C:\Users\me"))
+            );
+            assert_eq!(
+                home_dir_from(env(&split)),
+                Some(PathBuf::from(r"D:\Users\me"))
+            );
+        } else {
+            assert_eq!(home_dir_from(env(&profile)), None);
+            assert_eq!(home_dir_from(env(&split)), None);
+        }
+    }
+
+    #[test]
+    fn own_pid_is_alive() {
+        assert!(is_pid_alive(std::process::id()));
+    }
+
+    #[test]
+    fn temp_dir_is_one_local_filesystem() {
+        let dir = tempfile::tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        std::fs::create_dir(&sub).unwrap();
+        assert_eq!(filesystem_id(dir.path()), filesystem_id(&sub));
+        assert!(filesystem_id(&dir.path().join("missing")).is_none());
+    }
+
+    #[test]
+    fn pager_color_args_only_for_less() {
+        assert_eq!(pager_color_args("less", &[]), &["-R"]);
+        assert_eq!(pager_color_args("/usr/bin/less", &[]), &["-R"]);
+        assert_eq!(pager_color_args(r"C:\tools\LESS.EXE", &[]), &["-R"]);
+        assert!(pager_color_args("more", &[]).is_empty());
+        assert!(pager_color_args("bat", &[]).is_empty());
+    }
+
+    #[test]
+    fn pager_color_args_respect_existing_flags() {
+        let args = |list: &[&str]| list.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert!(pager_color_args("less", &args(&["-FRX"])).is_empty());
+        assert!(pager_color_args("less", &args(&["-r"])).is_empty());
+        assert!(pager_color_args("less", &args(&["--RAW-CONTROL-CHARS"])).is_empty());
+        assert_eq!(pager_color_args("less", &args(&["-FX"])), &["-R"]);
+        assert_eq!(
+            pager_color_args("less", &args(&["--quit-if-one-screen"])),
+            &["-R"]
+        );
+    }
+
+    #[test]
+    fn split_words_handles_quotes_and_escapes() {
+        let split = |line| split_words(line).unwrap();
+        assert_eq!(split("less -FRX"), ["less", "-FRX"]);
+        assert_eq!(
+            split("  bat   --paging=always "),
+            ["bat", "--paging=always"]
+        );
+        assert_eq!(
+            split(r#"less '--prompt=page %d' "-P x\"y""#),
+            ["less", "--prompt=page %d", r#"-P x"y"#]
+        );
+        assert_eq!(split(r"my\ pager ''"), ["my pager", ""]);
+        assert_eq!(split(r"C:\tools\less.exe -R"), [r"C:\tools\less.exe", "-R"]);
+        assert!(split("").is_empty());
+        assert_eq!(split_words("less 'unclosed"), None);
+        assert_eq!(split_words(r#"less "unclosed"#), None);
+    }
+
+    #[test]
+    fn pager_commands_fall_back_to_less_then_more() {
+        let commands = |pager| {
+            pager_commands(pager)
+                .into_iter()
+                .map(|(program, args)| format!("{program} {}", args.join(" ")))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(commands(None), ["less -R", "more "]);
+        assert_eq!(commands(Some("")), ["less -R", "more "]);
+        assert_eq!(
+            commands(Some("less -FRX")),
+            ["less -FRX", "less -R", "more "]
+        );
+        assert_eq!(
+            commands(Some("bat --paging=always")),
+            ["bat --paging=always", "less -R", "more "]
+        );
+        assert_eq!(commands(Some("less")), ["less -R", "more "]);
+        assert_eq!(commands(Some("'unclosed")), ["less -R", "more "]);
+    }
+}
@@ -0,0 +1,363 @@
+//! Post-land hook: a user command the worker runs after an agent lands
+//! commits on main (e.g. to kick off or poll CI).
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::task::JoinHandle;
+
+use crate::worktree::{self, LandSummary};
+
+/// Default for `--post-land-timeout`.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// How many lines of hook output to show under the land.
+pub const PREVIEW_LINES: usize = 5;
+
+/// How many landed commits to list before summarizing the rest.
+pub const SUMMARY_COMMITS: usize = 10;
+
+/// Worker configuration for the hook (`--post-land` and friends).
+#[derive(Debug, Clone)]
+pub struct PostLandHook {
+    pub command: String,
+    /// Pause the worker when the hook fails instead of just warning.
+    pub required: bool,
+    pub timeout: Duration,
+}
+
+/// Main after an agent landed onto it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Landed {
+    pub main_path: PathBuf,
+    pub main_branch: String,
+    pub sha: String,
+    /// What the land brought in since `main_before`.
+    #[serde(default)]
+    pub summary: LandSummary,
+}
+
+/// Detect whether the worktree's commits were landed on main since
+/// `main_before` was recorded.
+///
+/// A land moves main to a commit the worktree's HEAD contains; main moving
+/// anywhere else means a peer landed, which isn't ours to report.
+pub fn detect_land(worktree_path: &Path, main_before: &str) -> Result<Option<Landed>> {
+    let main = worktree::main_head(worktree_path)?;
+    if main.sha == main_before || !worktree::head_contains(worktree_path, &main.sha)? {
+        return Ok(None);
+    }
+    let summary = worktree::land_summary(worktree_path, main_before, &main.sha)?;
+    Ok(Some(Landed {
+        main_path: main.path,
+        main_branch: main.branch,
+        sha: main.sha,
+        summary,
+    }))
+}
+
+/// Everything the hook is told about the land, passed as `COVEN_*` variables.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LandInfo {
+    pub branch: String,
+    pub main_branch: String,
+    pub landed_sha: String,
+    pub agent: String,
+    /// Cost of the agent phase that landed, in USD.
+    pub cost: f64,
+}
+
+impl LandInfo {
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("COVEN_BRANCH", self.branch.clone()),
+            ("COVEN_MAIN_BRANCH", self.main_branch.clone()),
+            ("COVEN_LANDED_SHA", self.landed_sha.clone()),
+            ("COVEN_AGENT", self.agent.clone()),
+            ("COVEN_COST", format!("{:.4}", self.cost)),
+        ]
+    }
+}
+
+/// How the hook finished.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HookStatus {
+    Success,
+    /// Nonzero exit; `None` when killed by a signal.
+    Failed(Option<i32>),
+    TimedOut,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HookOutput {
+    pub status: HookStatus,
+    /// Combined stdout and stderr.
+    pub output: String,
+}
+
+impl HookOutput {
+    /// One-line description of a failure, `None` on success.
+    pub fn failure(&self, timeout: Duration) -> Option<String> {
+        match self.status {
+            HookStatus::Success => None,
+            HookStatus::Failed(Some(code)) => {
+                Some(format!("post-land hook exited with code {code}"))
+            }
+            HookStatus::Failed(None) => Some("post-land hook was killed by a signal".to_string()),
+            HookStatus::TimedOut => Some(format!(
+                "post-land hook timed out after {}s",
+                timeout.as_secs()
+            )),
+        }
+    }
+}
+
+/// Arguments for the VCR-recorded hook run.
+#[derive(Serialize, Deserialize)]
+pub struct RunArgs {
+    pub command: String,
+    pub dir: PathBuf,
+    pub info: LandInfo,
+    pub timeout_secs: u64,
+}
+
+/// Kills a hook's process group when dropped, so whatever the hook started
+/// dies with it instead of only the shell running it.
+struct KillGroupOnDrop(Option<u32>);
+
+impl Drop for KillGroupOnDrop {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            crate::platform::kill_process_group(pid);
+        }
+    }
+}
+
+/// How long to keep reading a hook's output after it exits. Its pipes close
+/// right away unless something it left in the background still holds them.
+const PIPE_GRACE: Duration = Duration::from_millis(100);
+
+/// Reads one of the hook's output pipes in the background, so the hook is
+/// done when it exits rather than when every process holding the pipe does.
+struct PipeReader {
+    buf: Arc<Mutex<Vec<u8>>>,
+    task: JoinHandle<()>,
+}
+
+impl PipeReader {
+    fn spawn(pipe: Option<impl AsyncRead + Unpin + Send + 'static>) -> Self {
+        let buf = Arc::new(Mutex::new(Vec::new()));
+        let shared = Arc::clone(&buf);
+        let task = tokio::spawn(async move {
+            let Some(mut pipe) = pipe else { return };
+            let mut chunk = [0; 4096];
+            while let Ok(n @ 1..) = pipe.read(&mut chunk).await {
+                shared
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .extend_from_slice(&chunk[..n]);
+            }
+        });
+        Self { buf, task }
+    }
+
+    /// Everything read so far, once the pipe closes or `PIPE_GRACE` passes.
+    async fn finish(mut self) -> String {
+        let _ = tokio::time::timeout(PIPE_GRACE, &mut self.task).await;
+        let buf = self.buf.lock().unwrap_or_else(PoisonError::into_inner);
+        String::from_utf8_lossy(&buf).into_owned()
+    }
+}
+
+impl Drop for PipeReader {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Run the hook through the platform shell in `dir`, killing it and
+/// everything it started if it outlives `timeout`.
+pub async fn run(
+    command: &str,
+    dir: &Path,
+    info: &LandInfo,
+    timeout: Duration,
+) -> Result<HookOutput> {
+    let (shell, flag) = crate::platform::shell();
+    let mut hook = tokio::process::Command::new(shell);
+    hook.arg(flag)
+        .arg(command)
+        .current_dir(dir)
+        .envs(info.env())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    crate::platform::own_process_group(&mut hook);
+    let mut child = hook
+        .spawn()
+        .with_context(|| format!("failed to spawn post-land hook: {command}"))?;
+    // Also covers this future being dropped mid-run (e.g. on shutdown).
+    let mut group = KillGroupOnDrop(child.id());
+    let stdout = PipeReader::spawn(child.stdout.take());
+    let stderr = PipeReader::spawn(child.stderr.take());
+
+    let Ok(status) = tokio::time::timeout(timeout, child.wait()).await else {
+        return Ok(HookOutput {
+            status: HookStatus::TimedOut,
+            output: String::new(),
+        });
+    };
+    // The hook finished; anything it left running in the background is its
+    // own business.
+    group.0 = None;
+    let status = status.context("failed to wait for post-land hook")?;
+
+    let mut text = stdout.finish().await;
+    text.push_str(&stderr.finish().await);
+    let status = if status.success() {
+        HookStatus::Success
+    } else {
+        HookStatus::Failed(status.code())
+    };
+    Ok(HookOutput {
+        status,
+        output: text,
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn info() -> LandInfo {
+        LandInfo {
+            branch: "swift-fox-3".into(),
+            main_branch: "main".into(),
+            landed_sha: "abc123".into(),
+            agent: "main".into(),
+            cost: 0.12345,
+        }
+    }
+
+    #[test]
+    fn env_describes_land() {
+        let env = info().env();
+        assert_eq!(
+            env,
+            vec![
+                ("COVEN_BRANCH", "swift-fox-3".to_string()),
+                ("COVEN_MAIN_BRANCH", "main".to_string()),
+                ("COVEN_LANDED_SHA", "abc123".to_string()),
+                ("COVEN_AGENT", "main".to_string()),
+                ("COVEN_COST", "0.1235".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn failure_messages() {
+        let out = |status| HookOutput {
+            status,
+            output: String::new(),
+        };
+        let t = Duration::from_secs(30);
+        assert_eq!(out(HookStatus::Success).failure(t), None);
+        assert_eq!(
+            out(HookStatus::Failed(Some(2))).failure(t).unwrap(),
+            "post-land hook exited with code 2"
+        );
+        assert_eq!(
+            out(HookStatus::TimedOut).failure(t).unwrap(),
+            "post-land hook timed out after 30s"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn hook_receives_land_env() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let out = run(
+            "echo \"$COVEN_BRANCH $COVEN_MAIN_BRANCH $COVEN_LANDED_SHA $COVEN_AGENT $COVEN_COST\"; pwd",
+            dir.path(),
+            &info(),
+            Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert_eq!(out.status, HookStatus::Success);
+        let mut lines = out.output.lines();
+        assert_eq!(lines.next(), Some("swift-fox-3 main abc123 main 0.1235"));
+        let pwd = Path::new(lines.next().unwrap()).canonicalize().unwrap();
+        assert_eq!(pwd, dir.path().canonicalize().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn hook_failure_keeps_output() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let out = run(
+            "echo ci red >&2; exit 3",
+            dir.path(),
+            &info(),
+            Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert_eq!(out.status, HookStatus::Failed(Some(3)));
+        assert_eq!(out.output, "ci red\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn hook_returns_when_it_exits_despite_background_children() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let start = std::time::Instant::now();
+        let out = run(
+            "sleep 30 & echo started",
+            dir.path(),
+            &info(),
+            Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert_eq!(out.status, HookStatus::Success);
+        assert_eq!(out.output, "started\n");
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn hook_times_out() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let start = std::time::Instant::now();
+        let out = run("sleep 30", dir.path(), &info(), Duration::from_millis(200))
+            .await
+            .unwrap();
+        assert_eq!(out.status, HookStatus::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn hook_timeout_kills_its_children() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let out = run(
+            "(sleep 1; touch survived) & wait",
+            dir.path(),
+            &info(),
+            Duration::from_millis(200),
+        )
+        .await
+        .unwrap();
+        assert_eq!(out.status, HookStatus::TimedOut);
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!dir.path().join("survived").exists());
+    }
+}
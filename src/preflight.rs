@@ -0,0 +1,390 @@
+//! Connectivity preflight (`--preflight-connectivity`): check that the API
+//! host is reachable before spawning claude, and back off while it isn't.
+
+use std::io::Write;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use crossterm::event::Event;
+
+use crate::display::input::{InputAction, InputHandler};
+use crate::display::renderer::Renderer;
+use crate::session::event_loop;
+use crate::session::state::SessionState;
+use crate::vcr::{Io, IoEvent, VcrContext};
+
+/// How long resolving and connecting to the API host may take.
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+const BACKOFF_BASE: Duration = Duration::from_secs(5);
+const BACKOFF_MAX: Duration = Duration::from_mins(5);
+
+/// How many temporary API errors in a row loops retry before giving up.
+pub const MAX_API_RETRIES: u32 = 5;
+
+/// Where the connectivity check should connect, given a lookup of
+/// environment variables, or `None` when it can't tell and should be skipped.
+///
+/// With Bedrock or Vertex (`CLAUDE_CODE_USE_BEDROCK`/`_VERTEX`) claude talks
+/// to a cloud endpoint coven doesn't know, so there's nothing to check.
+/// Behind a proxy (`HTTPS_PROXY`, else `ALL_PROXY`) the proxy is what has to
+/// be reachable, unless `NO_PROXY` exempts the API host.
+pub fn probe_address(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let var = |name: &str| {
+        var(name)
+            .or_else(|| var(&name.to_ascii_lowercase()))
+            .filter(|v| !v.trim().is_empty())
+    };
+    let enabled = |name: &str| var(name).is_some_and(|v| !matches!(v.trim(), "0" | "false"));
+    if enabled("CLAUDE_CODE_USE_BEDROCK") || enabled("CLAUDE_CODE_USE_VERTEX") {
+        return None;
+    }
+    let api = api_address(var("ANTHROPIC_BASE_URL").as_deref());
+    let proxy = var("HTTPS_PROXY").or_else(|| var("ALL_PROXY"));
+    match proxy {
+        Some(proxy) if !no_proxy_matches(var("NO_PROXY").as_deref(), &api) => {
+            Some(authority(&proxy, 1080))
+        }
+        _ => Some(api),
+    }
+}
+
+/// `host:port` of the API, from `ANTHROPIC_BASE_URL` if set.
+pub fn api_address(base_url: Option<&str>) -> String {
+    match base_url.map(str::trim).filter(|u| !u.is_empty()) {
+        Some(url) if url.starts_with("http://") => authority(url, 80),
+        Some(url) => authority(url, 443),
+        None => "api.anthropic.com:443".to_string(),
+    }
+}
+
+/// The `host:port` a URL points at, with `default_port` if it names none.
+/// Any scheme and credentials are dropped.
+fn authority(url: &str, default_port: u16) -> String {
+    let rest = url.trim().split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split('/').next().unwrap_or(rest);
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host_has_port = authority
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+    if host_has_port {
+        authority.to_string()
+    } else {
+        format!("{authority}:{default_port}")
+    }
+}
+
+/// Whether a `NO_PROXY` list exempts `address` (`host:port`): `*`, the host
+/// itself, or a domain it's under.
+fn no_proxy_matches(no_proxy: Option<&str>, address: &str) -> bool {
+    let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+    no_proxy
+        .into_iter()
+        .flat_map(|list| list.split(','))
+        .any(|entry| {
+            let entry = entry.trim().trim_start_matches('.');
+            entry == "*"
+                || entry.eq_ignore_ascii_case(host)
+                || host
+                    .to_ascii_lowercase()
+                    .ends_with(&format!(".{}", entry.to_ascii_lowercase()))
+        })
+}
+
+/// Resolve `address` and open a TCP connection to it. Costs nothing and
+/// needs no credentials, unlike a claude session.
+pub async fn check(address: &str, timeout: Duration) -> Result<()> {
+    let connect = async {
+        let addrs = tokio::net::lookup_host(address)
+            .await
+            .with_context(|| format!("can't resolve {address}"))?;
+        let mut last_err = anyhow!("{address} resolved to no addresses");
+        for addr in addrs {
+            match tokio::net::TcpStream::connect(addr).await {
+                Ok(_) => return Ok(()),
+                Err(e) => last_err = anyhow!(e).context(format!("can't connect to {address}")),
+            }
+        }
+        Err(last_err)
+    };
+    tokio::time::timeout(timeout, connect)
+        .await
+        .map_err(|_| anyhow!("timed out connecting to {address}"))?
+}
+
+/// Exponential backoff between connectivity failures: 5s, 10s, 20s, … up
+/// to 5 minutes. A success resets it.
+#[derive(Debug, Default)]
+pub struct Backoff {
+    failures: u32,
+}
+
+impl Backoff {
+    /// Record a failure: a failed preflight, or a session that ended with an
+    /// error result.
+    pub fn record_failure(&mut self) {
+        self.failures = self.failures.saturating_add(1);
+    }
+
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+    }
+
+    /// Record how a claude session ended.
+    pub fn record_session(&mut self, state: &SessionState) {
+        if state.result_error.is_some() || state.failed_retryably() {
+            self.record_failure();
+        } else {
+            self.record_success();
+        }
+    }
+
+    /// How long to wait before the next attempt, if the last one failed.
+    pub fn delay(&self) -> Option<Duration> {
+        let doublings = self.failures.checked_sub(1)?;
+        Some(
+            BACKOFF_BASE
+                .checked_mul(1 << doublings.min(16))
+                .map_or(BACKOFF_MAX, |d| d.min(BACKOFF_MAX)),
+        )
+    }
+}
+
+/// Wait out any pending backoff, then (if `preflight` is set) check
+/// connectivity, repeating until the check passes. Returns false if the
+/// user exited while waiting.
+pub async fn wait_until_online<W: Write>(
+    preflight: bool,
+    backoff: &mut Backoff,
+    renderer: &mut Renderer<W>,
+    input: &mut InputHandler,
+    io: &mut Io,
+    vcr: &VcrContext,
+) -> Result<bool> {
+    loop {
+        if let Some(delay) = backoff.delay() {
+            renderer.render_backoff(delay.as_secs());
+            if !wait_interruptible(delay, renderer, input, io, vcr).await? {
+                return Ok(false);
+            }
+        }
+        if !preflight {
+            return Ok(true);
+        }
+        let failure = vcr
+            .call("preflight_connectivity", (), async |(): &()| {
+                let Some(address) = probe_address(|name| std::env::var(name).ok()) else {
+                    return Ok(None);
+                };
+                Ok(check(&address, CHECK_TIMEOUT)
+                    .await
+                    .err()
+                    .map(|e| format!("{e:#}")))
+            })
+            .await?;
+        match failure {
+            None => return Ok(true),
+            Some(error) => {
+                renderer.render_offline(&error);
+                backoff.record_failure();
+            }
+        }
+    }
+}
+
+/// Sleep for `delay` while still handling keys. Returns false if the user
+/// pressed Ctrl+C or Ctrl+D, or shutdown was requested.
+async fn wait_interruptible<W: Write>(
+    delay: Duration,
+    renderer: &mut Renderer<W>,
+    input: &mut InputHandler,
+    io: &mut Io,
+    vcr: &VcrContext,
+) -> Result<bool> {
+    let deadline = tokio::time::Instant::now() + delay;
+    io.clear_event_channel();
+    loop {
+        let event = vcr
+            .call("backoff_wait", (), async |(): &()| {
+                tokio::select! {
+                    () = tokio::time::sleep_until(deadline) => Ok(None),
+                    event = io.next_event() => event.map(Some),
+                }
+            })
+            .await?;
+        let Some(event) = event else {
+            return Ok(true);
+        };
+        if matches!(event, IoEvent::Shutdown) {
+            io.note_shutdown();
+            return Ok(false);
+        }
+        if let IoEvent::Terminal(Event::Key(key_event)) = event {
+            let action = input.handle_key(&key_event, renderer.writer());
+            match event_loop::record_note(action, renderer, vcr).await? {
+                InputAction::Interrupt | InputAction::EndSession => return Ok(false),
+                InputAction::ViewMessage(ref query) => {
+                    event_loop::view_message(renderer, query, io)?;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::protocol::types::ApiError;
+
+    #[test]
+    fn api_address_defaults_and_overrides() {
+        assert_eq!(api_address(None), "api.anthropic.com:443");
+        assert_eq!(api_address(Some("")), "api.anthropic.com:443");
+        assert_eq!(
+            api_address(Some("https://proxy.example.com/v1")),
+            "proxy.example.com:443"
+        );
+        assert_eq!(api_address(Some("http://localhost:8080")), "localhost:8080");
+        assert_eq!(api_address(Some("http://gateway")), "gateway:80");
+    }
+
+    fn probe(vars: &[(&str, &str)]) -> Option<String> {
+        probe_address(|name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| (*v).to_string())
+        })
+    }
+
+    #[test]
+    fn probe_address_honours_proxies_and_cloud_providers() {
+        assert_eq!(probe(&[]).as_deref(), Some("api.anthropic.com:443"));
+        assert_eq!(probe(&[("CLAUDE_CODE_USE_BEDROCK", "1")]), None);
+        assert_eq!(probe(&[("CLAUDE_CODE_USE_VERTEX", "true")]), None);
+        assert_eq!(
+            probe(&[("CLAUDE_CODE_USE_BEDROCK", "0")]).as_deref(),
+            Some("api.anthropic.com:443")
+        );
+        assert_eq!(
+            probe(&[("https_proxy", "http://user:pw@proxy.corp:3128")]).as_deref(),
+            Some("proxy.corp:3128")
+        );
+        assert_eq!(
+            probe(&[("ALL_PROXY", "socks5://localhost")]).as_deref(),
+            Some("localhost:1080")
+        );
+        assert_eq!(
+            probe(&[
+                ("HTTPS_PROXY", "http://proxy.corp:3128"),
+                ("NO_PROXY", "localhost,.anthropic.com"),
+            ])
+            .as_deref(),
+            Some("api.anthropic.com:443")
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_cap_and_resets() {
+        let mut backoff = Backoff::default();
+        assert_eq!(backoff.delay(), None);
+        let delays: Vec<u64> = (0..8)
+            .map(|_| {
+                backoff.record_failure();
+                backoff.delay().unwrap().as_secs()
+            })
+            .collect();
+        assert_eq!(delays, [5, 10, 20, 40, 80, 160, 300, 300]);
+        backoff.record_success();
+        assert_eq!(backoff.delay(), None);
+    }
+
+    #[test]
+    fn backoff_survives_many_failures() {
+        let mut backoff = Backoff { failures: u32::MAX };
+        backoff.record_failure();
+        assert_eq!(backoff.delay(), Some(BACKOFF_MAX));
+    }
+
+    #[test]
+    fn retryable_api_error_counts_as_failure() {
+        let error = |status: u16| ApiError {
+            status: Some(status),
+            message: "m".to_string(),
+        };
+        let mut backoff = Backoff::default();
+        let mut state = SessionState {
+            api_error: Some(Box::new(error(429))),
+            ..Default::default()
+        };
+        backoff.record_session(&state);
+        assert_eq!(backoff.delay(), Some(BACKOFF_BASE));
+
+        state.api_error = Some(Box::new(error(401)));
+        backoff.record_session(&state);
+        assert_eq!(backoff.delay(), None);
+    }
+
+    /// Replay context with a failed preflight, the backoff elapsing, then a
+    /// passing preflight.
+    fn offline_then_online() -> VcrContext {
+        let ndjson = [
+            serde_json::json!({
+                "label": "preflight_connectivity",
+                "args": null,
+                "result": {"Ok": "can't resolve api.anthropic.com:443"},
+            }),
+            serde_json::json!({"label": "backoff_wait", "args": null, "result": {"Ok": null}}),
+            serde_json::json!({"label": "preflight_connectivity", "args": null, "result": {"Ok": null}}),
+        ]
+        .map(|entry| entry.to_string());
+        VcrContext::replay(&ndjson.join("\n")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn skips_spawn_and_retries_until_online() {
+        let vcr = offline_then_online();
+        let mut renderer = Renderer::with_writer(Vec::new());
+        let mut input = InputHandler::new(2);
+        let mut io = Io::dummy();
+        let mut backoff = Backoff::default();
+        let online =
+            wait_until_online(true, &mut backoff, &mut renderer, &mut input, &mut io, &vcr)
+                .await
+                .unwrap();
+        assert!(online);
+        assert!(vcr.unreplayed_labels().is_empty());
+        let out = String::from_utf8(renderer.writer().clone()).unwrap();
+        assert!(out.contains("can't resolve api.anthropic.com:443"), "{out}");
+        assert!(out.contains("retrying in 5s"), "{out}");
+    }
+
+    #[tokio::test]
+    async fn disabled_preflight_only_waits_out_backoff() {
+        let vcr = VcrContext::replay(
+            &serde_json::json!({"label": "backoff_wait", "args": null, "result": {"Ok": null}})
+                .to_string(),
+        )
+        .unwrap();
+        let mut renderer = Renderer::with_writer(Vec::new());
+        let mut input = InputHandler::new(2);
+        let mut io = Io::dummy();
+        let mut backoff = Backoff::default();
+        backoff.record_failure();
+        let online = wait_until_online(
+            false,
+            &mut backoff,
+            &mut renderer,
+            &mut input,
+            &mut io,
+            &vcr,
+        )
+        .await
+        .unwrap();
+        assert!(online);
+        assert!(vcr.unreplayed_labels().is_empty());
+    }
+}
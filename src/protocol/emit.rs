@@ -16,6 +16,16 @@ pub fn format_user_message(text: &str) -> serde_json::Result<String> {
     serde_json::to_string(&msg)
 }
 
+/// Check that `text` is a single JSON object, as required of a `:raw` line.
+///
+/// # Errors
+///
+/// Returns the parse error if `text` is malformed, not an object, or has
+/// anything after the object.
+pub fn check_raw_message(text: &str) -> serde_json::Result<()> {
+    serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(text).map(drop)
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -36,4 +46,12 @@ mod tests {
         let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
         assert_eq!(parsed["message"]["content"], "hello \"world\"\nnewline");
     }
+
+    #[test]
+    fn raw_message_must_be_one_object() {
+        assert!(check_raw_message(r#"{"type":"control_request"}"#).is_ok());
+        for bad in ["", "[1]", r#""type""#, "{\"type\":}", "{} {}"] {
+            assert!(check_raw_message(bad).is_err(), "{bad}");
+        }
+    }
 }
@@ -53,6 +53,8 @@ pub fn parse_line(line: &str) -> Result<Option<InboundEvent>> {
 #[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
+    use crate::protocol::types::{ApiError, ApiErrorKind, ResultSubtype};
+    use crate::session::state::TokenUsage;
 
     #[test]
     fn extract_tag_basic() {
@@ -116,6 +118,111 @@ mod tests {
         assert!(parse_line(line).is_ok());
     }
 
+    /// A result event from claude 2.1 with permission denials and a
+    /// per-model usage breakdown.
+    const RESULT_WITH_DENIALS: &str = r#"{"type":"result","subtype":"success","is_error":false,"duration_ms":57979,"duration_api_ms":58443,"num_turns":14,"result":"Should I proceed with resolving the conflict?","session_id":"bca5da0c-1f12-4ed0-acee-c566484e67ed","total_cost_usd":0.06383910000000001,"usage":{"input_tokens":114,"cache_creation_input_tokens":7958,"cache_read_input_tokens":325556,"output_tokens":3569,"server_tool_use":{"web_search_requests":0,"web_fetch_requests":0},"service_tier":"standard","cache_creation":{"ephemeral_1h_input_tokens":7958,"ephemeral_5m_input_tokens":0},"inference_geo":"","iterations":[],"speed":"standard"},"modelUsage":{"claude-haiku-4-5-20251001":{"inputTokens":2626,"outputTokens":3742,"cacheReadInputTokens":325556,"cacheCreationInputTokens":7958,"webSearchRequests":0,"costUSD":0.06383910000000001,"contextWindow":200000,"maxOutputTokens":32000}},"permission_denials":[{"tool_name":"Bash","tool_use_id":"toolu_01PbAMZM85zGKr6HBgaUno1e","tool_input":{"command":"git checkout --ours CONTRIBUTING.md && git add CONTRIBUTING.md && git rebase --continue","description":"Resolve conflict by keeping main branch version and continue rebase"}},{"tool_name":"Bash","tool_use_id":"toolu_01FfHaJaxFqr6ARjkjC4Eg9z","tool_input":{"command":"git checkout --ours CONTRIBUTING.md","description":"Keep the main branch version of CONTRIBUTING.md in conflict resolution"}}],"uuid":"ed9befe1-8864-4066-8b11-48ad4f842119"}"#;
+
+    fn parse_result(line: &str) -> crate::protocol::types::SessionResult {
+        let event = parse_line(line).unwrap();
+        let result = match event {
+            Some(InboundEvent::Result(result)) => Some(result),
+            _ => None,
+        };
+        result.unwrap()
+    }
+
+    #[test]
+    fn parse_result_denials_and_model_usage() {
+        let result = parse_result(RESULT_WITH_DENIALS);
+        let tools: Vec<_> = result
+            .permission_denials
+            .iter()
+            .map(|d| {
+                (
+                    d.tool_name.as_str(),
+                    d.tool_input["command"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            tools,
+            [
+                (
+                    "Bash",
+                    "git checkout --ours CONTRIBUTING.md && git add CONTRIBUTING.md && git rebase --continue"
+                ),
+                ("Bash", "git checkout --ours CONTRIBUTING.md"),
+            ]
+        );
+        assert_eq!(
+            result.permission_denials[1].tool_use_id,
+            "toolu_01FfHaJaxFqr6ARjkjC4Eg9z"
+        );
+        let haiku = &result.model_usage["claude-haiku-4-5-20251001"];
+        assert_eq!((haiku.input_tokens, haiku.output_tokens), (2626, 3742));
+        assert_eq!(haiku.cache_read_input_tokens, 325_556);
+        assert_eq!(haiku.cache_creation_input_tokens, 7958);
+        assert!((haiku.cost_usd - 0.0638).abs() < 1e-4);
+    }
+
+    #[test]
+    fn parse_result_usage() {
+        let result = parse_result(RESULT_WITH_DENIALS);
+        let usage = TokenUsage::from(result.usage.as_ref().unwrap());
+        assert_eq!(
+            usage,
+            TokenUsage {
+                fresh_input: 114,
+                cache_read: 325_556,
+                cache_creation: 7958,
+                output: 3569,
+            }
+        );
+        assert_eq!(usage.summary(), "333k in (98% cached) · 3.5k out");
+    }
+
+    #[test]
+    fn result_without_denials_or_model_usage() {
+        let result = parse_result(r#"{"type":"result","subtype":"success","result":"ok"}"#);
+        assert!(result.permission_denials.is_empty());
+        assert!(result.model_usage.is_empty());
+    }
+
+    #[test]
+    fn result_subtypes_map_to_labels() {
+        let subtype = |line: &str| ResultSubtype::parse(&parse_result(line).subtype);
+        let max_turns = subtype(r#"{"type":"result","subtype":"error_max_turns","is_error":true}"#);
+        assert_eq!(max_turns, ResultSubtype::MaxTurns);
+        assert_eq!(max_turns.label(), Some("Max turns reached"));
+        let cases = [
+            ("success", ResultSubtype::Success, Some("Done")),
+            (
+                "error_during_execution",
+                ResultSubtype::ExecutionError,
+                Some("Execution error"),
+            ),
+            (
+                "error_max_budget_usd",
+                ResultSubtype::MaxBudget,
+                Some("Budget exhausted"),
+            ),
+            ("error_rate_limited", ResultSubtype::Unknown, None),
+        ];
+        for (raw, kind, label) in cases {
+            assert_eq!(ResultSubtype::parse(raw), kind, "{raw}");
+            assert_eq!(kind.label(), label, "{raw}");
+        }
+    }
+
+    #[test]
+    fn reshaped_denials_and_model_usage_are_dropped_not_fatal() {
+        let line = r#"{"type":"result","subtype":"success","result":"ok","num_turns":2,"permission_denials":{"count":1},"modelUsage":[{"model":"claude-opus"}]}"#;
+        let result = parse_result(line);
+        assert!(result.permission_denials.is_empty());
+        assert!(result.model_usage.is_empty());
+        assert_eq!(result.num_turns, 2);
+    }
+
     #[test]
     fn parse_rate_limit_event() {
         let line = r#"{"type":"rate_limit_event","rate_limit_info":{"status":"allowed_warning","resetsAt":1771545600,"rateLimitType":"seven_day","utilization":0.76,"isUsingOverage":false,"surpassedThreshold":0.75},"uuid":"e79d3169-e675-4aef-9400-8403f2237090","session_id":"bb1caa74-b643-4163-ba7d-8f6749891cc3"}"#;
@@ -126,6 +233,7 @@ mod tests {
                 assert_eq!(rl.rate_limit_info.rate_limit_type, "seven_day");
                 assert!((rl.rate_limit_info.utilization - 0.76).abs() < f64::EPSILON);
                 assert!(rl.rate_limit_info.is_warning());
+                assert_eq!(rl.rate_limit_info.resets_at, Some(1_771_545_600));
             }
             other => panic!("Expected RateLimit, got {other:?}"),
         }
@@ -142,4 +250,140 @@ mod tests {
             other => panic!("Expected RateLimit, got {other:?}"),
         }
     }
+
+    /// The API error of a result line shaped like claude's, which reports
+    /// the status in `api_error_status` and the message as the result.
+    fn parse_api_error(status: Option<u16>, message: &str) -> Option<ApiError> {
+        let line = serde_json::json!({
+            "type": "result",
+            "subtype": "success",
+            "is_error": true,
+            "api_error_status": status,
+            "terminal_reason": "api_error",
+            "result": message,
+            "session_id": "x",
+        });
+        match parse_line(&line.to_string()).unwrap().unwrap() {
+            InboundEvent::Result(result) => Some(result),
+            _ => None,
+        }
+        .unwrap()
+        .api_error()
+    }
+
+    #[test]
+    fn parse_overloaded_error() {
+        let message = r#"API Error: 529 {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        let error = parse_api_error(Some(529), message).unwrap();
+        assert_eq!(error.message, message);
+        assert_eq!(error.kind(), ApiErrorKind::Overloaded);
+        assert!(error.is_retryable());
+    }
+
+    #[test]
+    fn parse_rate_limit_error() {
+        let error = parse_api_error(Some(429), "API Error: 429 rate limited").unwrap();
+        assert_eq!(error.kind(), ApiErrorKind::RateLimited);
+        assert!(error.is_retryable());
+    }
+
+    #[test]
+    fn parse_server_error() {
+        let error = parse_api_error(Some(500), "API Error: 500 Internal server error").unwrap();
+        assert_eq!(error.kind(), ApiErrorKind::Server);
+        assert!(error.is_retryable());
+    }
+
+    #[test]
+    fn parse_context_length_error() {
+        let error = parse_api_error(Some(400), "Prompt is too long").unwrap();
+        assert_eq!(error.kind(), ApiErrorKind::ContextLength);
+        assert!(!error.is_retryable());
+    }
+
+    #[test]
+    fn parse_invalid_request_error() {
+        let error = parse_api_error(
+            Some(400),
+            "API Error: 400 messages: text content blocks must be non-empty",
+        )
+        .unwrap();
+        assert_eq!(error.kind(), ApiErrorKind::Fatal);
+        assert!(!error.is_retryable());
+    }
+
+    #[test]
+    fn parse_authentication_error() {
+        let error = parse_api_error(
+            Some(403),
+            "Failed to authenticate. API Error: 403 model not permitted",
+        )
+        .unwrap();
+        assert_eq!(error.kind(), ApiErrorKind::Fatal);
+    }
+
+    #[test]
+    fn parse_api_error_without_status() {
+        let error = parse_api_error(None, "API Error: Connection error.").unwrap();
+        assert_eq!(error.status, None);
+        assert_eq!(error.kind(), ApiErrorKind::Fatal);
+    }
+
+    #[test]
+    fn other_error_results_are_not_api_errors() {
+        let line = r#"{"type":"result","subtype":"error_max_turns","is_error":true,"result":"","session_id":"x"}"#;
+        let InboundEvent::Result(result) = parse_line(line).unwrap().unwrap() else {
+            unreachable!("not a result: {line}");
+        };
+        assert_eq!(result.api_error(), None);
+    }
+
+    fn parse_delta(line: &str) -> crate::protocol::types::Delta {
+        match parse_line(line).unwrap() {
+            Some(InboundEvent::StreamEvent(se)) => se.event.delta.unwrap(),
+            other => unreachable!("expected a stream event, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_signature_delta() {
+        let delta = parse_delta(
+            r#"{"type":"stream_event","event":{"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"EqQBCkgIBxABGAIqQKq3ZQn0XyHc9vAq1tFf"}},"session_id":"4c5b1e3a-2f1d-4a7e-9a39-0b8f7d2e6c11","parent_tool_use_id":null,"uuid":"d2a1b7c4-5e6f-4a8b-9c0d-1e2f3a4b5c6d"}"#,
+        );
+        assert_eq!(delta.r#type, "signature_delta");
+        assert!(delta.text.is_none() && delta.thinking.is_none());
+    }
+
+    #[test]
+    fn unknown_delta_types_parse_and_keep_their_fields() {
+        let line = r#"{"type":"stream_event","event":{"type":"content_block_delta","index":1,"delta":{"type":"citations_delta","citation":{"type":"char_location","cited_text":"Alpha is the first letter","document_index":0}}},"session_id":"4c5b1e3a-2f1d-4a7e-9a39-0b8f7d2e6c11","parent_tool_use_id":null}"#;
+        let event = parse_line(line).unwrap().unwrap();
+        let InboundEvent::StreamEvent(se) = &event else {
+            unreachable!("expected a stream event, got {event:?}");
+        };
+        assert_eq!(se.event.delta.as_ref().unwrap().r#type, "citations_delta");
+
+        // Round-trips (as recordings do) with the unknown fields intact.
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            value["event"]["delta"]["citation"]["cited_text"],
+            "Alpha is the first letter"
+        );
+        let reparsed: InboundEvent = serde_json::from_value(value).unwrap();
+        assert!(matches!(reparsed, InboundEvent::StreamEvent(_)));
+    }
+
+    #[test]
+    fn unknown_content_block_types_parse() {
+        let event = parse_line(
+            r#"{"type":"stream_event","event":{"type":"content_block_start","index":2,"content_block":{"type":"server_tool_use","id":"srvtoolu_01","name":"web_search","input":{}}},"session_id":"4c5b1e3a-2f1d-4a7e-9a39-0b8f7d2e6c11","parent_tool_use_id":null}"#,
+        )
+        .unwrap();
+        let Some(InboundEvent::StreamEvent(se)) = event else {
+            unreachable!("expected a stream event");
+        };
+        let block = se.event.content_block.unwrap();
+        assert_eq!(block.r#type, "server_tool_use");
+        assert_eq!(block.name.as_deref(), Some("web_search"));
+    }
 }
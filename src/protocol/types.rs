@@ -1,4 +1,6 @@
-use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
 /// Top-level inbound event from claude's stream-json output.
@@ -33,7 +35,7 @@ pub enum SystemEvent {
     Other,
 }
 
-/// The init event also carries `cwd`, `permissionMode`, `claude_code_version`,
+/// The init event also carries `permissionMode`, `claude_code_version`,
 /// `mcp_servers`, `plugins`, `agents`, `skills`, and more (silently ignored).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InitEvent {
@@ -41,6 +43,8 @@ pub struct InitEvent {
     pub session_id: String,
     #[serde(default)]
     pub model: String,
+    #[serde(default)]
+    pub cwd: String,
     #[serde(default, rename = "tools")]
     _tools: Vec<Value>,
 }
@@ -49,6 +53,8 @@ pub struct InitEvent {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamEvent {
     pub event: StreamEventPayload,
+    #[serde(default)]
+    pub parent_tool_use_id: Option<String>,
     #[serde(flatten)]
     _extra: Value,
 }
@@ -80,6 +86,9 @@ pub struct ContentBlock {
     _extra: Value,
 }
 
+/// A `content_block_delta` payload. The type stays a plain string, so delta
+/// types coven doesn't know (say `citations_delta`) still parse, with their
+/// fields kept in `_extra`, and the renderer ignores them.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Delta {
     #[serde(default)]
@@ -92,6 +101,9 @@ pub struct Delta {
     pub thinking: Option<String>,
     #[serde(default, rename = "stop_reason")]
     _stop_reason: Option<String>,
+    /// A thinking block's signature (`signature_delta`); not shown.
+    #[serde(default, rename = "signature")]
+    _signature: Option<String>,
     #[serde(flatten)]
     _extra: Value,
 }
@@ -161,12 +173,139 @@ pub struct SessionResult {
     pub duration_ms: u64,
     #[serde(default)]
     pub result: String,
+    #[serde(default)]
+    pub is_error: bool,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    /// Tool calls the CLI refused during the run.
+    #[serde(default, deserialize_with = "lenient")]
+    pub permission_denials: Vec<PermissionDenial>,
+    /// Usage broken down by model, keyed by model ID.
+    #[serde(default, rename = "modelUsage", deserialize_with = "lenient")]
+    pub model_usage: BTreeMap<String, ModelUsage>,
+    /// HTTP status of the API error that ended the turn, if one did.
+    #[serde(default)]
+    pub api_error_status: Option<u16>,
+    /// Why the turn ended, e.g. `api_error`.
+    #[serde(default)]
+    pub terminal_reason: Option<String>,
     #[serde(default, rename = "session_id")]
     _session_id: String,
     #[serde(flatten)]
     _extra: Value,
 }
 
+impl SessionResult {
+    /// The API error that ended the turn. claude reports one as an
+    /// `is_error` result whose text is the error message.
+    pub fn api_error(&self) -> Option<ApiError> {
+        let api_failed =
+            self.api_error_status.is_some() || self.terminal_reason.as_deref() == Some("api_error");
+        (self.is_error && api_failed).then(|| ApiError {
+            status: self.api_error_status,
+            message: self.result.clone(),
+        })
+    }
+}
+
+/// How a turn ended, from a result's `subtype`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultSubtype {
+    Success,
+    /// `error_max_turns`: the turn hit claude's `--max-turns` limit.
+    MaxTurns,
+    /// `error_during_execution`: the run itself failed, e.g. a session to
+    /// resume wasn't found.
+    ExecutionError,
+    /// `error_max_budget_usd`: claude's own `--max-budget-usd` ran out.
+    MaxBudget,
+    /// A subtype newer than coven.
+    Unknown,
+}
+
+impl ResultSubtype {
+    pub fn parse(subtype: &str) -> Self {
+        match subtype {
+            "success" => Self::Success,
+            "error_max_turns" => Self::MaxTurns,
+            "error_during_execution" => Self::ExecutionError,
+            "error_max_budget_usd" => Self::MaxBudget,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// The result line's label; `None` for an unknown subtype, which is
+    /// shown as-is.
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            Self::Success => Some("Done"),
+            Self::MaxTurns => Some("Max turns reached"),
+            Self::ExecutionError => Some("Execution error"),
+            Self::MaxBudget => Some("Budget exhausted"),
+            Self::Unknown => None,
+        }
+    }
+}
+
+/// A tool call refused for lack of permission.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionDenial {
+    #[serde(default)]
+    pub tool_name: String,
+    #[serde(default)]
+    pub tool_use_id: String,
+    #[serde(default)]
+    pub tool_input: Value,
+    #[serde(flatten)]
+    _extra: Value,
+}
+
+/// One model's share of a result's usage. Also carries `contextWindow`,
+/// `maxOutputTokens`, `webSearchRequests`, etc. (silently ignored).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelUsage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+    #[serde(default, rename = "costUSD")]
+    pub cost_usd: f64,
+    #[serde(flatten)]
+    _extra: Value,
+}
+
+/// Deserialize a field the CLI has been adding to, falling back to the
+/// default if its shape changes rather than failing the whole event.
+fn lenient<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: serde::de::DeserializeOwned + Default,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).unwrap_or_default())
+}
+
+/// Token usage reported on a result event. Also carries `cache_creation`,
+/// `server_tool_use`, `service_tier`, etc. (silently ignored).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+    #[serde(flatten)]
+    _extra: Value,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RateLimitEvent {
     pub rate_limit_info: RateLimitInfo,
@@ -183,6 +322,9 @@ pub struct RateLimitInfo {
     pub rate_limit_type: String,
     #[serde(default)]
     pub utilization: f64,
+    /// When the limit window resets, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub resets_at: Option<u64>,
     #[serde(flatten)]
     _extra: Value,
 }
@@ -191,6 +333,66 @@ impl RateLimitInfo {
     pub fn is_warning(&self) -> bool {
         self.status.contains("warning")
     }
+
+    /// The limit has been hit and requests are being refused.
+    pub fn is_rejected(&self) -> bool {
+        self.status == "rejected"
+    }
+}
+
+/// An API error that ended a turn, from its result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    /// HTTP status, e.g. 529 when the API is overloaded.
+    pub status: Option<u16>,
+    pub message: String,
+}
+
+/// What an [`ApiError`] means for the session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiErrorKind {
+    /// The API is overloaded (529).
+    Overloaded,
+    /// Too many requests (429).
+    RateLimited,
+    /// An internal API error (500).
+    Server,
+    /// The conversation no longer fits in the context window.
+    ContextLength,
+    /// Anything else: invalid requests, authentication, permissions.
+    Fatal,
+}
+
+impl ApiError {
+    pub fn kind(&self) -> ApiErrorKind {
+        match self.status {
+            Some(529) => ApiErrorKind::Overloaded,
+            Some(429) => ApiErrorKind::RateLimited,
+            _ if is_context_length_message(&self.message) => ApiErrorKind::ContextLength,
+            Some(500..=599) => ApiErrorKind::Server,
+            _ => ApiErrorKind::Fatal,
+        }
+    }
+
+    /// Whether the same request is likely to succeed after waiting.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self.kind(),
+            ApiErrorKind::Overloaded | ApiErrorKind::RateLimited | ApiErrorKind::Server
+        )
+    }
+}
+
+fn is_context_length_message(message: &str) -> bool {
+    let message = message.to_lowercase();
+    [
+        "prompt is too long",
+        "context length",
+        "context window",
+        "too many tokens",
+    ]
+    .iter()
+    .any(|phrase| message.contains(phrase))
 }
 
 #[derive(Debug, Clone, Serialize)]
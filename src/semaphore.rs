@@ -2,16 +2,26 @@
 //!
 //! Semaphore files live in `<git-common-dir>/coven/semaphores/`.
 //! For an agent with `max_concurrency: N`, files `<agent>.0.lock`
-//! through `<agent>.<N-1>.lock` are used as exclusive locks.
+//! through `<agent>.<N-1>.lock` are used as exclusive locks. Whoever holds
+//! a slot writes its branch and acquisition time into the file, so a worker
+//! left waiting can say who it's waiting on. A worker that takes a slot over
+//! (`--steal-stale-lock`) locks `<agent>.<i>.steal` instead, since the old
+//! holder may still have the slot's own lock.
 
 use std::fs::{self, File, OpenOptions};
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use fs2::FileExt;
+use serde::{Deserialize, Serialize};
 
 use crate::worker_state;
 
+/// How long `acquire` waits before reporting contention (`--lock-timeout`).
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_mins(10);
+
 /// A held semaphore permit. Released when dropped (the `File` lock is
 /// released on drop by fs2).
 pub struct SemaphorePermit {
@@ -31,32 +41,117 @@ impl crate::vcr::Recordable for SemaphorePermit {
     }
 }
 
-/// Acquire a semaphore permit for the given agent.
+/// How long to wait for a slot, and whether to break one held too long.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LockWait {
+    /// Give up and report the holders after this long.
+    pub timeout: Duration,
+    /// At the timeout, take over a slot held at least this long
+    /// (`--steal-stale-lock`).
+    pub steal_after: Option<Duration>,
+}
+
+impl Default for LockWait {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_LOCK_TIMEOUT,
+            steal_after: None,
+        }
+    }
+}
+
+/// A worker holding a slot, as of when `acquire` gave up waiting.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Holder {
+    pub branch: String,
+    pub held_secs: u64,
+}
+
+/// What's written into a held slot's file.
+#[derive(Serialize, Deserialize)]
+struct LockRecord {
+    branch: String,
+    acquired_at: u64,
+}
+
+/// The result of `acquire`.
+pub enum Acquired {
+    Permit(SemaphorePermit),
+    /// Every slot was still held at the timeout. Slots whose holder didn't
+    /// record itself (an older coven) are left out.
+    TimedOut(Vec<Holder>),
+    /// The slot was taken over from a holder past `steal_after`, which may
+    /// still be running.
+    Stolen(SemaphorePermit, Holder),
+}
+
+/// How an `Acquired` is recorded. A plain permit records as `null`, as
+/// permits did before `acquire` could time out.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum RecordedContention {
+    TimedOut { holders: Vec<Holder> },
+    Stolen { holder: Holder },
+}
+
+impl crate::vcr::Recordable for Acquired {
+    type Recorded = Option<RecordedContention>;
+
+    fn to_recorded(&self) -> Result<Self::Recorded> {
+        Ok(match self {
+            Acquired::Permit(_) => None,
+            Acquired::TimedOut(holders) => Some(RecordedContention::TimedOut {
+                holders: holders.clone(),
+            }),
+            Acquired::Stolen(_, holder) => Some(RecordedContention::Stolen {
+                holder: holder.clone(),
+            }),
+        })
+    }
+
+    fn from_recorded(recorded: Self::Recorded) -> Result<Self> {
+        Ok(match recorded {
+            None => Acquired::Permit(SemaphorePermit::from_recorded(())?),
+            Some(RecordedContention::TimedOut { holders }) => Acquired::TimedOut(holders),
+            Some(RecordedContention::Stolen { holder }) => {
+                Acquired::Stolen(SemaphorePermit::from_recorded(())?, holder)
+            }
+        })
+    }
+}
+
+/// Acquire a semaphore permit for the given agent, recording `branch` as
+/// the holder.
 ///
 /// Tries `try_lock_exclusive` on each slot `0..max_concurrency` in sequence.
-/// If all slots are locked, sleeps and retries. Retries forever — an automatic
-/// timeout could cause two workers to run the same exclusive agent simultaneously.
+/// If all slots are locked, sleeps and retries until `wait.timeout`, then
+/// returns the holders so the caller can say who it's waiting on and try
+/// again. Nothing is broken unless `wait.steal_after` asks for it — running
+/// an exclusive agent twice is worse than waiting.
 pub async fn acquire(
     repo_path: &Path,
     agent_name: &str,
     max_concurrency: u32,
-) -> Result<SemaphorePermit> {
+    branch: &str,
+    wait: LockWait,
+) -> Result<Acquired> {
     let sem_dir = worker_state::coven_dir(repo_path)?.join("semaphores");
     fs::create_dir_all(&sem_dir)
         .with_context(|| format!("failed to create {}", sem_dir.display()))?;
+    let slots: Vec<PathBuf> = (0..max_concurrency)
+        .map(|i| sem_dir.join(format!("{agent_name}.{i}.lock")))
+        .collect();
 
+    let deadline = Instant::now() + wait.timeout;
     loop {
-        for i in 0..max_concurrency {
-            let lock_path = sem_dir.join(format!("{agent_name}.{i}.lock"));
-            let file = OpenOptions::new()
-                .create(true)
-                .truncate(false)
-                .write(true)
-                .open(&lock_path)
-                .with_context(|| format!("failed to open {}", lock_path.display()))?;
-
+        for lock_path in &slots {
+            let file = open_slot(lock_path)?;
             match file.try_lock_exclusive() {
-                Ok(()) => return Ok(SemaphorePermit { _file: file }),
+                Ok(()) if is_stolen(lock_path)? => {}
+                Ok(()) => {
+                    write_record(&file, lock_path, branch)?;
+                    return Ok(Acquired::Permit(SemaphorePermit { _file: file }));
+                }
                 Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
                 Err(e) => {
                     return Err(anyhow::anyhow!(e)
@@ -65,10 +160,91 @@ pub async fn acquire(
             }
         }
 
-        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        if Instant::now() >= deadline {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let now = worker_state::now_secs().unwrap_or(0);
+    let held: Vec<(&PathBuf, Holder)> = slots
+        .iter()
+        .filter_map(|path| {
+            let record: LockRecord = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+            let holder = Holder {
+                branch: record.branch,
+                held_secs: now.saturating_sub(record.acquired_at),
+            };
+            Some((path, holder))
+        })
+        .collect();
+
+    if let Some(after) = wait.steal_after {
+        let mut stale: Vec<&(&PathBuf, Holder)> = held
+            .iter()
+            .filter(|(_, holder)| holder.held_secs >= after.as_secs())
+            .collect();
+        stale.sort_by_key(|(_, holder)| std::cmp::Reverse(holder.held_secs));
+        for (lock_path, holder) in stale {
+            // The old holder may still have the slot's own lock, so the
+            // steal lock is what keeps the slot taken once it lets go.
+            let steal_file = open_slot(&steal_path(lock_path))?;
+            if steal_file.try_lock_exclusive().is_err() {
+                continue;
+            }
+            write_record(&open_slot(lock_path)?, lock_path, branch)?;
+            return Ok(Acquired::Stolen(
+                SemaphorePermit { _file: steal_file },
+                holder.clone(),
+            ));
+        }
+    }
+
+    Ok(Acquired::TimedOut(
+        held.into_iter().map(|(_, holder)| holder).collect(),
+    ))
+}
+
+/// The lock held by a worker that took `lock_path` over with
+/// `--steal-stale-lock`. The slot is taken while either lock is held.
+fn steal_path(lock_path: &Path) -> PathBuf {
+    lock_path.with_extension("steal")
+}
+
+/// Whether a worker that stole `lock_path` still holds it.
+fn is_stolen(lock_path: &Path) -> Result<bool> {
+    let path = steal_path(lock_path);
+    if !path.exists() {
+        return Ok(false);
+    }
+    match open_slot(&path)?.try_lock_exclusive() {
+        Ok(()) => Ok(false),
+        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(true),
+        Err(e) => Err(anyhow::anyhow!(e).context(format!("failed to lock {}", path.display()))),
     }
 }
 
+fn open_slot(lock_path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(lock_path)
+        .with_context(|| format!("failed to open {}", lock_path.display()))
+}
+
+/// Replace the slot file's contents with a record naming this worker.
+fn write_record(mut file: &File, lock_path: &Path, branch: &str) -> Result<()> {
+    let record = LockRecord {
+        branch: branch.to_string(),
+        acquired_at: worker_state::now_secs().unwrap_or(0),
+    };
+    file.set_len(0)
+        .and_then(|()| file.write_all(&serde_json::to_vec(&record)?))
+        .with_context(|| format!("failed to write {}", lock_path.display()))
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -93,12 +269,37 @@ mod tests {
         run(&["commit", "-m", "init"]);
     }
 
+    /// Acquire as `main` with the default wait, expecting a plain permit.
+    async fn acquire_permit(repo: &Path, agent: &str, max_concurrency: u32) -> SemaphorePermit {
+        let acquired = acquire(repo, agent, max_concurrency, "main", LockWait::default());
+        match acquired.await.unwrap() {
+            Acquired::Permit(permit) => Some(permit),
+            _ => None,
+        }
+        .unwrap()
+    }
+
+    /// The holders reported at a timeout, if it timed out.
+    fn timed_out(acquired: Acquired) -> Option<Vec<Holder>> {
+        match acquired {
+            Acquired::TimedOut(holders) => Some(holders),
+            _ => None,
+        }
+    }
+
+    fn short_wait(steal_after: Option<Duration>) -> LockWait {
+        LockWait {
+            timeout: Duration::from_millis(200),
+            steal_after,
+        }
+    }
+
     #[tokio::test]
     async fn acquire_creates_lock_file() {
         let repo = TempDir::new().unwrap();
         init_repo(repo.path());
 
-        let _permit = acquire(repo.path(), "dispatch", 1).await.unwrap();
+        let _permit = acquire_permit(repo.path(), "dispatch", 1).await;
         let sem_dir = worker_state::coven_dir(repo.path())
             .unwrap()
             .join("semaphores");
@@ -110,8 +311,8 @@ mod tests {
         let repo = TempDir::new().unwrap();
         init_repo(repo.path());
 
-        let _permit1 = acquire(repo.path(), "implement", 2).await.unwrap();
-        let _permit2 = acquire(repo.path(), "implement", 2).await.unwrap();
+        let _permit1 = acquire_permit(repo.path(), "implement", 2).await;
+        let _permit2 = acquire_permit(repo.path(), "implement", 2).await;
 
         let sem_dir = worker_state::coven_dir(repo.path())
             .unwrap()
@@ -125,12 +326,12 @@ mod tests {
         let repo = TempDir::new().unwrap();
         init_repo(repo.path());
 
-        let _permit = acquire(repo.path(), "dispatch", 1).await.unwrap();
+        let _permit = acquire_permit(repo.path(), "dispatch", 1).await;
 
         // Try to acquire with a short timeout — should not succeed
         let result = tokio::time::timeout(
             std::time::Duration::from_millis(300),
-            acquire(repo.path(), "dispatch", 1),
+            acquire_permit(repo.path(), "dispatch", 1),
         )
         .await;
 
@@ -142,10 +343,96 @@ mod tests {
         let repo = TempDir::new().unwrap();
         init_repo(repo.path());
 
-        let permit = acquire(repo.path(), "dispatch", 1).await.unwrap();
+        let permit = acquire_permit(repo.path(), "dispatch", 1).await;
         drop(permit);
 
         // Should succeed immediately
-        let _permit2 = acquire(repo.path(), "dispatch", 1).await.unwrap();
+        let _permit2 = acquire_permit(repo.path(), "dispatch", 1).await;
+    }
+
+    #[tokio::test]
+    async fn acquire_records_the_holder() {
+        let repo = TempDir::new().unwrap();
+        init_repo(repo.path());
+
+        let _permit = acquire_permit(repo.path(), "dispatch", 1).await;
+        let sem_dir = worker_state::coven_dir(repo.path())
+            .unwrap()
+            .join("semaphores");
+        let record: LockRecord =
+            serde_json::from_str(&fs::read_to_string(sem_dir.join("dispatch.0.lock")).unwrap())
+                .unwrap();
+        assert_eq!(record.branch, "main");
+        assert!(record.acquired_at > 0);
+    }
+
+    #[tokio::test]
+    async fn timeout_reports_the_holders() {
+        let repo = TempDir::new().unwrap();
+        init_repo(repo.path());
+
+        let _permit = acquire_permit(repo.path(), "dispatch", 1).await;
+        let acquired = acquire(repo.path(), "dispatch", 1, "swift-fox", short_wait(None))
+            .await
+            .unwrap();
+        let holders = timed_out(acquired).unwrap();
+        assert_eq!(holders.len(), 1);
+        assert_eq!(holders[0].branch, "main");
+    }
+
+    #[tokio::test]
+    async fn steals_a_slot_held_past_the_threshold() {
+        let repo = TempDir::new().unwrap();
+        init_repo(repo.path());
+
+        let held = acquire_permit(repo.path(), "dispatch", 1).await;
+
+        // Held for less than an hour: only reported.
+        let wait = short_wait(Some(Duration::from_hours(1)));
+        let acquired = acquire(repo.path(), "dispatch", 1, "swift-fox", wait)
+            .await
+            .unwrap();
+        assert!(timed_out(acquired).is_some());
+
+        let wait = short_wait(Some(Duration::ZERO));
+        let acquired = acquire(repo.path(), "dispatch", 1, "swift-fox", wait)
+            .await
+            .unwrap();
+        let stolen = match acquired {
+            Acquired::Stolen(permit, holder) => Some((permit, holder)),
+            _ => None,
+        };
+        let (stolen_permit, holder) = stolen.unwrap();
+        assert_eq!(holder.branch, "main");
+
+        // The slot now names the new holder, and can't be stolen twice.
+        let acquired = acquire(repo.path(), "dispatch", 1, "calm-owl", wait)
+            .await
+            .unwrap();
+        let holders = timed_out(acquired).unwrap();
+        assert_eq!(holders[0].branch, "swift-fox");
+
+        // It stays taken after the old holder lets go, until the thief does.
+        drop(held);
+        let acquired = acquire(repo.path(), "dispatch", 1, "calm-owl", short_wait(None))
+            .await
+            .unwrap();
+        assert!(timed_out(acquired).is_some());
+        drop(stolen_permit);
+        let acquired = acquire(repo.path(), "dispatch", 1, "calm-owl", short_wait(None))
+            .await
+            .unwrap();
+        assert!(matches!(acquired, Acquired::Permit(_)));
+    }
+
+    #[test]
+    fn plain_permits_replay_from_older_recordings() {
+        use crate::vcr::Recordable;
+
+        let recorded: Option<RecordedContention> = serde_json::from_str("null").unwrap();
+        assert!(matches!(
+            Acquired::from_recorded(recorded).unwrap(),
+            Acquired::Permit(_)
+        ));
     }
 }
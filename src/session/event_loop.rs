@@ -1,18 +1,27 @@
+use std::fs::OpenOptions;
 use std::io::{IsTerminal, Write};
+use std::path::Path;
 use std::process::Command as StdCommand;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result, bail};
-use crossterm::event::{Event, KeyEvent};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use crossterm::terminal;
 
-use crate::display::input::{InputAction, InputHandler};
+use crate::display::TerminalSize;
+use crate::display::input::{InputAction, InputHandler, QueueCommand};
 use crate::display::renderer::Renderer;
 use crate::event::{AppEvent, InputMode};
 use crate::fork::{self, ForkConfig};
+use crate::protocol::emit::check_raw_message;
 use crate::protocol::types::{AssistantContentBlock, InboundEvent, SystemEvent};
+use crate::session::loop_detect::{LOOP_THRESHOLD, LoopDetector};
+use crate::session::outbox::Outbox;
 use crate::session::persist;
 use crate::session::runner::{SessionConfig, SessionRunner};
-use crate::session::state::{SessionState, SessionStatus};
+use crate::session::state::{SessionState, SessionStatus, TokenUsage};
+use crate::session::trust::TrustDetector;
+use crate::utc::UtcDateTime;
 use crate::vcr::{Io, IoEvent, VcrContext};
 
 /// Send a message to the session via VCR.
@@ -22,7 +31,7 @@ async fn vcr_send_message(
     message: String,
 ) -> Result<()> {
     vcr.call("send_message", message, async |t: &String| {
-        runner.send_message(t).await
+        runner.send_message(t)
     })
     .await
 }
@@ -35,6 +44,11 @@ pub enum SessionOutcome {
     Interrupted,
     /// Claude process exited unexpectedly.
     ProcessExited,
+    /// The session timeout armed on `io` (`--iteration-timeout`) expired
+    /// before a result.
+    TimedOut,
+    /// Shutdown was requested (SIGTERM/SIGINT) — caller should clean up and exit.
+    Shutdown,
     /// Model emitted a `<reload>` tag — caller should reload claude and resume.
     Reload { result_text: String },
 }
@@ -49,18 +63,66 @@ pub struct SessionFeatures<'a> {
     /// If any of these appear in a message that also contains tool calls, the model
     /// receives a warning via stdin that the tag will be ignored.
     pub watched_tags: Vec<String>,
+    /// Warn when claude produces no output for this long after a steering
+    /// message was sent. `None` disables the check.
+    pub steering_timeout: Option<Duration>,
+    /// On a steering timeout, kill the (presumably wedged) process and resume
+    /// the session with the undelivered steering as the prompt, instead of
+    /// only warning. Used by unattended workers.
+    pub resume_wedged: bool,
+    /// Spending cap (`--max-cost`). `None` means unlimited.
+    pub budget: Option<CostBudget>,
+    /// End the session after this many tool-call loop warnings, as if claude
+    /// had returned a result. `None` only warns.
+    pub max_loop_warnings: Option<usize>,
+    /// `--debug-protocol`: append claude output that fails to parse to this
+    /// file, besides warning about it.
+    pub protocol_log: Option<&'a Path>,
+}
+
+/// A `--max-cost` spending cap, and how much was already spent before the
+/// current claude process (earlier iterations, phases or resumed sessions).
+#[derive(Debug, Clone, Copy)]
+pub struct CostBudget {
+    pub max_cost: f64,
+    pub spent_before: f64,
+}
+
+impl CostBudget {
+    pub fn new(max_cost: Option<f64>, spent_before: f64) -> Option<Self> {
+        max_cost.map(|max_cost| Self {
+            max_cost,
+            spent_before,
+        })
+    }
+
+    /// Total spent once the current process has cost `session_cost`.
+    pub fn spent(&self, session_cost: f64) -> f64 {
+        self.spent_before + session_cost
+    }
+
+    pub fn exceeded(&self, session_cost: f64) -> bool {
+        self.spent(session_cost) > self.max_cost
+    }
 }
 
 /// Per-session transient state for event buffering and follow-ups.
 struct SessionLocals {
     event_buffer: Vec<AppEvent>,
-    pending_followups: Vec<String>,
+    /// The user's follow-ups and steering messages until they reach the
+    /// model: with claude's first request after a turn boundary.
+    outbox: Outbox,
     result_text: String,
     fork_config: Option<ForkConfig>,
     reload_enabled: bool,
     watched_tags: Vec<String>,
-    /// Warning to send when a special tag is found in a non-final assistant message.
-    tag_warning: Option<String>,
+    /// Warning to send to the model, e.g. when a special tag is found in a
+    /// non-final assistant message or the model is stuck in a tool-call loop.
+    system_warning: Option<String>,
+    budget: Option<CostBudget>,
+    loop_detector: LoopDetector,
+    max_loop_warnings: Option<usize>,
+    trust_detector: TrustDetector,
 }
 
 /// Run a single session's event loop with full input support.
@@ -69,7 +131,9 @@ struct SessionLocals {
 /// follow-up messages, message viewing, and interrupt/end-session signals.
 ///
 /// Returns when the session produces a Result event, the user interrupts,
-/// or the process exits.
+/// or the process exits. Messages the user sent that never reached claude
+/// are listed and left in `state.undelivered`; after an interrupt they are
+/// also put back in the input line for the resume prompt.
 pub async fn run_session<W: Write>(
     runner: &mut SessionRunner,
     state: &mut SessionState,
@@ -81,20 +145,28 @@ pub async fn run_session<W: Write>(
 ) -> Result<SessionOutcome> {
     let mut locals = SessionLocals {
         event_buffer: Vec::new(),
-        pending_followups: Vec::new(),
+        outbox: Outbox::default(),
         result_text: String::new(),
         fork_config: features.fork_config.cloned(),
         reload_enabled: features.reload_enabled,
         watched_tags: features.watched_tags.clone(),
-        tag_warning: None,
+        system_warning: None,
+        budget: features.budget,
+        loop_detector: LoopDetector::new(LOOP_THRESHOLD),
+        max_loop_warnings: features.max_loop_warnings,
+        trust_detector: TrustDetector::default(),
     };
 
-    loop {
-        let io_event: IoEvent = vcr
-            .call("next_event", (), async |(): &()| io.next_event().await)
-            .await?;
+    let mut activity = ActivityTimer::new(io.has_tty_stdin() && std::io::stdout().is_terminal());
+
+    let outcome = loop {
+        let show_activity = state.status == SessionStatus::Running && !input.is_active();
+        let io_event = next_io_event(io, vcr, renderer, &mut activity, show_activity).await?;
+        let mut fork_tasks = None;
         match io_event {
             IoEvent::Claude(app_event) => {
+                let outbox = &mut locals.outbox;
+                observe_claude_event(&app_event, outbox, state, renderer, vcr, features).await?;
                 if input.is_active() && state.status == SessionStatus::Running {
                     locals.event_buffer.push(app_event);
                 } else {
@@ -102,14 +174,13 @@ pub async fn run_session<W: Write>(
                         .await?
                     {
                         EventResult::Continue => {}
-                        EventResult::Fork(tasks) => {
-                            execute_fork(tasks, state, renderer, runner, io, vcr, features).await?;
-                        }
-                        EventResult::End(outcome) => return Ok(outcome),
+                        EventResult::Fork(tasks) => fork_tasks = Some(tasks),
+                        EventResult::End(outcome) => break outcome,
                     }
                 }
             }
             IoEvent::Terminal(Event::Key(key_event)) => {
+                let unacked_before = locals.outbox.unacknowledged();
                 let action = handle_session_key_event(
                     &key_event,
                     input,
@@ -120,30 +191,26 @@ pub async fn run_session<W: Write>(
                     vcr,
                 )
                 .await?;
+                arm_steering_timer(io, &locals.outbox, unacked_before, features);
                 match action {
                     LoopAction::Continue => {}
-                    LoopAction::Return(outcome) => return Ok(outcome),
-                    LoopAction::Fork(tasks) => {
-                        execute_fork(tasks, state, renderer, runner, io, vcr, features).await?;
-                    }
+                    LoopAction::Return(outcome) => break outcome,
+                    LoopAction::Fork(tasks) => fork_tasks = Some(tasks),
                     LoopAction::ViewMessage(ref query) => {
                         view_message(renderer, query, io)?;
                         let flush = flush_event_buffer(&mut locals, state, renderer);
-                        send_tag_warning(&mut locals, runner, vcr).await?;
+                        send_system_warning(&mut locals, runner, vcr).await?;
                         if let FlushResult::Completed(ref result_text) = flush {
-                            return Ok(SessionOutcome::Completed {
+                            break SessionOutcome::Completed {
                                 result_text: result_text.clone(),
-                            });
+                            };
                         }
                         if let Some(action) =
                             handle_flush_result(flush, state, renderer, runner, vcr).await?
                         {
                             match action {
-                                LoopAction::Return(outcome) => return Ok(outcome),
-                                LoopAction::Fork(tasks) => {
-                                    execute_fork(tasks, state, renderer, runner, io, vcr, features)
-                                        .await?;
-                                }
+                                LoopAction::Return(outcome) => break outcome,
+                                LoopAction::Fork(tasks) => fork_tasks = Some(tasks),
                                 _ => {}
                             }
                         }
@@ -154,11 +221,295 @@ pub async fn run_session<W: Write>(
                     }
                 }
             }
-            IoEvent::Terminal(_) => {}
+            IoEvent::Silence => {
+                handle_steering_silence(state, renderer, runner, &mut locals, io, vcr, features)
+                    .await?;
+            }
+            IoEvent::SessionTimeout => break SessionOutcome::TimedOut,
+            IoEvent::Shutdown => break SessionOutcome::Shutdown,
+            IoEvent::Terminal(event) => other_terminal_event(event, renderer, input),
+        }
+        if let Some(tasks) = fork_tasks {
+            let cx = ForkContext {
+                state,
+                renderer,
+                input,
+                runner,
+                io,
+                vcr,
+                features,
+            };
+            execute_fork(tasks, cx).await?;
+        }
+    };
+    report_undelivered(&outcome, state, renderer, input, &mut locals.outbox);
+    Ok(outcome)
+}
+
+/// Start timing claude's silence if a key press just queued steering.
+///
+/// A tool call can run for minutes without claude printing anything, so only
+/// time the silence outside of one.
+fn arm_steering_timer(
+    io: &mut Io,
+    outbox: &Outbox,
+    unacked_before: usize,
+    features: &SessionFeatures<'_>,
+) {
+    if let Some(window) = features.steering_timeout
+        && outbox.unacknowledged() > unacked_before
+        && !outbox.tool_running()
+    {
+        io.arm_silence_timer(window);
+    }
+}
+
+/// A terminal event other than a key press: follow resizes and take pastes.
+fn other_terminal_event<W: Write>(
+    event: Event,
+    renderer: &mut Renderer<W>,
+    input: &mut InputHandler,
+) {
+    match event {
+        Event::Resize(cols, rows) => resize(renderer, input, cols, rows),
+        Event::Paste(text) => paste(&text, renderer, input),
+        _ => {}
+    }
+}
+
+/// Follow a terminal resize: truncate output and wrap input at the new width.
+/// A too-narrow terminal renders at the default width; the notice saying so
+/// waits while the input line is showing.
+fn resize<W: Write>(renderer: &mut Renderer<W>, input: &mut InputHandler, columns: u16, rows: u16) {
+    renderer.set_size(TerminalSize {
+        width: usize::from(columns),
+        height: usize::from(rows).max(1),
+    });
+    input.set_width(renderer.width());
+    if !input.is_active() {
+        renderer.render_narrow_notice();
+    }
+}
+
+/// Put a bracketed paste on the input line, starting one if none is showing.
+/// Only Enter sends it, however many lines it has.
+fn paste<W: Write>(text: &str, renderer: &mut Renderer<W>, input: &mut InputHandler) {
+    if let InputAction::Activated(_) = input.handle_paste(text, renderer.writer()) {
+        renderer.begin_input_line_with_hints();
+        input.set_has_hint_line();
+        input.redraw(renderer.writer());
+    }
+}
+
+/// List the messages the session ended without delivering and leave them in
+/// `state.undelivered`. After an interrupt, they also pre-fill the input line.
+fn report_undelivered<W: Write>(
+    outcome: &SessionOutcome,
+    state: &mut SessionState,
+    renderer: &mut Renderer<W>,
+    input: &mut InputHandler,
+    outbox: &mut Outbox,
+) {
+    state.undelivered = outbox.take_undelivered();
+    if state.undelivered.is_empty() {
+        return;
+    }
+    renderer.render_undelivered(&state.undelivered);
+    if matches!(outcome, SessionOutcome::Interrupted) {
+        // The input line is single-line, so the messages go side by side.
+        input.set_draft(state.undelivered.join(" "));
+    }
+}
+
+/// Follow the user's messages to the model through claude's own turn
+/// boundaries and requests; a subagent's don't carry them.
+fn track_delivery(inbound: &InboundEvent, outbox: &mut Outbox) {
+    match inbound {
+        InboundEvent::User(result) if result.parent_tool_use_id.is_none() => {
+            outbox.turn_boundary(false);
+        }
+        InboundEvent::Result(_) => outbox.turn_boundary(true),
+        InboundEvent::StreamEvent(stream)
+            if stream.parent_tool_use_id.is_none()
+                && stream.event.event_type == "message_start" =>
+        {
+            outbox.request_started();
+        }
+        InboundEvent::Assistant(msg) if msg.parent_tool_use_id.is_none() => {
+            outbox.request_started();
+            if msg
+                .message
+                .content
+                .iter()
+                .any(|b| matches!(b, AssistantContentBlock::ToolUse { .. }))
+            {
+                outbox.tool_started();
+            }
         }
+        _ => {}
     }
 }
 
+/// Bookkeeping for each event from claude as it arrives, whether it's
+/// rendered right away or buffered: acknowledge sent messages, time runs of
+/// `land.sh`, and log unparseable output with `--debug-protocol`.
+async fn observe_claude_event<W: Write>(
+    app_event: &AppEvent,
+    outbox: &mut Outbox,
+    state: &mut SessionState,
+    renderer: &mut Renderer<W>,
+    vcr: &VcrContext,
+    features: &SessionFeatures<'_>,
+) -> Result<()> {
+    if app_event.is_claude_output() {
+        outbox.acknowledge();
+    }
+    if let AppEvent::Claude(inbound) = app_event {
+        track_delivery(inbound, outbox);
+        state.land.observe(inbound, || vcr.clock_ms())?;
+    }
+    let (Some(path), AppEvent::ParseWarning(warning)) = (features.protocol_log, app_event) else {
+        return Ok(());
+    };
+    let created = vcr
+        .call(
+            "log_unparsed",
+            (path.display().to_string(), warning.clone()),
+            async |(p, w): &(String, String)| append_protocol_log(Path::new(p), w),
+        )
+        .await?;
+    if created {
+        renderer.render_protocol_log(&path.display().to_string());
+    }
+    Ok(())
+}
+
+/// Append `entry` to the log at `path`. Returns whether the file is new.
+fn append_protocol_log(path: &Path, entry: &str) -> Result<bool> {
+    let created = !path.exists();
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    writeln!(file, "{entry}").with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(created)
+}
+
+/// Silence from claude before the `…working` indicator appears.
+const ACTIVITY_DELAY: Duration = Duration::from_secs(2);
+
+/// Tracks how long claude has been silent, for the `…working` indicator.
+/// Only enabled on a real terminal, so the indicator never appears in VCR
+/// recordings or tests.
+struct ActivityTimer {
+    enabled: bool,
+    last_event: tokio::time::Instant,
+}
+
+impl ActivityTimer {
+    fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            last_event: tokio::time::Instant::now(),
+        }
+    }
+
+    /// When the indicator should next be drawn: `ACTIVITY_DELAY` after the
+    /// last event, then on each whole second after that.
+    fn next_tick(&self, now: tokio::time::Instant) -> tokio::time::Instant {
+        let elapsed = now.saturating_duration_since(self.last_event);
+        if elapsed < ACTIVITY_DELAY {
+            return self.last_event + ACTIVITY_DELAY;
+        }
+        self.last_event + Duration::from_secs(elapsed.as_secs() + 1)
+    }
+}
+
+/// Wait for the next event, keeping the `…working` indicator up to date while
+/// claude is silent (if `show_activity`). The indicator is erased before the
+/// event is returned.
+///
+/// Ticks aren't VCR calls. The indicator is only enabled in live mode, where
+/// `next_event` is a cancel-safe channel select, so dropping it on a tick
+/// loses nothing.
+async fn next_io_event<W: Write>(
+    io: &mut Io,
+    vcr: &VcrContext,
+    renderer: &mut Renderer<W>,
+    activity: &mut ActivityTimer,
+    show_activity: bool,
+) -> Result<IoEvent> {
+    loop {
+        let tick = activity.next_tick(tokio::time::Instant::now());
+        tokio::select! {
+            biased;
+            event = vcr.call("next_event", (), async |(): &()| io.next_event().await) => {
+                let event = event?;
+                renderer.clear_activity();
+                match event {
+                    IoEvent::Claude(_) => activity.last_event = tokio::time::Instant::now(),
+                    IoEvent::Shutdown => io.note_shutdown(),
+                    _ => {}
+                }
+                return Ok(event);
+            }
+            () = tokio::time::sleep_until(tick), if show_activity && activity.enabled => {
+                renderer.render_activity(activity.last_event.elapsed().as_secs());
+            }
+        }
+    }
+}
+
+/// Handle an expired steering silence timer: claude accepted a steering
+/// message on stdin but has produced no output since, so the process may be
+/// wedged. Warns the user, and with `resume_wedged` kills the process and
+/// resumes the session with the undelivered steering.
+async fn handle_steering_silence<W: Write>(
+    state: &mut SessionState,
+    renderer: &mut Renderer<W>,
+    runner: &mut SessionRunner,
+    locals: &mut SessionLocals,
+    io: &mut Io,
+    vcr: &VcrContext,
+    features: &SessionFeatures<'_>,
+) -> Result<()> {
+    let Some(window) = features.steering_timeout else {
+        return Ok(());
+    };
+    if locals.outbox.unacknowledged() == 0 {
+        return Ok(());
+    }
+    let session_id = state.session_id.clone().filter(|_| features.resume_wedged);
+    renderer.render_steering_silence(window.as_secs(), session_id.is_some());
+    let Some(session_id) = session_id else {
+        return Ok(());
+    };
+    runner.kill().await?;
+    let prompt = compose_wedged_resume(&locals.outbox.take_in_flight());
+    let config = features.base_config.resume_with(prompt, session_id);
+    *runner = spawn_session(config, io, vcr).await?;
+    state.suppress_next_separator = true;
+    state.status = SessionStatus::Running;
+    Ok(())
+}
+
+/// Build the resume prompt for a session whose process stopped responding:
+/// the undelivered steering messages, followed by a note explaining the restart.
+fn compose_wedged_resume(undelivered: &[String]) -> String {
+    let mut prompt = undelivered.join("\n\n");
+    prompt.push_str(
+        "\n\n[system] The previous claude process stopped responding after the message(s) \
+         above were sent, so it was restarted. Continue where you left off, taking them \
+         into account.",
+    );
+    prompt
+}
+
 /// Flow control signals from key event handlers.
 enum LoopAction {
     Continue,
@@ -204,12 +555,13 @@ enum ClaudeEventAction {
 ///
 /// When `has_pending_followups` is true, Result events update state but skip
 /// rendering the Done line — the follow-up will continue the conversation.
-fn handle_inbound<W: Write>(
+pub(crate) fn handle_inbound<W: Write>(
     event: &InboundEvent,
     state: &mut SessionState,
     renderer: &mut Renderer<W>,
     has_pending_followups: bool,
 ) {
+    renderer.emit_json_event(event);
     match event {
         InboundEvent::System(SystemEvent::Init(init)) => {
             let same_session = state.session_id.as_deref() == Some(&init.session_id);
@@ -223,7 +575,11 @@ fn handle_inbound<W: Write>(
                 }
             } else {
                 state.suppress_next_separator = false;
-                renderer.render_session_header(&init.session_id, &init.model);
+                if std::mem::take(&mut state.resuming) {
+                    renderer.render_resumed_session_header(&init.session_id, &init.model);
+                } else {
+                    renderer.render_session_header(&init.session_id, &init.model);
+                }
             }
         }
         InboundEvent::System(SystemEvent::Status { status: Some(s) }) if s == "compacting" => {
@@ -240,6 +596,8 @@ fn handle_inbound<W: Write>(
                         renderer.render_subagent_tool_call(name, input, parent_id);
                     }
                 }
+            } else {
+                renderer.name_unnamed_tools(&msg.message.content);
             }
         }
         InboundEvent::User(u) => {
@@ -255,6 +613,15 @@ fn handle_inbound<W: Write>(
         }
         InboundEvent::Result(result) => {
             state.total_cost_usd = result.total_cost_usd;
+            state.result_error = (result.is_error || result.subtype.starts_with("error"))
+                .then(|| result.subtype.clone());
+            state.api_error = result.api_error().map(Box::new);
+            if let Some(ref error) = state.api_error {
+                renderer.render_api_error(error);
+            }
+            if let Some(ref usage) = result.usage {
+                state.usage = TokenUsage::from(usage);
+            }
             state.status = SessionStatus::WaitingForInput;
             if !has_pending_followups {
                 renderer.render_result(
@@ -263,10 +630,12 @@ fn handle_inbound<W: Write>(
                     result.duration_ms,
                     result.num_turns,
                 );
+                renderer.render_usage("tokens", &state.usage);
+                renderer.render_permission_denials(&result.permission_denials);
             }
         }
         InboundEvent::RateLimit(rl) => {
-            if rl.rate_limit_info.is_warning() {
+            if rl.rate_limit_info.is_warning() || rl.rate_limit_info.is_rejected() {
                 renderer.render_rate_limit(&rl.rate_limit_info);
             }
         }
@@ -293,7 +662,21 @@ fn classify_claude_event<W: Write>(
         state.last_message_id = Some(id.clone());
     }
 
-    let fork_tasks = if let InboundEvent::Result(_) = *inbound {
+    let over_budget = match *inbound {
+        InboundEvent::Result(ref result) => {
+            locals.budget.filter(|b| b.exceeded(result.total_cost_usd))
+        }
+        _ => None,
+    };
+    let skipped_followups = if over_budget.is_some() {
+        locals.outbox.drop_queued()
+    } else {
+        0
+    };
+
+    let fork_tasks = if let InboundEvent::Result(_) = *inbound
+        && over_budget.is_none()
+    {
         locals
             .fork_config
             .as_ref()
@@ -303,52 +686,45 @@ fn classify_claude_event<W: Write>(
     };
 
     let reload_detected = if let InboundEvent::Result(_) = *inbound {
-        locals.reload_enabled
+        over_budget.is_none()
+            && locals.reload_enabled
             && crate::protocol::parse::extract_tag_inner(&locals.result_text, "reload").is_some()
     } else {
         false
     };
 
-    let has_pending =
-        !locals.pending_followups.is_empty() || fork_tasks.is_some() || reload_detected;
+    let has_pending = locals.outbox.has_queued() || fork_tasks.is_some() || reload_detected;
     handle_inbound(inbound, state, renderer, has_pending);
+    if let Some(budget) = over_budget {
+        state.budget_exceeded = true;
+        renderer.render_budget_exceeded(
+            budget.spent(state.total_cost_usd),
+            budget.max_cost,
+            skipped_followups,
+        );
+    }
+
+    if let Some(dir) = locals.trust_detector.observe(inbound) {
+        renderer.render_untrusted(&dir);
+        state.untrusted_dir = Some(dir);
+        locals.system_warning = None;
+        return ClaudeEventAction::Completed(
+            "[coven] Session ended early: every tool call was denied (directory not trusted)."
+                .to_string(),
+        );
+    }
 
-    // Check for special tags in non-final assistant messages (messages with tool calls).
-    // These tags are only processed in Result events, so they'd be silently ignored.
     if let InboundEvent::Assistant(ref msg) = *inbound
         && msg.parent_tool_use_id.is_none()
-        && !locals.watched_tags.is_empty()
     {
-        let has_tool_use = msg
-            .message
-            .content
-            .iter()
-            .any(|b| matches!(b, AssistantContentBlock::ToolUse { .. }));
-        if has_tool_use {
-            let found: Vec<&str> = locals
-                .watched_tags
-                .iter()
-                .filter(|tag| {
-                    msg.message.content.iter().any(|b| {
-                        matches!(b, AssistantContentBlock::Text { text }
-                            if crate::protocol::parse::extract_tag_inner(text, tag).is_some())
-                    })
-                })
-                .map(String::as_str)
-                .collect();
-            if !found.is_empty() {
-                let tags_str: String = found
-                    .iter()
-                    .map(|t| format!("<{t}>"))
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                locals.tag_warning = Some(format!(
-                    "[system] Warning: {tags_str} found in a message that also contains tool \
-                     calls. Special tags are only processed in your final text response \
-                     (without tool calls) and will be ignored here. To use them, output them \
-                     in a response with no tool calls."
-                ));
-            }
+        check_watched_tags(&msg.message.content, locals);
+        if check_tool_loops(&msg.message.content, locals, renderer) {
+            locals.system_warning = None;
+            return ClaudeEventAction::Completed(format!(
+                "[coven] Session ended early: stuck repeating identical tool calls \
+                 ({} loop warnings).",
+                locals.loop_detector.warnings()
+            ));
         }
     }
 
@@ -357,17 +733,93 @@ fn classify_claude_event<W: Write>(
     } else if reload_detected {
         ClaudeEventAction::Reload(locals.result_text.clone())
     } else if matches!(*inbound, InboundEvent::Result(_)) {
-        if locals.pending_followups.is_empty() {
-            ClaudeEventAction::Completed(locals.result_text.clone())
-        } else {
-            let text = locals.pending_followups.remove(0);
-            ClaudeEventAction::Followup(text)
+        match locals.outbox.next_queued() {
+            Some(text) => ClaudeEventAction::Followup(text),
+            None => ClaudeEventAction::Completed(locals.result_text.clone()),
         }
     } else {
         ClaudeEventAction::Rendered
     }
 }
 
+/// Warn the model about special tags in a non-final assistant message (one
+/// with tool calls). These tags are only processed in Result events, so
+/// they'd be silently ignored.
+fn check_watched_tags(content: &[AssistantContentBlock], locals: &mut SessionLocals) {
+    if locals.watched_tags.is_empty()
+        || !content
+            .iter()
+            .any(|b| matches!(b, AssistantContentBlock::ToolUse { .. }))
+    {
+        return;
+    }
+    let found: Vec<&str> = locals
+        .watched_tags
+        .iter()
+        .filter(|tag| {
+            content.iter().any(|b| {
+                matches!(b, AssistantContentBlock::Text { text }
+                    if crate::protocol::parse::extract_tag_inner(text, tag).is_some())
+            })
+        })
+        .map(String::as_str)
+        .collect();
+    if !found.is_empty() {
+        let tags_str: String = found
+            .iter()
+            .map(|t| format!("<{t}>"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        queue_system_warning(
+            locals,
+            format!(
+                "[system] Warning: {tags_str} found in a message that also contains tool \
+                 calls. Special tags are only processed in your final text response \
+                 (without tool calls) and will be ignored here. To use them, output them \
+                 in a response with no tool calls."
+            ),
+        );
+    }
+}
+
+/// Feed the message's tool calls to the loop detector, nudging the model when
+/// it repeats itself. Returns true once `max_loop_warnings` is reached.
+fn check_tool_loops<W: Write>(
+    content: &[AssistantContentBlock],
+    locals: &mut SessionLocals,
+    renderer: &mut Renderer<W>,
+) -> bool {
+    for block in content {
+        let AssistantContentBlock::ToolUse { name, input, .. } = block else {
+            continue;
+        };
+        if let Some(count) = locals.loop_detector.record(name, input) {
+            renderer.render_tool_loop(name, count);
+            queue_system_warning(
+                locals,
+                format!(
+                    "[system] You have run the same command {count} times with identical \
+                     input; if it keeps failing, try a different approach or explain what \
+                     you're waiting for."
+                ),
+            );
+        }
+    }
+    locals
+        .max_loop_warnings
+        .is_some_and(|max| locals.loop_detector.warnings() >= max)
+}
+
+fn queue_system_warning(locals: &mut SessionLocals, warning: String) {
+    match locals.system_warning {
+        Some(ref mut pending) => {
+            pending.push_str("\n\n");
+            pending.push_str(&warning);
+        }
+        None => locals.system_warning = Some(warning),
+    }
+}
+
 /// Handle a key event during an active session.
 async fn handle_session_key_event<W: Write>(
     key_event: &KeyEvent,
@@ -379,6 +831,7 @@ async fn handle_session_key_event<W: Write>(
     vcr: &VcrContext,
 ) -> Result<LoopAction> {
     let action = input.handle_key(key_event, renderer.writer());
+    let action = record_note(action, renderer, vcr).await?;
     match action {
         InputAction::Activated(_) => {
             renderer.begin_input_line_with_hints();
@@ -387,7 +840,7 @@ async fn handle_session_key_event<W: Write>(
         }
         InputAction::Submit(text, mode) => {
             let flush = flush_event_buffer(locals, state, renderer);
-            send_tag_warning(locals, runner, vcr).await?;
+            send_system_warning(locals, runner, vcr).await?;
             // Completed is intentionally not special-cased here: if the session
             // completed during the flush, state is WaitingForInput and the match
             // below will send the user's text as a follow-up.
@@ -397,24 +850,26 @@ async fn handle_session_key_event<W: Write>(
                     // text as a follow-up — it will be sent after the fork
                     // results return and the parent produces a new Result.
                     renderer.render_followup_queued(&text);
-                    locals.pending_followups.push(text);
+                    locals.outbox.queue(text);
                 }
                 return Ok(action);
             }
             match mode {
                 InputMode::Steering => {
                     renderer.render_steering_sent(&text);
-                    vcr_send_message(runner, vcr, text).await?;
+                    vcr_send_message(runner, vcr, text.clone()).await?;
+                    locals.outbox.sent(text);
                 }
                 InputMode::FollowUp => {
                     if state.status == SessionStatus::WaitingForInput {
                         renderer.render_user_message(&text);
                         state.suppress_next_separator = true;
-                        vcr_send_message(runner, vcr, text).await?;
+                        vcr_send_message(runner, vcr, text.clone()).await?;
+                        locals.outbox.sent(text);
                         state.status = SessionStatus::Running;
                     } else {
                         renderer.render_followup_queued(&text);
-                        locals.pending_followups.push(text);
+                        locals.outbox.queue(text);
                     }
                 }
             }
@@ -422,6 +877,14 @@ async fn handle_session_key_event<W: Write>(
         InputAction::ViewMessage(query) => {
             return Ok(LoopAction::ViewMessage(query));
         }
+        InputAction::Raw(line) => {
+            send_raw_line(line, renderer, runner, vcr).await?;
+            if state.status == SessionStatus::WaitingForInput {
+                renderer.show_prompt_with_hints(false);
+                input.activate();
+                input.set_has_hint_line();
+            }
+        }
         InputAction::Interrupt => {
             runner.kill().await?;
             return Ok(LoopAction::Return(SessionOutcome::Interrupted));
@@ -429,9 +892,14 @@ async fn handle_session_key_event<W: Write>(
         InputAction::EndSession => {
             runner.close_input();
         }
-        InputAction::Cancel | InputAction::Dismiss => {
+        action @ (InputAction::Cancel | InputAction::Dismiss | InputAction::Queue(_)) => {
+            // Edit the queue before flushing, so a buffered Result doesn't
+            // send a follow-up the user just dropped.
+            if let InputAction::Queue(command) = action {
+                edit_queue(command, &mut locals.outbox, renderer);
+            }
             let flush = flush_event_buffer(locals, state, renderer);
-            send_tag_warning(locals, runner, vcr).await?;
+            send_system_warning(locals, runner, vcr).await?;
             if let FlushResult::Completed(ref result_text) = flush {
                 return Ok(LoopAction::Return(SessionOutcome::Completed {
                     result_text: result_text.clone(),
@@ -446,6 +914,14 @@ async fn handle_session_key_event<W: Write>(
                 input.set_has_hint_line();
             }
         }
+        InputAction::Redraw => {
+            renderer.redraw_screen();
+            if input.is_active() {
+                renderer.begin_input_line_with_hints();
+                input.set_has_hint_line();
+                input.redraw(renderer.writer());
+            }
+        }
         InputAction::WaitRequested => {
             state.wait_requested = !state.wait_requested;
             if state.wait_requested {
@@ -454,18 +930,48 @@ async fn handle_session_key_event<W: Write>(
                 renderer.write_raw("\r\n[wait cancelled]\r\n");
             }
         }
-        InputAction::Interactive | InputAction::None => {}
+        // Notes were already turned into Submit/Cancel by `record_note`.
+        InputAction::Interactive | InputAction::Note { .. } | InputAction::None => {}
     }
     Ok(LoopAction::Continue)
 }
 
-/// Send a pending tag warning (if any) to the running session via stdin.
-async fn send_tag_warning(
+/// Carry out `:queue`: list the queued follow-ups, or drop one of them.
+fn edit_queue<W: Write>(command: QueueCommand, outbox: &mut Outbox, renderer: &mut Renderer<W>) {
+    match command {
+        QueueCommand::List => renderer.render_queue(&outbox.queued()),
+        QueueCommand::Drop(n) => match outbox.unqueue(n) {
+            Some(text) => renderer.render_followup_dropped(&text),
+            None => renderer.render_warning(&format!("no queued follow-up {n}")),
+        },
+    }
+}
+
+/// Write a `:raw` line to claude's stdin, unless it isn't one JSON object.
+async fn send_raw_line<W: Write>(
+    line: String,
+    renderer: &mut Renderer<W>,
+    runner: &mut SessionRunner,
+    vcr: &VcrContext,
+) -> Result<()> {
+    if let Err(e) = check_raw_message(&line) {
+        renderer.render_warning(&format!(":raw needs a single JSON object: {e}"));
+        return Ok(());
+    }
+    renderer.render_raw_sent(&line);
+    vcr.call("send_raw_line", line, async |l: &String| {
+        runner.send_raw_line(l)
+    })
+    .await
+}
+
+/// Send a pending system warning (if any) to the running session via stdin.
+async fn send_system_warning(
     locals: &mut SessionLocals,
     runner: &mut SessionRunner,
     vcr: &VcrContext,
 ) -> Result<()> {
-    if let Some(warning) = locals.tag_warning.take() {
+    if let Some(warning) = locals.system_warning.take() {
         vcr_send_message(runner, vcr, warning).await?;
     }
     Ok(())
@@ -486,21 +992,33 @@ async fn send_followup<W: Write>(
     Ok(())
 }
 
+/// The parts of the session loop a fork takes over while its children run.
+struct ForkContext<'a, 'f, W: Write> {
+    state: &'a mut SessionState,
+    renderer: &'a mut Renderer<W>,
+    input: &'a mut InputHandler,
+    runner: &'a mut SessionRunner,
+    io: &'a mut Io,
+    vcr: &'a VcrContext,
+    features: &'a SessionFeatures<'f>,
+}
+
 /// Execute a fork: kill parent, run children, respawn parent with results.
 ///
 /// The parent CLI process is killed before fork children run to prevent async
 /// task notifications from triggering an invisible continuation. After children
 /// complete, a fresh parent session is spawned (resuming the same session ID)
 /// with the reintegration message as the initial prompt.
-async fn execute_fork<W: Write>(
-    tasks: Vec<String>,
-    state: &mut SessionState,
-    renderer: &mut Renderer<W>,
-    runner: &mut SessionRunner,
-    io: &mut Io,
-    vcr: &VcrContext,
-    features: &SessionFeatures<'_>,
-) -> Result<()> {
+async fn execute_fork<W: Write>(tasks: Vec<String>, cx: ForkContext<'_, '_, W>) -> Result<()> {
+    let ForkContext {
+        state,
+        renderer,
+        input,
+        runner,
+        io,
+        vcr,
+        features,
+    } = cx;
     let session_id = state
         .session_id
         .clone()
@@ -517,7 +1035,16 @@ async fn execute_fork<W: Write>(
     // from triggering an invisible continuation while fork children run.
     runner.kill().await?;
 
-    let msg = fork::run_fork(&session_id, tasks, fork_cfg, renderer, vcr).await?;
+    let msg = fork::run_fork(
+        fork::ForkOrigin::Session(&session_id),
+        tasks,
+        fork_cfg,
+        renderer,
+        input,
+        io,
+        vcr,
+    )
+    .await?;
 
     // Respawn the parent session (resuming the same session ID) with the
     // reintegration message, so the event loop continues with a fresh process.
@@ -550,7 +1077,7 @@ async fn process_claude_event<W: Write>(
     match event {
         AppEvent::Claude(inbound) => {
             let action = classify_claude_event(&inbound, locals, state, renderer);
-            if let Some(warning) = locals.tag_warning.take() {
+            if let Some(warning) = locals.system_warning.take() {
                 vcr_send_message(runner, vcr, warning).await?;
             }
             match action {
@@ -569,9 +1096,10 @@ async fn process_claude_event<W: Write>(
                 ClaudeEventAction::Rendered => {}
             }
         }
-        AppEvent::ParseWarning(warning) | AppEvent::Stderr(warning) => {
-            renderer.render_warning(&warning);
-        }
+        AppEvent::ParseWarning(warning) => renderer.render_warning(&warning),
+        AppEvent::Stderr(text) => renderer.render_stderr(&text),
+        AppEvent::SendProgress { sent, total } => renderer.render_send_progress(sent, total),
+        AppEvent::SendFailed(error) => renderer.render_send_failed(&error),
         AppEvent::ProcessExit(code) => {
             renderer.render_exit(code);
             state.status = SessionStatus::Ended;
@@ -605,9 +1133,10 @@ fn flush_event_buffer<W: Write>(
                     ClaudeEventAction::Rendered => {}
                 }
             }
-            AppEvent::ParseWarning(warning) | AppEvent::Stderr(warning) => {
-                renderer.render_warning(&warning);
-            }
+            AppEvent::ParseWarning(warning) => renderer.render_warning(&warning),
+            AppEvent::Stderr(text) => renderer.render_stderr(&text),
+            AppEvent::SendProgress { sent, total } => renderer.render_send_progress(sent, total),
+            AppEvent::SendFailed(error) => renderer.render_send_failed(&error),
             AppEvent::ProcessExit(code) => {
                 renderer.render_exit(code);
                 state.status = SessionStatus::Ended;
@@ -659,8 +1188,11 @@ pub enum WaitResult {
     Text(String),
     /// User wants to drop into the native Claude TUI.
     Interactive,
-    /// User dismissed the wait prompt (Escape on empty buffer).
+    /// User dismissed the wait prompt (Escape, or Ctrl+W in a Ctrl+W wait,
+    /// on an empty buffer).
     Dismissed,
+    /// User pressed Ctrl+D: end this session. Most callers exit, as on Ctrl+C.
+    EndSession,
 }
 
 /// Result from `wait_for_dismissable_input` — like `Option<String>` but
@@ -670,6 +1202,8 @@ pub enum WaitInterruptResult {
     Text(String),
     /// User dismissed the wait (Escape on empty buffer).
     Dismissed,
+    /// User pressed Ctrl+D (see [`WaitResult::EndSession`]).
+    EndSession,
 }
 
 /// Show a prompt and wait for user to type a follow-up or exit.
@@ -685,8 +1219,9 @@ pub async fn wait_for_followup<W: Write>(
         return Ok(FollowUpAction::Exit);
     }
     renderer.write_raw("\x07");
+    crate::notify::waiting("claude finished; waiting for input", io, renderer, vcr).await?;
     vcr.call("idle", (), async |(): &()| Ok(())).await?;
-    match wait_for_text_input(input, renderer, false, io, vcr).await? {
+    match wait_for_text_input(input, renderer, false, false, io, vcr).await? {
         Some(WaitResult::Text(text)) => {
             state.suppress_next_separator = true;
             vcr_send_message(runner, vcr, text).await?;
@@ -694,7 +1229,7 @@ pub async fn wait_for_followup<W: Write>(
             Ok(FollowUpAction::Sent)
         }
         Some(WaitResult::Interactive) => Ok(FollowUpAction::Interactive),
-        Some(WaitResult::Dismissed) | None => Ok(FollowUpAction::Exit),
+        Some(WaitResult::Dismissed | WaitResult::EndSession) | None => Ok(FollowUpAction::Exit),
     }
 }
 
@@ -711,7 +1246,7 @@ pub async fn wait_for_user_input<W: Write>(
     if io.is_headless() {
         return Ok(None);
     }
-    wait_for_text_input(input, renderer, true, io, vcr).await
+    wait_for_text_input(input, renderer, true, false, io, vcr).await
 }
 
 /// Wait for user input from the interrupted state, handling Ctrl+O to open
@@ -725,15 +1260,28 @@ pub async fn wait_for_interrupt_input<W: Write>(
     session_id: &str,
     base_config: &SessionConfig,
 ) -> Result<Option<String>> {
-    match wait_for_input_inner(input, renderer, io, vcr, session_id, base_config).await? {
+    match wait_for_interrupt_choice(input, renderer, io, vcr, session_id, base_config).await? {
         Some(WaitInterruptResult::Text(text)) => Ok(Some(text)),
-        Some(WaitInterruptResult::Dismissed) | None => Ok(None),
+        Some(WaitInterruptResult::Dismissed | WaitInterruptResult::EndSession) | None => Ok(None),
     }
 }
 
+/// Like `wait_for_interrupt_input`, but tells Escape and Ctrl+D apart from
+/// Ctrl+C, for a worker's dispatch phase where Ctrl+D skips the dispatch.
+pub async fn wait_for_interrupt_choice<W: Write>(
+    input: &mut InputHandler,
+    renderer: &mut Renderer<W>,
+    io: &mut Io,
+    vcr: &VcrContext,
+    session_id: &str,
+    base_config: &SessionConfig,
+) -> Result<Option<WaitInterruptResult>> {
+    wait_for_input_inner(input, renderer, io, vcr, session_id, base_config, false).await
+}
+
 /// Like `wait_for_interrupt_input`, but distinguishes "user dismissed the
-/// wait" (Escape on empty buffer) from "user exited" (Ctrl+C / Ctrl+D).
-/// Used for Ctrl+W waits where dismiss means "proceed without input."
+/// wait" (Escape or Ctrl+W on an empty buffer) from "user exited" (Ctrl+C /
+/// Ctrl+D). Used for Ctrl+W waits where dismiss means "proceed without input."
 pub async fn wait_for_dismissable_input<W: Write>(
     input: &mut InputHandler,
     renderer: &mut Renderer<W>,
@@ -742,7 +1290,7 @@ pub async fn wait_for_dismissable_input<W: Write>(
     session_id: &str,
     base_config: &SessionConfig,
 ) -> Result<Option<WaitInterruptResult>> {
-    wait_for_input_inner(input, renderer, io, vcr, session_id, base_config).await
+    wait_for_input_inner(input, renderer, io, vcr, session_id, base_config, true).await
 }
 
 /// Shared implementation for `wait_for_interrupt_input` and
@@ -754,6 +1302,7 @@ async fn wait_for_input_inner<W: Write>(
     vcr: &VcrContext,
     session_id: &str,
     base_config: &SessionConfig,
+    dismissable: bool,
 ) -> Result<Option<WaitInterruptResult>> {
     // Headless: no way to receive user input. Return `Dismissed` so callers
     // that distinguish "dismissed" from "exited" (ralph/worker) fall through
@@ -763,13 +1312,14 @@ async fn wait_for_input_inner<W: Write>(
         return Ok(Some(WaitInterruptResult::Dismissed));
     }
     io.clear_event_channel();
+    crate::notify::waiting("waiting for input", io, renderer, vcr).await?;
     vcr.call("idle", (), async |(): &()| Ok(())).await?;
     let interactive_config = SessionConfig {
         resume: Some(session_id.to_string()),
         ..base_config.clone()
     };
     loop {
-        match wait_for_text_input(input, renderer, false, io, vcr).await? {
+        match wait_for_text_input(input, renderer, false, dismissable, io, vcr).await? {
             Some(WaitResult::Text(text)) => return Ok(Some(WaitInterruptResult::Text(text))),
             Some(WaitResult::Interactive) => {
                 open_interactive_session(&interactive_config, io)?;
@@ -778,6 +1328,7 @@ async fn wait_for_input_inner<W: Write>(
             Some(WaitResult::Dismissed) => {
                 return Ok(Some(WaitInterruptResult::Dismissed));
             }
+            Some(WaitResult::EndSession) => return Ok(Some(WaitInterruptResult::EndSession)),
             None => return Ok(None),
         }
     }
@@ -787,10 +1338,12 @@ async fn wait_for_input_inner<W: Write>(
 ///
 /// Shows the prompt, activates input, and loops on events.
 /// Returns the submitted text / interactive request, or None if the user interrupted/ended.
+/// When `dismissable`, Ctrl+W on an empty prompt dismisses it like Escape.
 async fn wait_for_text_input<W: Write>(
     input: &mut InputHandler,
     renderer: &mut Renderer<W>,
     is_first_message: bool,
+    dismissable: bool,
     io: &mut Io,
     vcr: &VcrContext,
 ) -> Result<Option<WaitResult>> {
@@ -799,6 +1352,9 @@ async fn wait_for_text_input<W: Write>(
     if !is_first_message {
         input.set_has_hint_line();
     }
+    if input.restore_draft() {
+        input.redraw(renderer.writer());
+    }
 
     loop {
         let io_event: IoEvent = vcr
@@ -806,7 +1362,21 @@ async fn wait_for_text_input<W: Write>(
             .await?;
         match io_event {
             IoEvent::Terminal(Event::Key(key_event)) => {
+                let wait_key = key_event.code == KeyCode::Char('w')
+                    && key_event.modifiers.contains(KeyModifiers::CONTROL);
+                if dismissable && wait_key && input.is_empty() {
+                    input.discard(renderer.writer());
+                    return Ok(Some(WaitResult::Dismissed));
+                }
                 let action = input.handle_key(&key_event, renderer.writer());
+                let action = record_note(action, renderer, vcr).await?;
+                if matches!(action, InputAction::Raw(_)) {
+                    renderer.render_warning(":raw only works while claude is running");
+                }
+                if let InputAction::Queue(command) = action {
+                    // Nothing waits in a queue here: a follow-up goes out at once.
+                    edit_queue(command, &mut Outbox::default(), renderer);
+                }
                 match action {
                     InputAction::Submit(text, _) => {
                         renderer.render_user_message(&text);
@@ -823,7 +1393,7 @@ async fn wait_for_text_input<W: Write>(
                             input.set_has_hint_line();
                         }
                     }
-                    InputAction::Cancel => {
+                    InputAction::Cancel | InputAction::Raw(_) | InputAction::Queue(_) => {
                         renderer.show_prompt_with_hints(is_first_message);
                         input.activate();
                         if !is_first_message {
@@ -833,19 +1403,28 @@ async fn wait_for_text_input<W: Write>(
                     InputAction::Dismiss => {
                         return Ok(Some(WaitResult::Dismissed));
                     }
-                    InputAction::Interrupt | InputAction::EndSession => {
-                        return Ok(None);
-                    }
+                    InputAction::Interrupt => return Ok(None),
+                    InputAction::EndSession => return Ok(Some(WaitResult::EndSession)),
                     InputAction::Activated(_) => {
                         renderer.begin_input_line_with_hints();
                         input.set_has_hint_line();
                         input.redraw(renderer.writer());
                     }
-                    InputAction::WaitRequested | InputAction::None => {}
+                    InputAction::Redraw => {
+                        renderer.redraw_screen();
+                        renderer.show_prompt_with_hints(is_first_message);
+                        input.redraw(renderer.writer());
+                    }
+                    InputAction::WaitRequested | InputAction::Note { .. } | InputAction::None => {}
                 }
             }
             IoEvent::Claude(AppEvent::ProcessExit(_)) => return Ok(None),
-            IoEvent::Terminal(_) | IoEvent::Claude(_) => {}
+            IoEvent::Terminal(event) => other_terminal_event(event, renderer, input),
+            IoEvent::Shutdown => {
+                io.note_shutdown();
+                return Ok(None);
+            }
+            IoEvent::Claude(_) | IoEvent::Silence | IoEvent::SessionTimeout => {}
         }
     }
 }
@@ -858,23 +1437,46 @@ pub async fn spawn_session(
 ) -> Result<SessionRunner> {
     vcr.call("spawn", config, async |c: &SessionConfig| {
         let tx = io.replace_event_channel();
-        SessionRunner::spawn(c.clone(), tx).await
+        SessionRunner::spawn(c.clone(), tx)
     })
     .await
 }
 
+/// Sent to a ralph or worker session that hit claude's `--max-turns` limit.
+pub const WRAP_UP_PROMPT: &str =
+    "You reached the maximum number of turns. Please wrap up and produce your final output now.";
+
+/// Resume a session that hit `--max-turns` with [`WRAP_UP_PROMPT`], so a
+/// loop gets its final output (and any tags) rather than just an error.
+/// Callers do this once per session before giving up on it.
+pub async fn spawn_wrap_up_session<W: Write>(
+    session_id: String,
+    base_config: &SessionConfig,
+    renderer: &mut Renderer<W>,
+    io: &mut Io,
+    vcr: &VcrContext,
+) -> Result<(SessionRunner, SessionState)> {
+    renderer.render_warning("max turns reached \u{2014} resuming once to wrap up");
+    let config = base_config.resume_with(WRAP_UP_PROMPT.to_string(), session_id.clone());
+    let runner = spawn_session(config, io, vcr).await?;
+    let state = SessionState {
+        session_id: Some(session_id),
+        ..Default::default()
+    };
+    Ok((runner, state))
+}
+
 /// Restore terminal state after exclusive access (interactive session or pager).
 ///
 /// Flushes buffered input from the kernel queue, drains any residual events
 /// queued before the pause took effect, resumes the background terminal reader,
-/// and re-enables raw mode.
+/// and re-enables raw mode and bracketed paste.
 fn restore_terminal(io: &mut Io) -> Result<()> {
-    // SAFETY: tcflush on STDIN_FILENO with TCIFLUSH is a POSIX syscall that
-    // discards buffered input bytes — no memory or resource safety concerns.
-    unsafe { libc::tcflush(libc::STDIN_FILENO, libc::TCIFLUSH) };
+    crate::platform::flush_stdin_input();
     io.drain_term_events();
     io.resume_term_reader();
     terminal::enable_raw_mode().context("failed to re-enable raw mode")?;
+    crate::display::set_bracketed_paste(true);
     Ok(())
 }
 
@@ -897,6 +1499,7 @@ pub fn open_interactive_session(config: &SessionConfig, io: &mut Io) -> Result<S
     // exclusive access to stdin — prevents keypress competition.
     io.pause_term_reader();
 
+    crate::display::set_bracketed_paste(false);
     terminal::disable_raw_mode().context("failed to disable raw mode for interactive session")?;
     print!("\r\n[opening interactive session — exit to return]\r\n");
 
@@ -952,31 +1555,73 @@ fn generate_uuid_v4() -> String {
     )
 }
 
+/// Store a `:note` on the renderer and translate it into the action the
+/// caller should handle instead: a steering `Submit` for `:note!`, otherwise
+/// `Cancel` (flush buffered output and restore the prompt).
+pub async fn record_note<W: Write>(
+    action: InputAction,
+    renderer: &mut Renderer<W>,
+    vcr: &VcrContext,
+) -> Result<InputAction> {
+    let InputAction::Note { text, steer } = action else {
+        return Ok(action);
+    };
+    if text.is_empty() {
+        renderer.render_warning("usage: :note <text> (:note! also sends it to claude)");
+        return Ok(InputAction::Cancel);
+    }
+    let now: u64 = vcr
+        .call("note_time", (), async |(): &()| {
+            Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+        })
+        .await?;
+    renderer.add_note(&utc_clock(now), &text);
+    Ok(if steer {
+        InputAction::Submit(text, InputMode::Steering)
+    } else {
+        InputAction::Cancel
+    })
+}
+
+/// Format seconds since the Unix epoch as a UTC wall-clock time (`14:03:22Z`).
+fn utc_clock(secs: u64) -> String {
+    let t = UtcDateTime::from_unix(secs);
+    format!("{:02}:{:02}:{:02}Z", t.hour, t.minute, t.second)
+}
+
 /// Open a message in $PAGER, looked up by label query (e.g. "3" or "2/1").
 ///
 /// Pauses the background terminal reader so the pager gets exclusive stdin
 /// access — same pattern as [`open_interactive_session`].
 pub fn view_message<W: Write>(renderer: &mut Renderer<W>, query: &str, io: &mut Io) -> Result<()> {
-    use crate::display::renderer::format_message;
+    use crate::display::renderer::{format_index, format_message, format_notes};
 
     // Check completed messages first, then fall back to in-progress thinking block.
-    let mut content = if let Some(c) = format_message(renderer.messages(), query) {
-        c
-    } else if let Some(ref in_progress) = renderer.in_progress_thinking()
-        && let Some(c) = format_message(std::slice::from_ref(in_progress), query)
-    {
-        c
+    let lookup = if query.is_empty() {
+        Ok(format_index(renderer.messages()))
     } else {
-        renderer.write_raw(&format!("No message {query}\r\n"));
-        return Ok(());
+        format_message(renderer.messages(), query)
+    };
+    let mut content = match lookup {
+        Ok(c) => c,
+        Err(_) if query.eq_ignore_ascii_case("notes") => format_notes(renderer.notes()),
+        Err(err) => {
+            let in_progress = renderer
+                .in_progress_thinking()
+                .and_then(|thinking| format_message(std::slice::from_ref(&thinking), query).ok());
+            let Some(c) = in_progress else {
+                renderer.write_raw(&format!("{err}\r\n"));
+                return Ok(());
+            };
+            c
+        }
     };
 
     // Pad short content with trailing newlines so the pager shows it top-aligned.
-    if let Ok((_, rows)) = terminal::size() {
-        let line_count = content.chars().filter(|&c| c == '\n').count() + 1;
-        if line_count < rows as usize {
-            content.extend(std::iter::repeat_n('\n', rows as usize - line_count));
-        }
+    let rows = renderer.height();
+    let line_count = content.chars().filter(|&c| c == '\n').count() + 1;
+    if line_count < rows {
+        content.extend(std::iter::repeat_n('\n', rows - line_count));
     }
 
     // Pause the background terminal reader so the pager gets exclusive
@@ -985,18 +1630,16 @@ pub fn view_message<W: Write>(renderer: &mut Renderer<W>, query: &str, io: &mut
 
     // Leave raw mode so the pager can handle keyboard input.
     // The pager manages its own alternate screen.
+    crate::display::set_bracketed_paste(false);
     terminal::disable_raw_mode().ok();
 
-    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less".to_string());
-    let mut child = match StdCommand::new(&pager)
-        .arg("-R") // handle ANSI colors
-        .stdin(std::process::Stdio::piped())
-        .spawn()
-    {
+    let mut child = match spawn_pager() {
         Ok(child) => child,
         Err(e) => {
             restore_terminal(io)?;
-            renderer.write_raw(&format!("Failed to open pager '{pager}': {e}\r\n"));
+            renderer.write_raw(&format!("{e} \u{2014} showing it here\r\n"));
+            let content = content.trim_end_matches('\n').replace('\n', "\r\n");
+            renderer.write_raw(&format!("{content}\r\n"));
             return Ok(());
         }
     };
@@ -1015,3 +1658,308 @@ pub fn view_message<W: Write>(renderer: &mut Renderer<W>, query: &str, io: &mut
     restore_terminal(io)?;
     Ok(())
 }
+
+/// Start the first pager from [`crate::platform::pager_commands`] that
+/// spawns, reading from a pipe. When none does, the error says why the
+/// first one (`$PAGER`, if set) failed.
+fn spawn_pager() -> Result<std::process::Child, String> {
+    let pager = std::env::var("PAGER").ok();
+    let mut first_error = None;
+    for (program, args) in crate::platform::pager_commands(pager.as_deref()) {
+        let spawned = StdCommand::new(&program)
+            .args(&args)
+            .stdin(std::process::Stdio::piped())
+            .spawn();
+        match spawned {
+            Ok(child) => return Ok(child),
+            Err(e) => {
+                first_error.get_or_insert_with(|| format!("Failed to open pager '{program}': {e}"));
+            }
+        }
+    }
+    Err(first_error.unwrap_or_else(|| "No pager to open".to_string()))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn render_init(state: &mut SessionState) -> String {
+        let init = crate::protocol::parse::parse_line(
+            r#"{"type":"system","subtype":"init","session_id":"abc-123","model":"opus","tools":[]}"#,
+        )
+        .unwrap()
+        .unwrap();
+        let mut renderer = Renderer::with_writer(Vec::<u8>::new());
+        handle_inbound(&init, state, &mut renderer, false);
+        String::from_utf8(renderer.writer().clone()).unwrap()
+    }
+
+    #[test]
+    fn init_renders_resumed_header_once() {
+        let mut state = SessionState {
+            resuming: true,
+            ..Default::default()
+        };
+        assert!(render_init(&mut state).contains("Resumed session abc-123 (opus)"));
+        assert!(!state.resuming);
+        assert_eq!(state.session_id.as_deref(), Some("abc-123"));
+
+        let mut fresh = SessionState::default();
+        let output = render_init(&mut fresh);
+        assert!(output.contains("Session abc-123 (opus)"));
+        assert!(!output.contains("Resumed"));
+    }
+
+    #[test]
+    fn rate_limit_renders_only_when_notable() {
+        let render = |status: &str| {
+            let line = format!(
+                r#"{{"type":"rate_limit_event","rate_limit_info":{{"status":"{status}","resetsAt":1771545600,"rateLimitType":"seven_day","utilization":0.76}},"uuid":"a","session_id":"b"}}"#
+            );
+            let event = crate::protocol::parse::parse_line(&line).unwrap().unwrap();
+            let mut renderer = Renderer::with_writer(Vec::<u8>::new());
+            handle_inbound(&event, &mut SessionState::default(), &mut renderer, false);
+            String::from_utf8(renderer.writer().clone()).unwrap()
+        };
+        assert!(render("allowed").is_empty());
+        assert!(render("allowed_warning").contains("seven_day usage at 76%"));
+        assert!(render("rejected").contains("seven_day limit reached"));
+    }
+
+    #[test]
+    fn api_errors_render_and_mark_state() {
+        let handle = |state: &mut SessionState, line: &str| {
+            let event = crate::protocol::parse::parse_line(line).unwrap().unwrap();
+            let mut renderer = Renderer::with_writer(Vec::<u8>::new());
+            handle_inbound(&event, state, &mut renderer, false);
+            String::from_utf8(renderer.writer().clone()).unwrap()
+        };
+
+        let api_error = |status: u16, message: &str| {
+            serde_json::json!({
+                "type": "result",
+                "subtype": "success",
+                "is_error": true,
+                "api_error_status": status,
+                "terminal_reason": "api_error",
+                "result": message,
+                "session_id": "x",
+            })
+            .to_string()
+        };
+
+        let mut state = SessionState::default();
+        let out = handle(&mut state, &api_error(529, "API Error: 529 Overloaded"));
+        assert!(
+            out.contains("[api error 529] API Error: 529 Overloaded"),
+            "{out}"
+        );
+        assert!(out.contains("retry"), "{out}");
+        assert!(state.failed_retryably());
+
+        // A successful result means claude recovered on its own.
+        handle(
+            &mut state,
+            r#"{"type":"result","subtype":"success","is_error":false,"total_cost_usd":0.01,"num_turns":1,"duration_ms":100,"result":"ok","session_id":"x"}"#,
+        );
+        assert!(!state.failed_retryably());
+        assert!(state.api_error.is_none());
+
+        let out = handle(&mut state, &api_error(400, "Prompt is too long"));
+        assert!(out.contains("Prompt is too long"), "{out}");
+        assert!(out.contains("/compact"), "{out}");
+        assert!(!state.failed_retryably());
+    }
+
+    fn next_event(event: &IoEvent) -> serde_json::Value {
+        serde_json::json!({"label": "next_event", "args": null, "result": {"Ok": event}})
+    }
+
+    /// Type `text` and submit it with Enter (`modifiers` picks the mode).
+    fn submit(text: &str, modifiers: KeyModifiers) -> Vec<serde_json::Value> {
+        let key = |code, modifiers| {
+            next_event(&IoEvent::Terminal(Event::Key(KeyEvent::new(
+                code, modifiers,
+            ))))
+        };
+        text.chars()
+            .map(|c| key(KeyCode::Char(c), KeyModifiers::NONE))
+            .chain([key(KeyCode::Enter, modifiers)])
+            .collect()
+    }
+
+    /// Run a session against recorded calls, returning how it ended and
+    /// what was rendered.
+    async fn run_replayed(
+        calls: &[serde_json::Value],
+        input: &mut InputHandler,
+        io: &mut Io,
+        protocol_log: Option<&Path>,
+    ) -> (SessionOutcome, SessionState, String) {
+        let ndjson: Vec<String> = calls.iter().map(ToString::to_string).collect();
+        let vcr = VcrContext::replay(&ndjson.join("\n")).unwrap();
+        let base_config = SessionConfig::default();
+        let features = SessionFeatures {
+            fork_config: None,
+            reload_enabled: false,
+            base_config: &base_config,
+            watched_tags: Vec::new(),
+            steering_timeout: None,
+            resume_wedged: false,
+            budget: None,
+            max_loop_warnings: None,
+            protocol_log,
+        };
+        let mut renderer = Renderer::with_writer(Vec::<u8>::new());
+        let mut state = SessionState::default();
+        let outcome = run_session(
+            &mut SessionRunner::stub(),
+            &mut state,
+            &mut renderer,
+            input,
+            io,
+            &vcr,
+            &features,
+        )
+        .await
+        .unwrap();
+        assert!(vcr.unreplayed_labels().is_empty());
+        let output = String::from_utf8(renderer.writer().clone()).unwrap();
+        (outcome, state, output)
+    }
+
+    #[tokio::test]
+    async fn multi_line_paste_is_sent_as_one_steering_message() {
+        let trace = "panicked at src/lib.rs:9:5:\nindex out of bounds";
+        let mut calls = vec![next_event(&IoEvent::Terminal(Event::Paste(
+            trace.replace('\n', "\r\n"),
+        )))];
+        calls.extend(submit("", KeyModifiers::NONE));
+        calls.push(serde_json::json!({
+            "label": "send_message",
+            "args": trace,
+            "result": {"Ok": null},
+        }));
+        calls.push(next_event(&IoEvent::Claude(AppEvent::ProcessExit(Some(0)))));
+        let (_, _, output) =
+            run_replayed(&calls, &mut InputHandler::new(2), &mut Io::dummy(), None).await;
+        assert!(
+            output.contains("src/lib.rs:9:5:\u{23ce}index"),
+            "{output:?}"
+        );
+        assert!(
+            output.contains("src/lib.rs:9:5:\r\nindex out of bounds"),
+            "{output:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn raw_command_rejects_malformed_json_and_sends_objects_verbatim() {
+        let raw = r#"{"type":"control_request","request":{"subtype":"interrupt"}}"#;
+        let mut calls = submit(r#":raw {"type":"#, KeyModifiers::NONE);
+        calls.extend(submit(&format!(":raw {raw}"), KeyModifiers::NONE));
+        calls.push(
+            serde_json::json!({"label": "send_raw_line", "args": raw, "result": {"Ok": null}}),
+        );
+        calls.push(next_event(&IoEvent::Claude(AppEvent::ProcessExit(Some(0)))));
+        let mut input = InputHandler::new(2);
+        input.set_raw_commands(true);
+        let (outcome, state, output) =
+            run_replayed(&calls, &mut input, &mut Io::dummy(), None).await;
+        assert!(matches!(outcome, SessionOutcome::ProcessExited));
+        assert!(
+            output.contains(":raw needs a single JSON object: EOF"),
+            "{output}"
+        );
+        assert!(
+            output.contains(&format!("[raw \u{2192}] {raw}")),
+            "{output}"
+        );
+        // Raw lines aren't the user's messages, so they aren't tracked.
+        assert!(state.undelivered.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unparsed_output_goes_to_the_protocol_log() {
+        let warning = "Failed to parse claude output: expected value\n  Line: {oops";
+        let event = next_event(&IoEvent::Claude(AppEvent::ParseWarning(warning.into())));
+        let exit = next_event(&IoEvent::Claude(AppEvent::ProcessExit(Some(0))));
+        let log = |created: bool| {
+            serde_json::json!({
+                "label": "log_unparsed",
+                "args": ["/tmp/coven-protocol.log", warning],
+                "result": {"Ok": created},
+            })
+        };
+        let calls = [
+            event.clone(),
+            log(true),
+            event.clone(),
+            log(false),
+            exit.clone(),
+        ];
+        let path = Path::new("/tmp/coven-protocol.log");
+        let (_, _, output) = run_replayed(
+            &calls,
+            &mut InputHandler::new(2),
+            &mut Io::dummy(),
+            Some(path),
+        )
+        .await;
+        assert_eq!(
+            output.matches("unparsed output logged to").count(),
+            1,
+            "{output}"
+        );
+        assert_eq!(
+            output.matches("[warn] Failed to parse").count(),
+            2,
+            "{output}"
+        );
+
+        // Without --debug-protocol, nothing is logged.
+        run_replayed(
+            &[event, exit],
+            &mut InputHandler::new(2),
+            &mut Io::dummy(),
+            None,
+        )
+        .await;
+    }
+
+    #[test]
+    fn protocol_log_reports_creation_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("debug").join("protocol.log");
+        assert!(append_protocol_log(&path, "first").unwrap());
+        assert!(!append_protocol_log(&path, "second").unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+    }
+
+    #[test]
+    fn utc_clock_formats_time_of_day() {
+        assert_eq!(utc_clock(0), "00:00:00Z");
+        assert_eq!(utc_clock(1_792_120_691), "03:18:11Z");
+    }
+
+    #[test]
+    fn wedged_resume_prepends_undelivered_steering() {
+        let prompt = compose_wedged_resume(&["use the v2 API".into(), "and skip tests".into()]);
+        assert!(prompt.starts_with("use the v2 API\n\nand skip tests\n\n"));
+        assert!(prompt.contains("stopped responding"));
+    }
+
+    #[test]
+    fn activity_ticks_after_delay_then_each_second() {
+        let activity = ActivityTimer::new(true);
+        let start = activity.last_event;
+        let at = |ms| start + Duration::from_millis(ms);
+        assert_eq!(activity.next_tick(at(0)), at(2000));
+        assert_eq!(activity.next_tick(at(1999)), at(2000));
+        assert_eq!(activity.next_tick(at(2000)), at(3000));
+        assert_eq!(activity.next_tick(at(2500)), at(3000));
+        assert_eq!(activity.next_tick(at(7000)), at(8000));
+    }
+}
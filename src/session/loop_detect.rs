@@ -0,0 +1,116 @@
+//! Spotting a model stuck repeating the same tool call.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use serde_json::Value;
+
+/// Identical consecutive tool calls that count as a loop.
+pub const LOOP_THRESHOLD: usize = 3;
+
+/// Tracks the run of identical consecutive tool calls in a session.
+///
+/// Calls are compared by tool name and input. Object keys are sorted when
+/// serialized, so inputs that differ only in key order count as identical.
+#[derive(Debug)]
+pub struct LoopDetector {
+    threshold: usize,
+    last: Option<u64>,
+    repeats: usize,
+    warned: bool,
+    warnings: usize,
+}
+
+impl LoopDetector {
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            last: None,
+            repeats: 0,
+            warned: false,
+            warnings: 0,
+        }
+    }
+
+    /// Record a tool call. Returns the length of the run when it first
+    /// reaches the threshold — once per distinct loop, however long it goes on.
+    pub fn record(&mut self, name: &str, input: &Value) -> Option<usize> {
+        let mut hasher = DefaultHasher::new();
+        name.hash(&mut hasher);
+        input.to_string().hash(&mut hasher);
+        let key = hasher.finish();
+
+        if self.last == Some(key) {
+            self.repeats += 1;
+        } else {
+            self.last = Some(key);
+            self.repeats = 1;
+            self.warned = false;
+        }
+        if self.warned || self.repeats < self.threshold {
+            return None;
+        }
+        self.warned = true;
+        self.warnings += 1;
+        Some(self.repeats)
+    }
+
+    /// Loops warned about so far.
+    pub fn warnings(&self) -> usize {
+        self.warnings
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn warns_once_per_loop() {
+        let mut detector = LoopDetector::new(3);
+        let input = json!({"command": "cargo test"});
+        assert_eq!(detector.record("Bash", &input), None);
+        assert_eq!(detector.record("Bash", &input), None);
+        assert_eq!(detector.record("Bash", &input), Some(3));
+        assert_eq!(detector.record("Bash", &input), None);
+        assert_eq!(detector.record("Bash", &input), None);
+        assert_eq!(detector.warnings(), 1);
+    }
+
+    #[test]
+    fn different_call_breaks_the_run() {
+        let mut detector = LoopDetector::new(3);
+        let test = json!({"command": "cargo test"});
+        let build = json!({"command": "cargo build"});
+        detector.record("Bash", &test);
+        detector.record("Bash", &test);
+        assert_eq!(detector.record("Bash", &build), None);
+        assert_eq!(detector.record("Bash", &test), None);
+        assert_eq!(detector.record("Read", &test), None);
+        assert_eq!(detector.warnings(), 0);
+    }
+
+    #[test]
+    fn new_loop_after_a_break_warns_again() {
+        let mut detector = LoopDetector::new(2);
+        let input = json!({"file_path": "src/main.rs"});
+        detector.record("Read", &input);
+        assert_eq!(detector.record("Read", &input), Some(2));
+        detector.record("Edit", &input);
+        detector.record("Read", &input);
+        assert_eq!(detector.record("Read", &input), Some(2));
+        assert_eq!(detector.warnings(), 2);
+    }
+
+    #[test]
+    fn key_order_does_not_matter() {
+        let mut detector = LoopDetector::new(2);
+        let a: Value = serde_json::from_str(r#"{"pattern": "foo", "path": "src"}"#).unwrap();
+        let b: Value = serde_json::from_str(r#"{"path": "src", "pattern": "foo"}"#).unwrap();
+        detector.record("Grep", &a);
+        assert_eq!(detector.record("Grep", &b), Some(2));
+    }
+}
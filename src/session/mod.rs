@@ -1,4 +1,7 @@
 pub mod event_loop;
+pub mod loop_detect;
+pub mod outbox;
 pub mod persist;
 pub mod runner;
 pub mod state;
+pub mod trust;
@@ -0,0 +1,295 @@
+//! Delivery tracking for the user's messages to a session.
+//!
+//! A message the user submits is accepted, then written to claude's stdin
+//! (sent), then read by claude (it produces output again), then handed to the
+//! model with its next API request. Claude only adds new messages to a request
+//! at a turn boundary: with the results of the tools it ran, or as a new turn
+//! once the last one is over. Steering typed during a long tool call, say, is
+//! read right away but only delivered with the tool's result. Whatever hasn't
+//! been delivered when the session ends never reached the model.
+
+/// Where an accepted message is on its way to the model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delivery {
+    /// Accepted, waiting to be sent (a queued follow-up).
+    Queued,
+    /// Written to stdin, with no claude output since.
+    Sent,
+    /// Claude has read it, but the request in progress predates it.
+    Read,
+    /// Past a turn boundary: claude's next request includes it.
+    Boarding,
+}
+
+/// The user's messages that haven't been delivered yet, in the order they
+/// were accepted.
+#[derive(Debug, Default)]
+pub struct Outbox {
+    messages: Vec<(Delivery, String)>,
+    /// Claude finished its turn and hasn't started a request since, so a
+    /// message sent now starts the next one.
+    between_turns: bool,
+    /// The model called a tool and claude hasn't reported its result yet.
+    /// Claude stays silent while it runs, however long that takes.
+    tool_running: bool,
+}
+
+impl Outbox {
+    /// Accept a message to send later, e.g. a follow-up typed mid-turn.
+    pub fn queue(&mut self, text: String) {
+        self.messages.push((Delivery::Queued, text));
+    }
+
+    /// Record a message written to stdin right away, e.g. steering.
+    pub fn sent(&mut self, text: String) {
+        let delivery = self.sent_state();
+        self.messages.push((delivery, text));
+    }
+
+    fn sent_state(&self) -> Delivery {
+        if self.between_turns {
+            Delivery::Boarding
+        } else {
+            Delivery::Sent
+        }
+    }
+
+    /// Whether any accepted message is still waiting to be sent.
+    pub fn has_queued(&self) -> bool {
+        self.messages.iter().any(|(d, _)| *d == Delivery::Queued)
+    }
+
+    /// Take the oldest queued message to send it, marking it sent.
+    pub fn next_queued(&mut self) -> Option<String> {
+        let sent = self.sent_state();
+        let (delivery, text) = self
+            .messages
+            .iter_mut()
+            .find(|(d, _)| *d == Delivery::Queued)?;
+        *delivery = sent;
+        Some(text.clone())
+    }
+
+    /// The messages waiting to be sent, oldest first.
+    pub fn queued(&self) -> Vec<&str> {
+        self.messages
+            .iter()
+            .filter(|(d, _)| *d == Delivery::Queued)
+            .map(|(_, text)| text.as_str())
+            .collect()
+    }
+
+    /// Remove the `n`th queued message (counting from 1) before it's sent.
+    pub fn unqueue(&mut self, n: usize) -> Option<String> {
+        let index = self
+            .messages
+            .iter()
+            .enumerate()
+            .filter(|(_, (d, _))| *d == Delivery::Queued)
+            .nth(n.checked_sub(1)?)?
+            .0;
+        Some(self.messages.remove(index).1)
+    }
+
+    /// Drop the queued messages without sending them, returning how many
+    /// there were. For when the caller reports them itself (`--max-cost`).
+    pub fn drop_queued(&mut self) -> usize {
+        let before = self.messages.len();
+        self.messages.retain(|(d, _)| *d != Delivery::Queued);
+        before - self.messages.len()
+    }
+
+    /// Claude produced output, so it has read everything sent so far.
+    pub fn acknowledge(&mut self) {
+        self.advance(Delivery::Sent, Delivery::Read);
+    }
+
+    /// Claude reported a tool result (`turn_over` false) or the end of its
+    /// turn: everything it has read goes with its next request.
+    pub fn turn_boundary(&mut self, turn_over: bool) {
+        self.advance(Delivery::Sent, Delivery::Boarding);
+        self.advance(Delivery::Read, Delivery::Boarding);
+        self.between_turns = turn_over;
+        self.tool_running = false;
+    }
+
+    /// The model called a tool; claude reports its result at the next
+    /// turn boundary.
+    pub fn tool_started(&mut self) {
+        self.tool_running = true;
+    }
+
+    /// Whether a tool call is running, so silence from claude is expected.
+    pub fn tool_running(&self) -> bool {
+        self.tool_running
+    }
+
+    /// Claude started a request to the model, delivering what was boarding.
+    pub fn request_started(&mut self) {
+        self.messages.retain(|(d, _)| *d != Delivery::Boarding);
+        self.between_turns = false;
+    }
+
+    fn advance(&mut self, from: Delivery, to: Delivery) {
+        for (delivery, _) in &mut self.messages {
+            if *delivery == from {
+                *delivery = to;
+            }
+        }
+    }
+
+    /// Messages sent with no claude output since.
+    pub fn unacknowledged(&self) -> usize {
+        self.messages
+            .iter()
+            .filter(|(d, _)| *d == Delivery::Sent)
+            .count()
+    }
+
+    /// Remove and return the messages sent but not yet delivered, to resend.
+    pub fn take_in_flight(&mut self) -> Vec<String> {
+        let (sent, queued) = std::mem::take(&mut self.messages)
+            .into_iter()
+            .partition(|(d, _)| *d != Delivery::Queued);
+        self.messages = queued;
+        sent.into_iter().map(|(_, text)| text).collect()
+    }
+
+    /// Remove and return every message that never reached the model: sent
+    /// but not delivered, or never sent at all.
+    pub fn take_undelivered(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.messages)
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect()
+    }
+}
+
+/// Append messages that never reached an earlier session to the prompt of
+/// the next one (ralph iteration, worker phase), set off as an operator note.
+pub fn carry_into_prompt(prompt: &str, undelivered: &[String]) -> String {
+    if undelivered.is_empty() {
+        return prompt.to_string();
+    }
+    format!(
+        "{prompt}\n\n--- operator note ---\n\
+         The operator sent the following during the previous session, but it ended \
+         before they were delivered:\n\n{}",
+        undelivered.join("\n\n")
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_tool_call_runs_until_its_result() {
+        let mut outbox = Outbox::default();
+        outbox.request_started();
+        outbox.tool_started();
+        assert!(outbox.tool_running());
+        outbox.turn_boundary(false);
+        assert!(!outbox.tool_running());
+    }
+
+    #[test]
+    fn steering_is_delivered_with_the_next_request_after_a_boundary() {
+        let mut outbox = Outbox::default();
+        outbox.sent("use the v2 API".into());
+        assert_eq!(outbox.unacknowledged(), 1);
+        // Output from the request already in flight: read, not delivered.
+        outbox.acknowledge();
+        assert_eq!(outbox.unacknowledged(), 0);
+        outbox.request_started();
+        assert_eq!(outbox.take_undelivered(), ["use the v2 API"]);
+
+        outbox.sent("use the v2 API".into());
+        outbox.turn_boundary(false);
+        outbox.request_started();
+        assert!(outbox.take_undelivered().is_empty());
+    }
+
+    #[test]
+    fn steering_during_a_tool_call_waits_for_its_result() {
+        let mut outbox = Outbox::default();
+        outbox.request_started();
+        outbox.sent("skip the slow tests".into());
+        outbox.acknowledge();
+        // The session dies before the long tool call returns.
+        assert_eq!(outbox.take_undelivered(), ["skip the slow tests"]);
+    }
+
+    #[test]
+    fn messages_sent_between_turns_start_the_next_request() {
+        let mut outbox = Outbox::default();
+        outbox.queue("first".into());
+        outbox.queue("second".into());
+        outbox.turn_boundary(true);
+        assert!(outbox.has_queued());
+
+        assert_eq!(outbox.next_queued().unwrap(), "first");
+        assert_eq!(outbox.unacknowledged(), 0);
+        outbox.request_started();
+        assert_eq!(outbox.take_undelivered(), ["second"]);
+        assert!(!outbox.has_queued());
+    }
+
+    #[test]
+    fn unqueue_removes_by_queue_position() {
+        let mut outbox = Outbox::default();
+        outbox.queue("first".into());
+        outbox.sent("steering".into());
+        outbox.queue("second".into());
+        outbox.queue("third".into());
+        assert_eq!(outbox.queued(), ["first", "second", "third"]);
+
+        assert_eq!(outbox.unqueue(2).unwrap(), "second");
+        assert_eq!(outbox.unqueue(0), None);
+        assert_eq!(outbox.unqueue(3), None);
+        assert_eq!(outbox.next_queued().unwrap(), "first");
+        assert_eq!(outbox.queued(), ["third"]);
+        assert_eq!(outbox.take_undelivered(), ["first", "steering", "third"]);
+    }
+
+    #[test]
+    fn undelivered_keeps_acceptance_order() {
+        let mut outbox = Outbox::default();
+        outbox.queue("queued follow-up".into());
+        outbox.sent("in-flight steering".into());
+        assert_eq!(
+            outbox.take_undelivered(),
+            ["queued follow-up", "in-flight steering"]
+        );
+    }
+
+    #[test]
+    fn take_in_flight_leaves_queued() {
+        let mut outbox = Outbox::default();
+        outbox.queue("later".into());
+        outbox.sent("read".into());
+        outbox.acknowledge();
+        outbox.sent("now".into());
+        assert_eq!(outbox.take_in_flight(), ["read", "now"]);
+        assert_eq!(outbox.take_undelivered(), ["later"]);
+    }
+
+    #[test]
+    fn drop_queued_counts_only_queued() {
+        let mut outbox = Outbox::default();
+        outbox.queue("a".into());
+        outbox.queue("b".into());
+        outbox.sent("c".into());
+        assert_eq!(outbox.drop_queued(), 2);
+        assert_eq!(outbox.take_undelivered(), ["c"]);
+    }
+
+    #[test]
+    fn carried_messages_follow_an_operator_note() {
+        assert_eq!(carry_into_prompt("fix bugs", &[]), "fix bugs");
+        let prompt = carry_into_prompt("fix bugs", &["skip the docs".into(), "use v2".into()]);
+        assert!(prompt.starts_with("fix bugs\n\n--- operator note ---\n"));
+        assert!(prompt.ends_with("delivered:\n\nskip the docs\n\nuse v2"));
+    }
+}
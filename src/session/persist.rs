@@ -11,20 +11,63 @@ use crate::vcr::VcrContext;
 /// How long to wait for the session file to be updated before giving up.
 const PERSIST_TIMEOUT: Duration = Duration::from_secs(5);
 
-/// Compute the path to a Claude Code session JSONL file.
+/// Compute the directory Claude Code stores a project's sessions in.
 ///
 /// Claude Code stores sessions at `~/.claude/projects/<encoded-path>/<session-id>.jsonl`
-/// where `<encoded-path>` is the canonical working directory with `/` replaced by `-`.
-fn session_file_path(working_dir: &Path, session_id: &str) -> Result<PathBuf> {
+/// where `<encoded-path>` is the canonical working directory with path
+/// separators (and the Windows drive colon) replaced by `-`.
+fn project_sessions_dir(working_dir: &Path) -> Result<PathBuf> {
     let canonical = working_dir
         .canonicalize()
         .with_context(|| format!("failed to canonicalize {}", working_dir.display()))?;
-    let encoded = canonical.display().to_string().replace('/', "-");
-    let home = std::env::var("HOME").context("HOME not set")?;
-    Ok(PathBuf::from(home)
-        .join(".claude/projects")
-        .join(encoded)
-        .join(format!("{session_id}.jsonl")))
+    let encoded = encode_project_dir(&canonical.display().to_string());
+    let home = crate::platform::home_dir()
+        .with_context(|| format!("{} not set", crate::platform::HOME_VAR))?;
+    Ok(home.join(".claude").join("projects").join(encoded))
+}
+
+/// Compute the path to a Claude Code session JSONL file.
+fn session_file_path(working_dir: &Path, session_id: &str) -> Result<PathBuf> {
+    Ok(project_sessions_dir(working_dir)?.join(format!("{session_id}.jsonl")))
+}
+
+/// ID of the most recently modified session file in `dir`, if any.
+fn latest_session_in(dir: &Path) -> Option<String> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "jsonl"))
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .max_by_key(|(modified, _)| *modified)
+        .and_then(|(_, path)| Some(path.file_stem()?.to_str()?.to_string()))
+}
+
+/// Find the most recent claude session for a working directory (`--continue`).
+///
+/// Defaults to the current directory. Returns `None` if the directory has no sessions.
+pub async fn latest_session_id(
+    vcr: &VcrContext,
+    working_dir: Option<&Path>,
+) -> Result<Option<String>> {
+    let dir = working_dir.map(|d| d.display().to_string());
+    vcr.call("latest_session_id", dir, async |d: &Option<String>| {
+        let dir = match d {
+            Some(d) => PathBuf::from(d),
+            None => std::env::current_dir()?,
+        };
+        Ok(latest_session_in(&project_sessions_dir(&dir)?))
+    })
+    .await
+}
+
+fn encode_project_dir(path: &str) -> String {
+    // `canonicalize` on Windows yields a verbatim `\\?\C:\...` path; Claude
+    // Code encodes the plain form, turning every non-alphanumeric character
+    // into `-`.
+    let path = path.strip_prefix(r"\\?\").unwrap_or(path);
+    path.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
 }
 
 /// Check whether the last line of a JSONL file contains an assistant message
@@ -159,3 +202,63 @@ pub async fn wait_if_needed(
             .await;
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn latest_session_picks_newest_jsonl() {
+        let dir = TempDir::new().unwrap();
+        let old = dir.path().join("old-session.jsonl");
+        let new = dir.path().join("new-session.jsonl");
+        std::fs::write(&old, "{}\n").unwrap();
+        std::fs::write(&new, "{}\n").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+        let past = std::time::SystemTime::now() - Duration::from_mins(1);
+        std::fs::File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+
+        assert_eq!(
+            latest_session_in(dir.path()).as_deref(),
+            Some("new-session")
+        );
+    }
+
+    #[test]
+    fn latest_session_none_without_sessions() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(latest_session_in(dir.path()), None);
+        assert_eq!(latest_session_in(&dir.path().join("missing")), None);
+    }
+
+    #[test]
+    fn encode_unix_path() {
+        assert_eq!(encode_project_dir("/home/me/proj"), "-home-me-proj");
+    }
+
+    #[test]
+    fn encode_windows_path() {
+        assert_eq!(encode_project_dir(r"C:\Users\me\proj"), "C--Users-me-proj");
+        assert_eq!(
+            encode_project_dir(r"\\?\C:\Users\me\proj"),
+            "C--Users-me-proj"
+        );
+    }
+
+    #[test]
+    fn encode_punctuation_and_spaces() {
+        assert_eq!(
+            encode_project_dir("/tmp/coven-vcr-worker_basic"),
+            "-tmp-coven-vcr-worker-basic"
+        );
+        assert_eq!(encode_project_dir("/home/me/.config"), "-home-me--config");
+        assert_eq!(encode_project_dir("/home/me/my proj"), "-home-me-my-proj");
+    }
+}
@@ -1,16 +1,23 @@
 use std::path::PathBuf;
 use std::process::Stdio;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, ensure};
 use serde::{Deserialize, Serialize};
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout};
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdout};
 use tokio::sync::mpsc;
 
 use crate::event::AppEvent;
 use crate::protocol::emit::format_user_message;
 use crate::protocol::parse::parse_line;
 
+/// Messages at least this large report their progress while being written.
+pub const LARGE_SEND_BYTES: usize = 1 << 20;
+/// Large messages are written in chunks of this size.
+const SEND_CHUNK_BYTES: usize = 64 * 1024;
+/// Progress is reported each time another this-many percent has been written.
+const SEND_PROGRESS_STEP: usize = 25;
+
 /// Configuration for spawning a claude session.
 #[derive(Default, Clone, Serialize, Deserialize)]
 pub struct SessionConfig {
@@ -22,6 +29,10 @@ pub struct SessionConfig {
     pub append_system_prompt: Option<String>,
     /// Resume an existing session by ID (uses `--resume`).
     pub resume: Option<String>,
+    /// Leave the permission mode to claude instead of defaulting to
+    /// `acceptEdits` (`--no-default-permission-mode`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_default_permission_mode: bool,
     /// Working directory for the claude process. If None, inherits from parent.
     /// Skipped in serde — not meaningful for VCR replay, only a runtime concern.
     #[serde(skip)]
@@ -44,20 +55,22 @@ impl SessionConfig {
 ///
 /// The `child` field is optional to support VCR replay mode, where a stub
 /// `SessionRunner` is constructed without a real process.
+///
+/// Stdin is owned by a writer task fed through a queue, so a slow-reading
+/// claude process never blocks the caller and messages are written whole,
+/// in the order they were sent.
 pub struct SessionRunner {
     child: Option<Child>,
-    stdin: Option<ChildStdin>,
+    stdin: Option<mpsc::UnboundedSender<String>>,
 }
 
 impl SessionRunner {
     /// Spawn a claude process and start reading its output.
     ///
-    /// Parsed events are sent to `event_tx`. The initial prompt (if any)
-    /// is sent as the first stdin message.
-    pub async fn spawn(
-        config: SessionConfig,
-        event_tx: mpsc::UnboundedSender<AppEvent>,
-    ) -> Result<Self> {
+    /// Parsed events are sent to `event_tx`, along with progress and errors
+    /// from writing to stdin. The initial prompt (if any) is sent as the first
+    /// stdin message.
+    pub fn spawn(config: SessionConfig, event_tx: mpsc::UnboundedSender<AppEvent>) -> Result<Self> {
         let args = Self::build_args(&config);
         let mut cmd = tokio::process::Command::new("claude");
         cmd.args(&args);
@@ -78,28 +91,20 @@ impl SessionRunner {
 
         let stdout = child.stdout.take().context("stdout should be piped")?;
         let stderr = child.stderr.take().context("stderr should be piped")?;
-        let mut stdin = child.stdin.take().context("stdin should be piped")?;
+        let stdin = child.stdin.take().context("stdin should be piped")?;
 
+        let mut runner = Self {
+            child: Some(child),
+            stdin: Some(Self::spawn_writer(stdin, event_tx.clone())),
+        };
         if let Some(prompt) = config.prompt {
-            let msg = format_user_message(&prompt)?;
-            stdin
-                .write_all(msg.as_bytes())
-                .await
-                .context("Failed to write initial prompt to claude stdin")?;
-            stdin
-                .write_all(b"\n")
-                .await
-                .context("Failed to write newline after initial prompt")?;
-            stdin.flush().await?;
+            runner.send_message(&prompt)?;
         }
 
         // Spawn stdout reader task (also collects stderr on exit)
         Self::spawn_reader(stdout, stderr, event_tx);
 
-        Ok(Self {
-            child: Some(child),
-            stdin: Some(stdin),
-        })
+        Ok(runner)
     }
 
     /// Create a stub `SessionRunner` for VCR replay mode.
@@ -120,7 +125,8 @@ impl SessionRunner {
             args.push(session_id.clone());
         }
 
-        if !has_flag(&config.extra_args, "--permission-mode") {
+        if !config.no_default_permission_mode && !has_flag(&config.extra_args, "--permission-mode")
+        {
             args.push("--permission-mode".to_string());
             args.push("acceptEdits".to_string());
         }
@@ -159,17 +165,35 @@ impl SessionRunner {
         Self::build_shared_args(config)
     }
 
-    /// Send a user message to claude's stdin.
-    pub async fn send_message(&mut self, text: &str) -> Result<()> {
-        let stdin = self.stdin.as_mut().context("stdin already closed")?;
-        let msg = format_user_message(text)?;
-        stdin.write_all(msg.as_bytes()).await?;
-        stdin.write_all(b"\n").await?;
-        stdin.flush().await?;
-        Ok(())
+    /// Queue a user message for claude's stdin.
+    ///
+    /// Returns once the message is queued; the write itself happens in the
+    /// background. Write failures arrive as [`AppEvent::SendFailed`].
+    pub fn send_message(&mut self, text: &str) -> Result<()> {
+        let mut msg = format_user_message(text)?;
+        msg.push('\n');
+        self.queue(msg)
+    }
+
+    /// Queue a hand-written stream-json line (`:raw` with `--debug-protocol`)
+    /// for claude's stdin, verbatim apart from the terminating newline.
+    pub fn send_raw_line(&mut self, line: &str) -> Result<()> {
+        ensure!(
+            !line.contains(['\n', '\r']),
+            "a raw line can't contain line breaks"
+        );
+        self.queue(format!("{line}\n"))
     }
 
-    /// Close stdin, signaling claude to finish.
+    fn queue(&self, msg: String) -> Result<()> {
+        let stdin = self.stdin.as_ref().context("stdin already closed")?;
+        stdin
+            .send(msg)
+            .ok()
+            .context("stdin closed after a failed write")
+    }
+
+    /// Close stdin once queued messages are written, signaling claude to finish.
     pub fn close_input(&mut self) {
         self.stdin.take();
     }
@@ -190,17 +214,40 @@ impl SessionRunner {
         Ok(())
     }
 
+    /// Spawn the task that owns stdin and writes queued messages to it one at
+    /// a time. Stops at the first write error, closing the queue.
+    fn spawn_writer<W: AsyncWrite + Unpin + Send + 'static>(
+        mut stdin: W,
+        event_tx: mpsc::UnboundedSender<AppEvent>,
+    ) -> mpsc::UnboundedSender<String> {
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if let Err(e) = write_message(&mut stdin, msg.as_bytes(), &event_tx).await {
+                    let _ = event_tx.send(AppEvent::SendFailed(e.to_string()));
+                    break;
+                }
+            }
+        });
+        tx
+    }
+
     fn spawn_reader(
         stdout: ChildStdout,
         stderr: ChildStderr,
         event_tx: mpsc::UnboundedSender<AppEvent>,
     ) {
-        // Collect stderr in the background so it doesn't block the process.
+        // Forward stderr line by line as it arrives, so it neither blocks
+        // the process nor waits for it to exit.
+        let stderr_tx = event_tx.clone();
         let stderr_handle = tokio::spawn(async move {
-            let mut reader = BufReader::new(stderr);
-            let mut buf = String::new();
-            reader.read_to_string(&mut buf).await.ok();
-            buf
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let line = line.trim_end();
+                if !line.is_empty() && stderr_tx.send(AppEvent::Stderr(line.to_string())).is_err() {
+                    break;
+                }
+            }
         });
 
         tokio::spawn(async move {
@@ -224,20 +271,81 @@ impl SessionRunner {
                 }
             }
 
-            // stdout closed — process is exiting or has exited.
-            // Collect any stderr content and surface it as a warning.
-            if let Ok(stderr_content) = stderr_handle.await {
-                let trimmed = stderr_content.trim();
-                if !trimmed.is_empty() {
-                    let _ = event_tx.send(AppEvent::Stderr(trimmed.to_string()));
-                }
-            }
+            // stdout closed — process is exiting or has exited. Let the
+            // last of stderr through before reporting the exit.
+            let _ = stderr_handle.await;
 
             let _ = event_tx.send(AppEvent::ProcessExit(None));
         });
     }
 }
 
+/// Write one message, reporting progress if it's large.
+async fn write_message<W: AsyncWrite + Unpin>(
+    stdin: &mut W,
+    msg: &[u8],
+    event_tx: &mpsc::UnboundedSender<AppEvent>,
+) -> std::io::Result<()> {
+    let total = msg.len();
+    if total < LARGE_SEND_BYTES {
+        stdin.write_all(msg).await?;
+        return stdin.flush().await;
+    }
+    let mut sent = 0;
+    let mut reported = 0;
+    for chunk in msg.chunks(SEND_CHUNK_BYTES) {
+        stdin.write_all(chunk).await?;
+        sent += chunk.len();
+        let percent = sent * 100 / total;
+        if percent >= reported + SEND_PROGRESS_STEP {
+            reported = percent - percent % SEND_PROGRESS_STEP;
+            let _ = event_tx.send(AppEvent::SendProgress { sent, total });
+        }
+    }
+    stdin.flush().await
+}
+
+/// The permission mode coven's sessions run with, and where it came from.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PermissionMode {
+    /// `--permission-mode` in the claude args (or `claude_args` in
+    /// `.coven/config.toml`).
+    Explicit(String),
+    /// Nothing given, so coven passes `acceptEdits`.
+    CovenDefault,
+    /// `--no-default-permission-mode`: claude picks, from its settings'
+    /// `defaultMode` or its own default.
+    ClaudeDefault,
+}
+
+impl PermissionMode {
+    pub fn resolve(claude_args: &[String], no_default: bool) -> Self {
+        let explicit = claude_args.iter().enumerate().find_map(|(i, arg)| {
+            match arg.strip_prefix("--permission-mode") {
+                Some("") => Some(claude_args.get(i + 1).cloned().unwrap_or_default()),
+                Some(rest) => rest.strip_prefix('=').map(String::from),
+                None => None,
+            }
+        });
+        match explicit {
+            Some(mode) => Self::Explicit(mode),
+            None if no_default => Self::ClaudeDefault,
+            None => Self::CovenDefault,
+        }
+    }
+
+    /// `acceptEdits (coven's default; ...)`, for the startup line.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Explicit(mode) => format!("{mode} (from --permission-mode)"),
+            Self::CovenDefault => "acceptEdits (coven's default; \
+                 --no-default-permission-mode leaves it to claude)"
+                .to_string(),
+            Self::ClaudeDefault => "claude's default (--no-default-permission-mode)".to_string(),
+        }
+    }
+}
+
 /// Check whether a flag is already present in the extra args.
 pub(crate) fn has_flag(args: &[String], flag: &str) -> bool {
     args.iter()
@@ -245,8 +353,179 @@ pub(crate) fn has_flag(args: &[String], flag: &str) -> bool {
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
+    use tokio::io::{AsyncBufReadExt, BufReader, DuplexStream};
+
+    /// A runner writing into one end of a small in-memory pipe. The other end
+    /// is returned for the test to read at its own pace.
+    fn piped_runner() -> (
+        SessionRunner,
+        DuplexStream,
+        mpsc::UnboundedReceiver<AppEvent>,
+    ) {
+        let (writer, reader) = tokio::io::duplex(4096);
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let runner = SessionRunner {
+            child: None,
+            stdin: Some(SessionRunner::spawn_writer(writer, event_tx)),
+        };
+        (runner, reader, event_rx)
+    }
+
+    async fn read_lines(reader: DuplexStream) -> Vec<String> {
+        let mut lines = BufReader::new(reader).lines();
+        let mut out = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            out.push(line);
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn large_send_does_not_block_caller() {
+        let (mut runner, reader, _events) = piped_runner();
+        let big = "x".repeat(4 * LARGE_SEND_BYTES);
+        runner.send_message(&big).unwrap();
+
+        // Nothing reads the pipe yet, so the write is stuck after the first
+        // few KB — yet this task keeps running and can queue more.
+        let probe = tokio::spawn(async { 42 });
+        assert_eq!(probe.await.unwrap(), 42);
+        runner.send_message("after").unwrap();
+        runner.close_input();
+
+        let lines = read_lines(reader).await;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], format_user_message(&big).unwrap());
+        assert_eq!(lines[1], format_user_message("after").unwrap());
+    }
+
+    #[tokio::test]
+    async fn sends_are_written_whole_and_in_order() {
+        let (mut runner, reader, _events) = piped_runner();
+        let messages: Vec<String> = (0..5)
+            .map(|i| i.to_string().repeat(LARGE_SEND_BYTES / 2 + i))
+            .collect();
+        for message in &messages {
+            runner.send_message(message).unwrap();
+        }
+        runner.close_input();
+
+        let expected: Vec<String> = messages
+            .iter()
+            .map(|m| format_user_message(m).unwrap())
+            .collect();
+        assert_eq!(read_lines(reader).await, expected);
+    }
+
+    #[tokio::test]
+    async fn progress_reported_only_for_large_sends() {
+        let (mut runner, reader, mut events) = piped_runner();
+        runner.send_message("small").unwrap();
+        runner
+            .send_message(&"y".repeat(2 * LARGE_SEND_BYTES))
+            .unwrap();
+        runner.close_input();
+        read_lines(reader).await;
+
+        let mut progress = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let AppEvent::SendProgress { sent, total } = event {
+                progress.push(sent * 100 / total);
+            }
+        }
+        assert_eq!(progress.len(), 100 / SEND_PROGRESS_STEP);
+        assert_eq!(progress.last(), Some(&100));
+    }
+
+    #[tokio::test]
+    async fn raw_lines_are_written_verbatim_between_messages() {
+        let (mut runner, reader, _events) = piped_runner();
+        let raw =
+            r#"{"type":"control_request","request_id":"r1","request":{"subtype":"interrupt"}}"#;
+        runner.send_message("before").unwrap();
+        runner.send_raw_line(raw).unwrap();
+        assert!(runner.send_raw_line("{}\n{}").is_err());
+        runner.send_message("after").unwrap();
+        runner.close_input();
+
+        let lines = read_lines(reader).await;
+        assert_eq!(
+            lines,
+            [
+                format_user_message("before").unwrap(),
+                raw.to_string(),
+                format_user_message("after").unwrap(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn write_failure_is_reported() {
+        let (mut runner, reader, mut events) = piped_runner();
+        drop(reader);
+        runner.send_message("hello").unwrap();
+        assert!(matches!(events.recv().await, Some(AppEvent::SendFailed(_))));
+        // The writer has stopped; further sends fail immediately.
+        assert!(runner.send_message("again").is_err());
+    }
+
+    #[test]
+    fn accept_edits_is_passed_unless_skipped_or_given() {
+        let permission_args = |config: &SessionConfig| {
+            let args = SessionRunner::build_args(config);
+            let i = args.iter().position(|a| a == "--permission-mode")?;
+            Some(args[i + 1].clone())
+        };
+        let mut config = SessionConfig::default();
+        assert_eq!(permission_args(&config).as_deref(), Some("acceptEdits"));
+        config.no_default_permission_mode = true;
+        assert_eq!(permission_args(&config), None);
+        assert!(!SessionRunner::build_interactive_args(&config).contains(&"acceptEdits".into()));
+        config.extra_args = vec!["--permission-mode".into(), "plan".into()];
+        assert_eq!(permission_args(&config).as_deref(), Some("plan"));
+    }
+
+    #[test]
+    fn skipping_the_default_is_recorded_only_when_set() {
+        let config = SessionConfig::default();
+        let json = serde_json::to_value(&config).unwrap();
+        assert!(json.get("no_default_permission_mode").is_none());
+        let config = SessionConfig {
+            no_default_permission_mode: true,
+            ..config
+        };
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["no_default_permission_mode"], true);
+    }
+
+    #[test]
+    fn permission_mode_source() {
+        let args = |a: &[&str]| a.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            PermissionMode::resolve(&args(&["--model", "opus"]), false),
+            PermissionMode::CovenDefault
+        );
+        assert_eq!(
+            PermissionMode::resolve(&[], true),
+            PermissionMode::ClaudeDefault
+        );
+        assert_eq!(
+            PermissionMode::resolve(&args(&["--permission-mode", "plan"]), true),
+            PermissionMode::Explicit("plan".into())
+        );
+        assert_eq!(
+            PermissionMode::resolve(&args(&["--permission-mode=default"]), false),
+            PermissionMode::Explicit("default".into())
+        );
+        assert!(
+            PermissionMode::CovenDefault
+                .describe()
+                .contains("acceptEdits (coven's default")
+        );
+    }
 
     #[test]
     fn exact_match() {
@@ -1,9 +1,17 @@
+use std::ops::AddAssign;
+
+use crate::protocol::types::{ApiError, ResultSubtype, Usage};
+use crate::timing::LandAttempts;
+
 /// Tracks accumulated session state across events.
 #[derive(Debug, Default)]
 pub struct SessionState {
     pub session_id: Option<String>,
     pub status: SessionStatus,
     pub total_cost_usd: f64,
+    /// Token usage from the latest result. Like `total_cost_usd`, this is
+    /// cumulative for the current claude process and resets on respawn.
+    pub usage: TokenUsage,
     /// When true, the next Init event for the same session will skip
     /// rendering the turn separator (`---`). Set when sending a follow-up
     /// so the separator doesn't appear between the follow-up message
@@ -16,6 +24,42 @@ pub struct SessionState {
     /// Message ID (`msg_...`) of the most recent top-level assistant message.
     /// Used to verify session file persistence before killing the process.
     pub last_message_id: Option<String>,
+    /// Set when starting a session with `--resume`/`--continue`. The first
+    /// Init clears it and renders a resumed-session header instead of a new one.
+    pub resuming: bool,
+    /// The last result took spending past the `--max-cost` budget. Set by the
+    /// event loop, which then skips queued follow-ups and completes the session;
+    /// callers should stop instead of continuing.
+    pub budget_exceeded: bool,
+    /// Subtype of the last result if it was an error (an `error_*` subtype
+    /// or `is_error`), e.g. because the API was unreachable.
+    pub result_error: Option<String>,
+    /// The API error that ended the last turn, from its result.
+    pub api_error: Option<Box<ApiError>>,
+    /// The user's messages that never reached claude before the session
+    /// ended, oldest first. Set by the event loop when it returns.
+    pub undelivered: Vec<String>,
+    /// Claude Code denied every tool from the start, so it's presumably
+    /// ignoring the permission mode because it doesn't trust this directory
+    /// (held here). Set by the event loop, which then ends the session.
+    pub untrusted_dir: Option<String>,
+    /// The agent's runs of `land.sh`, timed by the event loop.
+    pub land: LandAttempts,
+}
+
+impl SessionState {
+    /// Whether the session failed with an error that waiting out (overload,
+    /// rate limiting) should fix, so loops should back off and retry.
+    pub fn failed_retryably(&self) -> bool {
+        self.api_error.as_ref().is_some_and(|e| e.is_retryable())
+    }
+
+    /// Whether the last result was cut short by claude's `--max-turns`.
+    pub fn hit_max_turns(&self) -> bool {
+        self.result_error
+            .as_deref()
+            .is_some_and(|subtype| ResultSubtype::parse(subtype) == ResultSubtype::MaxTurns)
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -26,3 +70,124 @@ pub enum SessionStatus {
     WaitingForInput,
     Ended,
 }
+
+/// Token counts split into fresh input, cache reads, cache writes, and output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TokenUsage {
+    /// Input tokens that were neither read from nor written to the cache.
+    pub fresh_input: u64,
+    pub cache_read: u64,
+    pub cache_creation: u64,
+    pub output: u64,
+}
+
+impl TokenUsage {
+    /// All input tokens, cached or not.
+    pub fn total_input(&self) -> u64 {
+        self.fresh_input + self.cache_read + self.cache_creation
+    }
+
+    /// Percentage of input tokens served from the cache (rounded), or `None`
+    /// if there was no input at all.
+    pub fn cached_percent(&self) -> Option<u64> {
+        let total = self.total_input();
+        (total > 0).then(|| (self.cache_read * 100 + total / 2) / total)
+    }
+
+    /// One-line summary, e.g. `312k in (87% cached) · 9.4k out`.
+    pub fn summary(&self) -> String {
+        let cached = self
+            .cached_percent()
+            .map(|p| format!(" ({p}% cached)"))
+            .unwrap_or_default();
+        format!(
+            "{} in{cached} · {} out",
+            format_tokens(self.total_input()),
+            format_tokens(self.output)
+        )
+    }
+}
+
+impl From<&Usage> for TokenUsage {
+    fn from(u: &Usage) -> Self {
+        Self {
+            fresh_input: u.input_tokens,
+            cache_read: u.cache_read_input_tokens,
+            cache_creation: u.cache_creation_input_tokens,
+            output: u.output_tokens,
+        }
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, rhs: Self) {
+        self.fresh_input += rhs.fresh_input;
+        self.cache_read += rhs.cache_read;
+        self.cache_creation += rhs.cache_creation;
+        self.output += rhs.output;
+    }
+}
+
+/// Compact token count: `312`, `9.4k`, `312k`, `1.2M`.
+fn format_tokens(n: u64) -> String {
+    match n {
+        0..1_000 => n.to_string(),
+        1_000..10_000 => format!("{}.{}k", n / 1_000, n / 100 % 10),
+        10_000..1_000_000 => format!("{}k", n / 1_000),
+        _ => format!("{}.{}M", n / 1_000_000, n / 100_000 % 10),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(fresh_input: u64, cache_read: u64, cache_creation: u64, output: u64) -> TokenUsage {
+        TokenUsage {
+            fresh_input,
+            cache_read,
+            cache_creation,
+            output,
+        }
+    }
+
+    #[test]
+    fn summary_with_cache_activity() {
+        let u = usage(18, 271_440, 40_562, 9_400);
+        assert_eq!(u.summary(), "312k in (87% cached) · 9.4k out");
+    }
+
+    #[test]
+    fn summary_without_cache_activity() {
+        let u = usage(1_200, 0, 0, 300);
+        assert_eq!(u.cached_percent(), Some(0));
+        assert_eq!(u.summary(), "1.2k in (0% cached) · 300 out");
+    }
+
+    #[test]
+    fn empty_usage_has_no_percentage() {
+        let u = TokenUsage::default();
+        assert_eq!(u.cached_percent(), None);
+        assert_eq!(u.summary(), "0 in · 0 out");
+    }
+
+    #[test]
+    fn accumulates_across_epochs() {
+        let mut total = TokenUsage::default();
+        total += usage(10, 90, 0, 5);
+        total += usage(0, 0, 0, 0);
+        total += usage(100, 700, 100, 50);
+        assert_eq!(total, usage(110, 790, 100, 55));
+        assert_eq!(total.cached_percent(), Some(79));
+    }
+
+    #[test]
+    fn format_tokens_boundaries() {
+        assert_eq!(format_tokens(999), "999");
+        assert_eq!(format_tokens(1_000), "1.0k");
+        assert_eq!(format_tokens(9_999), "9.9k");
+        assert_eq!(format_tokens(10_000), "10k");
+        assert_eq!(format_tokens(999_999), "999k");
+        assert_eq!(format_tokens(1_250_000), "1.2M");
+    }
+}
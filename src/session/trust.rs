@@ -0,0 +1,182 @@
+//! Spotting a session whose directory Claude Code doesn't trust.
+//!
+//! In a directory that was never trusted (a fresh clone, a new worktree
+//! base), Claude Code ignores `--permission-mode acceptEdits` and asks before
+//! every tool. Nobody can answer in stream-json mode, so every call comes back
+//! denied and the model flails. The tell is a session whose first tool
+//! results are all permission denials.
+
+use serde_json::Value;
+
+use crate::protocol::types::{InboundEvent, SystemEvent};
+
+/// Consecutive denials, with no tool succeeding first, that count as untrusted.
+pub const DENIAL_THRESHOLD: usize = 3;
+
+/// What the CLI answers a tool call it wasn't allowed to make, in the
+/// wordings of the versions coven supports.
+const DENIAL_MARKERS: &[&str] = &["haven't granted it yet", "requires approval"];
+
+/// Watches the start of a session for the untrusted-directory signature.
+#[derive(Debug, Default)]
+pub struct TrustDetector {
+    /// Working directory from the init event.
+    cwd: Option<String>,
+    denials: usize,
+    /// A tool went through or a turn ended: past the start of the session.
+    settled: bool,
+}
+
+impl TrustDetector {
+    /// Feed an event. Returns the directory to trust the first time the
+    /// session looks blocked by a missing trust decision.
+    pub fn observe(&mut self, event: &InboundEvent) -> Option<String> {
+        match event {
+            InboundEvent::System(SystemEvent::Init(init)) if !init.cwd.is_empty() => {
+                self.cwd = Some(init.cwd.clone());
+            }
+            InboundEvent::Result(_) => self.settled = true,
+            InboundEvent::User(user) if !self.settled && user.parent_tool_use_id.is_none() => {
+                let result = user.tool_use_result.as_ref()?;
+                let Some(text) = denial_text(result) else {
+                    self.settled = true;
+                    return None;
+                };
+                self.denials += 1;
+                if self.denials >= DENIAL_THRESHOLD || mentions_trust(text) {
+                    self.settled = true;
+                    return Some(self.cwd.clone().unwrap_or_else(|| ".".to_string()));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// The text of a permission-denied tool result. The CLI reports errors as
+/// a plain string rather than a result object.
+fn denial_text(result: &Value) -> Option<&str> {
+    result
+        .as_str()
+        .filter(|text| DENIAL_MARKERS.iter().any(|marker| text.contains(marker)))
+}
+
+/// Whether a denial names the trust dialog itself, which settles it at once.
+fn mentions_trust(text: &str) -> bool {
+    let text = text.to_lowercase();
+    text.contains("trust this folder") || text.contains("not trusted")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn event(value: Value) -> InboundEvent {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn init() -> InboundEvent {
+        event(json!({
+            "type": "system", "subtype": "init", "session_id": "s1",
+            "model": "claude-opus", "cwd": "/home/me/fresh-clone",
+            "permissionMode": "acceptEdits",
+        }))
+    }
+
+    fn denied(tool: &str) -> InboundEvent {
+        event(json!({
+            "type": "user",
+            "tool_use_result": format!(
+                "Error: Claude requested permissions to use {tool}, but you haven't granted it yet."
+            ),
+        }))
+    }
+
+    fn succeeded() -> InboundEvent {
+        event(json!({
+            "type": "user",
+            "tool_use_result": {"type": "text", "file": {"content": "fn main() {}"}},
+        }))
+    }
+
+    fn detect(events: &[InboundEvent]) -> Option<String> {
+        let mut detector = TrustDetector::default();
+        events.iter().find_map(|e| detector.observe(e))
+    }
+
+    #[test]
+    fn leading_denials_flag_the_session_directory() {
+        let events = [init(), denied("Edit"), denied("Write"), denied("Bash")];
+        assert_eq!(detect(&events).unwrap(), "/home/me/fresh-clone");
+        assert_eq!(detect(&events[..3]), None);
+    }
+
+    #[test]
+    fn current_denial_wording_counts() {
+        let denied = event(json!({
+            "type": "user",
+            "tool_use_result": "Error: This command requires approval",
+        }));
+        let events = [init(), denied.clone(), denied.clone(), denied];
+        assert_eq!(detect(&events).unwrap(), "/home/me/fresh-clone");
+    }
+
+    #[test]
+    fn reports_once() {
+        let mut detector = TrustDetector::default();
+        let flagged: Vec<bool> = [init(), denied("Edit"), denied("Edit"), denied("Edit")]
+            .iter()
+            .chain(&[denied("Edit"), denied("Edit")])
+            .map(|e| detector.observe(e).is_some())
+            .collect();
+        assert_eq!(flagged, [false, false, false, true, false, false]);
+    }
+
+    #[test]
+    fn a_working_tool_first_means_trusted() {
+        let events = [
+            init(),
+            succeeded(),
+            denied("Bash"),
+            denied("Bash"),
+            denied("Bash"),
+        ];
+        assert_eq!(detect(&events), None);
+    }
+
+    #[test]
+    fn denials_after_the_first_turn_are_ordinary() {
+        let result = event(json!({"type": "result", "subtype": "success", "result": "done"}));
+        let events = [
+            init(),
+            result,
+            denied("Bash"),
+            denied("Bash"),
+            denied("Bash"),
+        ];
+        assert_eq!(detect(&events), None);
+    }
+
+    #[test]
+    fn subagent_results_are_ignored() {
+        let sub = event(json!({
+            "type": "user",
+            "parent_tool_use_id": "task_1",
+            "tool_use_result": "Error: Claude requested permissions to use Bash, but you haven't granted it yet.",
+        }));
+        assert_eq!(detect(&[init(), sub.clone(), sub.clone(), sub]), None);
+    }
+
+    #[test]
+    fn trust_wording_settles_it_at_once() {
+        let untrusted = event(json!({
+            "type": "user",
+            "tool_use_result": "Error: Claude requested permissions to use Edit, but you haven't granted it yet. This folder is not trusted.",
+        }));
+        assert_eq!(detect(&[untrusted]).unwrap(), ".");
+    }
+}
@@ -0,0 +1,277 @@
+//! Wall-clock spans for worker phases and ralph iterations.
+//!
+//! Claude's `duration_ms` covers the model's turns only; syncing with main,
+//! waiting for an agent's concurrency slot, landing and post-land hooks don't
+//! show up in it. Readings come from [`VcrContext::clock_ms`](crate::vcr::VcrContext::clock_ms),
+//! so replays show the times of the recording.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::protocol::types::{AssistantContentBlock, InboundEvent};
+
+/// Waits shorter than this aren't worth a mention.
+const MIN_WAIT: Duration = Duration::from_secs(1);
+
+/// One timed stretch of work.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub label: String,
+    pub elapsed: Duration,
+    /// The part of `elapsed` spent waiting for the agent's concurrency slot.
+    pub lock_wait: Duration,
+    /// Attempts that stopped on rebase conflicts.
+    pub conflicts: usize,
+}
+
+impl Span {
+    /// The span from `start_ms` to `end_ms`, as read from the clock.
+    pub fn between(label: &str, start_ms: u64, end_ms: u64) -> Self {
+        Self {
+            label: label.to_string(),
+            elapsed: Duration::from_millis(end_ms.saturating_sub(start_ms)),
+            lock_wait: Duration::ZERO,
+            conflicts: 0,
+        }
+    }
+
+    /// `dispatch 1m02s (lock wait 47s)`, `land 3m (2 conflicts)`.
+    pub fn describe(&self) -> String {
+        let text = format!("{} {}", self.label, format_elapsed(self.elapsed));
+        let note = match self.conflicts {
+            0 if self.lock_wait < MIN_WAIT => return text,
+            0 => format!("lock wait {}", format_elapsed(self.lock_wait)),
+            1 => "1 conflict".to_string(),
+            n => format!("{n} conflicts"),
+        };
+        format!("{text} ({note})")
+    }
+}
+
+/// What `land.sh` prints when the rebase stops on conflicts.
+const CONFLICT_MARKER: &str = "Rebase has conflicts";
+
+/// The agent's runs of `land.sh` in a session: from the first one starting
+/// to the last one finishing, counting those that hit rebase conflicts.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LandAttempts {
+    /// Tool-use IDs of runs still going.
+    running: Vec<String>,
+    start_ms: Option<u64>,
+    end_ms: u64,
+    attempts: usize,
+    conflicts: usize,
+}
+
+impl LandAttempts {
+    /// Feed a top-level event from claude, reading `clock` when a run
+    /// starts or finishes.
+    pub fn observe(
+        &mut self,
+        event: &InboundEvent,
+        clock: impl FnOnce() -> Result<u64>,
+    ) -> Result<()> {
+        match event {
+            InboundEvent::Assistant(msg) if msg.parent_tool_use_id.is_none() => {
+                let before = self.running.len();
+                self.running
+                    .extend(msg.message.content.iter().filter_map(land_call));
+                if self.running.len() > before && self.start_ms.is_none() {
+                    self.start_ms = Some(clock()?);
+                }
+            }
+            InboundEvent::User(user) if user.parent_tool_use_id.is_none() => {
+                let Some(results) = user.message.as_ref().and_then(|m| m["content"].as_array())
+                else {
+                    return Ok(());
+                };
+                let mut finished = false;
+                for result in results {
+                    let Some(pos) = self
+                        .running
+                        .iter()
+                        .position(|id| result["tool_use_id"].as_str() == Some(id))
+                    else {
+                        continue;
+                    };
+                    self.running.remove(pos);
+                    self.attempts += 1;
+                    self.conflicts += usize::from(mentions_conflicts(&result["content"]));
+                    finished = true;
+                }
+                if finished {
+                    self.end_ms = clock()?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Fold in the runs of a later session of the same phase.
+    pub fn merge(&mut self, later: &Self) {
+        if later.attempts == 0 {
+            return;
+        }
+        self.start_ms = self.start_ms.or(later.start_ms);
+        self.end_ms = later.end_ms;
+        self.attempts += later.attempts;
+        self.conflicts += later.conflicts;
+    }
+
+    /// The `land` span, if `land.sh` ran.
+    pub fn span(&self) -> Option<Span> {
+        if self.attempts == 0 {
+            return None;
+        }
+        let mut span = Span::between("land", self.start_ms?, self.end_ms);
+        span.conflicts = self.conflicts;
+        Some(span)
+    }
+}
+
+/// The ID of a Bash tool call that runs `land.sh`.
+fn land_call(block: &AssistantContentBlock) -> Option<String> {
+    let AssistantContentBlock::ToolUse { id, name, input } = block else {
+        return None;
+    };
+    let command = input["command"].as_str()?;
+    (name == "Bash" && command.contains("land.sh")).then(|| id.clone())
+}
+
+/// Whether a tool result's content (a string, or text blocks) reports
+/// rebase conflicts.
+fn mentions_conflicts(content: &Value) -> bool {
+    match content {
+        Value::String(text) => text.contains(CONFLICT_MARKER),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|b| b["text"].as_str())
+            .any(|text| text.contains(CONFLICT_MARKER)),
+        _ => false,
+    }
+}
+
+/// Spans finished since the last timing line was shown.
+#[derive(Debug, Default)]
+pub struct Spans {
+    pending: Vec<Span>,
+}
+
+impl Spans {
+    pub fn push(&mut self, span: Span) {
+        self.pending.push(span);
+    }
+
+    /// The pending spans as one line
+    /// (`sync 2s · dispatch 1m02s (lock wait 47s)`), clearing them. `None`
+    /// if nothing was timed.
+    pub fn take_line(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let parts: Vec<String> = self.pending.drain(..).map(|s| s.describe()).collect();
+        Some(parts.join(" \u{b7} "))
+    }
+}
+
+/// A duration for a timing line: `47s`, `1m02s`, `11m`, `1h05m`.
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{h}h{m:02}m")
+    } else if m == 0 {
+        format!("{s}s")
+    } else if s == 0 {
+        format!("{m}m")
+    } else {
+        format!("{m}m{s:02}s")
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn elapsed_formats() {
+        let cases = [
+            (0, "0s"),
+            (47, "47s"),
+            (62, "1m02s"),
+            (660, "11m"),
+            (3900, "1h05m"),
+        ];
+        for (secs, expected) in cases {
+            assert_eq!(format_elapsed(Duration::from_secs(secs)), expected);
+        }
+        assert_eq!(format_elapsed(Duration::from_millis(999)), "0s");
+    }
+
+    #[test]
+    fn take_line_joins_and_clears() {
+        let mut spans = Spans::default();
+        assert_eq!(spans.take_line(), None);
+        spans.push(Span::between("sync", 1_000, 3_400));
+        let mut dispatch = Span::between("dispatch", 3_400, 65_400);
+        dispatch.lock_wait = Duration::from_secs(47);
+        spans.push(dispatch);
+        let mut greet = Span::between("greet", 65_400, 725_400);
+        greet.lock_wait = Duration::from_millis(300);
+        spans.push(greet);
+        assert_eq!(
+            spans.take_line().unwrap(),
+            "sync 2s \u{b7} dispatch 1m02s (lock wait 47s) \u{b7} greet 11m"
+        );
+        assert_eq!(spans.take_line(), None);
+    }
+
+    #[test]
+    fn land_span_counts_conflicted_attempts() {
+        let events = [
+            json!({"type": "assistant", "message": {"content": [
+                {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "bash .coven/land.sh"}},
+            ]}}),
+            json!({"type": "user", "tool_use_result": "Error: Exit code 1", "message": {"content": [
+                {"type": "tool_result", "tool_use_id": "t1", "content": "Error: Exit code 1\nRebase has conflicts in:\n  a.txt"},
+            ]}}),
+            json!({"type": "assistant", "message": {"content": [
+                {"type": "tool_use", "id": "t2", "name": "Bash", "input": {"command": "git add a.txt"}},
+            ]}}),
+            json!({"type": "user", "tool_use_result": {"stdout": ""}, "message": {"content": [
+                {"type": "tool_result", "tool_use_id": "t2", "content": ""},
+            ]}}),
+            json!({"type": "assistant", "message": {"content": [
+                {"type": "tool_use", "id": "t3", "name": "Bash", "input": {"command": "bash .coven/land.sh"}},
+            ]}}),
+            json!({"type": "user", "tool_use_result": {"stdout": "Landed"}, "message": {"content": [
+                {"type": "tool_result", "tool_use_id": "t3", "content": [{"type": "text", "text": "Landed 1 commit(s) on main"}]},
+            ]}}),
+        ];
+        let mut clock = [10_000, 15_000, 190_000].into_iter();
+        let mut land = LandAttempts::default();
+        for event in events {
+            let event: InboundEvent = serde_json::from_value(event).unwrap();
+            land.observe(&event, || Ok(clock.next().unwrap())).unwrap();
+        }
+        assert_eq!(clock.next(), None, "only land.sh calls read the clock");
+        assert_eq!(land.span().unwrap().describe(), "land 3m (1 conflict)");
+
+        let mut phase = LandAttempts::default();
+        phase.merge(&LandAttempts::default());
+        assert_eq!(phase.span(), None);
+        phase.merge(&land);
+        assert_eq!(phase.span().unwrap().elapsed, Duration::from_mins(3));
+    }
+
+    #[test]
+    fn clock_going_backwards_is_zero() {
+        assert_eq!(Span::between("x", 5_000, 4_000).elapsed, Duration::ZERO);
+    }
+}
@@ -200,6 +200,22 @@ pub fn corrective_prompt(
     out
 }
 
+/// Build the corrective prompt for when dispatch picks an agent that other
+/// workers already run at its `max_concurrency` limit.
+pub fn saturated_prompt(saturated: &[(&str, u32)]) -> String {
+    let list: Vec<_> = saturated
+        .iter()
+        .map(|(name, max)| format!("- {name} (max_concurrency: {max})"))
+        .collect();
+    format!(
+        "These agents are already running on as many workers as their max_concurrency \
+         allows, so they can't be started now:\n\n{}\n\n\
+         Choose a different agent, or sleep if nothing else is worth doing, and output \
+         your decision in a new <next> tag.",
+        list.join("\n")
+    )
+}
+
 /// Extract content between `<tag>` and `</tag>`.
 fn extract_tag_content(text: &str, tag: &str) -> Result<String> {
     crate::protocol::parse::extract_tag_inner(text, tag)
@@ -542,4 +558,11 @@ issue: issues/fix-scroll-bug.md
         assert!(prompt.contains("agent: <agent-name>"));
         assert!(!prompt.contains("Available agents:"));
     }
+
+    #[test]
+    fn saturated_prompt_lists_agents_and_limits() {
+        let prompt = saturated_prompt(&[("implement", 2), ("audit", 1)]);
+        assert!(prompt.contains("- implement (max_concurrency: 2)\n- audit (max_concurrency: 1)"));
+        assert!(prompt.contains("<next>"));
+    }
 }
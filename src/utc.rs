@@ -0,0 +1,62 @@
+//! UTC calendar fields for the timestamps coven shows and writes, without a
+//! date-time dependency.
+
+/// A moment in UTC, broken down into calendar fields. `month` and `day`
+/// count from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UtcDateTime {
+    pub year: u64,
+    pub month: u64,
+    pub day: u64,
+    pub hour: u64,
+    pub minute: u64,
+    pub second: u64,
+}
+
+impl UtcDateTime {
+    /// Break down seconds since the Unix epoch.
+    pub fn from_unix(secs: u64) -> Self {
+        let (days, day_secs) = (secs / 86_400, secs % 86_400);
+        // Civil-from-days (Howard Hinnant), shifted so the year starts in March.
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z % 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        Self {
+            year: yoe + era * 400 + u64::from(month <= 2),
+            month,
+            day: doy - (153 * mp + 2) / 5 + 1,
+            hour: day_secs / 3600,
+            minute: day_secs / 60 % 60,
+            second: day_secs % 60,
+        }
+    }
+
+    /// Three-letter English month name, e.g. `Feb`.
+    pub fn month_abbrev(&self) -> &'static str {
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+        MONTHS[usize::try_from(self.month - 1).unwrap_or_default()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_unix_handles_epoch_leap_day_and_year_end() {
+        let at = |secs| {
+            let t = UtcDateTime::from_unix(secs);
+            (t.year, t.month, t.day, t.hour, t.minute, t.second)
+        };
+        assert_eq!(at(0), (1970, 1, 1, 0, 0, 0));
+        assert_eq!(at(951_782_400), (2000, 2, 29, 0, 0, 0));
+        assert_eq!(at(1_709_210_096), (2024, 2, 29, 12, 34, 56));
+        assert_eq!(at(1_798_761_599), (2026, 12, 31, 23, 59, 59));
+    }
+}
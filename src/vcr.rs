@@ -1,7 +1,10 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::io::Write;
 use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Result, bail};
 use crossterm::event::Event;
@@ -10,7 +13,10 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tokio::sync::mpsc;
 
+use crate::config::Config;
+use crate::display::TerminalSize;
 use crate::event::AppEvent;
+use crate::notify::Notifier;
 use crate::session::runner::SessionRunner;
 
 /// Default model used for VCR test recordings. Shared between record-vcr and the test harness
@@ -92,6 +98,24 @@ struct VcrEntry {
     result: Value,
 }
 
+/// The events claude sent in a recording, in order, with everything else
+/// (terminal input, other calls) dropped — a canned stand-in for a claude
+/// process (`coven tutorial`).
+pub fn recorded_claude_events(data: &str) -> Result<Vec<AppEvent>> {
+    let mut events = Vec::new();
+    for line in data.lines().filter(|line| !line.trim().is_empty()) {
+        let entry: VcrEntry = serde_json::from_str(line)?;
+        if entry.label != "next_event" {
+            continue;
+        }
+        let recorded: std::result::Result<IoEvent, String> = serde_json::from_value(entry.result)?;
+        if let Ok(IoEvent::Claude(event)) = recorded {
+            events.push(event);
+        }
+    }
+    Ok(events)
+}
+
 // ── VcrContext ───────────────────────────────────────────────────────────
 
 /// Operating mode for the VCR context.
@@ -114,6 +138,10 @@ struct ReplayState {
 pub struct VcrContext {
     mode: VcrMode,
     trigger_controller: Option<RefCell<TriggerController>>,
+    /// Rendered output to checksum (opt-in; see [`OutputTap`]).
+    output_tap: Option<OutputTap>,
+    /// A Result event was recorded; checksum the output once it's rendered.
+    checksum_pending: Cell<bool>,
 }
 
 impl VcrContext {
@@ -122,6 +150,8 @@ impl VcrContext {
         Self {
             mode: VcrMode::Live,
             trigger_controller: None,
+            output_tap: None,
+            checksum_pending: Cell::new(false),
         }
     }
 
@@ -130,6 +160,8 @@ impl VcrContext {
         Self {
             mode: VcrMode::Record(RefCell::new(Vec::new())),
             trigger_controller: None,
+            output_tap: None,
+            checksum_pending: Cell::new(false),
         }
     }
 
@@ -138,6 +170,8 @@ impl VcrContext {
         Self {
             mode: VcrMode::Record(RefCell::new(Vec::new())),
             trigger_controller: Some(RefCell::new(controller)),
+            output_tap: None,
+            checksum_pending: Cell::new(false),
         }
     }
 
@@ -157,9 +191,43 @@ impl VcrContext {
                 position: 0,
             })),
             trigger_controller: None,
+            output_tap: None,
+            checksum_pending: Cell::new(false),
         })
     }
 
+    /// Whether operations are being recorded.
+    pub fn is_recording(&self) -> bool {
+        matches!(self.mode, VcrMode::Record(_))
+    }
+
+    /// Checksum the output fed to `tap`: recorded after each Result event
+    /// and at [`checkpoint_output`](Self::checkpoint_output) when recording,
+    /// and compared against those checkpoints when replaying.
+    #[must_use]
+    pub fn with_output_tap(mut self, tap: OutputTap) -> Self {
+        self.output_tap = Some(tap);
+        self
+    }
+
+    /// Labels of recorded calls that replay hasn't reached (empty unless replaying).
+    pub fn unreplayed_labels(&self) -> Vec<String> {
+        let VcrMode::Replay(ref state) = self.mode else {
+            return Vec::new();
+        };
+        let state = state.borrow();
+        state.entries[state.position..]
+            .iter()
+            .map(|e| e.label.clone())
+            .collect()
+    }
+
+    /// Checkpoint the rendered output, e.g. when the command has returned.
+    pub fn checkpoint_output(&self) -> Result<()> {
+        self.checksum_pending.set(true);
+        self.sync_output_checksum()
+    }
+
     /// Write the recording to an NDJSON file.
     pub fn write_recording(&self, path: &Path) -> Result<()> {
         let VcrMode::Record(ref entries) = self.mode else {
@@ -193,6 +261,7 @@ impl VcrContext {
         A::Recorded: PartialEq + Debug,
         T: Recordable,
     {
+        self.sync_output_checksum()?;
         match &self.mode {
             VcrMode::Live => f(&args).await,
             VcrMode::Record(entries) => {
@@ -237,6 +306,7 @@ impl VcrContext {
         T: Recordable,
         E: RecordableError,
     {
+        self.sync_output_checksum()?;
         match &self.mode {
             VcrMode::Live => Ok(f(&args).await),
             VcrMode::Record(entries) => {
@@ -260,6 +330,108 @@ impl VcrContext {
         }
     }
 
+    /// The terminal size from `query`, recorded as an entry of its own.
+    ///
+    /// Replay returns the recorded size whatever terminal the tests run in.
+    /// Recordings made before sizes were captured have no such entry, and
+    /// replay at the default size.
+    pub fn terminal_size(&self, query: impl FnOnce() -> TerminalSize) -> Result<TerminalSize> {
+        self.sync_output_checksum()?;
+        match &self.mode {
+            VcrMode::Live => Ok(query()),
+            VcrMode::Record(entries) => {
+                let size = query();
+                self.push_entry(entries, TERMINAL_SIZE_LABEL, &(), &Ok::<_, String>(size))?;
+                Ok(size)
+            }
+            VcrMode::Replay(state) => {
+                Ok(Self::replay_optional(state, TERMINAL_SIZE_LABEL)?.unwrap_or_default())
+            }
+        }
+    }
+
+    /// `claude --version` output from `query` (`None` if claude didn't run).
+    /// Recorded like [`terminal_size`](Self::terminal_size); recordings
+    /// without a version entry replay as if claude didn't answer.
+    pub fn claude_version(&self, query: impl FnOnce() -> Option<String>) -> Result<Option<String>> {
+        self.sync_output_checksum()?;
+        match &self.mode {
+            VcrMode::Live => Ok(query()),
+            VcrMode::Record(entries) => {
+                let version = query();
+                self.push_entry(
+                    entries,
+                    CLAUDE_VERSION_LABEL,
+                    &(),
+                    &Ok::<_, String>(&version),
+                )?;
+                Ok(version)
+            }
+            VcrMode::Replay(state) => {
+                Ok(Self::replay_optional(state, CLAUDE_VERSION_LABEL)?.flatten())
+            }
+        }
+    }
+
+    /// The project's `.coven/config.toml` from `load`. Recorded like
+    /// [`terminal_size`](Self::terminal_size); recordings without a config
+    /// entry replay as if the project had no config file.
+    pub fn project_config(&self, load: impl FnOnce() -> Result<Config>) -> Result<Config> {
+        self.sync_output_checksum()?;
+        match &self.mode {
+            VcrMode::Live => load(),
+            VcrMode::Record(entries) => {
+                let config = load();
+                let recorded = config.as_ref().map_err(|e| format!("{e:#}"));
+                self.push_entry(entries, PROJECT_CONFIG_LABEL, &(), &recorded)?;
+                config
+            }
+            VcrMode::Replay(state) => {
+                Ok(Self::replay_optional(state, PROJECT_CONFIG_LABEL)?.unwrap_or_default())
+            }
+        }
+    }
+
+    /// Milliseconds since the Unix epoch, for timing worker phases and ralph
+    /// iterations. Recorded like [`terminal_size`](Self::terminal_size);
+    /// recordings without clock entries replay a stopped clock at 0.
+    pub fn clock_ms(&self) -> Result<u64> {
+        let now = || -> Result<u64> {
+            let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH)?;
+            Ok(u64::try_from(since_epoch.as_millis())?)
+        };
+        self.sync_output_checksum()?;
+        match &self.mode {
+            VcrMode::Live => now(),
+            VcrMode::Record(entries) => {
+                let ms = now()?;
+                self.push_entry(entries, CLOCK_LABEL, &(), &Ok::<_, String>(ms))?;
+                Ok(ms)
+            }
+            VcrMode::Replay(state) => Ok(Self::replay_optional(state, CLOCK_LABEL)?.unwrap_or(0)),
+        }
+    }
+
+    /// The next entry's result if it has `label`, consuming it; `None` (and
+    /// nothing consumed) for recordings made before `label` was captured.
+    fn replay_optional<T: DeserializeOwned>(
+        state: &RefCell<ReplayState>,
+        label: &str,
+    ) -> Result<Option<T>> {
+        let mut state = state.borrow_mut();
+        let Some(entry) = state
+            .entries
+            .get(state.position)
+            .filter(|entry| entry.label == label)
+        else {
+            return Ok(None);
+        };
+        let recorded: std::result::Result<T, String> =
+            serde_json::from_value(entry.result.clone())?;
+        state.position += 1;
+        recorded.map(Some).map_err(|msg| anyhow::anyhow!("{msg}"))
+    }
+
     /// Advance the replay position and validate that the label and args match.
     /// Returns the raw recorded result `Value` for the caller to deserialize.
     fn advance_replay<A>(state: &RefCell<ReplayState>, label: &str, args: &A) -> Result<Value>
@@ -319,11 +491,164 @@ impl VcrContext {
         };
         let result_value = entry.result.clone();
         entries.borrow_mut().push(entry);
+        if self.output_tap.is_some() && is_result_event(label, &result_value) {
+            self.checksum_pending.set(true);
+        }
         if let Some(ref tc) = self.trigger_controller {
             tc.borrow_mut().check(label, &result_value);
         }
         Ok(())
     }
+
+    /// Record or verify an output checksum at this point in the recording.
+    ///
+    /// Recording writes one when a checkpoint is pending. Replay checks every
+    /// checksum entry found next in the recording, so recordings made without
+    /// `--checksums` replay unchanged.
+    fn sync_output_checksum(&self) -> Result<()> {
+        match &self.mode {
+            VcrMode::Live => {}
+            VcrMode::Record(entries) => {
+                if let Some(tap) = &self.output_tap
+                    && self.checksum_pending.replace(false)
+                {
+                    entries.borrow_mut().push(VcrEntry {
+                        label: OUTPUT_CHECKSUM_LABEL.to_string(),
+                        args: Value::Null,
+                        result: serde_json::to_value(tap.checksum())?,
+                    });
+                }
+            }
+            VcrMode::Replay(state) => {
+                let mut state = state.borrow_mut();
+                while let Some(entry) = state.entries.get(state.position)
+                    && entry.label == OUTPUT_CHECKSUM_LABEL
+                {
+                    let pos = state.position;
+                    let expected: OutputChecksum = serde_json::from_value(entry.result.clone())?;
+                    state.position += 1;
+                    let Some(tap) = &self.output_tap else {
+                        continue;
+                    };
+                    let actual = tap.checksum();
+                    if actual != expected && std::env::var_os(SKIP_CHECKSUMS_ENV).is_none() {
+                        bail!(
+                            "rendered output diverged from the recording at VCR position {pos}: \
+                             expected {expected}, got {actual}. Rerun with \
+                             {SKIP_CHECKSUMS_ENV}=1 to see the snapshot diff; if the change is \
+                             intended, accept it with `cargo insta accept` and re-record with \
+                             `cargo run --bin record-vcr -- --checksums`."
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether a recorded entry is claude's Result event for the main session.
+fn is_result_event(label: &str, result: &Value) -> bool {
+    label == "next_event"
+        && is_subset(
+            &serde_json::json!({"Ok": {"Claude": {"Claude": {"type": "result"}}}}),
+            result,
+        )
+}
+
+// ── Output checksums ────────────────────────────────────────────────────
+
+/// Label of the VCR entry holding the terminal size.
+const TERMINAL_SIZE_LABEL: &str = "terminal_size";
+
+/// Label of the VCR entry holding `claude --version` output.
+const CLAUDE_VERSION_LABEL: &str = "claude_version";
+
+/// Label of the VCR entry holding the project's `.coven/config.toml`.
+const PROJECT_CONFIG_LABEL: &str = "project_config";
+
+/// Label of the VCR entries holding clock readings.
+const CLOCK_LABEL: &str = "clock";
+
+/// Label of the VCR entries holding rendered-output checksums.
+const OUTPUT_CHECKSUM_LABEL: &str = "output_checksum";
+
+/// Set this environment variable to skip output checksum comparison during
+/// replay, e.g. while deliberately changing rendering.
+pub const SKIP_CHECKSUMS_ENV: &str = "VCR_SKIP_CHECKSUMS";
+
+/// Length and [FNV-1a](crate::fnv) hash of everything rendered so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputChecksum {
+    pub bytes: u64,
+    pub fnv: u64,
+}
+
+impl Default for OutputChecksum {
+    fn default() -> Self {
+        Self {
+            bytes: 0,
+            fnv: crate::fnv::OFFSET_BASIS,
+        }
+    }
+}
+
+impl OutputChecksum {
+    fn update(mut self, buf: &[u8]) -> Self {
+        self.fnv = crate::fnv::extend(self.fnv, buf);
+        self.bytes += buf.len() as u64;
+        self
+    }
+}
+
+impl std::fmt::Display for OutputChecksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} bytes (fnv {:016x})", self.bytes, self.fnv)
+    }
+}
+
+/// Shared running checksum of a command's rendered output. Fed by a
+/// [`ChecksumWriter`] and read by the [`VcrContext`] it's attached to.
+#[derive(Clone, Default)]
+pub struct OutputTap(Rc<Cell<OutputChecksum>>);
+
+impl OutputTap {
+    /// Wrap `inner` so everything written to it is checksummed.
+    pub fn writer<W: Write>(&self, inner: W) -> ChecksumWriter<W> {
+        ChecksumWriter {
+            inner,
+            tap: self.clone(),
+        }
+    }
+
+    pub fn checksum(&self) -> OutputChecksum {
+        self.0.get()
+    }
+}
+
+/// Tee writer: passes output through to `inner` and feeds an [`OutputTap`].
+pub struct ChecksumWriter<W> {
+    inner: W,
+    tap: OutputTap,
+}
+
+impl<W> ChecksumWriter<W> {
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        let cell = &self.tap.0;
+        cell.set(cell.get().update(&buf[..n]));
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
 }
 
 // ── IoEvent ─────────────────────────────────────────────────────────────
@@ -337,6 +662,13 @@ pub enum IoEvent {
     Claude(AppEvent),
     /// A terminal key/resize/etc event.
     Terminal(Event),
+    /// The silence timer armed via [`Io::arm_silence_timer`] expired with no
+    /// claude output in the meantime.
+    Silence,
+    /// The session timeout armed via [`Io::arm_session_timeout`] expired.
+    SessionTimeout,
+    /// Shutdown was requested (SIGTERM/SIGINT, see [`Io::watch_shutdown`]).
+    Shutdown,
 }
 
 // ── Io struct ───────────────────────────────────────────────────────────
@@ -364,6 +696,19 @@ pub struct Io {
     /// for snapshots) but still has no physical tty, so raw mode can't
     /// actually be enabled. `RawModeGuard` checks this flag.
     has_tty_stdin: bool,
+    /// When set, `next_event` yields `IoEvent::Silence` if no claude event
+    /// arrives before this instant. Cleared by any claude event.
+    silence_deadline: Option<tokio::time::Instant>,
+    /// When set, `next_event` yields `IoEvent::SessionTimeout` at this
+    /// instant, whatever claude is doing.
+    session_deadline: Option<tokio::time::Instant>,
+    /// Set to true by the signal handler when shutdown is requested. Once it
+    /// is, every `next_event` yields `IoEvent::Shutdown`.
+    shutdown_rx: Option<tokio::sync::watch::Receiver<bool>>,
+    /// A shutdown event was seen, live or replayed (see `note_shutdown`).
+    shutdown_seen: bool,
+    /// The `--notify` command, run whenever coven waits for the user.
+    notifier: Option<Notifier>,
 }
 
 impl Io {
@@ -378,6 +723,11 @@ impl Io {
             term_gate: None,
             term_tx_keepalive: None,
             has_tty_stdin: false,
+            silence_deadline: None,
+            session_deadline: None,
+            shutdown_rx: None,
+            shutdown_seen: false,
+            notifier: None,
         }
     }
 
@@ -392,6 +742,11 @@ impl Io {
             term_gate: None,
             term_tx_keepalive: None,
             has_tty_stdin: false,
+            silence_deadline: None,
+            session_deadline: None,
+            shutdown_rx: None,
+            shutdown_seen: false,
+            notifier: None,
         }
     }
 
@@ -407,9 +762,23 @@ impl Io {
             term_gate: None,
             term_tx_keepalive: Some(tx2),
             has_tty_stdin: false,
+            silence_deadline: None,
+            session_deadline: None,
+            shutdown_rx: None,
+            shutdown_seen: false,
+            notifier: None,
         }
     }
 
+    /// Run `notifier` whenever coven starts waiting for the user.
+    pub fn set_notifier(&mut self, notifier: Notifier) {
+        self.notifier = Some(notifier);
+    }
+
+    pub fn notifier(&self) -> Option<&Notifier> {
+        self.notifier.as_ref()
+    }
+
     /// Mark this `Io` as headless (no tty stdin) by stashing the `term_tx`
     /// sender. See `term_tx_keepalive` for the rationale.
     pub fn set_term_tx_keepalive(&mut self, tx: mpsc::UnboundedSender<Event>) {
@@ -430,10 +799,42 @@ impl Io {
         self.has_tty_stdin
     }
 
+    /// Yield `IoEvent::Shutdown` from `next_event` once `rx` turns true.
+    /// Only production live mode installs this, from the signal handler.
+    pub fn watch_shutdown(&mut self, rx: tokio::sync::watch::Receiver<bool>) {
+        self.shutdown_rx = Some(rx);
+    }
+
+    /// Record that an `IoEvent::Shutdown` was handled. Replayed events never
+    /// pass through `next_event`, so consumers call this to keep
+    /// `shutdown_requested` accurate in tests.
+    pub fn note_shutdown(&mut self) {
+        self.shutdown_seen = true;
+    }
+
+    /// Whether shutdown has been requested, so callers can stop at the next
+    /// safe point instead of starting new work.
+    pub fn shutdown_requested(&self) -> bool {
+        self.shutdown_seen || self.shutdown_rx.as_ref().is_some_and(|rx| *rx.borrow())
+    }
+
     /// Get the next event from either the Claude process or the terminal.
+    ///
+    /// If the silence timer is armed and expires first, returns
+    /// `IoEvent::Silence` (once — the timer is disarmed). Likewise for the
+    /// session timeout and `IoEvent::SessionTimeout`. Once shutdown is
+    /// requested, returns `IoEvent::Shutdown` every time.
     pub async fn next_event(&mut self) -> Result<IoEvent> {
+        if self.shutdown_requested() {
+            return Ok(IoEvent::Shutdown);
+        }
+        let deadline = self.silence_deadline;
+        let session_deadline = self.session_deadline;
+        let shutdown_rx = self.shutdown_rx.as_mut();
         tokio::select! {
+            () = wait_for_shutdown(shutdown_rx) => Ok(IoEvent::Shutdown),
             event = self.event_rx.recv() => {
+                self.silence_deadline = None;
                 Ok(IoEvent::Claude(
                     event.unwrap_or(AppEvent::ProcessExit(None))
                 ))
@@ -444,14 +845,48 @@ impl Io {
                     None => Ok(IoEvent::Claude(AppEvent::ProcessExit(None))),
                 }
             }
+            () = sleep_until_opt(deadline) => {
+                self.silence_deadline = None;
+                Ok(IoEvent::Silence)
+            }
+            () = sleep_until_opt(session_deadline) => {
+                self.session_deadline = None;
+                Ok(IoEvent::SessionTimeout)
+            }
         }
     }
 
+    /// Get the next terminal event, ignoring the claude channel — for
+    /// reading input while the session's own process isn't running (fork).
+    /// Returns `None` once the terminal channel closes.
+    pub async fn next_term_event(&mut self) -> Option<Event> {
+        self.term_rx.recv().await
+    }
+
+    /// Arm (or re-arm) the silence timer: if no claude event arrives within
+    /// `window`, `next_event` yields `IoEvent::Silence`.
+    pub fn arm_silence_timer(&mut self, window: Duration) {
+        self.silence_deadline = Some(tokio::time::Instant::now() + window);
+    }
+
+    /// Arm (or re-arm) the session timeout: `window` from now, `next_event`
+    /// yields `IoEvent::SessionTimeout`. Unlike the silence timer, claude
+    /// output doesn't disarm it.
+    pub fn arm_session_timeout(&mut self, window: Duration) {
+        self.session_deadline = Some(tokio::time::Instant::now() + window);
+    }
+
+    /// Disarm the session timeout without waiting for it to fire.
+    pub fn disarm_session_timeout(&mut self) {
+        self.session_deadline = None;
+    }
+
     /// Replace the event channel and return the new sender.
     ///
     /// The old receiver (and any stale events like `ProcessExit`) is dropped.
     pub fn replace_event_channel(&mut self) -> mpsc::UnboundedSender<AppEvent> {
         self.idle_tx = None;
+        self.silence_deadline = None;
         let (tx, rx) = mpsc::unbounded_channel();
         self.event_rx = rx;
         tx
@@ -465,6 +900,7 @@ impl Io {
     /// Called automatically by `wait_for_interrupt_input`; also used
     /// directly in `wait_for_new_commits` and at startup.
     pub fn clear_event_channel(&mut self) {
+        self.silence_deadline = None;
         let (tx, rx) = mpsc::unbounded_channel();
         self.event_rx = rx;
         self.idle_tx = Some(tx);
@@ -495,6 +931,25 @@ impl Io {
     }
 }
 
+/// Sleep until `deadline`, or forever if there is none.
+async fn sleep_until_opt(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(d) => tokio::time::sleep_until(d).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Resolve once the shutdown watch turns true; never, if there's no watch or
+/// its sender is gone.
+async fn wait_for_shutdown(rx: Option<&mut tokio::sync::watch::Receiver<bool>>) {
+    if let Some(rx) = rx
+        && rx.wait_for(|&requested| requested).await.is_ok()
+    {
+        return;
+    }
+    std::future::pending::<()>().await;
+}
+
 // ── TriggerController ───────────────────────────────────────────────────
 
 /// Injects scripted terminal input during recording based on trigger conditions.
@@ -506,6 +961,8 @@ pub struct TriggerController {
     /// When true, automatically inject Ctrl+D after all triggers have fired
     /// and a result event is seen. Used for `run` mode recordings.
     auto_exit: bool,
+    /// Requests shutdown for `shutdown` triggers, as SIGTERM would.
+    shutdown_tx: Option<tokio::sync::watch::Sender<bool>>,
 }
 
 struct PendingTrigger {
@@ -519,8 +976,9 @@ struct PendingTrigger {
 }
 
 /// Whether a triggered message is a steering (Enter), follow-up (Alt+Enter),
-/// exit (Ctrl+D), interrupt (Ctrl+C followed by resume text), or typing
-/// (characters only, no Enter — activates input so subsequent events are buffered).
+/// exit (Ctrl+D), interrupt (Ctrl+C followed by resume text), typing
+/// (characters only, no Enter — activates input so subsequent events are buffered),
+/// or a shutdown signal (see [`TriggerController::with_shutdown`]).
 #[derive(Clone, Copy, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum TriggerInputMode {
@@ -530,6 +988,7 @@ pub enum TriggerInputMode {
     Exit,
     Interrupt,
     Typing,
+    Shutdown,
 }
 
 impl TriggerController {
@@ -560,9 +1019,18 @@ impl TriggerController {
             triggers,
             term_tx,
             auto_exit: false,
+            shutdown_tx: None,
         })
     }
 
+    /// Send `shutdown` triggers through `tx`, the sender for the watch the
+    /// recording's `Io` was given with [`Io::watch_shutdown`].
+    #[must_use]
+    pub fn with_shutdown(mut self, tx: tokio::sync::watch::Sender<bool>) -> Self {
+        self.shutdown_tx = Some(tx);
+        self
+    }
+
     /// Enable auto-exit: inject Ctrl+D after all triggers fired and an idle
     /// event is seen. Works across all command types (run, ralph, worker).
     #[must_use]
@@ -599,6 +1067,11 @@ impl TriggerController {
         for (text, mode) in &to_inject {
             match mode {
                 TriggerInputMode::Exit => inject_exit(&self.term_tx),
+                TriggerInputMode::Shutdown => {
+                    if let Some(tx) = &self.shutdown_tx {
+                        tx.send_replace(true);
+                    }
+                }
                 TriggerInputMode::Interrupt => {
                     inject_interrupt(&self.term_tx);
                     if !text.is_empty() {
@@ -641,8 +1114,11 @@ fn inject_text(term_tx: &mpsc::UnboundedSender<Event>, text: &str, mode: Trigger
     let enter_modifiers = match mode {
         TriggerInputMode::Followup => KeyModifiers::ALT,
         TriggerInputMode::Steering => KeyModifiers::NONE,
-        TriggerInputMode::Exit | TriggerInputMode::Interrupt | TriggerInputMode::Typing => {
-            unreachable!("Exit/Interrupt/Typing triggers are handled separately")
+        TriggerInputMode::Exit
+        | TriggerInputMode::Interrupt
+        | TriggerInputMode::Typing
+        | TriggerInputMode::Shutdown => {
+            unreachable!("Exit/Interrupt/Typing/Shutdown triggers are handled separately")
         }
     };
     let enter = Event::Key(KeyEvent {
@@ -705,6 +1181,8 @@ pub struct TestCase {
     pub gc: Option<GcTestConfig>,
     /// Configuration for the status command.
     pub status: Option<StatusTestConfig>,
+    /// Configuration for the fork command.
+    pub fork: Option<ForkTestConfig>,
     /// Configuration for multi-step tests (e.g. init + concurrent workers).
     pub multi: Option<MultiConfig>,
     /// Display/renderer configuration for test replay.
@@ -719,14 +1197,35 @@ pub struct TestCase {
     /// Message labels to snapshot via `:N` or `:P/C` view commands.
     #[serde(default)]
     pub views: Vec<String>,
+    /// Assertions checked against the command's structured report.
+    #[serde(default)]
+    pub expect: ExpectConfig,
+}
+
+/// Structured expectations for test replay (not used during recording).
+#[derive(Deserialize, Default)]
+pub struct ExpectConfig {
+    /// Session ID of the last completed turn.
+    pub session_id: Option<String>,
+    /// Substring the last result text must contain.
+    pub result_contains: Option<String>,
+    /// Number of ralph iterations that completed at least one turn.
+    pub iterations: Option<usize>,
+    /// Why the ralph loop stopped, as the `StopReason` variant name.
+    pub stop_reason: Option<String>,
+    /// The reason the model gave in its break tag.
+    pub break_reason: Option<String>,
+    /// Worker agent phases, in the order they ran.
+    #[serde(default)]
+    pub phases: Vec<String>,
 }
 
 /// Display configuration for test replay (not used during recording).
 #[derive(Deserialize, Default)]
 pub struct DisplayConfig {
-    /// Whether to stream thinking text inline.
+    /// Whether to stream thinking text inline (`--show-thinking`).
     #[serde(default)]
-    pub show_thinking: bool,
+    pub show_thinking: Option<bool>,
     /// Run the test as if stdin is not a terminal (no tty). The test runner
     /// and recorder construct an `Io` where `is_headless()` is true, which
     /// exercises the same code paths production headless invocations take.
@@ -738,18 +1237,39 @@ pub struct DisplayConfig {
 #[derive(Deserialize)]
 pub struct RunConfig {
     /// Prompt to send to claude.
+    #[serde(default)]
     pub prompt: String,
+    /// Read the prompt from this file (one of the case's `files`) instead,
+    /// as `--prompt-file` does.
+    #[serde(default)]
+    pub prompt_file: Option<String>,
     /// Extra arguments to pass through to claude.
     #[serde(default)]
     pub claude_args: Vec<String>,
     /// Whether to enable fork support.
     #[serde(default)]
     pub fork: bool,
+    /// `--fork-tools` spec restricting the forked children's tools.
+    #[serde(default)]
+    pub fork_tools: Option<String>,
+    /// `--fork-tasks` run before the first turn.
+    #[serde(default)]
+    pub fork_tasks: Vec<String>,
     /// Whether to enable reload support.
     #[serde(default)]
     pub reload: bool,
 }
 
+impl RunConfig {
+    /// The prompt to send: `prompt`, or `prompt_file` read from `dir`.
+    pub fn resolve_prompt(&self, dir: &Path) -> Result<String> {
+        match &self.prompt_file {
+            Some(file) => crate::commands::read_prompt_file(&dir.join(file)),
+            None => Ok(self.prompt.clone()),
+        }
+    }
+}
+
 /// CLI configuration for ralph loop mode (mirrors coven's ralph subcommand args).
 #[derive(Deserialize)]
 pub struct RalphConfig {
@@ -765,6 +1285,9 @@ pub struct RalphConfig {
     /// Disable <wait-for-user> tag detection.
     #[serde(default)]
     pub no_wait: bool,
+    /// `--iteration-timeout` in seconds.
+    #[serde(default)]
+    pub iteration_timeout: Option<u64>,
     /// Extra arguments to pass through to claude.
     #[serde(default)]
     pub claude_args: Vec<String>,
@@ -780,6 +1303,44 @@ pub struct WorkerTestConfig {
     /// Extra arguments to pass through to claude.
     #[serde(default)]
     pub claude_args: Vec<String>,
+    /// Command to run after an agent lands (`--post-land`).
+    #[serde(default)]
+    pub post_land: Option<String>,
+    /// How many cycles to run (`--once`, `--dispatch-only`).
+    #[serde(flatten)]
+    pub cycles: WorkerTestCycles,
+    /// Other workers the recorder registers (under its own PID) first.
+    #[serde(default)]
+    pub peers: Vec<StatusTestWorker>,
+    /// Request shutdown, as a signal would, before the worker starts.
+    #[serde(default)]
+    pub shutdown_at_start: bool,
+    /// Extra agent definitions directory (`--agents-dir`), relative to the
+    /// repo.
+    #[serde(default)]
+    pub agents_dir: Option<String>,
+    /// Save each dispatch's inputs and output (`--debug-dispatch`).
+    #[serde(default)]
+    pub debug_dispatch: bool,
+    /// Pass `--no-default-permission-mode`.
+    #[serde(default)]
+    pub no_default_permission_mode: bool,
+    /// Print the worktree removal plan on exit instead of removing it
+    /// (`--dry-run-cleanup`).
+    #[serde(default)]
+    pub dry_run_cleanup: bool,
+}
+
+/// The cycle flags of a worker test case, written at the top level of
+/// `[worker]`.
+#[derive(Deserialize)]
+pub struct WorkerTestCycles {
+    /// Run a single dispatch cycle, then exit (`--once`).
+    #[serde(default)]
+    pub once: bool,
+    /// Run one dispatch, show its decision and exit (`--dispatch-only`).
+    #[serde(default)]
+    pub dispatch_only: bool,
 }
 
 /// A message to send during a recording session.
@@ -794,7 +1355,8 @@ pub struct TestMessage {
     /// If set, only match VCR calls with this label.
     #[serde(default)]
     pub label: Option<String>,
-    /// How to send: "followup" (Alt+Enter) or "steering" (Enter). Defaults to "followup".
+    /// How to send: "followup" (Alt+Enter), "steering" (Enter), or another
+    /// [`TriggerInputMode`]. Defaults to "followup".
     #[serde(default)]
     pub mode: TriggerInputMode,
 }
@@ -805,6 +1367,9 @@ pub struct InitTestConfig {
     /// Stdin input for the CLAUDE.md prompt (e.g. "y" or "n").
     #[serde(default)]
     pub stdin: String,
+    /// Run `init --update` instead of a fresh init.
+    #[serde(default)]
+    pub update: bool,
 }
 
 /// Configuration for the gc command test case.
@@ -813,7 +1378,37 @@ pub struct GcTestConfig {}
 
 /// Configuration for the status command test case.
 #[derive(Deserialize, Default)]
-pub struct StatusTestConfig {}
+pub struct StatusTestConfig {
+    /// Pass `--json`.
+    #[serde(default)]
+    pub json: bool,
+    /// Workers the recorder registers (under its own PID) before running status.
+    #[serde(default)]
+    pub workers: Vec<StatusTestWorker>,
+}
+
+/// A worker registered for a status test case.
+#[derive(Deserialize)]
+pub struct StatusTestWorker {
+    pub branch: String,
+    #[serde(default)]
+    pub agent: Option<String>,
+    #[serde(default)]
+    pub args: HashMap<String, String>,
+}
+
+/// Configuration for the fork command test case.
+#[derive(Deserialize)]
+pub struct ForkTestConfig {
+    /// One child session per task.
+    pub tasks: Vec<String>,
+    /// Pass `--no-default-permission-mode`.
+    #[serde(default)]
+    pub no_default_permission_mode: bool,
+    /// Extra arguments to pass through to claude.
+    #[serde(default)]
+    pub claude_args: Vec<String>,
+}
 
 /// Configuration for multi-step test cases (e.g. init + concurrent workers).
 #[derive(Deserialize)]
@@ -874,3 +1469,252 @@ impl TestCase {
         self.multi.is_some()
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    /// Drive a fake session: a Result event, its rendering, then one more
+    /// event and a final line.
+    async fn render_session(vcr: &VcrContext, out: &mut impl Write, rendered: &str) -> Result<()> {
+        let result = serde_json::json!({"Claude": {"Claude": {"type": "result"}}});
+        vcr.call("next_event", (), async |(): &()| Ok(result.clone()))
+            .await?;
+        write!(out, "{rendered}")?;
+        vcr.call("next_event", (), async |(): &()| Ok(Value::Null))
+            .await?;
+        writeln!(out, "bye")?;
+        vcr.checkpoint_output()
+    }
+
+    fn recording(vcr: &VcrContext) -> String {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("case.vcr");
+        vcr.write_recording(&path).unwrap();
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    fn labels(ndjson: &str) -> Vec<String> {
+        ndjson
+            .lines()
+            .map(|l| serde_json::from_str::<VcrEntry>(l).unwrap().label)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn output_checksums_recorded_after_results_and_at_end() {
+        let tap = OutputTap::default();
+        let vcr = VcrContext::record().with_output_tap(tap.clone());
+        let mut out = tap.writer(Vec::new());
+        render_session(&vcr, &mut out, "hello\n").await.unwrap();
+        let ndjson = recording(&vcr);
+        assert_eq!(
+            labels(&ndjson),
+            [
+                "next_event",
+                OUTPUT_CHECKSUM_LABEL,
+                "next_event",
+                OUTPUT_CHECKSUM_LABEL
+            ]
+        );
+
+        let tap = OutputTap::default();
+        let replay = VcrContext::replay(&ndjson)
+            .unwrap()
+            .with_output_tap(tap.clone());
+        let mut out = tap.writer(Vec::new());
+        render_session(&replay, &mut out, "hello\n").await.unwrap();
+        assert_eq!(out.into_inner(), b"hello\nbye\n");
+    }
+
+    #[tokio::test]
+    async fn output_checksum_mismatch_fails_replay() {
+        let tap = OutputTap::default();
+        let vcr = VcrContext::record().with_output_tap(tap.clone());
+        render_session(&vcr, &mut tap.writer(std::io::sink()), "hello\n")
+            .await
+            .unwrap();
+        let ndjson = recording(&vcr);
+
+        let tap = OutputTap::default();
+        let replay = VcrContext::replay(&ndjson)
+            .unwrap()
+            .with_output_tap(tap.clone());
+        let err = render_session(&replay, &mut tap.writer(std::io::sink()), "hullo\n")
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with("rendered output diverged from the recording at VCR position 1"),
+            "{err}"
+        );
+        assert!(err.contains(SKIP_CHECKSUMS_ENV), "{err}");
+    }
+
+    #[tokio::test]
+    async fn recordings_without_checksums_replay_with_tap() {
+        let vcr = VcrContext::record();
+        render_session(&vcr, &mut std::io::sink(), "hello\n")
+            .await
+            .unwrap();
+        let ndjson = recording(&vcr);
+        assert_eq!(labels(&ndjson), ["next_event", "next_event"]);
+
+        let tap = OutputTap::default();
+        let replay = VcrContext::replay(&ndjson)
+            .unwrap()
+            .with_output_tap(tap.clone());
+        render_session(&replay, &mut tap.writer(std::io::sink()), "changed\n")
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn output_checksum_is_fnv1a() {
+        // Reference FNV-1a 64 values.
+        assert_eq!(OutputChecksum::default().fnv, 0xcbf2_9ce4_8422_2325);
+        let a = OutputChecksum::default().update(b"a");
+        assert_eq!(a.fnv, 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(a.bytes, 1);
+        let split = OutputChecksum::default().update(b"fo").update(b"obar");
+        assert_eq!(split, OutputChecksum::default().update(b"foobar"));
+        assert_eq!(split.fnv, 0x8594_4171_f739_67e8);
+    }
+
+    #[tokio::test]
+    async fn silence_timer_fires_without_claude_output() {
+        let (_event_tx, event_rx) = mpsc::unbounded_channel();
+        let (_term_tx, term_rx) = mpsc::unbounded_channel();
+        let mut io = Io::new(event_rx, term_rx);
+        io.arm_silence_timer(Duration::from_millis(10));
+        assert!(matches!(io.next_event().await.unwrap(), IoEvent::Silence));
+    }
+
+    #[tokio::test]
+    async fn claude_output_disarms_silence_timer() {
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let (term_tx, term_rx) = mpsc::unbounded_channel();
+        let mut io = Io::new(event_rx, term_rx);
+        io.arm_silence_timer(Duration::from_millis(10));
+        event_tx.send(AppEvent::Stderr("alive".into())).unwrap();
+        assert!(matches!(
+            io.next_event().await.unwrap(),
+            IoEvent::Claude(AppEvent::Stderr(_))
+        ));
+
+        // The deadline passes, but the timer was cleared by the claude event.
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        term_tx.send(Event::FocusGained).unwrap();
+        assert!(matches!(
+            io.next_event().await.unwrap(),
+            IoEvent::Terminal(Event::FocusGained)
+        ));
+    }
+
+    #[tokio::test]
+    async fn terminal_input_does_not_disarm_silence_timer() {
+        let (_event_tx, event_rx) = mpsc::unbounded_channel();
+        let (term_tx, term_rx) = mpsc::unbounded_channel();
+        let mut io = Io::new(event_rx, term_rx);
+        io.arm_silence_timer(Duration::from_millis(10));
+        term_tx.send(Event::FocusGained).unwrap();
+        assert!(matches!(
+            io.next_event().await.unwrap(),
+            IoEvent::Terminal(_)
+        ));
+        assert!(matches!(io.next_event().await.unwrap(), IoEvent::Silence));
+    }
+
+    #[tokio::test]
+    async fn session_timeout_fires_despite_claude_output() {
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let (_term_tx, term_rx) = mpsc::unbounded_channel();
+        let mut io = Io::new(event_rx, term_rx);
+        io.arm_session_timeout(Duration::from_millis(10));
+        event_tx.send(AppEvent::Stderr("alive".into())).unwrap();
+        assert!(matches!(io.next_event().await.unwrap(), IoEvent::Claude(_)));
+        assert!(matches!(
+            io.next_event().await.unwrap(),
+            IoEvent::SessionTimeout
+        ));
+
+        // Fires once, and disarming stops it.
+        io.arm_session_timeout(Duration::from_millis(10));
+        io.disarm_session_timeout();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        event_tx
+            .send(AppEvent::Stderr("still here".into()))
+            .unwrap();
+        assert!(matches!(io.next_event().await.unwrap(), IoEvent::Claude(_)));
+    }
+
+    #[tokio::test]
+    async fn shutdown_signal_wakes_a_wait_and_sticks() {
+        let (_event_tx, event_rx) = mpsc::unbounded_channel();
+        let (_term_tx, term_rx) = mpsc::unbounded_channel();
+        let mut io = Io::new(event_rx, term_rx);
+        let (signal, rx) = tokio::sync::watch::channel(false);
+        io.watch_shutdown(rx);
+        assert!(!io.shutdown_requested());
+
+        let wait = tokio::spawn(async move {
+            let event = io.next_event().await.unwrap();
+            (io, event)
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        signal.send(true).unwrap();
+        let (mut io, event) = wait.await.unwrap();
+        assert!(matches!(event, IoEvent::Shutdown));
+        assert!(io.shutdown_requested());
+        assert!(matches!(io.next_event().await.unwrap(), IoEvent::Shutdown));
+    }
+
+    #[test]
+    fn terminal_size_replays_recorded_or_default() {
+        let size = TerminalSize {
+            width: 132,
+            height: 50,
+        };
+        let recorder = VcrContext::record();
+        assert_eq!(recorder.terminal_size(|| size).unwrap(), size);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("size.vcr");
+        recorder.write_recording(&path).unwrap();
+
+        let unreachable = || -> TerminalSize { unreachable!("replay never queries") };
+        let replay = VcrContext::replay(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(replay.terminal_size(unreachable).unwrap(), size);
+        assert!(replay.unreplayed_labels().is_empty());
+
+        // Older recordings have no size entry: the default, consuming nothing.
+        let older = serde_json::json!({"label": "spawn", "args": null, "result": {"Ok": null}});
+        let replay = VcrContext::replay(&older.to_string()).unwrap();
+        assert_eq!(
+            replay.terminal_size(unreachable).unwrap(),
+            TerminalSize::default()
+        );
+        assert_eq!(replay.unreplayed_labels(), ["spawn"]);
+    }
+
+    #[test]
+    fn clock_replays_recorded_or_stopped() {
+        let recorder = VcrContext::record();
+        let start = recorder.clock_ms().unwrap();
+        let end = recorder.clock_ms().unwrap();
+        assert!(end >= start);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clock.vcr");
+        recorder.write_recording(&path).unwrap();
+
+        let replay = VcrContext::replay(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(replay.clock_ms().unwrap(), start);
+        assert_eq!(replay.clock_ms().unwrap(), end);
+        assert!(replay.unreplayed_labels().is_empty());
+
+        let older = serde_json::json!({"label": "spawn", "args": null, "result": {"Ok": null}});
+        let replay = VcrContext::replay(&older.to_string()).unwrap();
+        assert_eq!(replay.clock_ms().unwrap(), 0);
+        assert_eq!(replay.unreplayed_labels(), ["spawn"]);
+    }
+}
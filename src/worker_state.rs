@@ -11,10 +11,14 @@ use std::collections::HashMap;
 use std::fmt::Write as _;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
+use crate::agents::AgentDef;
+use crate::platform::is_pid_alive;
+use crate::timing::format_elapsed;
 use crate::worktree;
 
 /// State of a single worker, serialized to JSON.
@@ -24,6 +28,21 @@ pub struct WorkerState {
     pub branch: String,
     pub agent: Option<String>,
     pub args: HashMap<String, String>,
+    /// The worker's worktree. Absent in state files written by older coven.
+    #[serde(default)]
+    pub worktree: Option<PathBuf>,
+    /// When the state was last written, in seconds since the Unix epoch.
+    /// Absent in state files written by older coven.
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+    /// When the current agent started, in seconds since the Unix epoch.
+    /// `None` while idle, and in state files written by older coven.
+    #[serde(default)]
+    pub phase_started_at: Option<u64>,
+    /// What the worker's finished phases have cost, in USD. Zero in state
+    /// files written by older coven.
+    #[serde(default)]
+    pub total_cost_usd: f64,
 }
 
 // ── Path helpers ────────────────────────────────────────────────────────
@@ -39,8 +58,11 @@ fn workers_dir(repo_path: &Path) -> Result<PathBuf> {
     Ok(coven_dir(repo_path)?.join("workers"))
 }
 
+/// `<branch>.json`, with any `/` in the branch replaced so every state file
+/// sits directly in the workers directory.
 fn state_file_path(repo_path: &Path, branch: &str) -> Result<PathBuf> {
-    Ok(workers_dir(repo_path)?.join(format!("{branch}.json")))
+    let name = branch.replace('/', "-");
+    Ok(workers_dir(repo_path)?.join(format!("{name}.json")))
 }
 
 // ── Public API ──────────────────────────────────────────────────────────
@@ -55,23 +77,34 @@ pub fn register(repo_path: &Path, branch: &str) -> Result<()> {
         branch: branch.to_string(),
         agent: None,
         args: HashMap::new(),
+        worktree: Some(repo_path.to_path_buf()),
+        updated_at: now_secs(),
+        phase_started_at: None,
+        total_cost_usd: 0.0,
     };
 
     write_state(repo_path, &state)
 }
 
-/// Update this worker's current agent and arguments.
+/// Update this worker's current agent and arguments, and its cost so far.
+/// Called as each agent starts, so that's when the phase clock starts.
 pub fn update<S: std::hash::BuildHasher>(
     repo_path: &Path,
     branch: &str,
     agent: Option<&str>,
     args: &HashMap<String, String, S>,
+    total_cost_usd: f64,
 ) -> Result<()> {
+    let now = now_secs();
     let state = WorkerState {
         pid: std::process::id(),
         branch: branch.to_string(),
         agent: agent.map(String::from),
         args: args.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+        worktree: Some(repo_path.to_path_buf()),
+        updated_at: now,
+        phase_started_at: now.filter(|_| agent.is_some()),
+        total_cost_usd,
     };
     write_state(repo_path, &state)
 }
@@ -117,55 +150,84 @@ pub fn read_all(repo_path: &Path) -> Result<Vec<WorkerState>> {
 /// Style variants for worker status formatting.
 #[derive(Clone, Copy)]
 pub enum StatusStyle {
-    /// CLI status command: indented, em-dash separator, bare agent name.
-    Cli,
+    /// CLI status command: indented, em-dash separator, and how long the
+    /// agent has been running (as of `now`, in seconds since the Unix epoch)
+    /// and what the worker has cost.
+    Cli { now: u64 },
     /// Dispatch prompt injection: list items, colon separator, "running" prefix.
     Dispatch,
 }
 
 /// Format worker states into a multi-line string.
 pub fn format_workers<S: Borrow<WorkerState>>(states: &[S], style: StatusStyle) -> String {
-    let (line_prefix, separator, agent_prefix) = match style {
-        StatusStyle::Cli => ("  ", " — ", ""),
-        StatusStyle::Dispatch => ("- ", ": ", "running "),
+    let (line_prefix, separator) = match style {
+        StatusStyle::Cli { .. } => ("  ", " — "),
+        StatusStyle::Dispatch => ("- ", ": "),
     };
 
     let mut out = String::new();
     for item in states {
         let state = item.borrow();
-        match &state.agent {
+        let mut activity = match &state.agent {
             Some(agent) => {
                 let mut args_parts: Vec<_> =
                     state.args.iter().map(|(k, v)| format!("{k}={v}")).collect();
                 args_parts.sort();
                 if args_parts.is_empty() {
-                    let _ = writeln!(
-                        out,
-                        "{line_prefix}{} (PID {}){separator}{agent_prefix}{agent}",
-                        state.branch, state.pid
-                    );
+                    format!("running {agent}")
                 } else {
-                    let args_str = args_parts.join(", ");
-                    let _ = writeln!(
-                        out,
-                        "{line_prefix}{} (PID {}){separator}{agent_prefix}{agent} ({args_str})",
-                        state.branch, state.pid
-                    );
+                    format!("running {agent} ({})", args_parts.join(", "))
                 }
             }
-            None => {
-                let _ = writeln!(
-                    out,
-                    "{line_prefix}{} (PID {}){separator}idle",
-                    state.branch, state.pid
-                );
-            }
+            None => "idle".to_string(),
+        };
+        if let StatusStyle::Cli { now } = style {
+            activity.push_str(&progress(state, now));
         }
+        let _ = writeln!(
+            out,
+            "{line_prefix}{} (PID {}){separator}{activity}",
+            state.branch, state.pid
+        );
     }
 
     out
 }
 
+/// ` for 12m, $3.41 total`: how long the agent has been running and what
+/// the worker has cost, for whichever of the two is known.
+fn progress(state: &WorkerState, now: u64) -> String {
+    let mut out = String::new();
+    if let Some(started) = state.phase_started_at.filter(|_| state.agent.is_some()) {
+        let elapsed = Duration::from_secs(now.saturating_sub(started));
+        let _ = write!(out, " for {}", format_elapsed(elapsed));
+    }
+    if state.total_cost_usd > 0.0 {
+        let _ = write!(out, ", ${:.2} total", state.total_cost_usd);
+    }
+    out
+}
+
+/// Agents already running on as many other workers (any branch but
+/// `branch`) as their `max_concurrency` allows, with that limit.
+pub fn saturated_agents<'a>(
+    agents: &'a [AgentDef],
+    states: &[WorkerState],
+    branch: &str,
+) -> Vec<(&'a str, u32)> {
+    agents
+        .iter()
+        .filter_map(|agent| {
+            let max = agent.frontmatter.max_concurrency?;
+            let running = states
+                .iter()
+                .filter(|s| s.branch != branch && s.agent.as_deref() == Some(agent.name.as_str()))
+                .count();
+            (running >= max as usize).then_some((agent.name.as_str(), max))
+        })
+        .collect()
+}
+
 // ── Sleep signal ─────────────────────────────────────────────────────────
 
 fn sleep_signal_path(repo_path: &Path) -> Result<PathBuf> {
@@ -194,6 +256,13 @@ pub fn read_sleep_signal(repo_path: &Path) -> Result<Option<String>> {
 
 // ── Private helpers ─────────────────────────────────────────────────────
 
+pub(crate) fn now_secs() -> Option<u64> {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
 fn write_state(repo_path: &Path, state: &WorkerState) -> Result<()> {
     let path = state_file_path(repo_path, &state.branch)?;
     let json = serde_json::to_string(state).context("failed to serialize worker state")?;
@@ -217,12 +286,6 @@ fn write_state(repo_path: &Path, state: &WorkerState) -> Result<()> {
     Ok(())
 }
 
-/// Check if a process with the given PID is alive.
-fn is_pid_alive(pid: u32) -> bool {
-    // SAFETY: kill with signal 0 performs error checking without sending a signal.
-    unsafe { libc::kill(pid.cast_signed(), 0) == 0 }
-}
-
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::panic)]
 mod tests {
@@ -265,6 +328,37 @@ mod tests {
         assert!(state.agent.is_none());
     }
 
+    #[test]
+    fn branch_with_slash_gets_a_flat_state_file() {
+        let repo = TempDir::new().unwrap();
+        init_repo(repo.path());
+
+        register(repo.path(), "laptop/swift-fox-42").unwrap();
+
+        let path = state_file_path(repo.path(), "laptop/swift-fox-42").unwrap();
+        assert_eq!(path.file_name().unwrap(), "laptop-swift-fox-42.json");
+        let states = read_all(repo.path()).unwrap();
+        assert_eq!(states[0].branch, "laptop/swift-fox-42");
+
+        deregister(repo.path(), "laptop/swift-fox-42");
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn status_in_repo_with_spaces_and_unicode() {
+        let root = TempDir::new().unwrap();
+        let repo = root.path().join("Team Drive").join("projets été");
+        fs::create_dir_all(&repo).unwrap();
+        init_repo(&repo);
+
+        register(&repo, "swift-fox-42").unwrap();
+
+        let states = read_all(&repo).unwrap();
+        assert_eq!(states.len(), 1);
+        assert_eq!(states[0].branch, "swift-fox-42");
+        assert_eq!(states[0].worktree.as_deref(), Some(repo.as_path()));
+    }
+
     #[test]
     fn update_changes_state() {
         let repo = TempDir::new().unwrap();
@@ -273,7 +367,7 @@ mod tests {
         register(repo.path(), "swift-fox-42").unwrap();
 
         let args = HashMap::from([("issue".to_string(), "issues/foo.md".to_string())]);
-        update(repo.path(), "swift-fox-42", Some("plan"), &args).unwrap();
+        update(repo.path(), "swift-fox-42", Some("plan"), &args, 1.25).unwrap();
 
         let path = state_file_path(repo.path(), "swift-fox-42").unwrap();
         let content = fs::read_to_string(&path).unwrap();
@@ -284,6 +378,13 @@ mod tests {
             state.args.get("issue").map(String::as_str),
             Some("issues/foo.md")
         );
+        assert!(state.phase_started_at.is_some());
+        assert!((state.total_cost_usd - 1.25).abs() < f64::EPSILON);
+
+        update(repo.path(), "swift-fox-42", None, &HashMap::new(), 2.5).unwrap();
+        let state = read_all(repo.path()).unwrap().pop().unwrap();
+        assert_eq!(state.phase_started_at, None);
+        assert!((state.total_cost_usd - 2.5).abs() < f64::EPSILON);
     }
 
     #[test]
@@ -309,6 +410,18 @@ mod tests {
         assert_eq!(states.len(), 1);
         assert_eq!(states[0].pid, std::process::id());
         assert_eq!(states[0].branch, "test-branch");
+        assert_eq!(states[0].worktree.as_deref(), Some(repo.path()));
+        assert!(states[0].updated_at.is_some());
+    }
+
+    #[test]
+    fn reads_state_files_without_worktree_or_timestamp() {
+        let state: WorkerState =
+            serde_json::from_str(r#"{"pid":1,"branch":"b","agent":null,"args":{}}"#).unwrap();
+        assert_eq!(state.worktree, None);
+        assert_eq!(state.updated_at, None);
+        assert_eq!(state.phase_started_at, None);
+        assert!(state.total_cost_usd.abs() < f64::EPSILON);
     }
 
     #[test]
@@ -324,6 +437,10 @@ mod tests {
             branch: "stale-branch".into(),
             agent: Some("plan".into()),
             args: HashMap::new(),
+            worktree: None,
+            updated_at: None,
+            phase_started_at: None,
+            total_cost_usd: 0.0,
         };
         let stale_path = dir.join("stale-branch.json");
         fs::write(
@@ -345,16 +462,101 @@ mod tests {
                 branch: "swift-fox-42".into(),
                 agent: Some("implement".into()),
                 args: HashMap::from([("issue".into(), "issues/foo.md".into())]),
+                worktree: None,
+                updated_at: None,
+                phase_started_at: None,
+                total_cost_usd: 0.0,
             },
             WorkerState {
                 pid: 12346,
                 branch: "bold-oak-7".into(),
                 agent: None,
                 args: HashMap::new(),
+                worktree: None,
+                updated_at: None,
+                phase_started_at: None,
+                total_cost_usd: 0.0,
+            },
+        ];
+        let formatted = format_workers(&states, StatusStyle::Cli { now: 0 });
+        assert_eq!(
+            formatted,
+            "  swift-fox-42 (PID 12345) — running implement (issue=issues/foo.md)\n\
+             \x20 bold-oak-7 (PID 12346) — idle\n"
+        );
+    }
+
+    #[test]
+    fn format_workers_cli_shows_phase_time_and_cost() {
+        let running = WorkerState {
+            pid: 12345,
+            branch: "swift-fox-42".into(),
+            agent: Some("implement".into()),
+            args: HashMap::new(),
+            worktree: None,
+            updated_at: Some(1_000),
+            phase_started_at: Some(1_000),
+            total_cost_usd: 3.413,
+        };
+        let idle = WorkerState {
+            pid: 12346,
+            branch: "bold-oak-7".into(),
+            agent: None,
+            args: HashMap::new(),
+            worktree: None,
+            updated_at: Some(1_000),
+            phase_started_at: None,
+            total_cost_usd: 0.5,
+        };
+        let cli = format_workers(&[&running, &idle], StatusStyle::Cli { now: 1_720 });
+        assert_eq!(
+            cli,
+            "  swift-fox-42 (PID 12345) — running implement for 12m, $3.41 total\n\
+             \x20 bold-oak-7 (PID 12346) — idle, $0.50 total\n"
+        );
+        // Dispatch doesn't need either.
+        let dispatch = format_workers(&[&running], StatusStyle::Dispatch);
+        assert_eq!(dispatch, "- swift-fox-42 (PID 12345): running implement\n");
+    }
+
+    #[test]
+    fn saturated_agents_counts_other_workers() {
+        let agent = |name: &str, max_concurrency: Option<u32>| AgentDef {
+            name: name.into(),
+            frontmatter: crate::agents::AgentFrontmatter {
+                description: String::new(),
+                args: Vec::new(),
+                max_concurrency,
+                claude_args: Vec::new(),
+                title: None,
             },
+            prompt_template: String::new(),
+        };
+        let worker = |branch: &str, agent: Option<&str>| WorkerState {
+            pid: 1,
+            branch: branch.into(),
+            agent: agent.map(String::from),
+            args: HashMap::new(),
+            worktree: None,
+            updated_at: None,
+            phase_started_at: None,
+            total_cost_usd: 0.0,
+        };
+        let agents = [
+            agent("dispatch", Some(1)),
+            agent("implement", Some(2)),
+            agent("audit", Some(1)),
+            agent("plan", None),
+        ];
+        let states = [
+            worker("me", Some("dispatch")),
+            worker("a", Some("implement")),
+            worker("b", Some("implement")),
+            worker("c", Some("plan")),
+            worker("d", None),
         ];
-        let formatted = format_workers(&states, StatusStyle::Cli);
-        assert!(formatted.contains("  swift-fox-42 (PID 12345) — implement (issue=issues/foo.md)"));
-        assert!(formatted.contains("  bold-oak-7 (PID 12346) — idle"));
+        // Our own dispatch doesn't count; unlimited agents never saturate.
+        assert_eq!(saturated_agents(&agents, &states, "me"), [("implement", 2)]);
+        assert!(saturated_agents(&agents, &states[..2], "me").is_empty());
     }
 }
@@ -1,28 +1,49 @@
-use std::io::Write as _;
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::Mutex;
 
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::covenignore::IgnoreRules;
+
 #[derive(Debug, Serialize, Deserialize, thiserror::Error)]
 pub enum WorktreeError {
     #[error("not a git repository")]
     NotGitRepo,
     #[error("branch '{0}' already exists")]
     BranchExists(String),
+    #[error("worktree directory '{}' already exists", .0.display())]
+    WorktreePathExists(PathBuf),
     #[error("git command failed: {0}")]
     GitCommand(String),
+    #[error("path '{}' isn't valid UTF-8 (in '{component}'); rename it or pick another location", .path.display())]
+    NonUtf8Path { path: PathBuf, component: String },
+    #[error("branch prefix '{prefix}' {reason}")]
+    InvalidBranchPrefix { prefix: String, reason: String },
+    #[error("no local branch '{0}' to branch from")]
+    NoSuchBranchFrom(String),
 }
 
 /// Configuration for spawn operations.
 pub struct SpawnOptions<'a> {
     /// Path to the git repo (or any worktree of it).
     pub repo_path: &'a Path,
-    /// Optional branch name. If None, a random adjective-noun-N name is generated.
+    /// Optional branch name. If None, a random adjective-noun-N name is generated
+    /// (retrying on collisions).
     pub branch: Option<&'a str>,
+    /// Prepended to generated names as `<prefix>/<name>`; ignored when
+    /// `branch` is given.
+    pub branch_prefix: Option<&'a str>,
     /// Base directory for worktrees. Worktree will be created at `<base>/<project>/<branch>/`.
     pub base_path: &'a Path,
+    /// Local branch to start from and land on instead of main's branch
+    /// (`coven worker --branch-from`).
+    pub branch_from: Option<&'a str>,
 }
 
 /// Result of a successful spawn operation.
@@ -32,6 +53,15 @@ pub struct SpawnResult {
     pub branch: String,
 }
 
+impl SpawnResult {
+    /// The base the worktree was spawned under: [`spawn`] puts it at
+    /// `<base>/<project>/<branch>`, and the branch may contain slashes.
+    pub fn worktree_base(&self) -> Option<&Path> {
+        let depth = self.branch.split('/').count() + 1;
+        self.worktree_path.ancestors().nth(depth)
+    }
+}
+
 // ── Word lists for random branch names ──────────────────────────────────
 
 const ADJECTIVES: &[&str] = &[
@@ -48,24 +78,44 @@ const NOUNS: &[&str] = &[
 
 // ── Internal helpers ────────────────────────────────────────────────────
 
-fn path_str(path: &Path) -> Result<&str, WorktreeError> {
-    path.to_str()
-        .ok_or_else(|| WorktreeError::GitCommand("path is not valid UTF-8".into()))
+/// `path` as a string, for the places that record paths as strings (VCR
+/// arguments, dry-run plans). Names the first component that isn't UTF-8.
+pub fn utf8_path(path: &Path) -> Result<&str, WorktreeError> {
+    path.to_str().ok_or_else(|| {
+        let component = path
+            .components()
+            .find(|c| c.as_os_str().to_str().is_none())
+            .map_or_else(|| path.as_os_str(), |c| c.as_os_str());
+        WorktreeError::NonUtf8Path {
+            path: path.to_path_buf(),
+            component: component.to_string_lossy().into_owned(),
+        }
+    })
+}
+
+/// `dir` followed by a `/`, which rsync takes to mean "the contents of".
+fn with_trailing_slash(dir: &Path) -> OsString {
+    let mut arg = dir.as_os_str().to_os_string();
+    arg.push("/");
+    arg
 }
 
-/// Run a git command in the given directory and return stdout.
-fn git(dir: &Path, args: &[&str]) -> Result<String, WorktreeError> {
+/// Run a git command in the given directory and return stdout. Arguments
+/// go straight to git, never through a shell, so paths may hold spaces.
+fn git<S: AsRef<OsStr>>(dir: &Path, args: &[S]) -> Result<String, WorktreeError> {
     let output = Command::new("git")
         .arg("-C")
         .arg(dir)
         .args(args)
         .output()
         .map_err(|e| WorktreeError::GitCommand(format!("failed to run git: {e}")))?;
+    trace(dir, args, output.status, &output.stderr);
 
     if output.status.success() {
         Ok(String::from_utf8_lossy(&output.stdout).into_owned())
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
+        let args: Vec<_> = args.iter().map(|a| a.as_ref().to_string_lossy()).collect();
         Err(WorktreeError::GitCommand(format!(
             "git {} failed: {}",
             args.join(" "),
@@ -75,16 +125,113 @@ fn git(dir: &Path, args: &[&str]) -> Result<String, WorktreeError> {
 }
 
 /// Run a git command and return whether it exited successfully (ignoring output).
-fn git_status(dir: &Path, args: &[&str]) -> Result<bool, WorktreeError> {
-    let status = Command::new("git")
+fn git_status<S: AsRef<OsStr>>(dir: &Path, args: &[S]) -> Result<bool, WorktreeError> {
+    let output = Command::new("git")
         .arg("-C")
         .arg(dir)
         .args(args)
         .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
+        .output()
         .map_err(|e| WorktreeError::GitCommand(format!("failed to run git: {e}")))?;
-    Ok(status.success())
+    trace(dir, args, output.status, &output.stderr);
+    Ok(output.status.success())
+}
+
+// ── Tracing and dry runs ────────────────────────────────────────────────
+
+/// Where `--trace-git` logs each git invocation, if anywhere.
+///
+/// A process-wide sink rather than a parameter: [`git`] and [`git_status`]
+/// back dozens of call sites, many inside VCR-recorded closures, and tracing
+/// never changes what runs. Dry runs do, so they're an explicit
+/// [`Execution`] argument instead.
+static GIT_TRACE: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+
+/// Log every git command coven runs to `sink` (`--trace-git`): the full
+/// command line, its exit status, and on failure the first line of stderr.
+pub fn trace_git_to(sink: Box<dyn Write + Send>) {
+    if let Ok(mut trace) = GIT_TRACE.lock() {
+        *trace = Some(sink);
+    }
+}
+
+fn trace<S: AsRef<OsStr>>(dir: &Path, args: &[S], status: ExitStatus, stderr: &[u8]) {
+    let Ok(mut guard) = GIT_TRACE.lock() else {
+        return;
+    };
+    let Some(sink) = guard.as_mut() else {
+        return;
+    };
+    let command = GitInvocation {
+        dir: dir.to_path_buf(),
+        args: args
+            .iter()
+            .map(|a| a.as_ref().to_string_lossy().into_owned())
+            .collect(),
+    };
+    let status = status
+        .code()
+        .map_or_else(|| "killed by signal".to_string(), |c| format!("exit {c}"));
+    let stderr = String::from_utf8_lossy(stderr);
+    let first_line = stderr.lines().next().filter(|_| status != "exit 0");
+    let written = match first_line {
+        Some(line) => writeln!(sink, "[git] {command} ({status}): {line}"),
+        None => writeln!(sink, "[git] {command} ({status})"),
+    };
+    if written.and_then(|()| sink.flush()).is_err() {
+        // Like `--log-file`, a failing trace stops rather than breaking git.
+        *guard = None;
+    }
+}
+
+/// Whether a destructive operation runs its git commands or only reports
+/// them (`--dry-run`). Read-only queries run either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Execution {
+    Run,
+    DryRun,
+}
+
+/// A git command line: the directory it runs in and its arguments.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitInvocation {
+    pub dir: PathBuf,
+    pub args: Vec<String>,
+}
+
+impl GitInvocation {
+    fn new(dir: &Path, args: &[&str]) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            args: args.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    fn run(&self) -> Result<String, WorktreeError> {
+        let args: Vec<&str> = self.args.iter().map(String::as_str).collect();
+        git(&self.dir, &args)
+    }
+}
+
+/// Shell-style, so a traced or dry-run command can be pasted into a terminal.
+impl fmt::Display for GitInvocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "git -C {}", shell_quote(&self.dir.to_string_lossy()))?;
+        for arg in &self.args {
+            write!(f, " {}", shell_quote(arg))?;
+        }
+        Ok(())
+    }
+}
+
+/// Single-quote `arg` unless it's made only of characters the shell leaves alone.
+fn shell_quote(arg: &str) -> Cow<'_, str> {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''")))
+    }
 }
 
 /// Parse `git worktree list --porcelain` to find the main worktree path and branch.
@@ -118,14 +265,114 @@ fn find_main_worktree(repo: &Path) -> Result<(PathBuf, String), WorktreeError> {
     }
 }
 
+/// Per-branch git config key (`branch.<name>.coven-base`) naming the
+/// branch a worktree was started from with `--branch-from`. `land.sh`
+/// reads it too.
+const BASE_CONFIG_KEY: &str = "coven-base";
+
+/// The main worktree's path and the branch `repo`'s work lands on: the
+/// branch its worktree was started from, if `--branch-from` was used, or
+/// else the main worktree's branch.
+fn find_target(repo: &Path) -> Result<(PathBuf, String), WorktreeError> {
+    let (main_path, main_branch) = find_main_worktree(repo)?;
+    let base = git(repo, &["symbolic-ref", "--quiet", "--short", "HEAD"])
+        .ok()
+        .and_then(|head| {
+            let key = format!("branch.{}.{BASE_CONFIG_KEY}", head.trim());
+            git(repo, &["config", "--default", "", "--get", &key]).ok()
+        })
+        .map(|base| base.trim().to_string())
+        .filter(|base| !base.is_empty());
+    Ok((main_path, base.unwrap_or(main_branch)))
+}
+
+/// Point `branch` at `commit`. Where the main worktree has it checked out
+/// that updates its files too, and `reset --keep` refuses rather than
+/// clobber edits there.
+fn move_branch(main_path: &Path, branch: &str, commit: &str) -> Result<(), WorktreeError> {
+    let (_, checked_out) = find_main_worktree(main_path)?;
+    if branch == checked_out {
+        git(main_path, &["reset", "--keep", commit])?;
+    } else {
+        git(
+            main_path,
+            &["update-ref", &format!("refs/heads/{branch}"), commit],
+        )?;
+    }
+    Ok(())
+}
+
+/// How many generated branch names to try before giving up on a spawn.
+const MAX_NAME_ATTEMPTS: usize = 8;
+
 fn generate_branch_name() -> String {
     let mut rng = rand::rng();
     let adj = ADJECTIVES.choose(&mut rng).copied().unwrap_or("swift");
     let noun = NOUNS.choose(&mut rng).copied().unwrap_or("fox");
-    let num: u32 = rng.random_range(0..100);
+    let num: u32 = rng.random_range(0..1000);
     format!("{adj}-{noun}-{num}")
 }
 
+/// Check that `<prefix>/<generated name>` is a legal branch name, following
+/// `git check-ref-format`. Returns the prefix without trailing slashes.
+pub fn validate_branch_prefix(prefix: &str) -> Result<&str, WorktreeError> {
+    let trimmed = prefix.trim_end_matches('/');
+    let invalid = |reason: &str| {
+        Err(WorktreeError::InvalidBranchPrefix {
+            prefix: prefix.to_string(),
+            reason: reason.to_string(),
+        })
+    };
+    if trimmed.is_empty() {
+        return invalid("is empty");
+    }
+    if trimmed.chars().any(char::is_whitespace) {
+        return invalid("contains whitespace");
+    }
+    if trimmed.contains("..") {
+        return invalid("contains '..'");
+    }
+    if trimmed.contains("@{") {
+        return invalid("contains '@{'");
+    }
+    if trimmed
+        .chars()
+        .any(|c| c.is_control() || "~^:?*[\\".contains(c))
+    {
+        return invalid("contains one of ~ ^ : ? * [ \\ or a control character");
+    }
+    if trimmed.starts_with('-') {
+        return invalid("starts with '-'");
+    }
+    for component in trimmed.split('/') {
+        if component.is_empty() {
+            return invalid("has an empty path component");
+        }
+        if component.starts_with('.') {
+            return invalid("has a component starting with '.'");
+        }
+        if Path::new(component)
+            .extension()
+            .is_some_and(|e| e == "lock")
+        {
+            return invalid("has a component ending in '.lock'");
+        }
+    }
+    Ok(trimmed)
+}
+
+fn branch_exists(repo_path: &Path, branch: &str) -> Result<bool, WorktreeError> {
+    git_status(
+        repo_path,
+        &[
+            "show-ref",
+            "--verify",
+            "--quiet",
+            &format!("refs/heads/{branch}"),
+        ],
+    )
+}
+
 /// Resolve the git common directory for a repository or worktree.
 ///
 /// Runs `git rev-parse --git-common-dir` and normalizes the result to an
@@ -142,9 +389,10 @@ pub fn git_common_dir(repo_path: &Path) -> Result<PathBuf, WorktreeError> {
 
 // ── Public API ──────────────────────────────────────────────────────────
 
-/// Get the main branch name by parsing `git worktree list --porcelain`.
+/// Get the name of the branch `repo`'s work lands on: the main worktree's
+/// branch, or the one given to `--branch-from`.
 pub fn main_branch_name(repo: &Path) -> Result<String, WorktreeError> {
-    let (_, branch) = find_main_worktree(repo)?;
+    let (_, branch) = find_target(repo)?;
     Ok(branch)
 }
 
@@ -199,47 +447,90 @@ pub fn list_worktrees(repo_path: &Path) -> Result<Vec<WorktreeEntry>, WorktreeEr
 /// Spawn a new worktree with a random branch name (or caller-provided name).
 ///
 /// - Validates we're in a git repo
-/// - Generates a random adjective-noun-N branch name if none provided
+/// - Generates a random adjective-noun-N branch name if none provided,
+///   regenerating when the branch or its worktree directory already exists,
+///   under `branch_prefix/` if one is set
 /// - Runs `git worktree add -b <branch> <path>`
-/// - Rsyncs gitignored files from main repo to worktree
+/// - Rsyncs gitignored files from main repo to worktree, except those
+///   matched by the main repo's `.covenignore`
 /// - Worktree location: `<base_path>/<project>/<branch>/`
 pub fn spawn(options: &SpawnOptions<'_>) -> Result<SpawnResult, WorktreeError> {
+    spawn_with_names(options, generate_branch_name)
+}
+
+/// [`spawn`] with an injectable name generator, so tests can force collisions.
+fn spawn_with_names(
+    options: &SpawnOptions<'_>,
+    mut generate: impl FnMut() -> String,
+) -> Result<SpawnResult, WorktreeError> {
     if !git_status(options.repo_path, &["rev-parse", "--git-dir"])? {
         return Err(WorktreeError::NotGitRepo);
     }
-
-    let branch = match options.branch {
-        Some(b) => b.to_string(),
-        None => generate_branch_name(),
-    };
-
-    if git_status(
-        options.repo_path,
-        &[
-            "show-ref",
-            "--verify",
-            "--quiet",
-            &format!("refs/heads/{branch}"),
-        ],
-    )? {
-        return Err(WorktreeError::BranchExists(branch));
+    let prefix = options
+        .branch_prefix
+        .filter(|_| options.branch.is_none())
+        .map(validate_branch_prefix)
+        .transpose()?;
+
+    let (main_path, main_branch) = find_main_worktree(options.repo_path)?;
+    if let Some(from) = options.branch_from
+        && !branch_exists(options.repo_path, from)?
+    {
+        return Err(WorktreeError::NoSuchBranchFrom(from.to_string()));
     }
-
-    let (main_path, _) = find_main_worktree(options.repo_path)?;
     let project = main_path
         .file_name()
-        .and_then(|n| n.to_str())
         .ok_or_else(|| WorktreeError::GitCommand("could not determine project name".into()))?;
+    let project_dir = options.base_path.join(project);
+
+    // A user-provided name is used as-is; a generated one is retried on collision.
+    let mut attempt = 0;
+    let (branch, worktree_path) = loop {
+        attempt += 1;
+        let branch = match options.branch {
+            Some(b) => b.to_string(),
+            None => match prefix {
+                Some(prefix) => format!("{prefix}/{}", generate()),
+                None => generate(),
+            },
+        };
+        let worktree_path = project_dir.join(&branch);
+        let collision = if branch_exists(options.repo_path, &branch)? {
+            WorktreeError::BranchExists(branch)
+        } else if worktree_path.exists() {
+            // Likely left behind by a crashed run.
+            WorktreeError::WorktreePathExists(worktree_path)
+        } else {
+            break (branch, worktree_path);
+        };
+        if options.branch.is_some() || attempt == MAX_NAME_ATTEMPTS {
+            return Err(collision);
+        }
+    };
 
-    let worktree_path = options.base_path.join(project).join(&branch);
-
-    std::fs::create_dir_all(options.base_path.join(project))
+    // A branch with slashes nests its worktree, e.g. `<project>/laptop/swift-fox-42`.
+    let parent = worktree_path.parent().unwrap_or(&project_dir);
+    std::fs::create_dir_all(parent)
         .map_err(|e| WorktreeError::GitCommand(format!("failed to create directory: {e}")))?;
 
-    let wt_str = path_str(&worktree_path)?;
-    git(&main_path, &["worktree", "add", "-b", &branch, wt_str])?;
+    let mut add: Vec<&OsStr> = vec![
+        "worktree".as_ref(),
+        "add".as_ref(),
+        "-b".as_ref(),
+        branch.as_ref(),
+        worktree_path.as_ref(),
+    ];
+    let base = options.branch_from.filter(|from| *from != main_branch);
+    if let Some(from) = base {
+        add.push(from.as_ref());
+    }
+    git(&main_path, &add)?;
+    if let Some(from) = base {
+        let key = format!("branch.{branch}.{BASE_CONFIG_KEY}");
+        git(&main_path, &["config", &key, from])?;
+    }
 
-    rsync_ignored(&main_path, &worktree_path)?;
+    copy_ignored(&main_path, &worktree_path)?;
 
     Ok(SpawnResult {
         worktree_path,
@@ -260,26 +551,38 @@ pub fn spawn(options: &SpawnOptions<'_>) -> Result<SpawnResult, WorktreeError> {
 /// Once the worktree is removed, there's no reason to keep the branch.
 ///
 /// Intended for worker shutdown, not after every land.
-pub fn remove(worktree_path: &Path, force: bool) -> Result<(), WorktreeError> {
+///
+/// Returns the destructive commands, which with [`Execution::DryRun`] are
+/// only worked out (the branch and main worktree are still looked up).
+pub fn remove(
+    worktree_path: &Path,
+    force: bool,
+    execution: Execution,
+) -> Result<Vec<GitInvocation>, WorktreeError> {
     let branch = git(worktree_path, &["rev-parse", "--abbrev-ref", "HEAD"])?;
     let branch = branch.trim().to_string();
 
     let (main_path, _) = find_main_worktree(worktree_path)?;
 
-    let wt_str = path_str(worktree_path)?;
-    if force {
-        git(&main_path, &["worktree", "remove", "--force", wt_str])?;
+    let wt_str = utf8_path(worktree_path)?;
+    let remove_worktree = if force {
+        GitInvocation::new(&main_path, &["worktree", "remove", "--force", wt_str])
     } else {
-        git(&main_path, &["worktree", "remove", wt_str])?;
-    }
+        GitInvocation::new(&main_path, &["worktree", "remove", wt_str])
+    };
+    let delete_branch = GitInvocation::new(&main_path, &["branch", "-D", &branch]);
 
-    // Delete the branch (ignore errors — branch may already be gone)
-    let _ = git(&main_path, &["branch", "-D", &branch]);
+    if execution == Execution::Run {
+        remove_worktree.run()?;
+        // Delete the branch (ignore errors — branch may already be gone)
+        let _ = delete_branch.run();
+    }
 
-    Ok(())
+    Ok(vec![remove_worktree, delete_branch])
 }
 
-/// Update the worktree branch to include the latest commits from main.
+/// Update the worktree branch to include the latest commits from main (or
+/// its `--branch-from` branch, as everywhere below).
 ///
 /// If the worktree has no unique commits (normal state after landing),
 /// this is a fast-forward. If the worktree has unique commits, they
@@ -287,7 +590,7 @@ pub fn remove(worktree_path: &Path, force: bool) -> Result<(), WorktreeError> {
 ///
 /// Call this before dispatch so the agent sees the latest issue files.
 pub fn sync_to_main(worktree_path: &Path) -> Result<(), WorktreeError> {
-    let (_, main_branch) = find_main_worktree(worktree_path)?;
+    let (_, main_branch) = find_target(worktree_path)?;
     git(worktree_path, &["rebase", &main_branch])?;
     Ok(())
 }
@@ -297,7 +600,7 @@ pub fn sync_to_main(worktree_path: &Path) -> Result<(), WorktreeError> {
 /// Used after a failed land to put the worktree back in a clean state
 /// so the next dispatch can start fresh.
 pub fn reset_to_main(worktree_path: &Path) -> Result<(), WorktreeError> {
-    let (_, main_branch) = find_main_worktree(worktree_path)?;
+    let (_, main_branch) = find_target(worktree_path)?;
     git(worktree_path, &["reset", "--hard", &main_branch])?;
     Ok(())
 }
@@ -308,28 +611,212 @@ pub fn abort_rebase(worktree_path: &Path) -> Result<(), WorktreeError> {
     Ok(())
 }
 
-/// Remove untracked, non-ignored files and directories from the worktree.
+/// Clear untracked, non-ignored files and directories out of the worktree
+/// by moving them into `trash_dir`, keeping their relative paths.
 ///
-/// Runs `git clean -fd`. Gitignored files (build artifacts, etc.) are preserved.
-/// Used during land failure recovery to prevent stray files from blocking
-/// future land attempts.
-pub fn clean(worktree_path: &Path) -> Result<(), WorktreeError> {
-    git(worktree_path, &["clean", "-fd"])?;
-    Ok(())
+/// Moves what `git clean -fd` would remove, so nothing an agent left behind
+/// is deleted outright. Gitignored files (build artifacts, etc.) are
+/// preserved. Returns the moved paths, relative to the worktree;
+/// `trash_dir` is only created when there is something to move. See
+/// [`trash_dir`] for where trash goes.
+pub fn clean(worktree_path: &Path, trash_dir: &Path) -> Result<Vec<String>, WorktreeError> {
+    let untracked = untracked_paths(worktree_path)?;
+    for path in &untracked {
+        let relative = path.trim_end_matches('/');
+        let destination = trash_dir.join(relative);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                WorktreeError::GitCommand(format!("failed to create {}: {e}", parent.display()))
+            })?;
+        }
+        std::fs::rename(worktree_path.join(relative), &destination).map_err(|e| {
+            WorktreeError::GitCommand(format!("failed to move {relative} to trash: {e}"))
+        })?;
+    }
+    Ok(untracked)
+}
+
+/// What `git clean -fd` would remove, relative to the worktree.
+/// Directories end in `/`. Listed NUL-separated, so no path is quoted.
+pub fn untracked_paths(worktree_path: &Path) -> Result<Vec<String>, WorktreeError> {
+    let output = git(
+        worktree_path,
+        &[
+            "ls-files",
+            "--others",
+            "--exclude-standard",
+            "--directory",
+            "-z",
+        ],
+    )?;
+    Ok(output
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Where [`clean`] puts a branch's files for one cleanup:
+/// `<worktree_base>/trash/<branch>/<timestamp>/`.
+pub fn trash_dir(worktree_base: &Path, branch: &str, timestamp: u64) -> PathBuf {
+    worktree_base
+        .join("trash")
+        .join(branch)
+        .join(timestamp.to_string())
 }
 
 /// Check whether the worktree branch has any commits ahead of main.
 pub fn has_unique_commits(worktree_path: &Path) -> Result<bool, WorktreeError> {
-    let (_, main_branch) = find_main_worktree(worktree_path)?;
+    Ok(unique_commit_count(worktree_path)? > 0)
+}
+
+/// Count the commits on the worktree branch that aren't on main.
+pub fn unique_commit_count(worktree_path: &Path) -> Result<u64, WorktreeError> {
+    let (_, main_branch) = find_target(worktree_path)?;
     let output = git(
         worktree_path,
         &["rev-list", "--count", &format!("{main_branch}..HEAD")],
     )?;
-    let count: u64 = output
+    output
+        .trim()
+        .parse()
+        .map_err(|e| WorktreeError::GitCommand(format!("failed to parse rev-list count: {e}")))
+}
+
+/// Count the modified and untracked files in the worktree. Doesn't take the
+/// index lock, so it's safe to run while an agent is using the worktree.
+pub fn changed_file_count(worktree_path: &Path) -> Result<usize, WorktreeError> {
+    let output = git(
+        worktree_path,
+        &["--no-optional-locks", "status", "--porcelain"],
+    )?;
+    Ok(output.lines().filter(|line| !line.is_empty()).count())
+}
+
+/// Whether the working tree has modified or untracked, non-ignored files.
+/// Works in any git repo, not just coven's worktrees.
+pub fn dirty_state(repo: &Path) -> Result<bool, WorktreeError> {
+    Ok(changed_file_count(repo)? > 0)
+}
+
+/// Stage everything (`git add -A`) and commit it with `message`. Returns
+/// false without committing when the tree is clean.
+pub fn commit_all(repo: &Path, message: &str) -> Result<bool, WorktreeError> {
+    if !dirty_state(repo)? {
+        return Ok(false);
+    }
+    git(repo, &["add", "-A"])?;
+    git(repo, &["commit", "--quiet", "-m", message])?;
+    Ok(true)
+}
+
+/// When HEAD was committed, in seconds since the Unix epoch.
+pub fn head_commit_time(worktree_path: &Path) -> Result<u64, WorktreeError> {
+    let output = git(worktree_path, &["log", "-1", "--format=%ct"])?;
+    output
         .trim()
         .parse()
-        .map_err(|e| WorktreeError::GitCommand(format!("failed to parse rev-list count: {e}")))?;
-    Ok(count > 0)
+        .map_err(|e| WorktreeError::GitCommand(format!("failed to parse commit time: {e}")))
+}
+
+/// The last `n` commits on main, newest first, as `git log --oneline`
+/// lines.
+pub fn recent_log(repo: &Path, n: usize) -> Result<String, WorktreeError> {
+    let (_, main_branch) = find_target(repo)?;
+    let output = git(
+        repo,
+        &["log", "--oneline", "-n", &n.to_string(), &main_branch],
+    )?;
+    Ok(output.trim_end().to_string())
+}
+
+/// The main worktree's location, branch, and current commit.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MainHead {
+    pub path: PathBuf,
+    pub branch: String,
+    pub sha: String,
+}
+
+/// Resolve the main worktree and the commit its branch (or the
+/// `--branch-from` branch) points at.
+pub fn main_head(repo: &Path) -> Result<MainHead, WorktreeError> {
+    let (path, branch) = find_target(repo)?;
+    let sha = git(repo, &["rev-parse", &branch])?.trim().to_string();
+    Ok(MainHead { path, branch, sha })
+}
+
+/// Check whether `commit` is HEAD of the worktree or one of its ancestors.
+pub fn head_contains(worktree_path: &Path, commit: &str) -> Result<bool, WorktreeError> {
+    git_status(
+        worktree_path,
+        &["merge-base", "--is-ancestor", commit, "HEAD"],
+    )
+}
+
+/// What a land brought onto main.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LandSummary {
+    /// Oldest first.
+    pub commits: Vec<LandedCommit>,
+    pub stat: DiffStat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LandedCommit {
+    /// Abbreviated SHA.
+    pub sha: String,
+    pub subject: String,
+}
+
+/// `git diff --shortstat` counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffStat {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// Summarize the commits in `from..to`: what moving main from `from` to
+/// `to` landed.
+pub fn land_summary(repo: &Path, from: &str, to: &str) -> Result<LandSummary, WorktreeError> {
+    let range = format!("{from}..{to}");
+    let log = git(repo, &["log", "--reverse", "--format=%h%x1f%s", &range])?;
+    let commits = log
+        .lines()
+        .filter_map(|line| line.split_once('\x1f'))
+        .map(|(sha, subject)| LandedCommit {
+            sha: sha.to_string(),
+            subject: subject.to_string(),
+        })
+        .collect();
+    let shortstat = git(repo, &["diff", "--shortstat", from, to])?;
+    Ok(LandSummary {
+        commits,
+        stat: parse_shortstat(&shortstat),
+    })
+}
+
+/// Parse `" 3 files changed, 10 insertions(+), 2 deletions(-)"`; git leaves
+/// out the parts that are zero.
+fn parse_shortstat(line: &str) -> DiffStat {
+    let mut stat = DiffStat::default();
+    for part in line.trim().split(", ") {
+        let Some((count, what)) = part.split_once(' ') else {
+            continue;
+        };
+        let Ok(count) = count.parse() else {
+            continue;
+        };
+        if what.starts_with("file") {
+            stat.files_changed = count;
+        } else if what.starts_with("insertion") {
+            stat.insertions = count;
+        } else if what.starts_with("deletion") {
+            stat.deletions = count;
+        }
+    }
+    stat
 }
 
 /// Check if a rebase is currently in progress in the worktree.
@@ -339,12 +826,116 @@ pub fn is_rebase_in_progress(worktree_path: &Path) -> Result<bool, WorktreeError
     Ok(git_dir.join("rebase-merge").exists() || git_dir.join("rebase-apply").exists())
 }
 
+// ── Remotes (`--push-after-land`) ───────────────────────────────────────
+
+/// How pushing main to a remote went.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PushOutcome {
+    Pushed,
+    /// The remote has commits main doesn't: the push wasn't a fast-forward.
+    Rejected,
+    /// Anything else — usually authentication or the network. Holds git's
+    /// error.
+    Failed(String),
+}
+
+/// Push the main branch to `remote` (a remote name, e.g. `origin`).
+pub fn push_main(repo: &Path, remote: &str) -> Result<PushOutcome, WorktreeError> {
+    let (_, main_branch) = find_target(repo)?;
+    let args = ["push", "--porcelain", remote, &main_branch];
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|e| WorktreeError::GitCommand(format!("failed to run git: {e}")))?;
+    trace(repo, &args, output.status, &output.stderr);
+    if output.status.success() {
+        return Ok(PushOutcome::Pushed);
+    }
+    // `--porcelain` marks each refused ref with a `!` line on stdout.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let rejected = stdout.lines().any(|line| {
+        line.starts_with('!') && (line.contains("non-fast-forward") || line.contains("fetch first"))
+    });
+    if rejected {
+        return Ok(PushOutcome::Rejected);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Ok(PushOutcome::Failed(stderr.trim().to_string()))
+}
+
+/// Fetch `remote`'s main branch into `refs/remotes/<remote>/<main>` and
+/// return that ref's name.
+pub fn fetch_remote_main(repo: &Path, remote: &str) -> Result<String, WorktreeError> {
+    let (_, main_branch) = find_target(repo)?;
+    let tracking = format!("refs/remotes/{remote}/{main_branch}");
+    git(
+        repo,
+        &[
+            "fetch",
+            remote,
+            &format!("+refs/heads/{main_branch}:{tracking}"),
+        ],
+    )?;
+    Ok(tracking)
+}
+
+/// Like [`sync_to_main`], but onto `remote`'s main as last fetched by
+/// [`fetch_remote_main`] when that's ahead of main, so dispatch sees work
+/// other machines pushed. Main itself only moves when the worktree next
+/// lands: the main worktree, which concurrent workers share, is left alone.
+/// When main has commits the remote doesn't, this syncs to main; the next
+/// push reconciles them.
+pub fn sync_to_remote_main(worktree_path: &Path, remote: &str) -> Result<(), WorktreeError> {
+    let (_, main_branch) = find_target(worktree_path)?;
+    let tracking = format!("refs/remotes/{remote}/{main_branch}");
+    let behind = git_status(
+        worktree_path,
+        &["merge-base", "--is-ancestor", &main_branch, &tracking],
+    )?;
+    let onto = if behind { &tracking } else { &main_branch };
+    git(worktree_path, &["rebase", onto])?;
+    Ok(())
+}
+
+/// After a rejected push: fetch `remote` and land again on top of it —
+/// rebase the worktree onto the remote's main and move main to the result.
+/// Main's unpushed commits are the ones rebased, so nothing is lost;
+/// `reset --keep` refuses rather than clobber edits in the main worktree.
+///
+/// Only done when the worktree sits exactly at main, as it does right
+/// after a land. Returns `false`, changing nothing, when it doesn't or the
+/// rebase conflicts.
+pub fn reland_onto_remote(worktree_path: &Path, remote: &str) -> Result<bool, WorktreeError> {
+    let tracking = fetch_remote_main(worktree_path, remote)?;
+    let main = main_head(worktree_path)?;
+    let head = git(worktree_path, &["rev-parse", "HEAD"])?;
+    if head.trim() != main.sha {
+        return Ok(false);
+    }
+    if !git_status(worktree_path, &["rebase", &tracking])? {
+        abort_rebase(worktree_path)?;
+        return Ok(false);
+    }
+    let rebased = git(worktree_path, &["rev-parse", "HEAD"])?;
+    move_branch(&main.path, &main.branch, rebased.trim())?;
+    Ok(true)
+}
+
 // ── Private helpers ─────────────────────────────────────────────────────
 
-fn rsync_ignored(main_path: &Path, worktree_path: &Path) -> Result<(), WorktreeError> {
-    let ignored = git(
+/// Copy gitignored files (build caches, `.env`, ...) from the main worktree,
+/// minus those excluded by its `.covenignore`.
+///
+/// Uses rsync when it's installed and falls back to a native copy walk
+/// otherwise (e.g. on Windows or minimal containers).
+fn copy_ignored(main_path: &Path, worktree_path: &Path) -> Result<(), WorktreeError> {
+    let mut ignored = git(
         main_path,
         &[
+            "-c",
+            "core.quotePath=false",
             "ls-files",
             "--others",
             "--ignored",
@@ -352,22 +943,42 @@ fn rsync_ignored(main_path: &Path, worktree_path: &Path) -> Result<(), WorktreeE
             "--directory",
         ],
     )?;
+    let rules = IgnoreRules::load(main_path).map_err(|e| {
+        WorktreeError::GitCommand(format!(
+            "failed to read {}: {e}",
+            crate::covenignore::FILE_NAME
+        ))
+    })?;
+    if !rules.is_empty() {
+        (ignored, _) = filter_listing(main_path, &ignored, &rules);
+    }
 
     if ignored.trim().is_empty() {
         return Ok(());
     }
 
-    let mut child = Command::new("rsync")
+    let mut child = match Command::new("rsync")
         .arg("-a")
         .arg("-r")
         .arg("--files-from=-")
-        .arg(format!("{}/", main_path.display()))
-        .arg(format!("{}/", worktree_path.display()))
+        .arg(with_trailing_slash(main_path))
+        .arg(with_trailing_slash(worktree_path))
         .stdin(Stdio::piped())
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn()
-        .map_err(|e| WorktreeError::GitCommand(format!("failed to run rsync: {e}")))?;
+    {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            copy_listed(main_path, worktree_path, &ignored);
+            return Ok(());
+        }
+        Err(e) => {
+            return Err(WorktreeError::GitCommand(format!(
+                "failed to run rsync: {e}"
+            )));
+        }
+    };
 
     if let Some(mut stdin) = child.stdin.take() {
         // Write and drop to signal EOF; ignore broken pipe (some files may not exist)
@@ -380,6 +991,114 @@ fn rsync_ignored(main_path: &Path, worktree_path: &Path) -> Result<(), WorktreeE
     Ok(())
 }
 
+/// Drop the paths `rules` exclude from a `git ls-files --directory` listing,
+/// returning the rest and how many files and directories were dropped.
+///
+/// A listed directory with excluded entries inside is replaced by the paths
+/// it keeps; excluded subdirectories aren't walked.
+fn filter_listing(root: &Path, listing: &str, rules: &IgnoreRules) -> (String, usize) {
+    let mut kept = String::new();
+    let mut dropped = 0;
+    for entry in listing.lines().filter(|e| !e.is_empty()) {
+        let Some(dir) = entry.strip_suffix('/') else {
+            if rules.is_ignored(entry, false) {
+                dropped += 1;
+            } else {
+                kept.push_str(entry);
+                kept.push('\n');
+            }
+            continue;
+        };
+        if rules.is_ignored(dir, true) {
+            dropped += 1;
+            continue;
+        }
+        let mut inside = Vec::new();
+        let dropped_inside = walk_kept(root, dir, rules, &mut inside);
+        if dropped_inside == 0 {
+            inside = vec![entry.to_string()];
+        }
+        dropped += dropped_inside;
+        for path in inside {
+            kept.push_str(&path);
+            kept.push('\n');
+        }
+    }
+    (kept, dropped)
+}
+
+/// Collect the paths under `root/dir` that `rules` keep into `kept`,
+/// returning how many were excluded. Directories no rule reaches into
+/// aren't walked.
+fn walk_kept(root: &Path, dir: &str, rules: &IgnoreRules, kept: &mut Vec<String>) -> usize {
+    if !rules.may_exclude_within(dir) {
+        return 0;
+    }
+    let Ok(entries) = std::fs::read_dir(root.join(dir)) else {
+        return 0;
+    };
+    let mut dropped = 0;
+    for entry in entries.flatten() {
+        let Some(name) = entry.file_name().to_str().map(String::from) else {
+            continue;
+        };
+        let path = format!("{dir}/{name}");
+        // Symlinks are copied as links, never followed.
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+        if rules.is_ignored(&path, is_dir) {
+            dropped += 1;
+        } else if is_dir {
+            let mut inside = Vec::new();
+            let dropped_inside = walk_kept(root, &path, rules, &mut inside);
+            if dropped_inside == 0 {
+                inside = vec![format!("{path}/")];
+            }
+            dropped += dropped_inside;
+            kept.extend(inside);
+        } else {
+            kept.push(path);
+        }
+    }
+    dropped
+}
+
+/// Copy each path in a `git ls-files` listing from `src_root` to `dst_root`.
+///
+/// Best-effort like the rsync path: entries that vanish or can't be copied
+/// are skipped rather than failing the spawn.
+fn copy_listed(src_root: &Path, dst_root: &Path, listing: &str) {
+    for entry in listing.lines() {
+        let rel = entry.trim_end_matches('/');
+        if rel.is_empty() {
+            continue;
+        }
+        let _ = copy_recursive(&src_root.join(rel), &dst_root.join(rel));
+    }
+}
+
+fn copy_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
+    let meta = std::fs::symlink_metadata(src)?;
+    if let Some(parent) = dst.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if meta.file_type().is_symlink() {
+        // Recreate the link as-is (rsync -a semantics). Creating symlinks on
+        // Windows needs extra privileges, so they're skipped there.
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(std::fs::read_link(src)?, dst)?;
+        return Ok(());
+    }
+    if meta.is_dir() {
+        std::fs::create_dir_all(dst)?;
+        for child in std::fs::read_dir(src)? {
+            let child = child?;
+            let _ = copy_recursive(&child.path(), &dst.join(child.file_name()));
+        }
+        return Ok(());
+    }
+    std::fs::copy(src, dst).map(|_| ())
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -412,7 +1131,9 @@ mod tests {
         SpawnOptions {
             repo_path: repo,
             branch,
+            branch_prefix: None,
             base_path: base,
+            branch_from: None,
         }
     }
 
@@ -458,6 +1179,163 @@ mod tests {
         assert!(result.worktree_path.join("build/output.txt").exists());
     }
 
+    #[test]
+    fn spawn_skips_covenignored_files() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+        fs::write(repo_dir.path().join(".gitignore"), "build/\n*.env\n").unwrap();
+        fs::write(
+            repo_dir.path().join(".covenignore"),
+            "*.env\n!dev.env\nbuild/cache/\n",
+        )
+        .unwrap();
+        git(repo_dir.path(), &["add", ".gitignore", ".covenignore"]).unwrap();
+        git(repo_dir.path(), &["commit", "-m", "add ignore files"]).unwrap();
+
+        for file in ["prod.env", "dev.env", "build/out.txt", "build/cache/blob"] {
+            let path = repo_dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x").unwrap();
+        }
+
+        let result = spawn(&spawn_opts(
+            repo_dir.path(),
+            base_dir.path(),
+            Some("wt-cov"),
+        ))
+        .unwrap();
+        let wt = result.worktree_path;
+        assert!(wt.join("dev.env").exists());
+        assert!(wt.join("build/out.txt").exists());
+        assert!(!wt.join("prod.env").exists());
+        assert!(!wt.join("build/cache").exists());
+    }
+
+    #[test]
+    fn filter_listing_expands_only_dirs_with_exclusions() {
+        let root = TempDir::new().unwrap();
+        for file in [
+            "target/debug/app",
+            "target/debug/app.log",
+            "node_modules/a/index.js",
+        ] {
+            let path = root.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x").unwrap();
+        }
+        let rules = IgnoreRules::parse("*.log\n");
+        let listing = "target/\nnode_modules/\nserver.log\n.env\n";
+        let (kept, dropped) = filter_listing(root.path(), listing, &rules);
+        assert_eq!(kept, "target/debug/app\nnode_modules/\n.env\n");
+        assert_eq!(dropped, 2);
+    }
+
+    #[test]
+    fn main_head_tracks_landed_commits() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+        let wt = spawn(&spawn_opts(
+            repo_dir.path(),
+            base_dir.path(),
+            Some("lander"),
+        ))
+        .unwrap();
+        let before = main_head(&wt.worktree_path).unwrap();
+
+        fs::write(wt.worktree_path.join("new.txt"), "x").unwrap();
+        git(&wt.worktree_path, &["add", "."]).unwrap();
+        git(&wt.worktree_path, &["commit", "-m", "work"]).unwrap();
+        assert!(head_contains(&wt.worktree_path, &before.sha).unwrap());
+        assert_eq!(main_head(&wt.worktree_path).unwrap().sha, before.sha);
+
+        git(repo_dir.path(), &["merge", "--ff-only", "lander"]).unwrap();
+        let after = main_head(&wt.worktree_path).unwrap();
+        assert_ne!(after.sha, before.sha);
+        assert!(head_contains(&wt.worktree_path, &after.sha).unwrap());
+    }
+
+    #[test]
+    fn land_summary_lists_landed_commits_and_stat() {
+        let repo_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+        let repo = repo_dir.path();
+        let before = main_head(repo).unwrap().sha;
+        commit_file(repo, "src/a.rs", "one\ntwo\n", "Add a");
+        commit_file(repo, "src/b.rs", "three\n", "Add b");
+        fs::write(repo.join("README.md"), "# renamed\n").unwrap();
+        git(repo, &["commit", "-am", "Retitle readme"]).unwrap();
+        let after = main_head(repo).unwrap().sha;
+
+        let summary = land_summary(repo, &before, &after).unwrap();
+        let subjects: Vec<&str> = summary.commits.iter().map(|c| c.subject.as_str()).collect();
+        assert_eq!(subjects, ["Add a", "Add b", "Retitle readme"]);
+        assert!(after.starts_with(&summary.commits[2].sha));
+        assert_eq!(
+            summary.stat,
+            DiffStat {
+                files_changed: 3,
+                insertions: 4,
+                deletions: 1,
+            }
+        );
+        assert_eq!(
+            land_summary(repo, &after, &after).unwrap(),
+            LandSummary::default()
+        );
+    }
+
+    #[test]
+    fn shortstat_parts_are_optional() {
+        assert_eq!(
+            parse_shortstat(" 1 file changed, 2 deletions(-)\n"),
+            DiffStat {
+                files_changed: 1,
+                insertions: 0,
+                deletions: 2,
+            }
+        );
+        assert_eq!(parse_shortstat(""), DiffStat::default());
+    }
+
+    #[test]
+    fn copy_listed_copies_nested_dirs_and_files() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        fs::create_dir_all(src.path().join("target/debug")).unwrap();
+        fs::write(src.path().join("target/debug/app"), "bin").unwrap();
+        fs::write(src.path().join(".env"), "KEY=1").unwrap();
+
+        copy_listed(src.path(), dst.path(), "target/\n.env\nmissing.txt\n");
+
+        assert_eq!(
+            fs::read_to_string(dst.path().join("target/debug/app")).unwrap(),
+            "bin"
+        );
+        assert_eq!(
+            fs::read_to_string(dst.path().join(".env")).unwrap(),
+            "KEY=1"
+        );
+        assert!(!dst.path().join("missing.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_listed_preserves_symlinks() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        fs::create_dir_all(src.path().join("cache")).unwrap();
+        std::os::unix::fs::symlink("../elsewhere", src.path().join("cache/link")).unwrap();
+
+        copy_listed(src.path(), dst.path(), "cache/\n");
+
+        assert_eq!(
+            fs::read_link(dst.path().join("cache/link")).unwrap(),
+            Path::new("../elsewhere")
+        );
+    }
+
     #[test]
     fn spawn_custom_branch_name() {
         let repo_dir = TempDir::new().unwrap();
@@ -475,6 +1353,86 @@ mod tests {
         assert!(result.worktree_path.ends_with("my-feature"));
     }
 
+    #[test]
+    fn spawn_prefixes_generated_names() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+
+        let mut opts = spawn_opts(repo_dir.path(), base_dir.path(), None);
+        opts.branch_prefix = Some("laptop/");
+        let result = spawn_with_names(&opts, names(&["swift-fox-42"])).unwrap();
+
+        assert_eq!(result.branch, "laptop/swift-fox-42");
+        let project = repo_dir.path().file_name().unwrap();
+        assert_eq!(
+            result.worktree_path,
+            base_dir.path().join(project).join("laptop/swift-fox-42")
+        );
+        assert!(result.worktree_path.join("README.md").exists());
+        assert!(branch_exists(repo_dir.path(), "laptop/swift-fox-42").unwrap());
+    }
+
+    #[test]
+    fn spawn_ignores_prefix_for_explicit_branch() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+
+        let mut opts = spawn_opts(repo_dir.path(), base_dir.path(), Some("my-feature"));
+        opts.branch_prefix = Some("laptop");
+        assert_eq!(spawn(&opts).unwrap().branch, "my-feature");
+    }
+
+    #[test]
+    fn spawn_rejects_invalid_prefix() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+
+        let mut opts = spawn_opts(repo_dir.path(), base_dir.path(), None);
+        opts.branch_prefix = Some("my laptop");
+        assert!(matches!(
+            spawn(&opts),
+            Err(WorktreeError::InvalidBranchPrefix { .. })
+        ));
+    }
+
+    #[test]
+    fn validate_branch_prefix_follows_ref_rules() {
+        assert_eq!(validate_branch_prefix("laptop").unwrap(), "laptop");
+        assert_eq!(
+            validate_branch_prefix("team/laptop//").unwrap(),
+            "team/laptop"
+        );
+        for bad in [
+            "",
+            "/",
+            "my laptop",
+            "a..b",
+            "a@{b",
+            "a~b",
+            "a:b",
+            "a*b",
+            "a\\b",
+            "-x",
+            "a//b",
+            ".hidden",
+            "a/.b",
+            "x.lock",
+            "/abs",
+        ] {
+            assert!(
+                validate_branch_prefix(bad).is_err(),
+                "{bad:?} should be rejected"
+            );
+        }
+        assert_eq!(
+            validate_branch_prefix("a..b").unwrap_err().to_string(),
+            "branch prefix 'a..b' contains '..'"
+        );
+    }
+
     #[test]
     fn spawn_duplicate_branch_errors() {
         let repo_dir = TempDir::new().unwrap();
@@ -495,6 +1453,73 @@ mod tests {
         );
     }
 
+    /// Name generator that yields `names` in order.
+    fn names(names: &[&str]) -> impl FnMut() -> String {
+        let mut iter = names
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .into_iter();
+        move || iter.next().unwrap()
+    }
+
+    #[test]
+    fn spawn_retries_generated_name_on_collision() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+
+        // One name collides with a branch, one with a stale worktree directory.
+        git(repo_dir.path(), &["branch", "taken-branch"]).unwrap();
+        let project = repo_dir.path().file_name().unwrap();
+        fs::create_dir_all(base_dir.path().join(project).join("stale-dir")).unwrap();
+
+        let result = spawn_with_names(
+            &spawn_opts(repo_dir.path(), base_dir.path(), None),
+            names(&["taken-branch", "stale-dir", "fresh-name"]),
+        )
+        .unwrap();
+
+        assert_eq!(result.branch, "fresh-name");
+        assert!(result.worktree_path.ends_with("fresh-name"));
+        assert!(result.worktree_path.join("README.md").exists());
+    }
+
+    #[test]
+    fn spawn_gives_up_after_max_attempts() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+        git(repo_dir.path(), &["branch", "taken-branch"]).unwrap();
+
+        let mut calls = 0;
+        let result = spawn_with_names(&spawn_opts(repo_dir.path(), base_dir.path(), None), || {
+            calls += 1;
+            "taken-branch".to_string()
+        });
+
+        assert!(matches!(result, Err(WorktreeError::BranchExists(ref b)) if b == "taken-branch"));
+        assert_eq!(calls, MAX_NAME_ATTEMPTS);
+    }
+
+    #[test]
+    fn spawn_provided_name_with_stale_dir_errors() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+        let project = repo_dir.path().file_name().unwrap();
+        let stale = base_dir.path().join(project).join("my-branch");
+        fs::create_dir_all(&stale).unwrap();
+
+        let result = spawn(&spawn_opts(
+            repo_dir.path(),
+            base_dir.path(),
+            Some("my-branch"),
+        ));
+
+        assert!(matches!(result, Err(WorktreeError::WorktreePathExists(ref p)) if *p == stale));
+    }
+
     #[test]
     fn sync_to_main_picks_up_new_commits() {
         let repo_dir = TempDir::new().unwrap();
@@ -571,7 +1596,7 @@ mod tests {
     }
 
     #[test]
-    fn clean_removes_untracked_files() {
+    fn clean_moves_untracked_files_to_trash() {
         let repo_dir = TempDir::new().unwrap();
         let base_dir = TempDir::new().unwrap();
         init_repo(repo_dir.path());
@@ -595,12 +1620,21 @@ mod tests {
         assert!(spawned.worktree_path.join("stray.txt").exists());
         assert!(spawned.worktree_path.join("stray-dir/nested.txt").exists());
 
-        clean(&spawned.worktree_path).unwrap();
+        let trash = trash_dir(base_dir.path(), "clean-branch", 1_700_000_000);
+        let moved = clean(&spawned.worktree_path, &trash).unwrap();
 
+        assert_eq!(moved, ["stray-dir/", "stray.txt"]);
         assert!(!spawned.worktree_path.join("stray.txt").exists());
         assert!(!spawned.worktree_path.join("stray-dir").exists());
         // Tracked files should still be there
         assert!(spawned.worktree_path.join("README.md").exists());
+        // The files themselves survive in the trash
+        assert!(trash.ends_with("trash/clean-branch/1700000000"));
+        assert_eq!(
+            fs::read_to_string(trash.join("stray-dir/nested.txt")).unwrap(),
+            "nested\n"
+        );
+        assert!(trash.join("stray.txt").exists());
     }
 
     #[test]
@@ -626,7 +1660,11 @@ mod tests {
         fs::write(spawned.worktree_path.join("build/output.bin"), "binary\n").unwrap();
         fs::write(spawned.worktree_path.join("stray.txt"), "leftover\n").unwrap();
 
-        clean(&spawned.worktree_path).unwrap();
+        let trash = base_dir.path().join("trash");
+        assert_eq!(
+            clean(&spawned.worktree_path, &trash).unwrap(),
+            ["stray.txt"]
+        );
 
         // Untracked file should be removed
         assert!(!spawned.worktree_path.join("stray.txt").exists());
@@ -634,6 +1672,64 @@ mod tests {
         assert!(spawned.worktree_path.join("build/output.bin").exists());
     }
 
+    #[test]
+    fn clean_without_untracked_files_creates_no_trash() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+        let spawned = spawn(&spawn_opts(
+            repo_dir.path(),
+            base_dir.path(),
+            Some("clean-empty"),
+        ))
+        .unwrap();
+
+        let trash = base_dir.path().join("trash");
+        assert!(clean(&spawned.worktree_path, &trash).unwrap().is_empty());
+        assert!(!trash.exists());
+    }
+
+    #[test]
+    fn clean_moves_oddly_named_paths_unquoted() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+        let spawned = spawn(&spawn_opts(
+            repo_dir.path(),
+            base_dir.path(),
+            Some("clean-odd"),
+        ))
+        .unwrap();
+
+        // git would C-quote these in human-readable output.
+        for name in ["odd\tname", "caf\u{e9}.txt", "say \"hi\""] {
+            fs::write(spawned.worktree_path.join(name), "x\n").unwrap();
+        }
+
+        let trash = base_dir.path().join("trash");
+        let moved = clean(&spawned.worktree_path, &trash).unwrap();
+        assert_eq!(moved, ["caf\u{e9}.txt", "odd\tname", "say \"hi\""]);
+        for name in &moved {
+            assert!(trash.join(name).exists(), "{name}");
+        }
+    }
+
+    #[test]
+    fn spawn_result_knows_its_worktree_base() {
+        let result = |branch: &str| SpawnResult {
+            worktree_path: Path::new("/base/project").join(branch),
+            branch: branch.to_string(),
+        };
+        assert_eq!(
+            result("swift-fox").worktree_base(),
+            Some(Path::new("/base"))
+        );
+        assert_eq!(
+            result("coven/swift-fox").worktree_base(),
+            Some(Path::new("/base"))
+        );
+    }
+
     #[test]
     fn is_rebase_in_progress_false_normally() {
         let repo_dir = TempDir::new().unwrap();
@@ -673,6 +1769,57 @@ mod tests {
         assert!(has_unique_commits(&spawned.worktree_path).unwrap());
     }
 
+    #[test]
+    fn counts_unsaved_work() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+        let wt = spawn(&spawn_opts(
+            repo_dir.path(),
+            base_dir.path(),
+            Some("unsaved"),
+        ))
+        .unwrap()
+        .worktree_path;
+        assert_eq!(changed_file_count(&wt).unwrap(), 0);
+        assert_eq!(unique_commit_count(&wt).unwrap(), 0);
+
+        commit_file(&wt, "a.txt", "a\n", "add a");
+        commit_file(&wt, "b.txt", "b\n", "add b");
+        fs::write(wt.join("a.txt"), "changed\n").unwrap();
+        fs::write(wt.join("untracked.txt"), "new\n").unwrap();
+        assert_eq!(changed_file_count(&wt).unwrap(), 2);
+        assert_eq!(unique_commit_count(&wt).unwrap(), 2);
+        assert!(head_commit_time(&wt).unwrap() > 1_600_000_000);
+    }
+
+    #[test]
+    fn commit_all_commits_only_a_dirty_tree() {
+        let repo_dir = TempDir::new().unwrap();
+        let repo = repo_dir.path();
+        init_repo(repo);
+        assert!(!dirty_state(repo).unwrap());
+        assert!(!commit_all(repo, "ralph iteration 1: nothing to do").unwrap());
+
+        fs::write(repo.join("README.md"), "# changed\n").unwrap();
+        fs::write(repo.join("new.txt"), "new\n").unwrap();
+        assert!(dirty_state(repo).unwrap());
+        assert!(commit_all(repo, "ralph iteration 2: edit the readme").unwrap());
+        assert!(!dirty_state(repo).unwrap());
+
+        fs::remove_file(repo.join("new.txt")).unwrap();
+        assert!(commit_all(repo, "ralph iteration 3: drop new.txt").unwrap());
+        let log = git(repo, &["log", "--format=%s"]).unwrap();
+        assert_eq!(
+            log.lines().collect::<Vec<_>>(),
+            [
+                "ralph iteration 3: drop new.txt",
+                "ralph iteration 2: edit the readme",
+                "initial commit"
+            ]
+        );
+    }
+
     #[test]
     fn remove_worktree() {
         let repo_dir = TempDir::new().unwrap();
@@ -688,7 +1835,7 @@ mod tests {
 
         assert!(spawned.worktree_path.exists());
 
-        remove(&spawned.worktree_path, false).unwrap();
+        remove(&spawned.worktree_path, false, Execution::Run).unwrap();
 
         // Directory should be gone
         assert!(!spawned.worktree_path.exists());
@@ -718,7 +1865,7 @@ mod tests {
         // Make a commit in the worktree so the branch has unmerged work
         commit_file(&spawned.worktree_path, "work.txt", "wip\n", "wip commit");
 
-        remove(&spawned.worktree_path, false).unwrap();
+        remove(&spawned.worktree_path, false, Execution::Run).unwrap();
 
         assert!(!spawned.worktree_path.exists());
 
@@ -735,4 +1882,408 @@ mod tests {
         .unwrap();
         assert!(!branch_check);
     }
+
+    /// Collects `--trace-git` output for inspection.
+    #[derive(Clone, Default)]
+    struct SharedBuf(std::sync::Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn dry_run_remove_plans_exactly_what_run_executes() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+        let spawned = spawn(&spawn_opts(
+            repo_dir.path(),
+            base_dir.path(),
+            Some("dry-branch"),
+        ))
+        .unwrap();
+        let trace = SharedBuf::default();
+        trace_git_to(Box::new(trace.clone()));
+        // Other tests run git concurrently, so only look at this plan's lines.
+        let traced = |plan: &[GitInvocation]| -> Vec<String> {
+            let out = String::from_utf8(trace.0.lock().unwrap().clone()).unwrap();
+            out.lines()
+                .filter(|l| plan.iter().any(|c| l.starts_with(&format!("[git] {c} ("))))
+                .map(String::from)
+                .collect()
+        };
+
+        let plan = remove(&spawned.worktree_path, true, Execution::DryRun).unwrap();
+        let wt = spawned.worktree_path.display().to_string();
+        assert_eq!(plan[0].args, ["worktree", "remove", "--force", wt.as_str()]);
+        assert_eq!(plan[1].args, ["branch", "-D", "dry-branch"]);
+        assert!(traced(&plan).is_empty());
+        assert!(spawned.worktree_path.exists());
+        assert!(branch_exists(repo_dir.path(), "dry-branch").unwrap());
+
+        let executed = remove(&spawned.worktree_path, true, Execution::Run).unwrap();
+        assert_eq!(executed, plan);
+        let expected: Vec<String> = plan.iter().map(|c| format!("[git] {c} (exit 0)")).collect();
+        assert_eq!(traced(&plan), expected);
+        assert!(!spawned.worktree_path.exists());
+        assert!(!branch_exists(repo_dir.path(), "dry-branch").unwrap());
+    }
+
+    #[test]
+    fn recent_log_lists_main_newest_first() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+        for n in 1..=3 {
+            commit_file(
+                repo_dir.path(),
+                "log.txt",
+                &n.to_string(),
+                &format!("Commit {n}"),
+            );
+        }
+        let spawned = spawn(&spawn_opts(repo_dir.path(), base_dir.path(), Some("wt"))).unwrap();
+        commit_file(&spawned.worktree_path, "wip.txt", "wip", "Unlanded work");
+
+        let log = recent_log(&spawned.worktree_path, 2).unwrap();
+        let subjects: Vec<_> = log
+            .lines()
+            .map(|line| line.split_once(' ').unwrap().1)
+            .collect();
+        assert_eq!(subjects, ["Commit 3", "Commit 2"]);
+    }
+
+    #[test]
+    fn spaces_and_unicode_in_repo_and_base_paths() {
+        let root = TempDir::new().unwrap();
+        let shared = root.path().join("Team Drive").join("projets été");
+        let repo = shared.join("my repo");
+        fs::create_dir_all(&repo).unwrap();
+        init_repo(&repo);
+        commit_file(&repo, ".gitignore", "build/\n", "ignore build");
+        fs::create_dir_all(repo.join("build/données")).unwrap();
+        fs::write(repo.join("build/données/out.txt"), "artifact").unwrap();
+        let base = shared.join("work trees");
+
+        let spawned = spawn(&spawn_opts(&repo, &base, Some("feature"))).unwrap();
+        let wt = &spawned.worktree_path;
+        assert_eq!(*wt, base.join("my repo").join("feature"));
+        assert!(wt.join("build/données/out.txt").exists());
+        let listed: Vec<_> = list_worktrees(&repo)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.path.canonicalize().unwrap())
+            .collect();
+        assert!(listed.contains(&wt.canonicalize().unwrap()));
+
+        // Land the way an agent does: commit, then fast-forward main.
+        commit_file(wt, "new.txt", "hi\n", "Add new.txt");
+        let before = main_head(&repo).unwrap().sha;
+        git(&repo, &["merge", "--ff-only", "feature"]).unwrap();
+        let summary = land_summary(&repo, &before, "feature").unwrap();
+        assert_eq!(summary.commits[0].subject, "Add new.txt");
+        sync_to_main(wt).unwrap();
+        assert!(!has_unique_commits(wt).unwrap());
+
+        let plan = remove(wt, false, Execution::DryRun).unwrap();
+        let quoted = format!("'{}'", wt.display());
+        assert!(plan[0].to_string().ends_with(&quoted), "{}", plan[0]);
+        remove(wt, false, Execution::Run).unwrap();
+        assert!(!wt.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_path_error_names_the_component() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new("/mnt/Team Drive")
+            .join(OsStr::from_bytes(b"caf\xe9"))
+            .join("worktrees");
+        let err = utf8_path(&path).unwrap_err().to_string();
+        assert!(err.contains("(in 'caf\u{fffd}')"), "{err}");
+        assert_eq!(
+            utf8_path(Path::new("/mnt/Team Drive")).unwrap(),
+            "/mnt/Team Drive"
+        );
+    }
+
+    #[test]
+    fn invocations_display_as_shell_commands() {
+        let command = GitInvocation::new(
+            Path::new("/tmp/my repo"),
+            &["commit", "-m", "it's done", "--author=A <a@b>"],
+        );
+        assert_eq!(
+            command.to_string(),
+            r"git -C '/tmp/my repo' commit -m 'it'\''s done' '--author=A <a@b>'"
+        );
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("refs/heads/main"), "refs/heads/main");
+    }
+
+    /// A repo whose main branch is pushed to a bare `origin`, a worker
+    /// worktree, and another clone of `origin` standing in for a second
+    /// machine.
+    struct Remote {
+        _dirs: [TempDir; 4],
+        repo: PathBuf,
+        worktree: PathBuf,
+        other: PathBuf,
+    }
+
+    fn with_remote() -> Remote {
+        let dirs = [(); 4].map(|()| TempDir::new().unwrap());
+        let [origin, repo, base, other] = dirs.each_ref().map(|d| d.path().to_path_buf());
+        init_repo(&repo);
+        git(&origin, &["init", "--bare"]).unwrap();
+        git(
+            &repo,
+            &["remote", "add", "origin", utf8_path(&origin).unwrap()],
+        )
+        .unwrap();
+        assert_eq!(push_main(&repo, "origin").unwrap(), PushOutcome::Pushed);
+        git(
+            &base,
+            &[
+                "clone",
+                utf8_path(&origin).unwrap(),
+                utf8_path(&other).unwrap(),
+            ],
+        )
+        .unwrap();
+        git(&other, &["config", "user.email", "other@test.com"]).unwrap();
+        git(&other, &["config", "user.name", "Other"]).unwrap();
+        let worktree = spawn(&spawn_opts(&repo, &base, Some("worker")))
+            .unwrap()
+            .worktree_path;
+        Remote {
+            _dirs: dirs,
+            repo,
+            worktree,
+            other,
+        }
+    }
+
+    /// Commit in the worktree and fast-forward main to it, like `land.sh`.
+    fn land(remote: &Remote, name: &str) {
+        commit_file(&remote.worktree, name, "landed\n", &format!("add {name}"));
+        git(&remote.repo, &["merge", "--ff-only", "worker"]).unwrap();
+    }
+
+    #[test]
+    fn sync_to_remote_main_leaves_the_main_worktree_alone() {
+        let remote = with_remote();
+        commit_file(&remote.other, "theirs.txt", "pushed\n", "add theirs.txt");
+        git(&remote.other, &["push", "origin", "HEAD"]).unwrap();
+
+        fetch_remote_main(&remote.worktree, "origin").unwrap();
+        sync_to_remote_main(&remote.worktree, "origin").unwrap();
+        assert!(remote.worktree.join("theirs.txt").exists());
+        assert!(!remote.repo.join("theirs.txt").exists());
+
+        // Landing brings the remote's commits to main along with ours.
+        land(&remote, "ours.txt");
+        assert!(remote.repo.join("theirs.txt").exists());
+    }
+
+    #[test]
+    fn sync_to_remote_main_keeps_unpushed_main_commits() {
+        let remote = with_remote();
+        commit_file(&remote.repo, "local.txt", "unpushed\n", "add local.txt");
+
+        fetch_remote_main(&remote.worktree, "origin").unwrap();
+        sync_to_remote_main(&remote.worktree, "origin").unwrap();
+        assert!(remote.worktree.join("local.txt").exists());
+    }
+
+    #[test]
+    fn push_after_land_reaches_the_remote() {
+        let remote = with_remote();
+        land(&remote, "ours.txt");
+        assert_eq!(
+            push_main(&remote.worktree, "origin").unwrap(),
+            PushOutcome::Pushed
+        );
+        git(&remote.other, &["pull", "--ff-only"]).unwrap();
+        assert!(remote.other.join("ours.txt").exists());
+    }
+
+    #[test]
+    fn rejected_push_relands_onto_the_remote() {
+        let remote = with_remote();
+        commit_file(&remote.other, "theirs.txt", "pushed\n", "add theirs.txt");
+        git(&remote.other, &["push", "origin", "HEAD"]).unwrap();
+        land(&remote, "ours.txt");
+
+        assert_eq!(
+            push_main(&remote.worktree, "origin").unwrap(),
+            PushOutcome::Rejected
+        );
+        assert!(reland_onto_remote(&remote.worktree, "origin").unwrap());
+        assert!(remote.repo.join("theirs.txt").exists());
+        assert_eq!(
+            push_main(&remote.worktree, "origin").unwrap(),
+            PushOutcome::Pushed
+        );
+    }
+
+    #[test]
+    fn reland_leaves_conflicts_alone() {
+        let remote = with_remote();
+        commit_file(&remote.other, "same.txt", "theirs\n", "theirs");
+        git(&remote.other, &["push", "origin", "HEAD"]).unwrap();
+        land(&remote, "same.txt");
+        let before = main_head(&remote.worktree).unwrap().sha;
+
+        assert!(!reland_onto_remote(&remote.worktree, "origin").unwrap());
+        assert_eq!(main_head(&remote.worktree).unwrap().sha, before);
+        assert!(!is_rebase_in_progress(&remote.worktree).unwrap());
+    }
+
+    #[test]
+    fn push_to_a_missing_remote_fails_with_gits_error() {
+        let remote = with_remote();
+        let outcome = push_main(&remote.repo, "nowhere").unwrap();
+        assert!(
+            matches!(&outcome, PushOutcome::Failed(error) if error.contains("nowhere")),
+            "{outcome:?}"
+        );
+    }
+
+    /// A repo with a `feature` branch one commit ahead of main, and a
+    /// worktree started from it.
+    fn branched_from_feature() -> (TempDir, TempDir, PathBuf) {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+        git(repo_dir.path(), &["branch", "feature"]).unwrap();
+        let feature = spawn(&spawn_opts(repo_dir.path(), base_dir.path(), Some("f")))
+            .unwrap()
+            .worktree_path;
+        commit_file(&feature, "feature.txt", "wip\n", "start feature");
+        git(repo_dir.path(), &["branch", "-f", "feature", "f"]).unwrap();
+        remove(&feature, true, Execution::Run).unwrap();
+        let worktree = spawn(&SpawnOptions {
+            branch_from: Some("feature"),
+            ..spawn_opts(repo_dir.path(), base_dir.path(), Some("worker"))
+        })
+        .unwrap()
+        .worktree_path;
+        (repo_dir, base_dir, worktree)
+    }
+
+    #[test]
+    fn branch_from_starts_syncs_and_resets_against_the_branch() {
+        let (repo_dir, _base, worktree) = branched_from_feature();
+        assert!(worktree.join("feature.txt").exists());
+        assert_eq!(main_branch_name(&worktree).unwrap(), "feature");
+        assert_eq!(unique_commit_count(&worktree).unwrap(), 0);
+        // The main worktree itself still lands on main.
+        assert_ne!(main_branch_name(repo_dir.path()).unwrap(), "feature");
+
+        commit_file(&worktree, "mine.txt", "mine\n", "worker commit");
+        assert!(has_unique_commits(&worktree).unwrap());
+        reset_to_main(&worktree).unwrap();
+        assert!(!worktree.join("mine.txt").exists());
+        assert!(worktree.join("feature.txt").exists());
+
+        let main = main_head(&worktree).unwrap();
+        assert_eq!(main.branch, "feature");
+        assert_eq!(main.path, repo_dir.path().canonicalize().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn land_script_lands_on_the_branch_from_branch() {
+        let (repo_dir, _base, worktree) = branched_from_feature();
+        let main_before = git(repo_dir.path(), &["rev-parse", "HEAD"]).unwrap();
+        commit_file(&worktree, "mine.txt", "mine\n", "worker commit");
+
+        let script = Path::new(env!("CARGO_MANIFEST_DIR")).join(".coven/land.sh");
+        let output = Command::new("bash")
+            .arg(script)
+            .current_dir(&worktree)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let feature = git(repo_dir.path(), &["rev-parse", "feature"]).unwrap();
+        let head = git(&worktree, &["rev-parse", "HEAD"]).unwrap();
+        assert_eq!(feature, head);
+        assert_eq!(
+            git(repo_dir.path(), &["rev-parse", "HEAD"]).unwrap(),
+            main_before
+        );
+        sync_to_main(&worktree).unwrap();
+        assert!(!has_unique_commits(&worktree).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn land_script_shows_the_conflicts() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+        let worktree = spawn(&spawn_opts(
+            repo_dir.path(),
+            base_dir.path(),
+            Some("worker"),
+        ))
+        .unwrap()
+        .worktree_path;
+        let lines = |side: &str| {
+            let lines: Vec<String> = (1..=60).map(|i| format!("{side} {i}\n")).collect();
+            lines.concat()
+        };
+        commit_file(repo_dir.path(), "short.txt", "main\n", "main short");
+        commit_file(repo_dir.path(), "long.txt", &lines("main"), "main long");
+        fs::write(worktree.join("long.txt"), lines("worker")).unwrap();
+        git(&worktree, &["add", "long.txt"]).unwrap();
+        commit_file(&worktree, "short.txt", "worker\n", "worker edits");
+
+        let script = Path::new(env!("CARGO_MANIFEST_DIR")).join(".coven/land.sh");
+        let output = Command::new("bash")
+            .arg(script)
+            .current_dir(&worktree)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let content = &stderr[stderr.find("Current conflict content:").unwrap()..];
+        assert!(content.contains("++<<<<<<<"), "{stderr}");
+        assert!(content.contains("+ worker\n"), "{stderr}");
+        assert!(content.contains("main 1\n"), "{stderr}");
+        assert!(!content.contains("worker 60\n"), "{stderr}");
+        assert!(
+            content.contains("more line(s) — read long.txt for the rest"),
+            "{stderr}"
+        );
+        assert!(!content.contains("read short.txt"), "{stderr}");
+    }
+
+    #[test]
+    fn branch_from_a_missing_branch_is_an_error() {
+        let repo_dir = TempDir::new().unwrap();
+        let base_dir = TempDir::new().unwrap();
+        init_repo(repo_dir.path());
+        let err = spawn(&SpawnOptions {
+            branch_from: Some("nope"),
+            ..spawn_opts(repo_dir.path(), base_dir.path(), Some("worker"))
+        })
+        .unwrap_err();
+        assert!(matches!(err, WorktreeError::NoSuchBranchFrom(ref b) if b == "nope"));
+        assert!(!branch_exists(repo_dir.path(), "worker").unwrap());
+    }
 }
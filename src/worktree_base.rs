@@ -0,0 +1,174 @@
+//! Choosing where worker worktrees live, and spotting bases that will be slow.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::platform;
+
+/// `~/.coven/worktrees`, or `None` if the home directory is unknown.
+pub fn home_default() -> Option<PathBuf> {
+    platform::home_dir().map(|home| home.join(".coven").join("worktrees"))
+}
+
+/// Pick the worktree base: the `--worktree-base` flag, then `worktree_base`
+/// from the repo's `.coven/config.toml` (relative to `repo_path`), then the
+/// home default.
+pub fn resolve(
+    flag: Option<&Path>,
+    configured: Option<&Path>,
+    repo_path: &Path,
+    home_default: Option<PathBuf>,
+) -> Result<PathBuf> {
+    if let Some(flag) = flag {
+        return Ok(flag.to_path_buf());
+    }
+    if let Some(configured) = configured {
+        return Ok(repo_path.join(configured));
+    }
+    home_default.ok_or_else(|| {
+        anyhow!(
+            "{} not set; use --worktree-base to specify worktree location",
+            platform::HOME_VAR
+        )
+    })
+}
+
+/// Why a worktree base is likely to make worktrees slow.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BaseFilesystemWarning {
+    /// The base is on a network filesystem and the repo isn't.
+    NetworkFilesystem { fstype: String },
+    /// The base and the repo are on different filesystems.
+    DifferentFilesystem,
+}
+
+impl fmt::Display for BaseFilesystemWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NetworkFilesystem { fstype } => {
+                write!(f, "is on a network filesystem ({fstype})")
+            }
+            Self::DifferentFilesystem => write!(f, "is on a different filesystem than the repo"),
+        }
+    }
+}
+
+/// Check whether worktrees under `base` will live somewhere slower than
+/// `repo_path`. `base` doesn't need to exist yet.
+pub fn check_filesystem(repo_path: &Path, base: &Path) -> Option<BaseFilesystemWarning> {
+    check_filesystem_with(
+        repo_path,
+        base,
+        platform::filesystem_id,
+        platform::network_fs_type,
+    )
+}
+
+fn check_filesystem_with(
+    repo_path: &Path,
+    base: &Path,
+    filesystem_id: impl Fn(&Path) -> Option<u64>,
+    network_fs_type: impl Fn(&Path) -> Option<String>,
+) -> Option<BaseFilesystemWarning> {
+    let base = base.ancestors().find(|p| p.exists())?;
+    if network_fs_type(repo_path).is_none()
+        && let Some(fstype) = network_fs_type(base)
+    {
+        return Some(BaseFilesystemWarning::NetworkFilesystem { fstype });
+    }
+    let (repo_id, base_id) = (filesystem_id(repo_path)?, filesystem_id(base)?);
+    (repo_id != base_id).then_some(BaseFilesystemWarning::DifferentFilesystem)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn local(_: &Path) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn flag_beats_repo_config_and_home() {
+        let base = resolve(
+            Some(Path::new("/flag")),
+            Some(Path::new("/configured")),
+            Path::new("/repo"),
+            Some(PathBuf::from("/home/me/.coven/worktrees")),
+        )
+        .unwrap();
+        assert_eq!(base, PathBuf::from("/flag"));
+    }
+
+    #[test]
+    fn repo_config_beats_home() {
+        let home = Some(PathBuf::from("/home/me/.coven/worktrees"));
+        let absolute = resolve(
+            None,
+            Some(Path::new("/scratch/wt")),
+            Path::new("/repo"),
+            home.clone(),
+        )
+        .unwrap();
+        assert_eq!(absolute, PathBuf::from("/scratch/wt"));
+        let relative = resolve(None, Some(Path::new("../wt")), Path::new("/repo"), home).unwrap();
+        assert_eq!(relative, PathBuf::from("/repo/../wt"));
+    }
+
+    #[test]
+    fn home_default_is_last_resort() {
+        let home = PathBuf::from("/home/me/.coven/worktrees");
+        let base = resolve(None, None, Path::new("/repo"), Some(home.clone())).unwrap();
+        assert_eq!(base, home);
+        assert!(resolve(None, None, Path::new("/repo"), None).is_err());
+    }
+
+    #[test]
+    fn same_filesystem_is_fine() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir(&repo).unwrap();
+        // The base doesn't exist yet; its nearest existing ancestor is checked.
+        let base = dir.path().join("worktrees").join("nested");
+        assert_eq!(check_filesystem(&repo, &base), None);
+    }
+
+    #[test]
+    fn network_base_warns() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("nfs");
+        std::fs::create_dir(&base).unwrap();
+        let nfs = |p: &Path| p.starts_with(&base).then(|| "nfs".to_string());
+        let warning = check_filesystem_with(dir.path(), &base, |_| Some(1), nfs);
+        assert_eq!(
+            warning,
+            Some(BaseFilesystemWarning::NetworkFilesystem {
+                fstype: "nfs".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn network_repo_and_base_compare_devices() {
+        let dir = tempfile::tempdir().unwrap();
+        let nfs = |_: &Path| Some("nfs".to_string());
+        let warning = check_filesystem_with(dir.path(), dir.path(), |_| Some(1), nfs);
+        assert_eq!(warning, None);
+    }
+
+    #[test]
+    fn different_device_warns() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let base = dir.path().join("base");
+        std::fs::create_dir(&repo).unwrap();
+        std::fs::create_dir(&base).unwrap();
+        let device = |p: &Path| Some(u64::from(p.starts_with(&base)));
+        let warning = check_filesystem_with(&repo, &base, device, local);
+        assert_eq!(warning, Some(BaseFilesystemWarning::DifferentFilesystem));
+    }
+}
@@ -2,9 +2,10 @@
 
 use std::fmt::Write;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use coven::display::renderer::{StoredMessage, format_message};
-use coven::vcr::{Io, MultiStep, TestCase, VcrContext};
+use coven::vcr::{ExpectConfig, Io, MultiStep, OutputTap, TestCase, VcrContext};
 
 /// Strip ANSI escape codes for readable snapshots.
 fn strip_ansi(s: &str) -> String {
@@ -78,12 +79,69 @@ fn filter_snapshot_noise(s: &str) -> String {
     result
 }
 
+/// Load a recording for replay, with an output tap so any output checksums
+/// it holds are verified against the replayed rendering.
+fn replay_vcr(path: &Path) -> (VcrContext, OutputTap) {
+    let content = std::fs::read_to_string(path).expect("Failed to read VCR file");
+    let tap = OutputTap::default();
+    let vcr = VcrContext::replay(&content)
+        .expect("Failed to parse VCR file")
+        .with_output_tap(tap.clone());
+    (vcr, tap)
+}
+
 struct TestResult {
     display: String,
     messages: Vec<StoredMessage>,
     views: Vec<String>,
 }
 
+/// The parts of a command report that `[expect]` can assert against.
+#[derive(Default)]
+struct ReportSummary {
+    session_id: Option<String>,
+    result_text: Option<String>,
+    iterations: Option<usize>,
+    stop_reason: Option<String>,
+    break_reason: Option<String>,
+    phases: Vec<String>,
+}
+
+impl ReportSummary {
+    fn check(&self, expect: &ExpectConfig) {
+        if let Some(id) = &expect.session_id {
+            assert_eq!(self.session_id.as_ref(), Some(id), "session_id mismatch");
+        }
+        if let Some(needle) = &expect.result_contains {
+            let text = self.result_text.as_deref().unwrap_or_default();
+            assert!(
+                text.contains(needle.as_str()),
+                "result text {text:?} does not contain {needle:?}"
+            );
+        }
+        if let Some(n) = expect.iterations {
+            assert_eq!(self.iterations, Some(n), "iteration count mismatch");
+        }
+        if let Some(reason) = &expect.stop_reason {
+            assert_eq!(
+                self.stop_reason.as_ref(),
+                Some(reason),
+                "stop reason mismatch"
+            );
+        }
+        if let Some(reason) = &expect.break_reason {
+            assert_eq!(
+                self.break_reason.as_ref(),
+                Some(reason),
+                "break reason mismatch"
+            );
+        }
+        if !expect.phases.is_empty() {
+            assert_eq!(self.phases, expect.phases, "worker phases mismatch");
+        }
+    }
+}
+
 /// Run a test case through the real command function with VCR replay,
 /// capturing renderer output for snapshot comparison.
 async fn run_vcr_test(theme: &str, name: &str) -> TestResult {
@@ -98,38 +156,78 @@ async fn run_vcr_test(theme: &str, name: &str) -> TestResult {
         toml::from_str(&std::fs::read_to_string(&toml_path).expect("Failed to read TOML file"))
             .expect("Failed to parse TOML file");
 
-    let vcr_content = std::fs::read_to_string(&vcr_path).expect("Failed to read VCR file");
-    let vcr = VcrContext::replay(&vcr_content).expect("Failed to parse VCR file");
+    let (vcr, tap) = replay_vcr(&vcr_path);
     let mut io = if case.display.headless {
         Io::dummy_headless()
     } else {
         Io::dummy()
     };
-    let mut output = Vec::new();
+    let mut output = tap.writer(Vec::new());
     let views = case.views.clone();
 
     // Default to haiku, matching what record-vcr uses during recording.
     let default_model = coven::vcr::DEFAULT_TEST_MODEL;
 
+    let mut summary = ReportSummary::default();
     let messages = if case.is_worker() {
         let worker_config = case.worker.as_ref().unwrap();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+        io.watch_shutdown(shutdown_rx);
+        shutdown_tx.send_replace(worker_config.shutdown_at_start);
         let mut extra_args = worker_config.claude_args.clone();
         if !extra_args.iter().any(|a| a == "--model") {
             extra_args.extend(["--model".to_string(), default_model.to_string()]);
         }
         // Dummy path — never touched on disk since all worktree ops are VCR stubs during replay.
         let worktree_base = PathBuf::from("/tmp/coven-vcr-replay-worktrees");
-        coven::commands::worker::worker(
+        let report = coven::commands::worker::worker(
             coven::commands::worker::WorkerConfig {
-                show_thinking: case.display.show_thinking,
+                display: coven::commands::DisplayOptions {
+                    show_thinking: case.display.show_thinking,
+                    term_width: Some(80),
+                    ..Default::default()
+                },
+                protocol_log: None,
+                steering_timeout: None,
+                max_cost: None,
+                preflight: false,
+                no_default_permission_mode: worker_config.no_default_permission_mode,
                 branch: None,
-                worktree_base,
+                worktree_base: Some(worktree_base),
+                agents_dir: worker_config.agents_dir.as_ref().map(PathBuf::from),
                 extra_args,
                 working_dir: None,
-                fork: false,
-                reload: false,
+                tag_flags: coven::commands::TagFlags {
+                    fork: false,
+                    fork_tools: None,
+                    fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+                    reload: false,
+                },
                 no_wait: false,
-                term_width: Some(80),
+                cleanup: if worker_config.dry_run_cleanup {
+                    coven::commands::worker::ExitCleanup::DryRun
+                } else {
+                    coven::commands::worker::ExitCleanup::Remove
+                },
+                cycles: coven::commands::worker::Cycles::from_flags(
+                    worker_config.cycles.once,
+                    worker_config.cycles.dispatch_only,
+                ),
+                debug_dispatch: worker_config.debug_dispatch,
+                lock_wait: coven::semaphore::LockWait::default(),
+                branch_prefix: None,
+                branch_from: None,
+                max_loop_warnings: None,
+                wake_on: Vec::new(),
+                push_after_land: None,
+                post_land: worker_config.post_land.clone().map(|command| {
+                    coven::post_land::PostLandHook {
+                        command,
+                        required: false,
+                        timeout: Duration::from_secs(coven::post_land::DEFAULT_TIMEOUT_SECS),
+                    }
+                }),
+                control_socket: None,
             },
             &mut io,
             &vcr,
@@ -137,8 +235,16 @@ async fn run_vcr_test(theme: &str, name: &str) -> TestResult {
         )
         .await
         .expect("Command failed during VCR replay");
-        // Worker doesn't return StoredMessages; return empty vec.
-        Vec::new()
+        let last = report.phases.last();
+        summary = ReportSummary {
+            session_id: last.and_then(|p| p.session_id.clone()),
+            result_text: last.map(|p| p.result_text.clone()),
+            iterations: None,
+            stop_reason: None,
+            break_reason: None,
+            phases: report.phases.iter().map(|p| p.agent.clone()).collect(),
+        };
+        report.messages
     } else if case.is_ralph() {
         let ralph_config = case.ralph.as_ref().unwrap();
         let mut extra_args = ralph_config.claude_args.clone();
@@ -150,71 +256,151 @@ async fn run_vcr_test(theme: &str, name: &str) -> TestResult {
             ralph_config.prompt_command.clone(),
         )
         .expect("ralph fixture needs `prompt` or `prompt_command`");
-        coven::commands::ralph::ralph(
+        let report = coven::commands::ralph::ralph(
             coven::commands::ralph::RalphConfig {
                 prompt_source,
+                first_prompt: None,
                 iterations: 10,
-                break_tag: ralph_config.break_tag.clone(),
-                no_break: false,
+                break_tag: Some(ralph_config.break_tag.clone()),
                 no_wait: ralph_config.no_wait,
-                show_thinking: case.display.show_thinking,
-                tag_flags: coven::commands::ralph::TagFlags {
+                display: coven::commands::DisplayOptions {
+                    show_thinking: case.display.show_thinking,
+                    term_width: Some(80),
+                    ..Default::default()
+                },
+                protocol_log: None,
+                steering_timeout: None,
+                max_cost: None,
+                iteration_timeout: ralph_config.iteration_timeout.map(Duration::from_secs),
+                preflight: false,
+                no_default_permission_mode: false,
+                commit_each: false,
+                tag_flags: coven::commands::TagFlags {
                     fork: false,
+                    fork_tools: None,
+                    fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
                     reload: false,
                 },
                 extra_args,
                 working_dir: None,
-                term_width: Some(80),
             },
             &mut io,
             &vcr,
             &mut output,
         )
         .await
-        .expect("Command failed during VCR replay")
+        .expect("Command failed during VCR replay");
+        let last = report.iterations.last();
+        summary = ReportSummary {
+            session_id: last.and_then(|i| i.session_id.clone()),
+            result_text: last.map(|i| i.result_text.clone()),
+            iterations: Some(report.iterations.len()),
+            stop_reason: Some(format!("{:?}", report.stop_reason)),
+            break_reason: report.break_reason.clone(),
+            phases: Vec::new(),
+        };
+        report.messages
     } else if case.is_init() {
         let init_config = case.init.as_ref().unwrap();
         let stdin_input = format!("{}\n", init_config.stdin);
         let mut stdin = std::io::Cursor::new(stdin_input);
-        coven::commands::init::init(&vcr, &mut output, &mut stdin, None)
+        coven::commands::init::init(&vcr, &mut output, &mut stdin, None, init_config.update)
             .await
             .expect("Command failed during VCR replay");
         Vec::new()
     } else if case.is_gc() {
-        coven::commands::gc::gc(&vcr, false, None, &mut output)
+        let config = coven::commands::gc::GcConfig::default();
+        coven::commands::gc::gc(&vcr, &config, None, &mut output, &mut std::io::empty())
             .await
             .expect("Command failed during VCR replay");
         Vec::new()
-    } else if case.is_status() {
-        coven::commands::status::status(&vcr, None, &mut output)
+    } else if let Some(status_config) = &case.status {
+        coven::commands::status::status(&vcr, None, status_config.json, &mut output)
             .await
             .expect("Command failed during VCR replay");
         Vec::new()
+    } else if let Some(fork_config) = &case.fork {
+        let mut extra_args = fork_config.claude_args.clone();
+        if !extra_args.iter().any(|a| a == "--model") {
+            extra_args.extend(["--model".to_string(), default_model.to_string()]);
+        }
+        coven::commands::fork_cmd::fork(
+            coven::commands::fork_cmd::ForkCmdConfig {
+                tasks: fork_config.tasks.clone(),
+                extra_args,
+                no_default_permission_mode: fork_config.no_default_permission_mode,
+                working_dir: None,
+                term_width: Some(80),
+            },
+            &mut io,
+            &vcr,
+            &mut output,
+        )
+        .await
+        .expect("Command failed during VCR replay");
+        Vec::new()
     } else {
         let run_config = case.run.as_ref().unwrap();
+        let files_dir = tempfile::tempdir().unwrap();
+        if let Some(file) = &run_config.prompt_file {
+            std::fs::write(files_dir.path().join(file), &case.files[file]).unwrap();
+        }
+        let prompt = run_config.resolve_prompt(files_dir.path()).unwrap();
         let mut claude_args = run_config.claude_args.clone();
         if !claude_args.iter().any(|a| a == "--model") {
             claude_args.extend(["--model".to_string(), default_model.to_string()]);
         }
-        coven::commands::run::run(
+        let report = coven::commands::run::run(
             coven::commands::run::RunConfig {
-                prompt: Some(run_config.prompt.clone()),
+                prompt: Some(prompt),
+                resume: None,
+                continue_last: false,
+                notes_dir: None,
                 extra_args: claude_args,
-                show_thinking: case.display.show_thinking,
-                fork: run_config.fork,
-                reload: run_config.reload,
+                display: coven::commands::DisplayOptions {
+                    show_thinking: case.display.show_thinking,
+                    term_width: Some(80),
+                    ..Default::default()
+                },
+                protocol_log: None,
+                steering_timeout: None,
+                max_cost: None,
+                preflight: false,
+                no_default_permission_mode: false,
+                tag_flags: coven::commands::TagFlags {
+                    fork: run_config.fork,
+                    fork_tools: run_config
+                        .fork_tools
+                        .as_deref()
+                        .map(|spec| spec.parse().expect("invalid fork_tools spec")),
+                    fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+                    reload: run_config.reload,
+                },
+                fork_tasks: run_config.fork_tasks.clone(),
                 working_dir: None,
-                term_width: Some(80),
+                json_events: None,
             },
             &mut io,
             &vcr,
             &mut output,
         )
         .await
-        .expect("Command failed during VCR replay")
+        .expect("Command failed during VCR replay");
+        summary = ReportSummary {
+            session_id: report.session_id,
+            result_text: Some(report.result_text),
+            iterations: None,
+            stop_reason: None,
+            break_reason: None,
+            phases: Vec::new(),
+        };
+        report.messages
     };
+    summary.check(&case.expect);
+    vcr.checkpoint_output()
+        .expect("Output checksum mismatch at end of replay");
 
-    let raw = String::from_utf8(output).expect("Output should be valid UTF-8");
+    let raw = String::from_utf8(output.into_inner()).expect("Output should be valid UTF-8");
     TestResult {
         display: filter_snapshot_noise(&strip_ansi(&raw)),
         messages,
@@ -260,13 +446,11 @@ async fn run_multi_vcr_test(theme: &str, name: &str) -> TestResult {
                 .iter()
                 .map(|step| async {
                     let vcr_path = base.join(format!("{name}__{}.vcr", step.name));
-                    let vcr_content =
-                        std::fs::read_to_string(&vcr_path).expect("Failed to read step VCR file");
-                    let vcr =
-                        VcrContext::replay(&vcr_content).expect("Failed to parse step VCR file");
-                    let mut output = Vec::new();
+                    let (vcr, tap) = replay_vcr(&vcr_path);
+                    let mut output = tap.writer(Vec::new());
                     run_multi_step(step, &vcr, show_thinking, default_model, &mut output).await;
-                    let raw = String::from_utf8(output).expect("Output should be valid UTF-8");
+                    let raw = String::from_utf8(output.into_inner())
+                        .expect("Output should be valid UTF-8");
                     (step.name.clone(), raw)
                 })
                 .collect();
@@ -280,12 +464,10 @@ async fn run_multi_vcr_test(theme: &str, name: &str) -> TestResult {
         } else {
             // Sequential step (no concurrent group).
             let vcr_path = base.join(format!("{name}__{}.vcr", step.name));
-            let vcr_content =
-                std::fs::read_to_string(&vcr_path).expect("Failed to read step VCR file");
-            let vcr = VcrContext::replay(&vcr_content).expect("Failed to parse step VCR file");
-            let mut output = Vec::new();
+            let (vcr, tap) = replay_vcr(&vcr_path);
+            let mut output = tap.writer(Vec::new());
             run_multi_step(&step, &vcr, show_thinking, default_model, &mut output).await;
-            let raw = String::from_utf8(output).expect("Output should be valid UTF-8");
+            let raw = String::from_utf8(output.into_inner()).expect("Output should be valid UTF-8");
             combined_output.push_str(&format!("--- {} ---\n", step.name));
             combined_output.push_str(&filter_snapshot_noise(&strip_ansi(&raw)));
             combined_output.push('\n');
@@ -303,15 +485,15 @@ async fn run_multi_vcr_test(theme: &str, name: &str) -> TestResult {
 async fn run_multi_step(
     step: &MultiStep,
     vcr: &VcrContext,
-    show_thinking: bool,
+    show_thinking: Option<bool>,
     default_model: &str,
-    output: &mut Vec<u8>,
+    output: &mut impl std::io::Write,
 ) {
     match step.command.as_str() {
         "init" => {
             let stdin_input = format!("{}\n", step.stdin.as_deref().unwrap_or(""));
             let mut stdin = std::io::Cursor::new(stdin_input);
-            coven::commands::init::init(vcr, output, &mut stdin, None)
+            coven::commands::init::init(vcr, output, &mut stdin, None, false)
                 .await
                 .expect("Init step failed during VCR replay");
         }
@@ -324,15 +506,39 @@ async fn run_multi_step(
             let worktree_base = PathBuf::from("/tmp/coven-vcr-replay-worktrees");
             coven::commands::worker::worker(
                 coven::commands::worker::WorkerConfig {
-                    show_thinking,
+                    display: coven::commands::DisplayOptions {
+                        show_thinking,
+                        term_width: Some(80),
+                        ..Default::default()
+                    },
+                    protocol_log: None,
+                    steering_timeout: None,
+                    max_cost: None,
+                    preflight: false,
+                    no_default_permission_mode: false,
                     branch: None,
-                    worktree_base,
+                    worktree_base: Some(worktree_base),
+                    agents_dir: None,
                     extra_args,
                     working_dir: None,
-                    fork: false,
-                    reload: false,
+                    tag_flags: coven::commands::TagFlags {
+                        fork: false,
+                        fork_tools: None,
+                        fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+                        reload: false,
+                    },
                     no_wait: false,
-                    term_width: Some(80),
+                    cleanup: coven::commands::worker::ExitCleanup::Remove,
+                    cycles: coven::commands::worker::Cycles::Loop,
+                    debug_dispatch: false,
+                    lock_wait: coven::semaphore::LockWait::default(),
+                    branch_prefix: None,
+                    branch_from: None,
+                    max_loop_warnings: None,
+                    wake_on: Vec::new(),
+                    push_after_land: None,
+                    post_land: None,
+                    control_socket: None,
                 },
                 &mut io,
                 vcr,
@@ -343,6 +549,8 @@ async fn run_multi_step(
         }
         other => panic!("unsupported multi-step command: {other}"),
     }
+    vcr.checkpoint_output()
+        .expect("Output checksum mismatch at end of step replay");
 }
 
 /// Format view output for snapshot: one section per viewed message.
@@ -408,6 +616,14 @@ vcr_test!(session / interrupt_resume);
 vcr_test!(session / show_thinking);
 vcr_test!(session / error_handling);
 vcr_test!(session / reload_basic);
+vcr_test!(session / prompt_file);
+vcr_test!(session / interrupt_queued_followup);
+vcr_test!(session / queue_drop_buffered);
+vcr_test!(session / mixed_stderr);
+vcr_test!(session / compaction);
+vcr_test!(session / exit_with_steering);
+vcr_test!(session / interrupt_after_steering);
+vcr_test!(session / project_config);
 
 // Rendering: tool output display
 vcr_test!(rendering / tool_use);
@@ -423,8 +639,12 @@ vcr_test!(subagent / subagent_error);
 
 // Fork: parallel sub-sessions
 vcr_test!(fork / fork_basic);
+vcr_test!(fork / fork_command);
 vcr_test!(fork / fork_buffered);
 vcr_test!(fork / fork_single);
+vcr_test!(fork / fork_steer_child);
+vcr_test!(fork / fork_tasks);
+vcr_test!(fork / fork_tools);
 
 // Ralph: loop mode
 vcr_test!(ralph / ralph_break);
@@ -434,13 +654,647 @@ vcr_test!(ralph / ralph_no_wait);
 vcr_test!(ralph / ralph_continue);
 vcr_test!(ralph / ralph_immediate_break);
 vcr_test!(ralph / ralph_prompt_command);
+vcr_test!(ralph / ralph_custom_break_tag);
+vcr_test!(ralph / ralph_iteration_timeout);
 
 // Orchestration: worker, init, status, gc
 vcr_test!(orchestration / worker_basic);
+vcr_test!(orchestration / worker_land_summary);
+vcr_test!(orchestration / worker_post_land);
+vcr_test!(orchestration / worker_once);
+vcr_test!(orchestration / worker_once_idle);
+vcr_test!(orchestration / worker_trash_untracked);
+vcr_test!(orchestration / worker_saturated_sleep);
+vcr_test!(orchestration / worker_saturated_choose_again);
+vcr_test!(orchestration / worker_shutdown_at_start);
+vcr_test!(orchestration / worker_shutdown_mid_session);
+vcr_test!(orchestration / worker_untrusted_pause);
+vcr_test!(orchestration / worker_agent_claude_args);
+vcr_test!(orchestration / worker_agents_dir);
+vcr_test!(orchestration / worker_debug_dispatch);
+vcr_test!(orchestration / worker_recent_commits);
+vcr_test!(orchestration / worker_land_conflict);
+vcr_test!(orchestration / worker_wait_for_user);
+vcr_test!(orchestration / worker_wait_for_user_exit);
+vcr_test!(orchestration / worker_max_turns);
+vcr_test!(orchestration / worker_no_default_permission_mode);
+vcr_test!(orchestration / worker_dispatch_only);
+vcr_test!(orchestration / worker_dry_run_cleanup);
+vcr_test!(orchestration / worker_dispatch_interrupt_skip);
 vcr_test!(orchestration / init_fresh);
+vcr_test!(orchestration / init_update);
 vcr_test!(orchestration / status_no_workers);
+vcr_test!(orchestration / status_json_empty);
+vcr_test!(orchestration / status_json_workers);
 vcr_test!(orchestration / gc_no_orphans);
 multi_vcr_test!(orchestration / concurrent_workers);
 multi_vcr_test!(orchestration / ambiguous_task);
 multi_vcr_test!(orchestration / priority_dispatch);
 multi_vcr_test!(orchestration / landing_conflict);
+
+/// Worker config matching how `worker_basic` was recorded.
+fn worker_basic_config() -> coven::commands::worker::WorkerConfig {
+    coven::commands::worker::WorkerConfig {
+        display: coven::commands::DisplayOptions {
+            term_width: Some(80),
+            ..Default::default()
+        },
+        protocol_log: None,
+        steering_timeout: None,
+        max_cost: None,
+        preflight: false,
+        no_default_permission_mode: false,
+        branch: None,
+        worktree_base: Some(PathBuf::from("/tmp/coven-vcr-replay-worktrees")),
+        agents_dir: None,
+        extra_args: vec![
+            "--allowedTools".to_string(),
+            "Bash(git add:*),Bash(git commit:*)".to_string(),
+            "--model".to_string(),
+            coven::vcr::DEFAULT_TEST_MODEL.to_string(),
+        ],
+        working_dir: None,
+        tag_flags: coven::commands::TagFlags {
+            fork: false,
+            fork_tools: None,
+            fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+            reload: false,
+        },
+        no_wait: false,
+        cleanup: coven::commands::worker::ExitCleanup::Remove,
+        cycles: coven::commands::worker::Cycles::Loop,
+        debug_dispatch: false,
+        lock_wait: coven::semaphore::LockWait::default(),
+        branch_prefix: None,
+        branch_from: None,
+        max_loop_warnings: None,
+        wake_on: Vec::new(),
+        push_after_land: None,
+        post_land: None,
+        control_socket: None,
+    }
+}
+
+fn worker_basic_vcr() -> VcrContext {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/cases/orchestration/worker_basic/worker_basic.vcr");
+    replay_vcr(&path).0
+}
+
+fn case_vcr(theme: &str, name: &str) -> VcrContext {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/cases")
+        .join(theme)
+        .join(name)
+        .join(format!("{name}.vcr"));
+    replay_vcr(&path).0
+}
+
+/// With `--max-cost`, ralph finishes the iteration that crosses the budget
+/// and stops there. `ralph_break` iterations cost $0.037, $0.037, $0.031, ...
+#[tokio::test]
+async fn ralph_max_cost_stops_at_budget() {
+    let vcr = case_vcr("ralph", "ralph_break");
+    let mut io = Io::dummy();
+    let mut output = Vec::new();
+    let report = coven::commands::ralph::ralph(
+        coven::commands::ralph::RalphConfig {
+            prompt_source: coven::commands::ralph::PromptSource::Static(
+                "Check TODO.md for unchecked tasks. Complete one and mark it done.".to_string(),
+            ),
+            first_prompt: None,
+            iterations: 10,
+            break_tag: Some("break".to_string()),
+            no_wait: false,
+            display: coven::commands::DisplayOptions {
+                term_width: Some(80),
+                ..Default::default()
+            },
+            protocol_log: None,
+            steering_timeout: None,
+            max_cost: Some(0.06),
+            iteration_timeout: None,
+            preflight: false,
+            no_default_permission_mode: false,
+            commit_each: false,
+            tag_flags: coven::commands::TagFlags {
+                fork: false,
+                fork_tools: None,
+                fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+                reload: false,
+            },
+            extra_args: vec![
+                "--model".to_string(),
+                coven::vcr::DEFAULT_TEST_MODEL.to_string(),
+            ],
+            working_dir: None,
+        },
+        &mut io,
+        &vcr,
+        &mut output,
+    )
+    .await
+    .expect("Command failed during VCR replay");
+
+    assert_eq!(report.iterations.len(), 2);
+    assert_eq!(
+        report.stop_reason,
+        coven::commands::ralph::StopReason::BudgetExceeded
+    );
+    assert!(report.total_cost > 0.06);
+    let output = strip_ansi(&String::from_utf8(output).unwrap());
+    assert!(
+        output.contains("Budget exceeded: $0.07 spent of $0.06 (--max-cost)"),
+        "{output}"
+    );
+    assert!(!output.contains("--- Iteration 3 ---"), "{output}");
+}
+
+/// A run over budget ends after the turn instead of waiting for a follow-up.
+#[tokio::test]
+async fn run_max_cost_skips_follow_up() {
+    let vcr = case_vcr("session", "multi_turn");
+    let mut io = Io::dummy();
+    let mut output = Vec::new();
+    let report = coven::commands::run::run(
+        coven::commands::run::RunConfig {
+            prompt: Some("What is Rust?".to_string()),
+            resume: None,
+            continue_last: false,
+            notes_dir: None,
+            extra_args: vec![
+                "--model".to_string(),
+                coven::vcr::DEFAULT_TEST_MODEL.to_string(),
+            ],
+            display: coven::commands::DisplayOptions {
+                term_width: Some(80),
+                ..Default::default()
+            },
+            protocol_log: None,
+            steering_timeout: None,
+            max_cost: Some(0.005),
+            preflight: false,
+            no_default_permission_mode: false,
+            tag_flags: coven::commands::TagFlags {
+                fork: false,
+                fork_tools: None,
+                fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+                reload: false,
+            },
+            fork_tasks: Vec::new(),
+            working_dir: None,
+            json_events: None,
+        },
+        &mut io,
+        &vcr,
+        &mut output,
+    )
+    .await
+    .expect("Command failed during VCR replay");
+
+    assert!(!report.result_text.contains("ownership rules"));
+    let output = strip_ansi(&String::from_utf8(output).unwrap());
+    assert!(output.contains("Budget exceeded"), "{output}");
+    assert!(!output.contains("How does ownership work?"), "{output}");
+}
+
+/// `--log-file` gets the same transcript as the screen, as plain text.
+#[tokio::test]
+async fn run_log_file_matches_display() {
+    let vcr = case_vcr("session", "simple_qa");
+    let dir = tempfile::tempdir().unwrap();
+    let log_path = dir.path().join("logs").join("simple_qa.log");
+    std::fs::create_dir(dir.path().join("logs")).unwrap();
+    std::fs::write(&log_path, "earlier session\n").unwrap();
+    let mut io = Io::dummy();
+    let mut output = Vec::new();
+    coven::commands::run::run(
+        coven::commands::run::RunConfig {
+            prompt: Some("What is 2+2?".to_string()),
+            resume: None,
+            continue_last: false,
+            notes_dir: None,
+            extra_args: vec![
+                "--model".to_string(),
+                coven::vcr::DEFAULT_TEST_MODEL.to_string(),
+            ],
+            display: coven::commands::DisplayOptions {
+                log_file: Some(log_path.clone()),
+                term_width: Some(80),
+                ..Default::default()
+            },
+            protocol_log: None,
+            steering_timeout: None,
+            max_cost: None,
+            preflight: false,
+            no_default_permission_mode: false,
+            tag_flags: coven::commands::TagFlags {
+                fork: false,
+                fork_tools: None,
+                fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+                reload: false,
+            },
+            fork_tasks: Vec::new(),
+            working_dir: None,
+            json_events: None,
+        },
+        &mut io,
+        &vcr,
+        &mut output,
+    )
+    .await
+    .expect("Command failed during VCR replay");
+
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    let display = strip_ansi(&String::from_utf8(output).unwrap());
+    let logged = log
+        .strip_prefix("earlier session\n")
+        .expect("log was truncated");
+    assert_eq!(logged.trim_end(), display.trim_end());
+    assert!(logged.contains("2 + 2 = 4"), "{logged}");
+    assert!(!logged.contains(['\x1b', '\r']), "{logged:?}");
+}
+
+/// Collects `--json-events` output.
+#[derive(Clone, Default)]
+struct SharedBuf(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `--json-events` writes every claude event as a JSON line and ends with
+/// coven's own result line; the display still goes to its writer.
+#[tokio::test]
+async fn run_json_events_streams_ndjson() {
+    let vcr = case_vcr("session", "simple_qa");
+    let events = SharedBuf::default();
+    let mut io = Io::dummy();
+    let mut output = Vec::new();
+    let report = coven::commands::run::run(
+        coven::commands::run::RunConfig {
+            prompt: Some("What is 2+2?".to_string()),
+            resume: None,
+            continue_last: false,
+            notes_dir: None,
+            extra_args: vec![
+                "--model".to_string(),
+                coven::vcr::DEFAULT_TEST_MODEL.to_string(),
+            ],
+            display: coven::commands::DisplayOptions {
+                term_width: Some(80),
+                ..Default::default()
+            },
+            protocol_log: None,
+            steering_timeout: None,
+            max_cost: None,
+            preflight: false,
+            no_default_permission_mode: false,
+            tag_flags: coven::commands::TagFlags {
+                fork: false,
+                fork_tools: None,
+                fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+                reload: false,
+            },
+            fork_tasks: Vec::new(),
+            working_dir: None,
+            json_events: Some(Box::new(events.clone())),
+        },
+        &mut io,
+        &vcr,
+        &mut output,
+    )
+    .await
+    .expect("Command failed during VCR replay");
+
+    let ndjson = String::from_utf8(events.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<serde_json::Value> = ndjson
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("not a JSON line"))
+        .collect();
+    let types: Vec<&str> = lines.iter().map(|l| l["type"].as_str().unwrap()).collect();
+    assert_eq!(types.first(), Some(&"system"), "{types:?}");
+    assert!(types.contains(&"stream_event"), "{types:?}");
+    assert_eq!(&types[types.len() - 2..], ["result", "coven_result"]);
+
+    let summary = lines.last().unwrap();
+    assert_eq!(summary["session_id"].as_str(), report.session_id.as_deref());
+    assert_eq!(summary["total_cost_usd"].as_f64(), Some(report.cost));
+    assert_eq!(
+        summary["result"].as_str(),
+        Some(report.result_text.as_str())
+    );
+
+    let display = strip_ansi(&String::from_utf8(output).unwrap());
+    assert!(display.contains("2 + 2 = 4"), "{display}");
+    assert!(!display.contains("\"type\""), "{display}");
+}
+
+/// `--show-results` previews the first lines of each successful tool result;
+/// failed calls keep their error line.
+#[tokio::test]
+async fn run_show_results_previews_tool_output() {
+    let vcr = case_vcr("session", "error_handling");
+    let mut io = Io::dummy();
+    let mut output = Vec::new();
+    coven::commands::run::run(
+        coven::commands::run::RunConfig {
+            prompt: Some("Run `bash test.sh` and fix any issues".to_string()),
+            resume: None,
+            continue_last: false,
+            notes_dir: None,
+            extra_args: vec![
+                "--allowedTools".to_string(),
+                "Bash(bash test.sh)".to_string(),
+                "--model".to_string(),
+                coven::vcr::DEFAULT_TEST_MODEL.to_string(),
+            ],
+            display: coven::commands::DisplayOptions {
+                show_results: Some(3),
+                term_width: Some(80),
+                ..Default::default()
+            },
+            protocol_log: None,
+            steering_timeout: None,
+            max_cost: None,
+            preflight: false,
+            no_default_permission_mode: false,
+            tag_flags: coven::commands::TagFlags {
+                fork: false,
+                fork_tools: None,
+                fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+                reload: false,
+            },
+            fork_tasks: Vec::new(),
+            working_dir: None,
+            json_events: None,
+        },
+        &mut io,
+        &vcr,
+        &mut output,
+    )
+    .await
+    .expect("Command failed during VCR replay");
+
+    let display = filter_snapshot_noise(&strip_ansi(&String::from_utf8(output).unwrap()));
+    insta::with_settings!({
+        snapshot_path => "../tests/cases/session/error_handling",
+        prepend_module_to_snapshot => false,
+    }, {
+        insta::assert_snapshot!("error_handling__show_results", display);
+    });
+}
+
+/// `--quiet-stderr` drops claude's informational stderr lines and keeps the
+/// ones that look like errors as warnings.
+#[tokio::test]
+async fn run_quiet_stderr_keeps_only_errors() {
+    let vcr = case_vcr("session", "mixed_stderr");
+    let mut io = Io::dummy();
+    let mut output = Vec::new();
+    coven::commands::run::run(
+        coven::commands::run::RunConfig {
+            prompt: Some("Say hi in one short sentence.".to_string()),
+            resume: None,
+            continue_last: false,
+            notes_dir: None,
+            extra_args: [
+                "--mcp-config",
+                "mcp.json",
+                "--debug=mcp",
+                "--debug-to-stderr",
+                "--model",
+                coven::vcr::DEFAULT_TEST_MODEL,
+            ]
+            .map(String::from)
+            .to_vec(),
+            display: coven::commands::DisplayOptions {
+                quiet_stderr: Some(true),
+                term_width: Some(80),
+                ..Default::default()
+            },
+            protocol_log: None,
+            steering_timeout: None,
+            max_cost: None,
+            preflight: false,
+            no_default_permission_mode: false,
+            tag_flags: coven::commands::TagFlags {
+                fork: false,
+                fork_tools: None,
+                fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+                reload: false,
+            },
+            fork_tasks: Vec::new(),
+            working_dir: None,
+            json_events: None,
+        },
+        &mut io,
+        &vcr,
+        &mut output,
+    )
+    .await
+    .expect("Command failed during VCR replay");
+
+    let display = filter_snapshot_noise(&strip_ansi(&String::from_utf8(output).unwrap()));
+    insta::with_settings!({
+        snapshot_path => "../tests/cases/session/mixed_stderr",
+        prepend_module_to_snapshot => false,
+    }, {
+        insta::assert_snapshot!("mixed_stderr__quiet_stderr", display);
+    });
+}
+
+/// The `rate_limit_event` lines claude emits between turns parse cleanly:
+/// no parse warning, and an `allowed` status renders nothing.
+#[tokio::test]
+async fn run_parses_recorded_rate_limit_events() {
+    let path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cases/subagent/subagent/subagent.vcr");
+    let recording = std::fs::read_to_string(path).expect("Failed to read VCR file");
+    assert!(recording.contains(r#""type":"rate_limit_event""#));
+
+    let vcr = case_vcr("subagent", "subagent");
+    let mut io = Io::dummy();
+    let mut output = Vec::new();
+    coven::commands::run::run(
+        coven::commands::run::RunConfig {
+            prompt: Some(
+                "Use the Agent tool to spawn a subagent that reads README.md and summarizes \
+                 it in one sentence. Do not use any other tools."
+                    .to_string(),
+            ),
+            resume: None,
+            continue_last: false,
+            notes_dir: None,
+            extra_args: vec![
+                "--model".to_string(),
+                coven::vcr::DEFAULT_TEST_MODEL.to_string(),
+            ],
+            display: coven::commands::DisplayOptions {
+                term_width: Some(80),
+                ..Default::default()
+            },
+            protocol_log: None,
+            steering_timeout: None,
+            max_cost: None,
+            preflight: false,
+            no_default_permission_mode: false,
+            tag_flags: coven::commands::TagFlags {
+                fork: false,
+                fork_tools: None,
+                fork_concurrency: coven::fork::DEFAULT_FORK_CONCURRENCY,
+                reload: false,
+            },
+            fork_tasks: Vec::new(),
+            working_dir: None,
+            json_events: None,
+        },
+        &mut io,
+        &vcr,
+        &mut output,
+    )
+    .await
+    .expect("Command failed during VCR replay");
+
+    let display = strip_ansi(&String::from_utf8(output).unwrap());
+    assert!(!display.contains("Failed to parse"), "{display}");
+    assert!(!display.contains("[rate limit]"), "{display}");
+}
+
+/// With `fork_tools` set, the recorded fork children were spawned with the
+/// restricted tool lists and the parent with its own.
+#[test]
+fn fork_tools_restrict_only_the_children() {
+    let path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cases/fork/fork_tools/fork_tools.vcr");
+    let recording = std::fs::read_to_string(path).expect("Failed to read VCR file");
+    let spawn_args = |label: &str| -> Vec<Vec<String>> {
+        recording
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .filter(|entry| entry["label"] == label)
+            .map(|entry| serde_json::from_value(entry["args"]["extra_args"].clone()).unwrap())
+            .collect()
+    };
+
+    let children = spawn_args("fork_spawn");
+    assert_eq!(children.len(), 2);
+    for args in &children {
+        let allowed = args.iter().position(|a| a == "--allowedTools").unwrap();
+        assert_eq!(args[allowed + 1], "Read,Glob", "{args:?}");
+        let denied = args.iter().position(|a| a == "--disallowed-tools").unwrap();
+        assert_eq!(args[denied + 1], "Task,Agent,Bash", "{args:?}");
+    }
+    let parents = spawn_args("spawn");
+    assert!(!parents.is_empty());
+    for args in &parents {
+        assert!(!args.contains(&"--allowedTools".to_string()), "{args:?}");
+        let denied = args.iter().position(|a| a == "--disallowed-tools").unwrap();
+        assert_eq!(args[denied + 1], "Task,Agent", "{args:?}");
+    }
+}
+
+/// `--keep-worktree` deregisters the worker but never calls `worktree::remove`.
+#[tokio::test]
+async fn worker_keep_worktree_skips_remove() {
+    let vcr = worker_basic_vcr();
+    let mut io = Io::dummy();
+    let mut output = Vec::new();
+    coven::commands::worker::worker(
+        coven::commands::worker::WorkerConfig {
+            cleanup: coven::commands::worker::ExitCleanup::Keep,
+            ..worker_basic_config()
+        },
+        &mut io,
+        &vcr,
+        &mut output,
+    )
+    .await
+    .expect("Command failed during VCR replay");
+
+    assert_eq!(vcr.unreplayed_labels(), ["worktree::remove"]);
+    let output = strip_ansi(&String::from_utf8(output).unwrap());
+    assert!(output.contains("Keeping worktree (branch "), "{output}");
+    assert!(!output.contains("Removing worktree"), "{output}");
+}
+
+/// A dashboard connected to `--control-socket` during a worker replay sees
+/// the snapshot, then every phase and transition in order.
+#[cfg(unix)]
+#[tokio::test]
+async fn worker_control_socket_streams_events() {
+    use coven::control_socket::{ControlSocket, WorkerEvent};
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let vcr = worker_basic_vcr();
+    let mut io = Io::dummy();
+
+    let dir = tempfile::tempdir().unwrap();
+    let socket_path = dir.path().join("worker.sock");
+    let control = ControlSocket::bind(&socket_path).unwrap();
+    let stream = tokio::net::UnixStream::connect(&socket_path).await.unwrap();
+    let mut lines = BufReader::new(stream).lines();
+    // Once the snapshot arrives the client is subscribed to every later event.
+    let snapshot = lines.next_line().await.unwrap().unwrap();
+    assert!(matches!(
+        serde_json::from_str(&snapshot).unwrap(),
+        WorkerEvent::Snapshot(_)
+    ));
+
+    coven::commands::worker::worker(
+        coven::commands::worker::WorkerConfig {
+            control_socket: Some(control),
+            ..worker_basic_config()
+        },
+        &mut io,
+        &vcr,
+        Vec::new(),
+    )
+    .await
+    .expect("Command failed during VCR replay");
+    assert!(
+        !socket_path.exists(),
+        "socket file should be removed on exit"
+    );
+
+    let mut events = Vec::new();
+    while let Some(line) = lines.next_line().await.unwrap() {
+        let event: WorkerEvent = serde_json::from_str(&line).unwrap();
+        events.push(match event {
+            WorkerEvent::Started { .. } => "started".to_string(),
+            WorkerEvent::PhaseStarted { agent, .. } => format!("phase_started {agent}"),
+            WorkerEvent::PhaseEnded { agent, .. } => format!("phase_ended {agent}"),
+            WorkerEvent::Transition { from, next, .. } => {
+                format!(
+                    "transition {from} -> {}",
+                    next.as_deref().unwrap_or("sleep")
+                )
+            }
+            WorkerEvent::Sleeping { .. } => "sleeping".to_string(),
+            other => format!("{other:?}"),
+        });
+    }
+    assert_eq!(
+        events,
+        [
+            "started",
+            "phase_started dispatch",
+            "phase_ended dispatch",
+            "transition dispatch -> greet",
+            "phase_started greet",
+            "phase_ended greet",
+            "transition greet -> dispatch",
+            "phase_started dispatch",
+            "phase_ended dispatch",
+            "transition dispatch -> sleep",
+            "sleeping",
+        ]
+    );
+}